@@ -0,0 +1,178 @@
+package org.stormlightlabs.inkfinite
+
+import android.database.Cursor
+import android.database.MatrixCursor
+import android.os.CancellationSignal
+import android.os.Handler
+import android.os.ParcelFileDescriptor
+import android.provider.DocumentsContract.Document
+import android.provider.DocumentsContract.Root
+import android.provider.DocumentsProvider
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.io.FileNotFoundException
+import java.io.IOException
+
+/**
+ * Exposes the open workspace to the Storage Access Framework. Document ids are absolute paths;
+ * every read and write goes through the Rust document layer in `src/provider.rs`.
+ *
+ * Copy into gen/android/app/src/main/java/org/stormlightlabs/inkfinite/ and register in
+ * AndroidManifest.xml:
+ *
+ *     <provider
+ *         android:name=".InkfiniteDocumentsProvider"
+ *         android:authorities="org.stormlightlabs.inkfinite.documents"
+ *         android:exported="true"
+ *         android:grantUriPermissions="true"
+ *         android:permission="android.permission.MANAGE_DOCUMENTS">
+ *         <intent-filter>
+ *             <action android:name="android.content.action.DOCUMENTS_PROVIDER" />
+ *         </intent-filter>
+ *     </provider>
+ */
+class InkfiniteDocumentsProvider : DocumentsProvider() {
+    companion object {
+        init {
+            System.loadLibrary("desktop_lib")
+        }
+
+        private const val ROOT_ID = "workspace"
+        private const val MIME_DOCUMENT = "application/json"
+
+        private val ROOT_COLUMNS = arrayOf(
+            Root.COLUMN_ROOT_ID, Root.COLUMN_FLAGS, Root.COLUMN_TITLE,
+            Root.COLUMN_DOCUMENT_ID, Root.COLUMN_ICON, Root.COLUMN_MIME_TYPES,
+        )
+        private val DOCUMENT_COLUMNS = arrayOf(
+            Document.COLUMN_DOCUMENT_ID, Document.COLUMN_DISPLAY_NAME, Document.COLUMN_MIME_TYPE,
+            Document.COLUMN_FLAGS, Document.COLUMN_SIZE, Document.COLUMN_LAST_MODIFIED,
+        )
+
+        @JvmStatic private external fun nativeRoot(dataDir: String): String
+        @JvmStatic private external fun nativeList(root: String, dir: String): String
+        @JvmStatic private external fun nativeStat(root: String, path: String): String
+        @JvmStatic private external fun nativeRead(root: String, path: String): String
+        @JvmStatic private external fun nativeWrite(root: String, path: String, content: String): String
+        @JvmStatic private external fun nativeCreate(root: String, dir: String, name: String): String
+        @JvmStatic private external fun nativeDelete(root: String, path: String): String
+    }
+
+    override fun onCreate(): Boolean = true
+
+    /** Workspace chosen in the app, or null before one has been opened */
+    private fun workspace(): String? = try {
+        nativeRoot(context!!.dataDir.absolutePath)
+    } catch (e: IOException) {
+        null
+    }
+
+    private fun requireWorkspace(): String =
+        workspace() ?: throw FileNotFoundException("No workspace is open")
+
+    override fun queryRoots(projection: Array<out String>?): Cursor {
+        val cursor = MatrixCursor(projection ?: ROOT_COLUMNS)
+        val root = workspace() ?: return cursor
+        cursor.newRow().apply {
+            add(Root.COLUMN_ROOT_ID, ROOT_ID)
+            add(Root.COLUMN_FLAGS, Root.FLAG_SUPPORTS_CREATE or Root.FLAG_SUPPORTS_IS_CHILD)
+            add(Root.COLUMN_TITLE, "Inkfinite")
+            add(Root.COLUMN_DOCUMENT_ID, root)
+            add(Root.COLUMN_ICON, context!!.applicationInfo.icon)
+            add(Root.COLUMN_MIME_TYPES, MIME_DOCUMENT)
+        }
+        return cursor
+    }
+
+    override fun queryDocument(documentId: String, projection: Array<out String>?): Cursor {
+        val cursor = MatrixCursor(projection ?: DOCUMENT_COLUMNS)
+        addRow(cursor, JSONObject(call { nativeStat(requireWorkspace(), documentId) }))
+        return cursor
+    }
+
+    override fun queryChildDocuments(
+        parentDocumentId: String,
+        projection: Array<out String>?,
+        sortOrder: String?,
+    ): Cursor {
+        val cursor = MatrixCursor(projection ?: DOCUMENT_COLUMNS)
+        val entries = JSONArray(call { nativeList(requireWorkspace(), parentDocumentId) })
+        for (i in 0 until entries.length()) {
+            addRow(cursor, entries.getJSONObject(i))
+        }
+        return cursor
+    }
+
+    override fun isChildDocument(parentDocumentId: String, documentId: String): Boolean =
+        documentId.startsWith(parentDocumentId.trimEnd('/') + "/")
+
+    /**
+     * Reads hand out a validated copy; writes go to a scratch file that is imported through
+     * the document layer once the other app closes it, so invalid content never replaces a board.
+     */
+    override fun openDocument(
+        documentId: String,
+        mode: String,
+        signal: CancellationSignal?,
+    ): ParcelFileDescriptor {
+        val root = requireWorkspace()
+        val scratch = File.createTempFile("provider", ".inkfinite.json", context!!.cacheDir)
+        scratch.writeText(call { nativeRead(root, documentId) })
+
+        val accessMode = ParcelFileDescriptor.parseMode(mode)
+        if (accessMode and ParcelFileDescriptor.MODE_WRITE_ONLY == 0 &&
+            accessMode and ParcelFileDescriptor.MODE_READ_WRITE == 0
+        ) {
+            return ParcelFileDescriptor.open(scratch, accessMode).also { scratch.delete() }
+        }
+
+        val handler = Handler(context!!.mainLooper)
+        return ParcelFileDescriptor.open(scratch, accessMode, handler) { error ->
+            try {
+                if (error == null) {
+                    call { nativeWrite(root, documentId, scratch.readText()) }
+                    context!!.contentResolver.notifyChange(
+                        android.provider.DocumentsContract.buildDocumentUri(
+                            "${context!!.packageName}.documents", documentId,
+                        ),
+                        null,
+                    )
+                }
+            } finally {
+                scratch.delete()
+            }
+        }
+    }
+
+    override fun createDocument(parentDocumentId: String, mimeType: String, displayName: String): String =
+        call { nativeCreate(requireWorkspace(), parentDocumentId, displayName) }
+
+    override fun deleteDocument(documentId: String) {
+        call { nativeDelete(requireWorkspace(), documentId) }
+    }
+
+    private fun addRow(cursor: MatrixCursor, entry: JSONObject) {
+        val isDir = entry.getBoolean("isDir")
+        val flags = if (isDir) {
+            Document.FLAG_DIR_SUPPORTS_CREATE or Document.FLAG_SUPPORTS_DELETE
+        } else {
+            Document.FLAG_SUPPORTS_WRITE or Document.FLAG_SUPPORTS_DELETE
+        }
+        cursor.newRow().apply {
+            add(Document.COLUMN_DOCUMENT_ID, entry.getString("path"))
+            add(Document.COLUMN_DISPLAY_NAME, entry.getString("name"))
+            add(Document.COLUMN_MIME_TYPE, if (isDir) Document.MIME_TYPE_DIR else MIME_DOCUMENT)
+            add(Document.COLUMN_FLAGS, flags)
+            add(Document.COLUMN_SIZE, entry.getLong("size"))
+            add(Document.COLUMN_LAST_MODIFIED, entry.getLong("modified"))
+        }
+    }
+
+    /** Surface Rust errors the way the framework expects them */
+    private fun <T> call(block: () -> T): T = try {
+        block()
+    } catch (e: IOException) {
+        throw FileNotFoundException(e.message)
+    }
+}
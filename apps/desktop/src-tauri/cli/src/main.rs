@@ -0,0 +1,294 @@
+use inkfinite_core::cancel::CancelToken;
+use inkfinite_core::{backup, document, export, import, sanitize, search, workspace};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: inkfinite <command> <workspace> [arguments] [options]
+
+Commands:
+  list <workspace> [folder]                      List documents
+  search <workspace> <query> [--limit N]         Search document and indexed asset text
+  export <workspace> <format> <destination> [document...]
+                                                 Export documents, all of them when none are
+                                                 given, as json, markdown or a pandoc format
+  import <workspace> <file>... [--folder F] [--from FORMAT]
+                                                 Create documents from Markdown, or from any
+                                                 file pandoc reads
+  backup <workspace> <destination>               Copy the workspace into a timestamped folder
+
+Options:
+  --json           Print JSON instead of text
+  --pandoc PATH    pandoc binary to use, `pandoc` from PATH by default
+  -h, --help       Show this help";
+
+/// Command line split into positionals and the options every command shares
+struct Args {
+    command: String,
+    workspace: PathBuf,
+    positional: Vec<String>,
+    json: bool,
+    limit: Option<usize>,
+    folder: Option<String>,
+    from: Option<String>,
+    pandoc: PathBuf,
+}
+
+enum Failure {
+    Usage(String),
+    Failed(String),
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::Failed(message)
+    }
+}
+
+fn parse(raw: Vec<String>) -> Result<Option<Args>, Failure> {
+    let mut positional = Vec::new();
+    let mut json = false;
+    let mut limit = None;
+    let mut folder = None;
+    let mut from = None;
+    let mut pandoc = PathBuf::from("pandoc");
+    let mut raw = raw.into_iter();
+    while let Some(arg) = raw.next() {
+        let mut value = |name: &str| {
+            raw.next()
+                .ok_or_else(|| Failure::Usage(format!("{} needs a value", name)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--json" => json = true,
+            "--limit" => {
+                let text = value("--limit")?;
+                limit = Some(
+                    text.parse()
+                        .map_err(|_| Failure::Usage(format!("Invalid limit: {}", text)))?,
+                );
+            }
+            "--folder" => folder = Some(value("--folder")?),
+            "--from" => from = Some(value("--from")?),
+            "--pandoc" => pandoc = PathBuf::from(value("--pandoc")?),
+            flag if flag.starts_with("--") => {
+                return Err(Failure::Usage(format!("Unknown option: {}", flag)))
+            }
+            _ => positional.push(arg),
+        }
+    }
+    if positional.is_empty() {
+        return Ok(None);
+    }
+    let command = positional.remove(0);
+    if positional.is_empty() {
+        return Err(Failure::Usage(format!("{} needs a workspace", command)));
+    }
+    let workspace = PathBuf::from(positional.remove(0));
+    if !workspace.is_dir() {
+        return Err(Failure::Failed(format!(
+            "Workspace does not exist: {}",
+            workspace.display()
+        )));
+    }
+    Ok(Some(Args {
+        command,
+        workspace,
+        positional,
+        json,
+        limit,
+        folder,
+        from,
+        pandoc,
+    }))
+}
+
+/// Positional arguments after the workspace, requiring at least `min`
+fn positional<'a>(args: &'a Args, min: usize, names: &str) -> Result<&'a [String], Failure> {
+    if args.positional.len() < min {
+        return Err(Failure::Usage(format!("{} needs {}", args.command, names)));
+    }
+    Ok(&args.positional)
+}
+
+fn print_json(value: &serde_json::Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    );
+}
+
+fn run_list(args: &Args) -> Result<(), Failure> {
+    let dir = match args.positional.first() {
+        Some(folder) => args.workspace.join(folder),
+        None => args.workspace.clone(),
+    };
+    if !dir.is_dir() {
+        return Err(format!("Folder does not exist: {}", dir.display()).into());
+    }
+    let mut documents = Vec::new();
+    for path in workspace::list_documents(&dir)? {
+        let relative = workspace::relative_path(&args.workspace, &path);
+        let name = document::read_board(&path)
+            .map(|board| board.board.name)
+            .unwrap_or_else(|_| document::document_stem(&path));
+        documents.push((relative, name));
+    }
+    if args.json {
+        print_json(&json!(documents
+            .iter()
+            .map(|(path, name)| json!({ "path": path, "name": name }))
+            .collect::<Vec<_>>()));
+    } else {
+        for (path, name) in documents {
+            println!("{}\t{}", path, name);
+        }
+    }
+    Ok(())
+}
+
+fn run_search(args: &Args) -> Result<(), Failure> {
+    let query = positional(args, 1, "a query")?.join(" ");
+    let documents = workspace::list_documents(&args.workspace)?;
+    let hits = search::search(
+        &args.workspace,
+        &documents,
+        &query,
+        args.limit.unwrap_or(search::DEFAULT_LIMIT),
+        true,
+        &CancelToken::default(),
+    )?;
+    if args.json {
+        print_json(&json!(hits));
+    } else {
+        for hit in hits {
+            println!("{}\t{}", hit.path, hit.snippet);
+        }
+    }
+    Ok(())
+}
+
+fn run_export(args: &Args) -> Result<(), Failure> {
+    let rest = positional(args, 2, "a format and a destination")?;
+    let (format, destination) = (&rest[0], Path::new(&rest[1]));
+    let documents = if rest.len() > 2 {
+        rest[2..]
+            .iter()
+            .map(|path| args.workspace.join(path))
+            .collect()
+    } else {
+        workspace::list_documents(&args.workspace)?
+    };
+
+    let cancel = CancelToken::default();
+    let mut written = Vec::new();
+    let mut failed = 0;
+    for path in &documents {
+        let pandoc = || Ok(args.pandoc.clone());
+        match export::export_document(&args.workspace, path, destination, format, pandoc, &cancel) {
+            Ok(output) => written.push(output.to_string_lossy().to_string()),
+            Err(error) => {
+                failed += 1;
+                eprintln!(
+                    "{}: {}",
+                    workspace::relative_path(&args.workspace, path),
+                    error
+                );
+            }
+        }
+    }
+    report(args, &written);
+    finish(failed, documents.len(), "export")
+}
+
+fn run_import(args: &Args) -> Result<(), Failure> {
+    let files = positional(args, 1, "at least one file")?;
+    let dir = match &args.folder {
+        Some(folder) => args.workspace.join(folder),
+        None => args.workspace.clone(),
+    };
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+    let policy = sanitize::load_policy(&args.workspace);
+
+    let cancel = CancelToken::default();
+    let mut written = Vec::new();
+    let mut failed = 0;
+    for file in files {
+        match import::import_file(
+            Path::new(file),
+            &dir,
+            &policy,
+            Some(&args.pandoc),
+            args.from.as_deref(),
+            &cancel,
+        ) {
+            Ok(output) => written.push(workspace::relative_path(&args.workspace, &output)),
+            Err(error) => {
+                failed += 1;
+                eprintln!("{}: {}", file, error);
+            }
+        }
+    }
+    report(args, &written);
+    finish(failed, files.len(), "import")
+}
+
+fn run_backup(args: &Args) -> Result<(), Failure> {
+    let destination = &positional(args, 1, "a destination")?[0];
+    let report = backup::backup(&args.workspace, Path::new(destination))?;
+    if args.json {
+        print_json(&json!(report));
+    } else {
+        println!(
+            "{} ({} files, {} bytes)",
+            report.path, report.files, report.bytes
+        );
+    }
+    Ok(())
+}
+
+fn report(args: &Args, written: &[String]) {
+    if args.json {
+        print_json(&json!(written));
+    } else {
+        for path in written {
+            println!("{}", path);
+        }
+    }
+}
+
+fn finish(failed: usize, total: usize, action: &str) -> Result<(), Failure> {
+    if failed == 0 {
+        return Ok(());
+    }
+    Err(format!("{} of {} file(s) failed to {}", failed, total, action).into())
+}
+
+fn main() -> ExitCode {
+    let result = parse(std::env::args().skip(1).collect()).and_then(|args| {
+        let Some(args) = args else {
+            println!("{}", USAGE);
+            return Ok(());
+        };
+        match args.command.as_str() {
+            "list" => run_list(&args),
+            "search" => run_search(&args),
+            "export" => run_export(&args),
+            "import" => run_import(&args),
+            "backup" => run_backup(&args),
+            other => Err(Failure::Usage(format!("Unknown command: {}", other))),
+        }
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Usage(message)) => {
+            eprintln!("inkfinite: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Failure::Failed(message)) => {
+            eprintln!("inkfinite: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
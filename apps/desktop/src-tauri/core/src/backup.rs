@@ -0,0 +1,69 @@
+use crate::paths;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupReport {
+    /// Folder the copy was written to
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Copy the whole workspace, settings and history included, into a new timestamped folder in
+/// `destination`. Vault documents stay encrypted, so no vault needs to be unlocked.
+pub fn backup(root: &Path, destination: &Path) -> Result<BackupReport, String> {
+    let root = paths::resolve(root)?;
+    if !root.is_dir() {
+        return Err(format!("Workspace does not exist: {}", root.display()));
+    }
+    let destination = paths::resolve(destination)?;
+    if destination.starts_with(&root) {
+        return Err("Invalid backup destination: it is inside the workspace".to_string());
+    }
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "workspace".to_string());
+    let target = unused(&destination.join(format!(
+        "{}-{}",
+        name,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    )));
+
+    let mut report = BackupReport {
+        path: target.to_string_lossy().to_string(),
+        files: 0,
+        bytes: 0,
+    };
+    // Symbolic links are skipped so the copy never reaches outside the workspace
+    for entry in jwalk::WalkDir::new(&root).skip_hidden(false) {
+        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let relative = path.strip_prefix(&root).unwrap_or(&path);
+        let output = target.join(relative);
+        if let Some(dir) = output.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        report.bytes += fs::copy(&path, &output)
+            .map_err(|e| format!("Failed to copy {}: {}", relative.display(), e))?;
+        report.files += 1;
+    }
+    Ok(report)
+}
+
+/// `path`, or a numbered variant of it when it is taken
+fn unused(path: &Path) -> PathBuf {
+    let mut candidate = path.to_path_buf();
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = PathBuf::from(format!("{} {}", path.display(), counter));
+        counter += 1;
+    }
+    candidate
+}
@@ -0,0 +1,244 @@
+use crate::cancel::CancelToken;
+use crate::document::BoardFile;
+use crate::{backup, document, export, search, vault, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// A job file: tasks run in order against one workspace
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub workspace: PathBuf,
+    pub tasks: Vec<Task>,
+    /// Run the remaining tasks after one fails instead of stopping
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// pandoc binary for pandoc export formats, `pandoc` from `PATH` when omitted
+    pub pandoc: Option<PathBuf>,
+    /// Where to also write the report as JSON, for schedulers that keep no output
+    pub report: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(
+    tag = "task",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Task {
+    /// Rewrite documents whose file does not match the current format
+    Migrate,
+    /// Drop search index entries for files that no longer exist
+    RebuildIndex,
+    Export {
+        /// `markdown` when omitted
+        format: Option<String>,
+        destination: PathBuf,
+        /// Only documents under this workspace-relative folder
+        folder: Option<String>,
+    },
+    Backup {
+        destination: PathBuf,
+    },
+}
+
+impl Task {
+    fn name(&self) -> &'static str {
+        match self {
+            Task::Migrate => "migrate",
+            Task::RebuildIndex => "rebuildIndex",
+            Task::Export { .. } => "export",
+            Task::Backup { .. } => "backup",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskReport {
+    pub task: String,
+    pub ok: bool,
+    pub duration_ms: u64,
+    /// Counts and paths the task produced; `null` when it failed outright
+    pub result: Value,
+    /// Why the task failed, or the documents it could not process
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobReport {
+    pub workspace: String,
+    pub ok: bool,
+    pub tasks: Vec<TaskReport>,
+}
+
+/// Read and check a job file
+pub fn load(path: &Path) -> Result<Job, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read job file: {}", e))?;
+    let job: Job =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid job file: {}", e))?;
+    if !job.workspace.is_dir() {
+        return Err(format!(
+            "Workspace does not exist: {}",
+            job.workspace.display()
+        ));
+    }
+    Ok(job)
+}
+
+/// Run every task of `job`, stopping at the first failure unless `continue_on_error` is set
+pub fn run(job: &Job, cancel: &CancelToken) -> JobReport {
+    let mut report = JobReport {
+        workspace: job.workspace.to_string_lossy().to_string(),
+        ok: true,
+        tasks: Vec::new(),
+    };
+    for task in &job.tasks {
+        let started = Instant::now();
+        let (result, errors) = match run_task(job, task, cancel) {
+            Ok(outcome) => outcome,
+            Err(error) => (Value::Null, vec![error]),
+        };
+        let ok = errors.is_empty();
+        report.ok &= ok;
+        report.tasks.push(TaskReport {
+            task: task.name().to_string(),
+            ok,
+            duration_ms: started.elapsed().as_millis() as u64,
+            result,
+            errors,
+        });
+        if (!ok && !job.continue_on_error) || cancel.is_cancelled() {
+            break;
+        }
+    }
+    report
+}
+
+type Outcome = (Value, Vec<String>);
+
+fn run_task(job: &Job, task: &Task, cancel: &CancelToken) -> Result<Outcome, String> {
+    let root = &job.workspace;
+    match task {
+        Task::Migrate => migrate(root, cancel),
+        Task::RebuildIndex => {
+            let removed = search::prune(root)?;
+            Ok((serde_json::json!({ "removed": removed }), Vec::new()))
+        }
+        Task::Export {
+            format,
+            destination,
+            folder,
+        } => {
+            let format = format.as_deref().unwrap_or("markdown");
+            let source = match folder {
+                Some(folder) => root.join(folder),
+                None => root.clone(),
+            };
+            let mut exported = 0;
+            let mut errors = Vec::new();
+            for path in workspace::list_documents(&source)? {
+                cancel.check()?;
+                if vault::is_locked(&path) {
+                    continue;
+                }
+                let pandoc = || {
+                    Ok(job
+                        .pandoc
+                        .clone()
+                        .unwrap_or_else(|| PathBuf::from("pandoc")))
+                };
+                match export::export_document(&source, &path, destination, format, pandoc, cancel) {
+                    Ok(_) => exported += 1,
+                    Err(error) => errors.push(describe(root, &path, &error)),
+                }
+            }
+            Ok((serde_json::json!({ "exported": exported }), errors))
+        }
+        Task::Backup { destination } => {
+            let report = backup::backup(root, destination)?;
+            Ok((serde_json::json!(report), Vec::new()))
+        }
+    }
+}
+
+/// Rewrite documents that parse but differ from how this version writes them, skipping vaults
+/// and documents carrying fields this version does not know, which a rewrite would drop
+fn migrate(root: &Path, cancel: &CancelToken) -> Result<Outcome, String> {
+    let mut checked = 0;
+    let mut migrated = Vec::new();
+    let mut skipped = Vec::new();
+    let mut errors = Vec::new();
+    for path in workspace::list_documents(root)? {
+        cancel.check()?;
+        if vault::vault_of(&path).is_some() {
+            continue;
+        }
+        checked += 1;
+        let relative = workspace::relative_path(root, &path);
+        let parsed = fs::read(&path)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|content| {
+                serde_json::from_slice::<Value>(&content)
+                    .map_err(|e| format!("Invalid file format: {}", e))
+            })
+            .and_then(|raw| {
+                let board: BoardFile = serde_json::from_value(raw.clone())
+                    .map_err(|e| format!("Invalid file format: {}", e))?;
+                let current = serde_json::to_value(&board)
+                    .map_err(|e| format!("Failed to serialize document: {}", e))?;
+                Ok((raw, board, current))
+            });
+        let (raw, board, current) = match parsed {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                errors.push(describe(root, &path, &error));
+                continue;
+            }
+        };
+        if raw == current {
+            continue;
+        }
+        if !covers(&current, &raw) {
+            skipped.push(relative);
+            continue;
+        }
+        match document::write_board(&path, &board) {
+            Ok(()) => migrated.push(relative),
+            Err(error) => errors.push(describe(root, &path, &error)),
+        }
+    }
+    Ok((
+        serde_json::json!({ "checked": checked, "migrated": migrated, "skipped": skipped }),
+        errors,
+    ))
+}
+
+/// Whether every object key in `original` is still present in `rewritten`
+fn covers(rewritten: &Value, original: &Value) -> bool {
+    match (rewritten, original) {
+        (Value::Object(rewritten), Value::Object(original)) => {
+            original.iter().all(|(key, value)| {
+                // Nulls are dropped by fields that skip serializing empty values
+                value.is_null() || rewritten.get(key).is_some_and(|new| covers(new, value))
+            })
+        }
+        (Value::Array(rewritten), Value::Array(original)) => {
+            rewritten.len() == original.len()
+                && rewritten
+                    .iter()
+                    .zip(original)
+                    .all(|(new, old)| covers(new, old))
+        }
+        _ => true,
+    }
+}
+
+fn describe(root: &Path, path: &Path, error: &str) -> String {
+    format!("{}: {}", workspace::relative_path(root, path), error)
+}
@@ -0,0 +1,62 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
+
+pub const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Symmetric key, wiped from memory when dropped
+pub type SecretKey = Zeroizing<[u8; KEY_LEN]>;
+
+/// Stretch a passphrase into a key with Argon2id
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<SecretKey, String> {
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+pub fn random_key() -> SecretKey {
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    OsRng.fill_bytes(key.as_mut());
+    key
+}
+
+/// Fill `buffer` from the OS random number generator
+pub fn fill_random(buffer: &mut [u8]) {
+    OsRng.fill_bytes(buffer);
+}
+
+pub fn random_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Encrypt with XChaCha20-Poly1305, returning the random nonce followed by the ciphertext
+pub fn seal(key: &SecretKey, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_ref()));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| format!("Failed to encrypt: {}", e))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypt what [`seal`] produced; fails when the key is wrong or the data was changed
+pub fn open(key: &SecretKey, sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    if sealed.len() < NONCE_LEN {
+        return Err("Invalid encrypted data: too short".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| "Failed to decrypt: wrong key or damaged data".to_string())
+}
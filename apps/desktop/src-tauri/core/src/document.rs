@@ -0,0 +1,409 @@
+use crate::{read_only, vault};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File extension used for board documents
+pub const DOCUMENT_EXTENSION: &str = ".inkfinite.json";
+/// Documents at least this large are parsed from a memory map
+#[cfg(unix)]
+const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardMeta {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// When to remind about the whole document, in milliseconds since the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reminder_at: Option<i64>,
+    /// GitHub gist the document was last published to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gist_id: Option<String>,
+    /// Other names `[[wiki links]]` may use for the document
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PageRecord {
+    pub id: String,
+    pub name: String,
+    pub shape_ids: Vec<String>,
+}
+
+/// Shape record with typed common fields; shape-specific props are kept as raw JSON
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeRecord {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub page_id: String,
+    pub x: f64,
+    pub y: f64,
+    pub rot: f64,
+    pub props: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Document {
+    pub pages: Map<String, Value>,
+    pub shapes: Map<String, Value>,
+    pub bindings: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DocOrder {
+    pub page_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shape_order: Option<Map<String, Value>>,
+}
+
+/// On-disk representation of a board, mirroring `DesktopFileData` in inkfinite-core
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BoardFile {
+    pub board: BoardMeta,
+    pub doc: Document,
+    pub order: DocOrder,
+}
+
+impl BoardFile {
+    /// Create an empty board with a single page
+    pub fn new(name: &str) -> Self {
+        let timestamp = now_millis();
+        let page_id = create_id("page");
+        let mut pages = Map::new();
+        pages.insert(
+            page_id.clone(),
+            serde_json::json!({ "id": page_id, "name": "Page 1", "shapeIds": [] }),
+        );
+        let mut shape_order = Map::new();
+        shape_order.insert(page_id.clone(), Value::Array(Vec::new()));
+
+        BoardFile {
+            board: BoardMeta {
+                id: create_id("board"),
+                name: if name.is_empty() {
+                    "Untitled Board".to_string()
+                } else {
+                    name.to_string()
+                },
+                created_at: timestamp,
+                updated_at: timestamp,
+                reminder_at: None,
+                gist_id: None,
+                aliases: Vec::new(),
+            },
+            doc: Document {
+                pages,
+                shapes: Map::new(),
+                bindings: Map::new(),
+            },
+            order: DocOrder {
+                page_ids: vec![page_id],
+                shape_order: Some(shape_order),
+            },
+        }
+    }
+
+    /// Pages in document order, skipping entries that fail to parse
+    pub fn pages(&self) -> Vec<PageRecord> {
+        self.order
+            .page_ids
+            .iter()
+            .filter_map(|id| self.doc.pages.get(id))
+            .filter_map(|value| serde_json::from_value(value.clone()).ok())
+            .collect()
+    }
+
+    /// Shapes on a page in paint order, honoring the optional per-page override
+    pub fn page_shapes(&self, page: &PageRecord) -> Vec<ShapeRecord> {
+        let override_ids: Option<Vec<String>> = self
+            .order
+            .shape_order
+            .as_ref()
+            .and_then(|order| order.get(&page.id))
+            .and_then(|ids| serde_json::from_value(ids.clone()).ok());
+
+        override_ids
+            .as_ref()
+            .unwrap_or(&page.shape_ids)
+            .iter()
+            .filter_map(|id| self.doc.shapes.get(id))
+            .filter_map(|value| serde_json::from_value(value.clone()).ok())
+            .collect()
+    }
+
+    /// Append a markdown block below the existing content of the first page
+    pub fn push_markdown(&mut self, md: &str) {
+        let Some(page) = self.pages().into_iter().next() else {
+            return;
+        };
+
+        let y = self
+            .page_shapes(&page)
+            .iter()
+            .map(|shape| {
+                shape.y
+                    + shape
+                        .props
+                        .get("h")
+                        .and_then(Value::as_f64)
+                        .unwrap_or(MARKDOWN_LINE_HEIGHT)
+            })
+            .fold(0.0, f64::max);
+        let y = if page.shape_ids.is_empty() {
+            0.0
+        } else {
+            y + MARKDOWN_GAP
+        };
+
+        let shape = ShapeRecord {
+            id: create_id("shape"),
+            kind: "markdown".to_string(),
+            page_id: page.id.clone(),
+            x: 0.0,
+            y,
+            rot: 0.0,
+            props: serde_json::json!({
+                "md": md,
+                "w": MARKDOWN_WIDTH,
+                "fontSize": 16,
+                "fontFamily": "Inter",
+                "color": "#1f2933",
+            }),
+        };
+
+        self.add_shape(shape);
+    }
+
+    /// Add a shape on top of the others on its page
+    pub fn add_shape(&mut self, shape: ShapeRecord) {
+        let shape_id = shape.id.clone();
+        let page_id = shape.page_id.clone();
+        self.doc.shapes.insert(
+            shape_id.clone(),
+            serde_json::to_value(shape).unwrap_or_default(),
+        );
+        if let Some(Value::Array(ids)) = self
+            .doc
+            .pages
+            .get_mut(&page_id)
+            .and_then(|p| p.get_mut("shapeIds"))
+        {
+            ids.push(Value::String(shape_id.clone()));
+        }
+        if let Some(Value::Array(ids)) = self
+            .order
+            .shape_order
+            .as_mut()
+            .and_then(|o| o.get_mut(&page_id))
+        {
+            ids.push(Value::String(shape_id));
+        }
+    }
+
+    /// Replace the text of a text or markdown shape, returning false for other shapes
+    pub fn set_text(&mut self, shape_id: &str, text: &str) -> bool {
+        let Some(shape) = self.doc.shapes.get_mut(shape_id) else {
+            return false;
+        };
+        let key = match shape.get("type").and_then(Value::as_str) {
+            Some("markdown") => "md",
+            Some("text") => "text",
+            _ => return false,
+        };
+        match shape.get_mut("props").and_then(Value::as_object_mut) {
+            Some(props) => {
+                props.insert(key.to_string(), Value::String(text.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Delete a shape along with its page and paint order entries and any bindings to it
+    pub fn remove_shape(&mut self, shape_id: &str) {
+        self.doc.shapes.remove(shape_id);
+        let keep = |value: &Value| value.as_str() != Some(shape_id);
+        for page in self.doc.pages.values_mut() {
+            if let Some(Value::Array(ids)) = page.get_mut("shapeIds") {
+                ids.retain(keep);
+            }
+        }
+        if let Some(order) = self.order.shape_order.as_mut() {
+            for ids in order.values_mut() {
+                if let Value::Array(ids) = ids {
+                    ids.retain(keep);
+                }
+            }
+        }
+        self.doc.bindings.retain(|_, binding| {
+            ["fromShapeId", "toShapeId"]
+                .iter()
+                .all(|key| binding.get(key).and_then(Value::as_str) != Some(shape_id))
+        });
+    }
+
+    /// Render the board's textual content as Markdown
+    pub fn to_markdown(&self) -> String {
+        let pages = self.pages();
+        let mut out = format!("# {}\n", self.board.name);
+
+        for page in &pages {
+            if pages.len() > 1 {
+                out.push_str(&format!("\n## {}\n", page.name));
+            }
+            for shape in self.page_shapes(page) {
+                if let Some(text) = shape.text() {
+                    out.push('\n');
+                    out.push_str(text.trim_end());
+                    out.push('\n');
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Build a board from Markdown, placing the whole body in one markdown block
+    pub fn from_markdown(name: &str, md: &str) -> Self {
+        let mut board = BoardFile::new(name);
+        if !md.trim().is_empty() {
+            board.push_markdown(md.trim());
+        }
+        board
+    }
+}
+
+impl ShapeRecord {
+    /// Text content carried by text and markdown shapes
+    pub fn text(&self) -> Option<&str> {
+        let key = match self.kind.as_str() {
+            "markdown" => "md",
+            "text" => "text",
+            _ => return None,
+        };
+        self.props
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|s| !s.trim().is_empty())
+    }
+}
+
+const MARKDOWN_WIDTH: f64 = 600.0;
+const MARKDOWN_LINE_HEIGHT: f64 = 24.0;
+const MARKDOWN_GAP: f64 = 40.0;
+
+/// Read and parse a board file from disk
+pub fn read_board(path: &Path) -> Result<BoardFile, String> {
+    #[cfg(unix)]
+    if let Some(board) = read_mapped(path) {
+        return board;
+    }
+    let content = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let content = vault::unseal(path, &content)?;
+    serde_json::from_slice(&content).map_err(|e| format!("Invalid file format: {}", e))
+}
+
+/// Parse a large document straight from a memory map rather than a copy of its contents.
+///
+/// Returns `None` for small files and when the file cannot be mapped, such as on some network
+/// file systems, so the caller reads it normally. Windows is left out because a mapped file
+/// cannot be replaced, which would make saves fail while a read is in progress.
+#[cfg(unix)]
+fn read_mapped(path: &Path) -> Option<Result<BoardFile, String>> {
+    let file = fs::File::open(path).ok()?;
+    if file.metadata().ok()?.len() < MMAP_THRESHOLD {
+        return None;
+    }
+    // Safety: the map is read-only and dropped before returning. Documents are saved by
+    // renaming a new file into place, so the mapped file is never truncated by this app.
+    let map = unsafe { memmap2::Mmap::map(&file) }.ok()?;
+    Some(vault::unseal(path, &map).and_then(|content| {
+        serde_json::from_slice(&content).map_err(|e| format!("Invalid file format: {}", e))
+    }))
+}
+
+/// Serialize and write a board file to disk.
+///
+/// The content is written to a temporary file that then replaces the document, so readers
+/// never see a partly written file. Documents in a vault are encrypted.
+pub fn write_board(path: &Path, board: &BoardFile) -> Result<(), String> {
+    read_only::ensure_writable(path)?;
+    let content = serde_json::to_vec_pretty(board)
+        .map_err(|e| format!("Failed to serialize document: {}", e))?;
+    let content = vault::seal(path, &content)?;
+    let temp = saving_path(path);
+    fs::write(&temp, &content).map_err(|e| format!("Failed to write file: {}", e))?;
+    fs::rename(&temp, path).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        format!("Failed to write file: {}", e)
+    })
+}
+
+/// Temporary file next to `path` that a save writes before replacing it. Hidden, and without
+/// the document extension, so listings never pick it up; a bare UUID, since the colon in
+/// [`create_id`] ids is not allowed in Windows file names.
+pub fn saving_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!(".{}.saving", uuid::Uuid::new_v4().simple()))
+}
+
+/// File name without the `.inkfinite.json` (or plain) extension
+pub fn document_stem(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match name.strip_suffix(DOCUMENT_EXTENSION) {
+        Some(stem) => stem.to_string(),
+        None => path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or(name),
+    }
+}
+
+/// Pick a path in `dir` for `stem` + `extension` that does not collide with an existing file
+pub fn unique_path(dir: &Path, stem: &str, extension: &str) -> PathBuf {
+    let mut candidate = dir.join(format!("{}{}", stem, extension));
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = dir.join(format!("{} {}{}", stem, counter, extension));
+        counter += 1;
+    }
+    candidate
+}
+
+/// Generate a prefixed identifier in the same `prefix:uuid` format as the frontend
+pub fn create_id(prefix: &str) -> String {
+    format!("{}:{}", prefix, uuid::Uuid::new_v4())
+}
+
+/// Current time in milliseconds since the Unix epoch
+pub fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saving_paths_are_valid_windows_file_names() {
+        let temp = saving_path(Path::new("notes/plan.inkfinite.json"));
+        let name = temp.file_name().unwrap().to_string_lossy();
+        assert_eq!(temp.parent(), Some(Path::new("notes")));
+        assert!(name.starts_with('.') && name.ends_with(".saving"));
+        assert!(!name.contains(['<', '>', ':', '"', '/', '\\', '|', '?', '*']));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::cancel::CancelToken;
+use crate::document::{self, DOCUMENT_EXTENSION};
+use crate::pandoc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder below `destination` that mirrors where `path` sits under `source_root`, created on
+/// demand
+pub fn target_dir(source_root: &Path, path: &Path, destination: &Path) -> Result<PathBuf, String> {
+    let relative_dir = path
+        .parent()
+        .and_then(|dir| dir.strip_prefix(source_root).ok())
+        .unwrap_or(Path::new(""));
+    let target_dir = destination.join(relative_dir);
+    fs::create_dir_all(&target_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    Ok(target_dir)
+}
+
+/// Export one document below `destination`, mirroring its folder under `source_root`, as
+/// `json`, `markdown` or any pandoc output format; returns the file written. `pandoc` locates
+/// the binary and is only called for pandoc formats.
+pub fn export_document(
+    source_root: &Path,
+    path: &Path,
+    destination: &Path,
+    format: &str,
+    pandoc: impl FnOnce() -> Result<PathBuf, String>,
+    cancel: &CancelToken,
+) -> Result<PathBuf, String> {
+    let target_dir = target_dir(source_root, path, destination)?;
+    let stem = document::document_stem(path);
+    match format {
+        "json" => {
+            let output = target_dir.join(format!("{}{}", stem, DOCUMENT_EXTENSION));
+            fs::copy(path, &output).map_err(|e| format!("Failed to copy document: {}", e))?;
+            Ok(output)
+        }
+        "markdown" | "md" => {
+            let board = document::read_board(path)?;
+            let output = target_dir.join(format!("{}.md", stem));
+            fs::write(&output, board.to_markdown())
+                .map_err(|e| format!("Failed to write file: {}", e))?;
+            Ok(output)
+        }
+        other => {
+            let board = document::read_board(path)?;
+            let output = target_dir.join(format!("{}.{}", stem, pandoc::extension_for(other)));
+            pandoc::convert_markdown(
+                &pandoc()?,
+                &board.to_markdown(),
+                &board.board.name,
+                other,
+                &output,
+                cancel,
+            )?;
+            Ok(output)
+        }
+    }
+}
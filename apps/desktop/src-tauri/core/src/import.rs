@@ -0,0 +1,55 @@
+use crate::cancel::CancelToken;
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::pandoc;
+use crate::sanitize::{self, SanitizePolicy};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions read as Markdown as they are; anything else goes through pandoc
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Create a document named `name` in `dir` from Markdown, with its raw HTML cleaned by `policy`
+pub fn write_document(
+    dir: &Path,
+    name: &str,
+    markdown: &str,
+    policy: &SanitizePolicy,
+) -> Result<PathBuf, String> {
+    let markdown = sanitize::clean_markdown(policy, markdown);
+    let output = document::unique_path(dir, name, DOCUMENT_EXTENSION);
+    document::write_board(&output, &BoardFile::from_markdown(name, &markdown))?;
+    Ok(output)
+}
+
+/// Import `file` as a new document in `dir`, named after the file. Markdown and plain text are
+/// read directly; other formats, or any file when `from_format` is given, need `pandoc`.
+pub fn import_file(
+    file: &Path,
+    dir: &Path,
+    policy: &SanitizePolicy,
+    pandoc: Option<&Path>,
+    from_format: Option<&str>,
+    cancel: &CancelToken,
+) -> Result<PathBuf, String> {
+    if !file.is_file() {
+        return Err(format!("File does not exist: {}", file.display()));
+    }
+    let plain = from_format.is_none()
+        && file
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| MARKDOWN_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    let markdown = if plain {
+        fs::read_to_string(file).map_err(|e| format!("Failed to read file: {}", e))?
+    } else {
+        let pandoc = pandoc.ok_or(pandoc::PANDOC_MISSING)?;
+        pandoc::read_markdown(pandoc, file, from_format, cancel)?
+    };
+    cancel.check()?;
+
+    let name = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Imported".to_string());
+    write_document(dir, &name, &markdown, policy)
+}
@@ -0,0 +1,15 @@
+pub mod backup;
+pub mod batch;
+pub mod cancel;
+pub mod crypto;
+pub mod document;
+pub mod export;
+pub mod import;
+pub mod pandoc;
+pub mod paths;
+pub mod read_only;
+pub mod sanitize;
+pub mod search;
+pub mod template;
+pub mod vault;
+pub mod workspace;
@@ -0,0 +1,136 @@
+use crate::cancel::{self, CancelToken};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often a running conversion checks whether it was cancelled
+const CANCEL_POLL: Duration = Duration::from_millis(20);
+pub const PANDOC_MISSING: &str =
+    "Pandoc is not installed. Install it from https://pandoc.org/installing.html and try again.";
+
+/// Run `pandoc` with `args`, feeding it `input`, and return what it printed; the process is
+/// killed when `cancel` fires
+pub fn run(
+    pandoc: &Path,
+    args: &[&str],
+    input: Option<&str>,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let mut child = Command::new(pandoc)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start pandoc: {}", e))?;
+    // Drained on their own threads so pandoc never stalls on a full pipe while it is polled
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| format!("Failed to send document to pandoc: {}", e))?;
+    }
+
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("Failed to run pandoc: {}", e))?
+        {
+            break status;
+        }
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(cancel::CANCELLED.to_string());
+        }
+        std::thread::sleep(CANCEL_POLL);
+    };
+    let collect = |pipe: Option<JoinHandle<Vec<u8>>>| {
+        pipe.and_then(|pipe| pipe.join().ok()).unwrap_or_default()
+    };
+    let (stdout, stderr) = (collect(stdout), collect(stderr));
+
+    if !status.success() {
+        return Err(format!(
+            "Pandoc failed: {}",
+            String::from_utf8_lossy(&stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&stdout).to_string())
+}
+
+fn drain(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        bytes
+    })
+}
+
+/// Convert Markdown to `format` with pandoc, writing the result to `output`
+pub fn convert_markdown(
+    pandoc: &Path,
+    markdown: &str,
+    title: &str,
+    format: &str,
+    output: &Path,
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    let output = output.to_string_lossy().to_string();
+
+    run(
+        pandoc,
+        &[
+            "--from",
+            "markdown",
+            "--to",
+            format,
+            "--standalone",
+            "--metadata",
+            &format!("title={}", title),
+            "--output",
+            &output,
+        ],
+        Some(markdown),
+        cancel,
+    )
+    .map(|_| ())
+}
+
+/// File extension pandoc conventionally writes for an output format
+pub fn extension_for(format: &str) -> &str {
+    match format {
+        "latex" => "tex",
+        "asciidoc" => "adoc",
+        "mediawiki" => "wiki",
+        "commonmark" | "gfm" | "markdown" => "md",
+        "plain" => "txt",
+        "typst" => "typ",
+        other => other,
+    }
+}
+
+/// Convert any file pandoc reads to GitHub-flavoured Markdown
+pub fn read_markdown(
+    pandoc: &Path,
+    file: &Path,
+    from_format: Option<&str>,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let file = file.to_string_lossy();
+    let mut args = vec!["--to", "gfm", "--wrap", "none"];
+    if let Some(format) = from_format {
+        args.extend(["--from", format]);
+    }
+    args.push(&file);
+    run(pandoc, &args, None, cancel)
+}
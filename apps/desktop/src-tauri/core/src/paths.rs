@@ -0,0 +1,25 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Canonicalize `path`; for a path that does not exist yet, resolve its nearest existing
+/// ancestor and append the rest
+pub fn resolve(path: &Path) -> Result<PathBuf, String> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        match existing.canonicalize() {
+            Ok(mut resolved) => {
+                resolved.extend(missing.iter().rev());
+                return Ok(resolved);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let (Some(name), Some(parent)) = (existing.file_name(), existing.parent()) else {
+                    return Err(format!("Failed to resolve path {}: {}", path.display(), e));
+                };
+                missing.push(name);
+                existing = parent;
+            }
+            Err(e) => return Err(format!("Failed to resolve path {}: {}", path.display(), e)),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::paths;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Resolved roots of the read-only workspaces, kept here so [`ensure_writable`] can be called
+/// from helpers with no access to settings
+static ROOTS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// The read-only workspaces
+pub fn roots() -> Vec<PathBuf> {
+    ROOTS.lock().map(|roots| roots.clone()).unwrap_or_default()
+}
+
+/// Replace the read-only workspaces, as loaded from settings
+pub fn set_roots(roots: Vec<PathBuf>) {
+    if let Ok(mut current) = ROOTS.lock() {
+        *current = roots;
+    }
+}
+
+/// Whether `path` is inside a read-only workspace
+pub fn is_read_only(path: &Path) -> bool {
+    let Ok(roots) = ROOTS.lock() else {
+        return false;
+    };
+    if roots.is_empty() {
+        return false;
+    }
+    let resolved = paths::resolve(path).unwrap_or_else(|_| path.to_path_buf());
+    roots
+        .iter()
+        .any(|root| path.starts_with(root) || resolved.starts_with(root))
+}
+
+/// Refuse to modify `path` when it is inside a read-only workspace
+pub fn ensure_writable(path: &Path) -> Result<(), String> {
+    if is_read_only(path) {
+        return Err(format!("Workspace is read-only: {}", path.display()));
+    }
+    Ok(())
+}
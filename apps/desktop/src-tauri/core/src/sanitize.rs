@@ -0,0 +1,139 @@
+use crate::workspace;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+use std::path::Path;
+
+const CONFIG: &str = "sanitize";
+/// Tags whose content ammonia removes outright; they can never be allowed
+const UNSAFE_TAGS: &[&str] = &["script", "style"];
+
+/// Changes to ammonia's default allowlist, stored in `.inkfinite/sanitize.json`.
+///
+/// The defaults already drop scripts, styles, event handlers and `javascript:` links.
+#[derive(serde::Serialize, serde::Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SanitizePolicy {
+    /// Tags allowed on top of the defaults, such as `iframe` for trusted embeds
+    pub extra_tags: Vec<String>,
+    /// Default tags to remove as well
+    pub blocked_tags: Vec<String>,
+    /// Attributes allowed on every tag, such as `class`
+    pub extra_attributes: Vec<String>,
+    /// URL schemes links and images may use in place of the defaults (`http`, `https`,
+    /// `mailto` and a few others); empty keeps the defaults
+    pub url_schemes: Vec<String>,
+}
+
+pub fn load_policy(root: &Path) -> SanitizePolicy {
+    workspace::read_config(root, CONFIG).unwrap_or_default()
+}
+
+/// Remove anything that could run script from an HTML fragment
+pub fn clean(policy: &SanitizePolicy, html: &str) -> String {
+    clean_with_schemes(policy, html, &[])
+}
+
+/// [`clean`], also keeping URLs with `schemes`, such as `data` for images inlined into a page
+pub fn clean_with_schemes(policy: &SanitizePolicy, html: &str, schemes: &[&str]) -> String {
+    let mut builder = ammonia::Builder::default();
+    // Entries ammonia refuses to combine with its own settings are ignored
+    builder
+        .add_tags(
+            policy
+                .extra_tags
+                .iter()
+                .map(String::as_str)
+                .filter(|tag| !UNSAFE_TAGS.contains(tag)),
+        )
+        .rm_tags(policy.blocked_tags.iter().map(String::as_str))
+        .add_generic_attributes(
+            policy
+                .extra_attributes
+                .iter()
+                .map(String::as_str)
+                .filter(|attribute| *attribute != "rel"),
+        );
+    if !policy.url_schemes.is_empty() {
+        builder.url_schemes(policy.url_schemes.iter().map(String::as_str).collect());
+    }
+    builder.add_url_schemes(schemes.iter().copied());
+    builder.clean(html).to_string()
+}
+
+/// [`clean`] the raw HTML inside Markdown, leaving the Markdown itself untouched.
+///
+/// Inline tags arrive one at a time, so each is cleaned on its own: an allowed opening tag
+/// keeps its allowed attributes, a closing tag stays when its element is allowed, and the rest
+/// is dropped.
+pub fn clean_markdown(policy: &SanitizePolicy, markdown: &str) -> String {
+    let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+    let mut in_block = false;
+    for (event, range) in Parser::new_ext(markdown, Options::all()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::HtmlBlock) => {
+                in_block = true;
+                let block = &markdown[range.clone()];
+                let trailing = &block[block.trim_end().len()..];
+                let cleaned = format!("{}{}", clean(policy, block).trim_end(), trailing);
+                replacements.push((range, cleaned));
+            }
+            Event::End(TagEnd::HtmlBlock) => in_block = false,
+            Event::Html(_) | Event::InlineHtml(_) if !in_block => {
+                let tag = &markdown[range.clone()];
+                replacements.push((range, clean_tag(policy, tag)));
+            }
+            _ => {}
+        }
+    }
+    if replacements.is_empty() {
+        return markdown.to_string();
+    }
+
+    let mut out = String::with_capacity(markdown.len());
+    let mut last = 0;
+    for (range, replacement) in replacements {
+        out.push_str(&markdown[last..range.start]);
+        out.push_str(&replacement);
+        last = range.end;
+    }
+    out.push_str(&markdown[last..]);
+    out
+}
+
+fn clean_tag(policy: &SanitizePolicy, tag: &str) -> String {
+    let trimmed = tag.trim();
+    if let Some(name) = trimmed
+        .strip_prefix("</")
+        .and_then(|rest| rest.strip_suffix('>'))
+        .map(str::trim)
+    {
+        let allowed = !clean(policy, &format!("<{}></{}>", name, name)).is_empty();
+        let plain = name.chars().all(|c| c.is_ascii_alphanumeric());
+        return if allowed && plain {
+            format!("</{}>", name.to_ascii_lowercase())
+        } else {
+            String::new()
+        };
+    }
+    // The cleaned opening tag comes back closed, as in `<b></b>`; only the opening part is kept
+    let cleaned = clean(policy, trimmed);
+    match cleaned.find('>') {
+        Some(end) if cleaned.starts_with('<') => cleaned[..=end].to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Validate and store the policy of a workspace
+pub fn save_policy(root: &Path, policy: &SanitizePolicy) -> Result<(), String> {
+    if let Some(tag) = policy
+        .extra_tags
+        .iter()
+        .find(|tag| UNSAFE_TAGS.contains(&tag.to_ascii_lowercase().as_str()))
+    {
+        return Err(format!(
+            "Invalid sanitize policy: {} cannot be allowed",
+            tag
+        ));
+    }
+    workspace::write_config(root, CONFIG, policy)
+}
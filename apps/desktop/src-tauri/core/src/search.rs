@@ -0,0 +1,146 @@
+use crate::cancel::CancelToken;
+use crate::{document, vault, workspace};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const INDEX_CONFIG: &str = "search-index";
+const SNIPPET_RADIUS: usize = 60;
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// Extra searchable text attached to non-document files, such as OCR output for assets
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct SearchIndex {
+    /// Keyed by workspace-relative path of the file the text belongs to
+    entries: BTreeMap<String, IndexedText>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct IndexedText {
+    /// What produced the text, e.g. `ocr`
+    source: String,
+    text: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    /// Workspace-relative path of the matching document or asset
+    pub path: String,
+    /// `document` for boards, otherwise the indexed text's source
+    pub kind: String,
+    pub snippet: String,
+}
+
+/// Record text for `path` so workspace search can find it
+pub fn index_text(root: &Path, path: &Path, source: &str, text: &str) -> Result<(), String> {
+    let mut index: SearchIndex = workspace::read_config(root, INDEX_CONFIG)?;
+    index.entries.insert(
+        workspace::relative_path(root, path),
+        IndexedText {
+            source: source.to_string(),
+            text: text.to_string(),
+        },
+    );
+    workspace::write_config(root, INDEX_CONFIG, &index)
+}
+
+/// Drop the indexed text for `path`; returns whether there was any
+pub fn remove_text(root: &Path, path: &Path) -> Result<bool, String> {
+    let mut index: SearchIndex = workspace::read_config(root, INDEX_CONFIG)?;
+    if index
+        .entries
+        .remove(&workspace::relative_path(root, path))
+        .is_none()
+    {
+        return Ok(false);
+    }
+    workspace::write_config(root, INDEX_CONFIG, &index)?;
+    Ok(true)
+}
+
+/// Drop indexed text for files that no longer exist, returning how many entries were removed
+pub fn prune(root: &Path) -> Result<usize, String> {
+    let mut index: SearchIndex = workspace::read_config(root, INDEX_CONFIG)?;
+    let before = index.entries.len();
+    index.entries.retain(|path, _| root.join(path).exists());
+    let removed = before - index.entries.len();
+    if removed > 0 {
+        workspace::write_config(root, INDEX_CONFIG, &index)?;
+    }
+    Ok(removed)
+}
+
+/// Read the index so the first search does not wait on the disk, returning its entry count
+pub fn warm(root: &Path) -> Result<usize, String> {
+    let index: SearchIndex = workspace::read_config(root, INDEX_CONFIG)?;
+    Ok(index.entries.len())
+}
+
+/// Case-insensitive full-text search over `documents`, then over the indexed asset text of
+/// `root` when `include_index` is set. Stops after `limit` hits.
+pub fn search(
+    root: &Path,
+    documents: &[PathBuf],
+    query: &str,
+    limit: usize,
+    include_index: bool,
+    cancel: &CancelToken,
+) -> Result<Vec<SearchHit>, String> {
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut hits = Vec::new();
+    for path in documents {
+        cancel.check()?;
+        let Ok(board) = document::read_board(path) else {
+            continue;
+        };
+        let text = format!("{}\n{}", board.board.name, board.to_markdown());
+        if let Some(snippet) = snippet(&text, &needle) {
+            hits.push(SearchHit {
+                path: workspace::relative_path(root, path),
+                kind: "document".to_string(),
+                snippet,
+            });
+        }
+        if hits.len() >= limit {
+            return Ok(hits);
+        }
+    }
+
+    if !include_index {
+        return Ok(hits);
+    }
+    let index: SearchIndex = workspace::read_config(root, INDEX_CONFIG)?;
+    for (path, entry) in index.entries {
+        if vault::is_locked(&root.join(&path)) {
+            continue;
+        }
+        if let Some(snippet) = snippet(&entry.text, &needle) {
+            hits.push(SearchHit {
+                path,
+                kind: entry.source,
+                snippet,
+            });
+        }
+        if hits.len() >= limit {
+            break;
+        }
+    }
+
+    Ok(hits)
+}
+
+/// Text around the first match of an already-lowercased `needle`
+fn snippet(text: &str, needle: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    let at = lower.find(needle)?;
+    // Lowercasing can shift byte offsets for some scripts, so map back through char counts
+    let char_at = lower[..at].chars().count();
+    let chars: Vec<char> = text.chars().collect();
+    let start = char_at.saturating_sub(SNIPPET_RADIUS);
+    let end = (char_at + needle.chars().count() + SNIPPET_RADIUS).min(chars.len());
+    let snippet: String = chars[start.min(chars.len())..end].iter().collect();
+    Some(snippet.split_whitespace().collect::<Vec<_>>().join(" "))
+}
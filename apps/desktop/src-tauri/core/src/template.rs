@@ -0,0 +1,691 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Fences around the TOML header that declares a template's name and prompts
+const FRONT_MATTER: &str = "+++";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M";
+const NOW_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum PromptKind {
+    #[default]
+    Text,
+    Multiline,
+    /// One value per line, iterated with `{{#each}}`
+    List,
+    /// One of `options`
+    Choice,
+    /// A `YYYY-MM-DD` day, which `{{name "<format>"}}` can reformat
+    Date,
+}
+
+/// A field the user fills in before the template is evaluated
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Prompt {
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub kind: PromptKind,
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FrontMatter {
+    name: Option<String>,
+    description: Option<String>,
+    /// Name of created documents, itself a template
+    title: Option<String>,
+    /// Workspace-relative folder created documents go to, itself a template
+    folder: Option<String>,
+    prompts: Vec<Prompt>,
+}
+
+/// A Markdown template with an optional `+++` TOML header
+#[derive(Clone, Debug)]
+pub struct Template {
+    pub name: String,
+    pub description: Option<String>,
+    pub title: Option<String>,
+    pub folder: Option<String>,
+    pub prompts: Vec<Prompt>,
+    pub body: String,
+}
+
+impl Template {
+    /// Parse a template source, checking its syntax; `fallback_name` is used when the header
+    /// gives none
+    pub fn parse(source: &str, fallback_name: &str) -> Result<Template, String> {
+        let (header, body) = split_front_matter(source);
+        let header: FrontMatter = match header {
+            Some(header) => toml::from_str(header)
+                .map_err(|e| format!("Invalid template header: {}", e.message()))?,
+            None => FrontMatter::default(),
+        };
+        for prompt in &header.prompts {
+            if prompt.name.trim().is_empty() || prompt.name.contains(['.', ' ', '@']) {
+                return Err(format!("Invalid prompt name: {:?}", prompt.name));
+            }
+            if prompt.kind == PromptKind::Choice && prompt.options.is_empty() {
+                return Err(format!(
+                    "Invalid prompt {}: a choice needs options",
+                    prompt.name
+                ));
+            }
+        }
+        let template = Template {
+            name: header.name.unwrap_or_else(|| fallback_name.to_string()),
+            description: header.description,
+            title: header.title,
+            folder: header.folder,
+            prompts: header.prompts,
+            body: body.to_string(),
+        };
+        for source in [
+            Some(&template.body),
+            template.title.as_ref(),
+            template.folder.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            compile(source)?;
+        }
+        Ok(template)
+    }
+
+    /// Context for rendering: `values` from the prompts, with list prompts split into lines,
+    /// defaults filled in and required prompts checked
+    pub fn values(&self, values: &Value) -> Result<Value, String> {
+        let mut context = match values {
+            Value::Object(map) => map.clone(),
+            Value::Null => serde_json::Map::new(),
+            _ => return Err("Invalid template values: expected an object".to_string()),
+        };
+        for prompt in &self.prompts {
+            let value = match context.remove(&prompt.name) {
+                Some(Value::Null) | None => prompt.default.clone().map(Value::String),
+                Some(value) => Some(value),
+            };
+            let value = match (prompt.kind, value) {
+                (PromptKind::List, Some(Value::String(text))) => Some(Value::Array(
+                    text.lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(|line| Value::String(line.to_string()))
+                        .collect(),
+                )),
+                (PromptKind::Choice, Some(Value::String(choice)))
+                    if !prompt.options.contains(&choice) =>
+                {
+                    return Err(format!("Invalid value for {}: {}", prompt.name, choice));
+                }
+                (_, value) => value,
+            };
+            if prompt.required && value.as_ref().is_none_or(|value| !truthy(value)) {
+                let label = prompt.label.as_deref().unwrap_or(&prompt.name);
+                return Err(format!("Invalid template values: {} is required", label));
+            }
+            context.insert(prompt.name.clone(), value.unwrap_or(Value::Null));
+        }
+        Ok(Value::Object(context))
+    }
+}
+
+/// Split off a leading `+++` header, returning it and the rest of the source
+fn split_front_matter(source: &str) -> (Option<&str>, &str) {
+    let Some(rest) = source.strip_prefix(FRONT_MATTER).and_then(|rest| {
+        rest.strip_prefix('\n')
+            .or_else(|| rest.strip_prefix("\r\n"))
+    }) else {
+        return (None, source);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == FRONT_MATTER {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, source)
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    /// `{{path}}`, or `{{path "format"}}` to format a date
+    Value {
+        path: String,
+        format: Option<String>,
+    },
+    Each {
+        path: String,
+        body: Vec<Node>,
+    },
+    If {
+        path: String,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+enum Tag<'a> {
+    Value(&'a str),
+    Open(&'a str, &'a str),
+    Else,
+    Close(&'a str),
+    Comment,
+}
+
+impl Tag<'_> {
+    /// Block tags alone on a line take the whole line with them, so they leave no blank lines
+    fn standalone(&self) -> bool {
+        !matches!(self, Tag::Value(_))
+    }
+}
+
+fn parse_tag(inner: &str) -> Result<Tag<'_>, String> {
+    let inner = inner.trim();
+    if inner.starts_with('!') {
+        return Ok(Tag::Comment);
+    }
+    if inner == "else" {
+        return Ok(Tag::Else);
+    }
+    if let Some(rest) = inner.strip_prefix('#') {
+        let (block, path) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let path = path.trim();
+        if path.is_empty() {
+            return Err(format!(
+                "Invalid template: {{{{#{}}}}} needs a value",
+                block
+            ));
+        }
+        return Ok(Tag::Open(block, path));
+    }
+    if let Some(block) = inner.strip_prefix('/') {
+        return Ok(Tag::Close(block.trim()));
+    }
+    if inner.is_empty() {
+        return Err("Invalid template: empty {{}}".to_string());
+    }
+    Ok(Tag::Value(inner))
+}
+
+/// Split a source into text and tags, dropping the line around standalone block tags
+fn tokenize(source: &str) -> Result<Vec<(String, Option<Tag<'_>>)>, String> {
+    let mut tokens: Vec<(String, Option<Tag>)> = Vec::new();
+    let mut text = String::new();
+    let mut rest = source;
+    loop {
+        let Some(at) = rest.find("{{") else {
+            text.push_str(rest);
+            break;
+        };
+        // `\{{` is a literal `{{`
+        if rest[..at].ends_with('\\') {
+            text.push_str(&rest[..at - 1]);
+            text.push_str("{{");
+            rest = &rest[at + 2..];
+            continue;
+        }
+        text.push_str(&rest[..at]);
+        let end = rest[at..]
+            .find("}}")
+            .ok_or("Invalid template: {{ is never closed")?;
+        let tag = parse_tag(&rest[at + 2..at + end])?;
+        rest = &rest[at + end + 2..];
+
+        if tag.standalone() {
+            let line_start = text.rfind('\n').map_or(0, |i| i + 1);
+            let line_end = rest.find('\n');
+            let before = &text[line_start..];
+            let after = &rest[..line_end.unwrap_or(rest.len())];
+            let at_line_start = line_start > 0 || tokens.iter().all(|(text, _)| text.is_empty());
+            if at_line_start && before.trim().is_empty() && after.trim().is_empty() {
+                text.truncate(line_start);
+                rest = &rest[line_end.map_or(rest.len(), |i| i + 1)..];
+            }
+        }
+        tokens.push((std::mem::take(&mut text), Some(tag)));
+    }
+    tokens.push((text, None));
+    Ok(tokens)
+}
+
+fn compile(source: &str) -> Result<Vec<Node>, String> {
+    let mut tokens = tokenize(source)?.into_iter();
+    let (nodes, end) = compile_block(&mut tokens)?;
+    match end {
+        None => Ok(nodes),
+        Some(Ending::Else) => Err("Invalid template: {{else}} outside a block".to_string()),
+        Some(Ending::Close(block)) => Err(format!(
+            "Invalid template: {{{{/{}}}}} without an opening tag",
+            block
+        )),
+    }
+}
+
+enum Ending<'a> {
+    Else,
+    Close(&'a str),
+}
+
+fn compile_block<'a>(
+    tokens: &mut impl Iterator<Item = (String, Option<Tag<'a>>)>,
+) -> Result<(Vec<Node>, Option<Ending<'a>>), String> {
+    let mut nodes = Vec::new();
+    while let Some((text, tag)) = tokens.next() {
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        let Some(tag) = tag else {
+            return Ok((nodes, None));
+        };
+        match tag {
+            Tag::Comment => {}
+            Tag::Value(expression) => nodes.push(value_node(expression)?),
+            Tag::Else => return Ok((nodes, Some(Ending::Else))),
+            Tag::Close(block) => return Ok((nodes, Some(Ending::Close(block)))),
+            Tag::Open(block, path) => {
+                let (then, mut end) = compile_block(tokens)?;
+                let mut otherwise = Vec::new();
+                if matches!(end, Some(Ending::Else)) && block != "each" {
+                    (otherwise, end) = compile_block(tokens)?;
+                }
+                match end {
+                    Some(Ending::Close(closed)) if closed == block => {}
+                    _ => {
+                        return Err(format!(
+                            "Invalid template: {{{{#{} {}}}}} is never closed",
+                            block, path
+                        ))
+                    }
+                }
+                let path = path.to_string();
+                nodes.push(match block {
+                    "each" => Node::Each { path, body: then },
+                    "if" | "unless" => Node::If {
+                        path,
+                        negate: block == "unless",
+                        then,
+                        otherwise,
+                    },
+                    other => return Err(format!("Invalid template: unknown block #{}", other)),
+                });
+            }
+        }
+    }
+    Ok((nodes, None))
+}
+
+fn value_node(expression: &str) -> Result<Node, String> {
+    let (path, format) = match expression.split_once(char::is_whitespace) {
+        Some((path, format)) => {
+            let format = format.trim();
+            let format = format
+                .strip_prefix('"')
+                .and_then(|format| format.strip_suffix('"'))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid template: expected a quoted format in {{{{{}}}}}",
+                        expression
+                    )
+                })?;
+            (path, Some(format.to_string()))
+        }
+        None => (expression, None),
+    };
+    Ok(Node::Value {
+        path: path.to_string(),
+        format,
+    })
+}
+
+/// Output of a render, with the names that had no value
+pub struct Rendered {
+    pub text: String,
+    pub missing: BTreeSet<String>,
+}
+
+/// Render `source` with `values`. `{{date}}`, `{{time}}` and `{{now}}` come from `now` unless
+/// `values` has them; unknown names render empty and are reported.
+pub fn render(source: &str, values: &Value, now: DateTime<Local>) -> Result<Rendered, String> {
+    let nodes = compile(source)?;
+    let mut renderer = Renderer {
+        now,
+        scopes: vec![Scope {
+            value: values.clone(),
+            index: None,
+        }],
+        missing: BTreeSet::new(),
+    };
+    let mut text = String::new();
+    renderer.render(&nodes, &mut text)?;
+    Ok(Rendered {
+        text,
+        missing: renderer.missing,
+    })
+}
+
+struct Scope {
+    value: Value,
+    /// Position and length of the list being iterated
+    index: Option<(usize, usize)>,
+}
+
+struct Renderer {
+    now: DateTime<Local>,
+    scopes: Vec<Scope>,
+    missing: BTreeSet<String>,
+}
+
+impl Renderer {
+    fn lookup(&self, path: &str) -> Option<Value> {
+        let scope = self.scopes.last()?;
+        match path {
+            "this" | "." => return Some(scope.value.clone()),
+            "@index" => return scope.index.map(|(index, _)| Value::from(index)),
+            "@number" => return scope.index.map(|(index, _)| Value::from(index + 1)),
+            "@first" => return scope.index.map(|(index, _)| Value::Bool(index == 0)),
+            "@last" => {
+                return scope
+                    .index
+                    .map(|(index, len)| Value::Bool(index + 1 == len))
+            }
+            _ => {}
+        }
+        let (path, from_item) = match path.strip_prefix("this.") {
+            Some(path) => (path, true),
+            None => (path, false),
+        };
+        let mut segments = path.split('.');
+        let first = segments.next()?;
+        let scopes: Vec<&Scope> = if from_item {
+            vec![scope]
+        } else {
+            self.scopes.iter().rev().collect()
+        };
+        let mut value = scopes
+            .into_iter()
+            .find_map(|scope| scope.value.get(first))?
+            .clone();
+        for segment in segments {
+            value = match &value {
+                Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                other => other.get(segment),
+            }?
+            .clone();
+        }
+        Some(value)
+    }
+
+    fn builtin(&self, path: &str) -> Option<(NaiveDateTime, &'static str)> {
+        let now = self.now.naive_local();
+        match path {
+            "date" => Some((now, DATE_FORMAT)),
+            "time" => Some((now, TIME_FORMAT)),
+            "now" => Some((now, NOW_FORMAT)),
+            _ => None,
+        }
+    }
+
+    fn render(&mut self, nodes: &[Node], out: &mut String) -> Result<(), String> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Value { path, format } => {
+                    if let Some(value) = self.lookup(path) {
+                        match format {
+                            Some(format) => out.push_str(&format_date(&value, format)?),
+                            None => out.push_str(&display(&value)),
+                        }
+                    } else if let Some((now, default)) = self.builtin(path) {
+                        let format = format.as_deref().unwrap_or(default);
+                        out.push_str(&checked_format(now, format)?);
+                    } else {
+                        self.missing.insert(path.clone());
+                    }
+                }
+                Node::If {
+                    path,
+                    negate,
+                    then,
+                    otherwise,
+                } => {
+                    let value = self.lookup(path);
+                    if value.is_none() && self.builtin(path).is_none() {
+                        self.missing.insert(path.clone());
+                    }
+                    let set = value.as_ref().is_some_and(truthy) || self.builtin(path).is_some();
+                    self.render(if set != *negate { then } else { otherwise }, out)?;
+                }
+                Node::Each { path, body } => {
+                    let Some(value) = self.lookup(path) else {
+                        self.missing.insert(path.clone());
+                        continue;
+                    };
+                    let items: Vec<Value> = match value {
+                        Value::Array(items) => items,
+                        Value::Object(map) => map.into_values().collect(),
+                        Value::Null => Vec::new(),
+                        other => vec![other],
+                    };
+                    let len = items.len();
+                    for (index, item) in items.into_iter().enumerate() {
+                        self.scopes.push(Scope {
+                            value: item,
+                            index: Some((index, len)),
+                        });
+                        let result = self.render(body, out);
+                        self.scopes.pop();
+                        result?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(text) => !text.trim().is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// Reformat a `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM` value with a strftime format
+fn format_date(value: &Value, format: &str) -> Result<String, String> {
+    let text = display(value);
+    let date = NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(&text, DATE_FORMAT)
+                .map(|date| date.and_time(Default::default()))
+        })
+        .map_err(|_| format!("Invalid date: {}", text))?;
+    checked_format(date, format)
+}
+
+/// Format with a user-supplied strftime string, which chrono only rejects while writing
+fn checked_format(date: NaiveDateTime, format: &str) -> Result<String, String> {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    write!(out, "{}", date.format(format))
+        .map_err(|_| format!("Invalid date format: {}", format))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 4, 9, 5, 0).unwrap()
+    }
+
+    fn text(source: &str, values: Value) -> String {
+        render(source, &values, now()).unwrap().text
+    }
+
+    #[test]
+    fn header_is_parsed_and_body_kept() {
+        let template = Template::parse(
+            "+++\nname = \"Meeting\"\n[[prompts]]\nname = \"topic\"\nrequired = true\n+++\n# {{topic}}\n",
+            "fallback",
+        )
+        .unwrap();
+        assert_eq!(template.name, "Meeting");
+        assert_eq!(template.prompts.len(), 1);
+        assert!(template.prompts[0].required);
+        assert_eq!(template.body, "# {{topic}}\n");
+    }
+
+    #[test]
+    fn missing_header_uses_the_fallback_name() {
+        let template = Template::parse("Just text", "Daily").unwrap();
+        assert_eq!(template.name, "Daily");
+        assert_eq!(template.body, "Just text");
+    }
+
+    #[test]
+    fn invalid_prompts_are_rejected() {
+        let source = "+++\n[[prompts]]\nname = \"a.b\"\n+++\n";
+        assert!(Template::parse(source, "t").is_err());
+        let source = "+++\n[[prompts]]\nname = \"pick\"\nkind = \"choice\"\n+++\n";
+        assert!(Template::parse(source, "t").is_err());
+    }
+
+    #[test]
+    fn unbalanced_blocks_are_rejected() {
+        assert!(Template::parse("{{#if a}}open", "t").is_err());
+        assert!(Template::parse("{{/if}}", "t").is_err());
+        assert!(Template::parse("{{else}}", "t").is_err());
+        assert!(Template::parse("{{#each a}}x{{/if}}", "t").is_err());
+        assert!(Template::parse("{{#loop a}}x{{/loop}}", "t").is_err());
+        assert!(Template::parse("{{never closed", "t").is_err());
+    }
+
+    #[test]
+    fn values_lookup_nested_paths_and_report_missing_names() {
+        let rendered = render(
+            "{{user.name}} {{items.1}} {{nope}}",
+            &json!({"user": {"name": "Ada"}, "items": ["a", "b"]}),
+            now(),
+        )
+        .unwrap();
+        assert_eq!(rendered.text, "Ada b ");
+        assert_eq!(rendered.missing, BTreeSet::from(["nope".to_string()]));
+    }
+
+    #[test]
+    fn escaped_braces_are_literal() {
+        assert_eq!(text("\\{{name}}", json!({"name": "x"})), "{{name}}");
+    }
+
+    #[test]
+    fn standalone_block_tags_leave_no_blank_lines() {
+        let source = "start\n{{#if flag}}\nyes\n{{else}}\nno\n{{/if}}\nend\n";
+        assert_eq!(text(source, json!({"flag": true})), "start\nyes\nend\n");
+        assert_eq!(text(source, json!({"flag": false})), "start\nno\nend\n");
+    }
+
+    #[test]
+    fn unless_negates_the_condition() {
+        let source = "{{#unless done}}todo{{/unless}}";
+        assert_eq!(text(source, json!({"done": ""})), "todo");
+        assert_eq!(text(source, json!({"done": "yes"})), "");
+    }
+
+    #[test]
+    fn each_iterates_with_index_helpers() {
+        let source = "{{#each items}}{{@number}}. {{this}}{{#unless @last}}, {{/unless}}{{/each}}";
+        assert_eq!(
+            text(source, json!({"items": ["a", "b", "c"]})),
+            "1. a, 2. b, 3. c"
+        );
+    }
+
+    #[test]
+    fn each_falls_back_to_outer_scopes() {
+        let source = "{{#each people}}{{name}}@{{team}} {{/each}}";
+        let values = json!({"team": "core", "people": [{"name": "a"}, {"name": "b"}]});
+        assert_eq!(text(source, values), "a@core b@core ");
+    }
+
+    #[test]
+    fn builtin_dates_use_now_unless_overridden() {
+        assert_eq!(
+            text("{{date}} {{time}} {{now}}", json!({})),
+            "2026-03-04 09:05 2026-03-04 09:05"
+        );
+        assert_eq!(text("{{date \"%d/%m\"}}", json!({})), "04/03");
+        assert_eq!(text("{{date}}", json!({"date": "override"})), "override");
+    }
+
+    #[test]
+    fn date_values_are_reformatted() {
+        let values = json!({"due": "2026-12-25"});
+        assert_eq!(text("{{due \"%B %e\"}}", values), "December 25");
+        assert!(render("{{due \"%Y\"}}", &json!({"due": "soon"}), now()).is_err());
+        assert!(render("{{date %Y}}", &json!({}), now()).is_err());
+    }
+
+    #[test]
+    fn invalid_date_formats_are_errors_not_panics() {
+        assert!(render("{{date \"%Q\"}}", &json!({}), now()).is_err());
+    }
+
+    #[test]
+    fn prompt_values_are_defaulted_split_and_checked() {
+        let template = Template::parse(
+            "+++\n\
+             [[prompts]]\nname = \"tags\"\nkind = \"list\"\n\
+             [[prompts]]\nname = \"size\"\nkind = \"choice\"\noptions = [\"s\", \"m\"]\ndefault = \"m\"\n\
+             [[prompts]]\nname = \"title\"\nlabel = \"Title\"\nrequired = true\n\
+             +++\n",
+            "t",
+        )
+        .unwrap();
+        let values = template
+            .values(&json!({"tags": "a\n\n b \n", "title": "Hi"}))
+            .unwrap();
+        assert_eq!(values["tags"], json!(["a", "b"]));
+        assert_eq!(values["size"], "m");
+
+        let error = template.values(&json!({"title": " "})).unwrap_err();
+        assert!(error.contains("Title is required"));
+        assert!(template
+            .values(&json!({"title": "Hi", "size": "xl"}))
+            .is_err());
+    }
+}
@@ -0,0 +1,339 @@
+use crate::crypto::{self, SecretKey};
+use crate::document::now_millis;
+use crate::{document, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// File marking a folder as a vault, holding its wrapped key; hidden so listings skip it
+pub const MARKER: &str = ".inkfinite-vault.json";
+/// How every encrypted document starts, so plain ones are told apart without parsing
+const SEALED_PREFIX: &[u8] = b"{\"inkfiniteVault\":";
+const SEALED_VERSION: u32 = 1;
+
+/// Keys of unlocked vaults by canonical folder path.
+///
+/// Kept in a static so [`document::read_board`] and [`document::write_board`] can encrypt and
+/// decrypt wherever they are called from.
+static UNLOCKED: Mutex<Option<HashMap<PathBuf, SecretKey>>> = Mutex::new(None);
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VaultFile {
+    /// Argon2 salt for the passphrase, base64
+    salt: String,
+    /// Random vault key sealed with the passphrase key, base64
+    wrapped_key: String,
+    created_at: i64,
+}
+
+/// On-disk form of a document saved in a vault
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SealedDocument {
+    inkfinite_vault: u32,
+    /// Nonce and ciphertext of the document JSON, base64
+    data: String,
+}
+
+/// The vault folder `path` is in, or is, if any
+pub fn vault_of(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.join(MARKER).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Whether `path` is inside a vault that is locked
+pub fn is_locked(path: &Path) -> bool {
+    vault_of(path).is_some_and(|vault| key(&vault).is_none())
+}
+
+/// Whether any vault is unlocked
+pub fn any_unlocked() -> bool {
+    UNLOCKED
+        .lock()
+        .ok()
+        .and_then(|unlocked| unlocked.as_ref().map(|keys| !keys.is_empty()))
+        .unwrap_or(false)
+}
+
+/// Documents in the unlocked vaults under `root`
+pub fn unlocked_documents(root: &Path) -> Vec<PathBuf> {
+    let Ok(root) = root.canonicalize() else {
+        return Vec::new();
+    };
+    let vaults: Vec<PathBuf> = UNLOCKED
+        .lock()
+        .ok()
+        .and_then(|unlocked| unlocked.as_ref().map(|keys| keys.keys().cloned().collect()))
+        .unwrap_or_default();
+    vaults
+        .iter()
+        .filter(|vault| vault.starts_with(&root))
+        .filter_map(|vault| workspace::list_documents(vault).ok())
+        .flatten()
+        .collect()
+}
+
+/// Forget every vault key; returns whether any vault was unlocked
+pub fn lock_all() -> bool {
+    UNLOCKED
+        .lock()
+        .ok()
+        .and_then(|mut unlocked| unlocked.take())
+        .is_some_and(|keys| !keys.is_empty())
+}
+
+/// Key of `vault` while it is unlocked
+pub fn key(vault: &Path) -> Option<SecretKey> {
+    let vault = vault.canonicalize().ok()?;
+    let unlocked = UNLOCKED.lock().ok()?;
+    unlocked.as_ref()?.get(&vault).cloned()
+}
+
+/// Remember the key of an unlocked vault
+pub fn insert_key(vault: &Path, key: SecretKey) -> Result<(), String> {
+    let vault = vault
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve vault: {}", e))?;
+    let mut unlocked = UNLOCKED
+        .lock()
+        .map_err(|e| format!("Failed to store key: {}", e))?;
+    unlocked.get_or_insert_with(HashMap::new).insert(vault, key);
+    Ok(())
+}
+
+fn locked_error(vault: &Path) -> String {
+    format!("Vault is locked: {}", vault.display())
+}
+
+/// Encrypt `content` for writing to `path` when it is inside a vault; other paths get it back
+/// unchanged. Fails while the vault is locked.
+pub fn seal<'a>(path: &Path, content: &'a [u8]) -> Result<Cow<'a, [u8]>, String> {
+    let Some(vault) = vault_of(path) else {
+        return Ok(Cow::Borrowed(content));
+    };
+    let key = key(&vault).ok_or_else(|| locked_error(&vault))?;
+    let sealed = SealedDocument {
+        inkfinite_vault: SEALED_VERSION,
+        data: BASE64.encode(crypto::seal(&key, content)?),
+    };
+    serde_json::to_vec(&sealed)
+        .map(Cow::Owned)
+        .map_err(|e| format!("Failed to serialize document: {}", e))
+}
+
+/// Decrypt `content` read from `path` if it was saved in a vault; plain content is returned
+/// unchanged
+pub fn unseal<'a>(path: &Path, content: &'a [u8]) -> Result<Cow<'a, [u8]>, String> {
+    if !content.starts_with(SEALED_PREFIX) {
+        return Ok(Cow::Borrowed(content));
+    }
+    let vault = vault_of(path)
+        .ok_or_else(|| format!("Encrypted document is not in a vault: {}", path.display()))?;
+    let key = key(&vault).ok_or_else(|| locked_error(&vault))?;
+    let sealed: SealedDocument =
+        serde_json::from_slice(content).map_err(|e| format!("Invalid file format: {}", e))?;
+    if sealed.inkfinite_vault != SEALED_VERSION {
+        return Err(format!(
+            "Invalid file format: unknown vault version {}",
+            sealed.inkfinite_vault
+        ));
+    }
+    let data = BASE64
+        .decode(sealed.data)
+        .map_err(|e| format!("Invalid file format: {}", e))?;
+    Ok(Cow::Owned(crypto::open(&key, &data)?.to_vec()))
+}
+
+/// The vault `path` is in, resolved so differently spelled paths compare equal
+fn canonical_vault(path: &Path) -> Option<PathBuf> {
+    vault_of(path).map(|vault| vault.canonicalize().unwrap_or(vault))
+}
+
+/// Move a file or folder from `from` to `to`, re-encrypting a document that moves into, out
+/// of or between vaults so it stays readable where it lands. Fails while the vault of either
+/// side is locked; folders cannot cross into or out of a vault.
+pub fn rename(from: &Path, to: &Path) -> Result<(), String> {
+    let destination = to.parent().unwrap_or(to);
+    for side in [from, destination] {
+        if let Some(vault) = vault_of(side).filter(|vault| key(vault).is_none()) {
+            return Err(locked_error(&vault));
+        }
+    }
+    let folder = from.is_dir();
+    let source = match folder {
+        true => from.parent().unwrap_or(from),
+        false => from,
+    };
+    let content = match (
+        folder,
+        canonical_vault(source) == canonical_vault(destination),
+    ) {
+        (_, true) => None,
+        (true, false) => return Err("Folders cannot be moved into or out of a vault".to_string()),
+        (false, false) => {
+            let content = fs::read(from).map_err(|e| format!("Failed to read file: {}", e))?;
+            let name = from.to_string_lossy();
+            (name.ends_with(document::DOCUMENT_EXTENSION) || content.starts_with(SEALED_PREFIX))
+                .then_some(content)
+        }
+    };
+    let Some(content) = content else {
+        return fs::rename(from, to).map_err(|e| format!("Failed to rename file: {}", e));
+    };
+    let plain = unseal(from, &content)?;
+    let content = seal(to, &plain)?;
+    let temp = document::saving_path(to);
+    fs::write(&temp, &content).map_err(|e| format!("Failed to write file: {}", e))?;
+    fs::rename(&temp, to).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        format!("Failed to write file: {}", e)
+    })?;
+    fs::remove_file(from).map_err(|e| format!("Failed to remove file: {}", e))
+}
+
+fn read_vault_file(vault: &Path) -> Result<VaultFile, String> {
+    let content = fs::read_to_string(vault.join(MARKER))
+        .map_err(|e| format!("Vault does not exist: {} ({})", vault.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid vault file: {}", e))
+}
+
+fn unwrap_key(file: &VaultFile, passphrase: &str) -> Result<SecretKey, String> {
+    let salt = BASE64
+        .decode(&file.salt)
+        .map_err(|e| format!("Invalid vault file: {}", e))?;
+    let wrapped = BASE64
+        .decode(&file.wrapped_key)
+        .map_err(|e| format!("Invalid vault file: {}", e))?;
+    let key = crypto::open(&crypto::derive_key(passphrase, &salt)?, &wrapped)
+        .map_err(|_| "Wrong passphrase".to_string())?;
+    let key: [u8; crypto::KEY_LEN] = key
+        .as_slice()
+        .try_into()
+        .map_err(|_| "Invalid vault file: bad key length".to_string())?;
+    Ok(SecretKey::new(key))
+}
+
+/// Forget the key of `vault`; returns whether it was unlocked
+pub fn remove_key(vault: &Path) -> Result<bool, String> {
+    let vault = vault
+        .canonicalize()
+        .map_err(|e| format!("Vault does not exist: {} ({})", vault.display(), e))?;
+    let mut unlocked = UNLOCKED
+        .lock()
+        .map_err(|e| format!("Failed to forget key: {}", e))?;
+    Ok(unlocked
+        .as_mut()
+        .is_some_and(|keys| keys.remove(&vault).is_some()))
+}
+
+/// Make `dir` a vault protected by `passphrase`, encrypting the documents already in it. The
+/// vault starts unlocked.
+pub fn create(dir: &Path, passphrase: &str) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let key = crypto::random_key();
+    let salt = crypto::random_salt();
+    let wrapped = crypto::seal(&crypto::derive_key(passphrase, &salt)?, key.as_ref())?;
+    let file = VaultFile {
+        salt: BASE64.encode(salt),
+        wrapped_key: BASE64.encode(wrapped),
+        created_at: now_millis(),
+    };
+    let content = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize vault file: {}", e))?;
+    fs::write(dir.join(MARKER), content)
+        .map_err(|e| format!("Failed to write vault file: {}", e))?;
+    insert_key(dir, key)?;
+
+    for document in workspace::list_documents(dir)? {
+        let board = document::read_board(&document)?;
+        document::write_board(&document, &board)?;
+    }
+    Ok(())
+}
+
+/// The key of `vault`, unwrapped with its passphrase
+pub fn passphrase_key(vault: &Path, passphrase: &str) -> Result<SecretKey, String> {
+    unwrap_key(&read_vault_file(vault)?, passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::BoardFile;
+
+    fn workspace(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("inkfinite-vault-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn documents_moved_into_a_vault_are_encrypted() {
+        let root = workspace("into");
+        let vault = root.join("private");
+        create(&vault, "passphrase").unwrap();
+        let plain = root.join("notes.inkfinite.json");
+        document::write_board(&plain, &BoardFile::new("Notes")).unwrap();
+
+        let moved = vault.join("notes.inkfinite.json");
+        rename(&plain, &moved).unwrap();
+        assert!(!plain.exists());
+        assert!(fs::read(&moved).unwrap().starts_with(SEALED_PREFIX));
+        assert_eq!(document::read_board(&moved).unwrap().board.name, "Notes");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn documents_moved_out_of_a_vault_are_decrypted() {
+        let root = workspace("out");
+        let vault = root.join("private");
+        create(&vault, "passphrase").unwrap();
+        let sealed = vault.join("notes.inkfinite.json");
+        document::write_board(&sealed, &BoardFile::new("Notes")).unwrap();
+        assert!(fs::read(&sealed).unwrap().starts_with(SEALED_PREFIX));
+
+        let moved = root.join("notes.inkfinite.json");
+        rename(&sealed, &moved).unwrap();
+        assert!(!fs::read(&moved).unwrap().starts_with(SEALED_PREFIX));
+        assert_eq!(document::read_board(&moved).unwrap().board.name, "Notes");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn locked_vaults_refuse_moves_either_way() {
+        let root = workspace("locked");
+        let vault = root.join("private");
+        create(&vault, "passphrase").unwrap();
+        let inside = vault.join("secret.inkfinite.json");
+        document::write_board(&inside, &BoardFile::new("Secret")).unwrap();
+        let outside = root.join("notes.inkfinite.json");
+        document::write_board(&outside, &BoardFile::new("Notes")).unwrap();
+        remove_key(&vault).unwrap();
+
+        assert!(rename(&inside, &root.join("secret.inkfinite.json")).is_err());
+        assert!(rename(&outside, &vault.join("notes.inkfinite.json")).is_err());
+        assert!(inside.exists() && outside.exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn folders_cannot_cross_a_vault_boundary() {
+        let root = workspace("folders");
+        let vault = root.join("private");
+        create(&vault, "passphrase").unwrap();
+        fs::create_dir_all(root.join("drafts")).unwrap();
+        assert!(rename(&root.join("drafts"), &vault.join("drafts")).is_err());
+        rename(&root.join("drafts"), &root.join("later")).unwrap();
+        assert!(root.join("later").is_dir());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::document::{self, DOCUMENT_EXTENSION};
+use crate::{read_only, vault};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the per-workspace folder holding app-managed internals
+pub const INTERNAL_DIR: &str = ".inkfinite";
+
+/// Path of the internal folder inside a workspace
+pub fn internal_dir(root: &Path) -> PathBuf {
+    root.join(INTERNAL_DIR)
+}
+
+/// Load `.inkfinite/<name>.json`, falling back to defaults when it does not exist yet
+pub fn read_config<T: DeserializeOwned + Default>(root: &Path, name: &str) -> Result<T, String> {
+    let path = internal_dir(root).join(format!("{}.json", name));
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read config: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid config {}: {}", name, e))
+}
+
+/// Persist `.inkfinite/<name>.json`, creating the internal folder on demand
+pub fn write_config<T: Serialize>(root: &Path, name: &str, value: &T) -> Result<(), String> {
+    read_only::ensure_writable(root)?;
+    let dir = internal_dir(root);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", INTERNAL_DIR, e))?;
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    fs::write(dir.join(format!("{}.json", name)), content)
+        .map_err(|e| format!("Failed to write config: {}", e))
+}
+
+/// Append a timestamped line to `.inkfinite/logs/<name>.log`; read-only workspaces get no logs
+pub fn append_log(root: &Path, name: &str, line: &str) {
+    use std::io::Write;
+
+    if read_only::is_read_only(root) {
+        return;
+    }
+    let dir = internal_dir(root).join("logs");
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    if let Ok(mut file) = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{}.log", name)))
+    {
+        let _ = writeln!(file, "{} {}", chrono::Local::now().to_rfc3339(), line);
+    }
+}
+
+/// Recursively collect board documents under `root`, skipping hidden folders and locked vaults
+pub fn list_documents(root: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut results = Vec::new();
+    // Directories are read in parallel on the shared rayon pool
+    let walk = jwalk::WalkDir::new(root)
+        .skip_hidden(true)
+        .process_read_dir(|_, _, _, children| {
+            for child in children.iter_mut().flatten() {
+                if child.file_type().is_dir() && vault::is_locked(&child.path()) {
+                    child.read_children = None;
+                }
+            }
+        });
+    for entry in walk {
+        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+        if !entry.file_type().is_dir()
+            && entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(DOCUMENT_EXTENSION)
+        {
+            results.push(entry.path());
+        }
+    }
+    results.sort();
+    Ok(results)
+}
+
+/// Path relative to `root` with forward slashes, as stored in documents and config
+pub fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Shape props that hold a workspace-relative asset path
+const ASSET_FIELDS: &[&str] = &["src", "asset"];
+
+/// Point asset props equal to a replaced workspace-relative path at its replacement, returning
+/// the documents changed. Locked vault documents and unreadable ones are left alone.
+pub fn rewrite_references(
+    root: &Path,
+    replacements: &[(String, String)],
+) -> Result<Vec<PathBuf>, String> {
+    let replacements: HashMap<&str, &str> = replacements
+        .iter()
+        .map(|(from, to)| (from.as_str(), to.as_str()))
+        .collect();
+    let mut changed = Vec::new();
+    for path in list_documents(root)? {
+        if vault::is_locked(&path) {
+            continue;
+        }
+        let Ok(mut board) = document::read_board(&path) else {
+            continue;
+        };
+        let mut updated = false;
+        for shape in board.doc.shapes.values_mut() {
+            let Some(Value::Object(props)) = shape.get_mut("props") else {
+                continue;
+            };
+            for field in ASSET_FIELDS {
+                if let Some(Value::String(value)) = props.get_mut(*field) {
+                    if let Some(to) = replacements.get(value.as_str()) {
+                        *value = to.to_string();
+                        updated = true;
+                    }
+                }
+            }
+        }
+        if updated {
+            board.board.updated_at = document::now_millis();
+            document::write_board(&path, &board)?;
+            changed.push(path);
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use document::BoardFile;
+    use serde_json::json;
+
+    #[test]
+    fn only_asset_fields_equal_to_the_old_path_are_rewritten() {
+        let root = std::env::temp_dir().join(format!("inkfinite-relink-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("board.inkfinite.json");
+        let mut board = BoardFile::new("Board");
+        let shapes = [
+            ("a", json!({ "src": "assets/a.png" })),
+            ("b", json!({ "src": "assets/data.png" })),
+            (
+                "c",
+                json!({ "asset": "assets/a.png", "md": "assets/a.png" }),
+            ),
+        ];
+        for (id, props) in shapes {
+            board
+                .doc
+                .shapes
+                .insert(id.to_string(), json!({ "id": id, "props": props }));
+        }
+        document::write_board(&path, &board).unwrap();
+
+        let replacements = [("assets/a.png".to_string(), "assets/a.webp".to_string())];
+        assert_eq!(
+            rewrite_references(&root, &replacements).unwrap(),
+            [path.as_path()]
+        );
+        let shapes = document::read_board(&path).unwrap().doc.shapes;
+        assert_eq!(shapes["a"]["props"]["src"], "assets/a.webp");
+        assert_eq!(shapes["b"]["props"]["src"], "assets/data.png");
+        assert_eq!(shapes["c"]["props"]["asset"], "assets/a.webp");
+        assert_eq!(shapes["c"]["props"]["md"], "assets/a.png");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
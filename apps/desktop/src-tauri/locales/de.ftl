@@ -0,0 +1,60 @@
+## Errors, by error code. `.with-context` is used when the error names a path or value.
+
+error-not-found = Das Element wurde nicht gefunden.
+    .with-context = { $context } wurde nicht gefunden.
+error-permission-denied = Zugriff verweigert.
+    .with-context = Zugriff auf { $context } verweigert.
+error-conflict = Das Element existiert bereits oder wurde an anderer Stelle geändert.
+    .with-context = { $context } existiert bereits oder wurde an anderer Stelle geändert.
+error-invalid-path = Der Pfad ist hier nicht gültig.
+    .with-context = { $context } ist hier kein gültiger Pfad.
+error-invalid-data = Die Eingabe konnte nicht gelesen werden.
+    .with-context = { $context } konnte nicht gelesen werden.
+error-timeout = Der Vorgang hat zu lange gedauert.
+error-cancelled = Der Vorgang wurde abgebrochen.
+error-unavailable = Ein benötigtes Werkzeug oder ein Dienst ist nicht verfügbar.
+    .with-context = { $context } ist nicht verfügbar.
+error-read-only = Dieser Arbeitsbereich ist schreibgeschützt.
+error-locked = Dieser Arbeitsbereich ist gesperrt. Entsperren Sie ihn, um fortzufahren.
+error-internal = Etwas ist schiefgelaufen.
+
+## Notifications
+
+export-failed-title = Export „{ $name }“ fehlgeschlagen
+reminders-due-title =
+    { $count ->
+        [one] Eine Erinnerung ist fällig
+       *[other] { $count } Erinnerungen sind fällig
+    }
+focus-break-title = Zeit für eine Pause
+focus-break-body =
+    { $minutes ->
+        [one] Gönn dir eine Minute vor dem nächsten Fokusintervall.
+       *[other] Gönn dir { $minutes } Minuten vor dem nächsten Fokusintervall.
+    }
+focus-work-title = Die Pause ist vorbei
+focus-work-body =
+    { $minutes ->
+        [one] Das nächste Fokusintervall dauert eine Minute.
+       *[other] Das nächste Fokusintervall dauert { $minutes } Minuten.
+    }
+focus-finished-title = Fokussitzung beendet
+focus-finished-body =
+    { $count ->
+        [one] Du hast ein Fokusintervall abgeschlossen.
+       *[other] Du hast { $count } Fokusintervalle abgeschlossen.
+    }
+
+## Dialogs
+
+read-only-on-title = Arbeitsbereich schreibschützen?
+read-only-on-message = Dokumente und Einstellungen in diesem Arbeitsbereich können erst wieder geändert werden, wenn der Schreibschutz aufgehoben wird.
+read-only-off-title = Änderungen an diesem Arbeitsbereich erlauben?
+read-only-off-message = Dieser Arbeitsbereich war schreibgeschützt. Dokumente und Einstellungen können wieder bearbeitet werden.
+
+## Text written into documents and exports
+
+agenda-heading = Termine
+agenda-all-day = Ganztägig
+agenda-untitled = Unbenannter Termin
+shared-document-title = Geteilt am { $date }
@@ -0,0 +1,60 @@
+## Errors, by error code. `.with-context` is used when the error names a path or value.
+
+error-not-found = The item could not be found.
+    .with-context = { $context } could not be found.
+error-permission-denied = Permission was denied.
+    .with-context = Permission was denied for { $context }.
+error-conflict = The item already exists or was changed elsewhere.
+    .with-context = { $context } already exists or was changed elsewhere.
+error-invalid-path = The path is not valid here.
+    .with-context = { $context } is not a valid path here.
+error-invalid-data = The input could not be read.
+    .with-context = { $context } could not be read.
+error-timeout = The operation timed out.
+error-cancelled = The operation was cancelled.
+error-unavailable = A required tool or service is not available.
+    .with-context = { $context } is not available.
+error-read-only = This workspace is read-only.
+error-locked = This workspace is locked. Unlock it to continue.
+error-internal = Something went wrong.
+
+## Notifications
+
+export-failed-title = Export “{ $name }” failed
+reminders-due-title =
+    { $count ->
+        [one] One reminder is due
+       *[other] { $count } reminders are due
+    }
+focus-break-title = Time for a break
+focus-break-body =
+    { $minutes ->
+        [one] Take a minute before the next focus interval.
+       *[other] Take { $minutes } minutes before the next focus interval.
+    }
+focus-work-title = Break is over
+focus-work-body =
+    { $minutes ->
+        [one] The next focus interval lasts one minute.
+       *[other] The next focus interval lasts { $minutes } minutes.
+    }
+focus-finished-title = Focus session finished
+focus-finished-body =
+    { $count ->
+        [one] You completed one focus interval.
+       *[other] You completed { $count } focus intervals.
+    }
+
+## Dialogs
+
+read-only-on-title = Make workspace read-only?
+read-only-on-message = Documents and settings in this workspace cannot be changed until it is made writable again.
+read-only-off-title = Allow changes to this workspace?
+read-only-off-message = This workspace was marked read-only. Documents and settings will be editable again.
+
+## Text written into documents and exports
+
+agenda-heading = Events
+agenda-all-day = All day
+agenda-untitled = Untitled event
+shared-document-title = Shared { $date }
@@ -0,0 +1,60 @@
+## Errors, by error code. `.with-context` is used when the error names a path or value.
+
+error-not-found = No se encontró el elemento.
+    .with-context = No se encontró { $context }.
+error-permission-denied = Permiso denegado.
+    .with-context = Permiso denegado para { $context }.
+error-conflict = El elemento ya existe o se modificó en otro lugar.
+    .with-context = { $context } ya existe o se modificó en otro lugar.
+error-invalid-path = La ruta no es válida aquí.
+    .with-context = { $context } no es una ruta válida aquí.
+error-invalid-data = No se pudo leer la entrada.
+    .with-context = No se pudo leer { $context }.
+error-timeout = La operación tardó demasiado.
+error-cancelled = Se canceló la operación.
+error-unavailable = Falta una herramienta o un servicio necesario.
+    .with-context = { $context } no está disponible.
+error-read-only = Este espacio de trabajo es de solo lectura.
+error-locked = Este espacio de trabajo está bloqueado. Desbloquéalo para continuar.
+error-internal = Algo salió mal.
+
+## Notifications
+
+export-failed-title = Falló la exportación «{ $name }»
+reminders-due-title =
+    { $count ->
+        [one] Hay un recordatorio pendiente
+       *[other] Hay { $count } recordatorios pendientes
+    }
+focus-break-title = Hora de descansar
+focus-break-body =
+    { $minutes ->
+        [one] Tómate un minuto antes del próximo intervalo de concentración.
+       *[other] Tómate { $minutes } minutos antes del próximo intervalo de concentración.
+    }
+focus-work-title = Se acabó el descanso
+focus-work-body =
+    { $minutes ->
+        [one] El próximo intervalo de concentración dura un minuto.
+       *[other] El próximo intervalo de concentración dura { $minutes } minutos.
+    }
+focus-finished-title = Sesión de concentración terminada
+focus-finished-body =
+    { $count ->
+        [one] Completaste un intervalo de concentración.
+       *[other] Completaste { $count } intervalos de concentración.
+    }
+
+## Dialogs
+
+read-only-on-title = ¿Hacer el espacio de trabajo de solo lectura?
+read-only-on-message = Los documentos y ajustes de este espacio de trabajo no se podrán cambiar hasta que vuelva a ser editable.
+read-only-off-title = ¿Permitir cambios en este espacio de trabajo?
+read-only-off-message = Este espacio de trabajo estaba marcado como de solo lectura. Los documentos y ajustes volverán a ser editables.
+
+## Text written into documents and exports
+
+agenda-heading = Eventos
+agenda-all-day = Todo el día
+agenda-untitled = Evento sin título
+shared-document-title = Compartido { $date }
@@ -0,0 +1,60 @@
+## Errors, by error code. `.with-context` is used when the error names a path or value.
+
+error-not-found = L’élément est introuvable.
+    .with-context = { $context } est introuvable.
+error-permission-denied = Autorisation refusée.
+    .with-context = Autorisation refusée pour { $context }.
+error-conflict = L’élément existe déjà ou a été modifié ailleurs.
+    .with-context = { $context } existe déjà ou a été modifié ailleurs.
+error-invalid-path = Ce chemin n’est pas valide ici.
+    .with-context = { $context } n’est pas un chemin valide ici.
+error-invalid-data = Impossible de lire l’entrée.
+    .with-context = Impossible de lire { $context }.
+error-timeout = L’opération a pris trop de temps.
+error-cancelled = L’opération a été annulée.
+error-unavailable = Un outil ou un service nécessaire est indisponible.
+    .with-context = { $context } est indisponible.
+error-read-only = Cet espace de travail est en lecture seule.
+error-locked = Cet espace de travail est verrouillé. Déverrouillez-le pour continuer.
+error-internal = Une erreur s’est produite.
+
+## Notifications
+
+export-failed-title = Échec de l’export « { $name } »
+reminders-due-title =
+    { $count ->
+        [one] Un rappel est arrivé à échéance
+       *[other] { $count } rappels sont arrivés à échéance
+    }
+focus-break-title = C’est l’heure de la pause
+focus-break-body =
+    { $minutes ->
+        [one] Prenez une minute avant le prochain intervalle de concentration.
+       *[other] Prenez { $minutes } minutes avant le prochain intervalle de concentration.
+    }
+focus-work-title = La pause est terminée
+focus-work-body =
+    { $minutes ->
+        [one] Le prochain intervalle de concentration dure une minute.
+       *[other] Le prochain intervalle de concentration dure { $minutes } minutes.
+    }
+focus-finished-title = Session de concentration terminée
+focus-finished-body =
+    { $count ->
+        [one] Vous avez terminé un intervalle de concentration.
+       *[other] Vous avez terminé { $count } intervalles de concentration.
+    }
+
+## Dialogs
+
+read-only-on-title = Passer l’espace de travail en lecture seule ?
+read-only-on-message = Les documents et réglages de cet espace de travail ne pourront plus être modifiés tant qu’il n’est pas rendu modifiable.
+read-only-off-title = Autoriser les modifications de cet espace de travail ?
+read-only-off-message = Cet espace de travail était en lecture seule. Les documents et réglages seront à nouveau modifiables.
+
+## Text written into documents and exports
+
+agenda-heading = Événements
+agenda-all-day = Toute la journée
+agenda-untitled = Événement sans titre
+shared-document-title = Partagé le { $date }
@@ -0,0 +1,19 @@
+import Foundation
+import QuickLookUI
+import UniformTypeIdentifiers
+
+/// Data-based Quick Look preview that renders boards with the Rust preview renderer
+class PreviewProvider: QLPreviewProvider, QLPreviewingController {
+    func providePreview(for request: QLFilePreviewRequest) async throws -> QLPreviewReply {
+        guard let html = request.fileURL.path.withCString({ inkfinite_preview_html($0) }) else {
+            throw CocoaError(.fileReadCorruptFile)
+        }
+        defer { inkfinite_preview_free(html) }
+        let data = Data(String(cString: html).utf8)
+
+        return QLPreviewReply(dataOfContentType: .html, contentSize: CGSize(width: 800, height: 900)) { reply in
+            reply.stringEncoding = .utf8
+            return data
+        }
+    }
+}
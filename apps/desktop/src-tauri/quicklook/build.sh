@@ -0,0 +1,37 @@
+#!/bin/sh
+# Build the Quick Look extension into quicklook/build/InkfinitePreview.appex.
+# Runs as the macOS beforeBundleCommand, once cargo has produced libdesktop_lib.a.
+set -eu
+cd "$(dirname "$0")"
+
+profile=release
+if [ "${TAURI_ENV_DEBUG:-false}" = "true" ]; then
+  profile=debug
+fi
+lib="../target/${TAURI_ENV_TARGET_TRIPLE:-}/$profile"
+if [ ! -f "$lib/libdesktop_lib.a" ]; then
+  lib="../target/$profile"
+fi
+
+arch="${TAURI_ENV_ARCH:-$(uname -m)}"
+if [ "$arch" = "aarch64" ]; then
+  arch=arm64
+fi
+
+contents=build/InkfinitePreview.appex/Contents
+rm -rf build
+mkdir -p "$contents/MacOS"
+cp Info.plist "$contents/Info.plist"
+
+swiftc -O -parse-as-library -application-extension \
+  -module-name InkfinitePreview \
+  -import-objc-header inkfinite_preview.h \
+  -target "$arch-apple-macos12.0" \
+  -Xlinker -e -Xlinker _NSExtensionMain \
+  -L "$lib" -ldesktop_lib -lc++ \
+  -framework QuickLookUI -framework AppKit -framework WebKit \
+  -framework Security -framework SystemConfiguration -framework CoreServices \
+  PreviewProvider.swift -o "$contents/MacOS/InkfinitePreview"
+
+codesign --force --sign "${APPLE_SIGNING_IDENTITY:--}" \
+  --entitlements InkfinitePreview.entitlements build/InkfinitePreview.appex
@@ -0,0 +1,5 @@
+// Preview renderer exported by the desktop crate (src/preview.rs)
+#pragma once
+
+char *inkfinite_preview_html(const char *path);
+void inkfinite_preview_free(char *html);
@@ -0,0 +1,541 @@
+use crate::error::Error;
+use crate::{document, http, paths, settings, workspace};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+const DATABASE: &str = "analytics.sqlite";
+const SCHEMA_VERSION: i64 = 1;
+/// Only names and counts are kept: no paths, arguments or document content
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS usage (
+    day TEXT NOT NULL,
+    feature TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (day, feature)
+);
+CREATE TABLE IF NOT EXISTS timings (
+    day TEXT NOT NULL,
+    version TEXT NOT NULL,
+    command TEXT NOT NULL,
+    calls INTEGER NOT NULL,
+    errors INTEGER NOT NULL,
+    total_ms REAL NOT NULL,
+    max_ms REAL NOT NULL,
+    slow INTEGER NOT NULL,
+    PRIMARY KEY (day, version, command)
+);
+";
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+/// Days of history kept before older rows are deleted
+const RETENTION_DAYS: i64 = 180;
+const DEFAULT_DAYS: u32 = 30;
+/// Calls slower than this are counted separately
+const SLOW_MS: f64 = 250.0;
+/// Where analytics are uploaded, set by release builds
+const ENDPOINT: Option<&str> = option_env!("INKFINITE_ANALYTICS_ENDPOINT");
+/// Last day already uploaded, so each day is sent once
+const UPLOADED_KEY: &str = "analyticsUploadedThrough";
+
+/// Counts recorded since the last flush
+static PENDING: Mutex<Pending> = Mutex::new(Pending {
+    usage: BTreeMap::new(),
+    timings: BTreeMap::new(),
+});
+
+struct Pending {
+    usage: BTreeMap<String, u64>,
+    timings: BTreeMap<String, Timing>,
+}
+
+#[derive(Default)]
+struct Timing {
+    calls: u64,
+    errors: u64,
+    total_ms: f64,
+    max_ms: f64,
+    slow: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureUsage {
+    pub feature: String,
+    pub count: u64,
+    /// Days on which it was used
+    pub days: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUsage {
+    /// `YYYY-MM-DD`, local time
+    pub day: String,
+    pub count: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandTiming {
+    pub command: String,
+    /// App version the timings were taken with, so releases can be compared
+    pub version: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Calls slower than 250 ms
+    pub slow: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageDashboard {
+    pub enabled: bool,
+    /// Whether uploading is allowed
+    pub sharing: bool,
+    /// First day in the range with any data
+    pub since: Option<String>,
+    /// Most used first
+    pub features: Vec<FeatureUsage>,
+    pub daily: Vec<DailyUsage>,
+    /// Slowest on average first
+    pub commands: Vec<CommandTiming>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageRow {
+    day: String,
+    feature: String,
+    count: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TimingRow {
+    day: String,
+    version: String,
+    command: String,
+    calls: u64,
+    errors: u64,
+    total_ms: f64,
+    max_ms: f64,
+    slow: u64,
+}
+
+/// Everything kept, as exported or uploaded; there is no user or device identifier
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyticsExport {
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    exported_at: i64,
+    usage: Vec<UsageRow>,
+    timings: Vec<TimingRow>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResult {
+    /// Days sent
+    pub days: usize,
+    /// Last day sent; later uploads start after it
+    pub through: Option<String>,
+}
+
+/// Count a use of a feature; commands are counted as they are invoked
+pub fn record_use(feature: &str) {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending.usage.entry(feature.to_string()).or_default() += 1;
+    }
+}
+
+/// Add a command's duration, called when its span closes
+pub fn record_timing(command: &str, micros: u64, failed: bool) {
+    let Ok(mut pending) = PENDING.lock() else {
+        return;
+    };
+    let ms = micros as f64 / 1000.0;
+    let timing = pending.timings.entry(command.to_string()).or_default();
+    timing.calls += 1;
+    timing.errors += u64::from(failed);
+    timing.total_ms += ms;
+    timing.max_ms = timing.max_ms.max(ms);
+    timing.slow += u64::from(ms > SLOW_MS);
+}
+
+fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn days_ago(days: i64) -> String {
+    (chrono::Local::now() - chrono::Duration::days(days))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+fn database_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(DATABASE))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+fn connect(app: &AppHandle) -> Result<Connection, String> {
+    let path = database_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let failed = |e: rusqlite::Error| format!("Failed to open usage analytics: {}", e);
+    let connection = Connection::open(&path).map_err(failed)?;
+    connection
+        .execute_batch("PRAGMA journal_mode = WAL;")
+        .map_err(failed)?;
+    let version: i64 = connection
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(failed)?;
+    if version != SCHEMA_VERSION {
+        connection
+            .execute_batch(&format!(
+                "DROP TABLE IF EXISTS usage; DROP TABLE IF EXISTS timings; {} \
+                 PRAGMA user_version = {};",
+                SCHEMA, SCHEMA_VERSION
+            ))
+            .map_err(failed)?;
+    }
+    Ok(connection)
+}
+
+/// Write what was recorded since the last flush under today's date, or drop it when usage
+/// analytics are off
+pub fn flush(app: &AppHandle) -> Result<(), String> {
+    let pending = {
+        let mut pending = PENDING
+            .lock()
+            .map_err(|e| format!("Failed to read usage analytics: {}", e))?;
+        Pending {
+            usage: std::mem::take(&mut pending.usage),
+            timings: std::mem::take(&mut pending.timings),
+        }
+    };
+    if !settings::current(app, None).usage_analytics
+        || pending.usage.is_empty() && pending.timings.is_empty()
+    {
+        return Ok(());
+    }
+    let mut connection = connect(app)?;
+    let failed = |e: rusqlite::Error| format!("Failed to write usage analytics: {}", e);
+    let transaction = connection.transaction().map_err(failed)?;
+    let day = today();
+    for (feature, count) in &pending.usage {
+        transaction
+            .execute(
+                "INSERT INTO usage (day, feature, count) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (day, feature) DO UPDATE SET count = count + excluded.count",
+                params![day, feature, *count as i64],
+            )
+            .map_err(failed)?;
+    }
+    for (command, timing) in &pending.timings {
+        transaction
+            .execute(
+                "INSERT INTO timings (day, version, command, calls, errors, total_ms, max_ms, slow)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT (day, version, command) DO UPDATE SET
+                     calls = calls + excluded.calls,
+                     errors = errors + excluded.errors,
+                     total_ms = total_ms + excluded.total_ms,
+                     max_ms = max(max_ms, excluded.max_ms),
+                     slow = slow + excluded.slow",
+                params![
+                    day,
+                    env!("CARGO_PKG_VERSION"),
+                    command,
+                    timing.calls as i64,
+                    timing.errors as i64,
+                    timing.total_ms,
+                    timing.max_ms,
+                    timing.slow as i64,
+                ],
+            )
+            .map_err(failed)?;
+    }
+    let cutoff = days_ago(RETENTION_DAYS);
+    transaction
+        .execute("DELETE FROM usage WHERE day < ?1", params![cutoff])
+        .map_err(failed)?;
+    transaction
+        .execute("DELETE FROM timings WHERE day < ?1", params![cutoff])
+        .map_err(failed)?;
+    transaction.commit().map_err(failed)
+}
+
+/// Write recorded counts to the local store once a minute
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(FLUSH_INTERVAL);
+        if let Err(error) = flush(&app) {
+            tracing::warn!(%error, "Failed to save usage analytics");
+        }
+    });
+}
+
+/// Rows for days after `after`, up to and including `through` when given
+fn rows(
+    connection: &Connection,
+    after: &str,
+    through: Option<&str>,
+) -> Result<(Vec<UsageRow>, Vec<TimingRow>), String> {
+    let failed = |e: rusqlite::Error| format!("Failed to read usage analytics: {}", e);
+    let through = through.unwrap_or("9999-12-31");
+    let usage = connection
+        .prepare(
+            "SELECT day, feature, count FROM usage WHERE day > ?1 AND day <= ?2
+             ORDER BY day, feature",
+        )
+        .and_then(|mut statement| {
+            statement
+                .query_map(params![after, through], |row| {
+                    Ok(UsageRow {
+                        day: row.get(0)?,
+                        feature: row.get(1)?,
+                        count: row.get::<_, i64>(2)? as u64,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .map_err(failed)?;
+    let timings = connection
+        .prepare(
+            "SELECT day, version, command, calls, errors, total_ms, max_ms, slow FROM timings
+             WHERE day > ?1 AND day <= ?2 ORDER BY day, command",
+        )
+        .and_then(|mut statement| {
+            statement
+                .query_map(params![after, through], |row| {
+                    Ok(TimingRow {
+                        day: row.get(0)?,
+                        version: row.get(1)?,
+                        command: row.get(2)?,
+                        calls: row.get::<_, i64>(3)? as u64,
+                        errors: row.get::<_, i64>(4)? as u64,
+                        total_ms: row.get(5)?,
+                        max_ms: row.get(6)?,
+                        slow: row.get::<_, i64>(7)? as u64,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .map_err(failed)?;
+    Ok((usage, timings))
+}
+
+fn export(usage: Vec<UsageRow>, timings: Vec<TimingRow>) -> AnalyticsExport {
+    AnalyticsExport {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        exported_at: document::now_millis(),
+        usage,
+        timings,
+    }
+}
+
+fn dashboard(app: &AppHandle, days: u32) -> Result<UsageDashboard, String> {
+    flush(app)?;
+    let current = settings::current(app, None);
+    let connection = connect(app)?;
+    let (usage, timings) = rows(&connection, &days_ago(i64::from(days.max(1))), None)?;
+
+    let mut features: BTreeMap<&str, FeatureUsage> = BTreeMap::new();
+    let mut daily: BTreeMap<&str, u64> = BTreeMap::new();
+    for row in &usage {
+        let feature = features
+            .entry(&row.feature)
+            .or_insert_with(|| FeatureUsage {
+                feature: row.feature.clone(),
+                count: 0,
+                days: 0,
+            });
+        feature.count += row.count;
+        feature.days += 1;
+        *daily.entry(&row.day).or_default() += row.count;
+    }
+    let mut features: Vec<FeatureUsage> = features.into_values().collect();
+    features.sort_by(|a, b| b.count.cmp(&a.count).then(a.feature.cmp(&b.feature)));
+
+    let mut commands: BTreeMap<(&str, &str), Timing> = BTreeMap::new();
+    for row in &timings {
+        let timing = commands.entry((&row.command, &row.version)).or_default();
+        timing.calls += row.calls;
+        timing.errors += row.errors;
+        timing.total_ms += row.total_ms;
+        timing.max_ms = timing.max_ms.max(row.max_ms);
+        timing.slow += row.slow;
+    }
+    let mut commands: Vec<CommandTiming> = commands
+        .into_iter()
+        .map(|((command, version), timing)| CommandTiming {
+            command: command.to_string(),
+            version: version.to_string(),
+            calls: timing.calls,
+            errors: timing.errors,
+            mean_ms: timing.total_ms / timing.calls.max(1) as f64,
+            max_ms: timing.max_ms,
+            slow: timing.slow,
+        })
+        .collect();
+    commands.sort_by(|a, b| b.mean_ms.total_cmp(&a.mean_ms));
+
+    Ok(UsageDashboard {
+        enabled: current.usage_analytics,
+        sharing: current.share_usage_analytics,
+        since: daily.keys().next().map(|day| day.to_string()),
+        daily: daily
+            .into_iter()
+            .map(|(day, count)| DailyUsage {
+                day: day.to_string(),
+                count,
+            })
+            .collect(),
+        features,
+        commands,
+    })
+}
+
+/// Count a use of a frontend feature, such as a tool or a panel. Names are short identifiers
+/// like `tool.pen`; nothing else about the use is kept.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn track_feature(feature: String) -> Result<(), Error> {
+    let valid = !feature.is_empty()
+        && feature.len() <= 64
+        && feature
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-'));
+    if !valid {
+        return Err(format!("Invalid feature name: {}", feature).into());
+    }
+    record_use(&feature);
+    Ok(())
+}
+
+/// Feature use and command timings over the last `days` days, from the local store
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn get_usage_dashboard(
+    app: AppHandle,
+    days: Option<u32>,
+) -> Result<UsageDashboard, Error> {
+    tauri::async_runtime::spawn_blocking(move || Ok(dashboard(&app, days.unwrap_or(DEFAULT_DAYS))?))
+        .await
+        .map_err(|e| format!("Usage analytics task failed: {}", e))?
+}
+
+/// Write everything kept to a JSON file, to look at or share by hand
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn export_usage_analytics(app: AppHandle, destination: String) -> Result<(), Error> {
+    let destination = paths::check(&app, &destination, paths::Scope::Export)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        flush(&app)?;
+        let (usage, timings) = rows(&connect(&app)?, "", None)?;
+        let json = serde_json::to_vec_pretty(&export(usage, timings))
+            .map_err(|e| format!("Failed to encode usage analytics: {}", e))?;
+        std::fs::write(&destination, json)
+            .map_err(|e| format!("Failed to write usage analytics: {}", e))?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Usage analytics task failed: {}", e))?
+}
+
+/// Upload the days not yet sent, up to yesterday. Only runs when asked to, and only once
+/// `shareUsageAnalytics` is turned on.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn upload_usage_analytics(app: AppHandle) -> Result<UploadResult, Error> {
+    let endpoint = ENDPOINT
+        .ok_or_else(|| "Uploading usage analytics is not supported in this build".to_string())?;
+    if !settings::current(&app, None).share_usage_analytics {
+        return Err(
+            "Uploading usage analytics requires shareUsageAnalytics to be turned on".into(),
+        );
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        flush(&app)?;
+        let store = app
+            .store(workspace::STORE_NAME)
+            .map_err(|e| format!("Failed to open settings: {}", e))?;
+        let after = store
+            .get(UPLOADED_KEY)
+            .and_then(|value| value.as_str().map(str::to_string));
+        // Today is still being counted
+        let through = days_ago(1);
+        let (usage, timings) = rows(
+            &connect(&app)?,
+            after.as_deref().unwrap_or_default(),
+            Some(&through),
+        )?;
+        let mut days: Vec<&str> = usage
+            .iter()
+            .map(|row| row.day.as_str())
+            .chain(timings.iter().map(|row| row.day.as_str()))
+            .collect();
+        days.sort();
+        days.dedup();
+        let sent = days.len();
+        let last = days.last().map(|day| day.to_string());
+        let Some(last) = last else {
+            return Ok(UploadResult {
+                days: 0,
+                through: after,
+            });
+        };
+        let body = serde_json::to_string(&export(usage, timings))
+            .map_err(|e| format!("Failed to encode usage analytics: {}", e))?;
+        match http::agent()
+            .post(endpoint)
+            .header("content-type", "application/json")
+            .send(body)
+        {
+            Ok(_) => {}
+            Err(ureq::Error::StatusCode(status)) => {
+                return Err(format!("Analytics server replied with HTTP {}", status).into())
+            }
+            Err(e) => return Err(format!("Failed to upload usage analytics: {}", e).into()),
+        }
+        store.set(UPLOADED_KEY, last.clone());
+        store
+            .save()
+            .map_err(|e| format!("Failed to save settings: {}", e))?;
+        tracing::info!(days = sent, "Usage analytics uploaded");
+        Ok(UploadResult {
+            days: sent,
+            through: Some(last),
+        })
+    })
+    .await
+    .map_err(|e| format!("Usage analytics task failed: {}", e))?
+}
+
+/// Delete everything recorded so far
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn clear_usage_analytics(app: AppHandle) -> Result<(), Error> {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.usage.clear();
+        pending.timings.clear();
+    }
+    connect(&app)?
+        .execute_batch("DELETE FROM usage; DELETE FROM timings;")
+        .map_err(|e| format!("Failed to clear usage analytics: {}", e))?;
+    Ok(())
+}
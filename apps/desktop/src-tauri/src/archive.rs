@@ -0,0 +1,454 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::document::{self, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{blocking, localize, paths, vault, workspace};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::AppHandle;
+
+const CONFIG: &str = "archive";
+/// Where each archived document came from
+const STATE: &str = "archive-state";
+const DEFAULT_FOLDER: &str = "Archive";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ArchiveConfig {
+    /// Workspace-relative folder archived documents are moved into
+    pub folder: String,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            folder: DEFAULT_FOLDER.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedDocument {
+    /// Board id, unchanged by archiving
+    pub id: String,
+    /// Workspace-relative path before archiving
+    pub from: String,
+    /// Workspace-relative path in the archive folder
+    pub to: String,
+    pub archived_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct ArchiveState {
+    documents: Vec<ArchivedDocument>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveResult {
+    pub id: String,
+    /// Where the document is now
+    pub path: String,
+    /// Documents whose links to it were rewritten
+    pub relinked: Vec<String>,
+}
+
+/// The configured archive folder, workspace-relative, without surrounding slashes
+pub fn folder(root: &Path) -> String {
+    let config: ArchiveConfig = workspace::read_config(root, CONFIG).unwrap_or_default();
+    let folder = config.folder.trim().trim_matches('/').to_string();
+    match folder.is_empty() {
+        true => DEFAULT_FOLDER.to_string(),
+        false => folder,
+    }
+}
+
+/// Whether a workspace-relative path is inside the archive folder `folder`
+pub fn is_archived(folder: &str, path: &str) -> bool {
+    path.strip_prefix(folder)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// `path` with `.` and `..` folded away, without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Destination of a relative link, or `None` for URLs, anchors and absolute paths
+fn relative_target(destination: &str) -> Option<&str> {
+    let path = destination.split(['#', '?']).next().unwrap_or_default();
+    let scheme = path
+        .find(':')
+        .is_some_and(|colon| !path[..colon].contains('/'));
+    (!path.is_empty() && !scheme && !path.starts_with('/')).then_some(path)
+}
+
+/// Rewrite the relative Markdown link and image destinations in `text`. `resolve` maps where a
+/// destination points, relative to `from_dir`, to where it should point now; the new
+/// destination is written relative to `to_dir`.
+fn rebase(
+    text: &str,
+    from_dir: &Path,
+    to_dir: &Path,
+    resolve: impl Fn(PathBuf) -> PathBuf,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find("](") {
+        let start = at + 2;
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let bracketed = rest.starts_with('<');
+        let end = match bracketed {
+            true => rest.find('>').map(|end| end + 1),
+            false => rest.find(|c: char| c == ')' || c.is_whitespace()),
+        };
+        let Some(end) = end else {
+            continue;
+        };
+        let destination = match bracketed {
+            true => &rest[1..end - 1],
+            false => &rest[..end],
+        };
+        let Some(path) = relative_target(destination) else {
+            continue;
+        };
+        let suffix = &destination[path.len()..];
+        let target = from_dir.join(path.replace("%20", " "));
+        let moved = resolve(normalize(&target));
+        if moved == normalize(&target) && from_dir == to_dir {
+            continue;
+        }
+        let updated = localize::relative_to(to_dir, &moved);
+        match bracketed {
+            true => out.push_str(&format!("<{}{}>", updated, suffix)),
+            false => out.push_str(&format!("{}{}", updated.replace(' ', "%20"), suffix)),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Rewrite a document's text blocks with `edit`, saving it only when something changed
+fn edit_document(
+    app: &AppHandle,
+    path: &Path,
+    edit: impl Fn(&str) -> String,
+) -> Result<bool, String> {
+    let mut board = document::read_board(path)?;
+    let updates: Vec<(String, String)> = board
+        .pages()
+        .iter()
+        .flat_map(|page| board.page_shapes(page))
+        .filter_map(|shape| {
+            let text = shape.text()?;
+            let updated = edit(text);
+            (updated != text).then(|| (shape.id.clone(), updated))
+        })
+        .collect();
+    if updates.is_empty() {
+        return Ok(false);
+    }
+    for (id, text) in updates {
+        board.set_text(&id, &text);
+    }
+    board.board.updated_at = document::now_millis();
+    document::write_board(path, &board)?;
+    events::file_changed(app, path, ChangeKind::Modified);
+    Ok(true)
+}
+
+/// Point relative links in `text` that resolve against `from_dir` at the same targets from
+/// `to_dir`, for text moving from one document's folder to another's
+pub fn rebase_links(text: &str, from_dir: &Path, to_dir: &Path) -> String {
+    rebase(text, from_dir, to_dir, |target| target)
+}
+
+/// Rewrite relative links to `from` in every document so they point at `to`, returning the
+/// documents changed. Locked vault documents are left alone.
+pub fn redirect_links(
+    app: &AppHandle,
+    root: &Path,
+    from: &Path,
+    to: &Path,
+) -> Result<Vec<String>, String> {
+    redirect_all(
+        app,
+        root,
+        &HashMap::from([(from.to_path_buf(), to.to_path_buf())]),
+    )
+}
+
+/// [`redirect_links`] for several moved files at once, keyed by where they were
+pub fn redirect_all(
+    app: &AppHandle,
+    root: &Path,
+    moves: &HashMap<PathBuf, PathBuf>,
+) -> Result<Vec<String>, String> {
+    let follow = |target: PathBuf| moves.get(&target).cloned().unwrap_or(target);
+    let mut relinked = Vec::new();
+    for path in workspace::list_documents(root)? {
+        if vault::is_locked(&path) {
+            continue;
+        }
+        let dir = path.parent().unwrap_or(root);
+        if edit_document(app, &path, |text| rebase(text, dir, dir, follow)).unwrap_or(false) {
+            relinked.push(workspace::relative_path(root, &path));
+        }
+    }
+    Ok(relinked)
+}
+
+/// Move a document and point links at its new place: its own relative links are rebased, and
+/// other documents' links to it follow it
+fn relocate(app: &AppHandle, root: &Path, from: &Path, to: &Path) -> Result<Vec<String>, String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    vault::rename(from, to)?;
+    events::file_renamed(app, from, to);
+    audit::record(root, AuditAction::Rename, AuditSource::Ui, &[from, to]);
+
+    let follow = |target: PathBuf| match target == from {
+        true => to.to_path_buf(),
+        false => target,
+    };
+    let (from_dir, to_dir) = (from.parent().unwrap_or(root), to.parent().unwrap_or(root));
+    edit_document(app, to, |text| rebase(text, from_dir, to_dir, follow))?;
+    redirect_links(app, root, from, to)
+}
+
+/// The open workspace and `path` within it
+fn locate(app: &AppHandle, path: &str) -> Result<(PathBuf, PathBuf, String), Error> {
+    let resolved = paths::check(app, path, paths::Scope::Write)?;
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let root = root.canonicalize().unwrap_or(root);
+    if !resolved.starts_with(&root) {
+        return Err(Error::invalid_path("Path is outside the workspace").with_context(path));
+    }
+    if !resolved.to_string_lossy().ends_with(DOCUMENT_EXTENSION) {
+        return Err(Error::invalid_path("Only documents can be archived").with_context(path));
+    }
+    if !resolved.exists() {
+        return Err(Error::not_found("Document does not exist").with_context(path));
+    }
+    if vault::vault_of(&resolved).is_some() {
+        return Err("Archiving is not supported for vault documents".into());
+    }
+    let relative = workspace::relative_path(&root, &resolved);
+    Ok((root, resolved, relative))
+}
+
+/// A free path for `path`, numbering the name when something is already there
+fn free_path(path: PathBuf) -> PathBuf {
+    match (path.exists(), path.parent()) {
+        (true, Some(dir)) => {
+            document::unique_path(dir, &document::document_stem(&path), DOCUMENT_EXTENSION)
+        }
+        _ => path,
+    }
+}
+
+#[tauri::command]
+pub fn get_archive_config(app: AppHandle) -> Result<ArchiveConfig, Error> {
+    let root = workspace::current_root(&app).ok_or("No workspace is open")?;
+    Ok(workspace::read_config(&root, CONFIG)?)
+}
+
+#[tauri::command]
+pub fn set_archive_config(app: AppHandle, config: ArchiveConfig) -> Result<(), Error> {
+    let root = workspace::current_root(&app).ok_or("No workspace is open")?;
+    let folder = config.folder.trim().trim_matches('/');
+    if Path::new(folder)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(Error::invalid_path("Invalid archive folder").with_context(folder));
+    }
+    Ok(workspace::write_config(&root, CONFIG, &config)?)
+}
+
+/// Move a document into the archive folder, keeping its place in the folder tree and its id.
+/// Relative links to it are rewritten; `[[wiki links]]` and `inkfinite://open` links resolve
+/// by title and id, so they keep working as they are.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn archive_document(app: AppHandle, path: String) -> Result<ArchiveResult, Error> {
+    let (root, from, relative) = locate(&app, &path)?;
+    let folder = folder(&root);
+    if is_archived(&folder, &relative) {
+        return Err(Error::conflict("Document is already archived").with_context(&path));
+    }
+    blocking::run("archive document", move || {
+        let id = document::read_board(&from)?.board.id;
+        let to = free_path(root.join(&folder).join(&relative));
+        let relinked = relocate(&app, &root, &from, &to)?;
+
+        let archived = workspace::relative_path(&root, &to);
+        let mut state: ArchiveState = workspace::read_config(&root, STATE)?;
+        state
+            .documents
+            .retain(|entry| entry.id != id && entry.to != archived);
+        state.documents.push(ArchivedDocument {
+            id: id.clone(),
+            from: relative,
+            to: archived.clone(),
+            archived_at: document::now_millis(),
+        });
+        workspace::write_config(&root, STATE, &state)?;
+        Ok::<_, Error>(ArchiveResult {
+            id,
+            path: to.to_string_lossy().to_string(),
+            relinked,
+        })
+    })
+    .await
+}
+
+/// Move an archived document back where it was archived from, or, for documents moved into the
+/// archive folder by hand, to the same place outside it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn unarchive_document(app: AppHandle, path: String) -> Result<ArchiveResult, Error> {
+    let (root, from, relative) = locate(&app, &path)?;
+    let folder = folder(&root);
+    let mut state: ArchiveState = workspace::read_config(&root, STATE)?;
+    let entry = state
+        .documents
+        .iter()
+        .position(|entry| entry.to == relative);
+    let original = match entry {
+        Some(index) => state.documents[index].from.clone(),
+        None if is_archived(&folder, &relative) => relative[folder.len() + 1..].to_string(),
+        None => return Err(Error::not_found("Document is not archived").with_context(&path)),
+    };
+    blocking::run("unarchive document", move || {
+        let id = document::read_board(&from)?.board.id;
+        let to = free_path(root.join(&original));
+        let relinked = relocate(&app, &root, &from, &to)?;
+        if let Some(index) = entry {
+            state.documents.remove(index);
+            workspace::write_config(&root, STATE, &state)?;
+        }
+        Ok::<_, Error>(ArchiveResult {
+            id,
+            path: to.to_string_lossy().to_string(),
+            relinked,
+        })
+    })
+    .await
+}
+
+/// Archived documents with where they came from, most recent first. A link to a `from` path
+/// can be redirected to its `to`.
+#[tauri::command]
+pub fn list_archived(app: AppHandle) -> Result<Vec<ArchivedDocument>, Error> {
+    let root = workspace::current_root(&app).ok_or("No workspace is open")?;
+    let state: ArchiveState = workspace::read_config(&root, STATE)?;
+    let mut documents: Vec<ArchivedDocument> = state
+        .documents
+        .into_iter()
+        .filter(|entry| root.join(&entry.to).exists())
+        .collect();
+    documents.sort_by_key(|entry| std::cmp::Reverse(entry.archived_at));
+    Ok(documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_are_rebased_onto_the_new_folder() {
+        assert_eq!(
+            rebase_links(
+                "See [plan](plan.inkfinite.json) and ![img](assets/a.png)",
+                Path::new("/ws/projects"),
+                Path::new("/ws/archive/projects"),
+            ),
+            "See [plan](../../projects/plan.inkfinite.json) and \
+             ![img](../../projects/assets/a.png)"
+        );
+    }
+
+    #[test]
+    fn anchors_and_queries_are_kept() {
+        assert_eq!(
+            rebase_links(
+                "[a](notes.inkfinite.json#intro) [b](notes.inkfinite.json?page=2)",
+                Path::new("/ws/a"),
+                Path::new("/ws/b"),
+            ),
+            "[a](../a/notes.inkfinite.json#intro) [b](../a/notes.inkfinite.json?page=2)"
+        );
+    }
+
+    #[test]
+    fn encoded_spaces_are_decoded_and_reencoded() {
+        assert_eq!(
+            rebase_links(
+                "[a](my%20notes.inkfinite.json)",
+                Path::new("/ws/a"),
+                Path::new("/ws/b"),
+            ),
+            "[a](../a/my%20notes.inkfinite.json)"
+        );
+    }
+
+    #[test]
+    fn bracketed_destinations_keep_their_spaces() {
+        assert_eq!(
+            rebase_links(
+                "[a](<my notes.inkfinite.json#top>)",
+                Path::new("/ws/a"),
+                Path::new("/ws/b"),
+            ),
+            "[a](<../a/my notes.inkfinite.json#top>)"
+        );
+    }
+
+    #[test]
+    fn urls_anchors_and_absolute_paths_are_left_alone() {
+        let text = "[u](https://example.com/a) [h](#top) [p](/abs/x.png) [m](mailto:a@b.c)";
+        assert_eq!(
+            rebase_links(text, Path::new("/ws/a"), Path::new("/ws/b")),
+            text
+        );
+    }
+
+    #[test]
+    fn redirecting_rewrites_only_links_to_the_moved_target() {
+        let dir = Path::new("/ws/a");
+        let from = Path::new("/ws/a/old.inkfinite.json");
+        let to = Path::new("/ws/b/new.inkfinite.json");
+        let follow = |target: PathBuf| match target == from {
+            true => to.to_path_buf(),
+            false => target,
+        };
+        assert_eq!(
+            rebase(
+                "[x](old.inkfinite.json) [y](other.inkfinite.json)",
+                dir,
+                dir,
+                follow
+            ),
+            "[x](../b/new.inkfinite.json) [y](other.inkfinite.json)"
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::assets::ASSETS_DIR;
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::conditions;
+use crate::document::{self, now_millis};
+use crate::error::Error;
+use crate::paths;
+use crate::read_only;
+use crate::vault;
+use crate::workspace;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+
+const CONFIG: &str = "asset-gc";
+const LOG: &str = "asset-gc";
+pub const TRASH_DIR: &str = "trash";
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+/// Automatic collection runs at most this often
+const TICK: Duration = Duration::from_secs(6 * 60 * 60);
+/// Wait before checking again after system conditions deferred a run
+const RETRY: Duration = Duration::from_secs(15 * 60);
+
+/// GC policy and orphan marks, stored in `.inkfinite/asset-gc.json`
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GcPolicy {
+    /// Run collection automatically in the background
+    pub enabled: bool,
+    /// Days an unreferenced asset is kept before it moves to the trash
+    pub grace_period_days: u32,
+    /// Workspace-relative asset path -> time it was first seen unreferenced
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    marked: BTreeMap<String, i64>,
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        GcPolicy {
+            enabled: false,
+            grace_period_days: 30,
+            marked: BTreeMap::new(),
+        }
+    }
+}
+
+impl GcPolicy {
+    fn expires_at(&self, marked_at: i64) -> i64 {
+        marked_at + self.grace_period_days as i64 * DAY_MILLIS
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanAsset {
+    pub path: String,
+    pub size: u64,
+    pub marked_at: i64,
+    pub expires_at: i64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcStatus {
+    pub enabled: bool,
+    pub grace_period_days: u32,
+    pub orphans: Vec<OrphanAsset>,
+    pub orphan_bytes: u64,
+}
+
+#[derive(serde::Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    /// Assets moved (or, in a dry run, that would move) to the trash
+    pub trashed: Vec<String>,
+    /// Orphans still inside their grace period
+    pub pending: usize,
+    /// Previously marked assets that are referenced again
+    pub restored: usize,
+    pub bytes_freed: u64,
+    /// Trash folder for this run, if anything was moved
+    pub trash_dir: Option<String>,
+    pub dry_run: bool,
+    /// Nothing was collected because a locked vault may use any of the assets
+    pub skipped_locked: bool,
+}
+
+/// Report unreferenced assets with their expiry. Marks are only saved by a collection run, so
+/// assets found here for the first time count from now.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn gc_status(app: AppHandle, workspace: String) -> Result<GcStatus, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let root = Path::new(&workspace);
+    let mut policy: GcPolicy = workspace::read_config(root, CONFIG)?;
+    refresh_marks(root, &mut policy, now_millis())?;
+
+    let orphans: Vec<OrphanAsset> = policy
+        .marked
+        .iter()
+        .map(|(path, &marked_at)| OrphanAsset {
+            size: fs::metadata(root.join(path)).map(|m| m.len()).unwrap_or(0),
+            path: path.clone(),
+            marked_at,
+            expires_at: policy.expires_at(marked_at),
+        })
+        .collect();
+
+    Ok(GcStatus {
+        enabled: policy.enabled,
+        grace_period_days: policy.grace_period_days,
+        orphan_bytes: orphans.iter().map(|o| o.size).sum(),
+        orphans,
+    })
+}
+
+/// Change the automatic collection policy for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_gc_policy(
+    app: AppHandle,
+    workspace: String,
+    enabled: bool,
+    grace_period_days: u32,
+) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let root = Path::new(&workspace);
+    let mut policy: GcPolicy = workspace::read_config(root, CONFIG)?;
+    policy.enabled = enabled;
+    policy.grace_period_days = grace_period_days;
+    Ok(workspace::write_config(root, CONFIG, &policy)?)
+}
+
+/// Move orphaned assets whose grace period has passed into `.inkfinite/trash`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn run_asset_gc(
+    app: AppHandle,
+    workspace: String,
+    dry_run: Option<bool>,
+) -> Result<GcReport, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(collect(
+        Path::new(&workspace),
+        dry_run.unwrap_or(false),
+        AuditSource::Ui,
+    )?)
+}
+
+/// Collect the current workspace in the background according to its policy
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let mut wait = TICK;
+        if let Some(root) = workspace::current_root(&app) {
+            let enabled = workspace::read_config::<GcPolicy>(&root, CONFIG)
+                .map(|policy| policy.enabled)
+                .unwrap_or(false);
+            if enabled && conditions::defer_reason(&root, false).is_some() {
+                wait = RETRY;
+            } else if enabled {
+                match collect(&root, false, AuditSource::Batch) {
+                    Ok(report) if !report.trashed.is_empty() => workspace::append_log(
+                        &root,
+                        LOG,
+                        &format!(
+                            "moved {} assets ({} bytes) to trash",
+                            report.trashed.len(),
+                            report.bytes_freed
+                        ),
+                    ),
+                    Ok(_) => {}
+                    Err(error) => workspace::append_log(&root, LOG, &format!("failed: {}", error)),
+                }
+            }
+        }
+        std::thread::sleep(wait);
+    });
+}
+
+fn collect(root: &Path, dry_run: bool, origin: AuditSource) -> Result<GcReport, String> {
+    if !dry_run {
+        read_only::ensure_writable(root)?;
+    }
+    let mut policy: GcPolicy = workspace::read_config(root, CONFIG)?;
+    if vault::any_locked(root) {
+        return Ok(GcReport {
+            pending: policy.marked.len(),
+            dry_run,
+            skipped_locked: true,
+            ..Default::default()
+        });
+    }
+    let now = now_millis();
+    let mut report = GcReport {
+        restored: refresh_marks(root, &mut policy, now)?,
+        dry_run,
+        ..Default::default()
+    };
+
+    let expired: Vec<String> = policy
+        .marked
+        .iter()
+        .filter(|(_, &marked_at)| policy.expires_at(marked_at) <= now)
+        .map(|(path, _)| path.clone())
+        .collect();
+    report.pending = policy.marked.len() - expired.len();
+
+    let trash = workspace::internal_dir(root)
+        .join(TRASH_DIR)
+        .join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+
+    for path in expired {
+        let source = root.join(&path);
+        report.bytes_freed += fs::metadata(&source).map(|m| m.len()).unwrap_or(0);
+
+        if !dry_run {
+            let target = trash.join(&path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create trash: {}", e))?;
+            }
+            fs::rename(&source, &target)
+                .map_err(|e| format!("Failed to move {} to trash: {}", path, e))?;
+            audit::record(root, AuditAction::Trash, origin, &[&source]);
+            policy.marked.remove(&path);
+            report.trash_dir = Some(trash.to_string_lossy().to_string());
+        }
+        report.trashed.push(path);
+    }
+
+    if !dry_run {
+        workspace::write_config(root, CONFIG, &policy)?;
+    }
+    Ok(report)
+}
+
+/// Mark newly unreferenced assets and drop marks for assets in use again; returns the restored count.
+/// Marks are left alone while a vault is locked, since its documents cannot be read.
+fn refresh_marks(root: &Path, policy: &mut GcPolicy, now: i64) -> Result<usize, String> {
+    if vault::any_locked(root) {
+        return Ok(0);
+    }
+    let orphans = find_orphans(root)?;
+
+    let mut restored = 0;
+    policy.marked.retain(|path, _| {
+        let keep = orphans.contains(path);
+        // Marks for files deleted by hand simply disappear
+        if !keep && root.join(path.as_str()).exists() {
+            restored += 1;
+        }
+        keep
+    });
+
+    for path in orphans {
+        policy.marked.entry(path).or_insert(now);
+    }
+    Ok(restored)
+}
+
+/// Assets whose file name does not appear in any document
+fn find_orphans(root: &Path) -> Result<Vec<String>, String> {
+    let assets_dir = root.join(ASSETS_DIR);
+    if !assets_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    // Read through the board parser so documents in unlocked vaults are decrypted
+    let documents: Vec<String> = workspace::list_documents(root)?
+        .iter()
+        .filter_map(|path| document::read_board(path).ok())
+        .filter_map(|board| serde_json::to_string(&board).ok())
+        .collect();
+
+    let mut files = Vec::new();
+    collect_files(&assets_dir, &mut files)?;
+
+    Ok(files
+        .into_iter()
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            // Matching on the file name alone errs on the side of keeping assets
+            let encoded = name.replace(' ', "%20");
+            !documents
+                .iter()
+                .any(|doc| doc.contains(&name) || doc.contains(&encoded))
+        })
+        .map(|path| workspace::relative_path(root, &path))
+        .collect())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
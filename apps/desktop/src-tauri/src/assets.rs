@@ -0,0 +1,184 @@
+use crate::error::Error;
+use crate::{metadata, optimize, paths, read_only, svg, workspace};
+use image::{DynamicImage, ImageFormat};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Default folder for imported assets inside a workspace
+pub const ASSETS_DIR: &str = "assets";
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedAsset {
+    /// Absolute path of the written file
+    pub path: String,
+    /// Path to insert into documents, relative to the workspace root when inside one
+    pub relative_path: String,
+}
+
+/// Encodings supported when writing raster assets
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RasterFormat {
+    #[default]
+    Png,
+    Webp,
+    Avif,
+}
+
+impl RasterFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            RasterFormat::Png => "png",
+            RasterFormat::Webp => "webp",
+            RasterFormat::Avif => "avif",
+        }
+    }
+
+    pub fn image_format(self) -> ImageFormat {
+        match self {
+            RasterFormat::Png => ImageFormat::Png,
+            RasterFormat::Webp => ImageFormat::WebP,
+            RasterFormat::Avif => ImageFormat::Avif,
+        }
+    }
+}
+
+/// Encode an image into `dir` under a timestamped name such as `pasted-20250101-120000.png`
+pub fn save_image(
+    image: &DynamicImage,
+    dir: &Path,
+    prefix: &str,
+    format: RasterFormat,
+) -> Result<PathBuf, String> {
+    let path = timestamped_path(dir, prefix, format.extension())?;
+
+    // The WebP and AVIF encoders in the image crate only accept 8-bit RGB(A)
+    let image = match format {
+        RasterFormat::Webp | RasterFormat::Avif => DynamicImage::ImageRgba8(image.to_rgba8()),
+        RasterFormat::Png => image.clone(),
+    };
+    image
+        .save_with_format(&path, format.image_format())
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+
+    Ok(path)
+}
+
+/// Unused path in `dir` such as `pasted-20250101-120000.png`, creating `dir` if needed
+pub fn timestamped_path(dir: &Path, prefix: &str, extension: &str) -> Result<PathBuf, String> {
+    read_only::ensure_writable(dir)?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let stem = format!(
+        "{}-{}",
+        prefix,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    Ok(crate::document::unique_path(
+        dir,
+        &stem,
+        &format!(".{}", extension),
+    ))
+}
+
+/// Copy a file into an assets folder, sanitizing SVGs and optimizing raster images per the workspace's settings
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn import_asset(
+    app: AppHandle,
+    source: String,
+    destination_dir: Option<String>,
+    strip_metadata: Option<bool>,
+) -> Result<SavedAsset, Error> {
+    paths::check(&app, &source, paths::Scope::Read)?;
+    let source = Path::new(&source);
+    if !source.is_file() {
+        return Err(format!("File does not exist: {}", source.display()).into());
+    }
+    let destination = match destination_dir {
+        Some(dir) => paths::check(&app, &dir, paths::Scope::Write).map(|_| PathBuf::from(dir))?,
+        None => default_dir(&app)?,
+    };
+    read_only::ensure_writable(&destination)?;
+    fs::create_dir_all(&destination).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "asset".to_string());
+    let extension = source
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy().to_lowercase()))
+        .unwrap_or_default();
+
+    let bytes = fs::read(source).map_err(|e| format!("Failed to read file: {}", e))?;
+    let root = workspace::current_root(&app);
+    let settings = root
+        .as_deref()
+        .map(optimize::load_settings)
+        .unwrap_or_default();
+    let strip_metadata = strip_metadata.unwrap_or_else(|| {
+        root.as_deref()
+            .is_some_and(|root| metadata::load_privacy(root).strip_image_metadata)
+    });
+
+    let bytes = if svg::is_svg(source) {
+        let content = String::from_utf8(bytes).map_err(|_| "SVG is not valid UTF-8".to_string())?;
+        svg::sanitize(&content)?.0.into_bytes()
+    } else {
+        bytes
+    };
+
+    let optimized = if settings.optimize && optimize::is_raster(source) {
+        optimize::optimize(&bytes, &settings)?
+    } else {
+        None
+    };
+
+    let path = match optimized {
+        Some(optimized) => {
+            let path = crate::document::unique_path(
+                &destination,
+                &stem,
+                &format!(".{}", optimized.extension),
+            );
+            fs::write(&path, &optimized.bytes)
+                .map_err(|e| format!("Failed to write asset: {}", e))?;
+            path
+        }
+        None => {
+            // Re-encoded images have no metadata left; untouched ones are stripped losslessly
+            let bytes = if strip_metadata && optimize::is_raster(source) {
+                metadata::strip(&bytes).ok().flatten().unwrap_or(bytes)
+            } else {
+                bytes
+            };
+            let path = crate::document::unique_path(&destination, &stem, &extension);
+            fs::write(&path, &bytes).map_err(|e| format!("Failed to write asset: {}", e))?;
+            path
+        }
+    };
+
+    Ok(describe(&app, &path))
+}
+
+/// Assets folder of the current workspace, used when a command is not given a destination
+pub fn default_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    workspace::current_root(app)
+        .map(|root| root.join(ASSETS_DIR))
+        .ok_or_else(|| "No workspace is open".to_string())
+}
+
+/// Describe a saved asset relative to the current workspace (or its own folder's parent)
+pub fn describe(app: &AppHandle, path: &Path) -> SavedAsset {
+    let base = workspace::current_root(app)
+        .filter(|root| path.starts_with(root))
+        .or_else(|| path.parent().and_then(Path::parent).map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    SavedAsset {
+        path: path.to_string_lossy().to_string(),
+        relative_path: workspace::relative_path(&base, path),
+    }
+}
@@ -0,0 +1,219 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::error::Error;
+use crate::paths;
+use crate::read_only;
+use crate::workspace;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Workspace folder holding attachments, one file per unique content hash
+pub const ATTACHMENTS_DIR: &str = "attachments";
+const INDEX_CONFIG: &str = "attachments";
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct AttachmentIndex {
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct IndexEntry {
+    /// Original file name of the first upload
+    name: String,
+    /// Lowercase extension without dot, empty when the source had none
+    extension: String,
+    size: u64,
+    added_at: i64,
+    /// Workspace-relative paths of documents referencing this attachment
+    documents: BTreeSet<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub hash: String,
+    pub name: String,
+    pub size: u64,
+    /// Absolute path of the stored file
+    pub path: String,
+    /// Path to insert into documents, relative to the workspace root
+    pub relative_path: String,
+    /// Number of documents referencing this attachment
+    pub ref_count: usize,
+}
+
+impl IndexEntry {
+    fn file_name(&self, hash: &str) -> String {
+        if self.extension.is_empty() {
+            hash.to_string()
+        } else {
+            format!("{}.{}", hash, self.extension)
+        }
+    }
+
+    fn to_attachment(&self, root: &Path, hash: &str) -> Attachment {
+        let path = stored_path(root, hash, self);
+        Attachment {
+            hash: hash.to_string(),
+            name: self.name.clone(),
+            size: self.size,
+            relative_path: workspace::relative_path(root, &path),
+            path: path.to_string_lossy().to_string(),
+            ref_count: self.documents.len(),
+        }
+    }
+}
+
+/// Store a file in the workspace and record `document` as referencing it.
+///
+/// Identical content attached elsewhere is reused rather than copied again.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn attach_file(
+    app: AppHandle,
+    workspace: String,
+    document: String,
+    source: String,
+) -> Result<Attachment, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    paths::check(&app, &source, paths::Scope::Read)?;
+    let root = Path::new(&workspace);
+    read_only::ensure_writable(root)?;
+    let source = Path::new(&source);
+    if !source.is_file() {
+        return Err(format!("File does not exist: {}", source.display()).into());
+    }
+
+    let hash = hash_file(source)?;
+    let document = document_key(root, &document);
+    let mut index: AttachmentIndex = workspace::read_config(root, INDEX_CONFIG)?;
+
+    if !index.entries.contains_key(&hash) {
+        let size = fs::metadata(source)
+            .map_err(|e| format!("Failed to read metadata: {}", e))?
+            .len();
+        let entry = IndexEntry {
+            name: source
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| hash.clone()),
+            extension: source
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+            size,
+            added_at: chrono::Utc::now().timestamp_millis(),
+            documents: BTreeSet::new(),
+        };
+        index.entries.insert(hash.clone(), entry);
+    }
+    let entry = index
+        .entries
+        .get_mut(&hash)
+        .ok_or_else(|| "Attachment index is inconsistent".to_string())?;
+
+    let target = stored_path(root, &hash, entry);
+    if !target.is_file() {
+        let dir = root.join(ATTACHMENTS_DIR);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+        fs::copy(source, &target).map_err(|e| format!("Failed to store attachment: {}", e))?;
+    }
+
+    entry.documents.insert(document);
+    let attachment = entry.to_attachment(root, &hash);
+    workspace::write_config(root, INDEX_CONFIG, &index)?;
+
+    Ok(attachment)
+}
+
+/// List attachments referenced by a document
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_attachments(
+    app: AppHandle,
+    workspace: String,
+    document: String,
+) -> Result<Vec<Attachment>, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let root = Path::new(&workspace);
+    let document = document_key(root, &document);
+    let index: AttachmentIndex = workspace::read_config(root, INDEX_CONFIG)?;
+
+    Ok(index
+        .entries
+        .iter()
+        .filter(|(_, entry)| entry.documents.contains(&document))
+        .map(|(hash, entry)| entry.to_attachment(root, hash))
+        .collect())
+}
+
+/// Drop a document's reference to an attachment, deleting the file once nothing references it.
+///
+/// Returns `true` when the stored file was removed.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn detach(
+    app: AppHandle,
+    workspace: String,
+    document: String,
+    hash: String,
+) -> Result<bool, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let root = Path::new(&workspace);
+    read_only::ensure_writable(root)?;
+    let document = document_key(root, &document);
+    let mut index: AttachmentIndex = workspace::read_config(root, INDEX_CONFIG)?;
+
+    let Some(entry) = index.entries.get_mut(&hash) else {
+        return Err(format!("Unknown attachment: {}", hash).into());
+    };
+    entry.documents.remove(&document);
+
+    let removed = if entry.documents.is_empty() {
+        let path = stored_path(root, &hash, entry);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete attachment: {}", e))?;
+            audit::record(root, AuditAction::Delete, AuditSource::Ui, &[&path]);
+        }
+        index.entries.remove(&hash);
+        true
+    } else {
+        false
+    };
+
+    workspace::write_config(root, INDEX_CONFIG, &index)?;
+    Ok(removed)
+}
+
+fn stored_path(root: &Path, hash: &str, entry: &IndexEntry) -> PathBuf {
+    root.join(ATTACHMENTS_DIR).join(entry.file_name(hash))
+}
+
+/// Documents are tracked by workspace-relative path so the index survives moving the workspace
+fn document_key(root: &Path, document: &str) -> String {
+    workspace::relative_path(root, Path::new(document))
+}
+
+fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
@@ -0,0 +1,276 @@
+use crate::assets::{self, SavedAsset};
+use crate::error::Error;
+use crate::paths;
+use crate::tools;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use tauri::{AppHandle, State};
+
+type WavWriter = hound::WavWriter<std::io::BufWriter<fs::File>>;
+type SharedWriter = Arc<Mutex<Option<WavWriter>>>;
+
+/// Output encodings for voice memos
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Opus,
+    M4a,
+    Wav,
+}
+
+impl AudioFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Opus => "opus",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Wav => "wav",
+        }
+    }
+
+    fn ffmpeg_codec(self) -> Option<&'static [&'static str]> {
+        match self {
+            AudioFormat::Opus => Some(&["-c:a", "libopus", "-b:a", "48k"]),
+            AudioFormat::M4a => Some(&["-c:a", "aac", "-b:a", "96k"]),
+            AudioFormat::Wav => None,
+        }
+    }
+}
+
+/// Recording in progress, owned by the managed [`AudioRecorder`] state
+struct ActiveRecording {
+    stop: mpsc::Sender<()>,
+    worker: JoinHandle<Result<(), String>>,
+    wav_path: PathBuf,
+    format: AudioFormat,
+    started: Instant,
+}
+
+/// Managed state holding at most one recording at a time
+#[derive(Default)]
+pub struct AudioRecorder(Mutex<Option<ActiveRecording>>);
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioMemo {
+    #[serde(flatten)]
+    pub asset: SavedAsset,
+    pub duration_ms: u64,
+    /// Encoding actually written; falls back to `wav` when ffmpeg is unavailable
+    pub format: AudioFormat,
+}
+
+/// Start capturing the default microphone into the assets folder (the workspace's by default)
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn start_audio_recording(
+    app: AppHandle,
+    recorder: State<'_, AudioRecorder>,
+    format: Option<AudioFormat>,
+    destination_dir: Option<String>,
+) -> Result<(), Error> {
+    let mut active = recorder
+        .0
+        .lock()
+        .map_err(|_| "Recorder state is poisoned".to_string())?;
+    if active.is_some() {
+        return Err("A recording is already in progress".into());
+    }
+
+    let destination = match destination_dir {
+        Some(dir) => paths::check(&app, &dir, paths::Scope::Write).map(|_| PathBuf::from(dir))?,
+        None => assets::default_dir(&app)?,
+    };
+    let wav_path = assets::timestamped_path(&destination, "memo", "wav")?;
+
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let worker_path = wav_path.clone();
+    // cpal streams are not `Send`, so the stream lives and dies on its own thread
+    let worker = std::thread::spawn(move || record(&worker_path, ready_tx, stop_rx));
+
+    match ready_rx.recv() {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => {
+            let _ = worker.join();
+            let _ = fs::remove_file(&wav_path);
+            return Err(error.into());
+        }
+        Err(_) => {
+            let _ = fs::remove_file(&wav_path);
+            return Err("Recording thread exited unexpectedly".into());
+        }
+    }
+
+    *active = Some(ActiveRecording {
+        stop: stop_tx,
+        worker,
+        wav_path,
+        format: format.unwrap_or_default(),
+        started: Instant::now(),
+    });
+    Ok(())
+}
+
+/// Stop the current recording, encode it, and return the saved asset
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn stop_audio_recording(
+    app: AppHandle,
+    recorder: State<'_, AudioRecorder>,
+) -> Result<AudioMemo, Error> {
+    let recording = recorder
+        .0
+        .lock()
+        .map_err(|_| "Recorder state is poisoned".to_string())?
+        .take()
+        .ok_or_else(|| "No recording is in progress".to_string())?;
+
+    let duration_ms = recording.started.elapsed().as_millis() as u64;
+    let _ = recording.stop.send(());
+    recording
+        .worker
+        .join()
+        .map_err(|_| "Recording thread panicked".to_string())??;
+
+    let (path, format) = encode(&app, &recording.wav_path, recording.format)?;
+
+    Ok(AudioMemo {
+        asset: assets::describe(&app, &path),
+        duration_ms,
+        format,
+    })
+}
+
+/// Capture until `stop` fires, writing 16-bit PCM into a WAV file
+fn record(
+    path: &Path,
+    ready: mpsc::Sender<Result<(), String>>,
+    stop: mpsc::Receiver<()>,
+) -> Result<(), String> {
+    let started = open_stream(path);
+
+    let (stream, writer) = match started {
+        Ok(started) => {
+            let _ = ready.send(Ok(()));
+            started
+        }
+        Err(error) => {
+            let _ = ready.send(Err(error.clone()));
+            return Err(error);
+        }
+    };
+
+    // Either an explicit stop or the app dropping the sender ends the recording
+    let _ = stop.recv();
+    drop(stream);
+
+    let writer = writer
+        .lock()
+        .map_err(|_| "Recording buffer is poisoned".to_string())?
+        .take();
+    if let Some(writer) = writer {
+        writer
+            .finalize()
+            .map_err(|e| format!("Failed to finish recording: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Open the default microphone and start streaming samples into a new WAV file
+fn open_stream(path: &Path) -> Result<(cpal::Stream, SharedWriter), String> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| "No microphone is available".to_string())?;
+    let config = device
+        .default_input_config()
+        .map_err(|e| format!("Failed to read microphone config: {}", e))?;
+
+    let spec = hound::WavSpec {
+        channels: config.channels(),
+        sample_rate: config.sample_rate().0,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let writer = hound::WavWriter::create(path, spec)
+        .map_err(|e| format!("Failed to create recording: {}", e))?;
+    let writer = Arc::new(Mutex::new(Some(writer)));
+
+    let stream_config = config.config();
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, writer.clone()),
+        SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, writer.clone()),
+        SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, writer.clone()),
+        SampleFormat::I32 => build_stream::<i32>(&device, &stream_config, writer.clone()),
+        other => Err(format!("Unsupported microphone sample format: {}", other)),
+    }?;
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start recording: {}", e))?;
+    Ok((stream, writer))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    writer: SharedWriter,
+) -> Result<cpal::Stream, String>
+where
+    T: SizedSample,
+    i16: FromSample<T>,
+{
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                if let Ok(mut guard) = writer.lock() {
+                    if let Some(writer) = guard.as_mut() {
+                        for &sample in data {
+                            let _ = writer.write_sample(sample.to_sample::<i16>());
+                        }
+                    }
+                }
+            },
+            |error| tracing::warn!(%error, "Audio input error"),
+            None,
+        )
+        .map_err(|e| format!("Failed to open microphone: {}", e))
+}
+
+/// Transcode the captured WAV with ffmpeg, keeping the WAV when it is unavailable or fails
+fn encode(
+    app: &AppHandle,
+    wav_path: &Path,
+    format: AudioFormat,
+) -> Result<(PathBuf, AudioFormat), String> {
+    let Some(codec) = format.ffmpeg_codec() else {
+        return Ok((wav_path.to_path_buf(), AudioFormat::Wav));
+    };
+    let Some(ffmpeg) = tools::find_binary(app, "ffmpeg", "-version") else {
+        return Ok((wav_path.to_path_buf(), AudioFormat::Wav));
+    };
+
+    let target = wav_path.with_extension(format.extension());
+    let status = Command::new(ffmpeg)
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(wav_path)
+        .args(codec)
+        .arg(&target)
+        .status()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if status.success() {
+        let _ = fs::remove_file(wav_path);
+        Ok((target, format))
+    } else {
+        let _ = fs::remove_file(&target);
+        Ok((wav_path.to_path_buf(), AudioFormat::Wav))
+    }
+}
@@ -0,0 +1,154 @@
+use crate::document::now_millis;
+use crate::error::Error;
+use crate::{paths, read_only, workspace};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use tauri::AppHandle;
+
+/// `.inkfinite/logs/audit.jsonl`, one entry per line; lines are only ever appended
+const LOG_FILE: &str = "audit.jsonl";
+const DEFAULT_LIMIT: usize = 200;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditAction {
+    Rename,
+    Delete,
+    /// Content replaced in place, such as stripped metadata or a re-encoded image
+    Overwrite,
+    /// Moved into `.inkfinite/trash`
+    Trash,
+    Restore,
+    /// Tags renamed or merged across documents
+    Retag,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditSource {
+    /// A command the user ran
+    Ui,
+    Sync,
+    /// A background or batch job
+    Batch,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Unix milliseconds
+    pub at: i64,
+    pub action: AuditAction,
+    pub source: AuditSource,
+    /// Workspace-relative paths; a rename lists the old path, then the new one
+    pub paths: Vec<String>,
+    /// What changed, for actions the paths alone do not describe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Append an entry to the audit log of the workspace at `root`.
+///
+/// Failures are logged rather than returned so they never undo the operation being recorded.
+pub fn record(root: &Path, action: AuditAction, source: AuditSource, paths: &[&Path]) {
+    record_detail(root, action, source, paths, None);
+}
+
+/// [`record`] with a description of what changed
+pub fn record_detail(
+    root: &Path,
+    action: AuditAction,
+    source: AuditSource,
+    paths: &[&Path],
+    detail: Option<String>,
+) {
+    // Callers may hold resolved paths, which only share the resolved root's prefix
+    let resolved = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let entry = AuditEntry {
+        at: now_millis(),
+        action,
+        source,
+        paths: paths
+            .iter()
+            .map(|path| {
+                let base = if path.starts_with(root) {
+                    root
+                } else {
+                    &resolved
+                };
+                workspace::relative_path(base, path)
+            })
+            .collect(),
+        detail,
+    };
+    if let Err(error) = append(root, &entry) {
+        tracing::warn!(%error, "Failed to write audit log");
+    }
+}
+
+/// [`record`] for the open workspace; nothing is recorded when none is open
+pub fn record_current(app: &AppHandle, action: AuditAction, source: AuditSource, paths: &[&Path]) {
+    if let Some(root) = workspace::current_root(app) {
+        record(&root, action, source, paths);
+    }
+}
+
+fn append(root: &Path, entry: &AuditEntry) -> Result<(), String> {
+    read_only::ensure_writable(root)?;
+    let dir = workspace::internal_dir(root).join("logs");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log folder: {}", e))?;
+    let line =
+        serde_json::to_string(entry).map_err(|e| format!("Failed to serialize entry: {}", e))?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(LOG_FILE))
+        .map_err(|e| format!("Failed to open audit log: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write audit log: {}", e))
+}
+
+/// Audit log entries, newest first.
+///
+/// `path` matches entries touching that file or anything under that folder, `since` drops
+/// entries older than the given Unix milliseconds, and `limit` defaults to 200.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn query_audit_log(
+    app: AppHandle,
+    workspace: String,
+    path: Option<String>,
+    action: Option<AuditAction>,
+    since: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<AuditEntry>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let file = match fs::File::open(workspace::internal_dir(&root).join("logs").join(LOG_FILE)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::io("Failed to read audit log", e)),
+    };
+    let path = path.map(|path| workspace::relative_path(&root, Path::new(&path)));
+    let touches = |entry: &AuditEntry| match &path {
+        Some(path) => entry.paths.iter().any(|touched| {
+            touched == path
+                || touched
+                    .strip_prefix(path.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        }),
+        None => true,
+    };
+
+    // Lines cut short by a crash are skipped
+    let mut entries: Vec<AuditEntry> = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(&line).ok())
+        .filter(|entry| action.is_none_or(|action| entry.action == action))
+        .filter(|entry| since.is_none_or(|since| entry.at >= since))
+        .filter(|entry| touches(entry))
+        .collect();
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(entries)
+}
@@ -0,0 +1,362 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::cancel::{self, CancelToken};
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{catalog, exports, local_api, paths, search, vault, workspace};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, INT};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::AppHandle;
+
+/// Enough for a batch over a few thousand documents; runaway loops stop here
+const MAX_OPERATIONS: u64 = 200_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_STRING_SIZE: usize = 16 * 1024 * 1024;
+const MAX_COLLECTION_SIZE: usize = 100_000;
+/// Lines of `print` and `debug` output kept for the result
+const MAX_OUTPUT: usize = 1000;
+const DEFAULT_SEARCH_LIMIT: INT = 50;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationRun {
+    /// Value of the script's last expression
+    pub value: Value,
+    pub output: Vec<String>,
+    /// Workspace-relative paths of documents created, changed, renamed or exported
+    pub changed: Vec<String>,
+}
+
+/// State shared by the functions a script can call
+#[derive(Clone)]
+struct Host {
+    app: AppHandle,
+    root: PathBuf,
+    cancel: CancelToken,
+    changed: Arc<Mutex<Vec<String>>>,
+}
+
+impl Host {
+    /// A workspace document from a workspace-relative path; vaults are off limits
+    fn document(&self, relative: &str, scope: paths::Scope) -> Result<PathBuf, String> {
+        let mut path = self.within(relative)?;
+        if !path.to_string_lossy().ends_with(DOCUMENT_EXTENSION) {
+            path = PathBuf::from(format!("{}{}", path.to_string_lossy(), DOCUMENT_EXTENSION));
+        }
+        if vault::vault_of(&path).is_some() {
+            return Err("Scripts cannot open documents in a vault".to_string());
+        }
+        paths::check(&self.app, &path.to_string_lossy(), scope).map_err(|e| e.message)?;
+        Ok(path)
+    }
+
+    fn within(&self, relative: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(relative.trim_start_matches(['/', '\\']));
+        if relative
+            .components()
+            .any(|part| !matches!(part, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(format!("Invalid path: {}", relative.display()));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        workspace::relative_path(&self.root, path)
+    }
+
+    fn changed(&self, path: &Path) {
+        if let Ok(mut changed) = self.changed.lock() {
+            let relative = self.relative(path);
+            if !changed.contains(&relative) {
+                changed.push(relative);
+            }
+        }
+    }
+
+    fn list_documents(&self, folder: &str) -> Result<Array, String> {
+        let dir = self.within(folder)?;
+        if !dir.is_dir() {
+            return Err(format!("Folder does not exist: {}", folder));
+        }
+        Ok(workspace::list_documents(&dir)?
+            .into_iter()
+            .filter(|path| vault::vault_of(path).is_none())
+            .map(|path| Dynamic::from(self.relative(&path)))
+            .collect())
+    }
+
+    fn read_document(&self, relative: &str) -> Result<Map, String> {
+        let path = self.document(relative, paths::Scope::Read)?;
+        let board = document::read_board(&path)?;
+        let markdown = board.to_markdown();
+        let tags: Array = catalog::tags(&markdown)
+            .into_iter()
+            .map(Dynamic::from)
+            .collect();
+        let mut map = Map::new();
+        map.insert("path".into(), self.relative(&path).into());
+        map.insert("name".into(), board.board.name.into());
+        map.insert("markdown".into(), markdown.into());
+        map.insert("tags".into(), tags.into());
+        map.insert("createdAt".into(), board.board.created_at.into());
+        map.insert("updatedAt".into(), board.board.updated_at.into());
+        Ok(map)
+    }
+
+    fn create_document(&self, folder: &str, name: &str, markdown: &str) -> Result<String, String> {
+        let dir = self.within(folder)?;
+        if vault::vault_of(&dir).is_some() {
+            return Err("Scripts cannot open documents in a vault".to_string());
+        }
+        let dir = paths::check(&self.app, &dir.to_string_lossy(), paths::Scope::Write)
+            .map_err(|e| e.message)?;
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+        let path = document::unique_path(&dir, &local_api::file_stem(name), DOCUMENT_EXTENSION);
+        document::write_board(&path, &BoardFile::from_markdown(name.trim(), markdown))?;
+        events::file_changed(&self.app, &path, ChangeKind::Created);
+        self.changed(&path);
+        Ok(self.relative(&path))
+    }
+
+    fn append_document(&self, relative: &str, markdown: &str) -> Result<(), String> {
+        let path = self.document(relative, paths::Scope::Write)?;
+        let mut board = document::read_board(&path)?;
+        board.push_markdown(markdown);
+        board.board.updated_at = document::now_millis();
+        document::write_board(&path, &board)?;
+        events::file_changed(&self.app, &path, ChangeKind::Modified);
+        self.changed(&path);
+        Ok(())
+    }
+
+    /// Rename the document and its file in place; returns the new path
+    fn rename_document(&self, relative: &str, name: &str) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Invalid name: a document name cannot be empty".to_string());
+        }
+        let path = self.document(relative, paths::Scope::Write)?;
+        let mut board = document::read_board(&path)?;
+        let dir = path.parent().unwrap_or(&self.root);
+        let stem = local_api::file_stem(name);
+        let target = if stem == document::document_stem(&path) {
+            path.clone()
+        } else {
+            document::unique_path(dir, &stem, DOCUMENT_EXTENSION)
+        };
+        board.board.name = name.to_string();
+        board.board.updated_at = document::now_millis();
+        document::write_board(&path, &board)?;
+        if target == path {
+            events::file_changed(&self.app, &path, ChangeKind::Modified);
+        } else {
+            fs::rename(&path, &target).map_err(|e| format!("Failed to rename file: {}", e))?;
+            events::file_renamed(&self.app, &path, &target);
+            audit::record(
+                &self.root,
+                AuditAction::Rename,
+                AuditSource::Batch,
+                &[&path, &target],
+            );
+        }
+        self.changed(&target);
+        Ok(self.relative(&target))
+    }
+
+    fn search(&self, query: &str, limit: INT) -> ScriptResult<Array> {
+        let hits = search::search_documents(
+            self.app.clone(),
+            self.root.to_string_lossy().to_string(),
+            query.to_string(),
+            Some(limit.max(1) as usize),
+            None,
+            None,
+            None,
+        )
+        .map_err(|e| e.message)?;
+        // The search command includes unlocked vault documents
+        hits.into_iter()
+            .filter(|hit| vault::vault_of(&self.root.join(&hit.path)).is_none())
+            .map(rhai::serde::to_dynamic)
+            .collect()
+    }
+
+    /// Export to a folder, relative to the workspace unless absolute, with any format export rules
+    /// accept; returns the file written
+    fn export(&self, relative: &str, format: &str, destination: &str) -> Result<String, String> {
+        let path = self.document(relative, paths::Scope::Read)?;
+        let destination = match Path::new(destination) {
+            relative if relative.is_relative() => self.within(destination)?,
+            absolute => absolute.to_path_buf(),
+        };
+        let destination = paths::check(
+            &self.app,
+            &destination.to_string_lossy(),
+            paths::Scope::Export,
+        )
+        .map_err(|e| e.message)?;
+        let source = path.parent().unwrap_or(&self.root);
+        let output =
+            exports::export_document(&self.app, source, &path, &destination, format, &self.cancel)?;
+        if output.starts_with(&self.root) {
+            self.changed(&output);
+        }
+        Ok(output.to_string_lossy().to_string())
+    }
+}
+
+fn engine(host: &Host, output: &Arc<Mutex<Vec<String>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE);
+
+    let cancel = host.cancel.clone();
+    engine.on_progress(move |_| cancel.is_cancelled().then_some(Dynamic::UNIT));
+    let lines = output.clone();
+    engine.on_print(move |text| push_output(&lines, text.to_string()));
+    let lines = output.clone();
+    engine.on_debug(move |text, _, _| push_output(&lines, text.to_string()));
+
+    let h = host.clone();
+    engine.register_fn("list_documents", move || -> ScriptResult<Array> {
+        Ok(h.list_documents("")?)
+    });
+    let h = host.clone();
+    engine.register_fn(
+        "list_documents",
+        move |folder: &str| -> ScriptResult<Array> { Ok(h.list_documents(folder)?) },
+    );
+    let h = host.clone();
+    engine.register_fn("read_document", move |path: &str| -> ScriptResult<Map> {
+        Ok(h.read_document(path)?)
+    });
+    let h = host.clone();
+    engine.register_fn(
+        "create_document",
+        move |name: &str, markdown: &str| -> ScriptResult<String> {
+            Ok(h.create_document("", name, markdown)?)
+        },
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "create_document",
+        move |folder: &str, name: &str, markdown: &str| -> ScriptResult<String> {
+            Ok(h.create_document(folder, name, markdown)?)
+        },
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "append_document",
+        move |path: &str, markdown: &str| -> ScriptResult<()> {
+            Ok(h.append_document(path, markdown)?)
+        },
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "rename_document",
+        move |path: &str, name: &str| -> ScriptResult<String> {
+            Ok(h.rename_document(path, name)?)
+        },
+    );
+    let h = host.clone();
+    engine.register_fn("search", move |query: &str| -> ScriptResult<Array> {
+        h.search(query, DEFAULT_SEARCH_LIMIT)
+    });
+    let h = host.clone();
+    engine.register_fn(
+        "search",
+        move |query: &str, limit: INT| -> ScriptResult<Array> { h.search(query, limit) },
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "export",
+        move |path: &str, format: &str, destination: &str| -> ScriptResult<String> {
+            Ok(h.export(path, format, destination)?)
+        },
+    );
+    engine
+}
+
+fn push_output(lines: &Mutex<Vec<String>>, line: String) {
+    if let Ok(mut lines) = lines.lock() {
+        if lines.len() < MAX_OUTPUT {
+            lines.push(line);
+        }
+    }
+}
+
+fn run(
+    app: &AppHandle,
+    script: &str,
+    args: Value,
+    cancel: &CancelToken,
+) -> Result<AutomationRun, String> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let host = Host {
+        app: app.clone(),
+        root,
+        cancel: cancel.clone(),
+        changed: Arc::default(),
+    };
+    let output = Arc::default();
+    let engine = engine(&host, &output);
+
+    let mut scope = Scope::new();
+    let args = rhai::serde::to_dynamic(&args).map_err(|e| format!("Invalid args: {}", e))?;
+    scope.push("args", args);
+    let value = match engine.eval_with_scope::<Dynamic>(&mut scope, script) {
+        Ok(value) => value,
+        Err(error) if matches!(*error, EvalAltResult::ErrorTerminated(..)) => {
+            return Err(cancel::CANCELLED.to_string())
+        }
+        Err(error) => return Err(format!("Script failed: {}", error)),
+    };
+    let value: Value = rhai::serde::from_dynamic(&value)
+        .map_err(|e| format!("Failed to convert script result: {}", e))?;
+
+    let changed = host.changed.lock().map(|c| c.clone()).unwrap_or_default();
+    tracing::info!(changed = changed.len(), "Automation script finished");
+    Ok(AutomationRun {
+        value,
+        output: output.lock().map(|o| o.clone()).unwrap_or_default(),
+        changed,
+    })
+}
+
+/// Run a Rhai script against the open workspace, with `args` bound to the `args` variable.
+/// Scripts can call `list_documents([folder])`, `read_document(path)`,
+/// `create_document([folder,] name, markdown)`, `append_document(path, markdown)`,
+/// `rename_document(path, name)`, `search(query[, limit])` and
+/// `export(path, format, destination)`; paths are workspace-relative and vaults are off limits.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn run_automation(
+    app: AppHandle,
+    script: String,
+    args: Option<Value>,
+    op_id: Option<String>,
+) -> Result<AutomationRun, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = cancel::begin(&app, op_id);
+        run(
+            &app,
+            &script,
+            args.unwrap_or(Value::Null),
+            operation.token(),
+        )
+        .map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Automation task failed: {}", e))?
+}
@@ -0,0 +1,293 @@
+use crate::crypto::{self, SecretKey};
+use crate::error::Error;
+use crate::{lock, paths, secrets, vault, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Keychain names are `biometric:workspace` and `biometric:vault:<relative path>`
+const SECRET_PREFIX: &str = "biometric:";
+#[cfg(any(target_os = "macos", windows))]
+const CANCELLED: &str = "Biometric unlock was cancelled";
+#[cfg(any(target_os = "macos", windows, mobile))]
+const FAILED: &str = "Biometric verification failed";
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BiometricStatus {
+    pub available: bool,
+    /// `touchId`, `faceId`, `opticId` or `windowsHello`
+    pub kind: Option<&'static str>,
+    /// Why biometrics cannot be used, when they cannot
+    pub reason: Option<String>,
+    /// Whether the workspace lock can be opened with biometrics
+    pub workspace_enabled: bool,
+    /// Workspace-relative folders of the vaults that can be opened with biometrics
+    pub vaults: Vec<String>,
+}
+
+/// What a biometric key opens: the workspace lock, or the vault at `vault`
+struct Target {
+    root: PathBuf,
+    vault: Option<String>,
+    secret: String,
+}
+
+fn target(app: &AppHandle, vault: Option<String>) -> Result<Target, Error> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let Some(path) = vault else {
+        return Ok(Target {
+            root,
+            vault: None,
+            secret: format!("{}workspace", SECRET_PREFIX),
+        });
+    };
+    paths::check(app, &path, paths::Scope::Read)?;
+    let dir = Path::new(&path);
+    if vault::vault_of(dir).as_deref() != Some(dir) {
+        return Err(Error::not_found("Vault does not exist").with_context(&path));
+    }
+    Ok(Target {
+        secret: format!(
+            "{}vault:{}",
+            SECRET_PREFIX,
+            workspace::relative_path(&root, dir)
+        ),
+        root,
+        vault: Some(path),
+    })
+}
+
+/// Show the platform authenticator off the main thread
+async fn verify_async(app: &AppHandle, reason: &'static str) -> Result<(), Error> {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || platform::verify(&app, reason))
+        .await
+        .map_err(|e| format!("Failed to verify: {}", e))??;
+    Ok(())
+}
+
+/// Whether this device has a usable platform authenticator, and what is enrolled for the
+/// open workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_biometric_status(app: AppHandle) -> BiometricStatus {
+    let (kind, reason) = match platform::status(&app) {
+        Ok(kind) => (Some(kind), None),
+        Err(reason) => (None, Some(reason)),
+    };
+    let names = workspace::current_root(&app)
+        .and_then(|root| secrets::names(&root).ok())
+        .unwrap_or_default();
+    let vault_prefix = format!("{}vault:", SECRET_PREFIX);
+    BiometricStatus {
+        available: kind.is_some(),
+        kind,
+        reason,
+        workspace_enabled: names.contains(&format!("{}workspace", SECRET_PREFIX)),
+        vaults: names
+            .iter()
+            .filter_map(|name| name.strip_prefix(&vault_prefix))
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+/// Let the workspace lock, or the vault at `vault`, be opened with Touch ID, Windows Hello or
+/// the device's biometrics instead of the passphrase. It must be unlocked already.
+///
+/// The key is kept in the OS keychain and only released after the platform prompt succeeds; it
+/// is not bound to the secure enclave, so anyone able to read this account's keychain could
+/// read it too.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn enable_biometric_unlock(app: AppHandle, vault: Option<String>) -> Result<(), Error> {
+    let target = target(&app, vault)?;
+    let key = match &target.vault {
+        None => lock::key(&app, lock::WORKSPACE_KEY)?,
+        Some(path) => {
+            vault::key(Path::new(path)).ok_or_else(|| format!("Vault is locked: {}", path))?
+        }
+    };
+    verify_async(&app, "enable biometric unlock").await?;
+    secrets::store(&target.root, &target.secret, &BASE64.encode(key.as_ref()))?;
+    tracing::info!(vault = target.vault.is_some(), "Biometric unlock enabled");
+    Ok(())
+}
+
+/// Remove the keychain copy of the key; the passphrase keeps working. Returns `false` when
+/// biometric unlock was not enabled.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn disable_biometric_unlock(app: AppHandle, vault: Option<String>) -> Result<bool, Error> {
+    let target = target(&app, vault)?;
+    Ok(secrets::delete(&target.root, &target.secret)?)
+}
+
+/// Unlock the workspace lock, or the vault at `vault`, after the platform prompt succeeds,
+/// emitting `workspace:unlocked` or `vault:unlocked`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn unlock_with_biometric(app: AppHandle, vault: Option<String>) -> Result<(), Error> {
+    let target = target(&app, vault)?;
+    if !secrets::names(&target.root)?.contains(&target.secret) {
+        return Err(Error::not_found("Biometric unlock is not enabled"));
+    }
+    verify_async(
+        &app,
+        if target.vault.is_some() {
+            "unlock the vault"
+        } else {
+            "unlock the workspace"
+        },
+    )
+    .await?;
+    let encoded = secrets::get(&target.root, &target.secret)?
+        .ok_or_else(|| Error::not_found("Biometric unlock is not enabled"))?;
+    let key: [u8; crypto::KEY_LEN] = BASE64
+        .decode(encoded)
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or("Invalid biometric key: enable biometric unlock again")?;
+    let key = SecretKey::new(key);
+    match target.vault {
+        None => lock::unlock_with_key(&app, key)?,
+        Some(path) => vault::unlock_with_key(&app, path, key)?,
+    }
+    Ok(())
+}
+
+/// LocalAuthentication: Touch ID, Face ID on Macs paired with one, or Optic ID
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{CANCELLED, FAILED};
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2_foundation::{NSError, NSString};
+    use objc2_local_authentication::{LABiometryType, LAContext, LAPolicy};
+    use std::sync::mpsc;
+    use tauri::AppHandle;
+
+    const POLICY: LAPolicy = LAPolicy::DeviceOwnerAuthenticationWithBiometrics;
+    /// `LAErrorUserCancel`, `LAErrorSystemCancel` and `LAErrorAppCancel`
+    const CANCEL_CODES: [isize; 3] = [-2, -4, -9];
+
+    pub fn status(_app: &AppHandle) -> Result<&'static str, String> {
+        let context = unsafe { LAContext::new() };
+        unsafe { context.canEvaluatePolicy_error(POLICY) }
+            .map_err(|e| format!("Touch ID is not available: {}", e.localizedDescription()))?;
+        Ok(match unsafe { context.biometryType() } {
+            LABiometryType::FaceID => "faceId",
+            LABiometryType::OpticID => "opticId",
+            _ => "touchId",
+        })
+    }
+
+    pub fn verify(app: &AppHandle, reason: &str) -> Result<(), String> {
+        status(app)?;
+        let context = unsafe { LAContext::new() };
+        let (sender, receiver) = mpsc::channel();
+        let reply = RcBlock::new(move |success: Bool, error: *mut NSError| {
+            let code = unsafe { error.as_ref() }.map_or(0, |error| error.code());
+            let _ = sender.send((success.as_bool(), code));
+        });
+        unsafe {
+            context.evaluatePolicy_localizedReason_reply(
+                POLICY,
+                &NSString::from_str(reason),
+                &reply,
+            )
+        };
+        match receiver.recv() {
+            Ok((true, _)) => Ok(()),
+            Ok((false, code)) if CANCEL_CODES.contains(&code) => Err(CANCELLED.to_string()),
+            _ => Err(FAILED.to_string()),
+        }
+    }
+}
+
+/// Windows Hello, which accepts the device PIN as well as a face or fingerprint
+#[cfg(windows)]
+mod platform {
+    use super::{CANCELLED, FAILED};
+    use tauri::AppHandle;
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+
+    pub fn status(_app: &AppHandle) -> Result<&'static str, String> {
+        let availability = UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|operation| operation.get())
+            .map_err(|e| format!("Failed to query Windows Hello: {}", e))?;
+        if availability != UserConsentVerifierAvailability::Available {
+            return Err("Windows Hello is not available: set it up in Settings".to_string());
+        }
+        Ok("windowsHello")
+    }
+
+    pub fn verify(app: &AppHandle, reason: &str) -> Result<(), String> {
+        status(app)?;
+        let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))
+            .and_then(|operation| operation.get())
+            .map_err(|e| format!("Failed to verify with Windows Hello: {}", e))?;
+        match result {
+            UserConsentVerificationResult::Verified => Ok(()),
+            UserConsentVerificationResult::Canceled => Err(CANCELLED.to_string()),
+            _ => Err(FAILED.to_string()),
+        }
+    }
+}
+
+/// The biometric plugin: Face ID or Touch ID on iOS, BiometricPrompt on Android.
+///
+/// Android has no keychain yet (see [`crate::secrets`]), so enrolment is not offered there.
+#[cfg(mobile)]
+mod platform {
+    use super::FAILED;
+    use tauri::AppHandle;
+    use tauri_plugin_biometric::{AuthOptions, BiometricExt, BiometryType};
+
+    pub fn status(app: &AppHandle) -> Result<&'static str, String> {
+        if cfg!(target_os = "android") {
+            return Err("Biometric unlock is not supported on Android yet".to_string());
+        }
+        let status = app
+            .biometric()
+            .status()
+            .map_err(|e| format!("Failed to query biometrics: {}", e))?;
+        if !status.is_available {
+            return Err(format!(
+                "Biometrics are not available: {}",
+                status.error.unwrap_or_default()
+            ));
+        }
+        Ok(match status.biometry_type {
+            BiometryType::FaceID => "faceId",
+            _ => "touchId",
+        })
+    }
+
+    pub fn verify(app: &AppHandle, reason: &str) -> Result<(), String> {
+        status(app)?;
+        app.biometric()
+            .authenticate(reason.to_string(), AuthOptions::default())
+            .map_err(|e| format!("{}: {}", FAILED, e))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows, mobile)))]
+mod platform {
+    use tauri::AppHandle;
+
+    const UNSUPPORTED: &str = "Biometric unlock is not supported on this platform";
+
+    pub fn status(_app: &AppHandle) -> Result<&'static str, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn verify(_app: &AppHandle, _reason: &str) -> Result<(), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+}
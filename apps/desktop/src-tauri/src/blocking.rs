@@ -0,0 +1,52 @@
+use crate::error::{Error, ErrorCode};
+use std::time::Duration;
+
+/// How long a file system call may take before the command gives up on it
+pub const IO_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Run blocking file system work off the IPC thread, failing after [`IO_TIMEOUT`].
+///
+/// A call stuck on an unresponsive network drive keeps its worker thread, but the command
+/// returns an error instead of holding up the UI.
+pub async fn run<T, E, F>(what: &str, work: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    E: Into<Error> + Send + 'static,
+    F: FnOnce() -> Result<T, E> + Send + 'static,
+{
+    match tokio::time::timeout(IO_TIMEOUT, tauri::async_runtime::spawn_blocking(work)).await {
+        Ok(joined) => finish(what, joined),
+        Err(_) => Err(Error::new(
+            ErrorCode::Timeout,
+            format!(
+                "Timed out after {}s trying to {}",
+                IO_TIMEOUT.as_secs(),
+                what
+            ),
+        )),
+    }
+}
+
+/// [`run`] without the time limit, for work that is expected to take long, such as
+/// conversions, downloads and workspace scans, and that is cancelled instead of timed out
+pub async fn run_long<T, E, F>(what: &str, work: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    E: Into<Error> + Send + 'static,
+    F: FnOnce() -> Result<T, E> + Send + 'static,
+{
+    finish(what, tauri::async_runtime::spawn_blocking(work).await)
+}
+
+fn finish<T, E: Into<Error>>(
+    what: &str,
+    joined: Result<Result<T, E>, impl std::fmt::Display>,
+) -> Result<T, Error> {
+    match joined {
+        Ok(result) => result.map_err(Into::into),
+        Err(e) => Err(Error::new(
+            ErrorCode::Internal,
+            format!("Failed to {}: {}", what, e),
+        )),
+    }
+}
@@ -0,0 +1,416 @@
+use crate::document::{BoardMeta, DocOrder, ShapeRecord};
+use crate::error::Error;
+use crate::{paths, vault};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+/// Width assumed for text without a fixed width, per unit of font size, as the canvas does
+const TEXT_WIDTH_PER_FONT_SIZE: f64 = 10.0;
+const TEXT_LINE_HEIGHT: f64 = 1.2;
+/// Height assumed for markdown blocks that have not been laid out yet, per unit of font size
+const MARKDOWN_HEIGHT_PER_FONT_SIZE: f64 = 10.0;
+
+/// Axis-aligned world-space box
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Bounds {
+    fn around(points: impl IntoIterator<Item = (f64, f64)>) -> Option<Self> {
+        points
+            .into_iter()
+            .fold(None, |bounds: Option<Bounds>, (x, y)| {
+                Some(match bounds {
+                    None => Bounds::point(x, y),
+                    Some(b) => Bounds {
+                        min_x: b.min_x.min(x),
+                        min_y: b.min_y.min(y),
+                        max_x: b.max_x.max(x),
+                        max_y: b.max_y.max(y),
+                    },
+                })
+            })
+    }
+
+    fn point(x: f64, y: f64) -> Self {
+        Bounds {
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+        }
+    }
+
+    fn intersects(&self, other: &Bounds) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+}
+
+/// Where one shape's JSON sits in the document file
+struct BlockEntry {
+    offset: u64,
+    len: usize,
+    page_id: String,
+    bounds: Bounds,
+}
+
+struct BlockIndex {
+    /// File modification time and size when indexed; a save changes at least one
+    modified: Option<SystemTime>,
+    size: u64,
+    blocks: HashMap<String, BlockEntry>,
+}
+
+/// Managed state holding the block offset index of each document opened for lazy loading.
+///
+/// An index is rebuilt the next time it is used after the file changes on disk.
+#[derive(Default)]
+pub struct BlockIndexes(Mutex<HashMap<PathBuf, Arc<BlockIndex>>>);
+
+impl BlockIndexes {
+    fn get(&self, path: &Path) -> Result<Arc<BlockIndex>, String> {
+        let (modified, size) = stamp(path)?;
+        if let Some(index) = self.0.lock().ok().and_then(|indexes| {
+            indexes
+                .get(path)
+                .filter(|index| index.modified.is_some() && index.modified == modified)
+                .filter(|index| index.size == size)
+                .cloned()
+        }) {
+            return Ok(index);
+        }
+        let (index, _) = build(path)?;
+        Ok(self.insert(path, index))
+    }
+
+    /// Index a document ahead of its first [`load_blocks`] call
+    pub fn prime(&self, path: &Path) -> Result<(), String> {
+        self.get(path).map(|_| ())
+    }
+
+    /// Drop the indexes of documents inside `root`
+    pub fn evict(&self, root: &Path) {
+        if let Ok(mut indexes) = self.0.lock() {
+            indexes.retain(|path, _| !path.starts_with(root));
+        }
+    }
+
+    fn insert(&self, path: &Path, index: BlockIndex) -> Arc<BlockIndex> {
+        let index = Arc::new(index);
+        if let Ok(mut indexes) = self.0.lock() {
+            indexes.insert(path.to_path_buf(), index.clone());
+        }
+        index
+    }
+}
+
+impl BlockIndex {
+    /// Every indexed shape in file order
+    fn summaries(&self) -> Vec<BlockSummary> {
+        let mut blocks: Vec<(&String, &BlockEntry)> = self.blocks.iter().collect();
+        blocks.sort_unstable_by_key(|(_, entry)| entry.offset);
+        blocks
+            .into_iter()
+            .map(|(id, entry)| BlockSummary {
+                id: id.clone(),
+                page_id: entry.page_id.clone(),
+                bounds: entry.bounds,
+            })
+            .collect()
+    }
+}
+
+/// A shape's place on the canvas, without its contents
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSummary {
+    pub id: String,
+    pub page_id: String,
+    pub bounds: Bounds,
+}
+
+/// Everything in a document except shape contents, which are fetched with [`load_blocks`]
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedDocument {
+    pub board: BoardMeta,
+    pub pages: Map<String, Value>,
+    pub bindings: Map<String, Value>,
+    pub order: DocOrder,
+    pub blocks: Vec<BlockSummary>,
+}
+
+/// Visible region of a page, in world coordinates
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Viewport {
+    pub page_id: String,
+    #[serde(flatten)]
+    pub bounds: Bounds,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedBlocks {
+    /// Shape records exactly as stored, in file order
+    pub blocks: Vec<Box<RawValue>>,
+    /// Requested ids the document does not contain
+    pub missing: Vec<String>,
+}
+
+/// Document file parsed down to its shapes' raw JSON, which borrows from the file contents
+#[derive(Deserialize)]
+struct RawBoard<'a> {
+    board: BoardMeta,
+    #[serde(borrow)]
+    doc: RawDoc<'a>,
+    order: DocOrder,
+}
+
+#[derive(Deserialize)]
+struct RawDoc<'a> {
+    pages: Map<String, Value>,
+    #[serde(borrow)]
+    shapes: HashMap<Cow<'a, str>, &'a RawValue>,
+    bindings: Map<String, Value>,
+}
+
+/// Index a document's shapes and return its pages, bindings and shape positions.
+///
+/// Shape contents are left on disk; the canvas asks for the ones it needs with
+/// [`load_blocks`].
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn open_indexed_document(app: AppHandle, path: String) -> Result<IndexedDocument, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = PathBuf::from(path);
+        let (index, document) = build(&path)?;
+        let document = IndexedDocument {
+            blocks: index.summaries(),
+            ..document
+        };
+        app.state::<BlockIndexes>().insert(&path, index);
+        Ok(document)
+    })
+    .await
+    .map_err(|e| format!("Indexing task failed: {}", e))?
+}
+
+/// Read the given shapes, or every shape on a page overlapping `viewport`, from a document.
+///
+/// Each shape is read straight from its offset in the file, so the cost depends on what is
+/// requested rather than on the size of the document.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn load_blocks(
+    app: AppHandle,
+    path: String,
+    block_ids: Option<Vec<String>>,
+    viewport: Option<Viewport>,
+) -> Result<LoadedBlocks, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = PathBuf::from(path);
+        let index = app.state::<BlockIndexes>().get(&path)?;
+        let mut missing = Vec::new();
+        let mut ranges: Vec<(u64, usize)> = match (block_ids, viewport) {
+            (Some(ids), _) => ids
+                .into_iter()
+                .filter_map(|id| match index.blocks.get(&id) {
+                    Some(entry) => Some((entry.offset, entry.len)),
+                    None => {
+                        missing.push(id);
+                        None
+                    }
+                })
+                .collect(),
+            (None, Some(viewport)) => index
+                .blocks
+                .values()
+                .filter(|entry| {
+                    entry.page_id == viewport.page_id && entry.bounds.intersects(&viewport.bounds)
+                })
+                .map(|entry| (entry.offset, entry.len))
+                .collect(),
+            (None, None) => return Err("Either blockIds or a viewport is required".into()),
+        };
+        // Reading in file order keeps the seeks moving forward
+        ranges.sort_unstable();
+        ranges.dedup();
+        Ok(LoadedBlocks {
+            blocks: read_blocks(&path, &ranges)?,
+            missing,
+        })
+    })
+    .await
+    .map_err(|e| format!("Block read failed: {}", e))?
+}
+
+fn stamp(path: &Path) -> Result<(Option<SystemTime>, u64), String> {
+    let metadata =
+        std::fs::metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    Ok((metadata.modified().ok(), metadata.len()))
+}
+
+/// Parse a document once, recording the byte range and bounds of every shape
+fn build(path: &Path) -> Result<(BlockIndex, IndexedDocument), String> {
+    let (modified, size) = stamp(path)?;
+    let content = std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    // Offsets of a vault document are into its decrypted content
+    let content = vault::unseal(path, &content)?;
+    let raw: RawBoard =
+        serde_json::from_slice(&content).map_err(|e| format!("Invalid file format: {}", e))?;
+
+    let start = content.as_ptr() as usize;
+    let blocks = raw
+        .doc
+        .shapes
+        .into_iter()
+        .filter_map(|(id, value)| {
+            let json = value.get();
+            let shape: ShapeRecord = serde_json::from_str(json).ok()?;
+            Some((
+                id.into_owned(),
+                BlockEntry {
+                    offset: (json.as_ptr() as usize - start) as u64,
+                    len: json.len(),
+                    bounds: shape_bounds(&shape),
+                    page_id: shape.page_id,
+                },
+            ))
+        })
+        .collect();
+
+    Ok((
+        BlockIndex {
+            modified,
+            size,
+            blocks,
+        },
+        IndexedDocument {
+            board: raw.board,
+            pages: raw.doc.pages,
+            bindings: raw.doc.bindings,
+            order: raw.order,
+            blocks: Vec::new(),
+        },
+    ))
+}
+
+fn read_blocks(path: &Path, ranges: &[(u64, usize)]) -> Result<Vec<Box<RawValue>>, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    // A vault document cannot be read in place, so it is decrypted whole and sliced
+    let decrypted = match vault::vault_of(path) {
+        Some(_) => {
+            let content = std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            Some(vault::unseal(path, &content)?.into_owned())
+        }
+        None => None,
+    };
+    ranges
+        .iter()
+        .map(|&(offset, len)| {
+            let bytes = match &decrypted {
+                Some(content) => content
+                    .get(offset as usize..offset as usize + len)
+                    .ok_or_else(|| "Invalid file format: block is out of range".to_string())?
+                    .to_vec(),
+                None => {
+                    let mut bytes = vec![0; len];
+                    file.seek(SeekFrom::Start(offset))
+                        .and_then(|_| file.read_exact(&mut bytes))
+                        .map_err(|e| format!("Failed to read file: {}", e))?;
+                    bytes
+                }
+            };
+            let json = String::from_utf8(bytes)
+                .map_err(|_| "Invalid file format: block is not UTF-8".to_string())?;
+            RawValue::from_string(json).map_err(|e| format!("Invalid file format: {}", e))
+        })
+        .collect()
+}
+
+/// World-space bounds matching `shapeBounds` in inkfinite-core
+fn shape_bounds(shape: &ShapeRecord) -> Bounds {
+    let props = &shape.props;
+    let number = |key: &str| props.get(key).and_then(Value::as_f64);
+    let point = |value: &Value| Some((value.get("x")?.as_f64()?, value.get("y")?.as_f64()?));
+    let corners = |w: f64, h: f64| vec![(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+
+    let local = match shape.kind.as_str() {
+        "line" => ["a", "b"]
+            .iter()
+            .filter_map(|key| props.get(*key).and_then(point))
+            .collect(),
+        "arrow" => props
+            .get("points")
+            .and_then(Value::as_array)
+            .map(|points| points.iter().filter_map(point).collect())
+            .unwrap_or_default(),
+        "text" => {
+            let font_size = number("fontSize").unwrap_or(0.0);
+            corners(
+                number("w").unwrap_or(font_size * TEXT_WIDTH_PER_FONT_SIZE),
+                font_size * TEXT_LINE_HEIGHT,
+            )
+        }
+        "markdown" => corners(
+            number("w").unwrap_or(0.0),
+            number("h")
+                .unwrap_or(number("fontSize").unwrap_or(0.0) * MARKDOWN_HEIGHT_PER_FONT_SIZE),
+        ),
+        // Stroke outlines are never rotated; the brush widens them by half its size
+        "stroke" => {
+            let pad = props
+                .get("brush")
+                .and_then(|brush| brush.get("size"))
+                .and_then(Value::as_f64)
+                .unwrap_or(0.0)
+                / 2.0;
+            let points: Vec<(f64, f64)> = props
+                .get("points")
+                .and_then(Value::as_array)
+                .map(|points| {
+                    points
+                        .iter()
+                        .filter_map(|p| Some((p.get(0)?.as_f64()?, p.get(1)?.as_f64()?)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            return Bounds::around(points.iter().flat_map(|&(x, y)| {
+                [
+                    (shape.x + x - pad, shape.y + y - pad),
+                    (shape.x + x + pad, shape.y + y + pad),
+                ]
+            }))
+            .unwrap_or(Bounds::point(shape.x, shape.y));
+        }
+        _ => corners(number("w").unwrap_or(0.0), number("h").unwrap_or(0.0)),
+    };
+
+    let (sin, cos) = shape.rot.sin_cos();
+    Bounds::around(
+        local
+            .into_iter()
+            .map(|(x, y)| (shape.x + x * cos - y * sin, shape.y + x * sin + y * cos)),
+    )
+    .unwrap_or(Bounds::point(shape.x, shape.y))
+}
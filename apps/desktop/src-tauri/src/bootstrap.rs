@@ -0,0 +1,303 @@
+use crate::assets::ASSETS_DIR;
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::{paths, workspace};
+use inkfinite_core::template::Template;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const DEFAULT_FOLDERS: &[&str] = &["Notes", "Journal", "Projects", ASSETS_DIR];
+const WELCOME_NAME: &str = "Welcome";
+
+/// Starter templates as `(id, source)`, in the format `save_template` accepts
+const TEMPLATES: &[(&str, &str)] = &[
+    (
+        "daily-note",
+        r#"+++
+name = "Daily note"
+description = "Plans and notes for today"
+title = "{{date}}"
+folder = "Journal"
++++
+# {{date "%A, %B %-d"}}
+
+## Plans
+
+- [ ]
+
+## Notes
+
+"#,
+    ),
+    (
+        "meeting-notes",
+        r#"+++
+name = "Meeting notes"
+description = "Attendees, agenda and action items"
+title = "{{date}} {{topic}}"
+folder = "Notes"
+
+[[prompts]]
+name = "topic"
+label = "Topic"
+required = true
+
+[[prompts]]
+name = "attendees"
+label = "Attendees"
+kind = "list"
++++
+# {{topic}}
+
+{{date}} · {{time}}
+
+## Attendees
+
+{{#each attendees}}
+- {{this}}
+{{/each}}
+
+## Agenda
+
+## Action items
+
+- [ ]
+"#,
+    ),
+    (
+        "project-brief",
+        r#"+++
+name = "Project brief"
+description = "Goal, scope and milestones of a project"
+title = "{{project}}"
+folder = "Projects"
+
+[[prompts]]
+name = "project"
+label = "Project name"
+required = true
++++
+# {{project}}
+
+#project
+
+## Goal
+
+## Scope
+
+## Milestones
+
+- [ ]
+"#,
+    ),
+];
+
+const WELCOME: &str = "# Welcome to Inkfinite
+
+This workspace is a folder of documents. Each one is an infinite canvas that holds text, ink, \
+shapes and images side by side.
+
+## Start here
+
+- [ ] Double-click the canvas to add a text block
+- [ ] Draw with the pen, then select the strokes to recognize handwriting
+- [ ] Create a note from a template: *Daily note*, *Meeting notes* or *Project brief*
+- [ ] Link documents by name, like [[Sample note]], and tag them, like #ideas
+
+## How the workspace is laid out
+
+- **Notes**, **Journal** and **Projects** are ordinary folders; add, rename or remove them as \
+you like
+- **assets** keeps images and files added to documents
+- **.inkfinite** holds templates, settings and logs for this workspace
+
+Delete this document whenever you are done with it.
+";
+
+const SAMPLE_NAME: &str = "Sample note";
+const SAMPLE: &str = "# Sample note
+
+A note linked from [[Welcome]]. Tags such as #ideas group notes across folders, and links \
+between notes show up as related documents.
+
+> Quotes, **bold**, *italic* and `code` all work as in Markdown.
+
+1. Lists
+2. Numbered lists
+3. And [links](https://github.com/stormlightlabs/inkfinite)
+";
+
+/// Logs and caches a workspace writes under `.inkfinite`, which need not be versioned
+const INTERNAL_GITIGNORE: &str = "logs/\n";
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BootstrapOptions {
+    /// Workspace-relative folders to create; `Notes`, `Journal`, `Projects` and `assets` when
+    /// unset
+    pub folders: Option<Vec<String>>,
+    pub templates: bool,
+    /// Add a welcome document and a sample note
+    pub welcome: bool,
+}
+
+impl Default for BootstrapOptions {
+    fn default() -> Self {
+        BootstrapOptions {
+            folders: None,
+            templates: true,
+            welcome: true,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootstrapReport {
+    pub root: String,
+    /// Workspace-relative paths of everything created
+    pub created: Vec<String>,
+    /// The welcome document, to open first
+    pub welcome: Option<String>,
+}
+
+fn check_folder(folder: &str) -> Result<PathBuf, Error> {
+    let relative = Path::new(folder.trim_matches(['/', '\\']));
+    let valid = relative.components().next().is_some()
+        && relative
+            .components()
+            .all(|part| matches!(part, Component::Normal(_)));
+    if !valid {
+        return Err(Error::invalid_path("Invalid folder").with_context(folder));
+    }
+    Ok(relative.to_path_buf())
+}
+
+/// Write the whole layout into `staging`, returning what was created relative to it
+fn populate(staging: &Path, options: &BootstrapOptions) -> Result<Vec<PathBuf>, Error> {
+    let mut created = Vec::new();
+    let folders: Vec<PathBuf> = match &options.folders {
+        Some(folders) => folders
+            .iter()
+            .map(|folder| check_folder(folder))
+            .collect::<Result<_, _>>()?,
+        None => DEFAULT_FOLDERS.iter().map(PathBuf::from).collect(),
+    };
+    for folder in folders {
+        fs::create_dir_all(staging.join(&folder))
+            .map_err(|e| format!("Failed to create folder: {}", e))?;
+        created.push(folder);
+    }
+
+    let internal = workspace::internal_dir(Path::new(""));
+    fs::create_dir_all(staging.join(&internal))
+        .map_err(|e| format!("Failed to create {}: {}", internal.display(), e))?;
+    fs::write(
+        staging.join(internal.join(".gitignore")),
+        INTERNAL_GITIGNORE,
+    )
+    .map_err(|e| format!("Failed to write file: {}", e))?;
+    created.push(internal.clone());
+
+    if options.templates {
+        let templates = internal.join("templates");
+        fs::create_dir_all(staging.join(&templates))
+            .map_err(|e| format!("Failed to create folder: {}", e))?;
+        for (id, source) in TEMPLATES {
+            Template::parse(source, id)?;
+            let path = templates.join(format!("{}.md", id));
+            fs::write(staging.join(&path), source)
+                .map_err(|e| format!("Failed to write template: {}", e))?;
+            created.push(path);
+        }
+    }
+
+    if options.welcome {
+        // The sample sits in `Notes` when that folder is part of the layout
+        let notes = Path::new("Notes");
+        let sample_dir = match staging.join(notes).is_dir() {
+            true => notes.to_path_buf(),
+            false => PathBuf::new(),
+        };
+        for (dir, name, markdown) in [
+            (PathBuf::new(), WELCOME_NAME, WELCOME),
+            (sample_dir, SAMPLE_NAME, SAMPLE),
+        ] {
+            let path = dir.join(format!("{}{}", name, DOCUMENT_EXTENSION));
+            document::write_board(
+                &staging.join(&path),
+                &BoardFile::from_markdown(name, markdown),
+            )?;
+            created.push(path);
+        }
+    }
+    Ok(created)
+}
+
+/// Move the finished layout into place: a rename when the workspace folder does not exist yet,
+/// or replaces an empty one
+fn commit(staging: &Path, root: &Path) -> Result<(), String> {
+    if root.exists() {
+        fs::remove_dir(root).map_err(|e| format!("Failed to prepare workspace folder: {}", e))?;
+    }
+    fs::rename(staging, root).map_err(|e| {
+        let _ = fs::create_dir(root);
+        format!("Failed to create workspace: {}", e)
+    })
+}
+
+/// Create a new workspace at `path` in one step: its folders, `.inkfinite` internals, starter
+/// templates and a welcome document with sample content. Everything is written to a hidden
+/// folder beside it first and moved into place at the end, so a failure leaves nothing behind.
+/// The folder must not exist yet or be empty.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn bootstrap_workspace(
+    app: tauri::AppHandle,
+    path: String,
+    options: Option<BootstrapOptions>,
+) -> Result<BootstrapReport, Error> {
+    let options = options.unwrap_or_default();
+    let root = paths::check(&app, &path, paths::Scope::Export)?;
+    if root.is_file() {
+        return Err(Error::invalid_path("Workspace folder is a file").with_context(&path));
+    }
+    let occupied = fs::read_dir(&root).is_ok_and(|mut entries| entries.next().is_some());
+    if occupied {
+        return Err(Error::conflict("Workspace folder already has files in it").with_context(&path));
+    }
+    let (Some(parent), Some(name)) = (root.parent(), root.file_name()) else {
+        return Err(Error::invalid_path("Invalid workspace folder").with_context(&path));
+    };
+    fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+
+    let staging = parent.join(format!(
+        ".{}.bootstrap-{}",
+        name.to_string_lossy(),
+        uuid::Uuid::new_v4().simple()
+    ));
+    fs::create_dir(&staging).map_err(|e| format!("Failed to create workspace: {}", e))?;
+    let result = populate(&staging, &options).and_then(|created| {
+        commit(&staging, &root)?;
+        Ok(created)
+    });
+    let created = match result {
+        Ok(created) => created,
+        Err(error) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(error);
+        }
+    };
+    tracing::info!(root = %root.display(), "Workspace created");
+
+    let relative = |path: &PathBuf| path.to_string_lossy().replace('\\', "/");
+    Ok(BootstrapReport {
+        root: root.to_string_lossy().into_owned(),
+        welcome: options.welcome.then(|| {
+            root.join(format!("{}{}", WELCOME_NAME, DOCUMENT_EXTENSION))
+                .to_string_lossy()
+                .into_owned()
+        }),
+        created: created.iter().map(relative).collect(),
+    })
+}
@@ -0,0 +1,618 @@
+use crate::error::Error;
+use crate::events::ChangeKind;
+use crate::http::{self, Stream};
+use crate::{crypto, paths, read_only, secrets, vault, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const CONFIG: &str = "event-bridge";
+const DEFAULT_PREFIX: &str = "inkfinite";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// MQTT keep-alive; a ping is sent when nothing else was for half of it
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+/// Wait after a failed connection before trying again
+const RETRY: Duration = Duration::from_secs(30);
+
+/// Where events are republished
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum BridgeTarget {
+    /// An MQTT 3.1.1 broker; events are published with QoS 0 to `<prefix>/<topic>`
+    Mqtt {
+        host: String,
+        /// 1883, or 8883 with TLS
+        port: Option<u16>,
+        #[serde(default)]
+        tls: bool,
+        username: Option<String>,
+        /// Secret (see [`crate::secrets`]) holding the password
+        password_secret: Option<String>,
+        /// A random id when omitted
+        client_id: Option<String>,
+    },
+    /// A Unix domain socket created at `path`; every connected client receives each event as a
+    /// line of JSON
+    Socket { path: String },
+}
+
+/// Per-workspace bridge settings, stored in `.inkfinite/event-bridge.json`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeSettings {
+    pub target: BridgeTarget,
+    /// Topic prefix for MQTT, `inkfinite` when omitted
+    pub topic_prefix: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Config {
+    bridge: Option<BridgeSettings>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeStatus {
+    pub settings: Option<BridgeSettings>,
+    /// Whether the broker is connected or the socket is listening
+    pub connected: bool,
+    pub last_error: Option<String>,
+}
+
+enum Outgoing {
+    Event {
+        root: PathBuf,
+        topic: String,
+        data: Value,
+    },
+    /// Settings changed: drop the connection and read them again
+    Reload,
+}
+
+#[derive(Default)]
+struct Health {
+    /// Workspace the bridge is connected for
+    connected: Option<PathBuf>,
+    last_error: Option<String>,
+}
+
+/// Managed state: the channel to the bridge worker and how its connection is doing
+#[derive(Default)]
+pub struct EventBridge {
+    sender: Mutex<Option<Sender<Outgoing>>>,
+    health: Mutex<Health>,
+}
+
+/// Republish a backend event for the workspace at `root`. Returns at once; the event is
+/// dropped when the workspace has no bridge or it cannot connect.
+pub fn publish(app: &AppHandle, root: &Path, topic: &str, data: Value) {
+    let bridge = app.state::<EventBridge>();
+    let Ok(sender) = bridge.sender.lock() else {
+        return;
+    };
+    if let Some(sender) = sender.as_ref() {
+        let _ = sender.send(Outgoing::Event {
+            root: root.to_path_buf(),
+            topic: topic.to_string(),
+            data,
+        });
+    }
+}
+
+/// [`publish`] a change to a document of the open workspace; vault documents are not reported
+pub fn document_event(app: &AppHandle, path: &Path, kind: ChangeKind, from: Option<&Path>) {
+    let Some(root) = workspace::current_root(app) else {
+        return;
+    };
+    if !path.starts_with(&root) || vault::vault_of(path).is_some() {
+        return;
+    }
+    let data = json!({
+        "path": workspace::relative_path(&root, path),
+        "from": from.map(|from| workspace::relative_path(&root, from)),
+    });
+    publish(app, &root, &format!("document/{}", kind.as_str()), data);
+}
+
+fn load(root: &Path) -> Option<BridgeSettings> {
+    workspace::read_config::<Config>(root, CONFIG)
+        .map_err(|error| tracing::warn!(%error, "Failed to read event bridge settings"))
+        .ok()?
+        .bridge
+}
+
+/// Start the worker that owns the bridge connection
+pub fn start(app: AppHandle) {
+    let (sender, receiver) = mpsc::channel();
+    if let Ok(mut slot) = app.state::<EventBridge>().sender.lock() {
+        *slot = Some(sender);
+    }
+
+    std::thread::spawn(move || {
+        let mut worker = Worker {
+            app: app.clone(),
+            settings: None,
+            sink: None,
+            retry_at: None,
+        };
+        // Bring a configured socket up without waiting for the first event
+        worker.prepare(workspace::current_root(&app));
+        loop {
+            match receiver.recv_timeout(KEEP_ALIVE / 4) {
+                Ok(Outgoing::Event { root, topic, data }) => {
+                    worker.prepare(Some(root));
+                    worker.send(&topic, &data);
+                }
+                Ok(Outgoing::Reload) => {
+                    worker.disconnect();
+                    worker.settings = None;
+                    worker.retry_at = None;
+                    worker.prepare(workspace::current_root(&app));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    worker.keep_alive();
+                    worker.prepare(workspace::current_root(&app));
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    });
+}
+
+struct Worker {
+    app: AppHandle,
+    /// Settings of the workspace last seen, read once per workspace
+    settings: Option<(PathBuf, Option<BridgeSettings>)>,
+    sink: Option<Sink>,
+    retry_at: Option<Instant>,
+}
+
+impl Worker {
+    /// Load the settings for `root` and connect, unless connected already or waiting to retry
+    fn prepare(&mut self, root: Option<PathBuf>) {
+        let Some(root) = root else {
+            return;
+        };
+        if self
+            .settings
+            .as_ref()
+            .is_none_or(|(loaded, _)| loaded != &root)
+        {
+            self.disconnect();
+            self.retry_at = None;
+            let settings = load(&root);
+            self.settings = Some((root.clone(), settings));
+        }
+        let Some((_, Some(settings))) = &self.settings else {
+            return;
+        };
+        if self.sink.is_some() || self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return;
+        }
+        match Sink::open(&root, settings) {
+            Ok(sink) => {
+                tracing::info!("Event bridge connected");
+                self.sink = Some(sink);
+                self.retry_at = None;
+                self.health(|health| {
+                    health.connected = Some(root);
+                    health.last_error = None;
+                });
+            }
+            Err(error) => {
+                tracing::warn!(%error, "Failed to connect the event bridge");
+                self.retry_at = Some(Instant::now() + RETRY);
+                self.health(|health| {
+                    health.connected = None;
+                    health.last_error = Some(error);
+                });
+            }
+        }
+    }
+
+    fn send(&mut self, topic: &str, data: &Value) {
+        let Some((_, Some(settings))) = &self.settings else {
+            return;
+        };
+        let prefix = settings
+            .topic_prefix
+            .as_deref()
+            .map(|prefix| prefix.trim_matches('/'))
+            .filter(|prefix| !prefix.is_empty())
+            .unwrap_or(DEFAULT_PREFIX)
+            .to_string();
+        let Some(sink) = self.sink.as_mut() else {
+            return;
+        };
+        let message = json!({
+            "topic": topic,
+            "at": crate::document::now_millis(),
+            "data": data,
+        })
+        .to_string();
+        if let Err(error) = sink.send(&format!("{}/{}", prefix, topic), &message) {
+            tracing::warn!(%error, "Event bridge disconnected");
+            self.disconnect();
+            self.health(|health| health.last_error = Some(error));
+        }
+    }
+
+    fn keep_alive(&mut self) {
+        if let Some(Err(error)) = self.sink.as_mut().map(Sink::keep_alive) {
+            tracing::warn!(%error, "Event bridge disconnected");
+            self.disconnect();
+            self.health(|health| health.last_error = Some(error));
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(sink) = self.sink.take() {
+            sink.close();
+        }
+        self.health(|health| health.connected = None);
+    }
+
+    fn health(&self, update: impl FnOnce(&mut Health)) {
+        if let Ok(mut health) = self.app.state::<EventBridge>().health.lock() {
+            update(&mut health);
+        }
+    }
+}
+
+enum Sink {
+    Mqtt(Mqtt),
+    #[cfg(unix)]
+    Socket(unix::Server),
+}
+
+impl Sink {
+    fn open(root: &Path, settings: &BridgeSettings) -> Result<Sink, String> {
+        match &settings.target {
+            BridgeTarget::Mqtt {
+                host,
+                port,
+                tls,
+                username,
+                password_secret,
+                client_id,
+            } => {
+                let password = match password_secret {
+                    Some(name) => Some(
+                        secrets::get(root, name)?
+                            .ok_or_else(|| format!("Secret not found: {}", name))?,
+                    ),
+                    None => None,
+                };
+                let client_id = client_id.clone().unwrap_or_else(|| {
+                    let mut bytes = [0u8; 6];
+                    crypto::fill_random(&mut bytes);
+                    let suffix: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                    format!("inkfinite-{}", suffix)
+                });
+                Mqtt::connect(
+                    host,
+                    port.unwrap_or(if *tls { 8883 } else { 1883 }),
+                    *tls,
+                    &client_id,
+                    username.as_deref(),
+                    password.as_deref(),
+                )
+                .map(Sink::Mqtt)
+            }
+            #[cfg(unix)]
+            BridgeTarget::Socket { path } => {
+                unix::Server::listen(Path::new(path)).map(Sink::Socket)
+            }
+            #[cfg(not(unix))]
+            BridgeTarget::Socket { .. } => {
+                Err("Unix domain sockets are not supported on this platform".to_string())
+            }
+        }
+    }
+
+    fn send(&mut self, topic: &str, message: &str) -> Result<(), String> {
+        match self {
+            Sink::Mqtt(mqtt) => mqtt.publish(topic, message),
+            #[cfg(unix)]
+            Sink::Socket(server) => {
+                server.broadcast(message);
+                Ok(())
+            }
+        }
+    }
+
+    fn keep_alive(&mut self) -> Result<(), String> {
+        match self {
+            Sink::Mqtt(mqtt) => mqtt.keep_alive(),
+            #[cfg(unix)]
+            Sink::Socket(_) => Ok(()),
+        }
+    }
+
+    fn close(self) {
+        match self {
+            Sink::Mqtt(mut mqtt) => {
+                // DISCONNECT
+                let _ = mqtt.stream.write_all(&[0xe0, 0x00]);
+            }
+            #[cfg(unix)]
+            Sink::Socket(server) => drop(server),
+        }
+    }
+}
+
+/// A minimal MQTT 3.1.1 publisher: QoS 0 only, nothing subscribed
+struct Mqtt {
+    stream: Stream,
+    last_sent: Instant,
+}
+
+impl Mqtt {
+    fn connect(
+        host: &str,
+        port: u16,
+        tls: bool,
+        client_id: &str,
+        username: Option<&str>,
+        password: Option<&str>,
+    ) -> Result<Mqtt, String> {
+        let address = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| format!("Failed to resolve broker: {}", e))?
+            .next()
+            .ok_or_else(|| format!("Failed to resolve broker: {}", host))?;
+        let tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+            .map_err(|e| format!("Failed to connect to broker: {}", e))?;
+        let _ = tcp.set_nodelay(true);
+        let _ = tcp.set_read_timeout(Some(CONNECT_TIMEOUT));
+        let _ = tcp.set_write_timeout(Some(WRITE_TIMEOUT));
+        let stream = if tls {
+            Stream::Tls(Box::new(http::tls(host, tcp)?))
+        } else {
+            Stream::Plain(tcp)
+        };
+        let mut mqtt = Mqtt {
+            stream,
+            last_sent: Instant::now(),
+        };
+
+        // Clean session, with the user name and password flags as given
+        let mut flags = 0x02;
+        let mut body = Vec::new();
+        push_string(&mut body, "MQTT");
+        body.push(4);
+        if username.is_some() {
+            flags |= 0x80;
+        }
+        if password.is_some() {
+            flags |= 0x40;
+        }
+        body.push(flags);
+        body.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+        push_string(&mut body, client_id);
+        for field in [username, password].into_iter().flatten() {
+            push_string(&mut body, field);
+        }
+        mqtt.write(0x10, &body)?;
+
+        let mut ack = [0u8; 4];
+        mqtt.stream
+            .read_exact(&mut ack)
+            .map_err(|e| format!("Failed to connect to broker: {}", e))?;
+        if ack[0] != 0x20 || ack[1] != 0x02 {
+            return Err("Invalid broker reply".to_string());
+        }
+        match ack[3] {
+            0 => {}
+            4 | 5 => return Err("Broker refused the user name or password".to_string()),
+            code => return Err(format!("Broker refused the connection: code {}", code)),
+        }
+        // Only PINGRESP is ever read from here on, a byte at a time, so never block on it
+        let _ = mqtt
+            .stream
+            .tcp()
+            .set_read_timeout(Some(Duration::from_millis(1)));
+        Ok(mqtt)
+    }
+
+    fn publish(&mut self, topic: &str, payload: &str) -> Result<(), String> {
+        let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+        push_string(&mut body, topic);
+        body.extend_from_slice(payload.as_bytes());
+        self.write(0x30, &body)
+    }
+
+    fn keep_alive(&mut self) -> Result<(), String> {
+        // Discard pings answered since last time
+        let mut buffer = [0u8; 64];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err("Broker closed the connection".to_string()),
+                Ok(_) => {}
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break
+                }
+                Err(e) => return Err(format!("Failed to read from broker: {}", e)),
+            }
+        }
+        if self.last_sent.elapsed() >= KEEP_ALIVE / 2 {
+            self.write(0xc0, &[])?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, header: u8, body: &[u8]) -> Result<(), String> {
+        let mut packet = Vec::with_capacity(body.len() + 5);
+        packet.push(header);
+        // Remaining length, seven bits at a time
+        let mut length = body.len();
+        loop {
+            let mut byte = (length % 128) as u8;
+            length /= 128;
+            if length > 0 {
+                byte |= 0x80;
+            }
+            packet.push(byte);
+            if length == 0 {
+                break;
+            }
+        }
+        packet.extend_from_slice(body);
+        self.stream
+            .write_all(&packet)
+            .and_then(|_| self.stream.flush())
+            .map_err(|e| format!("Failed to write to broker: {}", e))?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+}
+
+fn push_string(buffer: &mut Vec<u8>, text: &str) {
+    buffer.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(text.as_bytes());
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::io::Write;
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// A socket that accepts any number of readers; removed again when dropped
+    pub struct Server {
+        path: PathBuf,
+        clients: Arc<Mutex<Vec<UnixStream>>>,
+        stop: Arc<AtomicBool>,
+    }
+
+    impl Server {
+        pub fn listen(path: &Path) -> Result<Server, String> {
+            // A socket left behind by a previous run is replaced; anything else is not touched
+            if let Ok(metadata) = std::fs::symlink_metadata(path) {
+                if !metadata.file_type().is_socket() {
+                    return Err(format!("Invalid socket path: {} exists", path.display()));
+                }
+                let _ = std::fs::remove_file(path);
+            }
+            let listener =
+                UnixListener::bind(path).map_err(|e| format!("Failed to create socket: {}", e))?;
+            // Only this user may read workspace activity
+            let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+
+            let clients: Arc<Mutex<Vec<UnixStream>>> = Arc::default();
+            let stop = Arc::new(AtomicBool::new(false));
+            let (accepted, stopped) = (clients.clone(), stop.clone());
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                    if let Ok(mut clients) = accepted.lock() {
+                        clients.push(stream);
+                    }
+                }
+            });
+            Ok(Server {
+                path: path.to_path_buf(),
+                clients,
+                stop,
+            })
+        }
+
+        /// Write a line to every reader, dropping those that went away or stopped reading
+        pub fn broadcast(&self, message: &str) {
+            if let Ok(mut clients) = self.clients.lock() {
+                clients.retain_mut(|client| writeln!(client, "{}", message).is_ok());
+            }
+        }
+    }
+
+    impl Drop for Server {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            // Wake the accept loop so it sees the flag
+            let _ = UnixStream::connect(&self.path);
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// The event bridge of a workspace and whether it is connected
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_event_bridge(app: AppHandle, workspace: String) -> Result<BridgeStatus, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let config: Config = workspace::read_config(&root, CONFIG)?;
+    let bridge = app.state::<EventBridge>();
+    let health = bridge
+        .health
+        .lock()
+        .map_err(|e| format!("Failed to read event bridge state: {}", e))?;
+    let connected = health.connected.as_ref() == Some(&root);
+    Ok(BridgeStatus {
+        connected,
+        last_error: config
+            .bridge
+            .as_ref()
+            .and(health.last_error.clone())
+            .filter(|_| !connected),
+        settings: config.bridge,
+    })
+}
+
+/// Set or clear (`settings: None`) the event bridge of a workspace; it reconnects at once
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_event_bridge(
+    app: AppHandle,
+    workspace: String,
+    settings: Option<BridgeSettings>,
+) -> Result<(), Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    match settings.as_ref().map(|settings| &settings.target) {
+        Some(BridgeTarget::Mqtt { host, .. }) if host.trim().is_empty() => {
+            return Err("Invalid event bridge: a broker host is required".into());
+        }
+        Some(BridgeTarget::Socket { path }) if !Path::new(path).is_absolute() => {
+            return Err("Invalid event bridge: the socket path must be absolute".into());
+        }
+        #[cfg(not(unix))]
+        Some(BridgeTarget::Socket { .. }) => {
+            return Err("Unix domain sockets are not supported on this platform".into());
+        }
+        _ => {}
+    }
+    workspace::write_config(&root, CONFIG, &Config { bridge: settings })?;
+    let bridge = app.state::<EventBridge>();
+    if let Ok(sender) = bridge.sender.lock() {
+        if let Some(sender) = sender.as_ref() {
+            let _ = sender.send(Outgoing::Reload);
+        }
+    }
+    Ok(())
+}
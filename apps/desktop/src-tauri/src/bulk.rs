@@ -0,0 +1,260 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::confirm::{Confirmation, Confirmations};
+use crate::document;
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{paths, read_only, workspace};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+
+/// Characters of context shown around the first match in a replace preview
+const PREVIEW_RADIUS: usize = 40;
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletePreview {
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(serde::Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteReport {
+    pub deleted: usize,
+    pub failed: Vec<BulkFailure>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceMatch {
+    /// Workspace-relative document path
+    pub path: String,
+    pub count: usize,
+    /// Text around the first match
+    pub preview: String,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplacePreview {
+    pub documents: Vec<ReplaceMatch>,
+    pub total: usize,
+}
+
+#[derive(serde::Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceReport {
+    pub documents: usize,
+    pub replacements: usize,
+    pub failed: Vec<BulkFailure>,
+}
+
+/// What a delete token is issued for; the order files were passed in does not matter
+fn delete_operation(paths: &[String]) -> String {
+    let mut paths = paths.to_vec();
+    paths.sort();
+    paths.dedup();
+    format!("delete\n{}", paths.join("\n"))
+}
+
+fn replace_operation(workspace: &str, find: &str, replace: &str) -> String {
+    format!("replace\n{}\n{}\n{}", workspace, find, replace)
+}
+
+fn check_files(app: &AppHandle, paths: &[String]) -> Result<(), Error> {
+    for path in paths {
+        paths::check(app, path, paths::Scope::Write)?;
+        let file = Path::new(path);
+        if !file.exists() {
+            return Err(Error::not_found("File does not exist").with_context(path));
+        }
+        if file.is_dir() {
+            return Err(Error::invalid_path("Path is a directory, not a file").with_context(path));
+        }
+    }
+    Ok(())
+}
+
+/// First phase of [`delete_files`]: what would be deleted, plus the token that deletes it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn preview_delete_files(
+    app: AppHandle,
+    confirmations: State<'_, Confirmations>,
+    paths: Vec<String>,
+) -> Result<Confirmation<DeletePreview>, Error> {
+    check_files(&app, &paths)?;
+    let bytes = paths
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    let operation = delete_operation(&paths);
+    Ok(confirmations.issue(
+        operation,
+        DeletePreview {
+            files: paths,
+            bytes,
+        },
+    ))
+}
+
+/// Delete several files at once, given the token from [`preview_delete_files`] for the same
+/// files
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_files(
+    app: AppHandle,
+    confirmations: State<'_, Confirmations>,
+    paths: Vec<String>,
+    token: String,
+) -> Result<DeleteReport, Error> {
+    // Validated first so a bad path does not use up the token
+    check_files(&app, &paths)?;
+    confirmations.redeem(&token, &delete_operation(&paths))?;
+    let mut report = DeleteReport::default();
+    for path in paths {
+        let file = Path::new(&path);
+        match fs::remove_file(file) {
+            Ok(()) => {
+                events::file_changed(&app, file, ChangeKind::Deleted);
+                audit::record_current(&app, AuditAction::Delete, AuditSource::Ui, &[file]);
+                report.deleted += 1;
+            }
+            Err(e) => report.failed.push(BulkFailure {
+                path,
+                error: format!("Failed to delete file: {}", e),
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// Documents with text blocks containing `find`, with the number of matches in each
+fn find_matches(root: &Path, find: &str) -> Result<Vec<(PathBuf, ReplaceMatch)>, String> {
+    let mut matches = Vec::new();
+    for path in workspace::list_documents(root)? {
+        let Ok(board) = document::read_board(&path) else {
+            continue;
+        };
+        let texts: Vec<String> = board
+            .pages()
+            .iter()
+            .flat_map(|page| board.page_shapes(page))
+            .filter_map(|shape| shape.text().map(str::to_string))
+            .collect();
+        let count = texts.iter().map(|text| text.matches(find).count()).sum();
+        let Some(preview) = texts.iter().find_map(|text| surrounding(text, find)) else {
+            continue;
+        };
+        matches.push((
+            path.clone(),
+            ReplaceMatch {
+                path: workspace::relative_path(root, &path),
+                count,
+                preview,
+            },
+        ));
+    }
+    Ok(matches)
+}
+
+fn surrounding(text: &str, find: &str) -> Option<String> {
+    let at = text.find(find)?;
+    let before: String = text[..at]
+        .chars()
+        .rev()
+        .take(PREVIEW_RADIUS)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = text[at + find.len()..]
+        .chars()
+        .take(PREVIEW_RADIUS)
+        .collect();
+    let preview = format!("{}{}{}", before, find, after);
+    Some(preview.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// First phase of [`replace_in_workspace`]: the documents whose text blocks contain `find`,
+/// plus the token that replaces it. Matching is exact and case-sensitive.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn preview_replace_in_workspace(
+    app: AppHandle,
+    confirmations: State<'_, Confirmations>,
+    workspace: String,
+    find: String,
+    replace: String,
+) -> Result<Confirmation<ReplacePreview>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    if find.is_empty() {
+        return Err("Invalid search text: empty".into());
+    }
+    let documents: Vec<ReplaceMatch> = find_matches(&root, &find)?
+        .into_iter()
+        .map(|(_, found)| found)
+        .collect();
+    let total = documents.iter().map(|found| found.count).sum();
+    Ok(confirmations.issue(
+        replace_operation(&workspace, &find, &replace),
+        ReplacePreview { documents, total },
+    ))
+}
+
+/// Replace `find` in the text blocks of every document, given the token from
+/// [`preview_replace_in_workspace`] for the same arguments
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn replace_in_workspace(
+    app: AppHandle,
+    confirmations: State<'_, Confirmations>,
+    workspace: String,
+    find: String,
+    replace: String,
+    token: String,
+) -> Result<ReplaceReport, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    confirmations.redeem(&token, &replace_operation(&workspace, &find, &replace))?;
+    let mut report = ReplaceReport::default();
+    for (path, found) in find_matches(&root, &find)? {
+        let result = document::read_board(&path).and_then(|mut board| {
+            for shape in board
+                .pages()
+                .iter()
+                .flat_map(|page| board.page_shapes(page))
+                .collect::<Vec<_>>()
+            {
+                if let Some(text) = shape.text().filter(|text| text.contains(find.as_str())) {
+                    let updated = text.replace(find.as_str(), &replace);
+                    board.set_text(&shape.id, &updated);
+                }
+            }
+            board.board.updated_at = document::now_millis();
+            document::write_board(&path, &board)
+        });
+        match result {
+            Ok(()) => {
+                events::file_changed(&app, &path, ChangeKind::Modified);
+                audit::record(&root, AuditAction::Overwrite, AuditSource::Ui, &[&path]);
+                report.documents += 1;
+                report.replacements += found.count;
+            }
+            Err(error) => report.failed.push(BulkFailure {
+                path: found.path,
+                error,
+            }),
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,864 @@
+use crate::document;
+use crate::error::Error;
+use crate::{deep_link, http, messages, paths, reminders, workspace};
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+use url::Url;
+
+const FEEDS_CONFIG: &str = "calendars";
+const EVENTS_CONFIG: &str = "calendar-events";
+const TICK: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// How often subscribed feeds are fetched again
+const REFRESH_MILLIS: i64 = 30 * 60 * 1000;
+/// Occurrences a recurring event is expanded to at most, so a broken rule cannot spin
+const MAX_OCCURRENCES: usize = 5000;
+/// Length given to reminders in the exported feed, which are points in time
+const REMINDER_MINUTES: i64 = 15;
+
+/// A read-only ICS subscription
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarFeed {
+    pub id: String,
+    pub name: String,
+    /// `https:`, `http:` or `webcal:` URL of the feed
+    pub url: String,
+    #[serde(default)]
+    pub last_fetched: Option<i64>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub event_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Feeds {
+    feeds: Vec<CalendarFeed>,
+}
+
+/// Events of every feed as last fetched, kept in `.inkfinite/calendar-events.json`
+#[derive(Serialize, Deserialize, Default)]
+struct EventCache {
+    feeds: BTreeMap<String, Vec<CalendarEvent>>,
+}
+
+/// A `VEVENT` as parsed, before recurrence is expanded. Times are in milliseconds since the
+/// epoch; all-day events start at local midnight.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct CalendarEvent {
+    uid: String,
+    summary: String,
+    location: Option<String>,
+    description: Option<String>,
+    start: i64,
+    end: i64,
+    all_day: bool,
+    rrule: Option<String>,
+    exdates: Vec<i64>,
+    /// Set on an event that replaces one occurrence of the recurring event with the same uid
+    recurrence_id: Option<i64>,
+}
+
+/// One occurrence of a subscribed event
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarOccurrence {
+    pub feed: String,
+    pub feed_name: String,
+    pub uid: String,
+    pub title: String,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub start: i64,
+    pub end: i64,
+    pub all_day: bool,
+}
+
+/// A day's events for the daily note, as data and as a Markdown list ready to insert
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyAgenda {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub events: Vec<CalendarOccurrence>,
+    pub markdown: String,
+}
+
+/// Join folded lines (RFC 5545 section 3.1)
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Property parameters, such as `TZID`, with upper-cased names
+type Params = Vec<(String, String)>;
+
+/// Split a content line into its upper-cased name, parameters and value
+fn property(line: &str) -> Option<(String, Params, &str)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+    let mut parts = line[..colon].split(';');
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    Some((name, params, &line[colon + 1..]))
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(other) => text.push(other),
+            None => {}
+        }
+    }
+    text
+}
+
+fn local_millis(time: NaiveDateTime) -> Option<i64> {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|time| time.timestamp_millis())
+}
+
+/// A `DATE` or `DATE-TIME` value as milliseconds, and whether it was a date.
+///
+/// Times with a `TZID` are read as local time; feeds are almost always in the viewer's zone,
+/// and the app carries no time zone database.
+fn parse_time(value: &str, params: &[(String, String)]) -> Option<(i64, bool)> {
+    let value = value.trim();
+    let is_date = params
+        .iter()
+        .any(|(key, value)| key == "VALUE" && value.eq_ignore_ascii_case("DATE"))
+        || value.len() == 8;
+    if is_date {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return local_millis(date.and_hms_opt(0, 0, 0)?).map(|millis| (millis, true));
+    }
+    match value.strip_suffix('Z') {
+        Some(utc) => {
+            let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            Some((Utc.from_utc_datetime(&time).timestamp_millis(), false))
+        }
+        None => {
+            let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+            local_millis(time).map(|millis| (millis, false))
+        }
+    }
+}
+
+/// A `DURATION` value such as `PT1H30M` or `-P1D`, in milliseconds
+fn parse_duration(value: &str) -> Option<i64> {
+    let (sign, rest) = match value.trim().strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.trim().trim_start_matches('+')),
+    };
+    let rest = rest.strip_prefix('P')?;
+    let mut total = 0i64;
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let amount: i64 = number.parse().ok()?;
+                number.clear();
+                total += amount
+                    * match unit {
+                        'W' => 7 * 86_400_000,
+                        'D' => 86_400_000,
+                        'H' => 3_600_000,
+                        'M' => 60_000,
+                        'S' => 1000,
+                        _ => return None,
+                    };
+            }
+        }
+    }
+    Some(sign * total)
+}
+
+/// Events of an ICS document; anything malformed is skipped rather than failing the feed
+fn parse(text: &str) -> Vec<CalendarEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<CalendarEvent> = None;
+    let mut duration = None;
+    let mut has_end = false;
+    // Components nested in the event, such as alarms, whose properties are not the event's
+    let mut nested = 0usize;
+
+    for line in unfold(text) {
+        let Some((name, params, value)) = property(&line) else {
+            continue;
+        };
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some(CalendarEvent::default());
+                duration = None;
+                has_end = false;
+                nested = 0;
+            }
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) => {
+                let Some(mut event) = current.take() else {
+                    continue;
+                };
+                if event.start == 0 {
+                    continue;
+                }
+                if !has_end {
+                    event.end = event.start
+                        + duration.unwrap_or(if event.all_day { 86_400_000 } else { 0 });
+                }
+                events.push(event);
+            }
+            (_, Some(_)) if nested > 0 => {}
+            ("UID", Some(event)) => event.uid = value.to_string(),
+            ("SUMMARY", Some(event)) => event.summary = unescape(value),
+            ("LOCATION", Some(event)) => event.location = Some(unescape(value)),
+            ("DESCRIPTION", Some(event)) => event.description = Some(unescape(value)),
+            ("DTSTART", Some(event)) => {
+                if let Some((start, all_day)) = parse_time(value, &params) {
+                    event.start = start;
+                    event.all_day = all_day;
+                }
+            }
+            ("DTEND", Some(event)) => {
+                if let Some((end, _)) = parse_time(value, &params) {
+                    event.end = end;
+                    has_end = true;
+                }
+            }
+            ("DURATION", Some(_)) => duration = parse_duration(value),
+            ("RRULE", Some(event)) => event.rrule = Some(value.to_string()),
+            ("EXDATE", Some(event)) => event.exdates.extend(
+                value
+                    .split(',')
+                    .filter_map(|time| parse_time(time, &params))
+                    .map(|(time, _)| time),
+            ),
+            ("RECURRENCE-ID", Some(event)) => {
+                event.recurrence_id = parse_time(value, &params).map(|(time, _)| time)
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Start times of a recurring event's occurrences from `start` until `to` (RFC 5545 section
+/// 3.3.10), for `FREQ`, `INTERVAL`, `COUNT`, `UNTIL` and weekly `BYDAY`. Recurrence is
+/// expanded in local time so occurrences keep their wall-clock time across daylight saving.
+fn expand(start: i64, rule: &str, to: i64) -> Vec<i64> {
+    let mut freq = "";
+    let mut interval = 1u32;
+    let mut count = None;
+    let mut until = None;
+    let mut days: Vec<Weekday> = Vec::new();
+    for part in rule.split(';') {
+        let Some((key, value)) = part.split_once('=') else {
+            continue;
+        };
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => freq = value,
+            "INTERVAL" => interval = value.parse().unwrap_or(1).max(1),
+            "COUNT" => count = value.parse::<usize>().ok(),
+            "UNTIL" => until = parse_time(value, &[]).map(|(time, _)| time),
+            "BYDAY" => {
+                days = value
+                    .split(',')
+                    .filter_map(|day| {
+                        // Ordinals like `1MO` only narrow monthly rules, which are not expanded by day
+                        match day.trim_start_matches(['+', '-', '0', '1', '2', '3', '4', '5']) {
+                            "MO" => Some(Weekday::Mon),
+                            "TU" => Some(Weekday::Tue),
+                            "WE" => Some(Weekday::Wed),
+                            "TH" => Some(Weekday::Thu),
+                            "FR" => Some(Weekday::Fri),
+                            "SA" => Some(Weekday::Sat),
+                            "SU" => Some(Weekday::Sun),
+                            _ => None,
+                        }
+                    })
+                    .collect();
+                days.sort_by_key(|day| day.num_days_from_monday());
+            }
+            _ => {}
+        }
+    }
+    let Some(first) = Local.timestamp_millis_opt(start).single() else {
+        return Vec::new();
+    };
+    let first = first.naive_local();
+    let end = until.map_or(to, |until| until.saturating_add(1).min(to));
+    let limit = count.unwrap_or(MAX_OCCURRENCES).min(MAX_OCCURRENCES);
+
+    let mut starts = Vec::new();
+    let mut produced = 0;
+    for period in 0..MAX_OCCURRENCES as u32 {
+        let step = period * interval;
+        let candidates: Vec<NaiveDateTime> = match freq {
+            "DAILY" => vec![first + Duration::days(i64::from(step))],
+            "WEEKLY" if !days.is_empty() => {
+                let monday = first.date()
+                    - Duration::days(i64::from(first.weekday().num_days_from_monday()))
+                    + Duration::weeks(i64::from(step));
+                days.iter()
+                    .map(|day| {
+                        (monday + Duration::days(i64::from(day.num_days_from_monday())))
+                            .and_time(first.time())
+                    })
+                    .filter(|time| *time >= first)
+                    .collect()
+            }
+            "WEEKLY" => vec![first + Duration::weeks(i64::from(step))],
+            "MONTHLY" => {
+                let months = first.month0() + step;
+                let year = first.year() + (months / 12) as i32;
+                // Months without the day, such as February 30th, are skipped
+                NaiveDate::from_ymd_opt(year, months % 12 + 1, first.day())
+                    .map(|date| vec![date.and_time(first.time())])
+                    .unwrap_or_default()
+            }
+            "YEARLY" => {
+                NaiveDate::from_ymd_opt(first.year() + step as i32, first.month(), first.day())
+                    .map(|date| vec![date.and_time(first.time())])
+                    .unwrap_or_default()
+            }
+            _ => return vec![start],
+        };
+        for candidate in candidates {
+            let Some(millis) = local_millis(candidate) else {
+                continue;
+            };
+            if millis >= end || produced >= limit {
+                return starts;
+            }
+            produced += 1;
+            starts.push(millis);
+        }
+    }
+    starts
+}
+
+/// Occurrences of `events` overlapping `from..to`
+fn occurrences(
+    feed: &CalendarFeed,
+    events: &[CalendarEvent],
+    from: i64,
+    to: i64,
+) -> Vec<CalendarOccurrence> {
+    let overridden: HashSet<(&str, i64)> = events
+        .iter()
+        .filter_map(|event| Some((event.uid.as_str(), event.recurrence_id?)))
+        .collect();
+    let mut found = Vec::new();
+    for event in events {
+        let length = event.end - event.start;
+        let starts = match (&event.rrule, event.recurrence_id) {
+            (Some(rule), None) => expand(event.start, rule, to),
+            _ => vec![event.start],
+        };
+        for start in starts {
+            let end = start + length;
+            let overlaps = start < to && (end > from || (length == 0 && start >= from));
+            let skipped = event.recurrence_id.is_none()
+                && (event.exdates.contains(&start)
+                    || overridden.contains(&(event.uid.as_str(), start)));
+            if overlaps && !skipped {
+                found.push(CalendarOccurrence {
+                    feed: feed.id.clone(),
+                    feed_name: feed.name.clone(),
+                    uid: event.uid.clone(),
+                    title: event.summary.clone(),
+                    location: event.location.clone(),
+                    description: event.description.clone(),
+                    start,
+                    end,
+                    all_day: event.all_day,
+                });
+            }
+        }
+    }
+    found
+}
+
+fn events_between(root: &Path, from: i64, to: i64) -> Result<Vec<CalendarOccurrence>, String> {
+    let feeds: Feeds = workspace::read_config(root, FEEDS_CONFIG)?;
+    let cache: EventCache = workspace::read_config(root, EVENTS_CONFIG)?;
+    let mut found: Vec<CalendarOccurrence> = feeds
+        .feeds
+        .iter()
+        .filter_map(|feed| Some((feed, cache.feeds.get(&feed.id)?)))
+        .flat_map(|(feed, events)| occurrences(feed, events, from, to))
+        .collect();
+    found.sort_by_key(|occurrence| (!occurrence.all_day, occurrence.start));
+    Ok(found)
+}
+
+fn fetch(url: &str) -> Result<String, String> {
+    // `webcal:` is only a hint to open a calendar app; the feed itself is served over HTTPS
+    let url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    http::agent()
+        .get(&url)
+        .call()
+        .map_err(|e| format!("Failed to fetch calendar: {}", e))?
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to fetch calendar: {}", e))
+}
+
+/// Fetch every feed of the workspace, or only those older than the refresh interval
+fn refresh(root: &Path, only_stale: bool) -> Result<Vec<CalendarFeed>, String> {
+    let mut feeds: Feeds = workspace::read_config(root, FEEDS_CONFIG)?;
+    let mut cache: EventCache = workspace::read_config(root, EVENTS_CONFIG)?;
+    let now = document::now_millis();
+    let mut changed = false;
+    for feed in &mut feeds.feeds {
+        if only_stale
+            && feed
+                .last_fetched
+                .is_some_and(|at| now - at < REFRESH_MILLIS)
+        {
+            continue;
+        }
+        changed = true;
+        feed.last_fetched = Some(now);
+        match fetch(&feed.url) {
+            Ok(text) => {
+                let events = parse(&text);
+                feed.event_count = events.len();
+                feed.last_error = None;
+                cache.feeds.insert(feed.id.clone(), events);
+            }
+            // The events from the last successful fetch stay until the feed recovers
+            Err(error) => feed.last_error = Some(error),
+        }
+    }
+    if changed {
+        cache
+            .feeds
+            .retain(|id, _| feeds.feeds.iter().any(|feed| &feed.id == id));
+        workspace::write_config(root, EVENTS_CONFIG, &cache)?;
+        workspace::write_config(root, FEEDS_CONFIG, &feeds)?;
+    }
+    Ok(feeds.feeds)
+}
+
+/// Refresh the current workspace's feeds in the background, emitting `calendar:updated`
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        if let Some(root) = workspace::current_root(&app) {
+            match refresh(&root, true) {
+                Ok(feeds) if feeds.iter().any(|feed| feed.last_fetched.is_some()) => {
+                    let _ = app.emit("calendar:updated", &feeds);
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    workspace::append_log(&root, FEEDS_CONFIG, &format!("failed: {}", error))
+                }
+            }
+        }
+        std::thread::sleep(TICK);
+    });
+}
+
+/// Escape text for an ICS value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Append a content line, folded at 75 octets without splitting a character
+fn push_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+fn utc_stamp(millis: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// The workspace's pending reminders as an ICS calendar, each with an alarm when it is due
+pub fn reminder_feed(root: &Path) -> Result<String, String> {
+    let mut pending = reminders::pending_in(root)?;
+    pending.sort_by_key(|reminder| reminder.due_at);
+    let stamp = utc_stamp(document::now_millis());
+    let mut ics = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Inkfinite//Reminders//EN",
+        "CALSCALE:GREGORIAN",
+        "X-WR-CALNAME:Inkfinite reminders",
+        "REFRESH-INTERVAL;VALUE=DURATION:PT1H",
+    ] {
+        push_line(&mut ics, line);
+    }
+    for reminder in &pending {
+        let doc = match &reminder.shape_id {
+            Some(shape_id) => reminder
+                .id
+                .strip_suffix(&format!(":{}", shape_id))
+                .unwrap_or(&reminder.id),
+            None => &reminder.id,
+        };
+        let name = document::document_stem(Path::new(&reminder.path));
+        let link = Url::parse_with_params(&format!("{}://open", deep_link::SCHEME), [("doc", doc)])
+            .map(|url| url.to_string())
+            .unwrap_or_default();
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(&mut ics, &format!("UID:{}@inkfinite", escape(&reminder.id)));
+        push_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut ics, &format!("DTSTART:{}", utc_stamp(reminder.due_at)));
+        push_line(&mut ics, &format!("DURATION:PT{}M", REMINDER_MINUTES));
+        push_line(&mut ics, &format!("SUMMARY:{}", escape(&reminder.title)));
+        push_line(&mut ics, &format!("DESCRIPTION:{}", escape(&name)));
+        push_line(&mut ics, &format!("URL:{}", link));
+        push_line(&mut ics, "BEGIN:VALARM");
+        push_line(&mut ics, "ACTION:DISPLAY");
+        push_line(
+            &mut ics,
+            &format!("DESCRIPTION:{}", escape(&reminder.title)),
+        );
+        push_line(&mut ics, "TRIGGER:PT0M");
+        push_line(&mut ics, "END:VALARM");
+        push_line(&mut ics, "END:VEVENT");
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    Ok(ics)
+}
+
+fn agenda_markdown(events: &[CalendarOccurrence]) -> String {
+    if events.is_empty() {
+        return String::new();
+    }
+    let time = |millis: i64| {
+        Local
+            .timestamp_millis_opt(millis)
+            .single()
+            .map(|time| time.format("%H:%M").to_string())
+            .unwrap_or_default()
+    };
+    let mut markdown = format!("## {}\n\n", messages::text("agenda-heading"));
+    let untitled = messages::text("agenda-untitled");
+    for event in events {
+        let when = if event.all_day {
+            messages::text("agenda-all-day")
+        } else if event.end > event.start {
+            format!("{}–{}", time(event.start), time(event.end))
+        } else {
+            time(event.start)
+        };
+        let title = if event.title.trim().is_empty() {
+            untitled.as_str()
+        } else {
+            event.title.trim()
+        };
+        markdown.push_str(&format!("- {} {}", when, title));
+        if let Some(location) = event.location.as_deref().filter(|l| !l.trim().is_empty()) {
+            markdown.push_str(&format!(" ({})", location.trim()));
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// Read-only ICS feeds the workspace subscribes to
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_calendar_feeds(app: AppHandle, workspace: String) -> Result<Vec<CalendarFeed>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let feeds: Feeds = workspace::read_config(&root, FEEDS_CONFIG)?;
+    Ok(feeds.feeds)
+}
+
+/// Subscribe to an ICS feed; its events are fetched on the next refresh
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn add_calendar_feed(
+    app: AppHandle,
+    workspace: String,
+    name: String,
+    url: String,
+) -> Result<CalendarFeed, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid calendar URL: {}", e))?;
+    if !matches!(parsed.scheme(), "https" | "http" | "webcal") {
+        return Err(format!(
+            "Invalid calendar URL: unsupported scheme {}",
+            parsed.scheme()
+        )
+        .into());
+    }
+    let mut feeds: Feeds = workspace::read_config(&root, FEEDS_CONFIG)?;
+    let feed = CalendarFeed {
+        id: document::create_id("calendar"),
+        name: if name.trim().is_empty() {
+            parsed.host_str().unwrap_or("Calendar").to_string()
+        } else {
+            name.trim().to_string()
+        },
+        url: parsed.to_string(),
+        last_fetched: None,
+        last_error: None,
+        event_count: 0,
+    };
+    feeds.feeds.push(feed.clone());
+    workspace::write_config(&root, FEEDS_CONFIG, &feeds)?;
+    Ok(feed)
+}
+
+/// Unsubscribe from a feed and drop its events; `false` when there was no such feed
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn remove_calendar_feed(app: AppHandle, workspace: String, id: String) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let mut feeds: Feeds = workspace::read_config(&root, FEEDS_CONFIG)?;
+    let before = feeds.feeds.len();
+    feeds.feeds.retain(|feed| feed.id != id);
+    if feeds.feeds.len() == before {
+        return Ok(false);
+    }
+    let mut cache: EventCache = workspace::read_config(&root, EVENTS_CONFIG)?;
+    cache.feeds.remove(&id);
+    workspace::write_config(&root, EVENTS_CONFIG, &cache)?;
+    workspace::write_config(&root, FEEDS_CONFIG, &feeds)?;
+    Ok(true)
+}
+
+/// Fetch every feed now, returning each with its fetch result
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn refresh_calendars(
+    app: AppHandle,
+    workspace: String,
+) -> Result<Vec<CalendarFeed>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(
+        tauri::async_runtime::spawn_blocking(move || refresh(&root, false))
+            .await
+            .map_err(|e| format!("Calendar task failed: {}", e))??,
+    )
+}
+
+/// Occurrences of subscribed events overlapping `from..to` (milliseconds since the epoch),
+/// all-day events first
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_calendar_events(
+    app: AppHandle,
+    workspace: String,
+    from: i64,
+    to: i64,
+) -> Result<Vec<CalendarOccurrence>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(events_between(&root, from, to)?)
+}
+
+/// Events on `date` (`YYYY-MM-DD`, today when unset) for the daily note
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_daily_agenda(
+    app: AppHandle,
+    workspace: String,
+    date: Option<String>,
+) -> Result<DailyAgenda, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let day = match &date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date: {}", e))?,
+        None => Local::now().date_naive(),
+    };
+    let bound = |day: NaiveDate| {
+        day.and_hms_opt(0, 0, 0)
+            .and_then(local_millis)
+            .ok_or_else(|| format!("Invalid date: {}", day))
+    };
+    let from = bound(day)?;
+    let to = bound(day + Duration::days(1))?;
+    let events = events_between(&root, from, to)?;
+    Ok(DailyAgenda {
+        date: day.format("%Y-%m-%d").to_string(),
+        markdown: agenda_markdown(&events),
+        events,
+    })
+}
+
+/// Write the workspace's pending reminders as an ICS file at `destination`, for calendar apps
+/// that subscribe to files. The local API serves the same feed at `/v1/reminders.ics`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn export_reminder_calendar(
+    app: AppHandle,
+    workspace: String,
+    destination: String,
+) -> Result<String, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let destination = paths::check(&app, &destination, paths::Scope::Export)?;
+    fs::write(&destination, reminder_feed(&root)?)
+        .map_err(|e| format!("Failed to write calendar: {}", e))?;
+    Ok(destination.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+        let date = NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        local_millis(date.and_hms_opt(hour, minute, 0).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn folded_lines_are_joined() {
+        let ics = "BEGIN:VEVENT\r\nSUMMARY:Team\r\n  sync\r\nDESCRIPTION:Agenda\r\n\tand notes\\, too\r\n\
+                   DTSTART:20260105T090000\r\nEND:VEVENT\r\n";
+        let events = parse(ics);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Team sync");
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Agendaand notes, too")
+        );
+    }
+
+    #[test]
+    fn tzid_times_are_local_and_dates_are_all_day() {
+        let ics = "BEGIN:VEVENT\nUID:a\nDTSTART;TZID=\"Europe/Paris\":20260105T093000\n\
+                   DTEND;TZID=Europe/Paris:20260105T103000\nEND:VEVENT\n\
+                   BEGIN:VEVENT\nUID:b\nDTSTART;VALUE=DATE:20260106\nEND:VEVENT\n\
+                   BEGIN:VEVENT\nUID:c\nDTSTART:20260107T120000Z\nDURATION:PT1H30M\nEND:VEVENT\n";
+        let events = parse(ics);
+        assert_eq!(events.len(), 3);
+
+        assert_eq!(events[0].start, at(2026, 1, 5, 9, 30));
+        assert_eq!(events[0].end, at(2026, 1, 5, 10, 30));
+        assert!(!events[0].all_day);
+
+        assert!(events[1].all_day);
+        assert_eq!(events[1].start, at(2026, 1, 6, 0, 0));
+        assert_eq!(events[1].end, events[1].start + 86_400_000);
+
+        let utc = Utc.with_ymd_and_hms(2026, 1, 7, 12, 0, 0).unwrap();
+        assert_eq!(events[2].start, utc.timestamp_millis());
+        assert_eq!(events[2].end, events[2].start + 90 * 60_000);
+    }
+
+    #[test]
+    fn recurrence_stops_at_count_until_and_window() {
+        // 2026-01-05 is a Monday
+        let start = at(2026, 1, 5, 9, 0);
+
+        assert_eq!(
+            expand(start, "FREQ=DAILY;COUNT=3", i64::MAX),
+            [
+                at(2026, 1, 5, 9, 0),
+                at(2026, 1, 6, 9, 0),
+                at(2026, 1, 7, 9, 0)
+            ]
+        );
+        // UNTIL includes an occurrence that starts exactly at it
+        assert_eq!(
+            expand(start, "FREQ=DAILY;UNTIL=20260107T090000", i64::MAX).len(),
+            3
+        );
+        assert_eq!(expand(start, "FREQ=DAILY", at(2026, 1, 8, 0, 0)).len(), 3);
+        assert_eq!(
+            expand(start, "FREQ=WEEKLY;BYDAY=WE,MO;COUNT=4", i64::MAX),
+            [
+                at(2026, 1, 5, 9, 0),
+                at(2026, 1, 7, 9, 0),
+                at(2026, 1, 12, 9, 0),
+                at(2026, 1, 14, 9, 0)
+            ]
+        );
+        assert_eq!(
+            expand(at(2026, 1, 31, 9, 0), "FREQ=MONTHLY;COUNT=3", i64::MAX),
+            [
+                at(2026, 1, 31, 9, 0),
+                at(2026, 3, 31, 9, 0),
+                at(2026, 5, 31, 9, 0)
+            ]
+        );
+        assert_eq!(expand(start, "FREQ=DAILY", i64::MAX).len(), MAX_OCCURRENCES);
+        assert_eq!(
+            expand(start, "FREQ=DAILY;COUNT=999999", i64::MAX).len(),
+            MAX_OCCURRENCES
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_skipped() {
+        let ics = "not a calendar\n\
+                   BEGIN:VEVENT\nUID:no-start\nSUMMARY:Missing start\nEND:VEVENT\n\
+                   BEGIN:VEVENT\nUID:bad-start\nDTSTART:yesterday\nEND:VEVENT\n\
+                   BEGIN:VEVENT\nUID:alarm\nDTSTART:20260105T090000\n\
+                   BEGIN:VALARM\nSUMMARY:Alarm text\nEND:VALARM\nSUMMARY:Kept\nEND:VEVENT\n\
+                   BEGIN:VEVENT\nUID:unterminated\nDTSTART:20260106T090000\n";
+        let events = parse(ics);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].uid, "alarm");
+        assert_eq!(events[0].summary, "Kept");
+
+        assert_eq!(parse_duration("PT1H30M"), Some(90 * 60_000));
+        assert_eq!(parse_duration("-P1D"), Some(-86_400_000));
+        assert_eq!(parse_duration("1H"), None);
+        assert_eq!(parse_duration("PT1X"), None);
+        assert_eq!(expand(0, "FREQ=SECONDLY", i64::MAX), [0]);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::error::Error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+pub use inkfinite_core::cancel::{CancelToken, CANCELLED};
+
+/// Managed state holding the tokens of running operations by the id the frontend gave them
+#[derive(Default)]
+pub struct Operations(Mutex<HashMap<String, CancelToken>>);
+
+/// Keeps an operation cancellable through [`cancel_operation`] until dropped
+pub struct Operation {
+    app: AppHandle,
+    id: Option<String>,
+    token: CancelToken,
+}
+
+impl Operation {
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            if let Ok(mut operations) = self.app.state::<Operations>().0.lock() {
+                operations.remove(id);
+            }
+        }
+    }
+}
+
+/// Register work under `op_id` so it can be cancelled; without an id it cannot be
+pub fn begin(app: &AppHandle, op_id: Option<String>) -> Operation {
+    let token = CancelToken::default();
+    if let Some(id) = &op_id {
+        if let Ok(mut operations) = app.state::<Operations>().0.lock() {
+            operations.insert(id.clone(), token.clone());
+        }
+    }
+    Operation {
+        app: app.clone(),
+        id: op_id,
+        token,
+    }
+}
+
+/// Ask the operation started with `op_id` to stop; it fails with a `cancelled` error.
+///
+/// Returns `false` when no such operation is running, such as when it already finished.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn cancel_operation(app: AppHandle, op_id: String) -> Result<bool, Error> {
+    let operations = app.state::<Operations>();
+    let operations = operations
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to read operations: {}", e))?;
+    Ok(match operations.get(&op_id) {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    })
+}
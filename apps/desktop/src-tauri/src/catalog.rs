@@ -0,0 +1,764 @@
+use crate::document::{self, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::outline;
+use crate::paths;
+use crate::safe_mode;
+use crate::tasks;
+use crate::vault;
+use crate::workspace;
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+const CATALOG_DIR: &str = "catalog";
+/// Bumped when the tables change; older caches are dropped and rebuilt
+const SCHEMA_VERSION: i64 = 5;
+const SCHEMA: &str = "
+    CREATE TABLE documents (
+        path TEXT PRIMARY KEY,
+        id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        word_count INTEGER NOT NULL,
+        created INTEGER NOT NULL
+    );
+    CREATE TABLE tags (
+        path TEXT NOT NULL REFERENCES documents(path) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (path, tag)
+    );
+    CREATE TABLE links (
+        path TEXT NOT NULL REFERENCES documents(path) ON DELETE CASCADE,
+        target TEXT NOT NULL,
+        PRIMARY KEY (path, target)
+    );
+    CREATE TABLE aliases (
+        path TEXT NOT NULL REFERENCES documents(path) ON DELETE CASCADE,
+        alias TEXT NOT NULL,
+        PRIMARY KEY (path, alias)
+    );
+    CREATE TABLE outlines (
+        path TEXT PRIMARY KEY REFERENCES documents(path) ON DELETE CASCADE,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        headings TEXT NOT NULL
+    );
+    CREATE TABLE tasks (
+        path TEXT NOT NULL REFERENCES documents(path) ON DELETE CASCADE,
+        block_id TEXT NOT NULL,
+        line INTEGER NOT NULL,
+        page_id TEXT NOT NULL,
+        text TEXT NOT NULL,
+        done INTEGER NOT NULL,
+        due TEXT,
+        priority INTEGER,
+        tags TEXT NOT NULL,
+        PRIMARY KEY (path, block_id, line)
+    );
+    CREATE INDEX tags_by_tag ON tags(tag);
+    CREATE INDEX links_by_target ON links(target);
+    CREATE INDEX tasks_by_due ON tasks(done, due);
+";
+/// Tables that outlive a schema change or a rebuild, since they cannot be recreated from the
+/// files
+const HISTORY_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS writing (
+        day TEXT NOT NULL,
+        path TEXT NOT NULL,
+        added INTEGER NOT NULL,
+        removed INTEGER NOT NULL,
+        PRIMARY KEY (day, path)
+    );
+    CREATE TABLE IF NOT EXISTS focus_sessions (
+        id TEXT PRIMARY KEY,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER NOT NULL,
+        path TEXT,
+        work_minutes INTEGER NOT NULL,
+        intervals INTEGER NOT NULL,
+        focused_ms INTEGER NOT NULL,
+        words INTEGER,
+        completed INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS opens (
+        id TEXT PRIMARY KEY,
+        last_opened INTEGER NOT NULL,
+        count INTEGER NOT NULL
+    );
+";
+
+pub type Database = Arc<Mutex<Connection>>;
+
+/// Managed state holding an open metadata cache per workspace.
+///
+/// The cache answers listings, filters and stats without walking the workspace. It is kept
+/// current by the backend's change notifications, and reconciled with the disk in the
+/// background the first time a workspace is used in a session.
+#[derive(Default)]
+pub struct Catalog(Mutex<HashMap<PathBuf, Database>>);
+
+/// A document as recorded in the cache
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogEntry {
+    /// Workspace-relative path
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    /// Modification time in epoch milliseconds
+    pub modified: i64,
+    pub word_count: u64,
+    pub tags: Vec<String>,
+    /// Targets of `[[wiki links]]` in the document
+    pub links: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub tag: String,
+    pub documents: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceStats {
+    pub documents: u64,
+    pub words: u64,
+    pub bytes: u64,
+    /// Most recent document change in epoch milliseconds
+    pub last_modified: Option<i64>,
+    /// Most used first
+    pub tags: Vec<TagCount>,
+}
+
+/// What the cache stores for one document file
+struct Indexed {
+    path: String,
+    id: String,
+    name: String,
+    size: u64,
+    modified: i64,
+    word_count: u64,
+    /// When the board was created, in epoch milliseconds
+    created: i64,
+    tags: BTreeSet<String>,
+    links: BTreeSet<String>,
+    aliases: BTreeSet<String>,
+    /// The outline, stored as [`outline`] caches it
+    headings: String,
+    tasks: Vec<tasks::BlockTask>,
+}
+
+/// Documents in the workspace from the cache, optionally only those with `tag` or linking to
+/// `links_to`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn list_workspace_documents(
+    app: AppHandle,
+    workspace: String,
+    tag: Option<String>,
+    links_to: Option<String>,
+) -> Result<Vec<CatalogEntry>, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = open(&app, Path::new(&workspace))?;
+        let db = db
+            .lock()
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        Ok(entries(&db, tag.as_deref(), links_to.as_deref())?)
+    })
+    .await
+    .map_err(|e| format!("Listing task failed: {}", e))?
+}
+
+/// Document, word and tag totals for the workspace, from the cache
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn get_workspace_stats(
+    app: AppHandle,
+    workspace: String,
+) -> Result<WorkspaceStats, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = open(&app, Path::new(&workspace))?;
+        let db = db
+            .lock()
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        Ok(stats(&db).map_err(|e| format!("Failed to read metadata cache: {}", e))?)
+    })
+    .await
+    .map_err(|e| format!("Stats task failed: {}", e))?
+}
+
+/// Re-read every document in the workspace into the cache
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn rebuild_workspace_cache(app: AppHandle, workspace: String) -> Result<usize, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let root = PathBuf::from(workspace);
+        let db = open(&app, &root)?;
+        if let Ok(db) = db.lock() {
+            db.execute("DELETE FROM documents", [])
+                .map_err(|e| format!("Failed to clear metadata cache: {}", e))?;
+        }
+        Ok(reconcile(&db, &root)?)
+    })
+    .await
+    .map_err(|e| format!("Rebuild task failed: {}", e))?
+}
+
+/// Workspace-relative paths of cached documents, optionally only those tagged `tag`
+pub fn document_paths(
+    app: &AppHandle,
+    root: &Path,
+    tag: Option<&str>,
+) -> Result<Vec<PathBuf>, String> {
+    let db = open(app, root)?;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    Ok(entries(&db, tag, None)?
+        .into_iter()
+        .map(|entry| root.join(entry.path))
+        .collect())
+}
+
+/// Every cached document with its tags and links
+pub fn documents(app: &AppHandle, root: &Path) -> Result<Vec<CatalogEntry>, String> {
+    let db = open(app, root)?;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    entries(&db, None, None)
+}
+
+/// Tags used in the workspace, most used first
+#[cfg(desktop)]
+pub fn tag_counts(app: &AppHandle, root: &Path) -> Result<Vec<TagCount>, String> {
+    let db = open(app, root)?;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    Ok(stats(&db)
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?
+        .tags)
+}
+
+/// Workspace-relative paths of cached documents without any tag
+#[cfg(desktop)]
+pub fn untagged_paths(app: &AppHandle, root: &Path) -> Result<Vec<String>, String> {
+    let db = open(app, root)?;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let mut statement = db
+        .prepare(
+            "SELECT path FROM documents d
+             WHERE NOT EXISTS (SELECT 1 FROM tags t WHERE t.path = d.path)
+             ORDER BY path",
+        )
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let paths = statement
+        .query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<String>>>())
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    Ok(paths)
+}
+
+/// Update the cache of any open workspace containing `path` after it changed on disk
+pub fn file_changed(app: &AppHandle, path: &Path) {
+    let Some((root, db)) = containing(app, path) else {
+        return;
+    };
+    let path = path.to_path_buf();
+    // Re-reading a large document should not hold up whoever reported the change
+    std::thread::spawn(move || {
+        let result = if path.is_dir() {
+            workspace::list_documents(&path)
+                .and_then(|paths| paths.iter().try_for_each(|path| update(&db, &root, path)))
+        } else {
+            update(&db, &root, &path)
+        };
+        if let Err(error) = result {
+            tracing::warn!(%error, "Failed to update metadata cache");
+        }
+    });
+}
+
+/// State of a workspace's metadata cache
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheHealth {
+    /// Whether SQLite's integrity check passed
+    pub intact: bool,
+    pub cached: usize,
+    pub on_disk: usize,
+}
+
+/// The workspace's cache database, for modules keeping their own tables next to the documents
+pub fn database(app: &AppHandle, root: &Path) -> Result<Database, String> {
+    open(app, root)
+}
+
+/// Rows the cache has written since it was opened, which changes whenever a document is added,
+/// removed, renamed or re-read
+pub fn revision(app: &AppHandle, root: &Path) -> Result<u64, String> {
+    let db = open(app, root)?;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    Ok(db.total_changes())
+}
+
+/// Check the cache's database and compare what it lists with the documents on disk
+pub fn health(app: &AppHandle, root: &Path) -> Result<CacheHealth, String> {
+    let on_disk = workspace::list_documents(root)?.len();
+    let db = open(app, root)?;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let check: String = db
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to check metadata cache: {}", e))?;
+    let cached: i64 = db
+        .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    Ok(CacheHealth {
+        intact: check == "ok",
+        cached: cached as usize,
+        on_disk,
+    })
+}
+
+/// Open the workspace's cache ahead of its first use, reconciling it with the disk
+pub fn prepare(app: &AppHandle, root: &Path) -> Result<(), String> {
+    open(app, root).map(|_| ())
+}
+
+/// Close the workspace's cache; its file stays for the next time the workspace is opened
+pub fn close(app: &AppHandle, root: &Path) {
+    if let Ok(mut open) = app.state::<Catalog>().0.lock() {
+        open.remove(root);
+    }
+}
+
+/// Close every workspace's cache, so each is opened afresh on next use
+pub fn close_all(app: &AppHandle) {
+    if let Ok(mut open) = app.state::<Catalog>().0.lock() {
+        open.clear();
+    }
+}
+
+/// Per-workspace cache file under the app cache dir, named by a hash of the workspace path.
+///
+/// It is kept out of the workspace itself so sync services never copy a live database.
+fn database_path(app: &AppHandle, root: &Path) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))?
+        .join(CATALOG_DIR);
+    let digest = format!("{:x}", Sha256::digest(root.to_string_lossy().as_bytes()));
+    Ok(dir.join(format!("{}.sqlite", &digest[..16])))
+}
+
+/// The workspace's cache, opened and reconciled in the background on first use.
+///
+/// A cache with no documents yet is filled before returning so the first listing is complete.
+fn open(app: &AppHandle, root: &Path) -> Result<Database, String> {
+    if !root.is_dir() {
+        return Err(format!("Workspace does not exist: {}", root.display()));
+    }
+    let catalog = app.state::<Catalog>();
+    let mut open = catalog
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to open metadata cache: {}", e))?;
+    if let Some(db) = open.get(root) {
+        return Ok(db.clone());
+    }
+
+    if safe_mode::disabled(safe_mode::Component::Index) {
+        // Nothing is read from the cache file, which may be what keeps the app from starting
+        let connection = Connection::open_in_memory()
+            .and_then(|connection| {
+                connection.execute_batch(&format!(
+                    "PRAGMA foreign_keys = ON; {} {} PRAGMA user_version = {};",
+                    SCHEMA, HISTORY_SCHEMA, SCHEMA_VERSION
+                ))?;
+                Ok(connection)
+            })
+            .map_err(|e| format!("Failed to create metadata cache: {}", e))?;
+        let db: Database = Arc::new(Mutex::new(connection));
+        open.insert(root.to_path_buf(), db.clone());
+        drop(open);
+        reconcile(&db, root)?;
+        return Ok(db);
+    }
+
+    let path = database_path(app, root)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    }
+    let connection = connect(&path).or_else(|error| {
+        // A corrupt cache is rebuilt rather than surfaced; the files are the source of truth
+        tracing::warn!(%error, "Discarding unreadable metadata cache");
+        let _ = std::fs::remove_file(&path);
+        connect(&path)
+    })?;
+    let empty = connection
+        .query_row("SELECT NOT EXISTS (SELECT 1 FROM documents)", [], |row| {
+            row.get::<_, bool>(0)
+        })
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let db: Database = Arc::new(Mutex::new(connection));
+    open.insert(root.to_path_buf(), db.clone());
+    drop(open);
+
+    if empty {
+        reconcile(&db, root)?;
+    } else {
+        let (db, root) = (db.clone(), root.to_path_buf());
+        std::thread::spawn(move || {
+            if let Err(error) = reconcile(&db, &root) {
+                tracing::warn!(%error, "Failed to refresh metadata cache");
+            }
+        });
+    }
+    Ok(db)
+}
+
+fn connect(path: &Path) -> Result<Connection, String> {
+    let connection =
+        Connection::open(path).map_err(|e| format!("Failed to open metadata cache: {}", e))?;
+    connection
+        .execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+        .map_err(|e| format!("Failed to open metadata cache: {}", e))?;
+    let version: i64 = connection
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    if version != SCHEMA_VERSION {
+        connection
+            .execute_batch(&format!(
+                "DROP TABLE IF EXISTS tasks; DROP TABLE IF EXISTS outlines; \
+                 DROP TABLE IF EXISTS aliases; \
+                 DROP TABLE IF EXISTS links; DROP TABLE IF EXISTS tags; \
+                 DROP TABLE IF EXISTS documents; {} PRAGMA user_version = {};",
+                SCHEMA, SCHEMA_VERSION
+            ))
+            .map_err(|e| format!("Failed to create metadata cache: {}", e))?;
+    }
+    connection
+        .execute_batch(HISTORY_SCHEMA)
+        .map_err(|e| format!("Failed to create metadata cache: {}", e))?;
+    Ok(connection)
+}
+
+/// Open workspace whose folder contains `path`, for documents and folders only
+pub fn containing(app: &AppHandle, path: &Path) -> Option<(PathBuf, Database)> {
+    let is_document = path.to_string_lossy().ends_with(DOCUMENT_EXTENSION);
+    if !is_document && !path.is_dir() && path.exists() {
+        return None;
+    }
+    let catalog = app.state::<Catalog>();
+    let open = catalog.0.lock().ok()?;
+    open.iter()
+        .filter(|(root, _)| path.starts_with(root))
+        .max_by_key(|(root, _)| root.components().count())
+        .map(|(root, db)| (root.clone(), db.clone()))
+}
+
+/// Bring the cache in line with the disk, re-reading only documents whose size or
+/// modification time changed. Returns how many documents were re-read.
+fn reconcile(db: &Database, root: &Path) -> Result<usize, String> {
+    let on_disk: Vec<(PathBuf, u64, i64)> = workspace::list_documents(root)?
+        .into_iter()
+        .filter_map(|path| {
+            let (size, modified) = stamp(&path)?;
+            Some((path, size, modified))
+        })
+        .collect();
+    let cached: HashMap<String, (u64, i64)> = {
+        let db = db
+            .lock()
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        let mut statement = db
+            .prepare("SELECT path, size, modified FROM documents")
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        rows.flatten().collect()
+    };
+
+    let mut seen = BTreeSet::new();
+    let mut changed = Vec::new();
+    for (path, size, modified) in on_disk {
+        let relative = workspace::relative_path(root, &path);
+        if cached.get(&relative) != Some(&(size, modified)) {
+            // Parsed without holding the lock so queries are answered meanwhile
+            if let Some(indexed) = read(root, &path) {
+                changed.push(indexed);
+            }
+        }
+        seen.insert(relative);
+    }
+    let removed: Vec<&String> = cached.keys().filter(|path| !seen.contains(*path)).collect();
+
+    let mut db = db
+        .lock()
+        .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    let transaction = db
+        .transaction()
+        .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    for path in removed {
+        transaction
+            .execute("DELETE FROM documents WHERE path = ?1", [path])
+            .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    }
+    for indexed in &changed {
+        store(&transaction, indexed)
+            .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    }
+    transaction
+        .commit()
+        .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    Ok(changed.len())
+}
+
+/// Re-read one document into the cache, or drop it if it no longer exists
+fn update(db: &Database, root: &Path, path: &Path) -> Result<(), String> {
+    let indexed = path.is_file().then(|| read(root, path)).flatten();
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    let result = match indexed {
+        Some(indexed) => store(&db, &indexed),
+        // Also covers a deleted folder: everything below it goes too
+        None => db
+            .execute(
+                "DELETE FROM documents
+                 WHERE path = ?1 OR substr(path, 1, length(?1) + 1) = ?1 || '/'",
+                [workspace::relative_path(root, path)],
+            )
+            .map(|_| ()),
+    };
+    result.map_err(|e| format!("Failed to write metadata cache: {}", e))
+}
+
+fn store(db: &Connection, indexed: &Indexed) -> rusqlite::Result<()> {
+    db.execute(
+        "INSERT OR REPLACE INTO documents (path, id, name, size, modified, word_count, created)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            indexed.path,
+            indexed.id,
+            indexed.name,
+            indexed.size,
+            indexed.modified,
+            indexed.word_count,
+            indexed.created
+        ],
+    )?;
+    db.execute("DELETE FROM tags WHERE path = ?1", [&indexed.path])?;
+    db.execute("DELETE FROM links WHERE path = ?1", [&indexed.path])?;
+    db.execute("DELETE FROM aliases WHERE path = ?1", [&indexed.path])?;
+    for tag in &indexed.tags {
+        db.execute(
+            "INSERT INTO tags (path, tag) VALUES (?1, ?2)",
+            [&indexed.path, tag],
+        )?;
+    }
+    for target in &indexed.links {
+        db.execute(
+            "INSERT INTO links (path, target) VALUES (?1, ?2)",
+            [&indexed.path, target],
+        )?;
+    }
+    for alias in &indexed.aliases {
+        db.execute(
+            "INSERT INTO aliases (path, alias) VALUES (?1, ?2)",
+            [&indexed.path, alias],
+        )?;
+    }
+    tasks::store(db, &indexed.path, &indexed.tasks)?;
+    db.execute(
+        "INSERT OR REPLACE INTO outlines (path, size, modified, headings)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            indexed.path,
+            indexed.size,
+            indexed.modified,
+            indexed.headings
+        ],
+    )?;
+    Ok(())
+}
+
+/// Size and modification time in epoch milliseconds, which the cache compares to spot changes
+pub fn stamp(path: &Path) -> Option<(u64, i64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as i64;
+    Some((metadata.len(), modified))
+}
+
+/// Parse a document into what the cache stores; unreadable files are left out, and so are
+/// vault documents, whose names and tags must not sit unencrypted in the cache
+fn read(root: &Path, path: &Path) -> Option<Indexed> {
+    if vault::vault_of(path).is_some() {
+        return None;
+    }
+    let (size, modified) = stamp(path)?;
+    let board = document::read_board(path).ok()?;
+    let text = board.to_markdown();
+    let headings = serde_json::to_string(&outline::headings(&board)).ok()?;
+    Some(Indexed {
+        path: workspace::relative_path(root, path),
+        id: board.board.id.clone(),
+        name: board.board.name.clone(),
+        size,
+        modified,
+        word_count: text.split_whitespace().count() as u64,
+        created: board.board.created_at,
+        tags: tags(&text),
+        links: links(&text),
+        aliases: board
+            .board
+            .aliases
+            .iter()
+            .map(|alias| alias.trim().to_string())
+            .filter(|alias| !alias.is_empty())
+            .collect(),
+        headings,
+        tasks: tasks::extract(&board),
+    })
+}
+
+/// `#tag` words, lowercased; headings (`# Title`) and numbers (`#1`) are not tags
+pub fn tags(text: &str) -> BTreeSet<String> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .filter(|tag| tag.chars().next().is_some_and(char::is_alphabetic))
+        .map(|tag| {
+            tag.trim_end_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|tag| {
+            tag.chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '/')
+        })
+        .collect()
+}
+
+/// Targets of `[[Target]]` and `[[Target|Label]]`, lowercased as titles are matched
+fn links(text: &str) -> BTreeSet<String> {
+    let mut targets = BTreeSet::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        let target = inner.split_once('|').map_or(inner, |(target, _)| target);
+        if !target.trim().is_empty() {
+            targets.insert(target.trim().to_lowercase());
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    targets
+}
+
+fn entries(
+    db: &Connection,
+    tag: Option<&str>,
+    links_to: Option<&str>,
+) -> Result<Vec<CatalogEntry>, String> {
+    let mut statement = db
+        .prepare(
+            "SELECT path, name, size, modified, word_count FROM documents d
+             WHERE (?1 IS NULL OR EXISTS (SELECT 1 FROM tags t WHERE t.path = d.path AND t.tag = ?1))
+               AND (?2 IS NULL OR EXISTS (SELECT 1 FROM links l WHERE l.path = d.path AND l.target = ?2))
+             ORDER BY path",
+        )
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let tag = tag.map(|tag| tag.trim_start_matches('#').to_lowercase());
+    let links_to = links_to.map(|target| target.trim().to_lowercase());
+    let rows = statement
+        .query_map(params![tag, links_to], |row| {
+            Ok(CatalogEntry {
+                path: row.get(0)?,
+                name: row.get(1)?,
+                size: row.get(2)?,
+                modified: row.get(3)?,
+                word_count: row.get(4)?,
+                tags: Vec::new(),
+                links: Vec::new(),
+            })
+        })
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let mut entries: Vec<CatalogEntry> = rows.flatten().collect();
+
+    let mut tags = grouped(db, "SELECT path, tag FROM tags ORDER BY tag")?;
+    let mut links = grouped(db, "SELECT path, target FROM links ORDER BY target")?;
+    for entry in &mut entries {
+        entry.tags = tags.remove(&entry.path).unwrap_or_default();
+        entry.links = links.remove(&entry.path).unwrap_or_default();
+    }
+    Ok(entries)
+}
+
+/// Second column of a two-column query, grouped by the first
+fn grouped(db: &Connection, sql: &str) -> Result<HashMap<String, Vec<String>>, String> {
+    let mut statement = db
+        .prepare(sql)
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for (path, value) in rows.flatten() {
+        groups.entry(path).or_default().push(value);
+    }
+    Ok(groups)
+}
+
+fn stats(db: &Connection) -> rusqlite::Result<WorkspaceStats> {
+    let (documents, words, bytes, last_modified) = db.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(word_count), 0), COALESCE(SUM(size), 0), MAX(modified)
+         FROM documents",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    let mut statement =
+        db.prepare("SELECT tag, COUNT(*) AS uses FROM tags GROUP BY tag ORDER BY uses DESC, tag")?;
+    let tags = statement
+        .query_map([], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                documents: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(WorkspaceStats {
+        documents,
+        words,
+        bytes,
+        last_modified,
+        tags,
+    })
+}
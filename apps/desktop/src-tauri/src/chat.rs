@@ -0,0 +1,272 @@
+use crate::cancel;
+use crate::error::Error;
+use crate::providers::{self, AiRequest, Route};
+use crate::vectors::{self, Hit};
+use crate::{catalog, workspace};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tauri::{AppHandle, Emitter};
+
+/// Passages retrieved before those the policy refuses are dropped
+const CANDIDATES: usize = 24;
+const DEFAULT_SOURCES: usize = 8;
+/// Characters of retrieved text put in the prompt
+const CONTEXT_CHARS: usize = 12_000;
+/// Earlier turns kept in the prompt
+const HISTORY_TURNS: usize = 6;
+/// Emitted with the citations before the answer starts streaming
+const SOURCES_EVENT: &str = "ask:sources";
+
+/// Which documents a question is answered from; the whole workspace when empty
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AskScope {
+    /// Workspace-relative folder
+    pub folder: Option<String>,
+    pub tag: Option<String>,
+    /// Workspace-relative paths of specific documents
+    pub paths: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatTurn {
+    /// `user` or `assistant`
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Citation {
+    /// Number the answer cites it by, as `[1]`
+    pub index: usize,
+    pub path: String,
+    pub name: String,
+    pub heading: Option<String>,
+    pub excerpt: String,
+    pub score: f32,
+    /// Whether the answer refers to it
+    pub cited: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Answer {
+    pub text: String,
+    pub citations: Vec<Citation>,
+    pub provider: String,
+    pub remote: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SourcesEvent {
+    op_id: Option<String>,
+    citations: Vec<Citation>,
+}
+
+/// Whether a workspace-relative path is inside the scope
+fn in_scope(scope: &AskScope, tagged: &Option<BTreeSet<String>>, path: &str) -> bool {
+    let folder = scope
+        .folder
+        .as_deref()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty());
+    folder.is_none_or(|folder| {
+        path.strip_prefix(folder)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }) && tagged.as_ref().is_none_or(|tagged| tagged.contains(path))
+        && (scope.paths.is_empty() || scope.paths.iter().any(|p| p == path))
+}
+
+/// The best passages that fit the context budget, leaving out documents the provider may not see
+fn select(hits: Vec<Hit>, route: &Route, root: &std::path::Path, limit: usize) -> Vec<Hit> {
+    let mut allowed = std::collections::HashMap::new();
+    let mut used = 0;
+    let mut selected = Vec::new();
+    for hit in hits {
+        let ok = *allowed
+            .entry(hit.path.clone())
+            .or_insert_with(|| route.allows(&root.join(&hit.path)));
+        if !ok || used + hit.text.len() > CONTEXT_CHARS && !selected.is_empty() {
+            continue;
+        }
+        used += hit.text.len();
+        selected.push(hit);
+        if selected.len() == limit {
+            break;
+        }
+    }
+    selected
+}
+
+fn prompt(question: &str, history: &[ChatTurn], sources: &[Hit]) -> String {
+    let mut prompt = String::from("Sources:\n");
+    for (index, hit) in sources.iter().enumerate() {
+        let heading = hit
+            .heading
+            .as_deref()
+            .map(|heading| format!(" > {}", heading))
+            .unwrap_or_default();
+        prompt.push_str(&format!(
+            "\n[{}] {}{}\n{}\n",
+            index + 1,
+            hit.name,
+            heading,
+            hit.text
+        ));
+    }
+    let earlier = &history[history.len().saturating_sub(HISTORY_TURNS)..];
+    if !earlier.is_empty() {
+        prompt.push_str("\nConversation so far:\n");
+        for turn in earlier {
+            prompt.push_str(&format!("{}: {}\n", turn.role, turn.content));
+        }
+    }
+    prompt.push_str(&format!("\nQuestion: {}", question));
+    prompt
+}
+
+/// Numbers in `[n]` or `[n, m]` citations in the answer
+fn cited(answer: &str) -> BTreeSet<usize> {
+    let mut numbers = BTreeSet::new();
+    let mut rest = answer;
+    while let Some(start) = rest.find('[') {
+        let Some(len) = rest[start + 1..].find(']') else {
+            break;
+        };
+        numbers.extend(
+            rest[start + 1..start + 1 + len]
+                .split(',')
+                .filter_map(|part| part.trim().parse::<usize>().ok()),
+        );
+        rest = &rest[start + 1 + len..];
+    }
+    numbers
+}
+
+/// A question and how to answer it
+struct Question {
+    text: String,
+    scope: AskScope,
+    history: Vec<ChatTurn>,
+    provider: Option<String>,
+    limit: usize,
+}
+
+fn ask(
+    app: &AppHandle,
+    question: Question,
+    op_id: Option<String>,
+    cancel: &cancel::CancelToken,
+) -> Result<Answer, String> {
+    let Question {
+        text: question,
+        scope,
+        history,
+        provider,
+        limit,
+    } = question;
+    let question = question.as_str();
+    if question.trim().is_empty() {
+        return Err("Invalid question: it is empty".to_string());
+    }
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let route = Route::resolve(app, provider.as_deref())?;
+    route.check_allowed()?;
+    vectors::sync(app, &root, &op_id, cancel)?;
+    let tagged = match &scope.tag {
+        Some(tag) => Some(
+            catalog::document_paths(app, &root, Some(tag.trim_start_matches('#')))?
+                .iter()
+                .map(|path| workspace::relative_path(&root, path))
+                .collect(),
+        ),
+        None => None,
+    };
+    let hits = vectors::search(app, &root, question, CANDIDATES.max(limit), |path| {
+        in_scope(&scope, &tagged, path)
+    })?;
+    let sources = select(hits, &route, &root, limit);
+    if sources.is_empty() {
+        return Err("No documents in scope match the question, or none may be sent".to_string());
+    }
+    let citations: Vec<Citation> = sources
+        .iter()
+        .enumerate()
+        .map(|(index, hit)| Citation {
+            index: index + 1,
+            path: hit.path.clone(),
+            name: hit.name.clone(),
+            heading: hit.heading.clone(),
+            excerpt: hit.text.chars().take(280).collect(),
+            score: hit.score,
+            cited: false,
+        })
+        .collect();
+    let _ = app.emit(
+        SOURCES_EVENT,
+        SourcesEvent {
+            op_id: op_id.clone(),
+            citations: citations.clone(),
+        },
+    );
+
+    let system = "You answer questions about the user's notes using only the numbered sources. \
+        Cite the sources you use inline as [1], [2]. If the sources do not contain the answer, \
+        say so instead of guessing. Answer in the language of the question.";
+    let request = AiRequest {
+        provider: Some(route.id.clone()),
+        model: None,
+        system: Some(system.to_string()),
+        prompt: prompt(question, &history, &sources),
+        documents: Vec::new(),
+        source: None,
+        max_tokens: None,
+        temperature: Some(0.2),
+    };
+    let completion = providers::complete(app, request, op_id, cancel)?;
+    let referenced = cited(&completion.text);
+    Ok(Answer {
+        citations: citations
+            .into_iter()
+            .map(|citation| Citation {
+                cited: referenced.contains(&citation.index),
+                ..citation
+            })
+            .collect(),
+        text: completion.text,
+        provider: completion.provider,
+        remote: completion.remote,
+    })
+}
+
+/// Answer a question from the open workspace: retrieve the closest passages from the
+/// embeddings index, emit them as `ask:sources`, and stream the answer as `llm:token` events.
+/// Documents the workspace policy keeps on the device are never sent to a remote provider.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn ask_workspace(
+    app: AppHandle,
+    question: String,
+    scope: Option<AskScope>,
+    history: Option<Vec<ChatTurn>>,
+    provider: Option<String>,
+    limit: Option<usize>,
+    op_id: Option<String>,
+) -> Result<Answer, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = cancel::begin(&app, op_id.clone());
+        let question = Question {
+            text: question,
+            scope: scope.unwrap_or_default(),
+            history: history.unwrap_or_default(),
+            provider,
+            limit: limit.unwrap_or(DEFAULT_SOURCES).max(1),
+        };
+        ask(&app, question, op_id, operation.token()).map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Question task failed: {}", e))?
+}
@@ -0,0 +1,229 @@
+use crate::error::Error;
+use crate::{paths, vault};
+use base64::Engine;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Emitted for each chunk of a stream started with [`read_file_chunks`]
+pub const FILE_CHUNK: &str = "file-chunk:data";
+/// Emitted when a stream stops because of an error or an unresponsive webview
+pub const FILE_CHUNK_FAILED: &str = "file-chunk:failed";
+
+const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+const MIN_CHUNK_SIZE: usize = 4 * 1024;
+const MAX_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// Chunks sent ahead of what the webview has acknowledged
+const MAX_IN_FLIGHT: u64 = 4;
+/// Streams whose webview stops acknowledging are abandoned after this long
+const ACK_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkStream {
+    pub id: String,
+    /// File size when the stream started; later growth is not read
+    pub size: u64,
+    pub chunk_size: usize,
+    pub chunks: u64,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Chunk {
+    stream_id: String,
+    index: u64,
+    offset: u64,
+    /// Base64-encoded bytes
+    data: String,
+    last: bool,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ChunkFailure {
+    stream_id: String,
+    error: String,
+}
+
+#[derive(Default)]
+struct Flow {
+    /// Chunks the webview has acknowledged; `None` until it is listening
+    received: Option<u64>,
+    cancelled: bool,
+}
+
+#[derive(Default)]
+struct Control {
+    flow: Mutex<Flow>,
+    wake: Condvar,
+}
+
+/// Managed state holding flow control for open chunk streams
+#[derive(Default)]
+pub struct ChunkStreams(Mutex<HashMap<String, Arc<Control>>>);
+
+impl ChunkStreams {
+    fn get(&self, id: &str) -> Option<Arc<Control>> {
+        self.0.lock().ok()?.get(id).cloned()
+    }
+
+    fn remove(&self, id: &str) {
+        if let Ok(mut streams) = self.0.lock() {
+            streams.remove(id);
+        }
+    }
+}
+
+/// Stream a file to the webview as `file-chunk:data` events instead of one large response.
+///
+/// Nothing is sent until the webview calls [`ack_file_chunks`] with `0`, so it can subscribe
+/// with the returned id first. After that at most a few chunks are in flight beyond the last
+/// acknowledged one; the final chunk has `last` set.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn read_file_chunks(
+    app: AppHandle,
+    streams: State<'_, ChunkStreams>,
+    path: String,
+    chunk_size: Option<usize>,
+) -> Result<ChunkStream, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    if vault::vault_of(Path::new(&path)).is_some() {
+        return Err("Chunked reads are not supported for vault documents".into());
+    }
+    let file = File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    let chunk_size = chunk_size
+        .unwrap_or(DEFAULT_CHUNK_SIZE)
+        .clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+    let stream = ChunkStream {
+        id: crate::document::create_id("stream"),
+        size,
+        chunk_size,
+        chunks: size.div_ceil(chunk_size as u64).max(1),
+    };
+
+    let control = Arc::new(Control::default());
+    streams
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to open stream: {}", e))?
+        .insert(stream.id.clone(), control.clone());
+
+    let id = stream.id.clone();
+    std::thread::spawn(move || {
+        if let Err(error) = send(&app, &id, file, size, chunk_size, &control) {
+            let _ = app.emit(
+                FILE_CHUNK_FAILED,
+                ChunkFailure {
+                    stream_id: id.clone(),
+                    error,
+                },
+            );
+        }
+        app.state::<ChunkStreams>().remove(&id);
+    });
+    Ok(stream)
+}
+
+/// Acknowledge the first `received` chunks of a stream, letting more be sent
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn ack_file_chunks(
+    streams: State<'_, ChunkStreams>,
+    id: String,
+    received: u64,
+) -> Result<(), Error> {
+    let control = streams
+        .get(&id)
+        .ok_or_else(|| format!("Stream is not open: {}", id))?;
+    if let Ok(mut flow) = control.flow.lock() {
+        flow.received = Some(flow.received.unwrap_or(0).max(received));
+    }
+    control.wake.notify_all();
+    Ok(())
+}
+
+/// Stop a stream; chunks already emitted may still arrive
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn cancel_file_read(streams: State<'_, ChunkStreams>, id: String) {
+    if let Some(control) = streams.get(&id) {
+        if let Ok(mut flow) = control.flow.lock() {
+            flow.cancelled = true;
+        }
+        control.wake.notify_all();
+    }
+}
+
+fn send(
+    app: &AppHandle,
+    id: &str,
+    file: File,
+    size: u64,
+    chunk_size: usize,
+    control: &Control,
+) -> Result<(), String> {
+    let mut reader = file.take(size);
+    let mut offset = 0;
+    for index in 0.. {
+        if !wait_for_credit(control, index)? {
+            return Ok(());
+        }
+
+        let mut data = Vec::with_capacity(chunk_size);
+        (&mut reader)
+            .take(chunk_size as u64)
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        let last = data.len() < chunk_size || offset + data.len() as u64 >= size;
+        app.emit(
+            FILE_CHUNK,
+            Chunk {
+                stream_id: id.to_string(),
+                index,
+                offset,
+                data: base64::engine::general_purpose::STANDARD.encode(&data),
+                last,
+            },
+        )
+        .map_err(|e| format!("Failed to send chunk: {}", e))?;
+        offset += data.len() as u64;
+        if last {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Block until chunk `index` may be sent, returning `false` if the stream was cancelled
+fn wait_for_credit(control: &Control, index: u64) -> Result<bool, String> {
+    let flow = control
+        .flow
+        .lock()
+        .map_err(|e| format!("Failed to read stream state: {}", e))?;
+    let (flow, timeout) = control
+        .wake
+        .wait_timeout_while(flow, ACK_TIMEOUT, |flow| {
+            !flow.cancelled
+                && flow
+                    .received
+                    .is_none_or(|received| index >= received + MAX_IN_FLIGHT)
+        })
+        .map_err(|e| format!("Failed to read stream state: {}", e))?;
+    if flow.cancelled {
+        return Ok(false);
+    }
+    if timeout.timed_out() {
+        return Err("The webview stopped acknowledging chunks".to_string());
+    }
+    Ok(true)
+}
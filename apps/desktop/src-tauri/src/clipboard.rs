@@ -0,0 +1,56 @@
+use crate::assets::{RasterFormat, SavedAsset};
+use crate::error::Error;
+use tauri::AppHandle;
+
+/// Save the image currently on the system clipboard into an assets folder (the workspace's by default)
+#[cfg(desktop)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_clipboard_image(
+    app: AppHandle,
+    destination_dir: Option<String>,
+    format: Option<RasterFormat>,
+) -> Result<SavedAsset, Error> {
+    use crate::{assets, paths};
+    use std::path::PathBuf;
+
+    let destination = match destination_dir {
+        Some(dir) => paths::check(&app, &dir, paths::Scope::Write).map(|_| PathBuf::from(dir))?,
+        None => assets::default_dir(&app)?,
+    };
+
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {}", e))?;
+    let data = clipboard.get_image().map_err(|e| match e {
+        arboard::Error::ContentNotAvailable => "Clipboard does not contain an image".to_string(),
+        other => format!("Failed to read clipboard image: {}", other),
+    })?;
+
+    let buffer = image::RgbaImage::from_raw(
+        data.width as u32,
+        data.height as u32,
+        data.bytes.into_owned(),
+    )
+    .ok_or_else(|| "Clipboard image has an unexpected size".to_string())?;
+
+    let path = assets::save_image(
+        &image::DynamicImage::ImageRgba8(buffer),
+        &destination,
+        "pasted",
+        format.unwrap_or_default(),
+    )?;
+
+    Ok(assets::describe(&app, &path))
+}
+
+/// Clipboard image access is not available on mobile builds
+#[cfg(mobile)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_clipboard_image(
+    _app: AppHandle,
+    _destination_dir: Option<String>,
+    _format: Option<RasterFormat>,
+) -> Result<SavedAsset, Error> {
+    Err("Clipboard images are not supported on this platform".into())
+}
@@ -0,0 +1,931 @@
+use crate::document::{self, now_millis, BoardFile, BoardMeta, DocOrder, Document};
+use crate::error::Error;
+use crate::websocket::{self, WebSocket};
+use crate::{paths, saves, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use url::Url;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long after the last change the merged document is written to disk
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+/// Presence is resent this often; peers silent for three heartbeats are dropped
+const HEARTBEAT: Duration = Duration::from_secs(10);
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(30);
+
+/// Record maps of [`Document`]; their keys are `<kind>/<record id>`
+const RECORD_KINDS: [&str; 3] = ["pages", "shapes", "bindings"];
+const PAGE_IDS: &str = "order/pageIds";
+const SHAPE_ORDER: &str = "order/shapeOrder";
+const NAME: &str = "board/name";
+
+/// Lamport clock of a write, with the replica that made it breaking ties so every replica
+/// picks the same winner
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct Stamp {
+    pub clock: u64,
+    pub replica: String,
+}
+
+/// A write to one key; deletions are kept as tombstones so they outrank older writes
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Update {
+    pub key: String,
+    #[serde(default)]
+    pub value: Option<Value>,
+    #[serde(flatten)]
+    pub stamp: Stamp,
+}
+
+/// New value of one key, `None` once deleted.
+///
+/// Keys are `pages/<id>`, `shapes/<id>` and `bindings/<id>` for records, and `order/pageIds`,
+/// `order/shapeOrder` and `board/name` for the rest of the document.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub key: String,
+    #[serde(default)]
+    pub value: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Cursor {
+    pub page_id: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Who is viewing the document and where
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Presence {
+    pub client: String,
+    pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub cursor: Option<Cursor>,
+    /// Selected shape ids
+    #[serde(default)]
+    pub selection: Vec<String>,
+    /// When this device last heard from the peer, in milliseconds since the epoch
+    #[serde(default, skip_deserializing)]
+    pub last_seen: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CollabSession {
+    pub id: String,
+    /// This device's replica and presence client id
+    pub client: String,
+    /// The merged document to render
+    pub document: BoardFile,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Connecting,
+    Connected,
+    Disconnected,
+    Closed,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StatusEvent {
+    session: String,
+    status: Status,
+    error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ChangedEvent {
+    session: String,
+    changes: Vec<Change>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PresenceEvent {
+    session: String,
+    peers: Vec<Presence>,
+}
+
+/// Messages exchanged through the relay, which passes each text message on to everyone else in
+/// the room. A replica that joins says hello and sends its whole state; everyone answers with
+/// theirs, so replicas converge however much they missed.
+#[derive(Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum Wire {
+    Hello { presence: Presence },
+    State { updates: Vec<Update> },
+    Update { updates: Vec<Update> },
+    Presence { presence: Presence },
+    Leave,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    /// Board id; rooms may be shared, so messages about other documents are ignored
+    document: String,
+    client: String,
+    #[serde(flatten)]
+    message: Wire,
+}
+
+/// `.inkfinite/collab-<board id>.json`: stamps from the last session, so records edited since
+/// are told apart from ones the room already has
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SavedState {
+    replica: String,
+    clock: u64,
+    entries: BTreeMap<String, SavedEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedEntry {
+    #[serde(flatten)]
+    stamp: Stamp,
+    /// Digest of the value, `None` for a tombstone
+    hash: Option<String>,
+}
+
+struct Entry {
+    value: Option<Value>,
+    stamp: Stamp,
+}
+
+/// Last-writer-wins map over the records of a document. Concurrent edits to different records
+/// all survive; edits to the same record resolve to the one with the highest [`Stamp`].
+struct Replica {
+    id: String,
+    clock: u64,
+    entries: BTreeMap<String, Entry>,
+}
+
+fn digest(value: &Value) -> String {
+    format!("{:x}", Sha256::digest(value.to_string().as_bytes()))
+}
+
+/// Whether `key` names part of a document and `value` has the right shape for it
+fn valid(key: &str, value: Option<&Value>) -> bool {
+    match key {
+        PAGE_IDS => value.is_none_or(|ids| {
+            ids.as_array()
+                .is_some_and(|ids| ids.iter().all(Value::is_string))
+        }),
+        SHAPE_ORDER => value.is_none_or(Value::is_object),
+        NAME => value.is_some_and(Value::is_string),
+        _ => key.split_once('/').is_some_and(|(kind, id)| {
+            RECORD_KINDS.contains(&kind) && !id.is_empty() && value.is_none_or(Value::is_object)
+        }),
+    }
+}
+
+/// Every key of `board` and its value
+fn records(board: &BoardFile) -> BTreeMap<String, Value> {
+    let mut records = BTreeMap::new();
+    let maps = [&board.doc.pages, &board.doc.shapes, &board.doc.bindings];
+    for (kind, map) in RECORD_KINDS.iter().zip(maps) {
+        for (id, value) in map {
+            records.insert(format!("{}/{}", kind, id), value.clone());
+        }
+    }
+    records.insert(
+        PAGE_IDS.to_string(),
+        Value::from(board.order.page_ids.clone()),
+    );
+    if let Some(order) = &board.order.shape_order {
+        records.insert(SHAPE_ORDER.to_string(), Value::Object(order.clone()));
+    }
+    records.insert(NAME.to_string(), Value::from(board.board.name.clone()));
+    records
+}
+
+fn config_name(document: &str) -> String {
+    format!(
+        "collab-{}",
+        document.replace(|c: char| !c.is_ascii_alphanumeric(), "-")
+    )
+}
+
+impl Replica {
+    /// Start from the document on disk, reusing the stamps of the last session for records
+    /// that have not changed since. Anything edited or deleted outside a session counts as a
+    /// new local write. Also returns whether any record was stamped afresh.
+    fn load(board: &BoardFile, saved: SavedState) -> (Self, bool) {
+        let mut replica = Replica {
+            id: if saved.replica.is_empty() {
+                uuid::Uuid::new_v4().simple().to_string()
+            } else {
+                saved.replica
+            },
+            clock: saved.clock,
+            entries: BTreeMap::new(),
+        };
+        let mut fresh = false;
+        let current = records(board);
+        for (key, value) in &current {
+            let stamp = match saved.entries.get(key) {
+                Some(entry) if entry.hash.as_deref() == Some(digest(value).as_str()) => {
+                    entry.stamp.clone()
+                }
+                _ => {
+                    fresh = true;
+                    replica.tick()
+                }
+            };
+            let value = Some(value.clone());
+            replica.entries.insert(key.clone(), Entry { value, stamp });
+        }
+        for (key, entry) in saved.entries {
+            if current.contains_key(&key) {
+                continue;
+            }
+            let stamp = if entry.hash.is_none() {
+                entry.stamp
+            } else {
+                fresh = true;
+                replica.tick()
+            };
+            replica.entries.insert(key, Entry { value: None, stamp });
+        }
+        (replica, fresh)
+    }
+
+    fn tick(&mut self) -> Stamp {
+        self.clock += 1;
+        Stamp {
+            clock: self.clock,
+            replica: self.id.clone(),
+        }
+    }
+
+    /// Make a local write, returning it stamped for sending
+    fn write(&mut self, change: Change) -> Update {
+        let stamp = self.tick();
+        self.entries.insert(
+            change.key.clone(),
+            Entry {
+                value: change.value.clone(),
+                stamp: stamp.clone(),
+            },
+        );
+        Update {
+            key: change.key,
+            value: change.value,
+            stamp,
+        }
+    }
+
+    /// Apply a remote write if it outranks the one already here
+    fn merge(&mut self, update: Update) -> Option<Change> {
+        if !valid(&update.key, update.value.as_ref()) {
+            return None;
+        }
+        self.clock = self.clock.max(update.stamp.clock);
+        if self
+            .entries
+            .get(&update.key)
+            .is_some_and(|entry| entry.stamp >= update.stamp)
+        {
+            return None;
+        }
+        self.entries.insert(
+            update.key.clone(),
+            Entry {
+                value: update.value.clone(),
+                stamp: update.stamp,
+            },
+        );
+        Some(Change {
+            key: update.key,
+            value: update.value,
+        })
+    }
+
+    fn updates(&self) -> Vec<Update> {
+        self.entries
+            .iter()
+            .map(|(key, entry)| Update {
+                key: key.clone(),
+                value: entry.value.clone(),
+                stamp: entry.stamp.clone(),
+            })
+            .collect()
+    }
+
+    fn board(&self, meta: &BoardMeta) -> BoardFile {
+        let mut board = BoardFile {
+            board: meta.clone(),
+            doc: Document::default(),
+            order: DocOrder::default(),
+        };
+        for (key, entry) in &self.entries {
+            let Some(value) = &entry.value else {
+                continue;
+            };
+            match key.as_str() {
+                PAGE_IDS => {
+                    board.order.page_ids = serde_json::from_value(value.clone()).unwrap_or_default()
+                }
+                SHAPE_ORDER => board.order.shape_order = value.as_object().cloned(),
+                NAME => board.board.name = value.as_str().unwrap_or_default().to_string(),
+                _ => {
+                    let map = match key.split_once('/') {
+                        Some(("pages", id)) => Some((&mut board.doc.pages, id)),
+                        Some(("shapes", id)) => Some((&mut board.doc.shapes, id)),
+                        Some(("bindings", id)) => Some((&mut board.doc.bindings, id)),
+                        _ => None,
+                    };
+                    if let Some((map, id)) = map {
+                        map.insert(id.to_string(), value.clone());
+                    }
+                }
+            }
+        }
+        board
+    }
+
+    fn saved(&self) -> SavedState {
+        SavedState {
+            replica: self.id.clone(),
+            clock: self.clock,
+            entries: self
+                .entries
+                .iter()
+                .map(|(key, entry)| {
+                    let saved = SavedEntry {
+                        stamp: entry.stamp.clone(),
+                        hash: entry.value.as_ref().map(digest),
+                    };
+                    (key.clone(), saved)
+                })
+                .collect(),
+        }
+    }
+}
+
+struct SessionState {
+    replica: Replica,
+    meta: BoardMeta,
+    presence: Presence,
+    peers: HashMap<String, Presence>,
+    /// When the document last changed without being saved
+    changed_at: Option<Instant>,
+}
+
+impl SessionState {
+    fn peers(&self) -> Vec<Presence> {
+        let mut peers: Vec<Presence> = self.peers.values().cloned().collect();
+        peers.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.client.cmp(&b.client)));
+        peers
+    }
+}
+
+struct Session {
+    id: String,
+    path: PathBuf,
+    root: Option<PathBuf>,
+    relay: Url,
+    document: String,
+    client: String,
+    open: AtomicBool,
+    /// Messages for the connection thread to send
+    outgoing: mpsc::Sender<String>,
+    state: Mutex<SessionState>,
+}
+
+impl Session {
+    fn lock(&self) -> Result<MutexGuard<'_, SessionState>, String> {
+        self.state
+            .lock()
+            .map_err(|e| format!("Failed to lock collaboration session: {}", e))
+    }
+
+    fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    fn message(&self, message: Wire) -> Result<String, String> {
+        serde_json::to_string(&Envelope {
+            document: self.document.clone(),
+            client: self.client.clone(),
+            message,
+        })
+        .map_err(|e| format!("Failed to serialize message: {}", e))
+    }
+
+    fn send(&self, message: Wire) -> Result<(), String> {
+        // The thread only stops once the session is closed
+        let _ = self.outgoing.send(self.message(message)?);
+        Ok(())
+    }
+}
+
+/// Open collaboration sessions by id
+#[derive(Default)]
+pub struct Collaborations(Mutex<HashMap<String, Arc<Session>>>);
+
+fn find(collaborations: &Collaborations, id: &str) -> Result<Arc<Session>, Error> {
+    collaborations
+        .0
+        .lock()
+        .ok()
+        .and_then(|sessions| sessions.get(id).cloned())
+        .ok_or_else(|| Error::not_found("Collaboration session not found").with_context(id))
+}
+
+fn status(app: &AppHandle, session: &Session, status: Status, error: Option<String>) {
+    let _ = app.emit(
+        "collab:status",
+        StatusEvent {
+            session: session.id.clone(),
+            status,
+            error,
+        },
+    );
+}
+
+fn emit_presence(app: &AppHandle, session: &Session) {
+    let Ok(state) = session.lock() else {
+        return;
+    };
+    let _ = app.emit(
+        "collab:presence",
+        PresenceEvent {
+            session: session.id.clone(),
+            peers: state.peers(),
+        },
+    );
+}
+
+/// Write the merged document once it has been still for [`SAVE_DELAY`], or now when `force`
+fn save(app: &AppHandle, session: &Session, force: bool) {
+    let (board, saved) = {
+        let Ok(mut state) = session.lock() else {
+            return;
+        };
+        match state.changed_at {
+            Some(at) if force || at.elapsed() >= SAVE_DELAY => {}
+            _ => return,
+        }
+        state.changed_at = None;
+        state.meta.updated_at = now_millis();
+        (state.replica.board(&state.meta), state.replica.saved())
+    };
+    // A save the editor queued before the session started would overwrite merged changes
+    saves::discard(app, &session.path);
+    if let Err(e) = document::write_board(&session.path, &board) {
+        tracing::warn!(error = %e, "Failed to save collaborative document");
+        return;
+    }
+    if let Some(root) = &session.root {
+        if let Err(e) = workspace::write_config(root, &config_name(&session.document), &saved) {
+            tracing::warn!(error = %e, "Failed to save collaboration state");
+        }
+    }
+}
+
+/// Connect to the relay and keep reconnecting with backoff until the session is stopped
+fn run(app: AppHandle, session: Arc<Session>, outgoing: mpsc::Receiver<String>) {
+    let mut retry = RETRY_MIN;
+    while session.is_open() {
+        status(&app, &session, Status::Connecting, None);
+        let error = match websocket::connect(&session.relay) {
+            Ok(mut socket) => {
+                retry = RETRY_MIN;
+                status(&app, &session, Status::Connected, None);
+                serve(&app, &session, &mut socket, &outgoing).err()
+            }
+            Err(e) => Some(e),
+        };
+        if !session.is_open() {
+            break;
+        }
+        tracing::warn!(error = ?error, "Collaboration relay disconnected");
+        if let Ok(mut state) = session.lock() {
+            state.peers.clear();
+        }
+        emit_presence(&app, &session);
+        status(&app, &session, Status::Disconnected, error);
+        let until = Instant::now() + retry;
+        while session.is_open() && Instant::now() < until {
+            save(&app, &session, false);
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        retry = (retry * 2).min(RETRY_MAX);
+    }
+    save(&app, &session, true);
+    status(&app, &session, Status::Closed, None);
+}
+
+fn serve(
+    app: &AppHandle,
+    session: &Session,
+    socket: &mut WebSocket,
+    outgoing: &mpsc::Receiver<String>,
+) -> Result<(), String> {
+    socket.set_poll_interval(POLL_INTERVAL);
+    // Whatever was queued while offline is part of the full state sent next
+    while outgoing.try_recv().is_ok() {}
+    let (presence, updates) = {
+        let state = session.lock()?;
+        (state.presence.clone(), state.replica.updates())
+    };
+    socket.send_text(&session.message(Wire::Hello { presence })?)?;
+    socket.send_text(&session.message(Wire::State { updates })?)?;
+
+    let mut heartbeat = Instant::now();
+    while session.is_open() {
+        while let Ok(text) = outgoing.try_recv() {
+            socket.send_text(&text)?;
+        }
+        if let Some(text) = socket.poll()? {
+            receive(app, session, socket, &text)?;
+        }
+        if heartbeat.elapsed() >= HEARTBEAT {
+            heartbeat = Instant::now();
+            let presence = session.lock()?.presence.clone();
+            socket.send_text(&session.message(Wire::Presence { presence })?)?;
+            prune(app, session);
+        }
+        save(app, session, false);
+    }
+    while let Ok(text) = outgoing.try_recv() {
+        socket.send_text(&text)?;
+    }
+    socket.send_text(&session.message(Wire::Leave)?)?;
+    socket.close();
+    Ok(())
+}
+
+fn receive(
+    app: &AppHandle,
+    session: &Session,
+    socket: &mut WebSocket,
+    text: &str,
+) -> Result<(), String> {
+    let Ok(envelope) = serde_json::from_str::<Envelope>(text) else {
+        tracing::debug!("Ignoring unknown relay message");
+        return Ok(());
+    };
+    // Some relays echo messages back to their sender
+    if envelope.document != session.document || envelope.client == session.client {
+        return Ok(());
+    }
+    let client = envelope.client;
+    match envelope.message {
+        Wire::Hello { presence } => {
+            let (presence, updates) = {
+                let mut state = session.lock()?;
+                state.peers.insert(client.clone(), seen(presence, &client));
+                (state.presence.clone(), state.replica.updates())
+            };
+            socket.send_text(&session.message(Wire::State { updates })?)?;
+            socket.send_text(&session.message(Wire::Presence { presence })?)?;
+            emit_presence(app, session);
+        }
+        Wire::State { updates } | Wire::Update { updates } => {
+            let changes: Vec<Change> = {
+                let mut state = session.lock()?;
+                let changes: Vec<Change> = updates
+                    .into_iter()
+                    .filter_map(|update| state.replica.merge(update))
+                    .collect();
+                if !changes.is_empty() {
+                    state.changed_at = Some(Instant::now());
+                }
+                changes
+            };
+            if !changes.is_empty() {
+                let _ = app.emit(
+                    "collab:changed",
+                    ChangedEvent {
+                        session: session.id.clone(),
+                        changes,
+                    },
+                );
+            }
+        }
+        Wire::Presence { presence } => {
+            session
+                .lock()?
+                .peers
+                .insert(client.clone(), seen(presence, &client));
+            emit_presence(app, session);
+        }
+        Wire::Leave => {
+            session.lock()?.peers.remove(&client);
+            emit_presence(app, session);
+        }
+    }
+    Ok(())
+}
+
+fn seen(mut presence: Presence, client: &str) -> Presence {
+    presence.client = client.to_string();
+    presence.last_seen = now_millis();
+    presence
+}
+
+/// Drop peers that left without saying so
+fn prune(app: &AppHandle, session: &Session) {
+    let cutoff = now_millis() - 3 * HEARTBEAT.as_millis() as i64;
+    let pruned = session.lock().is_ok_and(|mut state| {
+        let before = state.peers.len();
+        state.peers.retain(|_, peer| peer.last_seen >= cutoff);
+        state.peers.len() != before
+    });
+    if pruned {
+        emit_presence(app, session);
+    }
+}
+
+/// Share the document at `path` through the WebSocket relay at `relay` (`ws://` or `wss://`),
+/// whose URL names the room. The document must be open in the same room on every device; edits
+/// made anywhere merge and are saved to `path` here.
+///
+/// Returns the merged document. Afterwards the frontend sends its edits with
+/// [`apply_collab_changes`] instead of saving, and renders `collab:changed` (remote edits),
+/// `collab:presence` (who is viewing and their cursors) and `collab:status` events.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn start_collaboration(
+    app: AppHandle,
+    collaborations: State<'_, Collaborations>,
+    path: String,
+    relay: String,
+    name: String,
+    color: Option<String>,
+) -> Result<CollabSession, Error> {
+    let resolved = paths::check(&app, &path, paths::Scope::Write)?;
+    let relay = Url::parse(&relay).map_err(|e| format!("Invalid relay URL: {}", e))?;
+    if !matches!(relay.scheme(), "ws" | "wss") {
+        return Err("Invalid relay URL: use ws:// or wss://".into());
+    }
+    let mut sessions = collaborations
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to start collaboration: {}", e))?;
+    if sessions.values().any(|session| session.path == resolved) {
+        return Err(Error::conflict("Document is already being shared").with_context(&path));
+    }
+    let board = document::read_board(&resolved)?;
+    let root = workspace::current_root(&app);
+    let saved = root
+        .as_ref()
+        .and_then(|root| workspace::read_config(root, &config_name(&board.board.id)).ok())
+        .unwrap_or_default();
+    let (replica, fresh) = Replica::load(&board, saved);
+    let document = replica.board(&board.board);
+    let id = document::create_id("collab");
+    let client = replica.id.clone();
+    let (sender, receiver) = mpsc::channel();
+    let session = Arc::new(Session {
+        id: id.clone(),
+        path: resolved,
+        root,
+        relay,
+        document: board.board.id.clone(),
+        client: client.clone(),
+        open: AtomicBool::new(true),
+        outgoing: sender,
+        state: Mutex::new(SessionState {
+            presence: Presence {
+                client: client.clone(),
+                name,
+                color,
+                cursor: None,
+                selection: Vec::new(),
+                last_seen: 0,
+            },
+            replica,
+            meta: board.board,
+            peers: HashMap::new(),
+            changed_at: fresh.then(Instant::now),
+        }),
+    });
+    sessions.insert(id.clone(), session.clone());
+    std::thread::spawn(move || run(app, session, receiver));
+    tracing::info!("Collaboration started");
+    Ok(CollabSession {
+        id,
+        client,
+        document,
+    })
+}
+
+/// The merged document of a session
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_collab_document(
+    collaborations: State<'_, Collaborations>,
+    session: String,
+) -> Result<BoardFile, Error> {
+    let session = find(&collaborations, &session)?;
+    let state = session.lock()?;
+    Ok(state.replica.board(&state.meta))
+}
+
+/// Apply local edits, which are saved and sent to everyone in the room
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn apply_collab_changes(
+    collaborations: State<'_, Collaborations>,
+    session: String,
+    changes: Vec<Change>,
+) -> Result<(), Error> {
+    let session = find(&collaborations, &session)?;
+    if let Some(change) = changes
+        .iter()
+        .find(|change| !valid(&change.key, change.value.as_ref()))
+    {
+        return Err(Error::from(format!("Invalid change: {}", change.key)));
+    }
+    let updates = {
+        let mut state = session.lock()?;
+        let updates: Vec<Update> = changes
+            .into_iter()
+            .map(|change| state.replica.write(change))
+            .collect();
+        state.changed_at = Some(Instant::now());
+        updates
+    };
+    Ok(session.send(Wire::Update { updates })?)
+}
+
+/// Share this device's cursor and selection
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn update_presence(
+    collaborations: State<'_, Collaborations>,
+    session: String,
+    cursor: Option<Cursor>,
+    selection: Vec<String>,
+) -> Result<(), Error> {
+    let session = find(&collaborations, &session)?;
+    let presence = {
+        let mut state = session.lock()?;
+        state.presence.cursor = cursor;
+        state.presence.selection = selection;
+        state.presence.clone()
+    };
+    Ok(session.send(Wire::Presence { presence })?)
+}
+
+/// Everyone else currently in the session
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_collab_peers(
+    collaborations: State<'_, Collaborations>,
+    session: String,
+) -> Result<Vec<Presence>, Error> {
+    let session = find(&collaborations, &session)?;
+    let peers = session.lock()?.peers();
+    Ok(peers)
+}
+
+/// Leave every room for a document inside `root`, saving the documents first
+pub fn stop_under(app: &AppHandle, root: &Path) {
+    if let Ok(mut sessions) = app.state::<Collaborations>().0.lock() {
+        sessions.retain(|_, session| {
+            let inside = session.path.starts_with(root);
+            if inside {
+                session.open.store(false, Ordering::SeqCst);
+            }
+            !inside
+        });
+    }
+}
+
+/// Leave the room, saving the document first; returns `false` for an unknown session
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn stop_collaboration(collaborations: State<'_, Collaborations>, session: String) -> bool {
+    let session = collaborations
+        .0
+        .lock()
+        .ok()
+        .and_then(|mut sessions| sessions.remove(&session));
+    match session {
+        Some(session) => {
+            session.open.store(false, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn replica(id: &str) -> Replica {
+        Replica {
+            id: id.to_string(),
+            clock: 0,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    fn change(key: &str, value: Option<Value>) -> Change {
+        Change {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    fn values(replica: &Replica) -> BTreeMap<String, Option<Value>> {
+        replica
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn concurrent_edits_converge() {
+        let mut a = replica("a");
+        let mut b = replica("b");
+        let from_a = [
+            a.write(change("shapes/s1", Some(json!({ "x": 1 })))),
+            a.write(change("shapes/s2", Some(json!({ "x": 2 })))),
+        ];
+        let from_b = [
+            b.write(change("shapes/s1", Some(json!({ "x": 10 })))),
+            b.write(change("pages/p1", Some(json!({ "name": "Page" })))),
+        ];
+
+        for update in from_b.iter().rev() {
+            a.merge(update.clone());
+        }
+        for update in from_a {
+            b.merge(update);
+        }
+        // Redelivered state changes nothing
+        for update in b.updates() {
+            assert!(a.merge(update).is_none());
+        }
+
+        assert_eq!(values(&a), values(&b));
+        assert_eq!(a.entries.len(), 3);
+        // Equal clocks resolve by replica id, the same way everywhere
+        assert_eq!(values(&a)["shapes/s1"], Some(json!({ "x": 10 })));
+    }
+
+    #[test]
+    fn deletes_win_over_stale_edits() {
+        let mut a = replica("a");
+        let mut b = replica("b");
+        let edit = a.write(change("shapes/s1", Some(json!({ "x": 1 }))));
+        b.merge(edit.clone());
+        let delete = b.write(change("shapes/s1", None));
+
+        // An edit made before the delete was seen loses, whenever it arrives
+        assert!(b.merge(edit).is_none());
+        let merged = a.merge(delete).expect("delete applies");
+        assert!(merged.value.is_none());
+
+        // A replica that missed the delete still holds an older edit
+        let mut stale = replica("c");
+        stale.merge(Update {
+            key: "shapes/s1".to_string(),
+            value: Some(json!({ "x": 5 })),
+            stamp: Stamp {
+                clock: 1,
+                replica: "c".to_string(),
+            },
+        });
+        for update in stale.updates() {
+            assert!(a.merge(update.clone()).is_none());
+            assert!(b.merge(update).is_none());
+        }
+
+        assert_eq!(values(&a), values(&b));
+        assert_eq!(values(&a)["shapes/s1"], None);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::error::Error;
+use crate::paths;
+use crate::workspace;
+use std::path::Path;
+use tauri::AppHandle;
+
+const CONFIG: &str = "conditions";
+
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    Unknown,
+}
+
+/// Power and network state background work is scheduled around
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemConditions {
+    pub power_source: PowerSource,
+    pub battery_percent: Option<u8>,
+    /// Battery saver or Low Power Mode, where the OS reports it
+    pub power_saver: Option<bool>,
+    /// Whether the active connection is metered; `None` when the OS does not say
+    pub metered: Option<bool>,
+}
+
+/// When low-priority background work waits, stored in `.inkfinite/conditions.json`
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConditionPolicy {
+    pub defer_on_battery: bool,
+    /// Defer below this charge even when `defer_on_battery` is off
+    pub min_battery_percent: Option<u8>,
+    pub defer_in_power_saver: bool,
+    /// Only applies to work that uses the network
+    pub defer_on_metered: bool,
+}
+
+impl Default for ConditionPolicy {
+    fn default() -> Self {
+        ConditionPolicy {
+            defer_on_battery: false,
+            min_battery_percent: Some(20),
+            defer_in_power_saver: true,
+            defer_on_metered: true,
+        }
+    }
+}
+
+/// Current power source, battery level, and network cost
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_system_conditions() -> SystemConditions {
+    current()
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_condition_policy(app: AppHandle, workspace: String) -> Result<ConditionPolicy, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(workspace::read_config(Path::new(&workspace), CONFIG)?)
+}
+
+/// Change when low-priority background work is deferred for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_condition_policy(
+    app: AppHandle,
+    workspace: String,
+    policy: ConditionPolicy,
+) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    Ok(workspace::write_config(
+        Path::new(&workspace),
+        CONFIG,
+        &policy,
+    )?)
+}
+
+/// Why low-priority background work in a workspace should wait, or `None` to run it now.
+///
+/// Explicit user actions are never deferred; this is only for scheduled and automatic work.
+pub fn defer_reason(root: &Path, uses_network: bool) -> Option<String> {
+    let policy: ConditionPolicy = workspace::read_config(root, CONFIG).unwrap_or_default();
+    let conditions = current();
+    let on_battery = conditions.power_source == PowerSource::Battery;
+
+    if on_battery && policy.defer_on_battery {
+        return Some("running on battery".to_string());
+    }
+    if let (true, Some(percent), Some(min)) = (
+        on_battery,
+        conditions.battery_percent,
+        policy.min_battery_percent,
+    ) {
+        if percent < min {
+            return Some(format!("battery at {}%", percent));
+        }
+    }
+    if policy.defer_in_power_saver && conditions.power_saver == Some(true) {
+        return Some("power saver is on".to_string());
+    }
+    if uses_network && policy.defer_on_metered && conditions.metered == Some(true) {
+        return Some("network is metered".to_string());
+    }
+    None
+}
+
+#[cfg(windows)]
+fn current() -> SystemConditions {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    let power = unsafe { GetSystemPowerStatus(&mut status) }.ok();
+    let (power_source, battery_percent, power_saver) = match power {
+        Some(()) => (
+            match status.ACLineStatus {
+                0 => PowerSource::Battery,
+                1 => PowerSource::Ac,
+                _ => PowerSource::Unknown,
+            },
+            // 255 means the charge is unknown, as on desktops without a battery
+            (status.BatteryLifePercent <= 100).then_some(status.BatteryLifePercent),
+            Some(status.SystemStatusFlag == 1),
+        ),
+        None => (PowerSource::Unknown, None, None),
+    };
+    SystemConditions {
+        power_source,
+        battery_percent,
+        power_saver,
+        metered: windows_metered(),
+    }
+}
+
+#[cfg(windows)]
+fn windows_metered() -> Option<bool> {
+    use windows::Networking::Connectivity::{NetworkCostType, NetworkInformation};
+
+    let cost = NetworkInformation::GetInternetConnectionProfile()
+        .and_then(|profile| profile.GetConnectionCost())
+        .ok()?;
+    let kind = cost.NetworkCostType().ok()?;
+    Some(
+        kind == NetworkCostType::Fixed
+            || kind == NetworkCostType::Variable
+            || cost.Roaming().unwrap_or(false)
+            || cost.OverDataLimit().unwrap_or(false),
+    )
+}
+
+/// `pmset` reports the source and charge; macOS exposes network cost only through the
+/// Network framework, so metered stays unknown
+#[cfg(target_os = "macos")]
+fn current() -> SystemConditions {
+    let pmset = |args: &[&str]| {
+        std::process::Command::new("pmset")
+            .args(args)
+            .output()
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+    };
+
+    let battery = pmset(&["-g", "batt"]).unwrap_or_default();
+    let power_source = if battery.contains("'AC Power'") {
+        PowerSource::Ac
+    } else if battery.contains("'Battery Power'") {
+        PowerSource::Battery
+    } else {
+        PowerSource::Unknown
+    };
+    let battery_percent = battery
+        .split_whitespace()
+        .find_map(|word| word.strip_suffix("%;"))
+        .and_then(|percent| percent.parse().ok());
+    let power_saver = pmset(&["-g"]).map(|settings| {
+        settings.lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some("lowpowermode") && fields.next() == Some("1")
+        })
+    });
+
+    SystemConditions {
+        power_source,
+        battery_percent,
+        power_saver,
+        metered: None,
+    }
+}
+
+/// Power supplies come from sysfs and network cost from NetworkManager
+#[cfg(target_os = "linux")]
+fn current() -> SystemConditions {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut on_mains = None;
+    let mut battery_percent = None;
+    if let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") {
+        for supply in entries.flatten().map(|entry| entry.path()) {
+            match read(&supply.join("type")).as_str() {
+                "Mains" => {
+                    on_mains =
+                        Some(on_mains.unwrap_or(false) || read(&supply.join("online")) == "1")
+                }
+                "Battery" => {
+                    battery_percent =
+                        battery_percent.or(read(&supply.join("capacity")).parse().ok())
+                }
+                _ => {}
+            }
+        }
+    }
+    let power_source = match (on_mains, battery_percent) {
+        (Some(true), _) => PowerSource::Ac,
+        (Some(false), _) => PowerSource::Battery,
+        (None, Some(_)) => PowerSource::Battery,
+        (None, None) => PowerSource::Unknown,
+    };
+
+    SystemConditions {
+        power_source,
+        battery_percent,
+        power_saver: None,
+        metered: linux_metered(),
+    }
+}
+
+/// NetworkManager answers `(<uint32 N>,)`: 1 and 3 are metered (known or guessed), 2 and 4
+/// are not, 0 is unknown
+#[cfg(target_os = "linux")]
+fn linux_metered() -> Option<bool> {
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--system",
+            "--dest",
+            "org.freedesktop.NetworkManager",
+            "--object-path",
+            "/org/freedesktop/NetworkManager",
+            "--method",
+            "org.freedesktop.DBus.Properties.Get",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let value = text
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .nth(1)?;
+    match value {
+        "1" | "3" => Some(true),
+        "2" | "4" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+fn current() -> SystemConditions {
+    SystemConditions {
+        power_source: PowerSource::Unknown,
+        battery_percent: None,
+        power_saver: None,
+        metered: None,
+    }
+}
@@ -0,0 +1,70 @@
+use crate::document::now_millis;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a preview's token can be presented to carry out the operation
+const TOKEN_TTL: Duration = Duration::from_secs(120);
+
+/// Preview of a dangerous operation together with the token that carries it out
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Confirmation<T> {
+    pub token: String,
+    /// Unix milliseconds after which the token is refused
+    pub expires_at: i64,
+    pub preview: T,
+}
+
+struct Pending {
+    /// The operation and arguments the token was issued for
+    operation: String,
+    issued: Instant,
+}
+
+/// Managed state holding the tokens handed out with previews, so a mutation only runs on a
+/// second call presenting a token for the same arguments
+#[derive(Default)]
+pub struct Confirmations(Mutex<HashMap<String, Pending>>);
+
+impl Confirmations {
+    /// Hand out a single-use token for `operation`, which should spell out every argument
+    pub fn issue<T>(&self, operation: String, preview: T) -> Confirmation<T> {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        if let Ok(mut pending) = self.0.lock() {
+            pending.retain(|_, pending| pending.issued.elapsed() < TOKEN_TTL);
+            pending.insert(
+                token.clone(),
+                Pending {
+                    operation,
+                    issued: Instant::now(),
+                },
+            );
+        }
+        Confirmation {
+            token,
+            expires_at: now_millis() + TOKEN_TTL.as_millis() as i64,
+            preview,
+        }
+    }
+
+    /// Use up `token`, failing unless it was issued for `operation` and has not expired
+    pub fn redeem(&self, token: &str, operation: &str) -> Result<(), String> {
+        let pending = self
+            .0
+            .lock()
+            .map_err(|e| format!("Failed to read confirmations: {}", e))?
+            .remove(token);
+        match pending {
+            Some(pending)
+                if pending.operation == operation && pending.issued.elapsed() < TOKEN_TTL =>
+            {
+                Ok(())
+            }
+            _ => Err(
+                "Invalid confirmation token: it expired or was issued for another request"
+                    .to_string(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use crate::error::Error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, LogicalPosition, Manager, State, WebviewWindow, Wry};
+
+/// Prefix separating context menu item ids from the menu bar's
+const ID_PREFIX: &str = "context:";
+
+/// Entry in a context menu requested by the webview
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ContextMenuItem {
+    #[serde(rename_all = "camelCase")]
+    Item {
+        id: String,
+        label: String,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        /// Shown next to the label, as in `CmdOrCtrl+D`
+        accelerator: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Check {
+        id: String,
+        label: String,
+        checked: bool,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        accelerator: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Submenu {
+        label: String,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        items: Vec<ContextMenuItem>,
+    },
+    Separator,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Payload of `context-menu:selected`
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextMenuSelection {
+    pub id: String,
+    /// New state of a check item after the click
+    pub checked: Option<bool>,
+}
+
+/// The context menu currently shown, so a selection can be routed back to its window
+#[derive(Default)]
+pub struct ContextMenuState(Mutex<Option<OpenMenu>>);
+
+pub struct OpenMenu {
+    window: String,
+    checks: HashMap<String, CheckMenuItem<Wry>>,
+}
+
+/// Show a native context menu at the cursor, or at `x`/`y` in window coordinates.
+///
+/// The chosen item arrives as a `context-menu:selected` event on the calling window. Nothing is
+/// emitted when the menu is dismissed.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn show_context_menu(
+    window: WebviewWindow,
+    state: State<'_, ContextMenuState>,
+    items: Vec<ContextMenuItem>,
+    x: Option<f64>,
+    y: Option<f64>,
+) -> Result<(), Error> {
+    if items.is_empty() {
+        return Err("Context menu has no items".into());
+    }
+    let app = window.app_handle();
+    let mut checks = HashMap::new();
+    let menu = build_items(app, &items, &mut checks)
+        .and_then(|items| Menu::with_items(app, &refs(&items)))
+        .map_err(|e| format!("Failed to build context menu: {}", e))?;
+
+    if let Ok(mut open) = state.0.lock() {
+        *open = Some(OpenMenu {
+            window: window.label().to_string(),
+            checks,
+        });
+    }
+
+    Ok(match (x, y) {
+        (Some(x), Some(y)) => window.popup_menu_at(&menu, LogicalPosition::new(x, y)),
+        _ => window.popup_menu(&menu),
+    }
+    .map_err(|e| format!("Failed to show context menu: {}", e))?)
+}
+
+/// Route a context menu click to the window that opened it; false for other menu ids
+pub fn handle_event(app: &AppHandle, id: &str) -> bool {
+    let Some(id) = id.strip_prefix(ID_PREFIX) else {
+        return false;
+    };
+    let open = app
+        .state::<ContextMenuState>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|mut open| open.take());
+
+    if let Some(open) = open {
+        let checked = open.checks.get(id).and_then(|item| item.is_checked().ok());
+        let _ = app.emit_to(
+            open.window.as_str(),
+            "context-menu:selected",
+            ContextMenuSelection {
+                id: id.to_string(),
+                checked,
+            },
+        );
+    }
+    true
+}
+
+fn build_items(
+    app: &AppHandle,
+    items: &[ContextMenuItem],
+    checks: &mut HashMap<String, CheckMenuItem<Wry>>,
+) -> tauri::Result<Vec<Box<dyn IsMenuItem<Wry>>>> {
+    let mut built: Vec<Box<dyn IsMenuItem<Wry>>> = Vec::new();
+    for item in items {
+        match item {
+            ContextMenuItem::Item {
+                id,
+                label,
+                enabled,
+                accelerator,
+            } => built.push(Box::new(MenuItem::with_id(
+                app,
+                format!("{}{}", ID_PREFIX, id),
+                label,
+                *enabled,
+                accelerator.as_deref(),
+            )?)),
+            ContextMenuItem::Check {
+                id,
+                label,
+                checked,
+                enabled,
+                accelerator,
+            } => {
+                let check = CheckMenuItem::with_id(
+                    app,
+                    format!("{}{}", ID_PREFIX, id),
+                    label,
+                    *enabled,
+                    *checked,
+                    accelerator.as_deref(),
+                )?;
+                checks.insert(id.clone(), check.clone());
+                built.push(Box::new(check));
+            }
+            ContextMenuItem::Submenu {
+                label,
+                enabled,
+                items,
+            } => {
+                let children = build_items(app, items, checks)?;
+                built.push(Box::new(Submenu::with_items(
+                    app,
+                    label,
+                    *enabled,
+                    &refs(&children),
+                )?));
+            }
+            ContextMenuItem::Separator => built.push(Box::new(PredefinedMenuItem::separator(app)?)),
+        }
+    }
+    Ok(built)
+}
+
+fn refs(items: &[Box<dyn IsMenuItem<Wry>>]) -> Vec<&dyn IsMenuItem<Wry>> {
+    items.iter().map(|item| item.as_ref()).collect()
+}
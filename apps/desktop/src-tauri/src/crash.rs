@@ -0,0 +1,485 @@
+use crate::error::Error;
+use crate::{document, http, workspace};
+use base64::Engine;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+const CRASH_DIR: &str = "crashes";
+/// Reports kept before the oldest are deleted
+const MAX_REPORTS: usize = 20;
+/// Where reports are sent, set by release builds; other builds only keep them locally
+const ENDPOINT: Option<&str> = option_env!("INKFINITE_CRASH_ENDPOINT");
+/// Stands in for the signal name in the report written from the signal handler
+#[cfg(unix)]
+const SIGNAL_MARKER: &str = "\u{1}signal\u{1}";
+
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Set once a panic is written, so the abort that may follow is not reported again
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    /// `panic` or `native`
+    pub kind: String,
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: Option<String>,
+    /// Milliseconds since the epoch; reports written by the native handler take the file's time
+    pub created_at: i64,
+    #[serde(default)]
+    pub submitted: bool,
+    /// Whether a minidump was written alongside, on Windows
+    #[serde(default)]
+    pub minidump: bool,
+}
+
+impl CrashReport {
+    fn new(id: String, kind: &str, message: String) -> Self {
+        Self {
+            id,
+            kind: kind.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            message,
+            location: None,
+            thread: std::thread::current().name().map(str::to_string),
+            backtrace: None,
+            created_at: 0,
+            submitted: false,
+            minidump: false,
+        }
+    }
+}
+
+fn crash_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(CRASH_DIR))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+fn write_report(dir: &Path, report: &CrashReport) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(report)
+        .map_err(|e| format!("Failed to encode crash report: {}", e))?;
+    std::fs::write(dir.join(format!("{}.json", report.id)), json)
+        .map_err(|e| format!("Failed to write crash report: {}", e))
+}
+
+fn read_report(dir: &Path, id: &str) -> Result<CrashReport, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid crash report id: {}", id));
+    }
+    let path = dir.join(format!("{}.json", id));
+    let bytes = std::fs::read(&path).map_err(|_| format!("Crash report not found: {}", id))?;
+    let mut report: CrashReport = serde_json::from_slice(&bytes)
+        .map_err(|e| format!("Failed to read crash report: {}", e))?;
+    if report.created_at == 0 {
+        report.created_at = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_millis() as i64);
+    }
+    report.minidump = dir.join(format!("{}.dmp", id)).is_file();
+    Ok(report)
+}
+
+/// Every report, newest first
+fn reports(dir: &Path) -> Vec<CrashReport> {
+    let mut reports: Vec<CrashReport> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            (path.extension()? == "json")
+                .then(|| read_report(dir, path.file_stem()?.to_str()?).ok())
+                .flatten()
+        })
+        .collect();
+    reports.sort_by_key(|report| std::cmp::Reverse(report.created_at));
+    reports
+}
+
+fn prune(dir: &Path) {
+    for report in reports(dir).into_iter().skip(MAX_REPORTS) {
+        let _ = std::fs::remove_file(dir.join(format!("{}.json", report.id)));
+        let _ = std::fs::remove_file(dir.join(format!("{}.dmp", report.id)));
+    }
+}
+
+fn panic_message(info: &std::panic::PanicHookInfo) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+/// Write panics and fatal signals or unhandled exceptions to the app data dir. Nothing leaves the
+/// device unless a report is submitted.
+pub fn init(app: &AppHandle) {
+    let dir = match crash_dir(app) {
+        Ok(dir) => dir,
+        Err(error) => {
+            tracing::warn!(%error, "Crash reports are off");
+            return;
+        }
+    };
+    if let Err(error) = std::fs::create_dir_all(&dir) {
+        tracing::warn!(%error, "Failed to create crash report directory");
+        return;
+    }
+    prune(&dir);
+    let _ = DIR.set(dir.clone());
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(dir) = DIR.get() {
+            let mut report = CrashReport::new(new_id(), "panic", panic_message(info));
+            report.location = info.location().map(|location| location.to_string());
+            report.backtrace = Some(std::backtrace::Backtrace::force_capture().to_string());
+            report.created_at = document::now_millis();
+            if write_report(dir, &report).is_ok() {
+                PANICKED.store(true, Ordering::SeqCst);
+            }
+        }
+        previous(info);
+    }));
+    install_native(&dir);
+}
+
+#[cfg(unix)]
+struct Native {
+    path: std::ffi::CString,
+    /// The report rendered ahead of time around the signal name, since the handler may not
+    /// allocate
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+    previous: Vec<(libc::c_int, libc::sigaction)>,
+}
+
+#[cfg(unix)]
+const SIGNALS: [(libc::c_int, &str); 5] = [
+    (libc::SIGSEGV, "SIGSEGV"),
+    (libc::SIGBUS, "SIGBUS"),
+    (libc::SIGILL, "SIGILL"),
+    (libc::SIGFPE, "SIGFPE"),
+    (libc::SIGABRT, "SIGABRT"),
+];
+
+#[cfg(unix)]
+static NATIVE: OnceLock<Native> = OnceLock::new();
+
+/// Only async-signal-safe calls: write the pre-rendered report, then put the previous handler
+/// back so the signal does what it would have done once this returns
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int, _: *mut libc::siginfo_t, _: *mut libc::c_void) {
+    let Some(native) = NATIVE.get() else {
+        return;
+    };
+    let name = SIGNALS
+        .iter()
+        .find(|(number, _)| *number == signal)
+        .map_or("signal", |(_, name)| *name);
+    unsafe {
+        if signal != libc::SIGABRT || !PANICKED.load(Ordering::SeqCst) {
+            let fd = libc::open(
+                native.path.as_ptr(),
+                libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+                0o600,
+            );
+            if fd >= 0 {
+                for part in [&native.prefix[..], name.as_bytes(), &native.suffix[..]] {
+                    libc::write(fd, part.as_ptr().cast(), part.len());
+                }
+                libc::close(fd);
+            }
+        }
+        match native.previous.iter().find(|(number, _)| *number == signal) {
+            Some((_, previous)) => libc::sigaction(signal, previous, std::ptr::null_mut()),
+            None => {
+                libc::signal(signal, libc::SIG_DFL);
+                0
+            }
+        };
+    }
+    // Faults come back as soon as the instruction runs again; an abort is raised once more
+    if signal == libc::SIGABRT {
+        unsafe { libc::raise(signal) };
+    }
+}
+
+#[cfg(unix)]
+fn install_native(dir: &Path) {
+    let id = new_id();
+    let mut report = CrashReport::new(id.clone(), "native", format!("Fatal {}", SIGNAL_MARKER));
+    // Not known until the signal arrives
+    report.thread = None;
+    let Ok(json) = serde_json::to_string(&report) else {
+        return;
+    };
+    let Some((prefix, suffix)) = json.split_once(SIGNAL_MARKER) else {
+        return;
+    };
+    let path = dir.join(format!("{}.json", id));
+    let Ok(path) = std::ffi::CString::new(path.to_string_lossy().into_owned()) else {
+        return;
+    };
+    let previous = SIGNALS
+        .iter()
+        .map(|(signal, _)| {
+            let mut previous: libc::sigaction = unsafe { std::mem::zeroed() };
+            unsafe { libc::sigaction(*signal, std::ptr::null(), &mut previous) };
+            (*signal, previous)
+        })
+        .collect();
+    let native = Native {
+        path,
+        prefix: prefix.as_bytes().to_vec(),
+        suffix: suffix.as_bytes().to_vec(),
+        previous,
+    };
+    if NATIVE.set(native).is_err() {
+        return;
+    }
+    for (signal, _) in SIGNALS {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_signal as *const () as usize;
+            // On the alternate stack, which the runtime sets up, so stack overflows are caught too
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, std::ptr::null_mut());
+        }
+    }
+}
+
+/// Write a minidump and a report for an exception nothing else handled, then let Windows end
+/// the process as it would have
+#[cfg(windows)]
+unsafe extern "system" fn on_exception(
+    pointers: *const windows::Win32::System::Diagnostics::Debug::EXCEPTION_POINTERS,
+) -> i32 {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Diagnostics::Debug::{
+        MiniDumpNormal, MiniDumpWriteDump, MINIDUMP_EXCEPTION_INFORMATION,
+    };
+    use windows::Win32::System::Threading::{
+        GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId,
+    };
+
+    // EXCEPTION_CONTINUE_SEARCH
+    const CONTINUE: i32 = 0;
+    let Some(dir) = DIR.get() else {
+        return CONTINUE;
+    };
+    let id = new_id();
+    let code = unsafe { pointers.as_ref() }
+        .and_then(|pointers| unsafe { pointers.ExceptionRecord.as_ref() })
+        .map_or(0, |record| record.ExceptionCode.0 as u32);
+    let mut report = CrashReport::new(id.clone(), "native", format!("Exception 0x{:08X}", code));
+    report.created_at = document::now_millis();
+    if let Ok(file) = std::fs::File::create(dir.join(format!("{}.dmp", id))) {
+        let exception = MINIDUMP_EXCEPTION_INFORMATION {
+            ThreadId: unsafe { GetCurrentThreadId() },
+            ExceptionPointers: pointers as *mut _,
+            ClientPointers: false.into(),
+        };
+        report.minidump = unsafe {
+            MiniDumpWriteDump(
+                GetCurrentProcess(),
+                GetCurrentProcessId(),
+                HANDLE(file.as_raw_handle()),
+                MiniDumpNormal,
+                Some(&exception),
+                None,
+                None,
+            )
+        }
+        .is_ok();
+    }
+    let _ = write_report(dir, &report);
+    CONTINUE
+}
+
+#[cfg(windows)]
+fn install_native(_dir: &Path) {
+    use windows::Win32::System::Diagnostics::Debug::SetUnhandledExceptionFilter;
+
+    unsafe { SetUnhandledExceptionFilter(Some(on_exception)) };
+}
+
+#[cfg(not(any(unix, windows)))]
+fn install_native(_dir: &Path) {}
+
+/// Replace `from` with `placeholder` everywhere, along with the rest of each path that follows,
+/// unless it continues into one of `keep`
+fn replace_path(text: &str, from: &str, placeholder: &str, keep: &[&str]) -> String {
+    if from.len() < 2 {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(from) {
+        out.push_str(&rest[..start]);
+        out.push_str(placeholder);
+        let after = &rest[start + from.len()..];
+        let end = after
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '`' | ')' | ','))
+            .unwrap_or(after.len());
+        let tail = &after[..end];
+        if keep.iter().any(|kept| tail.starts_with(kept)) {
+            out.push_str(tail);
+        } else if !tail.is_empty() {
+            out.push_str("/…");
+        }
+        rest = &after[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Blank out text between double quotes or backticks, which is where panic messages quote values
+fn redact_quoted(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['"', '`']) {
+        let quote = rest[start..].chars().next().unwrap_or('"');
+        let Some(len) = rest[start + 1..].find(quote) else {
+            break;
+        };
+        out.push_str(&rest[..=start]);
+        out.push_str("<redacted>");
+        out.push(quote);
+        rest = &rest[start + 2 + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A copy of the report without workspace paths, file names or quoted content
+fn scrub(app: &AppHandle, report: &CrashReport) -> CrashReport {
+    let mut roots: Vec<(String, &str)> = Vec::new();
+    if let Some(root) = workspace::current_root(app) {
+        roots.push((root.to_string_lossy().into_owned(), "<workspace>"));
+    }
+    for file in workspace::recent_files(app) {
+        if let Some(parent) = Path::new(&file.path).parent() {
+            roots.push((parent.to_string_lossy().into_owned(), "<document>"));
+        }
+    }
+    if let Ok(dir) = app.path().app_data_dir() {
+        roots.push((dir.to_string_lossy().into_owned(), "<data>"));
+    }
+    if let Ok(home) = app.path().home_dir() {
+        roots.push((home.to_string_lossy().into_owned(), "<home>"));
+    }
+    // Longest first, so a workspace inside the home folder is named as the workspace
+    roots.sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+    // Toolchain sources in backtraces say nothing about the user
+    let keep = ["/.cargo/", "/.rustup/", "\\.cargo\\", "\\.rustup\\"];
+    let clean = |text: &str| {
+        let text = roots
+            .iter()
+            .fold(text.to_string(), |text, (from, placeholder)| {
+                let kept: &[&str] = if *placeholder == "<home>" { &keep } else { &[] };
+                replace_path(&text, from, placeholder, kept)
+            });
+        redact_quoted(&text)
+    };
+    CrashReport {
+        message: clean(&report.message),
+        location: report.location.as_deref().map(clean),
+        thread: report.thread.as_deref().map(clean),
+        backtrace: report.backtrace.as_deref().map(clean),
+        ..report.clone()
+    }
+}
+
+/// Crash reports kept on this device, newest first
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_crash_reports(app: AppHandle) -> Result<Vec<CrashReport>, Error> {
+    Ok(reports(&crash_dir(&app)?))
+}
+
+/// A report exactly as it would be submitted, with paths and quoted content scrubbed
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_crash_report(app: AppHandle, id: String) -> Result<CrashReport, Error> {
+    let report = read_report(&crash_dir(&app)?, &id)?;
+    Ok(scrub(&app, &report))
+}
+
+/// Upload a scrubbed report. The minidump holds raw process memory that cannot be scrubbed, so it
+/// is only sent when `includeMinidump` is set.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn submit_crash_report(
+    app: AppHandle,
+    id: String,
+    include_minidump: Option<bool>,
+) -> Result<CrashReport, Error> {
+    let endpoint =
+        ENDPOINT.ok_or_else(|| "Crash reporting is not supported in this build".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let dir = crash_dir(&app)?;
+        let mut report = read_report(&dir, &id)?;
+        let minidump = match include_minidump.unwrap_or(false) && report.minidump {
+            true => {
+                let bytes = std::fs::read(dir.join(format!("{}.dmp", id)))
+                    .map_err(|e| format!("Failed to read minidump: {}", e))?;
+                Some(base64::engine::general_purpose::STANDARD.encode(bytes))
+            }
+            false => None,
+        };
+        let body = serde_json::json!({
+            "report": scrub(&app, &report),
+            "minidump": minidump,
+        });
+        match http::agent()
+            .post(endpoint)
+            .header("content-type", "application/json")
+            .send(body.to_string())
+        {
+            Ok(_) => {}
+            Err(ureq::Error::StatusCode(status)) => {
+                return Err(format!("Crash report server replied with HTTP {}", status).into())
+            }
+            Err(e) => return Err(format!("Failed to submit crash report: {}", e).into()),
+        }
+        report.submitted = true;
+        write_report(&dir, &report)?;
+        tracing::info!(id = %report.id, "Crash report submitted");
+        Ok(report)
+    })
+    .await
+    .map_err(|e| format!("Crash report task failed: {}", e))?
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_crash_report(app: AppHandle, id: String) -> Result<(), Error> {
+    let dir = crash_dir(&app)?;
+    read_report(&dir, &id)?;
+    std::fs::remove_file(dir.join(format!("{}.json", id)))
+        .map_err(|e| format!("Failed to delete crash report: {}", e))?;
+    let _ = std::fs::remove_file(dir.join(format!("{}.dmp", id)));
+    Ok(())
+}
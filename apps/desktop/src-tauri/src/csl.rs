@@ -0,0 +1,1532 @@
+use crate::http;
+use crate::references::{self, CslDate, Name, Reference};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+/// Downloaded styles, kept in the app data folder
+const STYLES_DIR: &str = "csl-styles";
+/// The official CSL style repository; dependent styles live in its `dependent` folder
+const STYLES_URL: &str = "https://raw.githubusercontent.com/citation-style-language/styles/master";
+/// Deepest macro nesting followed, so macros that call each other cannot loop
+const MAX_DEPTH: usize = 32;
+
+/// English terms, used where the style's own locale does not define one: name, form, singular
+/// and plural
+const TERMS: &[(&str, &str, &str, &str)] = &[
+    ("and", "long", "and", "and"),
+    ("and", "symbol", "&", "&"),
+    ("et-al", "long", "et al.", "et al."),
+    ("and others", "long", "and others", "and others"),
+    ("anonymous", "long", "anonymous", "anonymous"),
+    ("anonymous", "short", "anon.", "anon."),
+    ("accessed", "long", "accessed", "accessed"),
+    ("retrieved", "long", "retrieved", "retrieved"),
+    ("available at", "long", "available at", "available at"),
+    ("from", "long", "from", "from"),
+    ("in", "long", "in", "in"),
+    ("by", "long", "by", "by"),
+    ("online", "long", "online", "online"),
+    ("presented at", "long", "presented at", "presented at"),
+    ("forthcoming", "long", "forthcoming", "forthcoming"),
+    ("no date", "long", "no date", "no dates"),
+    ("no date", "short", "n.d.", "n.d."),
+    ("circa", "long", "circa", "circa"),
+    ("circa", "short", "c.", "c."),
+    ("ibid", "long", "ibid.", "ibid."),
+    ("edition", "long", "edition", "editions"),
+    ("edition", "short", "ed.", "eds."),
+    ("editor", "long", "editor", "editors"),
+    ("editor", "short", "ed.", "eds."),
+    ("editor", "verb", "edited by", "edited by"),
+    ("editor", "verb-short", "ed. by", "ed. by"),
+    ("collection-editor", "long", "editor", "editors"),
+    ("collection-editor", "short", "ed.", "eds."),
+    ("container-author", "verb", "by", "by"),
+    ("translator", "long", "translator", "translators"),
+    ("translator", "short", "trans.", "trans."),
+    ("translator", "verb", "translated by", "translated by"),
+    ("translator", "verb-short", "trans.", "trans."),
+    ("director", "long", "director", "directors"),
+    ("director", "short", "dir.", "dirs."),
+    ("director", "verb", "directed by", "directed by"),
+    ("page", "long", "page", "pages"),
+    ("page", "short", "p.", "pp."),
+    ("number-of-pages", "long", "page", "pages"),
+    ("number-of-pages", "short", "p.", "pp."),
+    ("volume", "long", "volume", "volumes"),
+    ("volume", "short", "vol.", "vols."),
+    ("number-of-volumes", "long", "volume", "volumes"),
+    ("number-of-volumes", "short", "vol.", "vols."),
+    ("issue", "long", "issue", "issues"),
+    ("issue", "short", "no.", "nos."),
+    ("number", "long", "number", "numbers"),
+    ("number", "short", "no.", "nos."),
+    ("chapter", "long", "chapter", "chapters"),
+    ("chapter", "short", "chap.", "chaps."),
+    ("section", "long", "section", "sections"),
+    ("section", "short", "sec.", "secs."),
+    ("paragraph", "long", "paragraph", "paragraphs"),
+    ("paragraph", "short", "para.", "paras."),
+    ("open-quote", "long", "“", "“"),
+    ("close-quote", "long", "”", "”"),
+    ("open-inner-quote", "long", "‘", "‘"),
+    ("close-inner-quote", "long", "’", "’"),
+    ("ordinal", "long", "th", "th"),
+    ("ordinal-01", "long", "st", "st"),
+    ("ordinal-02", "long", "nd", "nd"),
+    ("ordinal-03", "long", "rd", "rd"),
+    ("long-ordinal-01", "long", "first", "first"),
+    ("long-ordinal-02", "long", "second", "second"),
+    ("long-ordinal-03", "long", "third", "third"),
+    ("long-ordinal-04", "long", "fourth", "fourth"),
+    ("long-ordinal-05", "long", "fifth", "fifth"),
+    ("long-ordinal-06", "long", "sixth", "sixth"),
+    ("long-ordinal-07", "long", "seventh", "seventh"),
+    ("long-ordinal-08", "long", "eighth", "eighth"),
+    ("long-ordinal-09", "long", "ninth", "ninth"),
+    ("long-ordinal-10", "long", "tenth", "tenth"),
+    ("month-01", "long", "January", "January"),
+    ("month-02", "long", "February", "February"),
+    ("month-03", "long", "March", "March"),
+    ("month-04", "long", "April", "April"),
+    ("month-05", "long", "May", "May"),
+    ("month-06", "long", "June", "June"),
+    ("month-07", "long", "July", "July"),
+    ("month-08", "long", "August", "August"),
+    ("month-09", "long", "September", "September"),
+    ("month-10", "long", "October", "October"),
+    ("month-11", "long", "November", "November"),
+    ("month-12", "long", "December", "December"),
+    ("month-01", "short", "Jan.", "Jan."),
+    ("month-02", "short", "Feb.", "Feb."),
+    ("month-03", "short", "Mar.", "Mar."),
+    ("month-04", "short", "Apr.", "Apr."),
+    ("month-05", "short", "May", "May"),
+    ("month-06", "short", "Jun.", "Jun."),
+    ("month-07", "short", "Jul.", "Jul."),
+    ("month-08", "short", "Aug.", "Aug."),
+    ("month-09", "short", "Sep.", "Sep."),
+    ("month-10", "short", "Oct.", "Oct."),
+    ("month-11", "short", "Nov.", "Nov."),
+    ("month-12", "short", "Dec.", "Dec."),
+    ("season-01", "long", "Spring", "Spring"),
+    ("season-02", "long", "Summer", "Summer"),
+    ("season-03", "long", "Autumn", "Autumn"),
+    ("season-04", "long", "Winter", "Winter"),
+];
+
+/// Words left lowercase inside a title-cased title
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+    "or", "the", "to", "up", "vs", "with",
+];
+
+/// An element of a style
+#[derive(Default)]
+struct Node {
+    name: String,
+    attrs: HashMap<String, String>,
+    children: Vec<Node>,
+    text: String,
+}
+
+impl Node {
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs.get(key).map(String::as_str)
+    }
+
+    fn is(&self, key: &str, value: &str) -> bool {
+        self.attr(key) == Some(value)
+    }
+
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+fn element(start: &BytesStart) -> Result<Node, String> {
+    let mut attrs = HashMap::new();
+    for attribute in start.attributes().with_checks(false).flatten() {
+        let value = attribute
+            .unescape_value()
+            .map_err(|e| format!("Invalid CSL style: {}", e))?;
+        attrs.insert(
+            String::from_utf8_lossy(attribute.key.as_ref()).to_string(),
+            value.to_string(),
+        );
+    }
+    Ok(Node {
+        name: String::from_utf8_lossy(start.local_name().as_ref()).to_string(),
+        attrs,
+        ..Default::default()
+    })
+}
+
+/// The `style` element of a CSL file as a tree
+fn parse_xml(source: &str) -> Result<Node, String> {
+    let mut reader = Reader::from_str(source);
+    let mut stack = vec![Node::default()];
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            format!(
+                "Invalid CSL style at byte {}: {}",
+                reader.error_position(),
+                e
+            )
+        })?;
+        let text = match event {
+            Event::Start(start) => {
+                stack.push(element(&start)?);
+                continue;
+            }
+            Event::Empty(start) => {
+                let node = element(&start)?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(node);
+                }
+                continue;
+            }
+            Event::End(_) => {
+                if stack.len() > 1 {
+                    let node = stack.pop().unwrap_or_default();
+                    if let Some(parent) = stack.last_mut() {
+                        parent.children.push(node);
+                    }
+                }
+                continue;
+            }
+            Event::Text(text) => text.decode().map(|text| text.to_string()).ok(),
+            Event::CData(data) => data.decode().map(|text| text.to_string()).ok(),
+            Event::GeneralRef(reference) => match reference.resolve_char_ref() {
+                Ok(Some(c)) => Some(c.to_string()),
+                _ => reference.decode().ok().and_then(|name| {
+                    quick_xml::escape::resolve_predefined_entity(&name).map(str::to_string)
+                }),
+            },
+            Event::Eof => break,
+            _ => None,
+        };
+        if let (Some(text), Some(node)) = (text, stack.last_mut()) {
+            node.text.push_str(&text);
+        }
+    }
+    stack
+        .pop()
+        .filter(|_| stack.is_empty())
+        .and_then(|document| {
+            document
+                .children
+                .into_iter()
+                .find(|node| node.name == "style")
+        })
+        .ok_or_else(|| "Invalid CSL style: no style element".to_string())
+}
+
+/// A CSL style, rendered with the subset of CSL 1.0 that reference lists use: macros,
+/// conditions, groups, names with et-al rules, dates, numbers, labels and sorting.
+/// Disambiguation and subsequent-author substitution are not applied, and terms the style does
+/// not define itself are English.
+pub struct CslStyle {
+    pub title: String,
+    /// Id of the style a dependent style formats with
+    pub parent: Option<String>,
+    /// Attributes of the `style` element, which `citation` and `bibliography` inherit
+    options: HashMap<String, String>,
+    macros: HashMap<String, Node>,
+    citation: Option<Node>,
+    bibliography: Option<Node>,
+    /// Terms from the style's own English or unlabelled locale, by name and form
+    terms: HashMap<(String, String), (String, String)>,
+}
+
+impl CslStyle {
+    pub fn parse(source: &str) -> Result<CslStyle, String> {
+        let root = parse_xml(source)?;
+        let mut style = CslStyle {
+            title: String::new(),
+            parent: None,
+            options: root.attrs,
+            macros: HashMap::new(),
+            citation: None,
+            bibliography: None,
+            terms: HashMap::new(),
+        };
+        for node in root.children {
+            match node.name.as_str() {
+                "info" => {
+                    for info in &node.children {
+                        match info.name.as_str() {
+                            "title" => style.title = info.text.trim().to_string(),
+                            "link" if info.is("rel", "independent-parent") => {
+                                style.parent = info
+                                    .attr("href")
+                                    .and_then(|href| href.trim_end_matches('/').rsplit('/').next())
+                                    .map(str::to_string);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                "macro" => {
+                    if let Some(name) = node.attr("name").map(str::to_string) {
+                        style.macros.insert(name, node);
+                    }
+                }
+                "citation" => style.citation = Some(node),
+                "bibliography" => style.bibliography = Some(node),
+                "locale" => {
+                    let english = node
+                        .attr("xml:lang")
+                        .is_none_or(|lang| lang.starts_with("en"));
+                    if !english {
+                        continue;
+                    }
+                    let terms = node.children.iter().filter(|child| child.name == "terms");
+                    for term in terms.flat_map(|terms| &terms.children) {
+                        let Some(name) = term.attr("name") else {
+                            continue;
+                        };
+                        let single = term
+                            .child("single")
+                            .map_or(term.text.trim(), |single| single.text.trim());
+                        let multiple = term
+                            .child("multiple")
+                            .map_or(single, |multiple| multiple.text.trim());
+                        style.terms.insert(
+                            (
+                                name.to_string(),
+                                term.attr("form").unwrap_or("long").to_string(),
+                            ),
+                            (single.to_string(), multiple.to_string()),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        if style.citation.is_none() && style.bibliography.is_none() && style.parent.is_none() {
+            return Err("Invalid CSL style: it has no citation or bibliography".to_string());
+        }
+        Ok(style)
+    }
+
+    /// Whether the style only points at another style to format with
+    pub fn is_dependent(&self) -> bool {
+        self.parent.is_some() && self.citation.is_none() && self.bibliography.is_none()
+    }
+
+    /// Options of `style` with those of the `citation` or `bibliography` element on top
+    fn inherited(&self, node: &Node) -> HashMap<String, String> {
+        let mut options = self.options.clone();
+        options.extend(node.attrs.iter().map(|(k, v)| (k.clone(), v.clone())));
+        options
+    }
+
+    /// Reference list entries as Markdown, with their keys, for `items` in cited order; the
+    /// entries are sorted as the style asks and numbered in that order
+    pub fn bibliography(&self, items: &[&Reference]) -> Result<Vec<(String, String)>, String> {
+        let bibliography = self
+            .bibliography
+            .as_ref()
+            .ok_or("Reference lists are not supported by this citation style")?;
+        let layout = bibliography
+            .child("layout")
+            .ok_or("Invalid CSL style: the bibliography has no layout")?;
+        let options = self.inherited(bibliography);
+        let mut ordered: Vec<&Reference> = items.to_vec();
+        if let Some(sort) = bibliography.child("sort") {
+            let keys: Vec<&Node> = sort.children.iter().filter(|k| k.name == "key").collect();
+            let mut keyed: Vec<(Vec<String>, usize, &Reference)> = items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let values = keys
+                        .iter()
+                        .map(|key| {
+                            Renderer::new(self, item, index + 1, None, &options).sort_key(key)
+                        })
+                        .collect();
+                    (values, index, *item)
+                })
+                .collect();
+            keyed.sort_by(|(a, first, _), (b, second, _)| {
+                a.iter()
+                    .zip(b)
+                    .zip(&keys)
+                    .map(|((a, b), key)| match (a.is_empty(), b.is_empty()) {
+                        (true, true) => Ordering::Equal,
+                        // Items without the value go last either way
+                        (true, false) => Ordering::Greater,
+                        (false, true) => Ordering::Less,
+                        _ if key.is("sort", "descending") => b.cmp(a),
+                        _ => a.cmp(b),
+                    })
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or_else(|| first.cmp(second))
+            });
+            ordered = keyed.into_iter().map(|(_, _, item)| item).collect();
+        }
+        Ok(ordered
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let mut renderer = Renderer::new(self, item, index + 1, None, &options);
+                let out = renderer.children(&layout.children, None);
+                (item.id.clone(), tidy(&renderer.decorate(layout, out.text)))
+            })
+            .collect())
+    }
+
+    /// The in-text citation of one item, citing `locator` when given
+    pub fn citation(&self, item: &Reference, locator: Option<&str>) -> Result<String, String> {
+        let citation = self
+            .citation
+            .as_ref()
+            .ok_or("Invalid CSL style: it has no citation")?;
+        let layout = citation
+            .child("layout")
+            .ok_or("Invalid CSL style: the citation has no layout")?;
+        let options = self.inherited(citation);
+        let mut renderer = Renderer::new(self, item, 1, locator, &options);
+        let out = renderer.children(&layout.children, None);
+        Ok(tidy(&renderer.decorate(layout, out.text)))
+    }
+}
+
+/// Rendered text of an element, and whether it asked for variables and found any; a group
+/// is left out when every variable it asked for is empty
+#[derive(Default)]
+struct Out {
+    text: String,
+    called: bool,
+    found: bool,
+}
+
+impl Out {
+    fn text(text: String) -> Out {
+        Out {
+            text,
+            ..Default::default()
+        }
+    }
+
+    fn called() -> Out {
+        Out {
+            called: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Renders one item
+struct Renderer<'a> {
+    style: &'a CslStyle,
+    item: &'a Reference,
+    number: usize,
+    locator: Option<&'a str>,
+    options: &'a HashMap<String, String>,
+    /// Variables a `substitute` used, which count as empty for the rest of the item
+    suppressed: HashSet<String>,
+    /// Variables rendered so far, to know which ones a substitute used
+    rendered: Vec<String>,
+    depth: usize,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(
+        style: &'a CslStyle,
+        item: &'a Reference,
+        number: usize,
+        locator: Option<&'a str>,
+        options: &'a HashMap<String, String>,
+    ) -> Renderer<'a> {
+        Renderer {
+            style,
+            item,
+            number,
+            locator,
+            options,
+            suppressed: HashSet::new(),
+            rendered: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    fn children(&mut self, nodes: &'a [Node], delimiter: Option<&str>) -> Out {
+        let mut out = Out::default();
+        let mut parts = Vec::new();
+        for node in nodes {
+            let rendered = self.render(node);
+            out.called |= rendered.called;
+            out.found |= rendered.found;
+            if !rendered.text.is_empty() {
+                parts.push(rendered.text);
+            }
+        }
+        out.text = parts.join(delimiter.unwrap_or(""));
+        out
+    }
+
+    fn render(&mut self, node: &'a Node) -> Out {
+        match node.name.as_str() {
+            "text" => self.text(node),
+            "number" => self.number(node),
+            "label" => self.label(node),
+            "date" => self.date(node),
+            "names" => self.names(node, None),
+            "group" => {
+                let inner = self.children(&node.children, node.attr("delimiter"));
+                if inner.called && !inner.found {
+                    return Out::called();
+                }
+                Out {
+                    text: self.decorate(node, inner.text),
+                    called: inner.called,
+                    found: inner.found,
+                }
+            }
+            "choose" => node
+                .children
+                .iter()
+                .find(|branch| self.condition(branch))
+                .map(|branch| self.children(&branch.children, None))
+                .unwrap_or_default(),
+            _ => Out::default(),
+        }
+    }
+
+    /// Output for a variable, empty when a substitute already used it
+    fn variable(&mut self, name: &str, text: String) -> Out {
+        if self.suppressed.contains(name) || text.is_empty() {
+            return Out::called();
+        }
+        self.rendered.push(name.to_string());
+        Out {
+            text,
+            called: true,
+            found: true,
+        }
+    }
+
+    /// A text variable as stored on the item, empty when it has none
+    fn value(&self, name: &str) -> String {
+        let item = self.item;
+        let field = match name {
+            "title" => Some(item.title.clone()),
+            "container-title" => item.container_title.clone(),
+            "publisher" => item.publisher.clone(),
+            "publisher-place" => item.publisher_place.clone(),
+            "volume" => item.volume.clone(),
+            "issue" => item.issue.clone(),
+            "page" => item.page.clone(),
+            "DOI" => item.doi.clone(),
+            "URL" => item.url.clone(),
+            "ISBN" => item.isbn.clone(),
+            "abstract" => item.abstract_text.clone(),
+            "citation-number" => Some(self.number.to_string()),
+            "citation-key" => Some(item.id.clone()),
+            "locator" => self.locator.map(str::to_string),
+            _ => None,
+        };
+        field
+            .or_else(|| match item.other.get(name) {
+                Some(Value::String(text)) => Some(text.clone()),
+                Some(Value::Number(number)) => Some(number.to_string()),
+                _ => None,
+            })
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    fn names_of(&self, name: &str) -> Vec<Name> {
+        match name {
+            "author" => self.item.author.clone(),
+            "editor" => self.item.editor.clone(),
+            _ => self
+                .item
+                .other
+                .get(name)
+                .and_then(|value| serde_json::from_value(value.clone()).ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn date_of(&self, name: &str) -> Option<CslDate> {
+        let date = match name {
+            "issued" => self.item.issued.clone(),
+            _ => self
+                .item
+                .other
+                .get(name)
+                .and_then(|value| serde_json::from_value(value.clone()).ok()),
+        };
+        date.filter(|date: &CslDate| {
+            !date.date_parts.is_empty() || date.raw.is_some() || date.literal.is_some()
+        })
+    }
+
+    fn has(&self, name: &str) -> bool {
+        !self.suppressed.contains(name)
+            && (!self.value(name).is_empty()
+                || !self.names_of(name).is_empty()
+                || self.date_of(name).is_some())
+    }
+
+    fn condition(&self, branch: &Node) -> bool {
+        match branch.name.as_str() {
+            "else" => return true,
+            "if" | "else-if" => {}
+            _ => return false,
+        }
+        let mut tests = Vec::new();
+        for (attribute, values) in &branch.attrs {
+            let values = values.split_whitespace();
+            match attribute.as_str() {
+                "type" => tests.extend(values.map(|kind| self.item.kind == kind)),
+                "variable" => tests.extend(values.map(|name| self.has(name))),
+                "is-numeric" => tests.extend(values.map(|name| is_numeric(&self.value(name)))),
+                "is-uncertain-date" => tests.extend(values.map(|_| false)),
+                "locator" => tests.extend(values.map(|_| self.locator.is_some())),
+                // Every item is rendered as a first, full citation
+                "position" => tests.extend(values.map(|position| position == "first")),
+                "disambiguate" => tests.extend(values.map(|value| value == "false")),
+                _ => {}
+            }
+        }
+        match branch.attr("match") {
+            Some("any") => tests.iter().any(|test| *test),
+            Some("none") => !tests.iter().any(|test| *test),
+            _ => tests.iter().all(|test| *test),
+        }
+    }
+
+    fn text(&mut self, node: &'a Node) -> Out {
+        if let Some(name) = node.attr("variable") {
+            let short = match node.is("form", "short") {
+                true => Some(self.value(&format!("{}-short", name))).filter(|v| !v.is_empty()),
+                false => None,
+            };
+            let value = short.unwrap_or_else(|| self.value(name));
+            let value = match name {
+                "page" | "locator" => references::pages(&value),
+                _ => value,
+            };
+            let out = self.variable(name, references::md(&value));
+            return Out {
+                text: self.decorate(node, out.text),
+                ..out
+            };
+        }
+        if let Some(name) = node.attr("macro") {
+            let Some(definition) = self.style.macros.get(name) else {
+                return Out::default();
+            };
+            if self.depth >= MAX_DEPTH {
+                return Out::default();
+            }
+            self.depth += 1;
+            let inner = self.children(&definition.children, None);
+            self.depth -= 1;
+            return Out {
+                text: self.decorate(node, inner.text),
+                ..inner
+            };
+        }
+        if let Some(name) = node.attr("term") {
+            let term = self.term(
+                name,
+                node.attr("form").unwrap_or("long"),
+                node.is("plural", "true"),
+            );
+            return Out::text(self.decorate(node, term));
+        }
+        match node.attr("value") {
+            Some(value) => Out::text(self.decorate(node, references::md(value))),
+            None => Out::default(),
+        }
+    }
+
+    fn number(&mut self, node: &'a Node) -> Out {
+        let Some(name) = node.attr("variable") else {
+            return Out::default();
+        };
+        let value = self.value(name);
+        let text = match (value.parse::<u32>(), node.attr("form")) {
+            (Ok(number), Some("ordinal")) => format!("{}{}", number, self.ordinal(number)),
+            (Ok(number @ 1..=10), Some("long-ordinal")) => {
+                self.term(&format!("long-ordinal-{:02}", number), "long", false)
+            }
+            (Ok(number), Some("long-ordinal")) => format!("{}{}", number, self.ordinal(number)),
+            (Ok(number), Some("roman")) => roman(number),
+            _ => references::md(&value),
+        };
+        let out = self.variable(name, text);
+        Out {
+            text: self.decorate(node, out.text),
+            ..out
+        }
+    }
+
+    fn ordinal(&self, number: u32) -> String {
+        let term = match (number % 100, number % 10) {
+            (11..=13, _) => "ordinal".to_string(),
+            (_, last @ 1..=3) => format!("ordinal-{:02}", last),
+            _ => "ordinal".to_string(),
+        };
+        self.term(&term, "long", false)
+    }
+
+    fn label(&mut self, node: &'a Node) -> Out {
+        let Some(name) = node.attr("variable") else {
+            return Out::default();
+        };
+        let value = self.value(name);
+        if value.is_empty() || self.suppressed.contains(name) {
+            return Out::default();
+        }
+        let plural = match node.attr("plural") {
+            Some("always") => true,
+            Some("never") => false,
+            _ => is_plural(&value),
+        };
+        let term = match name {
+            "locator" => "page",
+            _ => name,
+        };
+        let term = self.term(term, node.attr("form").unwrap_or("long"), plural);
+        Out::text(self.decorate(node, term))
+    }
+
+    fn date(&mut self, node: &'a Node) -> Out {
+        let Some(name) = node.attr("variable") else {
+            return Out::default();
+        };
+        let Some(date) = self.date_of(name) else {
+            return Out::called();
+        };
+        let text = match date_parts(&date) {
+            None => date
+                .literal
+                .or(date.raw)
+                .map(|text| references::md(&text))
+                .unwrap_or_default(),
+            Some(parts) => match node.attr("form") {
+                Some(form) => self.localized_date(node, parts, form),
+                None => {
+                    let rendered: Vec<String> = node
+                        .children
+                        .iter()
+                        .filter(|child| child.name == "date-part")
+                        .filter_map(|part| self.date_part(part, parts))
+                        .collect();
+                    match rendered.is_empty() {
+                        true => parts.0.to_string(),
+                        false => rendered.join(node.attr("delimiter").unwrap_or("")),
+                    }
+                }
+            },
+        };
+        let out = self.variable(name, text);
+        Out {
+            text: self.decorate(node, out.text),
+            ..out
+        }
+    }
+
+    /// A `text` or `numeric` date in the English formats
+    fn localized_date(&self, node: &Node, parts: DateParts, form: &str) -> String {
+        let (year, month, day) = parts;
+        let shown = node.attr("date-parts").unwrap_or("year-month-day");
+        let month = month.filter(|_| shown != "year");
+        let day = day.filter(|_| shown == "year-month-day");
+        match (form, month, day) {
+            ("numeric", Some(month), Some(day)) => format!("{}/{}/{}", month, day, year),
+            ("numeric", Some(month), None) => format!("{}/{}", month, year),
+            (_, Some(month), Some(day)) => {
+                format!("{} {}, {}", self.month(month, "long"), day, year)
+            }
+            (_, Some(month), None) => format!("{} {}", self.month(month, "long"), year),
+            _ => year.to_string(),
+        }
+    }
+
+    fn month(&self, month: u32, form: &str) -> String {
+        match month {
+            13..=16 => self.term(&format!("season-{:02}", month - 12), "long", false),
+            _ => self.term(&format!("month-{:02}", month), form, false),
+        }
+    }
+
+    fn date_part(&self, part: &Node, (year, month, day): DateParts) -> Option<String> {
+        let form = part.attr("form");
+        let text = match part.attr("name")? {
+            "year" if form == Some("short") => format!("{:02}", year.rem_euclid(100)),
+            "year" => year.to_string(),
+            "month" => match (month?, form) {
+                (month @ 1..=12, Some("numeric")) => month.to_string(),
+                (month @ 1..=12, Some("numeric-leading-zeros")) => format!("{:02}", month),
+                (month, Some("short")) => self.month(month, "short"),
+                (month, _) => self.month(month, "long"),
+            },
+            "day" => match (day?, form) {
+                (day, Some("numeric-leading-zeros")) => format!("{:02}", day),
+                (day, Some("ordinal")) => format!("{}{}", day, self.ordinal(day)),
+                (day, _) => day.to_string(),
+            },
+            _ => return None,
+        };
+        Some(self.decorate(part, text))
+    }
+
+    /// A name option from the `name` element, else inherited from `bibliography`, `citation`
+    /// or `style`
+    fn name_option(&self, name: Option<&'a Node>, key: &str) -> Option<&'a str> {
+        let options: &'a HashMap<String, String> = self.options;
+        let inherited = match key {
+            "form" => "name-form",
+            "delimiter" => "name-delimiter",
+            key => key,
+        };
+        name.and_then(|name| name.attr(key))
+            .or_else(|| options.get(inherited).map(String::as_str))
+    }
+
+    fn names(&mut self, node: &'a Node, parent: Option<&'a Node>) -> Out {
+        // A `names` inside `substitute` without children takes them from the one it stands in for
+        let holder = match node.child("name").or(node.child("label")) {
+            Some(_) => node,
+            None => parent.unwrap_or(node),
+        };
+        let name = holder.child("name");
+        let et_al = holder.child("et-al");
+        let label = holder.child("label");
+        let position = |name: &str| holder.children.iter().position(|child| child.name == name);
+        let label_first =
+            matches!((position("label"), position("name")), (Some(l), Some(n)) if l < n);
+
+        let mut lists = Vec::new();
+        for variable in node.attr("variable").unwrap_or_default().split_whitespace() {
+            if self.suppressed.contains(variable) {
+                continue;
+            }
+            let names = self.names_of(variable);
+            if names.is_empty() {
+                continue;
+            }
+            let mut text = self.name_list(&names, name, et_al);
+            if let Some(label) = label {
+                let term = self.term(
+                    variable,
+                    label.attr("form").unwrap_or("long"),
+                    names.len() > 1,
+                );
+                let term = self.decorate(label, term);
+                text = match label_first {
+                    true => format!("{}{}", term, text),
+                    false => format!("{}{}", text, term),
+                };
+            }
+            self.rendered.push(variable.to_string());
+            lists.push(text);
+        }
+
+        if lists.is_empty() {
+            let substitutes = node.child("substitute").map_or(&[][..], |s| &s.children);
+            for substitute in substitutes {
+                let before = self.rendered.len();
+                let out = match substitute.name.as_str() {
+                    "names" => self.names(substitute, Some(holder)),
+                    _ => self.render(substitute),
+                };
+                if !out.text.is_empty() {
+                    let used: Vec<String> = self.rendered[before..].to_vec();
+                    self.suppressed.extend(used);
+                    return Out {
+                        text: self.decorate(node, out.text),
+                        called: true,
+                        found: true,
+                    };
+                }
+            }
+            return Out::called();
+        }
+        Out {
+            text: self.decorate(node, lists.join(node.attr("delimiter").unwrap_or(""))),
+            called: true,
+            found: true,
+        }
+    }
+
+    fn name_list(&self, names: &[Name], name: Option<&'a Node>, et_al: Option<&Node>) -> String {
+        let option = |key: &str| self.name_option(name, key);
+        let number = |key: &str| option(key).and_then(|value| value.parse::<usize>().ok());
+        let delimiter = option("delimiter").unwrap_or(", ");
+        let form = option("form").unwrap_or("long");
+        if form == "count" {
+            return names.len().to_string();
+        }
+        let sort_order = option("name-as-sort-order");
+        let inverted = |index: usize| match sort_order {
+            Some("all") => true,
+            Some("first") => index == 0,
+            _ => false,
+        };
+        let use_first = number("et-al-use-first").unwrap_or(1).max(1);
+        let truncated =
+            number("et-al-min").is_some_and(|min| names.len() >= min) && use_first < names.len();
+        let shown = match truncated {
+            true => use_first,
+            false => names.len(),
+        };
+        let formatted: Vec<String> = names[..shown]
+            .iter()
+            .enumerate()
+            .map(|(index, person)| self.name(person, name, form, inverted(index)))
+            .collect();
+
+        if truncated {
+            if option("et-al-use-last") == Some("true") && shown + 1 < names.len() {
+                let last = names.len() - 1;
+                let last = self.name(&names[last], name, form, inverted(last));
+                return format!("{}{}… {}", formatted.join(delimiter), delimiter, last);
+            }
+            let term = et_al.and_then(|node| node.attr("term")).unwrap_or("et-al");
+            let term = self.term(term, "long", false);
+            let term = match et_al {
+                Some(node) => self.decorate(node, term),
+                None => term,
+            };
+            let precedes = match option("delimiter-precedes-et-al") {
+                Some("always") => true,
+                Some("never") => false,
+                Some("after-inverted-name") => inverted(shown - 1),
+                _ => shown > 1,
+            };
+            let separator = if precedes { delimiter } else { " " };
+            return format!("{}{}{}", formatted.join(delimiter), separator, term);
+        }
+
+        let and = match option("and") {
+            Some("text") => Some(self.term("and", "long", false)),
+            Some("symbol") => Some("&".to_string()),
+            _ => None,
+        };
+        match (formatted.as_slice(), and) {
+            ([], _) => String::new(),
+            ([one], _) => one.clone(),
+            (_, None) => formatted.join(delimiter),
+            ([rest @ .., last], Some(and)) => {
+                let precedes = match option("delimiter-precedes-last") {
+                    Some("always") => true,
+                    Some("never") => false,
+                    Some("after-inverted-name") => inverted(rest.len() - 1),
+                    _ => formatted.len() > 2,
+                };
+                let separator = if precedes { delimiter } else { " " };
+                format!("{}{}{} {}", rest.join(delimiter), separator, and, last)
+            }
+        }
+    }
+
+    fn name(&self, person: &Name, name: Option<&'a Node>, form: &str, inverted: bool) -> String {
+        let part = |kind: &str, text: String| {
+            let node = name.and_then(|name| {
+                name.children
+                    .iter()
+                    .find(|part| part.name == "name-part" && part.is("name", kind))
+            });
+            match node {
+                Some(node) => self.decorate(node, text),
+                None => text,
+            }
+        };
+        let Some(family) = person.family.as_deref().filter(|family| !family.is_empty()) else {
+            let literal = person.literal.as_deref().or(person.given.as_deref());
+            return references::md(literal.unwrap_or_default());
+        };
+        let family = part("family", references::md(family));
+        let given = person.given.as_deref().filter(|given| !given.is_empty());
+        let Some(given) = given.filter(|_| form != "short") else {
+            return family;
+        };
+        let given = match self.name_option(name, "initialize-with") {
+            Some(with) if self.name_option(name, "initialize") != Some("false") => {
+                self.initials(given, with)
+            }
+            _ => given.to_string(),
+        };
+        let given = part("given", references::md(&given));
+        match inverted {
+            true => {
+                let separator = self.name_option(name, "sort-separator").unwrap_or(", ");
+                format!("{}{}{}", family, separator, given)
+            }
+            false => format!("{} {}", given, family),
+        }
+    }
+
+    /// Given names as initials followed by `with`, as in `J. A.` or `J.-P.`
+    fn initials(&self, given: &str, with: &str) -> String {
+        let hyphen = self
+            .style
+            .options
+            .get("initialize-with-hyphen")
+            .map(String::as_str)
+            != Some("false");
+        given
+            .split_whitespace()
+            .map(|word| {
+                word.split('-')
+                    .filter_map(|piece| piece.chars().next())
+                    .map(|initial| format!("{}{}", initial, with.trim_end()))
+                    .collect::<Vec<_>>()
+                    .join(if hyphen { "-" } else { "" })
+            })
+            .collect::<Vec<_>>()
+            .join(if with.ends_with(' ') { " " } else { "" })
+    }
+
+    /// A term in `form`, falling back to shorter forms as CSL does, then to the long form
+    fn term(&self, name: &str, form: &str, plural: bool) -> String {
+        let forms: &[&str] = match form {
+            "verb-short" => &["verb-short", "verb", "long"],
+            "symbol" => &["symbol", "short", "long"],
+            "short" => &["short", "long"],
+            "verb" => &["verb", "long"],
+            _ => &["long"],
+        };
+        for form in forms {
+            let key = (name.to_string(), form.to_string());
+            let found = self
+                .style
+                .terms
+                .get(&key)
+                .map(|(single, multiple)| (single.as_str(), multiple.as_str()))
+                .or_else(|| {
+                    TERMS
+                        .iter()
+                        .find(|(term, term_form, _, _)| *term == name && term_form == form)
+                        .map(|(_, _, single, multiple)| (*single, *multiple))
+                });
+            if let Some((single, multiple)) = found {
+                return match plural {
+                    true => multiple.to_string(),
+                    false => single.to_string(),
+                };
+            }
+        }
+        String::new()
+    }
+
+    /// Value an item is sorted by for a `sort` key
+    fn sort_key(&mut self, key: &'a Node) -> String {
+        if let Some(name) = key.attr("macro") {
+            let Some(definition) = self.style.macros.get(name) else {
+                return String::new();
+            };
+            let out = self.children(&definition.children, None);
+            return plain(&out.text).to_lowercase();
+        }
+        let Some(variable) = key.attr("variable") else {
+            return String::new();
+        };
+        let names = self.names_of(variable);
+        if !names.is_empty() {
+            return names
+                .iter()
+                .map(|name| {
+                    [&name.family, &name.literal, &name.given]
+                        .into_iter()
+                        .flatten()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase();
+        }
+        if let Some(date) = self.date_of(variable) {
+            return match date_parts(&date) {
+                Some((year, month, day)) => format!(
+                    "{:05}{:02}{:02}",
+                    year.max(0),
+                    month.unwrap_or(0),
+                    day.unwrap_or(0)
+                ),
+                None => String::new(),
+            };
+        }
+        let value = self.value(variable);
+        match value.parse::<u64>() {
+            Ok(number) => format!("{:020}", number),
+            Err(_) => value.to_lowercase(),
+        }
+    }
+
+    /// Apply an element's formatting, case, quotes and affixes to its text
+    fn decorate(&self, node: &Node, text: String) -> String {
+        if text.is_empty() {
+            return text;
+        }
+        let mut text = text;
+        if node.is("strip-periods", "true") {
+            text = text.replace('.', "");
+        }
+        if let Some(case) = node.attr("text-case") {
+            text = text_case(&text, case);
+        }
+        if node.is("quotes", "true") {
+            text = format!(
+                "{}{}{}",
+                self.term("open-quote", "long", false),
+                text,
+                self.term("close-quote", "long", false)
+            );
+        }
+        if matches!(node.attr("font-style"), Some("italic" | "oblique")) {
+            text = format!("*{}*", text);
+        }
+        if node.is("font-weight", "bold") {
+            text = format!("**{}**", text);
+        }
+        format!(
+            "{}{}{}",
+            node.attr("prefix").unwrap_or_default(),
+            text,
+            node.attr("suffix").unwrap_or_default()
+        )
+    }
+}
+
+/// Year, month and day of a date
+type DateParts = (i64, Option<u32>, Option<u32>);
+
+/// The start of a date, from its parts or a `YYYY-MM-DD` raw value
+fn date_parts(date: &CslDate) -> Option<DateParts> {
+    let number = |value: &Value| match value {
+        Value::Number(number) => number.as_i64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    };
+    let positive = |value: Option<i64>| value.and_then(|value| u32::try_from(value).ok());
+    if let Some(parts) = date.date_parts.first() {
+        if let Some(year) = parts.first().and_then(number) {
+            return Some((
+                year,
+                positive(parts.get(1).and_then(number)),
+                positive(parts.get(2).and_then(number)),
+            ));
+        }
+    }
+    let raw = date.raw.as_deref()?.trim();
+    let mut parts = raw.splitn(3, ['-', '/']);
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next().and_then(|month| month.parse().ok());
+    let day = parts
+        .next()
+        .and_then(|day| day.get(..2).unwrap_or(day).parse().ok());
+    Some((year, month, day))
+}
+
+/// Whether a value reads as a number or a range or list of numbers, such as `12`, `3a` or
+/// `2-4, 7`
+fn is_numeric(value: &str) -> bool {
+    !value.is_empty()
+        && value.split(['-', '–', ',', '&']).all(|part| {
+            let part = part.trim();
+            !part.is_empty()
+                && part.chars().all(char::is_alphanumeric)
+                && part.chars().any(|c| c.is_ascii_digit())
+        })
+}
+
+fn is_plural(value: &str) -> bool {
+    value.contains(['-', '–', ',', '&']) || value.contains(" and ")
+}
+
+fn roman(mut number: u32) -> String {
+    const NUMERALS: &[(u32, &str)] = &[
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    let mut out = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= *value {
+            out.push_str(numeral);
+            number -= value;
+        }
+    }
+    out
+}
+
+/// Uppercase the first letter, looking past quotes and emphasis
+fn capitalize(word: &str) -> String {
+    match word.char_indices().find(|(_, c)| c.is_alphabetic()) {
+        Some((index, c)) => format!(
+            "{}{}{}",
+            &word[..index],
+            c.to_uppercase(),
+            &word[index + c.len_utf8()..]
+        ),
+        None => word.to_string(),
+    }
+}
+
+fn text_case(text: &str, case: &str) -> String {
+    match case {
+        "lowercase" => text.to_lowercase(),
+        "uppercase" => text.to_uppercase(),
+        "capitalize-first" | "sentence" => capitalize(text),
+        "capitalize-all" => text
+            .split(' ')
+            .map(capitalize)
+            .collect::<Vec<_>>()
+            .join(" "),
+        "title" => {
+            let words: Vec<&str> = text.split(' ').collect();
+            let last = words.len().saturating_sub(1);
+            words
+                .iter()
+                .enumerate()
+                .map(|(index, word)| {
+                    // Words with capitals already, such as acronyms, are left as they are
+                    let lowercase = !word.chars().any(char::is_uppercase);
+                    let minor = STOP_WORDS.contains(&word.to_lowercase().as_str());
+                    match lowercase && (index == 0 || index == last || !minor) {
+                        true => capitalize(word),
+                        false => word.to_string(),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Text without Markdown emphasis and escapes, for sorting
+fn plain(text: &str) -> String {
+    text.replace(['*', '\\'], "")
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_string()
+}
+
+/// Collapse the doubled spaces and punctuation left where empty parts met their affixes
+fn tidy(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == ' ' && (out.is_empty() || out.ends_with(' ')) {
+            continue;
+        }
+        if matches!(c, '.' | ',' | ';') && out.ends_with(' ') {
+            out.pop();
+        }
+        if c == ',' && out.ends_with(',') {
+            continue;
+        }
+        if c == '.' {
+            let before = out.trim_end_matches(['*', '”', '’', '"', '\'']);
+            if before.ends_with(['.', '?', '!']) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out.trim_end().to_string()
+}
+
+fn styles_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(STYLES_DIR))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// Style ids are the file names of the CSL repository, such as `ieee` or `nature`
+fn check_id(id: &str) -> Result<String, String> {
+    let id = id.trim().to_lowercase();
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    match valid {
+        true => Ok(id),
+        false => Err(format!("Invalid citation style id: {}", id)),
+    }
+}
+
+fn read_style(app: &AppHandle, id: &str) -> Result<Option<CslStyle>, String> {
+    let path = styles_dir(app)?.join(format!("{}.csl", check_id(id)?));
+    match fs::read_to_string(path) {
+        Ok(source) => CslStyle::parse(&source).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read citation style: {}", e)),
+    }
+}
+
+/// A downloaded style, with a dependent style resolved to the style it formats with
+pub fn load(app: &AppHandle, id: &str) -> Result<Option<CslStyle>, String> {
+    let Some(style) = read_style(app, id)? else {
+        return Ok(None);
+    };
+    let Some(parent) = style.parent.clone().filter(|_| style.is_dependent()) else {
+        return Ok(Some(style));
+    };
+    let mut independent = read_style(app, &parent)?
+        .filter(|parent| !parent.is_dependent())
+        .ok_or_else(|| format!("Citation style not found: {}, which {} uses", parent, id))?;
+    independent.title = style.title;
+    Ok(Some(independent))
+}
+
+/// Ids and titles of the downloaded styles
+pub fn downloaded(app: &AppHandle) -> Vec<(String, String)> {
+    let Ok(entries) = styles_dir(app).and_then(|dir| fs::read_dir(dir).map_err(|e| e.to_string()))
+    else {
+        return Vec::new();
+    };
+    let mut styles = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "csl") {
+            continue;
+        }
+        let id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match fs::read_to_string(&path).map(|source| CslStyle::parse(&source)) {
+            Ok(Ok(style)) => styles.push((id, style.title)),
+            Ok(Err(error)) => tracing::warn!("Skipping citation style {}: {}", id, error),
+            Err(error) => tracing::warn!("Skipping citation style {}: {}", id, error),
+        }
+    }
+    styles.sort_by_key(|(_, title)| title.to_lowercase());
+    styles
+}
+
+fn fetch(id: &str) -> Result<String, String> {
+    let agent = http::agent();
+    for folder in ["", "dependent/"] {
+        let url = format!("{}/{}{}.csl", STYLES_URL, folder, id);
+        match agent.get(&url).call() {
+            Ok(mut response) => {
+                return response
+                    .body_mut()
+                    .read_to_string()
+                    .map_err(|e| format!("Failed to download citation style: {}", e))
+            }
+            Err(ureq::Error::StatusCode(404)) => continue,
+            Err(e) => return Err(format!("Failed to download citation style: {}", e)),
+        }
+    }
+    Err(format!("Citation style not found: {}", id))
+}
+
+/// Download a style from the CSL repository into the local cache, with the style it depends
+/// on when it is a dependent style; returns its id and title
+pub fn download(app: &AppHandle, id: &str) -> Result<(String, String), String> {
+    let id = check_id(id)?;
+    let dir = styles_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+    let source = fetch(&id)?;
+    let style = CslStyle::parse(&source)?;
+    if let Some(parent) = style.parent.as_deref().filter(|_| style.is_dependent()) {
+        let parent = check_id(parent)?;
+        let path = dir.join(format!("{}.csl", parent));
+        if !path.is_file() {
+            let parent_source = fetch(&parent)?;
+            CslStyle::parse(&parent_source)?;
+            fs::write(path, parent_source)
+                .map_err(|e| format!("Failed to save citation style: {}", e))?;
+        }
+    }
+    fs::write(dir.join(format!("{}.csl", id)), source)
+        .map_err(|e| format!("Failed to save citation style: {}", e))?;
+    tracing::info!(%id, "Citation style downloaded");
+    Ok((id, style.title))
+}
+
+/// Delete a downloaded style; returns `false` when it was not downloaded
+pub fn remove(app: &AppHandle, id: &str) -> Result<bool, String> {
+    let path = styles_dir(app)?.join(format!("{}.csl", check_id(id)?));
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete citation style: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const STYLE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<style xmlns="http://purl.org/net/xbiblio/csl" class="in-text" version="1.0">
+  <info><title>Test Author-Date</title></info>
+  <locale xml:lang="en"><terms><term name="et-al">and others</term></terms></locale>
+  <macro name="author">
+    <names variable="author">
+      <name and="text" initialize-with=". " name-as-sort-order="first" et-al-min="3" et-al-use-first="1"/>
+      <substitute><names variable="editor"/><text variable="title"/></substitute>
+    </names>
+  </macro>
+  <macro name="year"><date variable="issued"><date-part name="year"/></date></macro>
+  <citation>
+    <layout prefix="(" suffix=")" delimiter="; ">
+      <group delimiter=", ">
+        <names variable="author"><name form="short" and="symbol" et-al-min="3" et-al-use-first="1"/></names>
+        <text macro="year"/>
+        <group delimiter=" ">
+          <label variable="locator" form="short"/>
+          <text variable="locator"/>
+        </group>
+      </group>
+    </layout>
+  </citation>
+  <bibliography>
+    <sort><key macro="author"/><key variable="issued" sort="descending"/></sort>
+    <layout suffix=".">
+      <group delimiter=". ">
+        <text macro="author"/>
+        <text macro="year"/>
+        <text variable="title" font-style="italic" text-case="title"/>
+        <group delimiter=", ">
+          <text variable="container-title"/>
+          <group><text variable="volume"/><text variable="issue" prefix="(" suffix=")"/></group>
+          <text variable="page"/>
+        </group>
+      </group>
+    </layout>
+  </bibliography>
+</style>"#;
+
+    fn reference(value: Value) -> Reference {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn items() -> Vec<Reference> {
+        vec![
+            reference(json!({
+                "id": "smith", "type": "article-journal", "title": "on the origin of things",
+                "author": [{"family": "Smith", "given": "Jane Anne"}, {"family": "Doe", "given": "John"}],
+                "issued": {"date-parts": [[2020, 5]]},
+                "container-title": "Journal", "volume": "4", "issue": "2", "page": "10-20",
+            })),
+            reference(json!({
+                "id": "adams", "type": "book", "title": "A Book",
+                "author": [{"family": "Adams", "given": "Ann"}, {"family": "Baker"}, {"family": "Cole"}],
+                "issued": {"date-parts": [[1999]]},
+            })),
+            reference(json!({"id": "anon", "type": "book", "title": "Zebra Notes"})),
+        ]
+    }
+
+    #[test]
+    fn styles_without_output_are_rejected_unless_dependent() {
+        let empty = r#"<style><info><title>Empty</title></info></style>"#;
+        assert!(CslStyle::parse(empty).is_err());
+        assert!(CslStyle::parse("<not-a-style/>").is_err());
+
+        let dependent = r#"<style><info><title>Journal</title>
+            <link rel="independent-parent" href="http://www.zotero.org/styles/apa"/>
+            </info></style>"#;
+        let style = CslStyle::parse(dependent).unwrap();
+        assert!(style.is_dependent());
+        assert_eq!(style.parent.as_deref(), Some("apa"));
+    }
+
+    #[test]
+    fn citations_use_short_names_locators_and_style_terms() {
+        let style = CslStyle::parse(STYLE).unwrap();
+        assert_eq!(style.title, "Test Author-Date");
+        let items = items();
+        assert_eq!(
+            style.citation(&items[0], None).unwrap(),
+            "(Smith & Doe, 2020)"
+        );
+        assert_eq!(
+            style.citation(&items[0], Some("12-14")).unwrap(),
+            "(Smith & Doe, 2020, pp. 12–14)"
+        );
+        assert_eq!(
+            style.citation(&items[1], Some("3")).unwrap(),
+            "(Adams and others, 1999, p. 3)"
+        );
+    }
+
+    #[test]
+    fn bibliography_is_sorted_and_skips_empty_groups() {
+        let style = CslStyle::parse(STYLE).unwrap();
+        let items = items();
+        let entries = style
+            .bibliography(&items.iter().collect::<Vec<_>>())
+            .unwrap();
+        let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["adams", "smith", "anon"]);
+        assert_eq!(entries[0].1, "Adams, A. and others. 1999. *A Book*.");
+        assert_eq!(
+            entries[1].1,
+            "Smith, J. A. and J. Doe. 2020. *On the Origin of Things*. Journal, 4(2), 10–20."
+        );
+        // The title stands in for the missing author, so it is not repeated
+        assert_eq!(entries[2].1, "Zebra Notes.");
+    }
+
+    #[test]
+    fn numbers_are_recognized_and_converted() {
+        assert!(is_numeric("12"));
+        assert!(is_numeric("3a"));
+        assert!(is_numeric("2-4, 7"));
+        assert!(!is_numeric("second"));
+        assert!(!is_numeric(""));
+        assert!(is_plural("2-4"));
+        assert!(!is_plural("4"));
+        assert_eq!(roman(1994), "mcmxciv");
+    }
+
+    #[test]
+    fn title_case_keeps_minor_words_and_acronyms() {
+        assert_eq!(
+            text_case("the rise of NASA in the west", "title"),
+            "The Rise of NASA in the West"
+        );
+        assert_eq!(
+            text_case("“quoted” start", "capitalize-first"),
+            "“Quoted” start"
+        );
+    }
+
+    #[test]
+    fn dates_fall_back_to_raw_values() {
+        let date = CslDate {
+            raw: Some("2021-03-04T10:00".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(date_parts(&date), Some((2021, Some(3), Some(4))));
+    }
+
+    #[test]
+    fn tidy_collapses_leftover_punctuation() {
+        assert_eq!(tidy("Title?. , Journal ,, 4 ."), "Title?, Journal, 4.");
+    }
+}
@@ -0,0 +1,274 @@
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::stats::DateRange;
+use crate::{local_api, paths, read_only, templates, vault, workspace};
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::AppHandle;
+
+/// Daily note settings for one workspace, `.inkfinite/daily-notes.json`
+const CONFIG: &str = "daily-notes";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+/// How far back the previous note is looked for when rolling tasks over
+const ROLL_OVER_DAYS: i64 = 31;
+/// Longest range listed at once
+const MAX_DAYS: i64 = 3660;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DailyNotesConfig {
+    /// Workspace-relative folder, a strftime pattern such as `Journal/%Y`
+    pub folder: String,
+    /// Document name, a strftime pattern
+    pub name: String,
+    /// Template id the note is created from; without one, or if it was deleted, the note
+    /// starts with the date as a heading
+    pub template: Option<String>,
+    /// Copy unfinished tasks from the latest earlier daily note into a new one
+    pub roll_over: bool,
+}
+
+impl Default for DailyNotesConfig {
+    fn default() -> Self {
+        DailyNotesConfig {
+            folder: "Journal".to_string(),
+            name: DATE_FORMAT.to_string(),
+            template: Some("daily-note".to_string()),
+            roll_over: false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyNote {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub path: String,
+    /// Whether the note was created by this call
+    pub created: bool,
+    /// Tasks copied from the previous note
+    pub rolled_over: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyNoteEntry {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub path: String,
+}
+
+fn parse_day(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, DATE_FORMAT).map_err(|_| format!("Invalid date: {}", value))
+}
+
+/// Format with a user-supplied strftime pattern, which chrono only rejects while writing
+fn format_date(date: NaiveDate, pattern: &str) -> Result<String, String> {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    write!(out, "{}", date.format(pattern))
+        .map_err(|_| format!("Invalid date pattern: {}", pattern))?;
+    Ok(out)
+}
+
+/// Where the note for `date` lives, whether or not it exists
+fn note_path(root: &Path, config: &DailyNotesConfig, date: NaiveDate) -> Result<PathBuf, String> {
+    let folder = format_date(date, &config.folder)?;
+    let folder = Path::new(folder.trim_matches(['/', '\\']));
+    if folder
+        .components()
+        .any(|part| !matches!(part, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("Invalid daily notes folder: {}", config.folder));
+    }
+    let name = local_api::file_stem(format_date(date, &config.name)?.trim());
+    if name.is_empty() {
+        return Err(format!("Invalid daily note name: {}", config.name));
+    }
+    Ok(root
+        .join(folder)
+        .join(format!("{}{}", name, DOCUMENT_EXTENSION)))
+}
+
+/// Where the workspace's daily note for `date` lives under its current settings
+pub fn path_for(root: &Path, date: NaiveDate) -> Result<PathBuf, String> {
+    let config: DailyNotesConfig = workspace::read_config(root, CONFIG)?;
+    note_path(root, &config, date)
+}
+
+/// Unfinished `- [ ]` tasks of the latest note in the days before `date`
+fn open_tasks(root: &Path, config: &DailyNotesConfig, date: NaiveDate) -> Vec<String> {
+    let previous = (1..=ROLL_OVER_DAYS)
+        .filter_map(|days| note_path(root, config, date - Duration::days(days)).ok())
+        .find(|path| path.is_file());
+    let Some(board) = previous.and_then(|path| document::read_board(&path).ok()) else {
+        return Vec::new();
+    };
+    board
+        .to_markdown()
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            ["- [ ] ", "* [ ] "].iter().any(|marker| {
+                line.strip_prefix(marker)
+                    .is_some_and(|t| !t.trim().is_empty())
+            })
+        })
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
+/// Put the tasks in place of the first empty task the template left, or after the note
+fn insert_tasks(markdown: &str, tasks: &[String]) -> String {
+    let tasks = tasks.join("\n");
+    let mut lines: Vec<&str> = markdown.lines().collect();
+    match lines
+        .iter()
+        .position(|line| matches!(line.trim(), "- [ ]" | "* [ ]"))
+    {
+        Some(index) => {
+            lines[index] = &tasks;
+            lines.join("\n") + "\n"
+        }
+        None => format!("{}\n\n{}\n", markdown.trim_end(), tasks),
+    }
+}
+
+fn create(
+    root: &Path,
+    config: &DailyNotesConfig,
+    date: NaiveDate,
+    path: &Path,
+) -> Result<usize, String> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .and_then(|name| name.strip_suffix(DOCUMENT_EXTENSION).map(str::to_string))
+        .unwrap_or_default();
+    // Dates in the template refer to the note's day, at the current time of day
+    let at = date
+        .and_time(Local::now().time())
+        .and_local_timezone(Local)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&date.and_time(Default::default())));
+    let rendered = match &config.template {
+        Some(id) => match templates::render_at(root, id, &name, at) {
+            Ok(preview) => Some(preview.markdown),
+            Err(error) if error.starts_with("Template not found") => None,
+            Err(error) => return Err(error),
+        },
+        None => None,
+    };
+    let mut markdown = rendered.unwrap_or_else(|| format!("# {}\n", name));
+
+    let tasks = match config.roll_over {
+        true => open_tasks(root, config, date),
+        false => Vec::new(),
+    };
+    if !tasks.is_empty() {
+        markdown = insert_tasks(&markdown, &tasks);
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    document::write_board(path, &BoardFile::from_markdown(&name, &markdown))?;
+    Ok(tasks.len())
+}
+
+/// Open the daily note for `date`, today by default, creating it from the configured template
+/// and folder and name patterns when it does not exist yet
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn open_daily_note(
+    app: AppHandle,
+    workspace: String,
+    date: Option<String>,
+) -> Result<DailyNote, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let date = match date {
+        Some(date) => parse_day(&date)?,
+        None => Local::now().date_naive(),
+    };
+    let config: DailyNotesConfig = workspace::read_config(&root, CONFIG)?;
+    let path = note_path(&root, &config, date)?;
+    let mut note = DailyNote {
+        date: date.format(DATE_FORMAT).to_string(),
+        path: path.to_string_lossy().to_string(),
+        created: false,
+        rolled_over: 0,
+    };
+    if path.is_file() {
+        paths::check(&app, &note.path, paths::Scope::Read)?;
+        return Ok(note);
+    }
+
+    read_only::ensure_writable(&root)?;
+    if vault::vault_of(&path).is_some() {
+        return Err("Daily notes cannot be created in a vault".into());
+    }
+    paths::check(&app, &note.path, paths::Scope::Write)?;
+    note.rolled_over = create(&root, &config, date, &path)?;
+    note.created = true;
+    events::file_changed(&app, &path, ChangeKind::Created);
+    tracing::info!(date = %note.date, rolled_over = note.rolled_over, "Daily note created");
+    Ok(note)
+}
+
+/// Daily notes that exist for days in `range`, for marking them in a calendar
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_daily_notes(
+    app: AppHandle,
+    workspace: String,
+    range: DateRange,
+) -> Result<Vec<DailyNoteEntry>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let (from, to) = (parse_day(&range.from)?, parse_day(&range.to)?);
+    if to < from {
+        return Err("Invalid range: it ends before it starts".into());
+    }
+    if (to - from).num_days() >= MAX_DAYS {
+        return Err(format!("Invalid range: longer than {} days", MAX_DAYS).into());
+    }
+    let config: DailyNotesConfig = workspace::read_config(&root, CONFIG)?;
+    let mut notes = Vec::new();
+    for date in from.iter_days().take_while(|date| *date <= to) {
+        let path = note_path(&root, &config, date)?;
+        if path.is_file() {
+            notes.push(DailyNoteEntry {
+                date: date.format(DATE_FORMAT).to_string(),
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+    }
+    Ok(notes)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_daily_notes_config(
+    app: AppHandle,
+    workspace: String,
+) -> Result<DailyNotesConfig, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(workspace::read_config(&root, CONFIG)?)
+}
+
+/// Save the daily note settings after checking that the patterns produce a valid path
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_daily_notes_config(
+    app: AppHandle,
+    workspace: String,
+    config: DailyNotesConfig,
+) -> Result<(), Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    note_path(&root, &config, Local::now().date_naive())?;
+    workspace::write_config(&root, CONFIG, &config)?;
+    Ok(())
+}
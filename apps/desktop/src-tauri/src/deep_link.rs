@@ -0,0 +1,161 @@
+use crate::{document, workspace};
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use url::Url;
+
+/// URL scheme registered for the app, as in `inkfinite://open?doc=<id>`
+pub const SCHEME: &str = "inkfinite";
+
+/// Navigation requested from outside the app, emitted to the webview as `deep-link:navigate`
+#[derive(serde::Serialize, Clone)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum Navigation {
+    /// `inkfinite://open?doc=<board id>`
+    #[serde(rename_all = "camelCase")]
+    Open {
+        doc: String,
+        /// Document in the current workspace carrying that board id
+        path: Option<String>,
+    },
+    /// `inkfinite://new?template=<name>&name=<title>`
+    #[serde(rename_all = "camelCase")]
+    New {
+        template: Option<String>,
+        name: Option<String>,
+    },
+    /// A document opened from the file manager; its workspace has already been selected
+    #[serde(rename_all = "camelCase")]
+    OpenFile { path: String, workspace: String },
+}
+
+/// Navigation received before the webview asked for it
+#[derive(Default)]
+pub struct PendingNavigation(Mutex<Pending>);
+
+#[derive(Default)]
+pub struct Pending {
+    queue: Vec<Navigation>,
+    /// Set once the webview has drained the queue and listens for events instead
+    ready: bool,
+}
+
+/// Take links and files that launched the app; later ones arrive as `deep-link:navigate` events
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn take_pending_navigation(pending: State<'_, PendingNavigation>) -> Vec<Navigation> {
+    pending
+        .0
+        .lock()
+        .map(|mut pending| {
+            pending.ready = true;
+            std::mem::take(&mut pending.queue)
+        })
+        .unwrap_or_default()
+}
+
+/// Emit a navigation to the webview, or queue it until the webview is listening
+pub fn navigate(app: &AppHandle, navigation: Navigation) {
+    if let Ok(mut pending) = app.state::<PendingNavigation>().0.lock() {
+        if !pending.ready {
+            pending.queue.push(navigation);
+            return;
+        }
+    }
+    #[cfg(desktop)]
+    crate::tray::show_main_window(app);
+    let _ = app.emit("deep-link:navigate", navigation);
+}
+
+/// Register the scheme where that happens at runtime and start listening for links
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    use tauri_plugin_deep_link::DeepLinkExt;
+
+    // macOS and mobile register schemes from the bundle; Linux and Windows need it at runtime
+    #[cfg(any(target_os = "linux", windows))]
+    app.deep_link()
+        .register_all()
+        .map_err(|e| format!("Failed to register {}:// links: {}", SCHEME, e))?;
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            open_url(&handle, &url);
+        }
+    });
+
+    let launched = app
+        .deep_link()
+        .get_current()
+        .map_err(|e| format!("Failed to read launch link: {}", e))?
+        .unwrap_or_default();
+    for url in &launched {
+        open_url(app, url);
+    }
+
+    // Windows and Linux pass documents opened from the file manager as arguments
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    crate::file_open::open_args(app, &args, &cwd);
+    Ok(())
+}
+
+/// Navigate to an `inkfinite://` link, ignoring other schemes and unknown actions
+pub fn open_url(app: &AppHandle, url: &Url) {
+    if url.scheme() == SCHEME && url.host_str() == Some(crate::x_callback::HOST) {
+        crate::x_callback::handle(app, url);
+        return;
+    }
+    if let Some(navigation) = resolve(app, url) {
+        navigate(app, navigation);
+    }
+}
+
+/// A second launch forwards its arguments here; links among them reach `on_open_url` on their own
+#[cfg(desktop)]
+pub fn on_second_instance(app: &AppHandle, args: Vec<String>, cwd: String) {
+    crate::tray::show_main_window(app);
+    crate::file_open::open_args(app, args.get(1..).unwrap_or_default(), Path::new(&cwd));
+}
+
+fn resolve(app: &AppHandle, url: &Url) -> Option<Navigation> {
+    if url.scheme() != SCHEME {
+        return None;
+    }
+    let param = |key: &str| {
+        url.query_pairs()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    // `inkfinite://open` parses with `open` as the host; `inkfinite:open` as the path
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path().trim_matches('/'));
+    match action {
+        "open" => {
+            let doc = param("doc")?;
+            let path = workspace::current_root(app)
+                .and_then(|root| find_document(&root, &doc))
+                .map(|path| path.to_string_lossy().to_string());
+            Some(Navigation::Open { doc, path })
+        }
+        "new" => Some(Navigation::New {
+            template: param("template"),
+            name: param("name"),
+        }),
+        // Desktop builds have no share target, and a web page must not write to the inbox
+        #[cfg(mobile)]
+        "share" => crate::share::receive(app, url),
+        _ => None,
+    }
+}
+
+/// Find the document in a workspace whose board id matches
+pub fn find_document(root: &Path, id: &str) -> Option<std::path::PathBuf> {
+    workspace::list_documents(root)
+        .ok()?
+        .into_iter()
+        .find(|path| document::read_board(path).is_ok_and(|board| board.board.id == id))
+}
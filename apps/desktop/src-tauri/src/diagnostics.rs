@@ -0,0 +1,326 @@
+use crate::error::Error;
+use crate::{catalog, document, packages, paths, read_only, settings, workspace};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+/// Free space below which writes are likely to start failing
+const LOW_DISK_BYTES: u64 = 1024 * 1024 * 1024;
+const CRITICAL_DISK_BYTES: u64 = 100 * 1024 * 1024;
+/// Temporary files younger than this may belong to a save still in progress
+const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+/// Leftover paths listed in a check's details
+const MAX_LISTED: usize = 20;
+
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Check {
+    /// `workspace`, `index`, `diskSpace`, `migrations`, `staleFiles` or `plugins`
+    pub id: &'static str,
+    pub status: Status,
+    pub message: String,
+    /// Paths, counts or errors behind the message
+    pub details: Vec<String>,
+    /// What the user can do about it
+    pub hint: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    /// The worst status of any check
+    pub status: Status,
+    pub version: String,
+    pub os: &'static str,
+    pub workspace: Option<String>,
+    pub checked_at: i64,
+    pub checks: Vec<Check>,
+}
+
+impl Check {
+    fn new(id: &'static str, status: Status, message: impl Into<String>) -> Check {
+        Check {
+            id,
+            status,
+            message: message.into(),
+            details: Vec::new(),
+            hint: None,
+        }
+    }
+
+    fn details(mut self, details: Vec<String>) -> Check {
+        self.details = details;
+        self
+    }
+
+    fn hint(mut self, hint: &str) -> Check {
+        self.hint = Some(hint.to_string());
+        self
+    }
+}
+
+/// Whether the workspace can be listed and written, trying a probe file in `.inkfinite`
+fn check_workspace(root: &Path) -> Check {
+    if let Err(error) = fs::read_dir(root) {
+        return Check::new(
+            "workspace",
+            Status::Error,
+            "Workspace folder cannot be read",
+        )
+        .details(vec![error.to_string()])
+        .hint("Check that the folder exists and that you have permission to open it");
+    }
+    if read_only::is_read_only(root) {
+        return Check::new("workspace", Status::Ok, "Workspace is open read-only");
+    }
+    let internal = workspace::internal_dir(root);
+    let probe = internal.join(format!(".probe-{}", uuid::Uuid::new_v4().simple()));
+    let written = fs::create_dir_all(&internal).and_then(|_| fs::write(&probe, b""));
+    let _ = fs::remove_file(&probe);
+    match written {
+        Ok(()) => Check::new("workspace", Status::Ok, "Workspace can be read and written"),
+        Err(error) => Check::new("workspace", Status::Error, "Workspace cannot be written")
+            .details(vec![error.to_string()])
+            .hint("Check the folder's permissions, or whether the disk is mounted read-only"),
+    }
+}
+
+fn check_index(app: &AppHandle, root: &Path) -> Check {
+    match catalog::health(app, root) {
+        Ok(health) if !health.intact => {
+            Check::new("index", Status::Error, "Metadata cache is damaged")
+                .hint("Rebuild the workspace cache")
+        }
+        Ok(health) if health.cached != health.on_disk => Check::new(
+            "index",
+            Status::Warning,
+            format!(
+                "Metadata cache lists {} documents, the workspace has {}",
+                health.cached, health.on_disk
+            ),
+        )
+        .hint("The cache catches up in the background; rebuild it if this persists"),
+        Ok(health) => Check::new(
+            "index",
+            Status::Ok,
+            format!("Metadata cache is current with {} documents", health.cached),
+        ),
+        Err(error) => Check::new("index", Status::Error, "Metadata cache cannot be opened")
+            .details(vec![error])
+            .hint("Rebuild the workspace cache"),
+    }
+}
+
+#[cfg(unix)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    unsafe { GetDiskFreeSpaceExW(PCWSTR(wide.as_ptr()), Some(&mut available), None, None) }.ok()?;
+    Some(available)
+}
+
+fn check_disk(path: &Path) -> Check {
+    let Some(free) = free_space(path) else {
+        return Check::new("diskSpace", Status::Warning, "Free disk space is unknown");
+    };
+    let message = format!("{} MB free", free / (1024 * 1024));
+    match free {
+        free if free < CRITICAL_DISK_BYTES => Check::new("diskSpace", Status::Error, message)
+            .hint("Free up disk space; saves may fail"),
+        free if free < LOW_DISK_BYTES => {
+            Check::new("diskSpace", Status::Warning, message).hint("Disk space is running low")
+        }
+        _ => Check::new("diskSpace", Status::Ok, message),
+    }
+}
+
+fn check_migrations(app: &AppHandle) -> Check {
+    match settings::pending_migration(app) {
+        Some(version) => Check::new(
+            "migrations",
+            Status::Warning,
+            format!("Settings are still at version {}", version),
+        )
+        .hint("Restart the app to migrate them"),
+        None => Check::new("migrations", Status::Ok, "Settings are up to date"),
+    }
+}
+
+/// Hidden temporary files a save or package install left behind when it was interrupted
+fn leftovers(root: Option<&Path>, package_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let stale = |path: &Path| {
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age >= STALE_AFTER)
+    };
+    let mut found = Vec::new();
+    if let Some(root) = root {
+        // Hidden folders such as `.git` are never saved into
+        let walk = jwalk::WalkDir::new(root)
+            .skip_hidden(false)
+            .process_read_dir(|_, _, _, children| {
+                children.retain(|child| {
+                    child.as_ref().is_ok_and(|child| {
+                        !child.file_type().is_dir()
+                            || !child.file_name().to_string_lossy().starts_with('.')
+                    })
+                });
+            });
+        for entry in walk.into_iter().flatten() {
+            let name = entry.file_name().to_string_lossy();
+            if name.starts_with('.') && name.ends_with(".saving") && stale(&entry.path()) {
+                found.push(entry.path());
+            }
+        }
+    }
+    for dir in package_dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let interrupted = name.starts_with('.')
+                && (name.ends_with(".staging") || name.ends_with(".previous"));
+            if interrupted && stale(&path) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+fn check_leftovers(app: &AppHandle, root: Option<&Path>) -> Check {
+    let found = leftovers(root, &packages::install_dirs(app, root));
+    if found.is_empty() {
+        return Check::new("staleFiles", Status::Ok, "No interrupted saves or installs");
+    }
+    let details = found
+        .iter()
+        .take(MAX_LISTED)
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    Check::new(
+        "staleFiles",
+        Status::Warning,
+        format!(
+            "{} files left by interrupted saves or installs",
+            found.len()
+        ),
+    )
+    .details(details)
+    .hint("They can be deleted once no save is in progress")
+}
+
+#[cfg(desktop)]
+fn check_plugins(app: &AppHandle) -> Check {
+    let plugins = match crate::plugins::list_plugins(app.clone()) {
+        Ok(plugins) => plugins,
+        Err(error) => {
+            return Check::new("plugins", Status::Error, "Plugins cannot be listed")
+                .details(vec![error.to_string()])
+        }
+    };
+    let failed: Vec<String> = plugins
+        .iter()
+        .filter_map(|plugin| {
+            let error = plugin.error.as_ref()?;
+            Some(format!("{}: {}", plugin.manifest.id, error))
+        })
+        .collect();
+    match failed.len() {
+        0 => Check::new(
+            "plugins",
+            Status::Ok,
+            format!("{} plugins loaded", plugins.len()),
+        ),
+        count => Check::new(
+            "plugins",
+            Status::Warning,
+            format!("{} plugins failed to load", count),
+        )
+        .details(failed)
+        .hint("Reload or update the plugins, or review what they were granted"),
+    }
+}
+
+/// Check the workspace and the app for problems the troubleshooting panel can explain: whether
+/// the workspace can be read and written, the metadata cache, free disk space, settings not yet
+/// migrated, leftovers of interrupted saves and installs, and plugins that failed to load
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn run_diagnostics(
+    app: AppHandle,
+    workspace: Option<String>,
+) -> Result<DiagnosticsReport, Error> {
+    let root = match workspace {
+        Some(workspace) => Some(paths::check_workspace(
+            &app,
+            &workspace,
+            paths::Scope::Read,
+        )?),
+        None => workspace::current_root(&app),
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut checks = Vec::new();
+        if let Some(root) = &root {
+            checks.push(check_workspace(root));
+            checks.push(check_index(&app, root));
+        }
+        let data_dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+        checks.push(check_disk(root.as_deref().unwrap_or(&data_dir)));
+        checks.push(check_migrations(&app));
+        checks.push(check_leftovers(&app, root.as_deref()));
+        #[cfg(desktop)]
+        checks.push(check_plugins(&app));
+
+        let status = checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(Status::Ok);
+        tracing::info!(?status, "Diagnostics finished");
+        Ok(DiagnosticsReport {
+            status,
+            version: app.package_info().version.to_string(),
+            os: std::env::consts::OS,
+            workspace: root.map(|root| root.to_string_lossy().to_string()),
+            checked_at: document::now_millis(),
+            checks,
+        })
+    })
+    .await
+    .map_err(|e| format!("Diagnostics task failed: {}", e))?
+}
@@ -0,0 +1,57 @@
+use crate::FileEntry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+struct Listing {
+    /// Directory modification time when listed; entries being added, removed or renamed,
+    /// including by other programs, changes it
+    modified: Option<SystemTime>,
+    entries: Vec<FileEntry>,
+}
+
+/// Managed state caching directory listings by directory and pattern.
+///
+/// Listings are dropped when the backend reports a change inside the directory, and are
+/// re-read when the directory's modification time no longer matches.
+#[derive(Default)]
+pub struct DirectoryCache(Mutex<HashMap<(PathBuf, String), Listing>>);
+
+impl DirectoryCache {
+    /// Cached listing of `dir`, if it is still current
+    pub fn get(&self, dir: &Path, pattern: &str) -> Option<Vec<FileEntry>> {
+        let modified = modified(dir);
+        let listings = self.0.lock().ok()?;
+        listings
+            .get(&(dir.to_path_buf(), pattern.to_string()))
+            .filter(|listing| modified.is_some() && listing.modified == modified)
+            .map(|listing| listing.entries.clone())
+    }
+
+    pub fn insert(&self, dir: &Path, pattern: &str, entries: &[FileEntry]) {
+        if let Ok(mut listings) = self.0.lock() {
+            listings.insert(
+                (dir.to_path_buf(), pattern.to_string()),
+                Listing {
+                    modified: modified(dir),
+                    entries: entries.to_vec(),
+                },
+            );
+        }
+    }
+
+    /// Forget listings affected by a change to `path`: its parent, and for a directory, itself
+    /// and everything below it
+    pub fn invalidate(&self, path: &Path) {
+        if let Ok(mut listings) = self.0.lock() {
+            listings.retain(|(dir, _), _| {
+                Some(dir.as_path()) != path.parent() && !dir.starts_with(path)
+            });
+        }
+    }
+}
+
+fn modified(dir: &Path) -> Option<SystemTime> {
+    std::fs::metadata(dir).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,181 @@
+use crate::error::Error;
+#[cfg(desktop)]
+use crate::{document, paths, tools};
+#[cfg(desktop)]
+use std::fs;
+#[cfg(desktop)]
+use std::path::{Path, PathBuf};
+use tauri::WebviewWindow;
+#[cfg(desktop)]
+use tauri::{Emitter, Manager};
+
+/// Drag preview used when the first item is not an image
+#[cfg(desktop)]
+const DEFAULT_ICON: &[u8] = include_bytes!("../icons/128x128.png");
+
+/// Something the user can drag out of the app
+#[derive(serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DragSource {
+    /// A file that already exists on disk, such as an asset or attachment
+    File { path: String },
+    /// A board document, exported to Markdown in a temp folder when the drag starts
+    Document { path: String },
+    /// Content that only exists in the editor, written to a temp file with the given name
+    Content { name: String, content: String },
+}
+
+#[cfg(desktop)]
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DragEvent {
+    /// `dropped` or `cancelled`
+    result: &'static str,
+    x: i32,
+    y: i32,
+}
+
+/// Start an OS drag from `window` carrying real file paths.
+///
+/// The result is emitted as a `drag:finished` event once the user drops or cancels.
+#[cfg(desktop)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn start_drag(
+    window: WebviewWindow,
+    items: Vec<DragSource>,
+    icon: Option<String>,
+) -> Result<(), Error> {
+    if items.is_empty() {
+        return Err("Nothing to drag".into());
+    }
+
+    let mut scratch = None;
+    let mut files = Vec::with_capacity(items.len());
+    for item in items {
+        if let DragSource::File { path } | DragSource::Document { path } = &item {
+            paths::check(window.app_handle(), path, paths::Scope::Read)?;
+        }
+        files.push(materialize(item, &mut scratch)?);
+    }
+
+    let preview = match icon {
+        Some(icon) => drag::Image::File(PathBuf::from(icon)),
+        None => files
+            .first()
+            .filter(|path| crate::optimize::is_raster(path))
+            .map(|path| drag::Image::File(path.clone()))
+            .unwrap_or_else(|| drag::Image::Raw(DEFAULT_ICON.to_vec())),
+    };
+
+    let target = window.clone();
+    // Drag sessions must begin on the UI thread that owns the window
+    Ok(window
+        .run_on_main_thread(move || {
+            #[cfg(target_os = "linux")]
+            let handle = target.gtk_window();
+            #[cfg(not(target_os = "linux"))]
+            let handle = tauri::Result::Ok(target.clone());
+
+            let started = handle
+                .map_err(|e| format!("Failed to access window: {}", e))
+                .and_then(|handle| {
+                    let events = target.clone();
+                    let scratch = scratch.clone();
+                    drag::start_drag(
+                        &handle,
+                        drag::DragItem::Files(files),
+                        preview,
+                        move |result, cursor| {
+                            let result = match result {
+                                drag::DragResult::Dropped => "dropped",
+                                drag::DragResult::Cancel => {
+                                    // Dropped temp files stay behind so the receiving app can read them
+                                    if let Some(dir) = &scratch {
+                                        let _ = fs::remove_dir_all(dir);
+                                    }
+                                    "cancelled"
+                                }
+                            };
+                            let _ = events.emit(
+                                "drag:finished",
+                                DragEvent {
+                                    result,
+                                    x: cursor.x,
+                                    y: cursor.y,
+                                },
+                            );
+                        },
+                        drag::Options::default(),
+                    )
+                    .map_err(|e| format!("Failed to start drag: {}", e))
+                });
+
+            if let Err(error) = started {
+                if let Some(dir) = &scratch {
+                    let _ = fs::remove_dir_all(dir);
+                }
+                let _ = target.emit("drag:failed", error);
+            }
+        })
+        .map_err(|e| format!("Failed to start drag: {}", e))?)
+}
+
+/// Dragging files out is not available on mobile builds
+#[cfg(mobile)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn start_drag(
+    _window: WebviewWindow,
+    _items: Vec<DragSource>,
+    _icon: Option<String>,
+) -> Result<(), Error> {
+    Err("Dragging files is not supported on this platform".into())
+}
+
+/// Resolve a drag source to an absolute path, writing virtual items into a shared scratch folder
+#[cfg(desktop)]
+fn materialize(item: DragSource, scratch: &mut Option<PathBuf>) -> Result<PathBuf, String> {
+    let (name, content) = match item {
+        DragSource::File { path } => {
+            return fs::canonicalize(&path)
+                .map_err(|e| format!("File does not exist: {} ({})", path, e));
+        }
+        DragSource::Document { path } => {
+            let path = Path::new(&path);
+            let board = document::read_board(path)?;
+            (
+                format!("{}.md", document::document_stem(path)),
+                board.to_markdown(),
+            )
+        }
+        DragSource::Content { name, content } => (name, content),
+    };
+
+    let dir = match scratch {
+        Some(dir) => dir.clone(),
+        None => scratch.insert(tools::scratch_dir("drag")?).clone(),
+    };
+    let target = dir.join(sanitize_name(&name));
+    fs::write(&target, content).map_err(|e| format!("Failed to write temp file: {}", e))?;
+    Ok(target)
+}
+
+/// Keep a file name usable on every platform the drop target might run on
+#[cfg(desktop)]
+fn sanitize_name(name: &str) -> String {
+    let clean: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+    let clean = clean.trim().trim_matches('.').to_string();
+    if clean.is_empty() {
+        "Untitled.md".to_string()
+    } else {
+        clean
+    }
+}
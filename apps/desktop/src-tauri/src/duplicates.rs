@@ -0,0 +1,475 @@
+use crate::archive;
+use crate::asset_gc::TRASH_DIR;
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{blocking, catalog, paths, vault, workspace};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const DEFAULT_THRESHOLD: f64 = 0.8;
+/// Values in each document's MinHash signature
+const SIGNATURE: usize = 64;
+/// Words in a shingle; shorter documents are compared word by word
+const SHINGLE: usize = 3;
+/// Documents with fewer words are too short to call duplicates
+const MIN_WORDS: usize = 3;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateDocument {
+    /// Workspace-relative path
+    pub path: String,
+    pub name: String,
+    pub word_count: usize,
+    pub updated_at: i64,
+}
+
+/// Arguments for [`merge_documents`]: the document to keep and those to fold into it
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeSuggestion {
+    pub target: String,
+    pub sources: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateCluster {
+    pub documents: Vec<DuplicateDocument>,
+    /// Estimated similarity of the least similar pair that joined the cluster, between 0 and 1
+    pub similarity: f64,
+    /// Whether every document has the same text, ignoring case, punctuation and spacing
+    pub exact: bool,
+    pub suggestion: MergeSuggestion,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeReport {
+    /// The merged document, workspace-relative
+    pub path: String,
+    pub blocks_added: usize,
+    /// Source titles added to the target's aliases, so `[[wiki links]]` to them still resolve
+    pub aliases_added: Vec<String>,
+    pub trashed: Vec<String>,
+    pub trash_dir: Option<String>,
+    /// Documents whose relative links to a source now point at the target
+    pub relinked: Vec<String>,
+}
+
+struct Fingerprint {
+    document: DuplicateDocument,
+    digest: String,
+    signature: [u64; SIGNATURE],
+}
+
+/// Lowercased words of `text`, without punctuation
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Text blocks of a document in reading order
+fn blocks(board: &BoardFile) -> Vec<String> {
+    board
+        .pages()
+        .iter()
+        .flat_map(|page| board.page_shapes(page))
+        .filter_map(|shape| shape.text().map(str::to_string))
+        .collect()
+}
+
+/// FNV-1a, stable across runs and platforms
+fn fnv(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// SplitMix64 finalizer, standing in for one random permutation per seed
+fn mix(value: u64, seed: u64) -> u64 {
+    let mut z = value ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// MinHash signature over word shingles; the share of equal values between two signatures
+/// estimates the Jaccard similarity of the documents' shingle sets
+fn signature(words: &[String]) -> [u64; SIGNATURE] {
+    let size = SHINGLE.min(words.len());
+    let shingles: HashSet<u64> = words.windows(size).map(|w| fnv(&w.join(" "))).collect();
+    let mut signature = [u64::MAX; SIGNATURE];
+    for shingle in shingles {
+        for (seed, slot) in signature.iter_mut().enumerate() {
+            *slot = (*slot).min(mix(shingle, seed as u64));
+        }
+    }
+    signature
+}
+
+fn estimate(a: &[u64; SIGNATURE], b: &[u64; SIGNATURE]) -> f64 {
+    a.iter().zip(b).filter(|(a, b)| a == b).count() as f64 / SIGNATURE as f64
+}
+
+fn find(parents: &mut [usize], node: usize) -> usize {
+    let mut root = node;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[node] = root;
+    root
+}
+
+fn fingerprints(app: &AppHandle, root: &Path) -> Result<Vec<Fingerprint>, String> {
+    let mut prints = Vec::new();
+    for entry in catalog::documents(app, root)? {
+        let Ok(board) = document::read_board(&root.join(&entry.path)) else {
+            continue;
+        };
+        let words = words(&blocks(&board).join("\n"));
+        if words.len() < MIN_WORDS {
+            continue;
+        }
+        prints.push(Fingerprint {
+            digest: format!("{:x}", Sha256::digest(words.join(" ").as_bytes())),
+            signature: signature(&words),
+            document: DuplicateDocument {
+                path: entry.path,
+                name: board.board.name,
+                word_count: words.len(),
+                updated_at: board.board.updated_at,
+            },
+        });
+    }
+    Ok(prints)
+}
+
+fn clusters(prints: Vec<Fingerprint>, threshold: f64) -> Vec<DuplicateCluster> {
+    // Candidates share a band of their signatures; narrower bands catch lower similarities
+    let rows = if threshold < 0.6 { 2 } else { 4 };
+    let mut candidates: BTreeSet<(usize, usize)> = BTreeSet::new();
+    let mut exact: HashMap<&str, usize> = HashMap::new();
+    let mut buckets: HashMap<(usize, &[u64]), Vec<usize>> = HashMap::new();
+    for (index, print) in prints.iter().enumerate() {
+        if let Some(&first) = exact.get(print.digest.as_str()) {
+            candidates.insert((first, index));
+        }
+        exact.entry(&print.digest).or_insert(index);
+        for (band, values) in print.signature.chunks(rows).enumerate() {
+            buckets.entry((band, values)).or_default().push(index);
+        }
+    }
+    for members in buckets.values() {
+        for (i, &a) in members.iter().enumerate() {
+            candidates.extend(members[i + 1..].iter().map(|&b| (a, b)));
+        }
+    }
+
+    let mut parents: Vec<usize> = (0..prints.len()).collect();
+    let mut weakest: HashMap<usize, f64> = HashMap::new();
+    let mut edges = Vec::new();
+    for (a, b) in candidates {
+        let similarity = match prints[a].digest == prints[b].digest {
+            true => 1.0,
+            false => estimate(&prints[a].signature, &prints[b].signature),
+        };
+        if similarity >= threshold {
+            let (ra, rb) = (find(&mut parents, a), find(&mut parents, b));
+            parents[ra.max(rb)] = ra.min(rb);
+            edges.push((a, similarity));
+        }
+    }
+    for (node, similarity) in edges {
+        let root = find(&mut parents, node);
+        let entry = weakest.entry(root).or_insert(1.0);
+        *entry = entry.min(similarity);
+    }
+
+    let mut groups: HashMap<usize, Vec<Fingerprint>> = HashMap::new();
+    for (index, print) in prints.into_iter().enumerate() {
+        let root = find(&mut parents, index);
+        groups.entry(root).or_default().push(print);
+    }
+    let mut clusters: Vec<DuplicateCluster> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(root, mut members)| {
+            // The most recently edited copy is kept, the longest on a tie
+            members.sort_by(|a, b| {
+                (b.document.updated_at, b.document.word_count)
+                    .cmp(&(a.document.updated_at, a.document.word_count))
+            });
+            let exact = members.iter().all(|m| m.digest == members[0].digest);
+            let documents: Vec<DuplicateDocument> =
+                members.into_iter().map(|m| m.document).collect();
+            DuplicateCluster {
+                suggestion: MergeSuggestion {
+                    target: documents[0].path.clone(),
+                    sources: documents[1..].iter().map(|d| d.path.clone()).collect(),
+                },
+                documents,
+                similarity: weakest.get(&root).copied().unwrap_or(1.0),
+                exact,
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then(b.documents.len().cmp(&a.documents.len()))
+    });
+    clusters
+}
+
+/// Group documents that are copies or near-copies of each other. Exact copies are found by
+/// hashing their normalized text, near-copies by MinHash over word shingles; `threshold` is
+/// the least estimated similarity, between 0 and 1, that makes two documents duplicates.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn find_duplicate_documents(
+    app: AppHandle,
+    threshold: Option<f64>,
+    workspace: Option<String>,
+) -> Result<Vec<DuplicateCluster>, Error> {
+    let root = paths::workspace_or_current(&app, workspace.as_deref(), paths::Scope::Read)?;
+    let threshold = threshold.unwrap_or(DEFAULT_THRESHOLD).clamp(0.1, 1.0);
+    tauri::async_runtime::spawn_blocking(move || {
+        Ok(clusters(fingerprints(&app, &root)?, threshold))
+    })
+    .await
+    .map_err(|e| format!("Duplicate search task failed: {}", e))?
+}
+
+/// Fold documents into `target`: their text blocks not already in it are appended, their
+/// titles become its aliases, links to them are pointed at it, and they are moved to the
+/// workspace trash
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn merge_documents(
+    app: AppHandle,
+    target: String,
+    sources: Vec<String>,
+    workspace: Option<String>,
+) -> Result<MergeReport, Error> {
+    let root = paths::workspace_or_current(&app, workspace.as_deref(), paths::Scope::Write)?;
+    if sources.is_empty() || sources.contains(&target) {
+        return Err("Invalid merge: sources must be other documents than the target".into());
+    }
+    let into = paths::check(
+        &app,
+        &root.join(&target).to_string_lossy(),
+        paths::Scope::Write,
+    )?;
+    // Resolved so the same document named twice is only merged once
+    let mut resolved: Vec<PathBuf> = Vec::new();
+    for source in &sources {
+        let path = paths::check(
+            &app,
+            &root.join(source).to_string_lossy(),
+            paths::Scope::Write,
+        )?;
+        // Trashed vault documents could never be decrypted again
+        if vault::vault_of(&path).is_some() {
+            return Err(
+                Error::invalid_path("Merging is not supported for vault documents")
+                    .with_context(source),
+            );
+        }
+        if path == into {
+            return Err("Invalid merge: sources must be other documents than the target".into());
+        }
+        if !resolved.contains(&path) {
+            resolved.push(path);
+        }
+    }
+    blocking::run("merge documents", move || {
+        let mut board = document::read_board(&into)?;
+        let target_dir = into.parent().unwrap_or(&root).to_path_buf();
+        let mut seen: HashSet<Vec<String>> = blocks(&board).iter().map(|b| words(b)).collect();
+        let mut names: HashSet<String> = board
+            .board
+            .aliases
+            .iter()
+            .chain([&board.board.name])
+            .map(|name| name.to_lowercase())
+            .collect();
+        let mut report = MergeReport {
+            path: target.clone(),
+            ..MergeReport::default()
+        };
+
+        let mut merged = Vec::new();
+        for path in resolved {
+            let source_board = document::read_board(&path)?;
+            let source_dir = path.parent().unwrap_or(&root);
+            for block in blocks(&source_board) {
+                let key = words(&block);
+                if key.is_empty() || !seen.insert(key) {
+                    continue;
+                }
+                board.push_markdown(&archive::rebase_links(&block, source_dir, &target_dir));
+                report.blocks_added += 1;
+            }
+            let titles = source_board
+                .board
+                .aliases
+                .iter()
+                .chain([&source_board.board.name]);
+            for title in titles {
+                if names.insert(title.to_lowercase()) {
+                    board.board.aliases.push(title.clone());
+                    report.aliases_added.push(title.clone());
+                }
+            }
+            merged.push(path);
+        }
+        board.board.updated_at = document::now_millis();
+        document::write_board(&into, &board)?;
+        events::file_changed(&app, &into, ChangeKind::Modified);
+
+        let trash = workspace::internal_dir(&root)
+            .join(TRASH_DIR)
+            .join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+        for path in merged {
+            let relative = workspace::relative_path(&root, &path);
+            let copy = trash.join(&relative);
+            if let Some(parent) = copy.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create trash: {}", e))?;
+            }
+            fs::rename(&path, &copy)
+                .map_err(|e| format!("Failed to move {} to trash: {}", relative, e))?;
+            events::file_changed(&app, &path, ChangeKind::Deleted);
+            audit::record(&root, AuditAction::Trash, AuditSource::Ui, &[&path]);
+            for document in archive::redirect_links(&app, &root, &path, &into)? {
+                if !report.relinked.contains(&document) {
+                    report.relinked.push(document);
+                }
+            }
+            report.trashed.push(relative);
+            report.trash_dir = Some(trash.to_string_lossy().to_string());
+        }
+        Ok::<_, Error>(report)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "The quarterly planning meeting covered the roadmap for the mobile app, \
+        the migration of the sync service to the new storage backend, hiring for the design \
+        team, and a review of the support queue. Action items were assigned to each owner \
+        with a due date before the next meeting in three weeks, and the notes were shared \
+        with the wider engineering group after the call ended on Thursday afternoon.";
+
+    fn print(path: &str, text: &str, updated_at: i64) -> Fingerprint {
+        let words = words(text);
+        Fingerprint {
+            digest: format!("{:x}", Sha256::digest(words.join(" ").as_bytes())),
+            signature: signature(&words),
+            document: DuplicateDocument {
+                path: path.to_string(),
+                name: path.to_string(),
+                word_count: words.len(),
+                updated_at,
+            },
+        }
+    }
+
+    fn near_copy() -> String {
+        TEXT.replace("Thursday", "Friday")
+    }
+
+    #[test]
+    fn words_ignore_case_and_punctuation() {
+        assert_eq!(words("Hello, WORLD!  it's"), ["hello", "world", "it", "s"]);
+    }
+
+    #[test]
+    fn identical_word_lists_have_identical_signatures() {
+        let a = signature(&words(TEXT));
+        let b = signature(&words(&TEXT.to_uppercase()));
+        assert_eq!(a, b);
+        assert_eq!(estimate(&a, &b), 1.0);
+    }
+
+    #[test]
+    fn exact_copies_cluster_with_the_newest_as_target() {
+        let found = clusters(
+            vec![
+                print("old.inkfinite.json", TEXT, 1),
+                print("new.inkfinite.json", &TEXT.to_lowercase(), 2),
+            ],
+            0.8,
+        );
+        assert_eq!(found.len(), 1);
+        assert!(found[0].exact);
+        assert_eq!(found[0].similarity, 1.0);
+        assert_eq!(found[0].suggestion.target, "new.inkfinite.json");
+        assert_eq!(found[0].suggestion.sources, ["old.inkfinite.json"]);
+    }
+
+    #[test]
+    fn near_copies_cluster_without_being_exact() {
+        let found = clusters(
+            vec![
+                print("a.inkfinite.json", TEXT, 1),
+                print("b.inkfinite.json", &near_copy(), 2),
+            ],
+            0.8,
+        );
+        assert_eq!(found.len(), 1);
+        assert!(!found[0].exact);
+        assert!(found[0].similarity >= 0.8 && found[0].similarity < 1.0);
+    }
+
+    #[test]
+    fn unrelated_documents_do_not_cluster() {
+        let other = "Recipe for sourdough bread: mix flour, water and starter, rest the dough \
+            overnight in a cool place, shape it in the morning and bake in a hot oven.";
+        let found = clusters(
+            vec![
+                print("a.inkfinite.json", TEXT, 1),
+                print("b.inkfinite.json", other, 2),
+            ],
+            0.5,
+        );
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn similarity_at_the_threshold_counts_as_duplicate() {
+        let similarity = estimate(&signature(&words(TEXT)), &signature(&words(&near_copy())));
+        let pair = || {
+            vec![
+                print("a.inkfinite.json", TEXT, 1),
+                print("b.inkfinite.json", &near_copy(), 2),
+            ]
+        };
+        assert_eq!(clusters(pair(), similarity).len(), 1);
+        assert!(clusters(pair(), similarity + 1.0 / SIGNATURE as f64).is_empty());
+    }
+
+    #[test]
+    fn exact_copies_cluster_at_any_threshold() {
+        let found = clusters(
+            vec![
+                print("a.inkfinite.json", TEXT, 1),
+                print("b.inkfinite.json", TEXT, 1),
+            ],
+            1.0,
+        );
+        assert_eq!(found.len(), 1);
+        assert!(found[0].exact);
+    }
+}
@@ -0,0 +1,572 @@
+use crate::cancel::CancelToken;
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::http::{self, Stream};
+use crate::{pandoc, paths, publish, sanitize, secrets, vault, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+const CONFIG: &str = "email";
+const TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REPLY: usize = 64 * 1024;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailFormat {
+    Html,
+    Pdf,
+    Markdown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SmtpSecurity {
+    /// TLS from the first byte, usually port 465
+    Tls,
+    /// Plain connection upgraded with `STARTTLS`, usually port 587
+    StartTls,
+}
+
+/// Outgoing mail server for a workspace. The password is the name of a workspace secret (see
+/// [`crate::secrets`]), so this can be kept in the workspace config.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpAccount {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: String,
+    pub password_secret: String,
+    /// Sender address
+    pub from: String,
+    #[serde(default)]
+    pub from_name: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Delivery {
+    /// Sent through the workspace's SMTP account
+    Sent,
+    /// A draft with the attachment opened in the mail client
+    Draft,
+    /// A `mailto:` draft without the attachment, which is revealed instead
+    Mailto,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailResult {
+    pub delivery: Delivery,
+    /// The exported file that was attached
+    pub attachment: String,
+}
+
+/// Reject anything that could smuggle extra headers or SMTP commands into a message
+fn check_address(address: &str) -> Result<(), String> {
+    let valid = address.contains('@')
+        && !address.starts_with('-')
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ',' | ';'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid email address: {}", address))
+    }
+}
+
+/// Export the document to `format` in the app cache, returning the file and its MIME type
+fn export(
+    app: &AppHandle,
+    root: &Path,
+    path: &Path,
+    board: &BoardFile,
+    format: EmailFormat,
+) -> Result<(PathBuf, &'static str), String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))?
+        .join("email");
+    // Only the latest attachment is kept; a draft may still be reading it
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let stem = document::document_stem(path);
+    match format {
+        EmailFormat::Markdown => {
+            let file = dir.join(format!("{}.md", stem));
+            fs::write(&file, board.to_markdown())
+                .map_err(|e| format!("Failed to write file: {}", e))?;
+            Ok((file, "text/markdown; charset=utf-8"))
+        }
+        EmailFormat::Html => {
+            let file = dir.join(format!("{}.html", stem));
+            let page = publish::render(root, path, board, &sanitize::load_policy(root));
+            fs::write(&file, page).map_err(|e| format!("Failed to write file: {}", e))?;
+            Ok((file, "text/html; charset=utf-8"))
+        }
+        EmailFormat::Pdf => {
+            let file = dir.join(format!("{}.pdf", stem));
+            // pandoc typesets LaTeX into a PDF when the output file ends in .pdf
+            pandoc::convert_markdown(
+                app,
+                &board.to_markdown(),
+                &board.board.name,
+                "latex",
+                &file,
+                &CancelToken::default(),
+            )?;
+            Ok((file, "application/pdf"))
+        }
+    }
+}
+
+/// A header value, RFC 2047 encoded when it is not plain ASCII
+fn header_text(text: &str) -> String {
+    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+    if text.is_ascii() {
+        text
+    } else {
+        format!("=?UTF-8?B?{}?=", BASE64.encode(text))
+    }
+}
+
+/// Base64 wrapped at 76 columns, as MIME requires
+fn base64_lines(bytes: &[u8]) -> String {
+    let encoded = BASE64.encode(bytes);
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / 38);
+    for line in encoded.as_bytes().chunks(76) {
+        wrapped.push_str(&String::from_utf8_lossy(line));
+        wrapped.push_str("\r\n");
+    }
+    wrapped
+}
+
+/// RFC 2231 value for a file name that may not be ASCII
+fn encode_file_name(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn message(
+    account: &SmtpAccount,
+    to: &[String],
+    subject: &str,
+    body: &str,
+    attachment: &Path,
+    mime: &str,
+) -> Result<String, String> {
+    let content = fs::read(attachment).map_err(|e| format!("Failed to read attachment: {}", e))?;
+    let name = attachment
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let fallback: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let boundary = format!("inkfinite-{}", uuid::Uuid::new_v4().simple());
+    let from = match &account.from_name {
+        Some(from_name) => format!("{} <{}>", header_text(from_name), account.from),
+        None => account.from.clone(),
+    };
+    let domain = account.from.rsplit('@').next().unwrap_or("localhost");
+
+    let mut message = String::new();
+    message.push_str(&format!("From: {}\r\n", from));
+    message.push_str(&format!("To: {}\r\n", to.join(", ")));
+    message.push_str(&format!("Subject: {}\r\n", header_text(subject)));
+    message.push_str(&format!("Date: {}\r\n", chrono::Local::now().to_rfc2822()));
+    message.push_str(&format!(
+        "Message-ID: <{}@{}>\r\n",
+        uuid::Uuid::new_v4(),
+        domain
+    ));
+    message.push_str("MIME-Version: 1.0\r\n");
+    message.push_str(&format!(
+        "Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n",
+        boundary
+    ));
+    message.push_str(&format!("--{}\r\n", boundary));
+    message.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+    message.push_str(&base64_lines(body.as_bytes()));
+    message.push_str(&format!("--{}\r\n", boundary));
+    message.push_str(&format!(
+        "Content-Type: {}; name=\"{}\"\r\n",
+        mime, fallback
+    ));
+    message.push_str(&format!(
+        "Content-Disposition: attachment; filename=\"{}\"; filename*=UTF-8''{}\r\n",
+        fallback,
+        encode_file_name(&name)
+    ));
+    message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+    message.push_str(&base64_lines(&content));
+    message.push_str(&format!("--{}--\r\n", boundary));
+    Ok(message)
+}
+
+/// A client session with a mail server (RFC 5321)
+struct Smtp {
+    stream: Stream,
+    buffer: Vec<u8>,
+}
+
+impl Smtp {
+    /// Read one reply, joining the lines of a multi-line reply
+    fn reply(&mut self) -> Result<(u16, String), String> {
+        let mut lines = Vec::new();
+        loop {
+            while !self.buffer.contains(&b'\n') {
+                if self.buffer.len() > MAX_REPLY {
+                    return Err("Invalid mail server reply: too long".to_string());
+                }
+                let mut chunk = [0u8; 1024];
+                let read = self
+                    .stream
+                    .read(&mut chunk)
+                    .map_err(|e| format!("Failed to read from mail server: {}", e))?;
+                if read == 0 {
+                    return Err("Mail server closed the connection".to_string());
+                }
+                self.buffer.extend_from_slice(&chunk[..read]);
+            }
+            let end = self.buffer.iter().position(|&b| b == b'\n').unwrap_or(0);
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| format!("Invalid mail server reply: {}", line))?;
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push(line.get(4..).unwrap_or_default().to_string());
+            if last {
+                return Ok((code, lines.join("\n")));
+            }
+        }
+    }
+
+    /// Read a reply and fail unless its code is in the `class` hundreds
+    fn expect(&mut self, class: u16) -> Result<String, String> {
+        let (code, text) = self.reply()?;
+        if code / 100 == class {
+            Ok(text)
+        } else {
+            Err(format!(
+                "Mail server refused the message: {} {}",
+                code, text
+            ))
+        }
+    }
+
+    fn command(&mut self, line: &str, class: u16) -> Result<String, String> {
+        self.stream
+            .write_all(format!("{}\r\n", line).as_bytes())
+            .and_then(|_| self.stream.flush())
+            .map_err(|e| format!("Failed to write to mail server: {}", e))?;
+        self.expect(class)
+    }
+
+    fn hello(&mut self) -> Result<String, String> {
+        self.command("EHLO inkfinite", 2)
+    }
+}
+
+/// Deliver `content` to `to` through `account`
+fn send(account: &SmtpAccount, password: &str, to: &[String], content: &str) -> Result<(), String> {
+    let address = (account.host.as_str(), account.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve mail server: {}", e))?
+        .next()
+        .ok_or_else(|| format!("Failed to resolve mail server: {}", account.host))?;
+    let tcp = TcpStream::connect_timeout(&address, TIMEOUT)
+        .map_err(|e| format!("Failed to connect to mail server: {}", e))?;
+    let _ = tcp.set_read_timeout(Some(TIMEOUT));
+    let _ = tcp.set_write_timeout(Some(TIMEOUT));
+    let stream = match account.security {
+        SmtpSecurity::Tls => Stream::Tls(Box::new(http::tls(&account.host, tcp)?)),
+        SmtpSecurity::StartTls => Stream::Plain(tcp),
+    };
+    let mut smtp = Smtp {
+        stream,
+        buffer: Vec::new(),
+    };
+    smtp.expect(2)?;
+    let mut capabilities = smtp.hello()?;
+    if account.security == SmtpSecurity::StartTls {
+        smtp.command("STARTTLS", 2)?;
+        let Stream::Plain(tcp) = smtp.stream else {
+            return Err("Failed to start TLS: connection is already encrypted".to_string());
+        };
+        // Anything buffered before the upgrade was not protected and is discarded
+        smtp = Smtp {
+            stream: Stream::Tls(Box::new(http::tls(&account.host, tcp)?)),
+            buffer: Vec::new(),
+        };
+        capabilities = smtp.hello()?;
+    }
+
+    let plain = capabilities
+        .lines()
+        .any(|line| line.to_ascii_uppercase().starts_with("AUTH") && line.contains("PLAIN"));
+    if plain {
+        let credentials = BASE64.encode(format!("\0{}\0{}", account.username, password));
+        smtp.command(&format!("AUTH PLAIN {}", credentials), 2)?;
+    } else {
+        smtp.command("AUTH LOGIN", 3)?;
+        smtp.command(&BASE64.encode(&account.username), 3)?;
+        smtp.command(&BASE64.encode(password), 2)?;
+    }
+
+    smtp.command(&format!("MAIL FROM:<{}>", account.from), 2)?;
+    for recipient in to {
+        smtp.command(&format!("RCPT TO:<{}>", recipient), 2)?;
+    }
+    smtp.command("DATA", 3)?;
+    // A line holding only "." ends the message, so leading dots are doubled
+    let mut data = String::with_capacity(content.len() + 8);
+    for line in content.split_inclusive("\r\n") {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+    }
+    data.push('.');
+    smtp.command(&data, 2)?;
+    let _ = smtp.command("QUIT", 2);
+    Ok(())
+}
+
+/// Open a draft with the attachment in the default mail client through Simple MAPI; `false`
+/// when no client is registered for it
+#[cfg(windows)]
+fn compose(to: &[String], subject: &str, body: &str, attachment: &Path) -> Result<bool, String> {
+    use windows::core::{s, w, PWSTR};
+    use windows::Win32::Foundation::{FreeLibrary, FARPROC};
+    use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+    use windows::Win32::System::Mapi::{
+        MapiFileDescW, MapiMessageW, MapiRecipDescW, LPMAPISENDMAILW, MAPI_DIALOG,
+        MAPI_E_USER_ABORT, MAPI_LOGON_UI, MAPI_TO, SUCCESS_SUCCESS,
+    };
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain([0]).collect()
+    }
+
+    let mut subject = wide(subject);
+    let mut body = wide(body);
+    let mut path = wide(&attachment.to_string_lossy());
+    let mut name = wide(
+        &attachment
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    );
+    let mut addresses: Vec<(Vec<u16>, Vec<u16>)> = to
+        .iter()
+        .map(|address| (wide(address), wide(&format!("SMTP:{}", address))))
+        .collect();
+    let mut recipients: Vec<MapiRecipDescW> = addresses
+        .iter_mut()
+        .map(|(name, address)| MapiRecipDescW {
+            ulRecipClass: MAPI_TO,
+            lpszName: PWSTR(name.as_mut_ptr()),
+            lpszAddress: PWSTR(address.as_mut_ptr()),
+            ..Default::default()
+        })
+        .collect();
+    let mut file = MapiFileDescW {
+        nPosition: u32::MAX,
+        lpszPathName: PWSTR(path.as_mut_ptr()),
+        lpszFileName: PWSTR(name.as_mut_ptr()),
+        ..Default::default()
+    };
+    let message = MapiMessageW {
+        lpszSubject: PWSTR(subject.as_mut_ptr()),
+        lpszNoteText: PWSTR(body.as_mut_ptr()),
+        nRecipCount: recipients.len() as u32,
+        lpRecips: if recipients.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            recipients.as_mut_ptr()
+        },
+        nFileCount: 1,
+        lpFiles: &mut file,
+        ..Default::default()
+    };
+
+    unsafe {
+        let Ok(library) = LoadLibraryW(w!("MAPI32.dll")) else {
+            return Ok(false);
+        };
+        let send = std::mem::transmute::<FARPROC, LPMAPISENDMAILW>(GetProcAddress(
+            library,
+            s!("MAPISendMailW"),
+        ));
+        // Blocks while the draft window is open
+        let status = send.map(|send| send(0, 0, &message, MAPI_DIALOG | MAPI_LOGON_UI, 0));
+        let _ = FreeLibrary(library);
+        match status {
+            Some(SUCCESS_SUCCESS) => Ok(true),
+            Some(MAPI_E_USER_ABORT) => Err("Email was cancelled".to_string()),
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Open a draft with the attachment through `xdg-email`; `false` when it is not installed or
+/// cannot hand the draft to a client
+#[cfg(target_os = "linux")]
+fn compose(to: &[String], subject: &str, body: &str, attachment: &Path) -> Result<bool, String> {
+    let status = std::process::Command::new("xdg-email")
+        .arg("--subject")
+        .arg(subject)
+        .arg("--body")
+        .arg(body)
+        .arg("--attach")
+        .arg(attachment)
+        .args(to)
+        .status();
+    Ok(status.is_ok_and(|status| status.success()))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn compose(
+    _to: &[String],
+    _subject: &str,
+    _body: &str,
+    _attachment: &Path,
+) -> Result<bool, String> {
+    Ok(false)
+}
+
+/// `mailto:` URL for a draft (RFC 6068)
+fn mailto(to: &[String], subject: &str, body: &str) -> String {
+    let encode = |text: &str| {
+        url::form_urlencoded::byte_serialize(text.as_bytes())
+            .collect::<String>()
+            .replace('+', "%20")
+    };
+    format!(
+        "mailto:{}?subject={}&body={}",
+        to.join(","),
+        encode(subject),
+        encode(body)
+    )
+}
+
+fn email(
+    app: &AppHandle,
+    path: &str,
+    format: EmailFormat,
+    to: Vec<String>,
+    message_text: Option<String>,
+) -> Result<EmailResult, Error> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let resolved = paths::check(app, path, paths::Scope::Read)?;
+    if vault::vault_of(&resolved).is_some() {
+        return Err("Email is not available for documents in a vault".into());
+    }
+    for address in &to {
+        check_address(address)?;
+    }
+    let board = document::read_board(&resolved)?;
+    let (attachment, mime) = export(app, &root, &resolved, &board, format)?;
+    let subject = board.board.name.clone();
+    let body = message_text.unwrap_or_default();
+    let account: Option<SmtpAccount> = workspace::read_config(&root, CONFIG)?;
+
+    let delivery = match account {
+        Some(account) if !to.is_empty() => {
+            let password = secrets::get(&root, &account.password_secret)?
+                .ok_or_else(|| format!("Secret not found: {}", account.password_secret))?;
+            let content = message(&account, &to, &subject, &body, &attachment, mime)?;
+            send(&account, &password, &to, &content)?;
+            tracing::info!(recipients = to.len(), "Document emailed");
+            Delivery::Sent
+        }
+        _ if compose(&to, &subject, &body, &attachment)? => Delivery::Draft,
+        _ => {
+            app.opener()
+                .open_url(mailto(&to, &subject, &body), None::<&str>)
+                .map_err(|e| format!("Failed to open mail client: {}", e))?;
+            // mailto cannot carry attachments, so show the file to drag into the draft
+            #[cfg(desktop)]
+            let _ = tauri_plugin_opener::reveal_item_in_dir(&attachment);
+            Delivery::Mailto
+        }
+    };
+    Ok(EmailResult {
+        delivery,
+        attachment: attachment.to_string_lossy().to_string(),
+    })
+}
+
+/// Email the document at `path` as an HTML, PDF or Markdown attachment.
+///
+/// With an SMTP account configured for the workspace and at least one recipient in `to`, the
+/// message is sent directly. Otherwise a draft opens in the default mail client: through MAPI
+/// on Windows and `xdg-email` on Linux, falling back to a `mailto:` link that cannot carry the
+/// attachment, in which case the exported file is revealed.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn email_document(
+    app: AppHandle,
+    path: String,
+    format: EmailFormat,
+    to: Option<Vec<String>>,
+    message: Option<String>,
+) -> Result<EmailResult, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        email(&app, &path, format, to.unwrap_or_default(), message)
+    })
+    .await
+    .map_err(|e| format!("Email task failed: {}", e))?
+}
+
+/// The SMTP account emails from a workspace are sent through, if one is set
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_email_account(app: AppHandle, workspace: String) -> Result<Option<SmtpAccount>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(workspace::read_config(&root, CONFIG)?)
+}
+
+/// Set the workspace's SMTP account, or remove it with `None` so emails open as drafts. The
+/// password itself is stored with `store_secret` under `passwordSecret`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_email_account(
+    app: AppHandle,
+    workspace: String,
+    account: Option<SmtpAccount>,
+) -> Result<(), Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    if let Some(account) = &account {
+        check_address(&account.from)?;
+        if account.host.trim().is_empty() || account.port == 0 {
+            return Err("Invalid SMTP account: missing host or port".into());
+        }
+    }
+    Ok(workspace::write_config(&root, CONFIG, &account)?)
+}
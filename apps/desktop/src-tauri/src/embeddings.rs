@@ -0,0 +1,367 @@
+use crate::cancel::{self, CancelToken};
+use crate::error::Error;
+use crate::{http, llm, workspace};
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::{AddBos, LlamaModel};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+/// Embedding models live in the app data dir as `models/embeddings/<id>.gguf`
+const MODELS_DIR: &str = "models/embeddings";
+/// Store key of the model semantic features use
+const MODEL_KEY: &str = "embeddingModel";
+const HUB: &str = "https://huggingface.co";
+/// Downloads are pinned to this branch, and checked against the hashes the hub lists for it
+const REVISION: &str = "main";
+const DOWNLOAD_EVENT: &str = "embeddings:download-progress";
+/// Longest input embedded at once; longer text is cut to the model's window
+const MAX_CONTEXT: u32 = 2048;
+
+/// A model the app knows how to download and run
+struct CatalogEntry {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    /// Hugging Face repository and GGUF file within it
+    repo: &'static str,
+    file: &'static str,
+    dimensions: usize,
+    /// Prefixes the model was trained with for search queries and for the passages searched
+    query_prefix: &'static str,
+    document_prefix: &'static str,
+}
+
+const CATALOG: &[CatalogEntry] = &[
+    CatalogEntry {
+        id: "all-minilm-l6-v2",
+        name: "all-MiniLM-L6-v2",
+        description: "Small and fast; English",
+        repo: "second-state/All-MiniLM-L6-v2-Embedding-GGUF",
+        file: "all-MiniLM-L6-v2-Q8_0.gguf",
+        dimensions: 384,
+        query_prefix: "",
+        document_prefix: "",
+    },
+    CatalogEntry {
+        id: "bge-small-en-v1.5",
+        name: "BGE small v1.5",
+        description: "Small, more accurate retrieval; English",
+        repo: "CompendiumLabs/bge-small-en-v1.5-gguf",
+        file: "bge-small-en-v1.5-q8_0.gguf",
+        dimensions: 384,
+        query_prefix: "Represent this sentence for searching relevant passages: ",
+        document_prefix: "",
+    },
+    CatalogEntry {
+        id: "nomic-embed-text-v1.5",
+        name: "Nomic Embed Text v1.5",
+        description: "Larger, long documents; English",
+        repo: "nomic-ai/nomic-embed-text-v1.5-GGUF",
+        file: "nomic-embed-text-v1.5.Q8_0.gguf",
+        dimensions: 768,
+        query_prefix: "search_query: ",
+        document_prefix: "search_document: ",
+    },
+];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingModel {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub dimensions: usize,
+    pub installed: bool,
+    /// Size on disk when installed
+    pub size: Option<u64>,
+    /// The model semantic features use
+    pub active: bool,
+}
+
+/// Managed state: the embedding model in use, loaded on first use
+#[derive(Default)]
+pub struct Embedder {
+    model: Mutex<Option<(String, Arc<LlamaModel>)>>,
+}
+
+/// Whether text is a search query or a passage to be searched
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Purpose {
+    Query,
+    Document,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DownloadEvent {
+    id: String,
+    received: u64,
+    total: Option<u64>,
+}
+
+fn entry(id: &str) -> Result<&'static CatalogEntry, String> {
+    CATALOG
+        .iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| format!("Embedding model not found: {}", id))
+}
+
+fn models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?
+        .join(MODELS_DIR))
+}
+
+fn file_path(app: &AppHandle, entry: &CatalogEntry) -> Result<PathBuf, String> {
+    Ok(models_dir(app)?.join(format!("{}.gguf", entry.id)))
+}
+
+fn chosen(app: &AppHandle) -> Option<String> {
+    app.store(workspace::STORE_NAME)
+        .ok()?
+        .get(MODEL_KEY)?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// The chosen model, or the first installed one
+fn active_id(app: &AppHandle) -> Option<String> {
+    let installed = |id: &str| {
+        entry(id)
+            .and_then(|entry| file_path(app, entry))
+            .is_ok_and(|path| path.is_file())
+    };
+    chosen(app).filter(|id| installed(id)).or_else(|| {
+        CATALOG
+            .iter()
+            .find(|entry| installed(entry.id))
+            .map(|entry| entry.id.to_string())
+    })
+}
+
+fn save_active(app: &AppHandle, id: Option<&str>) -> Result<(), String> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(MODEL_KEY, json!(id));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// SHA-256 and size the hub lists for the model file
+fn published_hash(entry: &CatalogEntry) -> Result<(String, u64), String> {
+    let url = format!("{}/api/models/{}/tree/{}", HUB, entry.repo, REVISION);
+    let body = http::agent()
+        .get(&url)
+        .call()
+        .map_err(|e| format!("Failed to fetch model listing: {}", e))?
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to read model listing: {}", e))?;
+    let listing: Value =
+        serde_json::from_str(&body).map_err(|e| format!("Invalid model listing: {}", e))?;
+    let file = listing
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|file| file["path"] == entry.file)
+        .ok_or_else(|| format!("Model file not found: {}", entry.file))?;
+    let hash = file["lfs"]["oid"]
+        .as_str()
+        .ok_or_else(|| format!("Model listing has no hash for {}", entry.file))?;
+    Ok((hash.to_string(), file["lfs"]["size"].as_u64().unwrap_or(0)))
+}
+
+fn download(app: &AppHandle, id: &str, cancel: &CancelToken) -> Result<EmbeddingModel, String> {
+    let entry = entry(id)?;
+    let (sha256, size) = published_hash(entry)?;
+    let target = file_path(app, entry)?;
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    let url = format!("{}/{}/resolve/{}/{}", HUB, entry.repo, REVISION, entry.file);
+    let received = llm::fetch(&url, &target, Some(&sha256), cancel, |received, total| {
+        let _ = app.emit(
+            DOWNLOAD_EVENT,
+            DownloadEvent {
+                id: entry.id.to_string(),
+                received,
+                total: total.or(Some(size).filter(|size| *size > 0)),
+            },
+        );
+    })?;
+    tracing::info!(id, bytes = received, "Embedding model downloaded");
+    // The first model downloaded becomes the active one
+    if chosen(app).is_none() {
+        save_active(app, Some(entry.id))?;
+    }
+    Ok(describe(app, entry, active_id(app).as_deref()))
+}
+
+fn describe(app: &AppHandle, entry: &CatalogEntry, active: Option<&str>) -> EmbeddingModel {
+    let size = file_path(app, entry)
+        .ok()
+        .and_then(|path| path.metadata().ok())
+        .map(|metadata| metadata.len());
+    EmbeddingModel {
+        id: entry.id.to_string(),
+        name: entry.name.to_string(),
+        description: entry.description.to_string(),
+        dimensions: entry.dimensions,
+        installed: size.is_some(),
+        size,
+        active: active == Some(entry.id),
+    }
+}
+
+/// Drop the loaded model, such as after the compute preference changed
+pub fn unload(app: &AppHandle) {
+    if let Ok(mut model) = app.state::<Embedder>().model.lock() {
+        *model = None;
+    }
+}
+
+/// Id of the model semantic features use, failing when none is installed
+pub fn active_model(app: &AppHandle) -> Result<String, String> {
+    active_id(app).ok_or_else(|| "No embedding model is installed. Download one first".to_string())
+}
+
+fn load_model(app: &AppHandle, entry: &CatalogEntry) -> Result<Arc<LlamaModel>, String> {
+    let state = app.state::<Embedder>();
+    let mut model = state
+        .model
+        .lock()
+        .map_err(|e| format!("Failed to load embedding model: {}", e))?;
+    if let Some((_, model)) = model.as_ref().filter(|(id, _)| id == entry.id) {
+        return Ok(model.clone());
+    }
+    *model = None;
+    let backend = app.state::<llm::LocalLlm>().backend()?;
+    let loaded = LlamaModel::load_from_file(
+        &backend,
+        file_path(app, entry)?,
+        &llm::model_params(app, &backend),
+    )
+    .map_err(|e| format!("Failed to load embedding model: {}", e))?;
+    let loaded = Arc::new(loaded);
+    *model = Some((entry.id.to_string(), loaded.clone()));
+    Ok(loaded)
+}
+
+/// Unit-length embeddings of `texts` with the model `id`, one per text
+pub fn embed(
+    app: &AppHandle,
+    id: &str,
+    texts: &[String],
+    purpose: Purpose,
+) -> Result<Vec<Vec<f32>>, String> {
+    let entry = entry(id)?;
+    let model = load_model(app, entry)?;
+    let backend = app.state::<llm::LocalLlm>().backend()?;
+    let window = model.n_ctx_train().clamp(1, MAX_CONTEXT);
+    let params = LlamaContextParams::default()
+        .with_n_ctx(NonZeroU32::new(window))
+        .with_n_batch(window)
+        .with_n_ubatch(window)
+        .with_embeddings(true);
+    let failed = |e: &dyn std::fmt::Display| format!("Failed to embed text: {}", e);
+    let mut context = model
+        .new_context(&backend, params)
+        .map_err(|e| failed(&e))?;
+    let prefix = match purpose {
+        Purpose::Query => entry.query_prefix,
+        Purpose::Document => entry.document_prefix,
+    };
+    let mut batch = LlamaBatch::new(window as usize, 1);
+    let mut vectors = Vec::with_capacity(texts.len());
+    for text in texts {
+        let mut tokens = model
+            .str_to_token(&format!("{}{}", prefix, text), AddBos::Always)
+            .map_err(|e| failed(&e))?;
+        tokens.truncate(window as usize);
+        batch.clear();
+        for (position, token) in tokens.iter().enumerate() {
+            batch
+                .add(*token, position as i32, &[0], true)
+                .map_err(|e| failed(&e))?;
+        }
+        context.clear_kv_cache();
+        context.decode(&mut batch).map_err(|e| failed(&e))?;
+        let mut vector = context
+            .embeddings_seq_ith(0)
+            .map_err(|e| failed(&e))?
+            .to_vec();
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vectors.push(vector);
+    }
+    Ok(vectors)
+}
+
+/// Embedding models the app can download, with what is installed and which one is active
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_available_models(app: AppHandle) -> Result<Vec<EmbeddingModel>, Error> {
+    let active = active_id(&app);
+    Ok(CATALOG
+        .iter()
+        .map(|entry| describe(&app, entry, active.as_deref()))
+        .collect())
+}
+
+/// Download an embedding model from the catalog, reporting `embeddings:download-progress` and
+/// checking the file against the hash the hub publishes for it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn download_model(
+    app: AppHandle,
+    id: String,
+    op_id: Option<String>,
+) -> Result<EmbeddingModel, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = cancel::begin(&app, op_id);
+        download(&app, &id, operation.token()).map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Download task failed: {}", e))?
+}
+
+/// Choose the model semantic features use; it must be installed
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_embedding_model(app: AppHandle, id: String) -> Result<EmbeddingModel, Error> {
+    let entry = entry(&id)?;
+    if !file_path(&app, entry)?.is_file() {
+        return Err(Error::not_found("Embedding model is not installed").with_context(&id));
+    }
+    save_active(&app, Some(entry.id))?;
+    Ok(describe(&app, entry, Some(entry.id)))
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_model(app: AppHandle, id: String) -> Result<bool, Error> {
+    let entry = entry(&id)?;
+    let deleted = match fs::remove_file(file_path(&app, entry)?) {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => return Err(format!("Failed to delete model: {}", e).into()),
+    };
+    if chosen(&app).as_deref() == Some(entry.id) {
+        save_active(&app, None)?;
+    }
+    unload(&app);
+    Ok(deleted)
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::io;
+
+/// What went wrong, for the frontend to branch on and localize
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    NotFound,
+    PermissionDenied,
+    /// The target already exists or changed underneath the operation
+    Conflict,
+    /// A path is malformed or outside where the operation may reach
+    InvalidPath,
+    /// Input or file content could not be parsed
+    InvalidData,
+    Timeout,
+    Cancelled,
+    /// A required tool, device or service is missing or unreachable
+    Unavailable,
+    /// The workspace was marked read-only
+    ReadOnly,
+    /// The workspace has a passphrase and has not been unlocked
+    Locked,
+    Internal,
+}
+
+/// Error returned by commands, serialized as `{ code, message, context, localizedMessage }`.
+///
+/// `message` is English text for logs and as a fallback; `context` carries the path or value
+/// the error is about, when there is one. `localizedMessage` is the code's message in the
+/// interface language.
+#[derive(Clone, Debug)]
+pub struct Error {
+    pub code: ErrorCode,
+    pub message: String,
+    pub context: Option<String>,
+}
+
+impl Error {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Error {
+            code,
+            message: message.into(),
+            context: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn invalid_path(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::InvalidPath, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::Conflict, message)
+    }
+
+    /// Wrap an I/O error, keeping its kind as the code
+    pub fn io(action: &str, error: io::Error) -> Self {
+        Error::new(code_for_io(error.kind()), format!("{}: {}", action, error))
+    }
+
+    pub fn with_context(mut self, context: impl fmt::Display) -> Self {
+        self.context = Some(context.to_string());
+        self
+    }
+}
+
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut error = serializer.serialize_struct("Error", 4)?;
+        error.serialize_field("code", &self.code)?;
+        error.serialize_field("message", &self.message)?;
+        error.serialize_field("context", &self.context)?;
+        error.serialize_field(
+            "localizedMessage",
+            &crate::messages::error(self.code, self.context.as_deref()),
+        )?;
+        error.end()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::new(code_for_io(error.kind()), error.to_string())
+    }
+}
+
+/// Classify the crate's `String` errors by the phrasing the modules use for them.
+///
+/// I/O failures embed the OS error number (`... (os error 2)`), which is mapped back to its
+/// kind so the code is right on every platform.
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        let code = os_error(&message)
+            .map(|errno| code_for_io(io::Error::from_raw_os_error(errno).kind()))
+            .unwrap_or_else(|| code_for_message(&message));
+        Error::new(code, message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::from(message.to_string())
+    }
+}
+
+fn code_for_io(kind: io::ErrorKind) -> ErrorCode {
+    match kind {
+        io::ErrorKind::NotFound => ErrorCode::NotFound,
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+            ErrorCode::PermissionDenied
+        }
+        io::ErrorKind::AlreadyExists
+        | io::ErrorKind::DirectoryNotEmpty
+        | io::ErrorKind::ResourceBusy => ErrorCode::Conflict,
+        io::ErrorKind::InvalidInput
+        | io::ErrorKind::InvalidFilename
+        | io::ErrorKind::NotADirectory
+        | io::ErrorKind::IsADirectory
+        | io::ErrorKind::CrossesDevices => ErrorCode::InvalidPath,
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => ErrorCode::InvalidData,
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ErrorCode::Timeout,
+        io::ErrorKind::Interrupted => ErrorCode::Cancelled,
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::NetworkUnreachable
+        | io::ErrorKind::HostUnreachable
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::Unsupported => ErrorCode::Unavailable,
+        _ => ErrorCode::Internal,
+    }
+}
+
+fn os_error(message: &str) -> Option<i32> {
+    let start = message.rfind("(os error ")? + "(os error ".len();
+    let end = start + message[start..].find(')')?;
+    message[start..end].parse().ok()
+}
+
+fn code_for_message(message: &str) -> ErrorCode {
+    let lower = message.to_lowercase();
+    let has = |phrase: &str| lower.contains(phrase);
+    if has("is read-only") {
+        ErrorCode::ReadOnly
+    } else if has("workspace is locked") {
+        ErrorCode::Locked
+    } else if has("does not exist") || has("not found") || has("no workspace is open") {
+        ErrorCode::NotFound
+    } else if has("outside the workspace") || has("invalid path") || has("is a directory") {
+        ErrorCode::InvalidPath
+    } else if has("is locked") || has("wrong passphrase") || has("verification failed") {
+        ErrorCode::PermissionDenied
+    } else if has("already exists") || has("conflict") {
+        ErrorCode::Conflict
+    } else if has("timed out") {
+        ErrorCode::Timeout
+    } else if has("cancelled") {
+        ErrorCode::Cancelled
+    } else if has("requires")
+        || has("not installed")
+        || has("not available")
+        || has("not supported")
+    {
+        ErrorCode::Unavailable
+    } else if has("invalid") {
+        ErrorCode::InvalidData
+    } else {
+        ErrorCode::Internal
+    }
+}
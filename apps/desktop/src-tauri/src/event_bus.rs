@@ -0,0 +1,373 @@
+use crate::document;
+use crate::error::Error;
+use crate::events::ChangeKind;
+use crate::jobs::JobInfo;
+use crate::lan_sync::SyncReport;
+use crate::{vault, workspace};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Version of the envelope and of every event's fields. Fields and topics may be added within a
+/// version; renaming or removing one bumps it.
+pub const VERSION: u32 = 1;
+/// Emitted to a window for each event matching one of its subscriptions
+const DELIVERY_EVENT: &str = "event-bus:event";
+/// How often the worker checks whether another workspace was opened
+const WORKSPACE_POLL: Duration = Duration::from_secs(2);
+
+/// Every topic with what it reports, for [`list_event_topics`]
+const TOPICS: &[(&str, &str)] = &[
+    (
+        "document.created",
+        "A document was created in the open workspace",
+    ),
+    (
+        "document.modified",
+        "A document of the open workspace changed",
+    ),
+    (
+        "document.renamed",
+        "A document was renamed or moved; `from` is its old path",
+    ),
+    ("document.deleted", "A document was deleted"),
+    (
+        "document.saved",
+        "An edit from the editor was written to disk",
+    ),
+    (
+        "document.exported",
+        "A document was exported; `output` is the file written",
+    ),
+    ("workspace.opened", "Another workspace was opened"),
+    (
+        "workspace.closed",
+        "The open workspace was closed or replaced",
+    ),
+    ("workspace.locked", "Workspace and vault keys were dropped"),
+    ("workspace.unlocked", "The open workspace was unlocked"),
+    ("sync.started", "A sync with a paired device started"),
+    ("sync.finished", "A sync with a paired device finished"),
+    ("sync.failed", "A sync with a paired device failed"),
+    ("job.queued", "A background job was queued"),
+    ("job.progress", "A running job reported progress"),
+    ("job.done", "A job finished"),
+    ("job.failed", "A job failed or was cancelled"),
+];
+
+/// An event as extensions see it. Document paths are relative to the open workspace, and
+/// documents in vaults are never reported.
+#[derive(Serialize, Clone)]
+#[serde(tag = "topic", content = "data", rename_all_fields = "camelCase")]
+pub enum BusEvent {
+    #[serde(rename = "document.created")]
+    DocumentCreated { path: String },
+    #[serde(rename = "document.modified")]
+    DocumentModified { path: String },
+    #[serde(rename = "document.renamed")]
+    DocumentRenamed { path: String, from: Option<String> },
+    #[serde(rename = "document.deleted")]
+    DocumentDeleted { path: String },
+    #[serde(rename = "document.saved")]
+    DocumentSaved { path: String },
+    #[serde(rename = "document.exported")]
+    DocumentExported { path: String, output: String },
+    #[serde(rename = "workspace.opened")]
+    WorkspaceOpened { root: String },
+    #[serde(rename = "workspace.closed")]
+    WorkspaceClosed { root: String },
+    #[serde(rename = "workspace.locked")]
+    WorkspaceLocked { reason: String },
+    #[serde(rename = "workspace.unlocked")]
+    WorkspaceUnlocked {},
+    #[serde(rename = "sync.started")]
+    SyncStarted { peer: String },
+    #[serde(rename = "sync.finished")]
+    SyncFinished { peer: String, report: SyncReport },
+    #[serde(rename = "sync.failed")]
+    SyncFailed { peer: String, error: String },
+    #[serde(rename = "job.queued")]
+    JobQueued { job: JobInfo },
+    #[serde(rename = "job.progress")]
+    JobProgress { job: JobInfo },
+    #[serde(rename = "job.done")]
+    JobDone { job: JobInfo },
+    #[serde(rename = "job.failed")]
+    JobFailed { job: JobInfo },
+}
+
+impl BusEvent {
+    pub fn topic(&self) -> &'static str {
+        match self {
+            BusEvent::DocumentCreated { .. } => "document.created",
+            BusEvent::DocumentModified { .. } => "document.modified",
+            BusEvent::DocumentRenamed { .. } => "document.renamed",
+            BusEvent::DocumentDeleted { .. } => "document.deleted",
+            BusEvent::DocumentSaved { .. } => "document.saved",
+            BusEvent::DocumentExported { .. } => "document.exported",
+            BusEvent::WorkspaceOpened { .. } => "workspace.opened",
+            BusEvent::WorkspaceClosed { .. } => "workspace.closed",
+            BusEvent::WorkspaceLocked { .. } => "workspace.locked",
+            BusEvent::WorkspaceUnlocked {} => "workspace.unlocked",
+            BusEvent::SyncStarted { .. } => "sync.started",
+            BusEvent::SyncFinished { .. } => "sync.finished",
+            BusEvent::SyncFailed { .. } => "sync.failed",
+            BusEvent::JobQueued { .. } => "job.queued",
+            BusEvent::JobProgress { .. } => "job.progress",
+            BusEvent::JobDone { .. } => "job.done",
+            BusEvent::JobFailed { .. } => "job.failed",
+        }
+    }
+}
+
+/// What subscribers receive: `{ version, seq, timestamp, topic, data }`
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    pub version: u32,
+    /// Increases by one for every event since the app started
+    pub seq: u64,
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub event: BusEvent,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Delivery {
+    subscription: String,
+    event: Envelope,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventTopics {
+    pub version: u32,
+    pub topics: Vec<EventTopic>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventTopic {
+    pub topic: String,
+    pub description: String,
+}
+
+struct Subscription {
+    window: String,
+    filters: Vec<String>,
+}
+
+/// Managed state: the channel to the delivery worker and the windows' subscriptions
+#[derive(Default)]
+pub struct EventBus {
+    sender: Mutex<Option<Sender<BusEvent>>>,
+    subscriptions: Mutex<BTreeMap<String, Subscription>>,
+}
+
+/// Whether a filter is `*`, a topic, or a group such as `document.*`
+fn valid_filter(filter: &str) -> bool {
+    if filter == "*" {
+        return true;
+    }
+    match filter.strip_suffix(".*") {
+        Some(group) => TOPICS
+            .iter()
+            .any(|(topic, _)| topic.split_once('.').is_some_and(|(g, _)| g == group)),
+        None => TOPICS.iter().any(|(topic, _)| *topic == filter),
+    }
+}
+
+/// Whether `topic` matches any of `filters`
+pub fn matches(filters: &[String], topic: &str) -> bool {
+    filters.iter().any(|filter| match filter.strip_suffix('*') {
+        Some(prefix) => topic.starts_with(prefix),
+        None => filter == topic,
+    })
+}
+
+/// Queue an event for delivery. Returns at once; events published before the worker starts are
+/// dropped.
+pub fn publish(app: &AppHandle, event: BusEvent) {
+    let bus = app.state::<EventBus>();
+    let Ok(sender) = bus.sender.lock() else {
+        return;
+    };
+    if let Some(sender) = sender.as_ref() {
+        let _ = sender.send(event);
+    }
+}
+
+/// A document path relative to the open workspace, or `None` for documents outside it or in a
+/// vault
+fn relative(app: &AppHandle, path: &Path) -> Option<String> {
+    let root = workspace::current_root(app)?;
+    if !path.starts_with(&root) || vault::vault_of(path).is_some() {
+        return None;
+    }
+    Some(workspace::relative_path(&root, path))
+}
+
+/// Publish a `document.*` event for a file change reported through [`crate::events`]
+pub fn document_changed(app: &AppHandle, path: &Path, kind: ChangeKind, from: Option<&Path>) {
+    let Some(path) = relative(app, path) else {
+        return;
+    };
+    let event = match kind {
+        ChangeKind::Created => BusEvent::DocumentCreated { path },
+        ChangeKind::Modified => BusEvent::DocumentModified { path },
+        ChangeKind::Renamed => BusEvent::DocumentRenamed {
+            path,
+            from: from.and_then(|from| relative(app, from)),
+        },
+        ChangeKind::Deleted => BusEvent::DocumentDeleted { path },
+    };
+    publish(app, event);
+}
+
+pub fn document_saved(app: &AppHandle, path: &Path) {
+    if let Some(path) = relative(app, path) {
+        publish(app, BusEvent::DocumentSaved { path });
+    }
+}
+
+pub fn document_exported(app: &AppHandle, path: &Path, output: &Path) {
+    if let Some(path) = relative(app, path) {
+        let output = output.to_string_lossy().to_string();
+        publish(app, BusEvent::DocumentExported { path, output });
+    }
+}
+
+fn deliver(app: &AppHandle, envelope: &Envelope) {
+    let topic = envelope.event.topic();
+    if let Ok(subscriptions) = app.state::<EventBus>().subscriptions.lock() {
+        for (id, subscription) in subscriptions.iter() {
+            if matches(&subscription.filters, topic) {
+                let delivery = Delivery {
+                    subscription: id.clone(),
+                    event: envelope.clone(),
+                };
+                let _ = app.emit_to(subscription.window.as_str(), DELIVERY_EVENT, delivery);
+            }
+        }
+    }
+    #[cfg(desktop)]
+    crate::plugins::deliver_event(app, topic, envelope);
+}
+
+/// Start the worker that numbers events and delivers them, and that reports workspace switches
+pub fn start(app: AppHandle) {
+    let (sender, receiver) = mpsc::channel();
+    if let Ok(mut slot) = app.state::<EventBus>().sender.lock() {
+        *slot = Some(sender);
+    }
+
+    std::thread::spawn(move || {
+        let mut seq = 0;
+        let mut root: Option<PathBuf> = workspace::current_root(&app);
+        loop {
+            let received = match receiver.recv_timeout(WORKSPACE_POLL) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+            // Reported before anything that happened in the newly opened workspace
+            let mut events = Vec::new();
+            let current = workspace::current_root(&app);
+            if current != root {
+                if let Some(old) = &root {
+                    let root = old.to_string_lossy().to_string();
+                    events.push(BusEvent::WorkspaceClosed { root });
+                }
+                if let Some(new) = &current {
+                    let root = new.to_string_lossy().to_string();
+                    events.push(BusEvent::WorkspaceOpened { root });
+                }
+                root = current;
+            }
+            events.extend(received);
+            for event in events {
+                seq += 1;
+                deliver(
+                    &app,
+                    &Envelope {
+                        version: VERSION,
+                        seq,
+                        timestamp: document::now_millis(),
+                        event,
+                    },
+                );
+            }
+        }
+    });
+}
+
+/// Drop the subscriptions of a closed window
+pub fn window_closed(app: &AppHandle, label: &str) {
+    if let Ok(mut subscriptions) = app.state::<EventBus>().subscriptions.lock() {
+        subscriptions.retain(|_, subscription| subscription.window != label);
+    }
+}
+
+/// Every event topic with a description, and the event version
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_event_topics() -> Result<EventTopics, Error> {
+    let topics = TOPICS
+        .iter()
+        .map(|(topic, description)| EventTopic {
+            topic: topic.to_string(),
+            description: description.to_string(),
+        })
+        .collect();
+    Ok(EventTopics {
+        version: VERSION,
+        topics,
+    })
+}
+
+/// Deliver events whose topic matches one of `filters` (`*`, a topic, or a group such as
+/// `document.*`) to the calling window as `event-bus:event`; returns the subscription id
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn subscribe_events(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    filters: Vec<String>,
+) -> Result<String, Error> {
+    if filters.is_empty() {
+        return Err("Invalid event filter: at least one filter is required".into());
+    }
+    if let Some(filter) = filters.iter().find(|filter| !valid_filter(filter)) {
+        return Err(format!("Invalid event filter: {}", filter).into());
+    }
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    app.state::<EventBus>()
+        .subscriptions
+        .lock()
+        .map_err(|e| format!("Failed to subscribe: {}", e))?
+        .insert(
+            id.clone(),
+            Subscription {
+                window: window.label().to_string(),
+                filters,
+            },
+        );
+    Ok(id)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn unsubscribe_events(app: AppHandle, id: String) -> Result<bool, Error> {
+    Ok(app
+        .state::<EventBus>()
+        .subscriptions
+        .lock()
+        .map_err(|e| format!("Failed to unsubscribe: {}", e))?
+        .remove(&id)
+        .is_some())
+}
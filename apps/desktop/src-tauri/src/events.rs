@@ -0,0 +1,93 @@
+use crate::dir_cache::DirectoryCache;
+use crate::{bridge, catalog, event_bus, webhooks};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Emitted to every window when the backend creates, changes, moves, or deletes a file
+pub const FILE_CHANGED: &str = "workspace:file-changed";
+/// Emitted to every window when extracted text is added to the search index
+pub const INDEX_UPDATED: &str = "workspace:index-updated";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Renamed,
+    Deleted,
+}
+
+impl ChangeKind {
+    /// The kind as it is spelled in events, such as `modified`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Renamed => "renamed",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FileChange {
+    path: String,
+    kind: ChangeKind,
+    /// Previous path of a renamed file
+    from: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct IndexUpdate {
+    path: String,
+    source: String,
+}
+
+/// Tell all windows that a file changed so any window showing it can reload
+pub fn file_changed(app: &AppHandle, path: &Path, kind: ChangeKind) {
+    app.state::<DirectoryCache>().invalidate(path);
+    catalog::file_changed(app, path);
+    webhooks::file_changed(app, path, kind, None);
+    bridge::document_event(app, path, kind, None);
+    event_bus::document_changed(app, path, kind, None);
+    let _ = app.emit(
+        FILE_CHANGED,
+        FileChange {
+            path: path.to_string_lossy().to_string(),
+            kind,
+            from: None,
+        },
+    );
+}
+
+pub fn file_renamed(app: &AppHandle, from: &Path, to: &Path) {
+    let cache = app.state::<DirectoryCache>();
+    cache.invalidate(from);
+    cache.invalidate(to);
+    catalog::file_changed(app, from);
+    catalog::file_changed(app, to);
+    webhooks::file_changed(app, to, ChangeKind::Renamed, Some(from));
+    bridge::document_event(app, to, ChangeKind::Renamed, Some(from));
+    event_bus::document_changed(app, to, ChangeKind::Renamed, Some(from));
+    let _ = app.emit(
+        FILE_CHANGED,
+        FileChange {
+            path: to.to_string_lossy().to_string(),
+            kind: ChangeKind::Renamed,
+            from: Some(from.to_string_lossy().to_string()),
+        },
+    );
+}
+
+/// Tell all windows that search results for a file may have changed
+pub fn index_updated(app: &AppHandle, path: &Path, source: &str) {
+    let _ = app.emit(
+        INDEX_UPDATED,
+        IndexUpdate {
+            path: path.to_string_lossy().to_string(),
+            source: source.to_string(),
+        },
+    );
+}
@@ -0,0 +1,388 @@
+use crate::blocking;
+use crate::cancel::{self, CancelToken};
+use crate::conditions;
+use crate::document;
+use crate::error::Error;
+use crate::event_bus;
+use crate::hooks;
+use crate::messages;
+use crate::pandoc;
+use crate::paths;
+use crate::power;
+use crate::workspace;
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+use inkfinite_core::export;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+const RULES_CONFIG: &str = "export-rules";
+const LOG_NAME: &str = "exports";
+const TICK: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Hourly,
+    Daily,
+    Weekly,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRule {
+    pub id: String,
+    pub name: String,
+    /// Folder to export, relative to the workspace root (empty for the whole workspace)
+    #[serde(default)]
+    pub source: String,
+    /// Destination folder; a leading `~` is expanded to the home directory
+    pub destination: String,
+    /// `markdown`, `json`, `plugin:<plugin>/<format>`, or any pandoc output format
+    pub format: String,
+    pub frequency: Frequency,
+    /// Local time of day (`HH:MM`) for daily and weekly rules
+    #[serde(default)]
+    pub time: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub last_run: Option<i64>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRunReport {
+    pub rule_id: String,
+    pub exported: usize,
+    pub failed: Vec<ExportFailure>,
+}
+
+#[derive(serde::Serialize, Clone)]
+pub struct ExportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+fn load_rules(root: &Path) -> Result<Vec<ExportRule>, String> {
+    workspace::read_config(root, RULES_CONFIG)
+}
+
+fn save_rules(root: &Path, rules: &[ExportRule]) -> Result<(), String> {
+    workspace::write_config(root, RULES_CONFIG, &rules)
+}
+
+/// List scheduled export rules for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_export_rules(app: AppHandle, workspace: String) -> Result<Vec<ExportRule>, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(load_rules(Path::new(&workspace))?)
+}
+
+/// Create or replace a scheduled export rule
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_export_rule(
+    app: AppHandle,
+    workspace: String,
+    mut rule: ExportRule,
+) -> Result<ExportRule, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    if let Some(time) = &rule.time {
+        NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| format!("Invalid time of day: {}", time))?;
+    }
+    if rule.id.is_empty() {
+        rule.id = document::create_id("export");
+    }
+
+    let root = Path::new(&workspace);
+    let mut rules = load_rules(root)?;
+    match rules.iter_mut().find(|r| r.id == rule.id) {
+        Some(existing) => *existing = rule.clone(),
+        None => rules.push(rule.clone()),
+    }
+    save_rules(root, &rules)?;
+
+    Ok(rule)
+}
+
+/// Remove a scheduled export rule
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_export_rule(app: AppHandle, workspace: String, id: String) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let root = Path::new(&workspace);
+    let mut rules = load_rules(root)?;
+    let before = rules.len();
+    rules.retain(|r| r.id != id);
+    if rules.len() == before {
+        return Err(format!("Export rule does not exist: {}", id).into());
+    }
+    Ok(save_rules(root, &rules)?)
+}
+
+/// Run an export rule immediately, regardless of its schedule
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn run_export_rule(
+    app: AppHandle,
+    workspace: String,
+    id: String,
+    op_id: Option<String>,
+) -> Result<ExportRunReport, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let operation = cancel::begin(&app, op_id);
+    blocking::run_long("run export rule", move || {
+        run_rule(&app, Path::new(&workspace), &id, operation.token())
+    })
+    .await
+}
+
+/// Run the rule `id` now, stopping between documents once `cancel` is set
+pub fn run_rule(
+    app: &AppHandle,
+    root: &Path,
+    id: &str,
+    cancel: &CancelToken,
+) -> Result<ExportRunReport, String> {
+    let rules = load_rules(root)?;
+    let rule = rules
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| format!("Export rule does not exist: {}", id))?;
+
+    let report = execute_and_record(app, root, &rule, cancel);
+    cancel.check()?;
+    Ok(report)
+}
+
+/// Start the background scheduler that runs due export rules for the current workspace
+pub fn start_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK);
+        if let Some(root) = workspace::current_root(&app) {
+            // Due rules stay due, so a deferred run happens on the first tick conditions allow
+            if conditions::defer_reason(&root, false).is_none() {
+                run_due_rules(&app, &root);
+            }
+        }
+    });
+}
+
+fn run_due_rules(app: &AppHandle, root: &Path) {
+    let Ok(rules) = load_rules(root) else {
+        return;
+    };
+    let now = Local::now();
+
+    for rule in rules.iter().filter(|r| r.enabled && is_due(r, now)) {
+        execute_and_record(app, root, rule, &CancelToken::default());
+    }
+}
+
+/// Whether the most recent scheduled slot for a rule has passed without a run
+fn is_due(rule: &ExportRule, now: DateTime<Local>) -> bool {
+    let last_run = rule
+        .last_run
+        .and_then(|millis| Local.timestamp_millis_opt(millis).single());
+
+    if rule.frequency == Frequency::Hourly {
+        return last_run.is_none_or(|last| now - last >= Duration::hours(1));
+    }
+
+    let time = rule
+        .time
+        .as_deref()
+        .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())
+        .unwrap_or(NaiveTime::MIN);
+    let Some(mut slot) = now
+        .date_naive()
+        .and_time(time)
+        .and_local_timezone(Local)
+        .earliest()
+    else {
+        return false;
+    };
+    if slot > now {
+        slot -= Duration::days(1);
+    }
+
+    let period = match rule.frequency {
+        Frequency::Weekly => Duration::days(7),
+        _ => Duration::days(1),
+    };
+
+    match last_run {
+        None => true,
+        Some(last) => last < slot && now - last >= period - Duration::hours(1),
+    }
+}
+
+fn execute_and_record(
+    app: &AppHandle,
+    root: &Path,
+    rule: &ExportRule,
+    cancel: &CancelToken,
+) -> ExportRunReport {
+    let _awake = power::prevent_sleep("Exporting documents");
+    let result = execute_rule(app, root, rule, cancel);
+
+    let (report, error) = match result {
+        Ok(report) if report.failed.is_empty() => (report, None),
+        Ok(report) => {
+            let error = format!("{} document(s) failed to export", report.failed.len());
+            (report, Some(error))
+        }
+        Err(error) => (
+            ExportRunReport {
+                rule_id: rule.id.clone(),
+                exported: 0,
+                failed: Vec::new(),
+            },
+            Some(error),
+        ),
+    };
+
+    workspace::append_log(
+        root,
+        LOG_NAME,
+        &format!(
+            "rule={} exported={} failed={}{}",
+            rule.name,
+            report.exported,
+            report.failed.len(),
+            error
+                .as_ref()
+                .map(|e| format!(" error={}", e))
+                .unwrap_or_default()
+        ),
+    );
+    for failure in &report.failed {
+        workspace::append_log(
+            root,
+            LOG_NAME,
+            &format!("  {}: {}", failure.path, failure.error),
+        );
+    }
+
+    if let Ok(mut rules) = load_rules(root) {
+        if let Some(stored) = rules.iter_mut().find(|r| r.id == rule.id) {
+            stored.last_run = Some(document::now_millis());
+            stored.last_error = error.clone();
+            let _ = save_rules(root, &rules);
+        }
+    }
+
+    match &error {
+        // Whoever cancelled already knows; there is nothing to notify about
+        Some(_) if cancel.is_cancelled() => {
+            let _ = app.emit("export:failed", &report);
+        }
+        Some(message) => {
+            let _ = app.emit("export:failed", &report);
+            let _ = app
+                .notification()
+                .builder()
+                .title(messages::format(
+                    "export-failed-title",
+                    &[("name", rule.name.as_str().into())],
+                ))
+                .body(message)
+                .show();
+        }
+        None => {
+            let _ = app.emit("export:completed", &report);
+        }
+    }
+
+    report
+}
+
+fn execute_rule(
+    app: &AppHandle,
+    root: &Path,
+    rule: &ExportRule,
+    cancel: &CancelToken,
+) -> Result<ExportRunReport, String> {
+    let source = root.join(&rule.source);
+    if !source.is_dir() {
+        return Err(format!("Directory does not exist: {}", source.display()));
+    }
+    let destination = expand_home(app, &rule.destination);
+    fs::create_dir_all(&destination).map_err(|e| format!("Failed to create destination: {}", e))?;
+
+    let mut report = ExportRunReport {
+        rule_id: rule.id.clone(),
+        exported: 0,
+        failed: Vec::new(),
+    };
+
+    for path in workspace::list_documents(&source)? {
+        cancel.check()?;
+        match export_document(app, &source, &path, &destination, &rule.format, cancel) {
+            Ok(_) => report.exported += 1,
+            Err(error) => report.failed.push(ExportFailure {
+                path: workspace::relative_path(root, &path),
+                error,
+            }),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Export one document below `destination`, mirroring its folder under `source_root`; returns
+/// the file written. Besides the formats [`export::export_document`] writes, desktop builds
+/// export with plugins through `plugin:<plugin>/<format>`.
+pub fn export_document(
+    app: &AppHandle,
+    source_root: &Path,
+    path: &Path,
+    destination: &Path,
+    format: &str,
+    cancel: &CancelToken,
+) -> Result<PathBuf, String> {
+    #[cfg(desktop)]
+    if let Some(plugin_format) = format.strip_prefix("plugin:") {
+        let target_dir = export::target_dir(source_root, path, destination)?;
+        let board = document::read_board(path)?;
+        let (bytes, extension) =
+            crate::plugins::export(app, plugin_format, &board.board.name, &board.to_markdown())?;
+        let output = target_dir.join(format!("{}.{}", document::document_stem(path), extension));
+        fs::write(&output, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+        hooks::document_exported(app, path, &output);
+        event_bus::document_exported(app, path, &output);
+        return Ok(output);
+    }
+    let output = export::export_document(
+        source_root,
+        path,
+        destination,
+        format,
+        || pandoc::require_pandoc(app),
+        cancel,
+    )?;
+    hooks::document_exported(app, path, &output);
+    event_bus::document_exported(app, path, &output);
+    Ok(output)
+}
+
+fn expand_home(app: &AppHandle, path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) => app
+            .path()
+            .home_dir()
+            .map(|home| home.join(rest.trim_start_matches(['/', '\\'])))
+            .unwrap_or_else(|_| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
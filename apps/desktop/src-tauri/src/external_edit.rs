@@ -0,0 +1,356 @@
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{paths, tools, workspace};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+const EDITOR_KEY: &str = "externalEditor";
+const POLL: Duration = Duration::from_millis(500);
+/// Editors exiting this soon handed the file to an existing window rather than waiting on it
+const HANDOFF: Duration = Duration::from_secs(3);
+/// Marker line preceding each block in the Markdown export, carrying its shape id
+const BLOCK_MARKER: &str = "<!-- inkfinite:block ";
+
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EditFormat {
+    /// Text blocks only, each introduced by a marker comment so edits map back to its block
+    #[default]
+    Markdown,
+    /// The full document file
+    Json,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalEdit {
+    pub id: String,
+    pub path: String,
+    pub temp_path: String,
+}
+
+/// Document and stop flag of each running external edit session
+#[derive(Default)]
+pub struct ExternalEdits(Mutex<HashMap<String, (PathBuf, Arc<AtomicBool>)>>);
+
+/// Editor command line for [`edit_externally`], if one has been configured
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_external_editor(app: AppHandle) -> Option<String> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(EDITOR_KEY))
+        .and_then(|value| value.as_str().map(str::to_string))
+        .filter(|command| !command.trim().is_empty())
+}
+
+/// Set the editor command, such as `code --wait`; `None` uses the system's default editor
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_external_editor(app: AppHandle, command: Option<String>) -> Result<(), Error> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    match command.filter(|command| !command.trim().is_empty()) {
+        Some(command) => store.set(EDITOR_KEY, command),
+        None => {
+            store.delete(EDITOR_KEY);
+        }
+    }
+    Ok(store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?)
+}
+
+/// Export a document to a temp file, open it in the external editor, and import every save back.
+///
+/// Syncs are reported as `external-edit:synced` or `external-edit:failed`. The session ends
+/// when an editor that waits (`code --wait`, a terminal editor) exits, or on
+/// [`finish_external_edit`]; it then emits `external-edit:finished`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn edit_externally(
+    app: AppHandle,
+    sessions: State<'_, ExternalEdits>,
+    path: String,
+    format: Option<EditFormat>,
+) -> Result<ExternalEdit, Error> {
+    paths::check(&app, &path, paths::Scope::Write)?;
+    let format = format.unwrap_or_default();
+    let document_path = PathBuf::from(&path);
+    let board = document::read_board(&document_path)?;
+
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let dir = tools::scratch_dir("edit")?;
+    let (name, content) = match format {
+        EditFormat::Markdown => (
+            format!("{}.md", document::document_stem(&document_path)),
+            export_markdown(&board),
+        ),
+        EditFormat::Json => (
+            document_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "document.inkfinite.json".to_string()),
+            serde_json::to_string_pretty(&board)
+                .map_err(|e| format!("Failed to serialize document: {}", e))?,
+        ),
+    };
+    let temp_path = dir.join(name);
+    std::fs::write(&temp_path, content).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    let child = launch(&app, &temp_path).inspect_err(|_| {
+        let _ = std::fs::remove_dir_all(&dir);
+    })?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    if let Ok(mut running) = sessions.0.lock() {
+        running.insert(id.clone(), (document_path.clone(), stop.clone()));
+    }
+
+    let session = ExternalEdit {
+        id,
+        path,
+        temp_path: temp_path.to_string_lossy().to_string(),
+    };
+    let watched = session.clone();
+    std::thread::spawn(move || watch(app, watched, format, child, stop, dir));
+    Ok(session)
+}
+
+/// End an external edit session after a final sync
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn finish_external_edit(sessions: State<'_, ExternalEdits>, id: String) -> Result<(), Error> {
+    let stop = sessions
+        .0
+        .lock()
+        .ok()
+        .and_then(|mut running| running.remove(&id))
+        .ok_or_else(|| format!("External edit does not exist: {}", id))?;
+    stop.1.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// End the external edit sessions of documents inside `root` after a final sync
+pub fn stop_under(app: &AppHandle, root: &Path) {
+    if let Ok(mut running) = app.state::<ExternalEdits>().0.lock() {
+        running.retain(|_, (path, stop)| {
+            let inside = path.starts_with(root);
+            if inside {
+                stop.store(true, Ordering::Relaxed);
+            }
+            !inside
+        });
+    }
+}
+
+fn launch(app: &AppHandle, file: &Path) -> Result<Option<std::process::Child>, String> {
+    if let Some(command) = get_external_editor(app.clone()) {
+        let mut parts = split_command(&command).into_iter();
+        let program = parts
+            .next()
+            .ok_or_else(|| "External editor command is empty".to_string())?;
+        return Command::new(&program)
+            .args(parts)
+            .arg(file)
+            .spawn()
+            .map(Some)
+            .map_err(|e| format!("Failed to launch {}: {}", program, e));
+    }
+
+    #[cfg(target_os = "macos")]
+    let default = Command::new("open").args(["-W", "-t"]).arg(file).spawn();
+    #[cfg(windows)]
+    let default = Command::new("notepad").arg(file).spawn();
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let default = Command::new("xdg-open").arg(file).spawn();
+
+    default
+        .map(Some)
+        .map_err(|e| format!("Failed to open editor: {}", e))
+}
+
+fn watch(
+    app: AppHandle,
+    session: ExternalEdit,
+    format: EditFormat,
+    mut child: Option<std::process::Child>,
+    stop: Arc<AtomicBool>,
+    dir: PathBuf,
+) {
+    let temp_path = PathBuf::from(&session.temp_path);
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last = modified(&temp_path);
+    let launched = Instant::now();
+
+    loop {
+        let mut finished = stop.load(Ordering::Relaxed) || !temp_path.exists();
+        if let Some(process) = child.as_mut() {
+            if !matches!(process.try_wait(), Ok(None)) {
+                // Keep watching after a handoff until the session is finished explicitly
+                if launched.elapsed() < HANDOFF {
+                    child = None;
+                } else {
+                    finished = true;
+                }
+            }
+        }
+
+        let current = modified(&temp_path);
+        if current.is_some() && current != last {
+            last = current;
+            sync(&app, &session, format);
+        }
+        if finished {
+            break;
+        }
+        std::thread::sleep(POLL);
+    }
+
+    if let Ok(mut running) = app.state::<ExternalEdits>().0.lock() {
+        running.remove(&session.id);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = app.emit("external-edit:finished", &session);
+}
+
+fn sync(app: &AppHandle, session: &ExternalEdit, format: EditFormat) {
+    let path = Path::new(&session.path);
+    let result = std::fs::read_to_string(&session.temp_path)
+        .map_err(|e| format!("Failed to read file: {}", e))
+        .and_then(|content| match format {
+            EditFormat::Markdown => {
+                let mut board = document::read_board(path)?;
+                import_markdown(&mut board, &content);
+                Ok(board)
+            }
+            EditFormat::Json => serde_json::from_str::<BoardFile>(&content)
+                .map_err(|e| format!("Invalid file format: {}", e)),
+        })
+        .and_then(|mut board| {
+            board.board.updated_at = document::now_millis();
+            document::write_board(path, &board)
+        });
+
+    match result {
+        Ok(()) => {
+            events::file_changed(app, path, ChangeKind::Modified);
+            let _ = app.emit("external-edit:synced", session);
+        }
+        Err(error) => {
+            let _ = app.emit(
+                "external-edit:failed",
+                serde_json::json!({ "id": session.id, "path": session.path, "error": error }),
+            );
+        }
+    }
+}
+
+fn export_markdown(board: &BoardFile) -> String {
+    let mut out = format!("# {}\n", board.board.name);
+    for page in board.pages() {
+        for shape in board.page_shapes(&page) {
+            if let Some(text) = shape.text() {
+                out.push_str(&format!(
+                    "\n{}{} -->\n{}\n",
+                    BLOCK_MARKER,
+                    shape.id,
+                    text.trim_end()
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Apply an edited Markdown export: the heading renames the board, marked sections replace
+/// their block's text, blocks whose marker was deleted are removed, and text before the first
+/// marker becomes a new block
+fn import_markdown(board: &mut BoardFile, content: &str) {
+    let exported: Vec<String> = board
+        .pages()
+        .iter()
+        .flat_map(|page| board.page_shapes(page))
+        .filter(|shape| shape.text().is_some())
+        .map(|shape| shape.id)
+        .collect();
+
+    let mut preamble = String::new();
+    let mut sections: Vec<(String, String)> = Vec::new();
+    let mut renamed = false;
+    for line in content.lines() {
+        if let Some(id) = line
+            .trim()
+            .strip_prefix(BLOCK_MARKER)
+            .and_then(|rest| rest.strip_suffix("-->"))
+        {
+            sections.push((id.trim().to_string(), String::new()));
+            continue;
+        }
+        match sections.last_mut() {
+            Some((_, text)) => {
+                text.push_str(line);
+                text.push('\n');
+            }
+            None if !renamed && line.starts_with("# ") => {
+                let name = line[2..].trim();
+                if !name.is_empty() {
+                    board.board.name = name.to_string();
+                }
+                renamed = true;
+            }
+            None => {
+                preamble.push_str(line);
+                preamble.push('\n');
+            }
+        }
+    }
+
+    for (id, text) in &sections {
+        let text = text.trim();
+        if text.is_empty() {
+            board.remove_shape(id);
+        } else {
+            board.set_text(id, text);
+        }
+    }
+    for id in exported
+        .iter()
+        .filter(|id| !sections.iter().any(|(section, _)| section == *id))
+    {
+        board.remove_shape(id);
+    }
+    if !preamble.trim().is_empty() {
+        board.push_markdown(preamble.trim());
+    }
+}
+
+/// Split a command line on whitespace, keeping double-quoted arguments together
+fn split_command(command: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in command.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
@@ -0,0 +1,1117 @@
+use crate::error::Error;
+use crate::{svg, tools};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Rendered figures, in the app cache folder by content hash
+const FIGURES_DIR: &str = "figures";
+/// Longest a renderer may take before it is stopped
+const RENDER_TIMEOUT: Duration = Duration::from_secs(30);
+const RENDER_POLL: Duration = Duration::from_millis(20);
+/// Fenced code block languages rendered as diagrams in exports
+const DIAGRAM_FENCES: &[(&str, DiagramKind)] = &[
+    ("dot", DiagramKind::Graphviz),
+    ("graphviz", DiagramKind::Graphviz),
+    ("mermaid", DiagramKind::Mermaid),
+];
+
+/// LaTeX commands whose Typst name differs; others keep their name, as Typst shares most of
+/// them (`alpha`, `sum`, `sin`, ...)
+const SYMBOLS: &[(&str, &str)] = &[
+    ("epsilon", "epsilon.alt"),
+    ("varepsilon", "epsilon"),
+    ("phi", "phi.alt"),
+    ("varphi", "phi"),
+    ("vartheta", "theta.alt"),
+    ("varpi", "pi.alt"),
+    ("varrho", "rho.alt"),
+    ("varsigma", "sigma.alt"),
+    ("cdot", "dot.op"),
+    ("cdotp", "dot.op"),
+    ("times", "times"),
+    ("div", "div"),
+    ("pm", "plus.minus"),
+    ("mp", "minus.plus"),
+    ("le", "<="),
+    ("leq", "<="),
+    ("ge", ">="),
+    ("geq", ">="),
+    ("ne", "!="),
+    ("neq", "!="),
+    ("ll", "<<"),
+    ("gg", ">>"),
+    ("sim", "tilde.op"),
+    ("simeq", "tilde.eq"),
+    ("cong", "tilde.equiv"),
+    ("propto", "prop"),
+    ("infty", "infinity"),
+    ("partial", "diff"),
+    ("prod", "product"),
+    ("coprod", "product.co"),
+    ("int", "integral"),
+    ("iint", "integral.double"),
+    ("iiint", "integral.triple"),
+    ("oint", "integral.cont"),
+    ("to", "->"),
+    ("rightarrow", "->"),
+    ("longrightarrow", "-->"),
+    ("leftarrow", "<-"),
+    ("gets", "<-"),
+    ("leftrightarrow", "<->"),
+    ("Rightarrow", "=>"),
+    ("implies", "==>"),
+    ("Leftarrow", "arrow.l.double"),
+    ("Leftrightarrow", "<=>"),
+    ("iff", "<==>"),
+    ("mapsto", "|->"),
+    ("uparrow", "arrow.t"),
+    ("downarrow", "arrow.b"),
+    ("notin", "in.not"),
+    ("ni", "in.rev"),
+    ("subseteq", "subset.eq"),
+    ("supseteq", "supset.eq"),
+    ("subsetneq", "subset.neq"),
+    ("cup", "union"),
+    ("bigcup", "union.big"),
+    ("cap", "sect"),
+    ("bigcap", "sect.big"),
+    ("setminus", "without"),
+    ("varnothing", "emptyset"),
+    ("neg", "not"),
+    ("lnot", "not"),
+    ("land", "and"),
+    ("wedge", "and"),
+    ("lor", "or"),
+    ("vee", "or"),
+    ("oplus", "plus.circle"),
+    ("otimes", "times.circle"),
+    ("circ", "compose"),
+    ("bullet", "bullet"),
+    ("star", "star.op"),
+    ("ast", "ast.op"),
+    ("ldots", "dots.h"),
+    ("dots", "dots.h"),
+    ("cdots", "dots.c"),
+    ("vdots", "dots.v"),
+    ("ddots", "dots.down"),
+    ("langle", "angle.l"),
+    ("rangle", "angle.r"),
+    ("lceil", "ceil.l"),
+    ("rceil", "ceil.r"),
+    ("lfloor", "floor.l"),
+    ("rfloor", "floor.r"),
+    ("mid", "divides"),
+    ("parallel", "parallel"),
+    ("perp", "perp"),
+    ("angle", "angle"),
+    ("degree", "degree"),
+    ("prime", "prime"),
+    ("hbar", "planck.reduce"),
+    ("ell", "ell"),
+    ("Re", "Re"),
+    ("Im", "Im"),
+    ("aleph", "aleph"),
+    ("triangle", "triangle.t"),
+    ("square", "square"),
+    ("checkmark", "checkmark"),
+    ("lVert", "||"),
+    ("rVert", "||"),
+    ("Vert", "||"),
+    ("|", "||"),
+    ("lvert", "|"),
+    ("rvert", "|"),
+    ("vert", "|"),
+    ("quad", "quad"),
+    ("qquad", "wide"),
+    (",", "thin"),
+    (":", "med"),
+    (">", "med"),
+    (";", "thick"),
+    ("!", ""),
+    (" ", "space"),
+    ("displaystyle", ""),
+    ("textstyle", ""),
+    ("limits", ""),
+    ("nolimits", ""),
+    ("\\", "\\"),
+    ("{", "\\{"),
+    ("}", "\\}"),
+    ("#", "\\#"),
+    ("$", "\\$"),
+    ("%", "%"),
+    ("&", "\\&"),
+    ("_", "\\_"),
+];
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagramKind {
+    #[serde(alias = "dot")]
+    Graphviz,
+    /// Flowcharts are laid out by Graphviz; other Mermaid diagrams need the Mermaid CLI
+    Mermaid,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum MathSyntax {
+    #[default]
+    Latex,
+    Typst,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Figure {
+    pub svg: String,
+    /// Size in points, when the renderer set one
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    /// The cached SVG file, for exports that link images by path
+    pub path: String,
+    /// Whether it came from the cache rather than a fresh render
+    pub cached: bool,
+}
+
+fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(FIGURES_DIR))
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))
+}
+
+fn cache_key(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Width and height of the root `svg` element in points
+fn dimensions(svg: &str) -> (Option<f64>, Option<f64>) {
+    let Some(start) = svg.find("<svg") else {
+        return (None, None);
+    };
+    let tag = &svg[start..svg[start..].find('>').map_or(svg.len(), |end| start + end)];
+    let attribute = |name: &str| {
+        let at = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+        let value = &tag[at..at + tag[at..].find('"')?];
+        let number = value.trim_end_matches(|c: char| c.is_alphabetic());
+        let number: f64 = number.parse().ok()?;
+        // Graphviz and Typst write points; CSS pixels are 0.75 points
+        Some(match value.ends_with("px") {
+            true => number * 0.75,
+            false => number,
+        })
+    };
+    (attribute("width"), attribute("height"))
+}
+
+/// A rendered figure from the cache, or rendered now, cleaned and cached
+fn cached(
+    app: &AppHandle,
+    key: String,
+    render: impl FnOnce() -> Result<String, String>,
+) -> Result<Figure, String> {
+    let dir = cache_dir(app)?;
+    let path = dir.join(format!("{}.svg", key));
+    let (svg, cached) = match fs::read_to_string(&path) {
+        Ok(svg) => (svg, true),
+        Err(_) => {
+            // Renderers may embed images from input; cleaning drops anything remote or active
+            let (svg, _) = svg::sanitize(&render()?)?;
+            fs::create_dir_all(&dir).map_err(|e| format!("Failed to create cache: {}", e))?;
+            fs::write(&path, &svg).map_err(|e| format!("Failed to write figure: {}", e))?;
+            (svg, false)
+        }
+    };
+    let (width, height) = dimensions(&svg);
+    Ok(Figure {
+        svg,
+        width,
+        height,
+        path: path.to_string_lossy().to_string(),
+        cached,
+    })
+}
+
+fn drain(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut out = Vec::new();
+        let _ = pipe.read_to_end(&mut out);
+        out
+    })
+}
+
+/// Run a renderer with `input` on stdin and return its stdout, stopping it after the timeout
+fn run(name: &str, binary: &Path, args: &[&str], input: Option<&str>) -> Result<String, String> {
+    let mut child = Command::new(binary)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", name, e))?;
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Some(input) = input {
+            stdin
+                .write_all(input.as_bytes())
+                .map_err(|e| format!("Failed to send input to {}: {}", name, e))?;
+        }
+    }
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("Failed to run {}: {}", name, e))?
+        {
+            break status;
+        }
+        if started.elapsed() > RENDER_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Rendering timed out: {} took too long", name));
+        }
+        std::thread::sleep(RENDER_POLL);
+    };
+    let output = stdout.and_then(|t| t.join().ok()).unwrap_or_default();
+    let errors = stderr.and_then(|t| t.join().ok()).unwrap_or_default();
+    if !status.success() {
+        let errors = String::from_utf8_lossy(&errors);
+        let message = errors.lines().take(4).collect::<Vec<_>>().join(" ");
+        return Err(format!(
+            "Invalid figure: {} failed: {}",
+            name,
+            message.trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output).to_string())
+}
+
+/// Translates LaTeX math to Typst math, covering the commands, environments and scripts used
+/// in notes; unknown commands keep their name and fail in Typst if it has no such symbol
+struct Latex {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Latex {
+    fn new(source: &str) -> Latex {
+        Latex {
+            chars: source.chars().collect(),
+            at: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    /// A command name after `\`: a run of letters, or one other character
+    fn name(&mut self) -> String {
+        let start = self.at;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.at += 1;
+        }
+        if self.at == start && self.peek().is_some() {
+            self.at += 1;
+        }
+        self.chars[start..self.at].iter().collect()
+    }
+
+    /// The text inside the next `{...}` as written, or the next character
+    fn raw_argument(&mut self) -> String {
+        self.skip_space();
+        if self.peek() != Some('{') {
+            return self
+                .peek()
+                .map(|c| {
+                    self.at += 1;
+                    c.to_string()
+                })
+                .unwrap_or_default();
+        }
+        self.at += 1;
+        let start = self.at;
+        let mut depth = 1;
+        while let Some(c) = self.peek() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            self.at += 1;
+        }
+        let raw = self.chars[start..self.at].iter().collect();
+        self.at += 1;
+        raw
+    }
+
+    /// The next argument translated, with commas and semicolons escaped so it can be passed to
+    /// a Typst function
+    fn argument(&mut self) -> String {
+        self.skip_space();
+        let tokens = match self.peek() {
+            Some('{') => {
+                self.at += 1;
+                self.tokens(Some('}'))
+            }
+            Some('\\') => {
+                self.at += 1;
+                let name = self.name();
+                vec![self.command(&name)]
+            }
+            Some(c) => {
+                self.at += 1;
+                vec![char_token(c)]
+            }
+            None => Vec::new(),
+        };
+        escape_arguments(tokens)
+    }
+
+    fn tokens(&mut self, until: Option<char>) -> Vec<String> {
+        let mut tokens: Vec<String> = Vec::new();
+        while let Some(c) = self.peek() {
+            self.at += 1;
+            match c {
+                _ if Some(c) == until => break,
+                '}' => break,
+                '{' => tokens.extend(self.tokens(Some('}'))),
+                '\\' => {
+                    let name = self.name();
+                    let token = self.command(&name);
+                    if !token.is_empty() {
+                        tokens.push(token);
+                    }
+                }
+                '^' | '_' | '\'' => {
+                    let attached = match c {
+                        '\'' => "'".to_string(),
+                        _ => format!("{}({})", c, self.argument()),
+                    };
+                    match tokens.last_mut() {
+                        Some(last) => last.push_str(&attached),
+                        None => tokens.push(format!("\"\"{}", attached)),
+                    }
+                }
+                '%' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.at += 1;
+                    }
+                }
+                c if c.is_whitespace() => {}
+                c if c.is_ascii_digit() => {
+                    let mut number = c.to_string();
+                    while let Some(next) = self.peek() {
+                        let decimal = next == '.'
+                            && self
+                                .chars
+                                .get(self.at + 1)
+                                .is_some_and(|c| c.is_ascii_digit());
+                        if !(next.is_ascii_digit() || decimal) {
+                            break;
+                        }
+                        number.push(next);
+                        self.at += 1;
+                    }
+                    tokens.push(number);
+                }
+                c => tokens.push(char_token(c)),
+            }
+        }
+        tokens
+    }
+
+    fn command(&mut self, name: &str) -> String {
+        let unary = |function: &str, this: &mut Latex| format!("{}({})", function, this.argument());
+        match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let (over, under) = (self.argument(), self.argument());
+                format!("frac({}, {})", over, under)
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let (n, k) = (self.argument(), self.argument());
+                format!("binom({}, {})", n, k)
+            }
+            "sqrt" => {
+                self.skip_space();
+                if self.peek() == Some('[') {
+                    self.at += 1;
+                    let index = escape_arguments(self.tokens(Some(']')));
+                    format!("root({}, {})", index, self.argument())
+                } else {
+                    unary("sqrt", self)
+                }
+            }
+            "text" | "textrm" | "textnormal" | "mbox" | "textit" | "textbf" | "textsf" => {
+                format!("\"{}\"", self.raw_argument().replace('"', "\\\""))
+            }
+            "operatorname" => format!("op(\"{}\")", self.raw_argument().replace('"', "\\\"")),
+            "mathrm" => unary("upright", self),
+            "mathbb" => unary("bb", self),
+            "mathbf" | "boldsymbol" | "bm" => unary("bold", self),
+            "mathit" => unary("italic", self),
+            "mathcal" | "mathscr" => unary("cal", self),
+            "mathfrak" => unary("frak", self),
+            "mathsf" => unary("sans", self),
+            "mathtt" => unary("mono", self),
+            "vec" | "overrightarrow" => unary("arrow", self),
+            "hat" | "widehat" => unary("hat", self),
+            "bar" | "overline" => unary("overline", self),
+            "underline" => unary("underline", self),
+            "tilde" | "widetilde" => unary("tilde", self),
+            "dot" => unary("dot", self),
+            "ddot" => unary("dot.double", self),
+            "overbrace" => unary("overbrace", self),
+            "underbrace" => unary("underbrace", self),
+            "cancel" => unary("cancel", self),
+            "left" | "right" | "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl"
+            | "Bigr" | "middle" => {
+                // Typst sizes matching delimiters on its own
+                self.skip_space();
+                match self.peek() {
+                    Some('.') => {
+                        self.at += 1;
+                        String::new()
+                    }
+                    Some('\\') => {
+                        self.at += 1;
+                        let name = self.name();
+                        self.command(&name)
+                    }
+                    Some(c) => {
+                        self.at += 1;
+                        char_token(c)
+                    }
+                    None => String::new(),
+                }
+            }
+            "begin" => {
+                let environment = self.raw_argument();
+                self.environment(&environment)
+            }
+            "end" => {
+                self.raw_argument();
+                String::new()
+            }
+            _ => match SYMBOLS.iter().find(|(latex, _)| *latex == name) {
+                Some((_, typst)) => typst.to_string(),
+                None => name.to_string(),
+            },
+        }
+    }
+
+    /// `\begin{...}` up to its `\end`, as a Typst matrix, cases or aligned lines
+    fn environment(&mut self, environment: &str) -> String {
+        let rest: String = self.chars[self.at..].iter().collect();
+        let end = format!("\\end{{{}}}", environment);
+        let (body, consumed) = match rest.find(&end) {
+            Some(at) => (&rest[..at], at + end.len()),
+            None => (rest.as_str(), rest.len()),
+        };
+        self.at += rest[..consumed].chars().count();
+        let mut body = body.to_string();
+        if environment == "array" {
+            // Column alignment, such as `{cc}`
+            let mut inner = Latex::new(&body);
+            inner.raw_argument();
+            body = inner.chars[inner.at..].iter().collect();
+        }
+        let rows: Vec<&str> = body
+            .split("\\\\")
+            .map(|row| row.trim().trim_start_matches("\\hline").trim())
+            .filter(|row| !row.is_empty())
+            .collect();
+        let translate = |source: &str| escape_arguments(Latex::new(source).tokens(None));
+        let grid = |rows: &[&str]| {
+            rows.iter()
+                .map(|row| {
+                    row.split('&')
+                        .map(&translate)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        let delimiter = match environment.trim_end_matches('*') {
+            "pmatrix" => Some("\"(\""),
+            "bmatrix" => Some("\"[\""),
+            "Bmatrix" => Some("\"{\""),
+            "vmatrix" => Some("\"|\""),
+            "Vmatrix" => Some("\"||\""),
+            "matrix" | "smallmatrix" | "array" => Some("#none"),
+            _ => None,
+        };
+        match (environment, delimiter) {
+            (_, Some(delimiter)) => format!("mat(delim: {}, {})", delimiter, grid(&rows)),
+            ("cases" | "dcases", _) => format!(
+                "cases({})",
+                rows.iter()
+                    .map(|row| translate(row))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            // align, aligned, gather, split, equation: lines with their `&` alignment points
+            _ => rows
+                .iter()
+                .map(|row| Latex::new(row).tokens(None).join(" "))
+                .collect::<Vec<_>>()
+                .join(" \\\n"),
+        }
+    }
+}
+
+/// One character of LaTeX math as Typst; letters stay single so `ab` is `a` times `b`
+fn char_token(c: char) -> String {
+    match c {
+        '"' | '#' | '$' | '\\' => format!("\\{}", c),
+        '~' => "space".to_string(),
+        c => c.to_string(),
+    }
+}
+
+fn escape_arguments(tokens: Vec<String>) -> String {
+    tokens
+        .into_iter()
+        .map(|token| match token.as_str() {
+            "," => "\\,".to_string(),
+            ";" => "\\;".to_string(),
+            _ => token,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// LaTeX math as Typst math
+pub fn latex_to_typst(source: &str) -> String {
+    Latex::new(source).tokens(None).join(" ")
+}
+
+fn render_typst(app: &AppHandle, math: &str, display: bool) -> Result<String, String> {
+    let typst = tools::find_binary(app, "typst", "--version").ok_or(
+        "Math rendering requires Typst: bundle it with the app or install it from typst.app",
+    )?;
+    let body = match display {
+        true => format!("$ {} $", math),
+        false => format!("${}$", math),
+    };
+    let source = format!(
+        "#set page(width: auto, height: auto, margin: 2pt, fill: none)\n#set text(size: 12pt)\n{}\n",
+        body
+    );
+    let scratch = tools::scratch_dir("math")?;
+    let input = scratch.join("figure.typ");
+    let output = scratch.join("figure.svg");
+    let result = fs::write(&input, source)
+        .map_err(|e| format!("Failed to write figure source: {}", e))
+        .and_then(|_| {
+            // The scratch folder is the root, so a Typst expression cannot read other files
+            let args = [
+                "compile".as_ref(),
+                "--root".as_ref(),
+                scratch.as_os_str(),
+                input.as_os_str(),
+                output.as_os_str(),
+            ];
+            let args: Vec<&str> = args.iter().filter_map(|arg| arg.to_str()).collect();
+            run("Typst", &typst, &args, None)
+        })
+        .and_then(|_| {
+            fs::read_to_string(&output).map_err(|e| format!("Failed to read figure: {}", e))
+        });
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+fn render_graphviz(app: &AppHandle, dot: &str) -> Result<String, String> {
+    let binary = tools::find_binary(app, "dot", "-V").ok_or(
+        "Diagram rendering requires Graphviz: bundle it with the app or install it from graphviz.org",
+    )?;
+    run("Graphviz", &binary, &["-Tsvg"], Some(dot))
+}
+
+fn render_mermaid_cli(app: &AppHandle, source: &str, kind: &str) -> Result<String, String> {
+    let binary = tools::find_binary(app, "mmdc", "--version").ok_or_else(|| {
+        format!(
+            "Mermaid {} diagrams require the Mermaid CLI (mmdc); flowcharts are supported without it",
+            kind
+        )
+    })?;
+    let scratch = tools::scratch_dir("mermaid")?;
+    let input = scratch.join("figure.mmd");
+    let output = scratch.join("figure.svg");
+    let result = fs::write(&input, source)
+        .map_err(|e| format!("Failed to write figure source: {}", e))
+        .and_then(|_| {
+            let (input, output) = (input.to_string_lossy(), output.to_string_lossy());
+            let args = ["-q", "-b", "transparent", "-i", &input, "-o", &output];
+            run("Mermaid", &binary, &args, None)
+        })
+        .and_then(|_| {
+            fs::read_to_string(&output).map_err(|e| format!("Failed to read figure: {}", e))
+        });
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+/// A quoted DOT string
+fn dot_string(text: &str) -> String {
+    let text = text
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n")
+        .replace("<br>", "\n");
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// A node's label and shape
+type Shape = (String, &'static str);
+
+struct Link {
+    label: Option<String>,
+    token: String,
+}
+
+/// Reads one Mermaid flowchart statement
+struct Cursor<'a> {
+    text: &'a str,
+    at: usize,
+}
+
+impl Cursor<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.at..]
+    }
+
+    fn skip_space(&mut self) {
+        let rest = self.rest();
+        self.at += rest.len() - rest.trim_start().len();
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> &str {
+        let start = self.at;
+        let length = self
+            .rest()
+            .find(|c: char| !keep(c))
+            .unwrap_or(self.rest().len());
+        self.at += length;
+        &self.text[start..self.at]
+    }
+
+    /// A node id with an optional shape and label, as in `A[Label]` or `B{Decision?}`
+    fn node(&mut self) -> Result<(String, Option<Shape>), String> {
+        self.skip_space();
+        let id = self
+            .take_while(|c| c.is_alphanumeric() || c == '_')
+            .to_string();
+        if id.is_empty() {
+            return Err(format!(
+                "Invalid diagram: expected a node in “{}”",
+                self.text
+            ));
+        }
+        const SHAPES: &[(&str, &str, &str)] = &[
+            ("(((", ")))", "doublecircle"),
+            ("((", "))", "circle"),
+            ("([", "])", "stadium"),
+            ("[[", "]]", "subroutine"),
+            ("[(", ")]", "cylinder"),
+            ("{{", "}}", "hexagon"),
+            ("[/", "/]", "parallelogram"),
+            ("[\\", "\\]", "parallelogram"),
+            ("[", "]", "box"),
+            ("(", ")", "rounded"),
+            ("{", "}", "diamond"),
+            (">", "]", "asymmetric"),
+        ];
+        let Some((open, close, shape)) = SHAPES
+            .iter()
+            .find(|(open, _, _)| self.rest().starts_with(open))
+        else {
+            return Ok((id, None));
+        };
+        self.at += open.len();
+        let rest = self.rest();
+        let (label, length) = match rest.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (
+                    &quoted[..end],
+                    end + 2 + quoted[end + 1..].find(close).unwrap_or(0),
+                ),
+                None => (quoted, rest.len()),
+            },
+            None => match rest.find(close) {
+                Some(end) => (&rest[..end], end),
+                None => (rest, rest.len()),
+            },
+        };
+        let label = label.trim().to_string();
+        self.at = (self.at + length + close.len()).min(self.text.len());
+        Ok((id, Some((label, *shape))))
+    }
+
+    /// An edge such as `-->`, `-.->`, `==>`, `--- `, `-- text -->` or `-->|text|`
+    fn link(&mut self) -> Option<Link> {
+        self.skip_space();
+        let mut token = self.take_while(|c| "<-=.>~".contains(c)).to_string();
+        if token.is_empty() {
+            return None;
+        }
+        // A circle or cross head, as in `--o`, when not the start of the next node
+        let mut chars = self.rest().chars();
+        if let (Some(head @ ('o' | 'x')), next) = (chars.next(), chars.next()) {
+            if next.is_none_or(char::is_whitespace) {
+                token.push(head);
+                self.at += 1;
+            }
+        }
+        let mut label = None;
+        if matches!(token.as_str(), "--" | "==" | "-.") {
+            let rest = self.rest();
+            let end = ["--", "==", ".-"]
+                .iter()
+                .filter_map(|close| rest.find(close))
+                .min();
+            if let Some(end) = end {
+                label = Some(rest[..end].trim().to_string());
+                self.at += end;
+                token.push_str(self.take_while(|c| "-=.>ox".contains(c)));
+            }
+        }
+        self.skip_space();
+        if let Some(quoted) = self.rest().strip_prefix('|') {
+            if let Some(end) = quoted.find('|') {
+                label = Some(quoted[..end].trim().to_string());
+                self.at += end + 2;
+            }
+        }
+        Some(Link {
+            label: label.filter(|label| !label.is_empty()),
+            token,
+        })
+    }
+}
+
+/// Builds DOT from Mermaid flowchart statements
+#[derive(Default)]
+struct Flowchart {
+    lines: Vec<String>,
+    /// Line declaring each node, updated when a later mention gives it a label
+    nodes: HashMap<String, usize>,
+    open: usize,
+    clusters: usize,
+}
+
+impl Flowchart {
+    fn node(&mut self, id: &str, shape: Option<(String, &str)>) {
+        let indent = "  ".repeat(self.open + 1);
+        let declare = |label: &str, shape: &str| {
+            let attributes = match shape {
+                "rounded" | "stadium" => "shape=box, style=rounded",
+                "subroutine" => "shape=box, peripheries=2",
+                "asymmetric" => "shape=cds",
+                "box" => "shape=box",
+                other => other,
+            };
+            let attributes = match attributes.starts_with("shape=") {
+                true => attributes.to_string(),
+                false => format!("shape={}", attributes),
+            };
+            format!(
+                "{}{} [label={}, {}];",
+                indent,
+                dot_string(id),
+                dot_string(label),
+                attributes
+            )
+        };
+        match (self.nodes.get(id), shape) {
+            (Some(&line), Some((label, shape))) => self.lines[line] = declare(&label, shape),
+            (Some(_), None) => {}
+            (None, shape) => {
+                let line = match shape {
+                    Some((label, shape)) => declare(&label, shape),
+                    None => declare(id, "box"),
+                };
+                self.nodes.insert(id.to_string(), self.lines.len());
+                self.lines.push(line);
+            }
+        }
+    }
+
+    fn group(&mut self, cursor: &mut Cursor) -> Result<Vec<String>, String> {
+        let mut ids = Vec::new();
+        loop {
+            let (id, shape) = cursor.node()?;
+            self.node(&id, shape);
+            ids.push(id);
+            cursor.skip_space();
+            match cursor.rest().strip_prefix('&') {
+                Some(_) => cursor.at += 1,
+                None => return Ok(ids),
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &str) -> Result<(), String> {
+        let keyword = statement.split_whitespace().next().unwrap_or_default();
+        match keyword {
+            "" | "classDef" | "class" | "style" | "linkStyle" | "click" | "direction" => Ok(()),
+            "end" => {
+                if self.open > 0 {
+                    self.open -= 1;
+                    self.lines.push(format!("{}}}", "  ".repeat(self.open + 1)));
+                }
+                Ok(())
+            }
+            "subgraph" => {
+                let title = statement["subgraph".len()..].trim();
+                // `subgraph id [Title]` or `subgraph Title`
+                let title = match (title.find('['), title.ends_with(']')) {
+                    (Some(open), true) => &title[open + 1..title.len() - 1],
+                    _ => title,
+                };
+                self.clusters += 1;
+                self.lines.push(format!(
+                    "{}subgraph cluster_{} {{ label={};",
+                    "  ".repeat(self.open + 1),
+                    self.clusters,
+                    dot_string(title.trim_matches('"'))
+                ));
+                self.open += 1;
+                Ok(())
+            }
+            _ => {
+                let mut cursor = Cursor {
+                    text: statement,
+                    at: 0,
+                };
+                let mut previous = self.group(&mut cursor)?;
+                loop {
+                    cursor.skip_space();
+                    if cursor.rest().is_empty() {
+                        return Ok(());
+                    }
+                    let link = cursor.link().ok_or_else(|| {
+                        format!("Invalid diagram: unexpected text in “{}”", statement)
+                    })?;
+                    let next = self.group(&mut cursor)?;
+                    for from in &previous {
+                        for to in &next {
+                            self.edge(from, to, &link);
+                        }
+                    }
+                    previous = next;
+                }
+            }
+        }
+    }
+
+    fn edge(&mut self, from: &str, to: &str, link: &Link) {
+        let token = &link.token;
+        let mut attributes = Vec::new();
+        if let Some(label) = &link.label {
+            attributes.push(format!("label={}", dot_string(label)));
+        }
+        if token.contains('~') {
+            attributes.push("style=invis".to_string());
+        } else if token.contains('.') {
+            attributes.push("style=dashed".to_string());
+        } else if token.contains('=') {
+            attributes.push("penwidth=2".to_string());
+        }
+        let head = |end: Option<char>| match end {
+            Some('>' | '<') => "normal",
+            Some('o') => "odot",
+            Some('x') => "tee",
+            _ => "none",
+        };
+        attributes.push(format!("arrowhead={}", head(token.chars().last())));
+        let tail = token
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '<' | 'o' | 'x'));
+        if tail.is_some() {
+            attributes.push(format!("dir=both, arrowtail={}", head(tail)));
+        }
+        self.lines.push(format!(
+            "{}{} -> {} [{}];",
+            "  ".repeat(self.open + 1),
+            dot_string(from),
+            dot_string(to),
+            attributes.join(", ")
+        ));
+    }
+}
+
+/// A Mermaid `graph` or `flowchart` as Graphviz DOT; `None` for other kinds of Mermaid diagram
+pub fn mermaid_to_dot(source: &str) -> Result<Option<String>, String> {
+    let mut lines = source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("%%"));
+    let header = lines.next().ok_or("Invalid diagram: it is empty")?;
+    let mut words = header.split([' ', ';']).filter(|word| !word.is_empty());
+    if !matches!(words.next(), Some("graph" | "flowchart")) {
+        return Ok(None);
+    }
+    let direction = match words.next() {
+        Some("LR") => "LR",
+        Some("RL") => "RL",
+        Some("BT") => "BT",
+        _ => "TB",
+    };
+    let mut chart = Flowchart::default();
+    for line in lines {
+        for statement in line.split(';') {
+            chart.statement(statement.trim())?;
+        }
+    }
+    while chart.open > 0 {
+        chart.statement("end")?;
+    }
+    Ok(Some(format!(
+        "digraph {{\n  rankdir={};\n  bgcolor=\"transparent\";\n  node [fontname=\"Helvetica\", fontsize=12];\n  edge [fontname=\"Helvetica\", fontsize=10];\n{}\n}}\n",
+        direction,
+        chart.lines.join("\n")
+    )))
+}
+
+fn diagram(app: &AppHandle, source: &str, kind: DiagramKind) -> Result<Figure, String> {
+    let name = match kind {
+        DiagramKind::Graphviz => "graphviz",
+        DiagramKind::Mermaid => "mermaid",
+    };
+    cached(app, cache_key(&[name, source]), || match kind {
+        DiagramKind::Graphviz => render_graphviz(app, source),
+        DiagramKind::Mermaid => match mermaid_to_dot(source)? {
+            Some(dot) => render_graphviz(app, &dot),
+            None => {
+                let kind = source.split_whitespace().next().unwrap_or("these");
+                render_mermaid_cli(app, source, kind)
+            }
+        },
+    })
+}
+
+/// Replace Graphviz and Mermaid code blocks with the rendered diagrams, linked by path, so
+/// exports show the diagram rather than its source; blocks that fail to render stay as code
+pub fn embed_diagrams(app: &AppHandle, markdown: &str) -> String {
+    let mut out = Vec::new();
+    let mut lines = markdown.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let fence = ["```", "~~~"]
+            .into_iter()
+            .find(|fence| trimmed.starts_with(fence));
+        let kind = fence.and_then(|fence| {
+            let language = trimmed[fence.len()..].trim().to_lowercase();
+            DIAGRAM_FENCES
+                .iter()
+                .find(|(name, _)| language == *name)
+                .map(|(_, kind)| *kind)
+        });
+        let (Some(fence), Some(kind)) = (fence, kind) else {
+            out.push(line.to_string());
+            continue;
+        };
+        let mut block = vec![line.to_string()];
+        let mut source = Vec::new();
+        for line in lines.by_ref() {
+            block.push(line.to_string());
+            if line.trim_start().starts_with(fence) {
+                break;
+            }
+            source.push(line);
+        }
+        match diagram(app, &source.join("\n"), kind) {
+            Ok(figure) => out.push(format!("![]({})", figure.path.replace(' ', "%20"))),
+            Err(error) => {
+                tracing::warn!(%error, "Leaving a diagram as code");
+                out.extend(block);
+            }
+        }
+    }
+    out.join("\n")
+}
+
+/// Render math to SVG without a browser or network: LaTeX, or Typst math with
+/// `syntax: "typst"`, typeset by Typst. `display_mode` sets it as a block rather than inline.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn render_math(
+    app: AppHandle,
+    expr: String,
+    display_mode: Option<bool>,
+    syntax: Option<MathSyntax>,
+) -> Result<Figure, Error> {
+    let display = display_mode.unwrap_or(false);
+    let math = match syntax.unwrap_or_default() {
+        MathSyntax::Latex => latex_to_typst(&expr),
+        MathSyntax::Typst => expr,
+    };
+    if math.trim().is_empty() {
+        return Err("Invalid math: the expression is empty".into());
+    }
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        let mode = if display { "display" } else { "inline" };
+        cached(&app, cache_key(&["math", mode, &math]), || {
+            render_typst(&app, &math, display)
+        })
+    })
+    .await
+    .map_err(|e| format!("Render task failed: {}", e))??)
+}
+
+/// Render a Graphviz or Mermaid diagram to SVG; Mermaid flowcharts are laid out by Graphviz,
+/// other Mermaid diagrams by the Mermaid CLI when it is installed
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn render_diagram(
+    app: AppHandle,
+    source: String,
+    kind: DiagramKind,
+) -> Result<Figure, Error> {
+    if source.trim().is_empty() {
+        return Err("Invalid diagram: it is empty".into());
+    }
+    Ok(
+        tauri::async_runtime::spawn_blocking(move || diagram(&app, &source, kind))
+            .await
+            .map_err(|e| format!("Render task failed: {}", e))??,
+    )
+}
@@ -0,0 +1,61 @@
+use crate::deep_link::{self, Navigation};
+use crate::document::DOCUMENT_EXTENSION;
+use crate::workspace;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Open documents passed as launch arguments, resolving relative paths against `cwd`
+pub fn open_args(app: &AppHandle, args: &[String], cwd: &Path) {
+    for arg in args.iter().filter(|arg| !arg.starts_with('-')) {
+        if arg.ends_with(DOCUMENT_EXTENSION) {
+            open_document(app, &cwd.join(arg));
+        }
+    }
+}
+
+/// Open documents delivered by macOS open events as `file://` URLs
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn open_urls(app: &AppHandle, urls: &[url::Url]) {
+    for url in urls.iter().filter(|url| url.scheme() == "file") {
+        if let Ok(path) = url.to_file_path() {
+            if path.to_string_lossy().ends_with(DOCUMENT_EXTENSION) {
+                open_document(app, &path);
+            }
+        }
+    }
+}
+
+/// Switch to the document's workspace and ask the webview to open it
+pub fn open_document(app: &AppHandle, path: &Path) {
+    let Ok(path) = path.canonicalize() else {
+        return;
+    };
+    if !path.is_file() {
+        return;
+    }
+    let root = workspace_for(app, &path);
+    if workspace::set_current_root(app, &root).is_err() {
+        return;
+    }
+    deep_link::navigate(
+        app,
+        Navigation::OpenFile {
+            path: path.to_string_lossy().to_string(),
+            workspace: root.to_string_lossy().to_string(),
+        },
+    );
+}
+
+/// The open workspace when it contains the document, else the nearest folder with workspace
+/// internals, else the document's own folder
+fn workspace_for(app: &AppHandle, path: &Path) -> PathBuf {
+    if let Some(root) = workspace::current_root(app).filter(|root| path.starts_with(root)) {
+        return root;
+    }
+    let parent = path.parent().unwrap_or(Path::new("/"));
+    parent
+        .ancestors()
+        .find(|dir| workspace::internal_dir(dir).is_dir())
+        .unwrap_or(parent)
+        .to_path_buf()
+}
@@ -0,0 +1,432 @@
+use crate::catalog::{self, Database};
+use crate::error::Error;
+use crate::{document, messages, paths, workspace};
+use rusqlite::{params, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+const CHANGED_EVENT: &str = "focus:changed";
+const FINISHED_EVENT: &str = "focus:finished";
+const DEFAULT_BREAK_MINUTES: u32 = 5;
+const MAX_MINUTES: u32 = 240;
+const DEFAULT_HISTORY: u32 = 50;
+/// The timer re-reads the wall clock at least this often, so it catches up after the machine
+/// slept
+const MAX_WAIT: Duration = Duration::from_secs(30);
+const MINUTE_MS: i64 = 60 * 1000;
+
+/// Managed state holding the running focus session.
+///
+/// The timer lives here rather than in the webview, so reloading a window only needs
+/// `get_focus_session` to pick it up again.
+#[derive(Default)]
+pub struct FocusSessions {
+    current: Mutex<Option<Running>>,
+    wake: Condvar,
+}
+
+struct Running {
+    session: FocusSession,
+    root: Option<PathBuf>,
+    /// Words in the document when the session started, from the metadata cache
+    words_at_start: Option<i64>,
+}
+
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusSession {
+    pub id: String,
+    pub document: Option<String>,
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+    /// Work intervals to go through; the session runs until stopped without one
+    pub intervals: Option<u32>,
+    pub phase: Phase,
+    /// Work intervals finished so far
+    pub completed: u32,
+    pub started_at: i64,
+    /// When the current interval ends, in epoch milliseconds; unset while paused
+    pub ends_at: Option<i64>,
+    /// Milliseconds left in the current interval
+    pub remaining_ms: i64,
+    pub paused: bool,
+}
+
+/// A finished or stopped session, as kept in the workspace's metadata cache next to its
+/// writing stats
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusRecord {
+    pub id: String,
+    pub started_at: i64,
+    pub ended_at: i64,
+    /// Workspace-relative path of the document worked on
+    pub document: Option<String>,
+    pub work_minutes: u32,
+    pub intervals: u32,
+    /// Time spent in work intervals, including the part of one cut short
+    pub focused_ms: i64,
+    /// Net words the document gained
+    pub words: Option<i64>,
+    /// Whether every planned interval was finished rather than the session being stopped
+    pub completed: bool,
+}
+
+impl FocusSession {
+    fn phase_ms(&self) -> i64 {
+        match self.phase {
+            Phase::Work => self.work_minutes as i64 * MINUTE_MS,
+            Phase::Break => self.break_minutes as i64 * MINUTE_MS,
+        }
+    }
+
+    /// The session with `remaining_ms` as of `now`
+    fn at(&self, now: i64) -> FocusSession {
+        let mut session = self.clone();
+        if let Some(ends_at) = session.ends_at {
+            session.remaining_ms = (ends_at - now).max(0);
+        }
+        session
+    }
+
+    fn begin(&mut self, phase: Phase, now: i64) {
+        self.phase = phase;
+        self.remaining_ms = self.phase_ms();
+        self.ends_at = Some(now + self.remaining_ms);
+    }
+}
+
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    let _ = app.notification().builder().title(title).body(body).show();
+}
+
+/// Words in `path` according to the cache of the workspace containing it
+fn word_count(db: &Database, root: &Path, path: &str) -> Option<i64> {
+    let relative = workspace::relative_path(root, Path::new(path));
+    let db = db.lock().ok()?;
+    db.query_row(
+        "SELECT word_count FROM documents WHERE path = ?1",
+        [relative],
+        |row| row.get(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// Write a session that ended into its workspace's history
+fn record(app: &AppHandle, running: &Running, now: i64, completed: bool) -> Option<FocusRecord> {
+    let session = &running.session;
+    let root = running.root.as_deref()?;
+    let mut focused_ms = session.completed as i64 * session.work_minutes as i64 * MINUTE_MS;
+    if session.phase == Phase::Work && !completed {
+        // The part of the interval that was cut short still counts
+        focused_ms += session.phase_ms() - session.at(now).remaining_ms;
+    }
+    let db = catalog::database(app, root).ok()?;
+    let document = session
+        .document
+        .as_deref()
+        .map(|path| workspace::relative_path(root, Path::new(path)));
+    let words = session.document.as_deref().and_then(|path| {
+        let after = word_count(&db, root, path)?;
+        Some(after - running.words_at_start?)
+    });
+    let record = FocusRecord {
+        id: session.id.clone(),
+        started_at: session.started_at,
+        ended_at: now,
+        document,
+        work_minutes: session.work_minutes,
+        intervals: session.completed,
+        focused_ms,
+        words,
+        completed,
+    };
+    let result = db
+        .lock()
+        .map_err(|e| format!("Failed to write focus history: {}", e))
+        .and_then(|db| {
+            db.execute(
+                "INSERT OR REPLACE INTO focus_sessions
+                     (id, started_at, ended_at, path, work_minutes, intervals, focused_ms, words,
+                      completed)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    record.id,
+                    record.started_at,
+                    record.ended_at,
+                    record.document,
+                    record.work_minutes,
+                    record.intervals,
+                    record.focused_ms,
+                    record.words,
+                    record.completed
+                ],
+            )
+            .map_err(|e| format!("Failed to write focus history: {}", e))
+        });
+    if let Err(error) = result {
+        tracing::warn!(%error, "Failed to record focus session");
+    }
+    Some(record)
+}
+
+/// Move the session on once its interval is over, notifying at the end of work and break
+/// intervals; returns whether the session goes on
+fn advance(app: &AppHandle, running: &mut Running, now: i64) -> bool {
+    let session = &mut running.session;
+    match session.phase {
+        Phase::Work => {
+            session.completed += 1;
+            if session.intervals == Some(session.completed) {
+                notify(
+                    app,
+                    &messages::text("focus-finished-title"),
+                    &messages::format(
+                        "focus-finished-body",
+                        &[("count", session.completed.into())],
+                    ),
+                );
+                let record = record(app, running, now, true);
+                tracing::info!(id = %running.session.id, "Focus session finished");
+                let _ = app.emit(FINISHED_EVENT, &record);
+                return false;
+            }
+            if session.break_minutes == 0 {
+                session.begin(Phase::Work, now);
+            } else {
+                session.begin(Phase::Break, now);
+                notify(
+                    app,
+                    &messages::text("focus-break-title"),
+                    &messages::format(
+                        "focus-break-body",
+                        &[("minutes", session.break_minutes.into())],
+                    ),
+                );
+            }
+        }
+        Phase::Break => {
+            session.begin(Phase::Work, now);
+            notify(
+                app,
+                &messages::text("focus-work-title"),
+                &messages::format(
+                    "focus-work-body",
+                    &[("minutes", session.work_minutes.into())],
+                ),
+            );
+        }
+    }
+    let _ = app.emit(CHANGED_EVENT, running.session.at(now));
+    true
+}
+
+/// Run the timer of the focus session, waking when its interval ends or the session changes
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || {
+        let sessions = app.state::<FocusSessions>();
+        let Ok(mut current) = sessions.current.lock() else {
+            return;
+        };
+        loop {
+            let now = document::now_millis();
+            let ends_at = current.as_ref().and_then(|running| running.session.ends_at);
+            let wait = match ends_at {
+                Some(ends_at) if ends_at <= now => {
+                    let goes_on = current
+                        .as_mut()
+                        .is_some_and(|running| advance(&app, running, now));
+                    if !goes_on {
+                        *current = None;
+                    }
+                    continue;
+                }
+                Some(ends_at) => Duration::from_millis((ends_at - now) as u64).min(MAX_WAIT),
+                None => MAX_WAIT,
+            };
+            current = match sessions.wake.wait_timeout(current, wait) {
+                Ok((current, _)) => current,
+                Err(_) => return,
+            };
+        }
+    });
+}
+
+/// Start a focus session of `duration`-minute work intervals, each followed by a break,
+/// optionally for `doc`. Replaces nothing: a running session has to be stopped first.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn start_focus_session(
+    app: AppHandle,
+    sessions: State<'_, FocusSessions>,
+    duration: u32,
+    doc: Option<String>,
+    break_minutes: Option<u32>,
+    intervals: Option<u32>,
+) -> Result<FocusSession, Error> {
+    let break_minutes = break_minutes.unwrap_or(DEFAULT_BREAK_MINUTES);
+    if duration == 0 || duration > MAX_MINUTES || break_minutes > MAX_MINUTES {
+        return Err(format!("Invalid duration: up to {} minutes", MAX_MINUTES).into());
+    }
+    if intervals == Some(0) {
+        return Err("Invalid intervals: at least one is needed".into());
+    }
+    let path = doc
+        .map(|doc| paths::check(&app, &doc, paths::Scope::Read))
+        .transpose()?;
+    let mut current = sessions
+        .current
+        .lock()
+        .map_err(|e| format!("Failed to start focus session: {}", e))?;
+    if current.is_some() {
+        return Err(Error::conflict("A focus session is already running"));
+    }
+
+    let open = match &path {
+        Some(path) => catalog::containing(&app, path),
+        None => None,
+    };
+    let root = open
+        .as_ref()
+        .map(|(root, _)| root.clone())
+        .or_else(|| workspace::current_root(&app));
+    let words_at_start = match (&open, &path) {
+        (Some((root, db)), Some(path)) => word_count(db, root, &path.to_string_lossy()),
+        _ => None,
+    };
+    let now = document::now_millis();
+    let mut session = FocusSession {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        document: path.map(|path| path.to_string_lossy().to_string()),
+        work_minutes: duration,
+        break_minutes,
+        intervals,
+        phase: Phase::Work,
+        completed: 0,
+        started_at: now,
+        ends_at: None,
+        remaining_ms: 0,
+        paused: false,
+    };
+    session.begin(Phase::Work, now);
+    *current = Some(Running {
+        session: session.clone(),
+        root,
+        words_at_start,
+    });
+    sessions.wake.notify_all();
+    tracing::info!(id = %session.id, duration, "Focus session started");
+    let _ = app.emit(CHANGED_EVENT, session.clone());
+    Ok(session)
+}
+
+/// The running focus session, if any
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_focus_session(sessions: State<'_, FocusSessions>) -> Option<FocusSession> {
+    let current = sessions.current.lock().ok()?;
+    current
+        .as_ref()
+        .map(|running| running.session.at(document::now_millis()))
+}
+
+/// Pause or resume the running session's current interval
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn pause_focus_session(
+    app: AppHandle,
+    sessions: State<'_, FocusSessions>,
+    paused: bool,
+) -> Result<FocusSession, Error> {
+    let mut current = sessions
+        .current
+        .lock()
+        .map_err(|e| format!("Failed to pause focus session: {}", e))?;
+    let running = current
+        .as_mut()
+        .ok_or_else(|| Error::not_found("Focus session not found"))?;
+    let now = document::now_millis();
+    let session = &mut running.session;
+    if paused && !session.paused {
+        session.remaining_ms = session.at(now).remaining_ms;
+        session.ends_at = None;
+    } else if !paused && session.paused {
+        session.ends_at = Some(now + session.remaining_ms);
+    }
+    session.paused = paused;
+    let session = session.at(now);
+    sessions.wake.notify_all();
+    let _ = app.emit(CHANGED_EVENT, session.clone());
+    Ok(session)
+}
+
+/// Stop the running session, keeping it in the history; returns what was recorded, or nothing
+/// when no session was running or it has no workspace to be kept in
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn stop_focus_session(
+    app: AppHandle,
+    sessions: State<'_, FocusSessions>,
+) -> Option<FocusRecord> {
+    let running = sessions.current.lock().ok()?.take()?;
+    sessions.wake.notify_all();
+    let record = record(&app, &running, document::now_millis(), false);
+    tracing::info!(id = %running.session.id, "Focus session stopped");
+    let _ = app.emit(FINISHED_EVENT, &record);
+    record
+}
+
+/// Focus sessions kept for the workspace, most recent first
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn focus_history(
+    app: AppHandle,
+    workspace: String,
+    limit: Option<u32>,
+) -> Result<Vec<FocusRecord>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = catalog::database(&app, &root)?;
+        let db = db
+            .lock()
+            .map_err(|e| format!("Failed to read focus history: {}", e))?;
+        let mut statement = db
+            .prepare(
+                "SELECT id, started_at, ended_at, path, work_minutes, intervals, focused_ms, words,
+                        completed
+                 FROM focus_sessions ORDER BY started_at DESC LIMIT ?1",
+            )
+            .map_err(|e| format!("Failed to read focus history: {}", e))?;
+        let records = statement
+            .query_map([limit.unwrap_or(DEFAULT_HISTORY)], |row| {
+                Ok(FocusRecord {
+                    id: row.get(0)?,
+                    started_at: row.get(1)?,
+                    ended_at: row.get(2)?,
+                    document: row.get(3)?,
+                    work_minutes: row.get(4)?,
+                    intervals: row.get(5)?,
+                    focused_ms: row.get(6)?,
+                    words: row.get(7)?,
+                    completed: row.get(8)?,
+                })
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read focus history: {}", e))?;
+        Ok(records)
+    })
+    .await
+    .map_err(|e| format!("Focus history task failed: {}", e))?
+}
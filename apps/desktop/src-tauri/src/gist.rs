@@ -0,0 +1,191 @@
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::{http, paths, secrets, site, vault, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use tauri::AppHandle;
+
+const API: &str = "https://api.github.com/gists";
+/// Workspace secret holding a GitHub personal access token with the `gist` scope
+const TOKEN_SECRET: &str = "github-token";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum GistVisibility {
+    Public,
+    /// Unlisted: readable by anyone with the link
+    Secret,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedGist {
+    pub id: String,
+    pub url: String,
+    pub file: String,
+    /// `false` when an existing gist was updated
+    pub created: bool,
+}
+
+#[derive(Deserialize)]
+struct GistReply {
+    id: String,
+    html_url: String,
+    public: bool,
+    #[serde(default)]
+    files: Map<String, Value>,
+}
+
+/// Markdown for the given shapes in document order, or the whole document when none are given
+fn selection_markdown(board: &BoardFile, blocks: Option<&[String]>) -> Result<String, String> {
+    let Some(blocks) = blocks.filter(|blocks| !blocks.is_empty()) else {
+        return Ok(board.to_markdown());
+    };
+    let selected: HashSet<&str> = blocks.iter().map(String::as_str).collect();
+    let texts: Vec<String> = board
+        .pages()
+        .iter()
+        .flat_map(|page| board.page_shapes(page))
+        .filter(|shape| selected.contains(shape.id.as_str()))
+        .filter_map(|shape| shape.text().map(|text| text.trim().to_string()))
+        .collect();
+    if texts.is_empty() {
+        return Err("The selection has no text to publish".to_string());
+    }
+    Ok(format!("{}\n", texts.join("\n\n")))
+}
+
+fn headers<B>(request: ureq::RequestBuilder<B>, token: &str) -> ureq::RequestBuilder<B> {
+    request
+        .header("accept", "application/vnd.github+json")
+        .header("authorization", format!("Bearer {}", token))
+        .header("x-github-api-version", "2022-11-28")
+}
+
+fn send(
+    request: ureq::RequestBuilder<ureq::typestate::WithBody>,
+    token: &str,
+    body: &Value,
+) -> Result<ureq::http::Response<ureq::Body>, ureq::Error> {
+    headers(request, token)
+        .header("content-type", "application/json")
+        .send(body.to_string())
+}
+
+fn reply(
+    response: Result<ureq::http::Response<ureq::Body>, ureq::Error>,
+) -> Result<GistReply, ureq::Error> {
+    let body = response?.body_mut().read_to_string()?;
+    serde_json::from_str(&body).map_err(|e| ureq::Error::Other(Box::new(e)))
+}
+
+fn publish(
+    app: &AppHandle,
+    doc: &str,
+    blocks: Option<&[String]>,
+    visibility: GistVisibility,
+) -> Result<PublishedGist, Error> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let resolved = paths::check(app, doc, paths::Scope::Write)?;
+    if vault::vault_of(&resolved).is_some() {
+        return Err("Gists are not available for documents in a vault".into());
+    }
+    let token = secrets::get(&root, TOKEN_SECRET)?.ok_or_else(|| {
+        format!(
+            "GitHub token not found: store a personal access token as the `{}` secret",
+            TOKEN_SECRET
+        )
+    })?;
+    let mut board = document::read_board(&resolved)?;
+    let markdown = selection_markdown(&board, blocks)?;
+    let file = format!("{}.md", site::slugify(&board.board.name));
+    let agent = http::agent();
+
+    let existing = match &board.board.gist_id {
+        Some(id) => match reply(headers(agent.get(format!("{}/{}", API, id)), &token).call()) {
+            Ok(gist) => Some(gist),
+            // Deleted on GitHub: publish a fresh one
+            Err(ureq::Error::StatusCode(404)) => None,
+            Err(e) => return Err(format!("Failed to read gist: {}", e).into()),
+        },
+        None => None,
+    };
+
+    let published = match existing {
+        Some(gist) => {
+            if gist.public != (visibility == GistVisibility::Public) {
+                return Err("Changing the visibility of a gist is not supported by GitHub".into());
+            }
+            // Rename the file published last time rather than adding a second one
+            let previous = gist
+                .files
+                .keys()
+                .find(|name| name.ends_with(".md"))
+                .cloned()
+                .unwrap_or_else(|| file.clone());
+            let body = json!({
+                "description": board.board.name,
+                "files": { previous: { "filename": file, "content": markdown } },
+            });
+            reply(send(
+                agent.patch(format!("{}/{}", API, gist.id)),
+                &token,
+                &body,
+            ))
+            .map_err(|e| format!("Failed to update gist: {}", e))?
+        }
+        None => {
+            let body = json!({
+                "description": board.board.name,
+                "public": visibility == GistVisibility::Public,
+                "files": { file.clone(): { "content": markdown } },
+            });
+            reply(send(agent.post(API), &token, &body))
+                .map_err(|e| format!("Failed to create gist: {}", e))?
+        }
+    };
+
+    let created = board.board.gist_id.as_deref() != Some(published.id.as_str());
+    if created {
+        board.board.gist_id = Some(published.id.clone());
+        document::write_board(&resolved, &board)?;
+    }
+    tracing::info!(created, "Gist published");
+    Ok(PublishedGist {
+        id: published.id,
+        url: published.html_url,
+        file,
+        created,
+    })
+}
+
+/// Export the selected blocks of `doc` (all of it when `blocks` is empty) to Markdown and publish
+/// them as a GitHub gist, using the `github-token` workspace secret. The gist id is kept in the
+/// document so publishing again updates the same gist.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn publish_gist(
+    app: AppHandle,
+    doc: String,
+    blocks: Option<Vec<String>>,
+    visibility: GistVisibility,
+) -> Result<PublishedGist, Error> {
+    tauri::async_runtime::spawn_blocking(move || publish(&app, &doc, blocks.as_deref(), visibility))
+        .await
+        .map_err(|e| format!("Gist task failed: {}", e))?
+}
+
+/// Forget the gist a document was published to, so the next publish creates a new one. The gist
+/// itself is left on GitHub.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn unlink_gist(app: AppHandle, doc: String) -> Result<bool, Error> {
+    let resolved = paths::check(&app, &doc, paths::Scope::Write)?;
+    let mut board = document::read_board(&resolved)?;
+    if board.board.gist_id.take().is_none() {
+        return Ok(false);
+    }
+    document::write_board(&resolved, &board)?;
+    Ok(true)
+}
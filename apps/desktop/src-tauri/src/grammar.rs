@@ -0,0 +1,593 @@
+use crate::error::Error;
+use crate::{http, paths, settings, tools, workspace};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Rules and phrases a workspace ignores, `.inkfinite/grammar.json`
+const CONFIG: &str = "grammar";
+/// LanguageTool server bundled with the app resources
+const SERVER_JAR: &str = "languagetool/languagetool-server.jar";
+const SERVER_CLASS: &str = "org.languagetool.server.HTTPServer";
+/// A JVM takes a while to start; checks use the built-in rules until the server answers
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(90);
+const STARTUP_POLL: Duration = Duration::from_millis(500);
+/// Sentences longer than this are flagged as hard to read
+const LONG_SENTENCE_WORDS: usize = 40;
+
+/// Wordy English phrases and what to write instead
+const WORDY_PHRASES: &[(&str, &str)] = &[
+    ("in order to", "to"),
+    ("due to the fact that", "because"),
+    ("at this point in time", "now"),
+    ("in the event that", "if"),
+    ("for the purpose of", "to"),
+    ("in spite of the fact that", "although"),
+    ("with regard to", "about"),
+    ("a large number of", "many"),
+    ("the majority of", "most"),
+    ("is able to", "can"),
+    ("has the ability to", "can"),
+    ("in close proximity to", "near"),
+    ("prior to", "before"),
+    ("each and every", "every"),
+    ("first and foremost", "first"),
+];
+/// Words after which a period does not end a sentence
+const ABBREVIATIONS: &[&str] = &[
+    "e.g", "i.e", "etc", "vs", "mr", "mrs", "ms", "dr", "st", "cf",
+];
+/// Words starting with a vowel letter but a consonant sound, and the other way round
+const A_BEFORE: &[&str] = &[
+    "uni", "use", "usu", "usa", "eu", "one", "once", "ubi", "ure",
+];
+const AN_BEFORE: &[&str] = &["hour", "honest", "honor", "honour", "heir"];
+
+/// Managed state holding the bundled LanguageTool server once it was started
+#[derive(Default)]
+pub struct GrammarServer(Mutex<ServerState>);
+
+#[derive(Default)]
+struct ServerState {
+    child: Option<Child>,
+    /// Set once the server answers
+    url: Option<String>,
+    /// Whether starting it was already tried, so a missing Java is looked for once
+    tried: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GrammarConfig {
+    /// Rule ids never reported, from LanguageTool or the built-in rules
+    pub disabled_rules: BTreeSet<String>,
+    /// Flagged text accepted as written, compared without case
+    pub ignored: BTreeSet<String>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Engine {
+    LanguageTool,
+    /// The rules shipped with the app, used when no LanguageTool server is available
+    Builtin,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Grammar,
+    Style,
+    Typography,
+    Spelling,
+    Other,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrammarIssue {
+    pub rule: String,
+    pub category: Category,
+    pub message: String,
+    /// Offsets in UTF-16 code units, matching JavaScript string indices
+    pub start: usize,
+    pub end: usize,
+    /// Replacements for the text between `start` and `end`, best first
+    pub replacements: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrammarReport {
+    pub engine: Engine,
+    pub issues: Vec<GrammarIssue>,
+}
+
+/// An issue found by the built-in rules, with byte offsets
+struct Found {
+    rule: &'static str,
+    category: Category,
+    message: String,
+    start: usize,
+    end: usize,
+    replacements: Vec<String>,
+}
+
+/// Words with their byte offsets
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices().chain(Some((text.len(), ' '))) {
+        let part_of_word = c.is_alphanumeric() || c == '\'' || c == '’';
+        match (part_of_word, start) {
+            (true, None) => start = Some(index),
+            (false, Some(from)) => {
+                words.push((from, &text[from..index]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Rules that hold in any language
+fn common_rules(text: &str, language: &str, found: &mut Vec<Found>) {
+    let words = words(text);
+    for pair in words.windows(2) {
+        let ((start, first), (next, second)) = (pair[0], pair[1]);
+        let between = &text[start + first.len()..next];
+        if first.to_lowercase() == second.to_lowercase()
+            && between.chars().all(char::is_whitespace)
+            && !first.chars().all(char::is_numeric)
+        {
+            found.push(Found {
+                rule: "REPEATED_WORD",
+                category: Category::Grammar,
+                message: format!("“{}” is repeated", first),
+                start,
+                end: next + second.len(),
+                replacements: vec![first.to_string()],
+            });
+        }
+    }
+
+    // French sets these apart with a space on purpose
+    let spaced: &[char] = match language.starts_with("fr") {
+        true => &[',', '.'],
+        false => &[',', '.', ';', ':', '?', '!'],
+    };
+    let bytes = text.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b' ' {
+            index += 1;
+            continue;
+        }
+        let run = bytes[index..].iter().take_while(|b| **b == b' ').count();
+        let before = text[..index].chars().next_back();
+        let after = text[index + run..].chars().next();
+        let inside = before.is_some_and(|c| !c.is_whitespace());
+        match after {
+            Some(c) if inside && spaced.contains(&c) => found.push(Found {
+                rule: "SPACE_BEFORE_PUNCTUATION",
+                category: Category::Typography,
+                message: format!("No space is needed before “{}”", c),
+                start: index,
+                end: index + run + c.len_utf8(),
+                replacements: vec![c.to_string()],
+            }),
+            Some(c) if inside && run > 1 && !c.is_whitespace() => found.push(Found {
+                rule: "DOUBLE_SPACE",
+                category: Category::Typography,
+                message: "More than one space between words".to_string(),
+                start: index,
+                end: index + run,
+                replacements: vec![" ".to_string()],
+            }),
+            _ => {}
+        }
+        index += run;
+    }
+}
+
+/// Rules for English text
+fn english_rules(text: &str, found: &mut Vec<Found>) {
+    let words = words(text);
+    for pair in words.windows(2) {
+        let ((start, article), (_, next)) = (pair[0], pair[1]);
+        let lower = next.to_lowercase();
+        let vowel = lower.starts_with(['a', 'e', 'i', 'o', 'u']);
+        let wants_an = match () {
+            _ if AN_BEFORE.iter().any(|word| lower.starts_with(word)) => true,
+            _ if A_BEFORE.iter().any(|word| lower.starts_with(word)) => false,
+            _ => vowel,
+        };
+        let replacement = match (article, wants_an) {
+            ("a", true) => "an",
+            ("A", true) => "An",
+            ("an", false) => "a",
+            ("An", false) => "A",
+            _ => continue,
+        };
+        // Letters and numbers read out one by one, such as "a 1" or "an FAQ", are left alone
+        if next.chars().all(|c| c.is_uppercase() || c.is_numeric()) {
+            continue;
+        }
+        found.push(Found {
+            rule: "EN_A_VS_AN",
+            category: Category::Grammar,
+            message: format!("Use “{}” before “{}”", replacement, next),
+            start,
+            end: start + article.len(),
+            replacements: vec![replacement.to_string()],
+        });
+    }
+
+    let lower = text.to_lowercase();
+    // Lowercasing can change byte lengths; phrase offsets are only trusted when it did not
+    if lower.len() == text.len() {
+        for (phrase, replacement) in WORDY_PHRASES {
+            for (start, _) in lower.match_indices(phrase) {
+                let end = start + phrase.len();
+                let bounded = !lower[..start].ends_with(char::is_alphanumeric)
+                    && !lower[end..].starts_with(char::is_alphanumeric);
+                if bounded {
+                    found.push(Found {
+                        rule: "EN_WORDY",
+                        category: Category::Style,
+                        message: format!("“{}” can be shorter", &text[start..end]),
+                        start,
+                        end,
+                        replacements: vec![replacement.to_string()],
+                    });
+                }
+            }
+        }
+    }
+
+    let mut sentence_start = 0;
+    let mut sentence_words = 0;
+    for (index, (start, word)) in words.iter().enumerate() {
+        sentence_words += 1;
+        let rest = &text[start + word.len()..];
+        let ends = rest.starts_with(['.', '!', '?'])
+            && !ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+            && !(word.len() == 1 && rest.starts_with('.'));
+        if !ends {
+            continue;
+        }
+        let end = start + word.len() + 1;
+        if sentence_words > LONG_SENTENCE_WORDS {
+            found.push(Found {
+                rule: "LONG_SENTENCE",
+                category: Category::Style,
+                message: format!("This sentence has {} words", sentence_words),
+                start: sentence_start,
+                end,
+                replacements: Vec::new(),
+            });
+        }
+        if let Some((next_start, next)) = words.get(index + 1) {
+            let gap = &text[end..*next_start];
+            let starts_lower = next.chars().next().is_some_and(char::is_lowercase);
+            if starts_lower && gap.starts_with(' ') && gap.trim().is_empty() {
+                found.push(Found {
+                    rule: "UPPERCASE_SENTENCE_START",
+                    category: Category::Grammar,
+                    message: "A sentence starts with a capital letter".to_string(),
+                    start: *next_start,
+                    end: next_start + next.len(),
+                    replacements: vec![capitalized(next)],
+                });
+            }
+            sentence_start = *next_start;
+        }
+        sentence_words = 0;
+    }
+}
+
+fn builtin(text: &str, language: &str) -> Vec<GrammarIssue> {
+    let mut found = Vec::new();
+    common_rules(text, language, &mut found);
+    if language == "auto" || language.starts_with("en") {
+        english_rules(text, &mut found);
+    }
+    found.sort_by_key(|issue| issue.start);
+    let utf16 = |byte: usize| text[..byte].encode_utf16().count();
+    found
+        .into_iter()
+        .map(|issue| GrammarIssue {
+            rule: issue.rule.to_string(),
+            category: issue.category,
+            message: issue.message,
+            start: utf16(issue.start),
+            end: utf16(issue.end),
+            replacements: issue.replacements,
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct CheckResponse {
+    matches: Vec<Match>,
+}
+
+#[derive(Deserialize)]
+struct Match {
+    message: String,
+    offset: usize,
+    length: usize,
+    #[serde(default)]
+    replacements: Vec<Replacement>,
+    rule: Rule,
+}
+
+#[derive(Deserialize)]
+struct Replacement {
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    #[serde(default)]
+    issue_type: String,
+}
+
+/// Ask a LanguageTool server; its offsets already count UTF-16 code units
+fn language_tool(
+    url: &str,
+    text: &str,
+    language: &str,
+    disabled: &BTreeSet<String>,
+) -> Result<Vec<GrammarIssue>, String> {
+    let disabled = disabled.iter().cloned().collect::<Vec<_>>().join(",");
+    let mut form = vec![("text", text), ("language", language)];
+    if !disabled.is_empty() {
+        form.push(("disabledRules", &disabled));
+    }
+    let body = http::agent()
+        .post(&format!("{}/v2/check", url.trim_end_matches('/')))
+        .send_form(form)
+        .map_err(|e| format!("Failed to reach LanguageTool: {}", e))?
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to read from LanguageTool: {}", e))?;
+    let response: CheckResponse =
+        serde_json::from_str(&body).map_err(|e| format!("Invalid LanguageTool response: {}", e))?;
+    Ok(response
+        .matches
+        .into_iter()
+        .map(|found| GrammarIssue {
+            category: match found.rule.issue_type.as_str() {
+                "grammar" => Category::Grammar,
+                "style" | "locale-violation" | "register" => Category::Style,
+                "typographical" | "whitespace" => Category::Typography,
+                "misspelling" => Category::Spelling,
+                _ => Category::Other,
+            },
+            rule: found.rule.id,
+            message: found.message,
+            start: found.offset,
+            end: found.offset + found.length,
+            replacements: found
+                .replacements
+                .into_iter()
+                .map(|replacement| replacement.value)
+                .collect(),
+        })
+        .collect())
+}
+
+/// Start the bundled LanguageTool server in the background if Java and the server are
+/// available; its URL is set once it answers
+fn start_server(app: &AppHandle) {
+    let server = app.state::<GrammarServer>();
+    let Ok(mut state) = server.0.lock() else {
+        return;
+    };
+    if state.tried {
+        return;
+    }
+    state.tried = true;
+    let jar = app
+        .path()
+        .resource_dir()
+        .ok()
+        .map(|dir| dir.join(SERVER_JAR))
+        .filter(|jar| jar.is_file());
+    let (Some(jar), Some(java)) = (jar, tools::find_binary(app, "java", "-version")) else {
+        tracing::debug!("No bundled LanguageTool server; using the built-in grammar rules");
+        return;
+    };
+    let Some(port) = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .ok()
+        .map(|address| address.port())
+    else {
+        return;
+    };
+    let child = Command::new(java)
+        .arg("-cp")
+        .arg(&jar)
+        .arg(SERVER_CLASS)
+        .args(["--port", &port.to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match child {
+        Ok(child) => state.child = Some(child),
+        Err(error) => {
+            tracing::warn!(%error, "Failed to start LanguageTool");
+            return;
+        }
+    }
+    drop(state);
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let url = format!("http://127.0.0.1:{}", port);
+        let started = Instant::now();
+        while started.elapsed() < STARTUP_TIMEOUT {
+            let ready = http::agent()
+                .get(&format!("{}/v2/languages", url))
+                .call()
+                .is_ok();
+            if ready {
+                if let Ok(mut state) = app.state::<GrammarServer>().0.lock() {
+                    state.url = Some(url);
+                }
+                tracing::info!(port, "LanguageTool started");
+                return;
+            }
+            std::thread::sleep(STARTUP_POLL);
+        }
+        tracing::warn!("LanguageTool did not start in time");
+        stop(&app);
+    });
+}
+
+/// Stop the bundled LanguageTool server, if it was started
+pub fn stop(app: &AppHandle) {
+    let server = app.state::<GrammarServer>();
+    let Ok(mut state) = server.0.lock() else {
+        return;
+    };
+    state.url = None;
+    if let Some(mut child) = state.child.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Check `text` for grammar and style issues.
+///
+/// A LanguageTool server set in settings is used first, then the one bundled with the app once
+/// it has started; until then, or without Java, the built-in rules check common mistakes.
+/// `language` is a tag such as `en-US` or `en_US`, detected when unset. Rules and phrases the
+/// workspace ignores are left out.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn check_text(
+    app: AppHandle,
+    text: String,
+    language: Option<String>,
+    workspace: Option<String>,
+) -> Result<GrammarReport, Error> {
+    let config: GrammarConfig = match &workspace {
+        Some(workspace) => workspace::read_config(
+            &paths::check_workspace(&app, workspace, paths::Scope::Read)?,
+            CONFIG,
+        )?,
+        None => GrammarConfig::default(),
+    };
+    let language = language
+        .map(|language| language.replace('_', "-"))
+        .unwrap_or_else(|| "auto".to_string());
+    tauri::async_runtime::spawn_blocking(move || {
+        let configured = settings::current(&app, None).grammar_server;
+        if configured.is_none() {
+            start_server(&app);
+        }
+        let server = configured.or_else(|| {
+            let state = app.state::<GrammarServer>();
+            let state = state.0.lock().ok()?;
+            state.url.clone()
+        });
+        let (engine, issues) = match server {
+            Some(url) => match language_tool(&url, &text, &language, &config.disabled_rules) {
+                Ok(issues) => (Engine::LanguageTool, issues),
+                Err(error) => {
+                    tracing::warn!(%error, "Falling back to the built-in grammar rules");
+                    (Engine::Builtin, builtin(&text, &language))
+                }
+            },
+            None => (Engine::Builtin, builtin(&text, &language)),
+        };
+
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let ignored: BTreeSet<String> = config.ignored.iter().map(|p| p.to_lowercase()).collect();
+        let issues = issues
+            .into_iter()
+            .filter(|issue| !config.disabled_rules.contains(&issue.rule))
+            .filter(|issue| {
+                let flagged = units
+                    .get(issue.start..issue.end)
+                    .map(String::from_utf16_lossy)
+                    .unwrap_or_default();
+                !ignored.contains(&flagged.to_lowercase())
+            })
+            .collect();
+        Ok(GrammarReport { engine, issues })
+    })
+    .await
+    .map_err(|e| format!("Grammar check failed: {}", e))?
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_grammar_config(app: AppHandle, workspace: String) -> Result<GrammarConfig, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(workspace::read_config(&root, CONFIG)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_grammar_config(
+    app: AppHandle,
+    workspace: String,
+    config: GrammarConfig,
+) -> Result<(), Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    workspace::write_config(&root, CONFIG, &config)?;
+    Ok(())
+}
+
+/// Stop reporting a rule, or a flagged phrase, in the workspace; returns `false` when it was
+/// already ignored
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn ignore_grammar_issue(
+    app: AppHandle,
+    workspace: String,
+    rule: Option<String>,
+    phrase: Option<String>,
+) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    update(&root, |config| {
+        let mut added = false;
+        if let Some(rule) = rule.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+            added |= config.disabled_rules.insert(rule.to_string());
+        }
+        if let Some(phrase) = phrase.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            added |= config.ignored.insert(phrase.to_string());
+        }
+        added
+    })
+    .map_err(Error::from)
+}
+
+fn update(root: &Path, change: impl FnOnce(&mut GrammarConfig) -> bool) -> Result<bool, String> {
+    let mut config: GrammarConfig = workspace::read_config(root, CONFIG)?;
+    let changed = change(&mut config);
+    if changed {
+        workspace::write_config(root, CONFIG, &config)?;
+    }
+    Ok(changed)
+}
@@ -0,0 +1,152 @@
+use tauri::AppHandle;
+
+/// Activity type declared under `NSUserActivityTypes` in the Info.plist files
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const ACTIVITY_TYPE: &str = "org.stormlightlabs.inkfinite.document";
+/// `userInfo` key carrying the document's `inkfinite://open` link
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const URL_KEY: &str = "url";
+
+/// Advertise the document a window is showing to nearby devices, or stop with `None`.
+///
+/// The activity carries the document's `inkfinite://open?doc=<id>` link; the receiving device
+/// routes it through the same deep-link handler as a clicked link.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn publish(app: &AppHandle, document: Option<&str>) {
+    let activity = document.and_then(|path| {
+        let board = crate::document::read_board(std::path::Path::new(path)).ok()?;
+        let mut link = url::Url::parse(&format!("{}://open", crate::deep_link::SCHEME)).ok()?;
+        link.query_pairs_mut().append_pair("doc", &board.board.id);
+        Some((board.board.name, link.to_string()))
+    });
+
+    let _ = app.run_on_main_thread(move || apple::publish(activity));
+}
+
+/// Accept activities continued from another device; must run at startup, since Handoff may be
+/// what launched the app
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn init(app: &AppHandle) {
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || apple::install(handle));
+}
+
+/// Handoff is an Apple continuity feature; other platforms have nothing to advertise
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub fn publish(_app: &AppHandle, _document: Option<&str>) {}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub fn init(_app: &AppHandle) {}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod apple {
+    use super::{ACTIVITY_TYPE, URL_KEY};
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyClass, AnyObject, Bool, Imp, Sel};
+    use objc2::{msg_send, sel, AnyThread};
+    use objc2_foundation::{NSDictionary, NSString, NSUserActivity};
+    use std::cell::RefCell;
+    use std::sync::{Once, OnceLock};
+    use tauri::AppHandle;
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+    static INSTALL: Once = Once::new();
+
+    thread_local! {
+        /// Activity currently advertised; only touched on the main thread
+        static CURRENT: RefCell<Option<Retained<NSUserActivity>>> = const { RefCell::new(None) };
+    }
+
+    pub fn publish(activity: Option<(String, String)>) {
+        CURRENT.with(|current| {
+            if let Some(previous) = current.borrow_mut().take() {
+                previous.invalidate();
+            }
+            let Some((title, url)) = activity else {
+                return;
+            };
+
+            let activity = NSUserActivity::initWithActivityType(
+                NSUserActivity::alloc(),
+                &NSString::from_str(ACTIVITY_TYPE),
+            );
+            activity.setTitle(Some(&NSString::from_str(&title)));
+            let info = NSDictionary::from_slices(
+                &[&*NSString::from_str(URL_KEY)],
+                &[&*NSString::from_str(&url)],
+            );
+            // Strings are valid property list keys and values
+            unsafe { activity.setUserInfo(Some(info.cast_unchecked())) };
+            activity.setEligibleForHandoff(true);
+            activity.becomeCurrent();
+            *current.borrow_mut() = Some(activity);
+        });
+    }
+
+    /// Teach the app delegate to accept activities continued from another device
+    pub fn install(app: AppHandle) {
+        let _ = APP.set(app);
+        INSTALL.call_once(|| {
+            let Some(delegate) = delegate() else {
+                return;
+            };
+            let class = delegate.class() as *const AnyClass as *mut AnyClass;
+            unsafe {
+                objc2::ffi::class_addMethod(
+                    class,
+                    sel!(application:continueUserActivity:restorationHandler:),
+                    std::mem::transmute::<
+                        extern "C-unwind" fn(
+                            &AnyObject,
+                            Sel,
+                            &AnyObject,
+                            &NSUserActivity,
+                            *mut AnyObject,
+                        ) -> Bool,
+                        Imp,
+                    >(continue_activity),
+                    c"B@:@@@?".as_ptr(),
+                );
+            }
+        });
+    }
+
+    fn delegate() -> Option<Retained<AnyObject>> {
+        #[cfg(target_os = "macos")]
+        let application: Option<Retained<AnyObject>> =
+            unsafe { msg_send![objc2::class!(NSApplication), sharedApplication] };
+        #[cfg(target_os = "ios")]
+        let application: Option<Retained<AnyObject>> =
+            unsafe { msg_send![objc2::class!(UIApplication), sharedApplication] };
+        unsafe { msg_send![&*application?, delegate] }
+    }
+
+    extern "C-unwind" fn continue_activity(
+        _this: &AnyObject,
+        _cmd: Sel,
+        _application: &AnyObject,
+        activity: &NSUserActivity,
+        _restoration: *mut AnyObject,
+    ) -> Bool {
+        if activity.activityType().to_string() != ACTIVITY_TYPE {
+            return Bool::NO;
+        }
+        let url = activity
+            .userInfo()
+            .and_then(|info| {
+                // Published with string keys and values above
+                let info: &NSDictionary<NSString, AnyObject> = unsafe { info.cast_unchecked() };
+                info.objectForKey(&NSString::from_str(URL_KEY))
+            })
+            .and_then(|value| value.downcast::<NSString>().ok())
+            .and_then(|value| url::Url::parse(&value.to_string()).ok());
+
+        match (APP.get(), url) {
+            (Some(app), Some(url)) => {
+                crate::deep_link::open_url(app, &url);
+                Bool::YES
+            }
+            _ => Bool::NO,
+        }
+    }
+}
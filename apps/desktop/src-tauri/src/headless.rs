@@ -0,0 +1,45 @@
+use crate::cancel::CancelToken;
+use inkfinite_core::batch;
+use std::path::Path;
+
+/// Launch flag that runs a job file instead of the app
+const FLAG: &str = "--headless";
+
+/// Exit statuses: every task succeeded, a task failed, or the job could not start
+const SUCCESS: i32 = 0;
+const FAILED: i32 = 1;
+const INVALID: i32 = 2;
+
+/// Run `--headless <job file>` without starting the app or opening a window, returning the
+/// exit status, or `None` when the flag is absent so the app starts normally.
+///
+/// A job file names a workspace and its tasks, run in order:
+/// `{ "workspace": "/srv/notes", "tasks": [{ "task": "migrate" }, { "task": "rebuildIndex" },
+/// { "task": "export", "format": "markdown", "destination": "/srv/export" }] }`. The report is
+/// printed as JSON, and also written to `report` when the job sets it. Locked vault documents
+/// are skipped, and the app's read-only workspace settings are not applied.
+pub fn run(args: &[String]) -> Option<i32> {
+    let at = args.iter().position(|arg| arg == FLAG)?;
+    let Some(path) = args.get(at + 1) else {
+        eprintln!("{} needs a job file", FLAG);
+        return Some(INVALID);
+    };
+    let job = match batch::load(Path::new(path)) {
+        Ok(job) => job,
+        Err(error) => {
+            eprintln!("{}", error);
+            return Some(INVALID);
+        }
+    };
+
+    let report = batch::run(&job, &CancelToken::default());
+    let json = serde_json::to_string_pretty(&report).unwrap_or_default();
+    println!("{}", json);
+    if let Some(path) = &job.report {
+        if let Err(error) = std::fs::write(path, &json) {
+            eprintln!("Failed to write report: {}", error);
+            return Some(FAILED);
+        }
+    }
+    Some(if report.ok { SUCCESS } else { FAILED })
+}
@@ -0,0 +1,687 @@
+use crate::document::{create_id, now_millis, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{paths, read_only, vault, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+const CONFIG: &str = "hooks";
+/// `.inkfinite/logs/hooks.jsonl`, one line per run
+const LOG_FILE: &str = "hooks.jsonl";
+/// Fingerprints of the hooks saved on this device, the only ones that run
+const TRUSTED_KEY: &str = "trustedHooks";
+const DEFAULT_LIMIT: usize = 200;
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+const MAX_TIMEOUT_SECS: u64 = 600;
+/// Bytes of stdout and of stderr kept in the log
+const MAX_OUTPUT: usize = 16 * 1024;
+/// Saves of the same document closer together than this run a hook once
+const SETTLE: Duration = Duration::from_secs(2);
+const POLL: Duration = Duration::from_millis(50);
+/// How long output is awaited once the command has exited
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+/// Variables a hook inherits from the app; everything else in its environment is cleared
+const INHERITED_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "TMPDIR",
+    "SSH_AUTH_SOCK",
+    "SystemRoot",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "TEMP",
+    "TMP",
+    "PATHEXT",
+    "COMSPEC",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    /// After a document save is written
+    OnSave,
+    /// After a document is exported, with the written file as `output`
+    OnExport,
+    /// Before a sync with a paired device; a failing hook stops the sync
+    PreSync,
+}
+
+impl HookEvent {
+    fn as_str(self) -> &'static str {
+        match self {
+            HookEvent::OnSave => "on-save",
+            HookEvent::OnExport => "on-export",
+            HookEvent::PreSync => "pre-sync",
+        }
+    }
+}
+
+/// A hook in `.inkfinite/hooks.json`: when `event` happens, run `command` with `args`.
+///
+/// The command is started directly rather than through a shell. `{{event}}`, `{{workspace}}`,
+/// `{{path}}`, `{{output}}` and `{{peer}}` in `args` are replaced, and are also set as
+/// `INKFINITE_EVENT`, `INKFINITE_WORKSPACE`, `INKFINITE_PATH`, `INKFINITE_OUTPUT` and
+/// `INKFINITE_PEER`. Only a few variables such as `PATH` and `HOME` are inherited from the app.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalHook {
+    /// Assigned when the hook is first saved
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    pub event: HookEvent,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Workspace-relative folder to run in, the workspace itself when omitted
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Only documents under this workspace-relative folder; ignored by `pre-sync`
+    pub folder: Option<String>,
+    /// Seconds before the command is killed; 60 when omitted, at most 600
+    pub timeout_secs: Option<u64>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Hooks {
+    hooks: Vec<ExternalHook>,
+}
+
+/// A hook as listed, with whether it may run on this device
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookStatus {
+    #[serde(flatten)]
+    pub hook: ExternalHook,
+    /// `false` for hooks added or changed outside the app, such as by a sync, until they are
+    /// saved here
+    pub trusted: bool,
+}
+
+/// One run of a hook, as kept in the log
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HookRun {
+    /// Unix milliseconds
+    pub at: i64,
+    pub hook: String,
+    pub event: HookEvent,
+    /// Workspace-relative path of the document
+    pub path: Option<String>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,
+    pub error: Option<String>,
+}
+
+impl HookRun {
+    fn succeeded(&self) -> bool {
+        self.error.is_none() && self.exit_code == Some(0)
+    }
+}
+
+/// What a hook runs for
+#[derive(Clone, Default)]
+struct Trigger {
+    path: Option<PathBuf>,
+    output: Option<PathBuf>,
+    peer: Option<String>,
+}
+
+struct Pending {
+    root: PathBuf,
+    hook: ExternalHook,
+    event: HookEvent,
+    trigger: Trigger,
+    due: Instant,
+}
+
+/// Managed state holding hook runs waiting for their document to settle
+#[derive(Default)]
+pub struct HookQueue {
+    pending: Mutex<Vec<Pending>>,
+    wake: Condvar,
+}
+
+fn load(root: &Path) -> Vec<ExternalHook> {
+    match workspace::read_config::<Hooks>(root, CONFIG) {
+        Ok(hooks) => hooks.hooks,
+        Err(error) => {
+            tracing::warn!(%error, "Failed to read hooks");
+            Vec::new()
+        }
+    }
+}
+
+/// Identifies what a hook runs and where, so any change to it needs trusting again
+fn fingerprint(root: &Path, hook: &ExternalHook) -> String {
+    let value = json!([
+        root.to_string_lossy(),
+        hook.command,
+        hook.args,
+        hook.cwd,
+        hook.env
+    ]);
+    format!("{:x}", Sha256::digest(value.to_string().as_bytes()))
+}
+
+fn trusted(app: &AppHandle) -> BTreeSet<String> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(TRUSTED_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn set_trusted(app: &AppHandle, fingerprint: String, trust: bool) -> Result<(), String> {
+    let mut fingerprints = trusted(app);
+    if trust {
+        fingerprints.insert(fingerprint);
+    } else {
+        fingerprints.remove(&fingerprint);
+    }
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(TRUSTED_KEY, json!(fingerprints));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// Enabled hooks of the open workspace bound to `event` for a document at `path`
+fn matching(
+    app: &AppHandle,
+    event: HookEvent,
+    path: &Path,
+) -> Option<(PathBuf, Vec<ExternalHook>)> {
+    let root = workspace::current_root(app)?;
+    if !path.starts_with(&root)
+        || !path.to_string_lossy().ends_with(DOCUMENT_EXTENSION)
+        || vault::vault_of(path).is_some()
+    {
+        return None;
+    }
+    let relative = workspace::relative_path(&root, path);
+    let hooks = load(&root)
+        .into_iter()
+        .filter(|hook| hook.enabled && hook.event == event)
+        .filter(|hook| {
+            hook.folder
+                .as_deref()
+                .map(|folder| folder.trim_matches('/'))
+                .filter(|folder| !folder.is_empty())
+                .is_none_or(|folder| {
+                    relative
+                        .strip_prefix(folder)
+                        .is_some_and(|rest| rest.starts_with('/'))
+                })
+        })
+        .collect();
+    Some((root, hooks))
+}
+
+fn enqueue(app: &AppHandle, event: HookEvent, trigger: Trigger) {
+    let Some(path) = trigger.path.as_deref() else {
+        return;
+    };
+    let Some((root, hooks)) = matching(app, event, path) else {
+        return;
+    };
+    if hooks.is_empty() {
+        return;
+    }
+    let queue = app.state::<HookQueue>();
+    let Ok(mut pending) = queue.pending.lock() else {
+        return;
+    };
+    let due = Instant::now() + SETTLE;
+    for hook in hooks {
+        let waiting = pending.iter_mut().find(|waiting| {
+            waiting.hook.id == hook.id
+                && waiting.trigger.path == trigger.path
+                && waiting.trigger.output == trigger.output
+        });
+        match waiting {
+            Some(waiting) => {
+                waiting.hook = hook;
+                waiting.due = due;
+            }
+            None => pending.push(Pending {
+                root: root.clone(),
+                hook,
+                event,
+                trigger: trigger.clone(),
+                due,
+            }),
+        }
+    }
+    queue.wake.notify_all();
+}
+
+/// Queue the `on-save` hooks for a document that was just written
+pub fn document_saved(app: &AppHandle, path: &Path) {
+    enqueue(
+        app,
+        HookEvent::OnSave,
+        Trigger {
+            path: Some(path.to_path_buf()),
+            ..Trigger::default()
+        },
+    );
+}
+
+/// Queue the `on-export` hooks for a document exported to `output`
+pub fn document_exported(app: &AppHandle, path: &Path, output: &Path) {
+    enqueue(
+        app,
+        HookEvent::OnExport,
+        Trigger {
+            path: Some(path.to_path_buf()),
+            output: Some(output.to_path_buf()),
+            peer: None,
+        },
+    );
+}
+
+/// Run the `pre-sync` hooks of `root` one after another, failing on the first that does not
+/// succeed
+pub fn before_sync(app: &AppHandle, root: &Path, peer: &str) -> Result<(), String> {
+    let trigger = Trigger {
+        peer: Some(peer.to_string()),
+        ..Trigger::default()
+    };
+    let trusted = trusted(app);
+    for hook in load(root)
+        .into_iter()
+        .filter(|hook| hook.enabled && hook.event == HookEvent::PreSync)
+    {
+        let run = execute(root, &hook, HookEvent::PreSync, &trigger, &trusted);
+        if !run.succeeded() {
+            let reason = run.error.unwrap_or_else(|| match run.exit_code {
+                Some(code) => format!("exited with status {}", code),
+                None => "was stopped by a signal".to_string(),
+            });
+            return Err(format!("Pre-sync hook {} failed: {}", hook.name, reason));
+        }
+    }
+    Ok(())
+}
+
+/// Run hooks as they come due, one at a time
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let queue = app.state::<HookQueue>();
+        let Ok(mut pending) = queue.pending.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        let next = pending.iter().map(|run| run.due).min();
+        let wait = next.map_or(Duration::from_secs(3600), |due| {
+            due.saturating_duration_since(now)
+        });
+        if !wait.is_zero() {
+            let _ = queue.wake.wait_timeout(pending, wait);
+            continue;
+        }
+        let due: Vec<Pending> = {
+            let (due, waiting) = pending.drain(..).partition(|run| run.due <= now);
+            *pending = waiting;
+            due
+        };
+        drop(pending);
+
+        let trusted = trusted(&app);
+        for run in due {
+            let path = run.trigger.path.as_deref();
+            let modified = path.and_then(modified_at);
+            let result = execute(&run.root, &run.hook, run.event, &run.trigger, &trusted);
+            // A formatter rewrote the document, so windows showing it reload
+            if let Some(path) = path {
+                if result.succeeded() && modified_at(path) != modified {
+                    events::file_changed(&app, path, ChangeKind::Modified);
+                }
+            }
+        }
+    });
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Run a hook to completion or its timeout and log the result
+fn execute(
+    root: &Path,
+    hook: &ExternalHook,
+    event: HookEvent,
+    trigger: &Trigger,
+    trusted: &BTreeSet<String>,
+) -> HookRun {
+    let started = Instant::now();
+    let mut run = HookRun {
+        at: now_millis(),
+        hook: hook.id.clone(),
+        event,
+        path: trigger
+            .path
+            .as_deref()
+            .map(|path| workspace::relative_path(root, path)),
+        exit_code: None,
+        duration_ms: 0,
+        stdout: String::new(),
+        stderr: String::new(),
+        timed_out: false,
+        error: None,
+    };
+    let result = if trusted.contains(&fingerprint(root, hook)) {
+        spawn(root, hook, event, trigger, &mut run)
+    } else {
+        Err("Hook is not trusted on this device; save it in settings to allow it".to_string())
+    };
+    run.error = result.err();
+    run.duration_ms = started.elapsed().as_millis() as u64;
+
+    if let Some(error) = &run.error {
+        tracing::warn!(%error, hook = %hook.name, "Hook failed");
+    } else if run.exit_code != Some(0) {
+        tracing::warn!(code = ?run.exit_code, hook = %hook.name, "Hook exited with an error");
+    }
+    if let Err(error) = append(root, &run) {
+        tracing::warn!(%error, "Failed to write hook log");
+    }
+    run
+}
+
+fn spawn(
+    root: &Path,
+    hook: &ExternalHook,
+    event: HookEvent,
+    trigger: &Trigger,
+    run: &mut HookRun,
+) -> Result<(), String> {
+    let dir = match hook.cwd.as_deref().filter(|cwd| !cwd.trim().is_empty()) {
+        Some(cwd) => root.join(contained(cwd)?),
+        None => root.to_path_buf(),
+    };
+    let display = |path: &Option<PathBuf>| {
+        path.as_deref()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let values = [
+        ("event", event.as_str().to_string()),
+        ("workspace", root.to_string_lossy().to_string()),
+        ("path", display(&trigger.path)),
+        ("output", display(&trigger.output)),
+        ("peer", trigger.peer.clone().unwrap_or_default()),
+    ];
+    let args = hook.args.iter().map(|arg| {
+        values.iter().fold(arg.clone(), |arg, (key, value)| {
+            arg.replace(&format!("{{{{{}}}}}", key), value)
+        })
+    });
+
+    let mut command = Command::new(&hook.command);
+    command
+        .args(args)
+        .current_dir(&dir)
+        .env_clear()
+        .envs(
+            INHERITED_ENV
+                .iter()
+                .filter_map(|key| std::env::var_os(key).map(|value| (*key, value))),
+        )
+        .envs(&hook.env)
+        .envs(
+            values
+                .iter()
+                .map(|(key, value)| (format!("INKFINITE_{}", key.to_uppercase()), value.as_str())),
+        )
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", hook.command, e))?;
+    // Drained on their own threads so the command never stalls on a full pipe while it is polled
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let timeout = Duration::from_secs(
+        hook.timeout_secs
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS),
+    );
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("Failed to run {}: {}", hook.command, e))?
+        {
+            break Some(status);
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(POLL);
+    };
+    // A process the command left running in the background may hold the pipes open
+    let collect = |pipe: Option<mpsc::Receiver<Vec<u8>>>| {
+        let bytes = pipe
+            .and_then(|pipe| pipe.recv_timeout(OUTPUT_GRACE).ok())
+            .unwrap_or_default();
+        String::from_utf8_lossy(&bytes).to_string()
+    };
+    run.stdout = collect(stdout);
+    run.stderr = collect(stderr);
+
+    match status {
+        Some(status) => {
+            run.exit_code = status.code();
+            Ok(())
+        }
+        None => {
+            run.timed_out = true;
+            Err(format!("Timed out after {} seconds", timeout.as_secs()))
+        }
+    }
+}
+
+/// Read a pipe to the end on its own thread, keeping only the first [`MAX_OUTPUT`] bytes
+fn drain(mut pipe: impl Read + Send + 'static) -> mpsc::Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe
+            .by_ref()
+            .take(MAX_OUTPUT as u64)
+            .read_to_end(&mut bytes);
+        let _ = std::io::copy(&mut pipe, &mut std::io::sink());
+        let _ = sender.send(bytes);
+    });
+    receiver
+}
+
+fn contained(relative: &str) -> Result<&Path, String> {
+    let path = Path::new(relative.trim_start_matches(['/', '\\']));
+    if path
+        .components()
+        .any(|part| !matches!(part, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("Invalid path: {}", relative));
+    }
+    Ok(path)
+}
+
+fn append(root: &Path, run: &HookRun) -> Result<(), String> {
+    read_only::ensure_writable(root)?;
+    let dir = workspace::internal_dir(root).join("logs");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log folder: {}", e))?;
+    let line =
+        serde_json::to_string(run).map_err(|e| format!("Failed to serialize entry: {}", e))?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(LOG_FILE))
+        .map_err(|e| format!("Failed to open hook log: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write hook log: {}", e))
+}
+
+/// Hooks of a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_hooks(app: AppHandle, workspace: String) -> Result<Vec<HookStatus>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let trusted = trusted(&app);
+    Ok(workspace::read_config::<Hooks>(&root, CONFIG)?
+        .hooks
+        .into_iter()
+        .map(|hook| HookStatus {
+            trusted: trusted.contains(&fingerprint(&root, &hook)),
+            hook,
+        })
+        .collect())
+}
+
+/// Add a hook, or replace the one with the same id, and trust it on this device; returns the
+/// hook with its id assigned
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_hook(
+    app: AppHandle,
+    workspace: String,
+    mut hook: ExternalHook,
+) -> Result<ExternalHook, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    if hook.name.trim().is_empty() {
+        return Err("Invalid hook: a name is required".into());
+    }
+    if hook.command.trim().is_empty() {
+        return Err("Invalid hook: a command is required".into());
+    }
+    if let Some(cwd) = &hook.cwd {
+        contained(cwd)?;
+    }
+    if hook
+        .timeout_secs
+        .is_some_and(|secs| secs == 0 || secs > MAX_TIMEOUT_SECS)
+    {
+        return Err(format!(
+            "Invalid hook: the timeout must be between 1 and {} seconds",
+            MAX_TIMEOUT_SECS
+        )
+        .into());
+    }
+    let mut hooks: Hooks = workspace::read_config(&root, CONFIG)?;
+    if hook.id.is_empty() {
+        hook.id = create_id("hook");
+    }
+    match hooks
+        .hooks
+        .iter_mut()
+        .find(|existing| existing.id == hook.id)
+    {
+        Some(existing) => *existing = hook.clone(),
+        None => hooks.hooks.push(hook.clone()),
+    }
+    workspace::write_config(&root, CONFIG, &hooks)?;
+    set_trusted(&app, fingerprint(&root, &hook), true)?;
+    Ok(hook)
+}
+
+/// Delete a hook; runs already queued for it still happen. Returns `false` when there was no
+/// such hook.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn remove_hook(app: AppHandle, workspace: String, id: String) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let mut hooks: Hooks = workspace::read_config(&root, CONFIG)?;
+    let Some(index) = hooks.hooks.iter().position(|hook| hook.id == id) else {
+        return Ok(false);
+    };
+    let hook = hooks.hooks.remove(index);
+    workspace::write_config(&root, CONFIG, &hooks)?;
+    set_trusted(&app, fingerprint(&root, &hook), false)?;
+    Ok(true)
+}
+
+/// Run a hook once now, for the document at `path` when given, ignoring its event and folder.
+/// The run is logged.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn run_hook(
+    app: AppHandle,
+    workspace: String,
+    id: String,
+    path: Option<String>,
+) -> Result<HookRun, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let path = path
+        .map(|path| paths::check(&app, &path, paths::Scope::Read))
+        .transpose()?;
+    let hook = load(&root)
+        .into_iter()
+        .find(|hook| hook.id == id)
+        .ok_or_else(|| Error::not_found("Hook not found").with_context(&id))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let trigger = Trigger {
+            path,
+            ..Trigger::default()
+        };
+        execute(&root, &hook, hook.event, &trigger, &trusted(&app))
+    })
+    .await
+    .map_err(|e| format!("Hook task failed: {}", e).into())
+}
+
+/// Hook runs, newest first, optionally for one hook; `limit` defaults to 200
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_hook_runs(
+    app: AppHandle,
+    workspace: String,
+    hook: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<HookRun>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let file = match fs::File::open(workspace::internal_dir(&root).join("logs").join(LOG_FILE)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::io("Failed to read hook log", e)),
+    };
+    // Lines cut short by a crash are skipped
+    let mut entries: Vec<HookRun> = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<HookRun>(&line).ok())
+        .filter(|entry| hook.as_ref().is_none_or(|hook| &entry.hook == hook))
+        .collect();
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(entries)
+}
@@ -0,0 +1,188 @@
+use crate::error::Error;
+use crate::workspace;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const HOTKEYS_KEY: &str = "hotkeys";
+
+/// OS-level shortcuts, stored in the app settings store; `None` disables an action
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HotkeySettings {
+    pub quick_capture: Option<String>,
+    pub toggle_window: Option<String>,
+    pub screenshot: Option<String>,
+}
+
+impl Default for HotkeySettings {
+    fn default() -> Self {
+        HotkeySettings {
+            quick_capture: Some("CmdOrControl+Shift+Space".to_string()),
+            toggle_window: Some("CmdOrControl+Shift+I".to_string()),
+            screenshot: Some("CmdOrControl+Shift+2".to_string()),
+        }
+    }
+}
+
+impl HotkeySettings {
+    /// Each action with its shortcut
+    pub fn bindings(&self) -> [(&'static str, Option<&str>); 3] {
+        [
+            ("quick-capture", self.quick_capture.as_deref()),
+            ("toggle-window", self.toggle_window.as_deref()),
+            ("screenshot", self.screenshot.as_deref()),
+        ]
+    }
+}
+
+/// Outcome of registering one action's shortcut
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyStatus {
+    pub action: String,
+    pub shortcut: String,
+    pub registered: bool,
+    /// Why registration failed, typically because another app owns the shortcut
+    pub error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyState {
+    pub settings: HotkeySettings,
+    pub status: Vec<HotkeyStatus>,
+}
+
+pub fn load_settings(app: &AppHandle) -> HotkeySettings {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(HOTKEYS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Read the configured hotkeys and whether each one is currently active
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_hotkeys(app: AppHandle) -> HotkeyState {
+    let settings = load_settings(&app);
+    let status = current_status(&app, &settings);
+    HotkeyState { settings, status }
+}
+
+/// Save new hotkeys and re-register them, reporting any that could not be taken
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_hotkeys(app: AppHandle, settings: HotkeySettings) -> Result<Vec<HotkeyStatus>, Error> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(&settings)
+        .map_err(|e| format!("Failed to serialize hotkeys: {}", e))?;
+    store.set(HOTKEYS_KEY, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(register_all(&app))
+}
+
+/// Register every configured hotkey, replacing any previous registrations.
+///
+/// A shortcut that is invalid or already taken is skipped and reported through a
+/// `hotkey:unavailable` event rather than failing the others.
+#[cfg(desktop)]
+pub fn register_all(app: &AppHandle) -> Vec<HotkeyStatus> {
+    use tauri::Emitter;
+    use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+    let shortcuts = app.global_shortcut();
+    let _ = shortcuts.unregister_all();
+
+    let settings = load_settings(app);
+    let mut status = Vec::new();
+    for (action, shortcut) in settings.bindings() {
+        let Some(shortcut) = shortcut.filter(|s| !s.trim().is_empty()) else {
+            continue;
+        };
+        let result = shortcuts
+            .on_shortcut(shortcut, move |app, _, event| {
+                if event.state() == ShortcutState::Pressed {
+                    trigger(app, action);
+                }
+            })
+            .map_err(|e| e.to_string());
+
+        let entry = HotkeyStatus {
+            action: action.to_string(),
+            shortcut: shortcut.to_string(),
+            registered: result.is_ok(),
+            error: result.err(),
+        };
+        if !entry.registered {
+            let _ = app.emit("hotkey:unavailable", entry.clone());
+        }
+        status.push(entry);
+    }
+    status
+}
+
+/// Global shortcuts are not available on mobile builds
+#[cfg(mobile)]
+pub fn register_all(_app: &AppHandle) -> Vec<HotkeyStatus> {
+    Vec::new()
+}
+
+#[cfg(desktop)]
+fn current_status(app: &AppHandle, settings: &HotkeySettings) -> Vec<HotkeyStatus> {
+    use tauri_plugin_global_shortcut::GlobalShortcutExt;
+
+    settings
+        .bindings()
+        .into_iter()
+        .filter_map(|(action, shortcut)| Some((action, shortcut?)))
+        .map(|(action, shortcut)| {
+            let registered = app.global_shortcut().is_registered(shortcut);
+            HotkeyStatus {
+                action: action.to_string(),
+                shortcut: shortcut.to_string(),
+                registered,
+                error: (!registered).then(|| "Shortcut is not registered".to_string()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(mobile)]
+fn current_status(_app: &AppHandle, _settings: &HotkeySettings) -> Vec<HotkeyStatus> {
+    Vec::new()
+}
+
+/// Run a hotkey action; every press is also emitted as `hotkey:<action>` for the frontend
+#[cfg(desktop)]
+fn trigger(app: &AppHandle, action: &str) {
+    use crate::{screenshot, tray};
+    use tauri::Emitter;
+
+    match action {
+        "quick-capture" => tray::show_main_window(app),
+        "toggle-window" => {
+            tray::toggle_main_window(app);
+            let _ = tray::refresh_tray(app.clone());
+        }
+        "screenshot" => {
+            let app = app.clone();
+            std::thread::spawn(move || {
+                match screenshot::capture(&app, screenshot::CaptureMode::Region, None) {
+                    Ok(asset) => {
+                        let _ = app.emit("screenshot:captured", asset);
+                    }
+                    Err(error) => {
+                        let _ = app.emit("screenshot:failed", error);
+                    }
+                }
+            });
+        }
+        _ => {}
+    }
+    let _ = app.emit(&format!("hotkey:{}", action), ());
+}
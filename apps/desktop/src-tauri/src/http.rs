@@ -0,0 +1,92 @@
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+const USER_AGENT: &str = concat!("Inkfinite/", env!("CARGO_PKG_VERSION"));
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Shared blocking HTTP client with the app's user agent and a global timeout
+pub fn agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .user_agent(USER_AGENT)
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .into()
+}
+
+/// Client for large downloads: the same user agent and connection timeouts, but no limit on how
+/// long the body takes
+pub fn download_agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .user_agent(USER_AGENT)
+        .timeout_connect(Some(TIMEOUT))
+        .timeout_recv_response(Some(TIMEOUT))
+        .build()
+        .into()
+}
+
+/// A TCP connection, optionally wrapped in TLS
+pub enum Stream {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Stream {
+    pub fn tcp(&self) -> &TcpStream {
+        match self {
+            Stream::Plain(tcp) => tcp,
+            Stream::Tls(tls) => &tls.sock,
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(tcp) => tcp.read(buf),
+            Stream::Tls(tls) => tls.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(tcp) => tcp.write(buf),
+            Stream::Tls(tls) => tls.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(tcp) => tcp.flush(),
+            Stream::Tls(tls) => tls.flush(),
+        }
+    }
+}
+
+/// Wrap `tcp` in TLS for `host`, checking the certificate against the Mozilla roots
+pub fn tls(host: &str, tcp: TcpStream) -> Result<StreamOwned<ClientConnection, TcpStream>, String> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("Failed to set up TLS: {}", e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    let name =
+        ServerName::try_from(host.to_string()).map_err(|e| format!("Invalid host name: {}", e))?;
+    let connection = ClientConnection::new(Arc::new(config), name)
+        .map_err(|e| format!("Failed to set up TLS: {}", e))?;
+    let mut stream = StreamOwned::new(connection, tcp);
+    stream
+        .conn
+        .complete_io(&mut stream.sock)
+        .map_err(|e| format!("TLS handshake failed: {}", e))?;
+    Ok(stream)
+}
@@ -0,0 +1,89 @@
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{paths, workspace};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_store::StoreExt;
+
+const INBOX_KEY: &str = "inboxDocument";
+/// Document created in the workspace root when no inbox has been chosen
+const DEFAULT_INBOX: &str = "Inbox";
+
+/// Inbox document quick captures are appended to, if one can be resolved
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_inbox_document(app: AppHandle) -> Option<String> {
+    inbox_path(&app).map(|path| path.to_string_lossy().to_string())
+}
+
+/// Choose the inbox document, or reset to the workspace default with `None`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_inbox_document(app: AppHandle, path: Option<String>) -> Result<(), Error> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    match path {
+        Some(path) => {
+            paths::check(&app, &path, paths::Scope::Write)?;
+            store.set(INBOX_KEY, path)
+        }
+        None => {
+            store.delete(INBOX_KEY);
+        }
+    }
+    Ok(store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?)
+}
+
+/// Append text to the inbox document without opening the main window, returning its path
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn quick_capture(app: AppHandle, text: String) -> Result<String, Error> {
+    Ok(capture(&app, &text).map(|path| path.to_string_lossy().to_string())?)
+}
+
+/// Append a timestamped Markdown block to the inbox document, creating it if needed
+pub fn capture(app: &AppHandle, text: &str) -> Result<PathBuf, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Nothing to capture".to_string());
+    }
+    let path = inbox_path(app)
+        .ok_or_else(|| "No inbox document is set and no workspace is open".to_string())?;
+
+    let kind = if path.exists() {
+        ChangeKind::Modified
+    } else {
+        ChangeKind::Created
+    };
+    let mut board = if path.exists() {
+        document::read_board(&path)?
+    } else {
+        BoardFile::new(&document::document_stem(&path))
+    };
+    let heading = chrono::Local::now().format("%Y-%m-%d %H:%M");
+    board.push_markdown(&format!("**{}**\n\n{}", heading, text));
+    board.board.updated_at = document::now_millis();
+    document::write_board(&path, &board)?;
+    events::file_changed(app, &path, kind);
+
+    let _ = app.emit("inbox:captured", path.to_string_lossy());
+    Ok(path)
+}
+
+fn inbox_path(app: &AppHandle) -> Option<PathBuf> {
+    let chosen = app
+        .store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(INBOX_KEY))
+        .and_then(|value| value.as_str().map(PathBuf::from))
+        .filter(|path| path.parent().is_some_and(Path::is_dir));
+
+    chosen.or_else(|| {
+        workspace::current_root(app)
+            .map(|root| root.join(format!("{}{}", DEFAULT_INBOX, DOCUMENT_EXTENSION)))
+    })
+}
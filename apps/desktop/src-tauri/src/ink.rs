@@ -0,0 +1,288 @@
+use crate::document::{self, BoardFile, ShapeRecord};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{ocr, paths, read_only, search, tools, workspace};
+use image::{GrayImage, Luma};
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Stroke prop holding the text recognized from it
+const RECOGNIZED_PROP: &str = "recognizedText";
+/// Pen width in the rasterized image; Tesseract reads thin, dark lines best
+const PEN_PIXELS: f64 = 6.0;
+const MARGIN_PIXELS: f64 = 32.0;
+/// Longest side of the rasterized image
+const MAX_PIXELS: f64 = 4096.0;
+/// Space between the ink and an inserted text shape
+const TEXT_GAP: f64 = 16.0;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InkText {
+    pub page_id: String,
+    pub text: String,
+    /// Ids of the strokes the text was read from
+    pub strokes: Vec<String>,
+    /// Text shape added below the ink, when one was asked for
+    pub shape_id: Option<String>,
+}
+
+struct Stroke {
+    id: String,
+    /// World-space points
+    points: Vec<(f64, f64)>,
+    size: f64,
+}
+
+fn stroke(shape: &ShapeRecord) -> Option<Stroke> {
+    let points = shape
+        .props
+        .get("points")?
+        .as_array()?
+        .iter()
+        .filter_map(|p| Some((shape.x + p.get(0)?.as_f64()?, shape.y + p.get(1)?.as_f64()?)))
+        .collect::<Vec<_>>();
+    let size = shape
+        .props
+        .get("brush")
+        .and_then(|brush| brush.get("size"))
+        .and_then(Value::as_f64)
+        .filter(|size| *size > 0.0)
+        .unwrap_or(4.0);
+    (!points.is_empty()).then(|| Stroke {
+        id: shape.id.clone(),
+        points,
+        size,
+    })
+}
+
+/// Strokes to recognize grouped by page, in paint order; every stroke when `ids` is empty
+fn strokes_by_page(
+    board: &BoardFile,
+    ids: &[String],
+) -> Result<Vec<(String, Vec<Stroke>)>, String> {
+    if let Some(missing) = ids.iter().find(|id| {
+        board
+            .doc
+            .shapes
+            .get(id.as_str())
+            .and_then(|shape| shape.get("type"))
+            .and_then(Value::as_str)
+            != Some("stroke")
+    }) {
+        return Err(format!("Stroke not found: {}", missing));
+    }
+    let mut pages = Vec::new();
+    for page in board.pages() {
+        let strokes: Vec<Stroke> = board
+            .page_shapes(&page)
+            .iter()
+            .filter(|shape| shape.kind == "stroke")
+            .filter(|shape| ids.is_empty() || ids.contains(&shape.id))
+            .filter_map(stroke)
+            .collect();
+        if !strokes.is_empty() {
+            pages.push((page.id, strokes));
+        }
+    }
+    Ok(pages)
+}
+
+/// World-space bounds of the strokes as `(min_x, min_y, max_x, max_y)`
+fn bounds(strokes: &[Stroke]) -> (f64, f64, f64, f64) {
+    strokes
+        .iter()
+        .flat_map(|stroke| {
+            let pad = stroke.size / 2.0;
+            stroke.points.iter().map(move |&(x, y)| (x, y, pad))
+        })
+        .fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(min_x, min_y, max_x, max_y), (x, y, pad)| {
+                (
+                    min_x.min(x - pad),
+                    min_y.min(y - pad),
+                    max_x.max(x + pad),
+                    max_y.max(y + pad),
+                )
+            },
+        )
+}
+
+fn dot(image: &mut GrayImage, cx: f64, cy: f64, radius: f64) {
+    let (width, height) = image.dimensions();
+    let x0 = (cx - radius).floor().max(0.0) as u32;
+    let y0 = (cy - radius).floor().max(0.0) as u32;
+    let x1 = ((cx + radius).ceil() as u32).min(width.saturating_sub(1));
+    let y1 = ((cy + radius).ceil() as u32).min(height.saturating_sub(1));
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            let (dx, dy) = (x as f64 - cx, y as f64 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                image.put_pixel(x, y, Luma([0]));
+            }
+        }
+    }
+}
+
+/// Draw the strokes black on white, scaled so the pen is about `PEN_PIXELS` wide
+fn rasterize(strokes: &[Stroke]) -> GrayImage {
+    let (min_x, min_y, max_x, max_y) = bounds(strokes);
+    let pen = strokes.iter().map(|stroke| stroke.size).sum::<f64>() / strokes.len() as f64;
+    let longest = (max_x - min_x).max(max_y - min_y).max(1.0);
+    let scale = (PEN_PIXELS / pen).min((MAX_PIXELS - 2.0 * MARGIN_PIXELS) / longest);
+    let width = ((max_x - min_x) * scale + 2.0 * MARGIN_PIXELS).ceil() as u32;
+    let height = ((max_y - min_y) * scale + 2.0 * MARGIN_PIXELS).ceil() as u32;
+    let mut image = GrayImage::from_pixel(width, height, Luma([255]));
+
+    let project = |(x, y): (f64, f64)| {
+        (
+            (x - min_x) * scale + MARGIN_PIXELS,
+            (y - min_y) * scale + MARGIN_PIXELS,
+        )
+    };
+    for stroke in strokes {
+        let radius = (stroke.size * scale / 2.0).max(1.0);
+        let mut last = project(stroke.points[0]);
+        dot(&mut image, last.0, last.1, radius);
+        for &point in &stroke.points[1..] {
+            let next = project(point);
+            let length = (next.0 - last.0).hypot(next.1 - last.1);
+            // Dots half a radius apart draw a solid line
+            let steps = (length / (radius / 2.0)).ceil().max(1.0) as usize;
+            for step in 1..=steps {
+                let t = step as f64 / steps as f64;
+                dot(
+                    &mut image,
+                    last.0 + (next.0 - last.0) * t,
+                    last.1 + (next.1 - last.1) * t,
+                    radius,
+                );
+            }
+            last = next;
+        }
+    }
+    image
+}
+
+fn recognize(tesseract: &Path, strokes: &[Stroke], language: &str) -> Result<String, String> {
+    let scratch = tools::scratch_dir("ink")?;
+    let result = (|| {
+        let image = scratch.join("ink.png");
+        rasterize(strokes)
+            .save(&image)
+            .map_err(|e| format!("Failed to write ink image: {}", e))?;
+        ocr::recognize(tesseract, &image, language)
+    })();
+    let _ = std::fs::remove_dir_all(&scratch);
+    result
+}
+
+/// Text shape placed under the ink's bounds
+fn text_shape(page_id: &str, strokes: &[Stroke], text: &str) -> ShapeRecord {
+    let (min_x, _, max_x, max_y) = bounds(strokes);
+    ShapeRecord {
+        id: document::create_id("shape"),
+        kind: "text".to_string(),
+        page_id: page_id.to_string(),
+        x: min_x,
+        y: max_y + TEXT_GAP,
+        rot: 0.0,
+        props: json!({
+            "text": text,
+            "fontSize": 16,
+            "fontFamily": "Inter",
+            "color": "#1f2933",
+            "w": (max_x - min_x).max(200.0),
+        }),
+    }
+}
+
+/// All text recognized from the board's strokes, each block once, in page order
+fn ink_text(board: &BoardFile) -> String {
+    let mut blocks: Vec<&str> = Vec::new();
+    for page in board.pages() {
+        for id in &page.shape_ids {
+            let text = board
+                .doc
+                .shapes
+                .get(id)
+                .and_then(|shape| shape.get("props"))
+                .and_then(|props| props.get(RECOGNIZED_PROP))
+                .and_then(Value::as_str)
+                .filter(|text| !text.trim().is_empty());
+            if let Some(text) = text.filter(|text| !blocks.contains(text)) {
+                blocks.push(text);
+            }
+        }
+    }
+    blocks.join("\n\n")
+}
+
+/// Recognize handwriting in a document's ink strokes with Tesseract, on the device. The text is
+/// stored on the strokes, indexed so workspace search finds the document by it, and, with
+/// `insert`, added below the ink as a text shape. Strokes on each page are read as one block;
+/// every stroke in the document is read when `strokes` is empty.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn recognize_ink(
+    app: AppHandle,
+    path: String,
+    strokes: Vec<String>,
+    language: Option<String>,
+    insert: Option<bool>,
+) -> Result<Vec<InkText>, Error> {
+    let resolved = paths::check(&app, &path, paths::Scope::Write)?;
+    read_only::ensure_writable(&resolved)?;
+    let language = ocr::language_code(language)?;
+    let tesseract = ocr::tesseract(&app)?;
+
+    let mut board = document::read_board(&resolved)?;
+    let pages = strokes_by_page(&board, &strokes)?;
+    if pages.is_empty() {
+        return Err("Invalid document: it has no ink strokes to recognize".into());
+    }
+
+    let mut results = Vec::new();
+    for (page_id, strokes) in pages {
+        let text = recognize(&tesseract, &strokes, &language)?;
+        let ids: Vec<String> = strokes.iter().map(|stroke| stroke.id.clone()).collect();
+        for id in &ids {
+            if let Some(props) = board
+                .doc
+                .shapes
+                .get_mut(id)
+                .and_then(|shape| shape.get_mut("props"))
+                .and_then(Value::as_object_mut)
+            {
+                props.insert(RECOGNIZED_PROP.to_string(), json!(text));
+            }
+        }
+        let shape_id = match insert.unwrap_or(false) && !text.is_empty() {
+            true => {
+                let shape = text_shape(&page_id, &strokes, &text);
+                let id = shape.id.clone();
+                board.add_shape(shape);
+                Some(id)
+            }
+            false => None,
+        };
+        results.push(InkText {
+            page_id,
+            text,
+            strokes: ids,
+            shape_id,
+        });
+    }
+
+    board.board.updated_at = document::now_millis();
+    document::write_board(&resolved, &board)?;
+    events::file_changed(&app, &resolved, ChangeKind::Modified);
+    let root = workspace::current_root(&app).filter(|root| resolved.starts_with(root));
+    if let Some(root) = root {
+        search::index_text(&root, &resolved, "ink", &ink_text(&board))?;
+        events::index_updated(&app, &resolved, "ink");
+    }
+    Ok(results)
+}
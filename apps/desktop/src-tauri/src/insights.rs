@@ -0,0 +1,330 @@
+use crate::catalog::{self, Database};
+use crate::document;
+use crate::error::Error;
+use crate::{archive, paths, vault, workspace};
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, TimeZone};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Silent reading speed of an adult reader
+const WORDS_PER_MINUTE: f64 = 230.0;
+const DEFAULT_STALE_MONTHS: u32 = 6;
+const DEFAULT_WEEKS: u32 = 26;
+/// Longest range returned week by week
+const MAX_WEEKS: u32 = 520;
+const DEFAULT_LIMIT: usize = 100;
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentInsight {
+    /// Workspace-relative path
+    pub path: String,
+    pub name: String,
+    pub word_count: u64,
+    /// Estimated minutes to read the whole document
+    pub read_minutes: f64,
+    /// Epoch milliseconds
+    pub created: i64,
+    /// Last saved, in epoch milliseconds
+    pub modified: i64,
+    /// Epoch milliseconds; `None` when never opened since opens were first recorded
+    pub last_opened: Option<i64>,
+    pub open_count: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekCount {
+    /// `YYYY-MM-DD` of the week's Monday, local time
+    pub week: String,
+    pub count: u64,
+    /// Running total up to and including the week
+    pub total: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagWeek {
+    /// `YYYY-MM-DD` of the week's Monday, local time
+    pub week: String,
+    /// Tags first used by a document created that week
+    pub added: Vec<String>,
+    /// Distinct tags in use by the end of the week
+    pub total: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceInsights {
+    pub documents: u64,
+    pub words: u64,
+    pub read_minutes: f64,
+    pub tags: u64,
+    /// Documents not saved or opened in the last six months, outside the archive
+    pub stale: u64,
+    pub created_per_week: Vec<WeekCount>,
+    pub tag_growth: Vec<TagWeek>,
+}
+
+fn read_minutes(words: u64) -> f64 {
+    (words as f64 / WORDS_PER_MINUTE * 10.0).round() / 10.0
+}
+
+/// Monday of the local week containing `millis`
+fn week_of(millis: i64) -> Option<NaiveDate> {
+    let day = Local.timestamp_millis_opt(millis).single()?.date_naive();
+    Some(day - Duration::days(day.weekday().num_days_from_monday() as i64))
+}
+
+/// Epoch milliseconds `months` before now
+fn months_ago(months: u32) -> i64 {
+    let now = Local::now();
+    now.checked_sub_months(Months::new(months))
+        .unwrap_or(now)
+        .timestamp_millis()
+}
+
+const SELECT_INSIGHTS: &str = "SELECT d.path, d.name, d.word_count, d.created, d.modified,
+        o.last_opened, COALESCE(o.count, 0)
+    FROM documents d LEFT JOIN opens o ON o.id = d.id";
+
+fn insight(row: &rusqlite::Row) -> rusqlite::Result<DocumentInsight> {
+    let word_count: u64 = row.get(2)?;
+    Ok(DocumentInsight {
+        path: row.get(0)?,
+        name: row.get(1)?,
+        word_count,
+        read_minutes: read_minutes(word_count),
+        created: row.get(3)?,
+        modified: row.get(4)?,
+        last_opened: row.get(5)?,
+        open_count: row.get(6)?,
+    })
+}
+
+/// Documents not saved or opened since `cutoff`, least recently touched first, leaving out
+/// the archive folder
+fn stale(db: &Database, root: &Path, cutoff: i64) -> Result<Vec<DocumentInsight>, String> {
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let mut statement = db
+        .prepare(&format!(
+            "{} WHERE MAX(d.modified, COALESCE(o.last_opened, 0)) < ?1
+             ORDER BY MAX(d.modified, COALESCE(o.last_opened, 0))",
+            SELECT_INSIGHTS
+        ))
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let folder = archive::folder(root);
+    let rows = statement
+        .query_map([cutoff], insight)
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    Ok(rows
+        .flatten()
+        .filter(|insight| !archive::is_archived(&folder, &insight.path))
+        .collect())
+}
+
+/// Weeks from the Monday `weeks - 1` weeks back through this week
+fn week_range(weeks: u32) -> Vec<NaiveDate> {
+    let today = Local::now().date_naive();
+    let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    (0..weeks as i64)
+        .rev()
+        .map(|back| this_week - Duration::weeks(back))
+        .collect()
+}
+
+fn summarize(db: &Database, root: &Path, weeks: u32) -> Result<WorkspaceInsights, String> {
+    let stale_count = stale(db, root, months_ago(DEFAULT_STALE_MONTHS))?.len() as u64;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let (documents, words): (u64, u64) = db
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(word_count), 0) FROM documents",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+
+    let mut statement = db
+        .prepare("SELECT created FROM documents")
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let mut created: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+    let rows = statement
+        .query_map([], |row| row.get::<_, i64>(0))
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    for week in rows.flatten().filter_map(week_of) {
+        *created.entry(week).or_default() += 1;
+    }
+
+    // A tag counts from the week the earliest document carrying it was created
+    let mut statement = db
+        .prepare(
+            "SELECT t.tag, MIN(d.created) FROM tags t JOIN documents d ON d.path = t.path
+             GROUP BY t.tag",
+        )
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let mut first_used: BTreeMap<NaiveDate, BTreeSet<String>> = BTreeMap::new();
+    let rows = statement
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let mut tags = 0;
+    for (tag, since) in rows.flatten() {
+        tags += 1;
+        if let Some(week) = week_of(since) {
+            first_used.entry(week).or_default().insert(tag);
+        }
+    }
+
+    let range = week_range(weeks);
+    let start = range.first().copied().unwrap_or_default();
+    let mut documents_total: u64 = created.range(..start).map(|(_, count)| count).sum();
+    let mut tags_total: u64 = first_used
+        .range(..start)
+        .map(|(_, tags)| tags.len() as u64)
+        .sum();
+    let mut created_per_week = Vec::with_capacity(range.len());
+    let mut tag_growth = Vec::with_capacity(range.len());
+    for week in range {
+        let label = week.format(DATE_FORMAT).to_string();
+        let count = created.get(&week).copied().unwrap_or(0);
+        documents_total += count;
+        created_per_week.push(WeekCount {
+            week: label.clone(),
+            count,
+            total: documents_total,
+        });
+        let added: Vec<String> = first_used
+            .remove(&week)
+            .map(|tags| tags.into_iter().collect())
+            .unwrap_or_default();
+        tags_total += added.len() as u64;
+        tag_growth.push(TagWeek {
+            week: label,
+            added,
+            total: tags_total,
+        });
+    }
+
+    Ok(WorkspaceInsights {
+        documents,
+        words,
+        read_minutes: read_minutes(words),
+        tags,
+        stale: stale_count,
+        created_per_week,
+        tag_growth,
+    })
+}
+
+/// Note that a document was opened, for last-opened times and stale-note detection. Opens are
+/// kept by board id, so they follow a document through renames and cache rebuilds; vault
+/// documents, which the cache leaves out, are not recorded.
+pub fn document_opened(app: &AppHandle, path: &Path) {
+    if vault::vault_of(path).is_some() {
+        return;
+    }
+    let Some((root, db)) = catalog::containing(app, path) else {
+        return;
+    };
+    if let Err(error) = record_open(&db, &root, path) {
+        tracing::warn!(%error, "Failed to record document open");
+    }
+}
+
+fn record_open(db: &Database, root: &Path, path: &Path) -> Result<(), String> {
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    let cached: Option<String> = db
+        .query_row(
+            "SELECT id FROM documents WHERE path = ?1",
+            [workspace::relative_path(root, path)],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let id = match cached {
+        Some(id) => id,
+        None => document::read_board(path)?.board.id,
+    };
+    db.execute(
+        "INSERT INTO opens (id, last_opened, count) VALUES (?1, ?2, 1)
+         ON CONFLICT (id) DO UPDATE SET last_opened = excluded.last_opened, count = count + 1",
+        params![id, document::now_millis()],
+    )
+    .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    Ok(())
+}
+
+/// Read time, word count and when a document was created, saved and last opened
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn document_insights(app: AppHandle, path: String) -> Result<DocumentInsight, Error> {
+    let path = paths::check(&app, &path, paths::Scope::Read)?;
+    let (root, db) = catalog::containing(&app, &path)
+        .ok_or_else(|| Error::not_found("Document not found in the metadata cache"))?;
+    let relative = workspace::relative_path(&root, &path);
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    db.query_row(
+        &format!("{} WHERE d.path = ?1", SELECT_INSIGHTS),
+        [&relative],
+        insight,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read metadata cache: {}", e))?
+    .ok_or_else(|| {
+        Error::not_found("Document not found in the metadata cache").with_context(&relative)
+    })
+}
+
+/// Documents not saved or opened in the last `months` months (six by default), least recently
+/// touched first. Archived documents are left out, being untouched on purpose.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn stale_documents(
+    app: AppHandle,
+    months: Option<u32>,
+    limit: Option<usize>,
+    workspace: Option<String>,
+) -> Result<Vec<DocumentInsight>, Error> {
+    let root = paths::workspace_or_current(&app, workspace.as_deref(), paths::Scope::Read)?;
+    let cutoff = months_ago(months.unwrap_or(DEFAULT_STALE_MONTHS));
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = catalog::database(&app, &root)?;
+        let mut documents = stale(&db, &root, cutoff)?;
+        documents.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+        Ok(documents)
+    })
+    .await
+    .map_err(|e| format!("Insights task failed: {}", e))?
+}
+
+/// Totals for the workspace with documents created and tags first used per week over the last
+/// `weeks` weeks, for a review dashboard. Everything comes from the metadata cache.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn workspace_insights(
+    app: AppHandle,
+    weeks: Option<u32>,
+    workspace: Option<String>,
+) -> Result<WorkspaceInsights, Error> {
+    let root = paths::workspace_or_current(&app, workspace.as_deref(), paths::Scope::Read)?;
+    let weeks = weeks.unwrap_or(DEFAULT_WEEKS).clamp(1, MAX_WEEKS);
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = catalog::database(&app, &root)?;
+        Ok(summarize(&db, &root, weeks)?)
+    })
+    .await
+    .map_err(|e| format!("Insights task failed: {}", e))?
+}
@@ -0,0 +1,367 @@
+use crate::cancel::CancelToken;
+use crate::error::Error;
+use crate::event_bus::{self, BusEvent};
+use crate::{document, exports, ocr, optimize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Jobs allowed to run at once; the rest wait in the queue
+const MAX_CONCURRENT: usize = 2;
+/// Finished jobs kept for [`list_jobs`]
+const KEEP_FINISHED: usize = 50;
+
+#[derive(
+    serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// Snapshot of a job, also the payload of every `job:*` event
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: String,
+    pub kind: String,
+    pub label: String,
+    pub priority: Priority,
+    pub status: JobStatus,
+    /// Fraction complete from 0 to 1, when the job reports it
+    pub progress: Option<f32>,
+    pub message: Option<String>,
+    pub error: Option<Error>,
+    /// What the underlying command would have returned
+    pub result: Option<serde_json::Value>,
+    pub created_at: i64,
+}
+
+/// Work the frontend can queue, mirroring the commands of the same name
+#[derive(serde::Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum JobRequest {
+    Export {
+        workspace: String,
+        rule_id: String,
+    },
+    Ocr {
+        path: String,
+        language: Option<String>,
+    },
+    OptimizeAssets {
+        workspace: String,
+        dry_run: Option<bool>,
+    },
+}
+
+type Work = Box<dyn FnOnce(&JobContext) -> Result<serde_json::Value, Error> + Send>;
+
+struct Pending {
+    id: String,
+    priority: Priority,
+    work: Work,
+}
+
+#[derive(Default)]
+struct Queue {
+    /// Every known job in the order it was queued
+    jobs: Vec<JobInfo>,
+    pending: Vec<Pending>,
+    /// Cancel flags of running jobs
+    running: HashMap<String, CancelToken>,
+}
+
+/// Managed state holding queued, running and recently finished jobs
+#[derive(Default)]
+pub struct Jobs(Mutex<Queue>);
+
+impl Jobs {
+    fn update(&self, id: &str, change: impl FnOnce(&mut JobInfo)) -> Option<JobInfo> {
+        let mut queue = self.0.lock().ok()?;
+        let job = queue.jobs.iter_mut().find(|job| job.id == id)?;
+        change(job);
+        Some(job.clone())
+    }
+}
+
+/// Handed to running work to report progress and check for cancellation
+pub struct JobContext {
+    app: AppHandle,
+    id: String,
+    cancelled: CancelToken,
+}
+
+impl JobContext {
+    /// Report progress as `job:progress`
+    pub fn progress(&self, fraction: f32, message: Option<String>) {
+        let job = self.app.state::<Jobs>().update(&self.id, |job| {
+            job.progress = Some(fraction.clamp(0.0, 1.0));
+            job.message = message;
+        });
+        if let Some(job) = job {
+            let _ = self.app.emit("job:progress", &job);
+            event_bus::publish(&self.app, BusEvent::JobProgress { job });
+        }
+    }
+
+    /// Whether [`cancel_job`] was called; long work should check this and return early
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+
+    /// The job's cancel flag, for work that also runs outside the queue
+    pub fn token(&self) -> &CancelToken {
+        &self.cancelled
+    }
+}
+
+/// Queue work to run in the background, returning the queued job
+pub fn enqueue(
+    app: &AppHandle,
+    kind: &str,
+    label: String,
+    priority: Priority,
+    work: impl FnOnce(&JobContext) -> Result<serde_json::Value, Error> + Send + 'static,
+) -> JobInfo {
+    let job = JobInfo {
+        id: document::create_id("job"),
+        kind: kind.to_string(),
+        label,
+        priority,
+        status: JobStatus::Queued,
+        progress: None,
+        message: None,
+        error: None,
+        result: None,
+        created_at: document::now_millis(),
+    };
+    if let Ok(mut queue) = app.state::<Jobs>().0.lock() {
+        queue.jobs.push(job.clone());
+        queue.pending.push(Pending {
+            id: job.id.clone(),
+            priority,
+            work: Box::new(work),
+        });
+    }
+    event_bus::publish(app, BusEvent::JobQueued { job: job.clone() });
+    start_next(app);
+    job
+}
+
+/// Queue a long-running command; progress and the result arrive as `job:*` events
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn enqueue_job(app: AppHandle, request: JobRequest, priority: Option<Priority>) -> JobInfo {
+    let priority = priority.unwrap_or_default();
+    let handle = app.clone();
+    match request {
+        JobRequest::Export { workspace, rule_id } => enqueue(
+            &app,
+            "export",
+            format!("Export {}", rule_id),
+            priority,
+            move |job| {
+                json(exports::run_rule(
+                    &handle,
+                    Path::new(&workspace),
+                    &rule_id,
+                    job.token(),
+                ))
+            },
+        ),
+        JobRequest::Ocr { path, language } => {
+            let label = format!("Recognize text in {}", file_name(&path));
+            enqueue(&app, "ocr", label, priority, move |_| {
+                json(ocr::recognize_asset(handle, path, language))
+            })
+        }
+        JobRequest::OptimizeAssets { workspace, dry_run } => enqueue(
+            &app,
+            "optimizeAssets",
+            "Optimize images".to_string(),
+            priority,
+            move |job| {
+                json(optimize::optimize_assets(
+                    &handle,
+                    Path::new(&workspace),
+                    dry_run.unwrap_or(false),
+                    Some(job),
+                ))
+            },
+        ),
+    }
+}
+
+/// Queued, running and recently finished jobs, oldest first
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn list_jobs(jobs: State<'_, Jobs>) -> Vec<JobInfo> {
+    jobs.0
+        .lock()
+        .map(|queue| queue.jobs.clone())
+        .unwrap_or_default()
+}
+
+/// Cancel a job. Queued jobs never start; running jobs are asked to stop and their result is
+/// discarded.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn cancel_job(app: AppHandle, id: String) -> Result<(), Error> {
+    let jobs = app.state::<Jobs>();
+    let cancelled = {
+        let mut queue = jobs
+            .0
+            .lock()
+            .map_err(|e| format!("Failed to read jobs: {}", e))?;
+        if let Some(token) = queue.running.get(&id) {
+            token.cancel();
+            return Ok(());
+        }
+        let before = queue.pending.len();
+        queue.pending.retain(|pending| pending.id != id);
+        queue.pending.len() != before
+    };
+    if !cancelled {
+        return Err(format!("Job is not queued or running: {}", id).into());
+    }
+    if let Some(job) = jobs.update(&id, |job| job.status = JobStatus::Cancelled) {
+        let _ = app.emit("job:failed", &job);
+        event_bus::publish(&app, BusEvent::JobFailed { job });
+    }
+    Ok(())
+}
+
+/// Start the highest-priority queued jobs while there is capacity
+fn start_next(app: &AppHandle) {
+    let jobs = app.state::<Jobs>();
+    loop {
+        let next = {
+            let Ok(mut queue) = jobs.0.lock() else {
+                return;
+            };
+            if queue.running.len() >= MAX_CONCURRENT {
+                return;
+            }
+            // Highest priority first, then the oldest at that priority
+            let Some(index) = queue
+                .pending
+                .iter()
+                .enumerate()
+                .max_by(|(a_index, a), (b_index, b)| {
+                    a.priority.cmp(&b.priority).then(b_index.cmp(a_index))
+                })
+                .map(|(index, _)| index)
+            else {
+                return;
+            };
+            let pending = queue.pending.remove(index);
+            let cancelled = CancelToken::default();
+            queue.running.insert(pending.id.clone(), cancelled.clone());
+            if let Some(job) = queue.jobs.iter_mut().find(|job| job.id == pending.id) {
+                job.status = JobStatus::Running;
+            }
+            (pending, cancelled)
+        };
+        run(app.clone(), next.0, next.1);
+    }
+}
+
+fn run(app: AppHandle, pending: Pending, cancelled: CancelToken) {
+    std::thread::spawn(move || {
+        let context = JobContext {
+            app: app.clone(),
+            id: pending.id.clone(),
+            cancelled,
+        };
+        let result = (pending.work)(&context);
+        let jobs = app.state::<Jobs>();
+
+        let job = jobs.update(&pending.id, |job| match result {
+            _ if context.is_cancelled() => job.status = JobStatus::Cancelled,
+            Ok(value) => {
+                job.status = JobStatus::Done;
+                job.progress = Some(1.0);
+                job.result = Some(value);
+            }
+            Err(error) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(error);
+            }
+        });
+        if let Ok(mut queue) = jobs.0.lock() {
+            queue.running.remove(&pending.id);
+            prune(&mut queue);
+        }
+        if let Some(job) = job {
+            let event = match job.status {
+                JobStatus::Done => "job:done",
+                _ => "job:failed",
+            };
+            let _ = app.emit(event, &job);
+            event_bus::publish(
+                &app,
+                match job.status {
+                    JobStatus::Done => BusEvent::JobDone { job },
+                    _ => BusEvent::JobFailed { job },
+                },
+            );
+        }
+        start_next(&app);
+    });
+}
+
+/// Drop the oldest finished jobs beyond [`KEEP_FINISHED`]
+fn prune(queue: &mut Queue) {
+    let finished = |job: &JobInfo| {
+        matches!(
+            job.status,
+            JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled
+        )
+    };
+    let mut excess = queue
+        .jobs
+        .iter()
+        .filter(|job| finished(job))
+        .count()
+        .saturating_sub(KEEP_FINISHED);
+    queue.jobs.retain(|job| {
+        if excess > 0 && finished(job) {
+            excess -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+fn json<T: serde::Serialize>(
+    result: Result<T, impl Into<Error>>,
+) -> Result<serde_json::Value, Error> {
+    let value = result.map_err(Into::into)?;
+    Ok(serde_json::to_value(value).map_err(|e| format!("Failed to serialize result: {}", e))?)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
@@ -0,0 +1,233 @@
+use crate::error::Error;
+use crate::{catalog, daily_notes, paths, read_only, stats, workspace};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Prompts the user added, `.inkfinite/journal.json`
+const CONFIG: &str = "journal";
+/// Days journaled and the prompt rotation, kept by the backend
+const STATE_CONFIG: &str = "journal-state";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+/// Days listed with the streak, for a calendar of recent entries
+const RECENT_DAYS: i64 = 90;
+
+const BUILTIN_PROMPTS: &[&str] = &[
+    "What is on your mind right now?",
+    "What are you looking forward to this week?",
+    "Describe a small moment from yesterday that you want to remember.",
+    "What did you learn recently that surprised you?",
+    "What is one thing you would like to do differently today?",
+    "Who made a difference to you lately, and how?",
+    "What are you avoiding, and why?",
+    "Write about a place where you feel at ease.",
+    "What would make today a good day?",
+    "What is a question you keep coming back to?",
+    "Which habit are you trying to build, and how is it going?",
+    "What are three things you are grateful for today?",
+    "Describe a problem you solved recently and how you got there.",
+    "What would you tell yourself from a year ago?",
+    "What drained your energy this week, and what restored it?",
+    "Write about something you changed your mind about.",
+    "What are you curious about at the moment?",
+    "What does a restful evening look like for you?",
+    "Which conversation from this week is still on your mind?",
+    "What is something you made or finished that you are proud of?",
+    "What would you do with a free day tomorrow?",
+    "Write a letter to someone you have not spoken to in a while.",
+    "What worries you, and what part of it is in your control?",
+    "Describe the book, film or song that stayed with you recently.",
+    "What is one goal for the next month, and the first step toward it?",
+    "When did you last feel completely focused? What were you doing?",
+    "What are you holding on to that you could let go of?",
+    "Write about a routine you enjoy and why it works.",
+    "What made you laugh recently?",
+    "What do you want to remember about this season of your life?",
+];
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JournalConfig {
+    /// Prompts added to the rotation
+    pub prompts: Vec<String>,
+    /// Whether the built-in prompts stay in the rotation
+    pub builtin: bool,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        JournalConfig {
+            prompts: Vec::new(),
+            builtin: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct JournalState {
+    /// `YYYY-MM-DD` of every day the daily note was written in
+    days: BTreeSet<String>,
+    /// Position in the rotation of the next day's prompt
+    next: usize,
+    /// The prompt chosen for `prompt_date`, kept so it stays the same all day
+    prompt: Option<String>,
+    prompt_date: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalStreak {
+    /// Days in a row journaled, up to today; a day not yet written does not break it
+    pub current: u32,
+    pub longest: u32,
+    pub total_days: usize,
+    /// Whether today's daily note has been written in
+    pub today: bool,
+    /// Days journaled in the last 90 days, `YYYY-MM-DD`
+    pub recent: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodaysPrompt {
+    pub date: String,
+    pub prompt: String,
+    pub streak: JournalStreak,
+}
+
+fn prompts(config: &JournalConfig) -> Vec<String> {
+    let builtin = BUILTIN_PROMPTS
+        .iter()
+        .filter(|_| config.builtin)
+        .map(|prompt| prompt.to_string());
+    let prompts: Vec<String> = builtin
+        .chain(
+            config
+                .prompts
+                .iter()
+                .map(|prompt| prompt.trim().to_string()),
+        )
+        .filter(|prompt| !prompt.is_empty())
+        .collect();
+    match prompts.is_empty() {
+        true => BUILTIN_PROMPTS.iter().map(|p| p.to_string()).collect(),
+        false => prompts,
+    }
+}
+
+fn streak(state: &JournalState, today: NaiveDate) -> JournalStreak {
+    let days: BTreeSet<NaiveDate> = state
+        .days
+        .iter()
+        .filter_map(|day| NaiveDate::parse_from_str(day, DATE_FORMAT).ok())
+        .collect();
+    let (current, longest) = stats::streaks(&days, today);
+    let since = today - chrono::Duration::days(RECENT_DAYS - 1);
+    JournalStreak {
+        current,
+        longest,
+        total_days: days.len(),
+        today: days.contains(&today),
+        recent: days
+            .range(since..)
+            .map(|day| day.format(DATE_FORMAT).to_string())
+            .collect(),
+    }
+}
+
+/// Count today as journaled when today's daily note is saved
+pub fn document_saved(app: &AppHandle, path: &Path) {
+    let root: Option<PathBuf> = catalog::containing(app, path)
+        .map(|(root, _)| root)
+        .or_else(|| workspace::current_root(app).filter(|root| path.starts_with(root)));
+    let Some(root) = root else {
+        return;
+    };
+    let today = Local::now().date_naive();
+    if daily_notes::path_for(&root, today).ok().as_deref() != Some(path) {
+        return;
+    }
+    let result =
+        workspace::read_config::<JournalState>(&root, STATE_CONFIG).and_then(|mut state| {
+            if state.days.insert(today.format(DATE_FORMAT).to_string()) {
+                workspace::write_config(&root, STATE_CONFIG, &state)?;
+            }
+            Ok(())
+        });
+    if let Err(error) = result {
+        tracing::warn!(%error, "Failed to record journal entry");
+    }
+}
+
+/// Today's journaling prompt, the same all day; the next day moves on to the next prompt in
+/// the rotation. `skip` moves on right away.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_todays_prompt(
+    app: AppHandle,
+    workspace: String,
+    skip: Option<bool>,
+) -> Result<TodaysPrompt, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let config: JournalConfig = workspace::read_config(&root, CONFIG)?;
+    let mut state: JournalState = workspace::read_config(&root, STATE_CONFIG)?;
+    let today = Local::now().date_naive();
+    let date = today.format(DATE_FORMAT).to_string();
+    let prompts = prompts(&config);
+
+    let kept = state
+        .prompt
+        .clone()
+        .filter(|_| state.prompt_date.as_deref() == Some(date.as_str()) && skip != Some(true));
+    let prompt = match kept {
+        Some(prompt) => prompt,
+        None => {
+            let prompt = prompts[state.next % prompts.len()].clone();
+            state.next = (state.next + 1) % prompts.len();
+            state.prompt = Some(prompt.clone());
+            state.prompt_date = Some(date.clone());
+            // A read-only workspace still gets a prompt, just not a remembered one
+            if !read_only::is_read_only(&root) {
+                workspace::write_config(&root, STATE_CONFIG, &state)?;
+            }
+            prompt
+        }
+    };
+    Ok(TodaysPrompt {
+        date,
+        prompt,
+        streak: streak(&state, today),
+    })
+}
+
+/// Days journaled in a row and in total, from saves of the daily notes
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_journal_streak(app: AppHandle, workspace: String) -> Result<JournalStreak, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let state: JournalState = workspace::read_config(&root, STATE_CONFIG)?;
+    Ok(streak(&state, Local::now().date_naive()))
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_journal_config(app: AppHandle, workspace: String) -> Result<JournalConfig, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(workspace::read_config(&root, CONFIG)?)
+}
+
+/// Save the prompts added to the rotation
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_journal_config(
+    app: AppHandle,
+    workspace: String,
+    config: JournalConfig,
+) -> Result<(), Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    workspace::write_config(&root, CONFIG, &config)?;
+    Ok(())
+}
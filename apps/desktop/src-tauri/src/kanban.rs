@@ -0,0 +1,380 @@
+use crate::document;
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::tasks::{self, Priority, TaskFilter};
+use crate::{catalog, paths};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CardSource {
+    #[default]
+    Tasks,
+    Documents,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CardKind {
+    Task,
+    Document,
+}
+
+/// A column and what puts a card in it
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnConfig {
+    pub name: String,
+    /// Status `#tag` of the column's cards, without the `#`
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// For tasks: checked (`true`) or open (`false`) ones. Checked tasks go to a column set to
+    /// `true` ahead of any column matching their tags, and moving a task there checks it
+    /// without adding the tag.
+    #[serde(default)]
+    pub done: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BoardViewConfig {
+    pub source: CardSource,
+    /// To do, doing and done when empty
+    pub columns: Vec<ColumnConfig>,
+    /// Only cards carrying this `#tag`
+    pub tag: Option<String>,
+    /// A document or folder, workspace-relative
+    pub path: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Card {
+    pub kind: CardKind,
+    /// Workspace-relative path of the document
+    pub path: String,
+    pub block_id: Option<String>,
+    pub line: Option<usize>,
+    /// The task's text, or the document's title
+    pub title: String,
+    pub tags: Vec<String>,
+    /// The status tag that placed the card in its column
+    pub status: Option<String>,
+    pub done: Option<bool>,
+    pub due: Option<String>,
+    pub priority: Option<Priority>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Column {
+    #[serde(flatten)]
+    pub column: ColumnConfig,
+    pub cards: Vec<Card>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardView {
+    pub columns: Vec<Column>,
+    /// Cards no column matches
+    pub unsorted: Vec<Card>,
+}
+
+/// The card to move, as `board_view` returned it
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardRef {
+    pub kind: CardKind,
+    pub path: String,
+    pub block_id: Option<String>,
+    pub line: Option<usize>,
+    pub status: Option<String>,
+}
+
+fn default_columns() -> Vec<ColumnConfig> {
+    let column = |name: &str, tag: &str, done: Option<bool>| ColumnConfig {
+        name: name.to_string(),
+        tag: Some(tag.to_string()),
+        done,
+    };
+    vec![
+        column("To do", "todo", None),
+        column("Doing", "doing", None),
+        column("Done", "done", Some(true)),
+    ]
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// The column a card belongs in: checked tasks go to the first column of checked tasks, other
+/// cards to the first column whose tag they carry and whose state they are in
+fn place(columns: &[ColumnConfig], tags: &[String], done: Option<bool>) -> Option<usize> {
+    let checked = columns.iter().position(|column| column.done == Some(true));
+    if let (Some(true), Some(index)) = (done, checked) {
+        return Some(index);
+    }
+    columns.iter().position(|column| {
+        match (column.tag.as_deref().map(normalize_tag), column.done) {
+            (None, None) => false,
+            (None, Some(state)) => done == Some(state),
+            (Some(tag), state) => {
+                tags.contains(&tag) && (state.is_none() || done.is_none() || state == done)
+            }
+        }
+    })
+}
+
+/// Within `text`, replace the first `#from` with `#to` and drop any other `#from`; without
+/// `from`, nothing is replaced. Returns the text and whether a tag was found.
+fn retag(text: &str, from: Option<&str>, to: Option<&str>) -> (String, bool) {
+    let Some(from) = from else {
+        return (text.to_string(), false);
+    };
+    let is_tag_char = |c: char| c.is_alphanumeric() || matches!(c, '-' | '_' | '/');
+    let mut out = String::with_capacity(text.len());
+    let mut found = false;
+    let mut rest = text;
+    while let Some(at) = rest.find('#') {
+        let starts_word = rest[..at].chars().next_back().map_or(
+            out.chars().next_back().is_none_or(char::is_whitespace),
+            char::is_whitespace,
+        );
+        let length = rest[at + 1..]
+            .find(|c: char| !is_tag_char(c))
+            .unwrap_or(rest.len() - at - 1);
+        let tag = &rest[at + 1..at + 1 + length];
+        if !starts_word || tag.to_lowercase() != from {
+            out.push_str(&rest[..at + 1]);
+            rest = &rest[at + 1..];
+            continue;
+        }
+        match (found, to) {
+            (false, Some(to)) => {
+                out.push_str(&rest[..at]);
+                out.push('#');
+                out.push_str(to);
+            }
+            _ => {
+                // The tag goes with the space before it
+                let before = rest[..at].strip_suffix(' ').unwrap_or(&rest[..at]);
+                out.push_str(before);
+            }
+        }
+        found = true;
+        rest = &rest[at + 1 + length..];
+    }
+    out.push_str(rest);
+    (out, found)
+}
+
+fn view(app: &AppHandle, root: &Path, config: BoardViewConfig) -> Result<BoardView, String> {
+    let columns = match config.columns.is_empty() {
+        true => default_columns(),
+        false => config.columns,
+    };
+    let scope = config.tag.as_deref().map(normalize_tag);
+    let cards: Vec<Card> = match config.source {
+        CardSource::Tasks => {
+            let db = catalog::database(app, root)?;
+            let filter = TaskFilter {
+                tag: scope,
+                path: config.path,
+                limit: Some(usize::MAX),
+                ..TaskFilter::default()
+            };
+            tasks::query(&db, &filter)?
+                .into_iter()
+                .map(|task| Card {
+                    kind: CardKind::Task,
+                    path: task.path,
+                    block_id: Some(task.task.block_id),
+                    line: Some(task.task.line),
+                    title: task.task.text,
+                    tags: task.task.tags,
+                    status: None,
+                    done: Some(task.task.done),
+                    due: task.task.due,
+                    priority: task.task.priority,
+                })
+                .collect()
+        }
+        CardSource::Documents => {
+            let folder = config
+                .path
+                .as_deref()
+                .map(|path| path.trim_matches('/').to_string())
+                .filter(|path| !path.is_empty());
+            catalog::documents(app, root)?
+                .into_iter()
+                .filter(|entry| scope.as_ref().is_none_or(|tag| entry.tags.contains(tag)))
+                .filter(|entry| {
+                    folder.as_ref().is_none_or(|folder| {
+                        entry.path == *folder || entry.path.starts_with(&format!("{}/", folder))
+                    })
+                })
+                .map(|entry| Card {
+                    kind: CardKind::Document,
+                    path: entry.path,
+                    block_id: None,
+                    line: None,
+                    title: entry.name,
+                    tags: entry.tags,
+                    status: None,
+                    done: None,
+                    due: None,
+                    priority: None,
+                })
+                .collect()
+        }
+    };
+
+    let mut view = BoardView {
+        columns: columns
+            .into_iter()
+            .map(|column| Column {
+                column,
+                cards: Vec::new(),
+            })
+            .collect(),
+        unsorted: Vec::new(),
+    };
+    let configs: Vec<ColumnConfig> = view.columns.iter().map(|c| c.column.clone()).collect();
+    for mut card in cards {
+        match place(&configs, &card.tags, card.done) {
+            Some(index) => {
+                card.status = configs[index]
+                    .tag
+                    .as_deref()
+                    .map(normalize_tag)
+                    .filter(|tag| card.tags.contains(tag));
+                view.columns[index].cards.push(card);
+            }
+            None => view.unsorted.push(card),
+        }
+    }
+    Ok(view)
+}
+
+/// Move a task to a column: its status tag is swapped and, for columns of checked or open
+/// tasks, its checkbox set. Moving a checked task to a column by tag alone reopens it.
+fn move_task(
+    app: &AppHandle,
+    path: &Path,
+    item: &CardRef,
+    to: Option<&str>,
+    done: Option<bool>,
+) -> Result<(), Error> {
+    let block_id = item
+        .block_id
+        .as_deref()
+        .ok_or("Invalid card: a task card needs its block")?;
+    tasks::edit_task(app, path, block_id, item.line, |task, line| {
+        let (mut line, found) = retag(line, item.status.as_deref(), to);
+        if let Some(to) = to.filter(|to| !found && !task.tags.iter().any(|tag| tag == to)) {
+            line = format!("{} #{}", line.trim_end(), to);
+        }
+        match done {
+            Some(done) => tasks::set_checked(&line, done),
+            None => line,
+        }
+    })?;
+    Ok(())
+}
+
+/// Move a document to a column by swapping its status tag where it is written, or adding the
+/// tag on a line of its own at the end of the first text block
+fn move_document(
+    app: &AppHandle,
+    path: &Path,
+    item: &CardRef,
+    to: Option<&str>,
+) -> Result<(), Error> {
+    let mut board = document::read_board(path)?;
+    let mut blocks: Vec<(String, String)> = board
+        .pages()
+        .iter()
+        .flat_map(|page| board.page_shapes(page))
+        .filter_map(|shape| Some((shape.id.clone(), shape.text()?.to_string())))
+        .collect();
+    let mut placed = false;
+    let mut changed = false;
+    for (id, text) in &mut blocks {
+        let target = if placed { None } else { to };
+        let (updated, found) = retag(text, item.status.as_deref(), target);
+        placed |= found && target.is_some();
+        if updated != *text {
+            board.set_text(id, &updated);
+            *text = updated;
+            changed = true;
+        }
+    }
+    let tagged = to.is_some_and(|to| catalog::tags(&board.to_markdown()).contains(to));
+    if let Some(to) = to.filter(|_| !placed && !tagged) {
+        match blocks.first() {
+            Some((id, text)) => {
+                board.set_text(id, &format!("{}\n\n#{}", text.trim_end(), to));
+            }
+            None => board.push_markdown(&format!("#{}", to)),
+        }
+        changed = true;
+    }
+    if changed {
+        board.board.updated_at = document::now_millis();
+        document::write_board(path, &board)?;
+        events::file_changed(app, path, ChangeKind::Modified);
+    }
+    Ok(())
+}
+
+/// Group the workspace's tasks or documents into kanban columns by their status tags. The
+/// columns are a projection of the cache, so re-querying on `file:changed` keeps them live.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn board_view(
+    app: AppHandle,
+    config: Option<BoardViewConfig>,
+    workspace: Option<String>,
+) -> Result<BoardView, Error> {
+    let root = paths::workspace_or_current(&app, workspace.as_deref(), paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || Ok(view(&app, &root, config.unwrap_or_default())?))
+        .await
+        .map_err(|e| format!("Board view task failed: {}", e))?
+}
+
+/// Move a card to another column, writing the status change back into the document it came
+/// from
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn move_card(
+    app: AppHandle,
+    item: CardRef,
+    to_column: ColumnConfig,
+    workspace: Option<String>,
+) -> Result<(), Error> {
+    let root = paths::workspace_or_current(&app, workspace.as_deref(), paths::Scope::Write)?;
+    let path = root.join(&item.path);
+    paths::check(&app, &path.to_string_lossy(), paths::Scope::Write)?;
+    let to = to_column
+        .tag
+        .as_deref()
+        .map(normalize_tag)
+        .filter(|tag| !tag.is_empty());
+    let item = CardRef {
+        status: item.status.as_deref().map(normalize_tag),
+        ..item
+    };
+    match item.kind {
+        CardKind::Task => {
+            // A column by tag alone takes open tasks
+            let done = to_column.done.or(to.is_some().then_some(false));
+            let to = to.filter(|_| done != Some(true));
+            move_task(&app, &path, &item, to.as_deref(), done)
+        }
+        CardKind::Document => move_document(&app, &path, &item, to.as_deref()),
+    }
+}
@@ -0,0 +1,386 @@
+use crate::error::{Error, ErrorCode};
+use crate::{hotkeys, workspace};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const KEYBINDINGS_KEY: &str = "keybindings";
+
+const SUPER: u8 = 1;
+const CTRL: u8 = 2;
+const ALT: u8 = 4;
+const SHIFT: u8 = 8;
+/// `CmdOrCtrl`: Command on macOS, Control elsewhere
+const PRIMARY: u8 = if cfg!(target_os = "macos") {
+    SUPER
+} else {
+    CTRL
+};
+
+/// Modifiers tried, in order, when looking for a free shortcut near a taken one
+const ALTERNATIVES: [u8; 3] = [SHIFT, ALT, ALT | SHIFT];
+
+/// Shortcuts the operating system handles before the app sees them
+#[cfg(target_os = "macos")]
+const RESERVED: &[&str] = &[
+    "Cmd+Q",
+    "Cmd+H",
+    "Cmd+Alt+H",
+    "Cmd+Tab",
+    "Cmd+Shift+Tab",
+    "Cmd+`",
+    "Cmd+Space",
+    "Ctrl+Space",
+    "Cmd+Alt+Escape",
+    "Cmd+Shift+3",
+    "Cmd+Shift+4",
+    "Cmd+Shift+5",
+    "Ctrl+Cmd+Q",
+];
+#[cfg(target_os = "windows")]
+const RESERVED: &[&str] = &[
+    "Alt+F4",
+    "Alt+Tab",
+    "Alt+Shift+Tab",
+    "Alt+Escape",
+    "Alt+Space",
+    "Ctrl+Alt+Delete",
+    "Ctrl+Shift+Escape",
+    "Super+D",
+    "Super+E",
+    "Super+L",
+    "Super+R",
+    "Super+Tab",
+    "Super+Shift+S",
+];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const RESERVED: &[&str] = &[
+    "Alt+F4",
+    "Alt+Tab",
+    "Alt+Shift+Tab",
+    "Ctrl+Alt+Delete",
+    "Ctrl+Alt+T",
+    "Super+L",
+    "Super+Tab",
+];
+
+/// Key names accepted in accelerators, as `(spellings, name)`
+const NAMED_KEYS: &[(&[&str], &str)] = &[
+    (&["space"], "Space"),
+    (&["tab"], "Tab"),
+    (&["enter", "return"], "Enter"),
+    (&["escape", "esc"], "Escape"),
+    (&["backspace"], "Backspace"),
+    (&["delete", "del"], "Delete"),
+    (&["insert", "ins"], "Insert"),
+    (&["home"], "Home"),
+    (&["end"], "End"),
+    (&["pageup"], "PageUp"),
+    (&["pagedown"], "PageDown"),
+    (&["up", "arrowup"], "Up"),
+    (&["down", "arrowdown"], "Down"),
+    (&["left", "arrowleft"], "Left"),
+    (&["right", "arrowright"], "Right"),
+    (&["plus"], "Plus"),
+];
+const PUNCTUATION: &str = "`-=[]\\;',./";
+
+/// A parsed accelerator, with `CmdOrCtrl` resolved for this platform so two spellings of one
+/// shortcut compare equal
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Accelerator {
+    modifiers: u8,
+    key: Key,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    Char(char),
+    Function(u8),
+    Named(&'static str),
+}
+
+impl Accelerator {
+    fn parse(text: &str) -> Result<Accelerator, String> {
+        let text = text.trim();
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "Plus"),
+            None => text.rsplit_once('+').unwrap_or(("", text)),
+        };
+        let mut flags = 0;
+        for modifier in modifiers.split('+').filter(|part| !part.is_empty()) {
+            let flag = match modifier.trim().to_ascii_lowercase().as_str() {
+                "cmd" | "command" | "super" | "meta" | "win" => SUPER,
+                "ctrl" | "control" => CTRL,
+                "cmdorctrl" | "cmdorcontrol" | "commandorcontrol" | "commandorctrl" => PRIMARY,
+                "alt" | "option" => ALT,
+                "shift" => SHIFT,
+                _ => {
+                    return Err(format!(
+                        "Invalid shortcut {}: unknown modifier {}",
+                        text, modifier
+                    ))
+                }
+            };
+            if flags & flag != 0 {
+                return Err(format!(
+                    "Invalid shortcut {}: {} is repeated",
+                    text, modifier
+                ));
+            }
+            flags |= flag;
+        }
+        Ok(Accelerator {
+            modifiers: flags,
+            key: parse_key(key.trim()).ok_or_else(|| match key.trim() {
+                "" => format!("Invalid shortcut {}: no key", text),
+                key => format!("Invalid shortcut {}: unknown key {}", text, key),
+            })?,
+        })
+    }
+
+    fn with(self, modifiers: u8) -> Accelerator {
+        Accelerator {
+            modifiers: self.modifiers | modifiers,
+            ..self
+        }
+    }
+}
+
+fn parse_key(key: &str) -> Option<Key> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            c if c.is_ascii_alphanumeric() => Some(Key::Char(c.to_ascii_uppercase())),
+            '+' => Some(Key::Named("Plus")),
+            c if PUNCTUATION.contains(c) => Some(Key::Char(c)),
+            _ => None,
+        };
+    }
+    let lower = key.to_ascii_lowercase();
+    if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=24).contains(&number).then_some(Key::Function(number));
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(spellings, _)| spellings.contains(&lower.as_str()))
+        .map(|(_, name)| Key::Named(name))
+}
+
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let super_name = if cfg!(target_os = "macos") {
+            "Cmd"
+        } else {
+            "Super"
+        };
+        for (flag, name) in [
+            (SUPER, super_name),
+            (CTRL, "Ctrl"),
+            (ALT, "Alt"),
+            (SHIFT, "Shift"),
+        ] {
+            if self.modifiers & flag != 0 {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.key {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Function(number) => write!(f, "F{}", number),
+            Key::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutConflict {
+    /// Custom keybinding action
+    pub action: String,
+    pub shortcut: String,
+    /// What else uses the shortcut: other actions, `menu:<id>` and `hotkey:<action>`
+    pub conflicts_with: Vec<String>,
+    /// A free shortcut close to it
+    pub suggestion: Option<String>,
+}
+
+/// A stored binding that no longer validates
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidBinding {
+    pub action: String,
+    pub shortcut: String,
+    pub error: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutReport {
+    pub conflicts: Vec<ShortcutConflict>,
+    pub invalid: Vec<InvalidBinding>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeybindingState {
+    /// Custom shortcuts by action, in canonical form; `None` unbinds the action
+    pub bindings: BTreeMap<String, Option<String>>,
+    pub report: ShortcutReport,
+}
+
+fn load(app: &AppHandle) -> BTreeMap<String, Option<String>> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(KEYBINDINGS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Shortcuts already taken outside the custom keybindings: menu items and global hotkeys
+fn taken(app: &AppHandle) -> HashMap<Accelerator, Vec<String>> {
+    let mut taken: HashMap<Accelerator, Vec<String>> = HashMap::new();
+    let mut add = |owner: String, shortcut: &str| {
+        if let Ok(accelerator) = Accelerator::parse(shortcut) {
+            taken.entry(accelerator).or_default().push(owner);
+        }
+    };
+    #[cfg(desktop)]
+    for (id, shortcut) in crate::menu::ACCELERATORS
+        .iter()
+        .chain(crate::menu::PREDEFINED_ACCELERATORS)
+    {
+        add(format!("menu:{}", id), shortcut);
+    }
+    for (action, shortcut) in hotkeys::load_settings(app).bindings() {
+        if let Some(shortcut) = shortcut {
+            add(format!("hotkey:{}", action), shortcut);
+        }
+    }
+    taken
+}
+
+fn reserved(accelerator: &Accelerator) -> bool {
+    RESERVED
+        .iter()
+        .filter_map(|shortcut| Accelerator::parse(shortcut).ok())
+        .any(|reserved| reserved == *accelerator)
+}
+
+/// Check every binding against each other, the menu and global hotkeys, suggesting a free
+/// shortcut for each conflict
+fn report(app: &AppHandle, bindings: &BTreeMap<String, Option<String>>) -> ShortcutReport {
+    let taken = taken(app);
+    let mut invalid = Vec::new();
+    let mut parsed = Vec::new();
+    for (action, shortcut) in bindings {
+        let Some(shortcut) = shortcut.as_deref().filter(|s| !s.trim().is_empty()) else {
+            continue;
+        };
+        let error = match Accelerator::parse(shortcut) {
+            Ok(accelerator) if reserved(&accelerator) => {
+                "Shortcut is reserved by the system".into()
+            }
+            Ok(accelerator) => {
+                parsed.push((action.clone(), accelerator));
+                continue;
+            }
+            Err(error) => error,
+        };
+        invalid.push(InvalidBinding {
+            action: action.clone(),
+            shortcut: shortcut.to_string(),
+            error,
+        });
+    }
+
+    let mut used: HashSet<Accelerator> = taken.keys().copied().collect();
+    used.extend(parsed.iter().map(|(_, accelerator)| *accelerator));
+    let mut conflicts = Vec::new();
+    for (action, accelerator) in &parsed {
+        let mut conflicts_with: Vec<String> = parsed
+            .iter()
+            .filter(|(other, shortcut)| other != action && shortcut == accelerator)
+            .map(|(other, _)| other.clone())
+            .collect();
+        conflicts_with.extend(taken.get(accelerator).into_iter().flatten().cloned());
+        if conflicts_with.is_empty() {
+            continue;
+        }
+        let suggestion = ALTERNATIVES
+            .iter()
+            .map(|modifiers| accelerator.with(*modifiers))
+            .find(|candidate| !used.contains(candidate) && !reserved(candidate));
+        if let Some(suggestion) = suggestion {
+            // Two conflicts are not offered the same way out
+            used.insert(suggestion);
+        }
+        conflicts.push(ShortcutConflict {
+            action: action.clone(),
+            shortcut: accelerator.to_string(),
+            conflicts_with,
+            suggestion: suggestion.map(|suggestion| suggestion.to_string()),
+        });
+    }
+    ShortcutReport { conflicts, invalid }
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_keybindings(app: AppHandle) -> KeybindingState {
+    let bindings = load(&app);
+    let report = report(&app, &bindings);
+    KeybindingState { bindings, report }
+}
+
+/// Save custom shortcuts by action, replacing the previous ones.
+///
+/// Shortcuts that do not parse or that the system reserves are refused. Conflicts with other
+/// actions, the menu or global hotkeys are saved but come back in the report.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_keybindings(
+    app: AppHandle,
+    bindings: BTreeMap<String, Option<String>>,
+) -> Result<KeybindingState, Error> {
+    let mut normalized = BTreeMap::new();
+    for (action, shortcut) in bindings {
+        let shortcut = match shortcut.filter(|s| !s.trim().is_empty()) {
+            Some(shortcut) => {
+                let accelerator = Accelerator::parse(&shortcut)
+                    .map_err(|e| Error::new(ErrorCode::InvalidData, e).with_context(&action))?;
+                if reserved(&accelerator) {
+                    return Err(Error::conflict("Shortcut is reserved by the system")
+                        .with_context(format!("{}: {}", action, accelerator)));
+                }
+                Some(accelerator.to_string())
+            }
+            None => None,
+        };
+        normalized.insert(action, shortcut);
+    }
+
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(&normalized)
+        .map_err(|e| format!("Failed to serialize keybindings: {}", e))?;
+    store.set(KEYBINDINGS_KEY, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+
+    let report = report(&app, &normalized);
+    Ok(KeybindingState {
+        bindings: normalized,
+        report,
+    })
+}
+
+/// Report saved keybindings that clash with each other, the menu or global hotkeys, or no
+/// longer validate, with a free shortcut suggested for each clash
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn resolve_shortcut_conflicts(app: AppHandle) -> ShortcutReport {
+    report(&app, &load(&app))
+}
@@ -0,0 +1,1556 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::event_bus::{self, BusEvent};
+use crate::events::{self, ChangeKind};
+use crate::mdns::{self, Service};
+use crate::{bridge, crypto, hooks, lock, read_only, saves, vault, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::hmac;
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, ServerConfig, ServerConnection,
+    SignatureScheme, StreamOwned,
+};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+const SERVICE: &str = "_inkfinite._tcp.local";
+const ENABLED_KEY: &str = "lanSync";
+const PEERS_KEY: &str = "lanPeers";
+const IDENTITY_FILE: &str = "lan-identity.json";
+/// `.inkfinite/lan-sync.json`, holding the id that tells peers which workspace is open; it
+/// travels with the folder, so copies of one workspace share it
+const WORKSPACE_CONFIG: &str = "lan-sync";
+/// Server name sent in the TLS handshake; certificates are pinned, so it is never checked
+const PEER_HOST: &str = "inkfinite.local";
+/// Label for the TLS keying material that binds pairing codes and sync proofs to a connection
+const EXPORTER_LABEL: &[u8] = b"EXPORTER-inkfinite-peer";
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long both people have to compare and confirm a pairing code
+const PAIR_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_MESSAGE: usize = 64 << 20;
+
+/// Whether this device answers discovery and accepts connections; off until the user turns it on
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// This device's long-term identity: a random id and an Ed25519 key behind a self-signed
+/// certificate
+struct Identity {
+    device_id: String,
+    name: String,
+    key: Vec<u8>,
+    certificate: CertificateDer<'static>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IdentityFile {
+    device_id: String,
+    /// PKCS#8 Ed25519 key, base64
+    key: String,
+}
+
+#[derive(Default)]
+struct LanState {
+    identity: Option<Arc<Identity>>,
+    /// Port the listener is bound to, once started
+    port: Option<u16>,
+    /// Addresses from the last discovery, by device id
+    discovered: HashMap<String, SocketAddr>,
+    /// Pairings waiting for the user to confirm the code, by the other device's id
+    pending: HashMap<String, mpsc::Sender<bool>>,
+}
+
+/// Managed state for syncing directly with paired devices on the local network
+#[derive(Default)]
+pub struct LanSync(Mutex<LanState>);
+
+/// A paired device as kept in the settings store
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PeerRecord {
+    id: String,
+    name: String,
+    /// SHA-256 of the peer's certificate, hex
+    fingerprint: String,
+    /// Secret agreed during pairing that proves this device to the peer, base64
+    secret: String,
+    last_address: Option<String>,
+    last_sync: Option<i64>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Peer {
+    pub id: String,
+    pub name: String,
+    pub last_sync: Option<i64>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredPeer {
+    pub id: String,
+    pub name: String,
+    pub address: String,
+    pub paired: bool,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LanSyncStatus {
+    pub enabled: bool,
+    pub device_id: String,
+    pub name: String,
+    pub port: Option<u16>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PairingEvent {
+    peer: String,
+    name: String,
+    /// Six digits both devices show; they match only when nothing sits between them
+    code: String,
+}
+
+#[derive(serde::Serialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub received: usize,
+    pub sent: usize,
+    /// Documents deleted here because they were deleted on the peer
+    pub deleted_here: usize,
+    pub deleted_there: usize,
+    /// Workspace-relative paths changed on both devices; the older version is kept as a
+    /// conflict copy next to it
+    pub conflicts: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    /// SHA-256 of the file, hex
+    hash: String,
+    modified: i64,
+}
+
+/// Document hashes both devices had after the last sync with a peer, stored as
+/// `.inkfinite/lan-sync-<workspace id>-<peer id>.json`; tells edits apart from deletions
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SyncBase {
+    documents: BTreeMap<String, String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SyncWorkspace {
+    id: Option<String>,
+}
+
+/// What a sync does with one document
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// Both copies are the same
+    Same,
+    DeleteHere,
+    DeleteThere,
+    Send,
+    Receive,
+    /// Both copies changed; the newer wins and the other is kept as a conflict copy
+    Conflict,
+}
+
+/// Length-prefixed JSON frames exchanged over TLS
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum Message {
+    /// Starts pairing: the client commits to its nonce before seeing the server's, so neither
+    /// side can steer the code
+    PairCommit {
+        device_id: String,
+        name: String,
+        fingerprint: String,
+        commitment: String,
+    },
+    PairNonce {
+        device_id: String,
+        name: String,
+        nonce: String,
+    },
+    PairReveal {
+        nonce: String,
+    },
+    PairDecision {
+        accepted: bool,
+    },
+    PairSecret {
+        secret: String,
+    },
+    /// Starts a sync: the proof is an HMAC of this connection's keying material with the
+    /// pairing secret
+    /// `workspace` is the id of the client's open workspace, and `empty` whether it has no
+    /// documents to sync yet
+    Hello {
+        device_id: String,
+        proof: String,
+        workspace: String,
+        empty: bool,
+    },
+    Manifest {
+        workspace: String,
+        entries: BTreeMap<String, Entry>,
+    },
+    Get {
+        path: String,
+    },
+    File {
+        content: Option<String>,
+    },
+    Put {
+        path: String,
+        content: String,
+    },
+    Delete {
+        path: String,
+    },
+    Done {
+        synced: BTreeMap<String, String>,
+    },
+    Ok,
+    Error {
+        message: String,
+    },
+}
+
+fn invalid_message() -> String {
+    "Invalid peer message".to_string()
+}
+
+/// Start listening if LAN sync was turned on
+pub fn start(app: AppHandle) {
+    let enabled = app
+        .store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(ENABLED_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
+    if enabled {
+        if let Err(error) = listen(&app) {
+            tracing::warn!(%error, "Failed to start LAN sync");
+        }
+    }
+}
+
+fn state(app: &AppHandle) -> Result<std::sync::MutexGuard<'_, LanState>, String> {
+    app.state::<LanSync>()
+        .inner()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to read LAN sync state: {}", e))
+}
+
+/// Bind the listener and mDNS responder on first use; later calls only re-enable them
+fn listen(app: &AppHandle) -> Result<u16, String> {
+    let identity = identity(app)?;
+    if let Some(port) = state(app)?.port {
+        ENABLED.store(true, Ordering::Relaxed);
+        return Ok(port);
+    }
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .map_err(|e| format!("Failed to listen for peers: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to listen for peers: {}", e))?
+        .port();
+    mdns::advertise(
+        Service {
+            kind: SERVICE.to_string(),
+            instance: identity.device_id.clone(),
+            port,
+            txt: vec![
+                ("name".to_string(), identity.name.clone()),
+                ("v".to_string(), "1".to_string()),
+            ],
+        },
+        || ENABLED.load(Ordering::Relaxed),
+    )?;
+    let config = server_config(&identity)?;
+    let handle = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if !ENABLED.load(Ordering::Relaxed) {
+                continue;
+            }
+            let app = handle.clone();
+            let identity = identity.clone();
+            let config = config.clone();
+            std::thread::spawn(move || {
+                if let Err(error) = serve(&app, &identity, config, stream) {
+                    tracing::warn!(%error, "Peer connection failed");
+                }
+            });
+        }
+    });
+    state(app)?.port = Some(port);
+    ENABLED.store(true, Ordering::Relaxed);
+    tracing::info!(port, "LAN sync listening");
+    Ok(port)
+}
+
+fn identity(app: &AppHandle) -> Result<Arc<Identity>, String> {
+    let mut state = state(app)?;
+    if let Some(identity) = &state.identity {
+        return Ok(identity.clone());
+    }
+    let path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?
+        .join(IDENTITY_FILE);
+    let file: IdentityFile = match fs::read_to_string(&path) {
+        Ok(content) => {
+            serde_json::from_str(&content).map_err(|e| format!("Invalid LAN identity: {}", e))?
+        }
+        Err(_) => {
+            let key = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                .map_err(|_| "Failed to generate LAN identity".to_string())?;
+            let file = IdentityFile {
+                device_id: uuid::Uuid::new_v4().simple().to_string(),
+                key: BASE64.encode(key.as_ref()),
+            };
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory: {}", e))?;
+            }
+            let content = serde_json::to_string_pretty(&file)
+                .map_err(|e| format!("Failed to serialize LAN identity: {}", e))?;
+            fs::write(&path, content)
+                .map_err(|e| format!("Failed to write LAN identity: {}", e))?;
+            file
+        }
+    };
+    let key = BASE64
+        .decode(&file.key)
+        .map_err(|e| format!("Invalid LAN identity: {}", e))?;
+    let pair = Ed25519KeyPair::from_pkcs8(&key)
+        .map_err(|_| "Invalid LAN identity: bad key".to_string())?;
+    let identity = Arc::new(Identity {
+        device_id: file.device_id,
+        name: device_name(),
+        certificate: certificate(&pair),
+        key,
+    });
+    state.identity = Some(identity.clone());
+    Ok(identity)
+}
+
+/// Host name shown to other devices
+fn device_name() -> String {
+    ["COMPUTERNAME", "HOSTNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .chain(fs::read_to_string("/etc/hostname").ok())
+        .chain(
+            std::process::Command::new("hostname")
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).to_string()),
+        )
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "Inkfinite".to_string())
+}
+
+/// DER for one tag-length-value element
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len = content.len().to_be_bytes();
+        let skip = len.iter().take_while(|&&byte| byte == 0).count();
+        out.push(0x80 | (len.len() - skip) as u8);
+        out.extend_from_slice(&len[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// Minimal self-signed X.509 v3 certificate for `key`; peers pin it by hash, so only the key
+/// and signature matter. Signing is deterministic, so the same key always gives the same
+/// certificate.
+fn certificate(key: &Ed25519KeyPair) -> CertificateDer<'static> {
+    const SEQUENCE: u8 = 0x30;
+    const SET: u8 = 0x31;
+    const INTEGER: u8 = 0x02;
+    const BIT_STRING: u8 = 0x03;
+    const OID: u8 = 0x06;
+    const UTF8_STRING: u8 = 0x0c;
+    const GENERALIZED_TIME: u8 = 0x18;
+    const ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+    const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+    let public = key.public_key().as_ref();
+    let mut serial = Sha256::digest(public)[..16].to_vec();
+    serial[0] = (serial[0] & 0x7f) | 0x01;
+    let algorithm = der(SEQUENCE, &der(OID, ED25519));
+    let name = der(
+        SEQUENCE,
+        &der(
+            SET,
+            &der(
+                SEQUENCE,
+                &[der(OID, COMMON_NAME), der(UTF8_STRING, b"inkfinite")].concat(),
+            ),
+        ),
+    );
+    let validity = der(
+        SEQUENCE,
+        &[
+            der(GENERALIZED_TIME, b"20240101000000Z"),
+            der(GENERALIZED_TIME, b"99991231235959Z"),
+        ]
+        .concat(),
+    );
+    let public_key = der(
+        SEQUENCE,
+        &[algorithm.clone(), der(BIT_STRING, &[&[0], public].concat())].concat(),
+    );
+    let tbs = der(
+        SEQUENCE,
+        &[
+            der(0xa0, &der(INTEGER, &[2])),
+            der(INTEGER, &serial),
+            algorithm.clone(),
+            name.clone(),
+            validity,
+            name,
+            public_key,
+        ]
+        .concat(),
+    );
+    let signature = key.sign(&tbs);
+    let certificate = der(
+        SEQUENCE,
+        &[
+            tbs,
+            algorithm,
+            der(BIT_STRING, &[&[0], signature.as_ref()].concat()),
+        ]
+        .concat(),
+    );
+    CertificateDer::from(certificate)
+}
+
+fn fingerprint(certificate: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(certificate))
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn server_config(identity: &Identity) -> Result<Arc<ServerConfig>, String> {
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(identity.key.clone()));
+    ServerConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(|e| format!("Failed to set up TLS: {}", e))?
+        .with_no_client_auth()
+        .with_single_cert(vec![identity.certificate.clone()], key)
+        .map(Arc::new)
+        .map_err(|e| format!("Failed to set up TLS: {}", e))
+}
+
+/// Peers use self-signed certificates: pairing accepts whichever one the peer presents and the
+/// code comparison vouches for it, later connections only accept the pinned one
+#[derive(Debug)]
+struct PinnedCertificate {
+    fingerprint: Option<String>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match &self.fingerprint {
+            Some(expected) if *expected != fingerprint(end_entity) => Err(rustls::Error::General(
+                "Peer certificate changed: pair the devices again".to_string(),
+            )),
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+type ClientStream = StreamOwned<ClientConnection, TcpStream>;
+type ServerStream = StreamOwned<ServerConnection, TcpStream>;
+
+/// Open TLS to a peer, accepting only the certificate with `fingerprint` when given
+fn connect(address: SocketAddr, fingerprint: Option<String>) -> Result<ClientStream, String> {
+    let provider = provider();
+    let verifier = PinnedCertificate {
+        fingerprint,
+        algorithms: provider.signature_verification_algorithms,
+    };
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(|e| format!("Failed to set up TLS: {}", e))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    let name = ServerName::try_from(PEER_HOST).map_err(|e| format!("Invalid peer name: {}", e))?;
+    let connection = ClientConnection::new(Arc::new(config), name)
+        .map_err(|e| format!("Failed to set up TLS: {}", e))?;
+    let tcp = TcpStream::connect_timeout(&address, IO_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to peer: {}", e))?;
+    let _ = tcp.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = tcp.set_write_timeout(Some(IO_TIMEOUT));
+    let mut stream = StreamOwned::new(connection, tcp);
+    stream
+        .conn
+        .complete_io(&mut stream.sock)
+        .map_err(|e| format!("Failed to connect to peer: {}", e))?;
+    Ok(stream)
+}
+
+/// Keying material unique to this TLS connection, so a proof or code cannot be replayed on
+/// another one
+fn exporter<C>(connection: &rustls::ConnectionCommon<C>) -> Result<[u8; 32], String> {
+    connection
+        .export_keying_material([0u8; 32], EXPORTER_LABEL, None)
+        .map_err(|e| format!("Failed to secure peer connection: {}", e))
+}
+
+fn send(stream: &mut impl Write, message: &Message) -> Result<(), String> {
+    let body = serde_json::to_vec(message).map_err(|e| format!("Failed to send to peer: {}", e))?;
+    stream
+        .write_all(&(body.len() as u32).to_be_bytes())
+        .and_then(|_| stream.write_all(&body))
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("Failed to send to peer: {}", e))
+}
+
+fn receive<T: DeserializeOwned>(stream: &mut impl Read) -> Result<T, String> {
+    let mut len = [0u8; 4];
+    stream
+        .read_exact(&mut len)
+        .map_err(|e| format!("Failed to read from peer: {}", e))?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE {
+        return Err(invalid_message());
+    }
+    let mut body = vec![0u8; len];
+    stream
+        .read_exact(&mut body)
+        .map_err(|e| format!("Failed to read from peer: {}", e))?;
+    serde_json::from_slice(&body).map_err(|_| invalid_message())
+}
+
+/// Send a message and wait for the answer, turning an error sent back into `Err`
+fn request(stream: &mut ClientStream, message: &Message) -> Result<Message, String> {
+    send(stream, message)?;
+    match receive(stream)? {
+        Message::Error { message } => Err(format!("Peer refused: {}", message)),
+        reply => Ok(reply),
+    }
+}
+
+fn random_base64() -> String {
+    let mut bytes = [0u8; 32];
+    crypto::fill_random(&mut bytes);
+    BASE64.encode(bytes)
+}
+
+fn pairing_code(exporter: &[u8], client_nonce: &str, server_nonce: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(exporter)
+        .chain_update(client_nonce)
+        .chain_update(server_nonce)
+        .finalize();
+    let value = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+    format!("{:06}", value % 1_000_000)
+}
+
+/// Show the code with a `peer:pairing` event and wait for [`confirm_pairing`]
+fn await_confirmation(
+    app: &AppHandle,
+    peer: &str,
+    name: &str,
+    code: String,
+) -> Result<bool, String> {
+    let (sender, receiver) = mpsc::channel();
+    state(app)?.pending.insert(peer.to_string(), sender);
+    let _ = app.emit(
+        "peer:pairing",
+        PairingEvent {
+            peer: peer.to_string(),
+            name: name.to_string(),
+            code,
+        },
+    );
+    let accepted = receiver.recv_timeout(PAIR_TIMEOUT).unwrap_or(false);
+    state(app)?.pending.remove(peer);
+    Ok(accepted)
+}
+
+fn load_peers(app: &AppHandle) -> Vec<PeerRecord> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(PEERS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn save_peers(app: &AppHandle, peers: &[PeerRecord]) -> Result<(), String> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(PEERS_KEY, serde_json::json!(peers));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+fn update_peer(app: &AppHandle, id: &str, change: impl FnOnce(&mut PeerRecord)) {
+    let mut peers = load_peers(app);
+    if let Some(peer) = peers.iter_mut().find(|peer| peer.id == id) {
+        change(peer);
+        if let Err(error) = save_peers(app, &peers) {
+            tracing::warn!(%error, "Failed to update paired peer");
+        }
+    }
+}
+
+fn remember_peer(app: &AppHandle, peer: PeerRecord) -> Result<(), String> {
+    let mut peers = load_peers(app);
+    peers.retain(|existing| existing.id != peer.id);
+    peers.push(peer);
+    save_peers(app, &peers)
+}
+
+fn find_peer(app: &AppHandle, id: &str) -> Result<PeerRecord, String> {
+    load_peers(app)
+        .into_iter()
+        .find(|peer| peer.id == id)
+        .ok_or_else(|| format!("Paired peer not found: {}", id))
+}
+
+/// Where a device is now: the last discovery, a fresh one, or where it was last seen
+fn resolve_address(app: &AppHandle, id: &str, last: Option<&str>) -> Result<SocketAddr, String> {
+    if let Some(address) = state(app)?.discovered.get(id) {
+        return Ok(*address);
+    }
+    if let Some(found) = mdns::browse(SERVICE, DISCOVERY_TIMEOUT)?
+        .into_iter()
+        .find(|found| found.instance == id)
+    {
+        state(app)?.discovered.insert(id.to_string(), found.address);
+        return Ok(found.address);
+    }
+    last.and_then(|address| address.parse().ok())
+        .ok_or_else(|| format!("Peer not found on the network: {}", id))
+}
+
+fn serve(
+    app: &AppHandle,
+    identity: &Identity,
+    config: Arc<ServerConfig>,
+    tcp: TcpStream,
+) -> Result<(), String> {
+    let _ = tcp.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = tcp.set_write_timeout(Some(IO_TIMEOUT));
+    let connection =
+        ServerConnection::new(config).map_err(|e| format!("Failed to set up TLS: {}", e))?;
+    let mut stream = StreamOwned::new(connection, tcp);
+    stream
+        .conn
+        .complete_io(&mut stream.sock)
+        .map_err(|e| format!("Failed to accept peer: {}", e))?;
+    let result = match receive(&mut stream)? {
+        Message::PairCommit {
+            device_id,
+            name,
+            fingerprint,
+            commitment,
+        } => accept_pairing(
+            app,
+            identity,
+            &mut stream,
+            PeerRecord {
+                id: device_id,
+                name,
+                fingerprint,
+                secret: String::new(),
+                last_address: None,
+                last_sync: None,
+            },
+            commitment,
+        ),
+        Message::Hello {
+            device_id,
+            proof,
+            workspace,
+            empty,
+        } => serve_sync(app, &mut stream, &device_id, &proof, &workspace, empty),
+        _ => Err(invalid_message()),
+    };
+    if let Err(message) = &result {
+        let _ = send(
+            &mut stream,
+            &Message::Error {
+                message: message.clone(),
+            },
+        );
+    }
+    result
+}
+
+/// Server side of [`pair_peer`]
+fn accept_pairing(
+    app: &AppHandle,
+    identity: &Identity,
+    stream: &mut ServerStream,
+    mut peer: PeerRecord,
+    commitment: String,
+) -> Result<(), String> {
+    let nonce = random_base64();
+    send(
+        stream,
+        &Message::PairNonce {
+            device_id: identity.device_id.clone(),
+            name: identity.name.clone(),
+            nonce: nonce.clone(),
+        },
+    )?;
+    let Message::PairReveal { nonce: peer_nonce } = receive(stream)? else {
+        return Err(invalid_message());
+    };
+    if format!("{:x}", Sha256::digest(&peer_nonce)) != commitment {
+        return Err("Pairing failed: the peer changed its nonce".to_string());
+    }
+    let code = pairing_code(&exporter(&stream.conn)?, &peer_nonce, &nonce);
+    let _ = stream
+        .sock
+        .set_read_timeout(Some(PAIR_TIMEOUT + IO_TIMEOUT));
+    let accepted = await_confirmation(app, &peer.id, &peer.name, code)?;
+    send(stream, &Message::PairDecision { accepted })?;
+    let Message::PairDecision {
+        accepted: peer_accepted,
+    } = receive(stream)?
+    else {
+        return Err(invalid_message());
+    };
+    if !(accepted && peer_accepted) {
+        tracing::info!("Pairing declined");
+        return Ok(());
+    }
+    peer.secret = random_base64();
+    send(
+        stream,
+        &Message::PairSecret {
+            secret: peer.secret.clone(),
+        },
+    )?;
+    tracing::info!("Peer paired");
+    remember_peer(app, peer)
+}
+
+/// Server side of [`sync_with_peer`]: answers the client's requests against the open workspace
+fn serve_sync(
+    app: &AppHandle,
+    stream: &mut ServerStream,
+    device_id: &str,
+    proof: &str,
+    peer_workspace: &str,
+    peer_empty: bool,
+) -> Result<(), String> {
+    if lock::is_locked(app) {
+        return Err("Workspace is locked".to_string());
+    }
+    let peer = find_peer(app, device_id)?;
+    let secret = BASE64
+        .decode(&peer.secret)
+        .map_err(|e| format!("Invalid paired peer: {}", e))?;
+    let proof = BASE64.decode(proof).map_err(|_| invalid_message())?;
+    hmac::verify(
+        &hmac::Key::new(hmac::HMAC_SHA256, &secret),
+        &exporter(&stream.conn)?,
+        &proof,
+    )
+    .map_err(|_| "Peer authentication failed".to_string())?;
+
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    saves::flush_all(app);
+    let entries = manifest(&root)?;
+    let mut id = workspace_id(&root)?;
+    if match_workspace(&id, entries.is_empty(), peer_workspace, peer_empty)? {
+        id = adopt_workspace_id(&root, peer_workspace)?;
+    }
+    send(
+        stream,
+        &Message::Manifest {
+            workspace: id.clone(),
+            entries,
+        },
+    )?;
+    loop {
+        let reply = match receive(stream)? {
+            Message::Get { path } => Message::File {
+                content: read_document(&root, &path)?,
+            },
+            Message::Put { path, content } => {
+                write_document(app, &root, &path, &content)?;
+                Message::Ok
+            }
+            Message::Delete { path } => {
+                delete_document(app, &root, &path)?;
+                Message::Ok
+            }
+            Message::Done { synced } => {
+                workspace::write_config(
+                    &root,
+                    &base_config(&id, device_id),
+                    &SyncBase { documents: synced },
+                )?;
+                send(stream, &Message::Ok)?;
+                break;
+            }
+            _ => return Err(invalid_message()),
+        };
+        send(stream, &reply)?;
+    }
+    update_peer(app, device_id, |peer| {
+        peer.last_sync = Some(document::now_millis())
+    });
+    Ok(())
+}
+
+fn base_config(workspace: &str, peer: &str) -> String {
+    format!("lan-sync-{}-{}", workspace, peer)
+}
+
+/// Id of the workspace at `root`, created on first use
+fn workspace_id(root: &Path) -> Result<String, String> {
+    let config: SyncWorkspace = workspace::read_config(root, WORKSPACE_CONFIG)?;
+    match config.id {
+        Some(id) => Ok(id),
+        None => adopt_workspace_id(root, &uuid::Uuid::new_v4().simple().to_string()),
+    }
+}
+
+fn adopt_workspace_id(root: &Path, id: &str) -> Result<String, String> {
+    let config = SyncWorkspace {
+        id: Some(id.to_string()),
+    };
+    workspace::write_config(root, WORKSPACE_CONFIG, &config)?;
+    Ok(id.to_string())
+}
+
+/// Whether two devices may sync, and whether this one should take the peer's workspace id.
+/// Only the same workspace syncs; an empty one takes the other's id so a new device can start
+/// from its peer's copy. Anything else would merge, or delete from, an unrelated workspace.
+fn match_workspace(
+    mine: &str,
+    mine_empty: bool,
+    theirs: &str,
+    theirs_empty: bool,
+) -> Result<bool, String> {
+    match (mine == theirs, mine_empty, theirs_empty) {
+        (true, _, _) => Ok(false),
+        (false, true, _) => Ok(true),
+        (false, false, true) => Ok(false),
+        (false, false, false) => Err("The peer has a different workspace open".to_string()),
+    }
+}
+
+/// Hash and modification time of every document outside vaults; vault documents stay on
+/// their device
+fn manifest(root: &Path) -> Result<BTreeMap<String, Entry>, String> {
+    let mut entries = BTreeMap::new();
+    for path in workspace::list_documents(root)? {
+        if vault::vault_of(&path).is_some() {
+            continue;
+        }
+        let content = fs::read(&path).map_err(|e| format!("Failed to read document: {}", e))?;
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_millis() as i64);
+        entries.insert(
+            workspace::relative_path(root, &path),
+            Entry {
+                hash: format!("{:x}", Sha256::digest(&content)),
+                modified,
+            },
+        );
+    }
+    Ok(entries)
+}
+
+/// Resolve a workspace-relative document path sent by a peer, refusing anything that could
+/// leave the workspace or reach internal files and vaults
+fn document_path(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let invalid = || format!("Invalid path: {}", relative);
+    let plain = Path::new(relative).components().all(|component| {
+        matches!(component, Component::Normal(part) if !part.to_string_lossy().starts_with('.'))
+    });
+    if relative.is_empty() || !plain || !relative.ends_with(DOCUMENT_EXTENSION) {
+        return Err(invalid());
+    }
+    let path = root.join(relative);
+    if vault::vault_of(&path).is_some() {
+        return Err(invalid());
+    }
+    Ok(path)
+}
+
+fn read_document(root: &Path, relative: &str) -> Result<Option<String>, String> {
+    let path = document_path(root, relative)?;
+    if !path.is_file() {
+        return Ok(None);
+    }
+    fs::read_to_string(&path)
+        .map(Some)
+        .map_err(|e| format!("Failed to read document: {}", e))
+}
+
+fn write_document(
+    app: &AppHandle,
+    root: &Path,
+    relative: &str,
+    content: &str,
+) -> Result<(), String> {
+    let path = document_path(root, relative)?;
+    read_only::ensure_writable(&path)?;
+    serde_json::from_str::<BoardFile>(content)
+        .map_err(|e| format!("Invalid file format: {}", e))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    saves::discard(app, &path);
+    let existed = path.exists();
+    let temp = document::saving_path(&path);
+    fs::write(&temp, content).map_err(|e| format!("Failed to write file: {}", e))?;
+    fs::rename(&temp, &path).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        format!("Failed to write file: {}", e)
+    })?;
+    if existed {
+        audit::record(root, AuditAction::Overwrite, AuditSource::Sync, &[&path]);
+    }
+    let kind = if existed {
+        ChangeKind::Modified
+    } else {
+        ChangeKind::Created
+    };
+    events::file_changed(app, &path, kind);
+    Ok(())
+}
+
+fn delete_document(app: &AppHandle, root: &Path, relative: &str) -> Result<(), String> {
+    let path = document_path(root, relative)?;
+    read_only::ensure_writable(&path)?;
+    if !path.is_file() {
+        return Ok(());
+    }
+    saves::discard(app, &path);
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete file: {}", e))?;
+    audit::record(root, AuditAction::Delete, AuditSource::Sync, &[&path]);
+    events::file_changed(app, &path, ChangeKind::Deleted);
+    Ok(())
+}
+
+/// Save the losing side of a conflict next to the document as a new board, returning its
+/// workspace-relative path
+fn write_conflict_copy(
+    app: &AppHandle,
+    root: &Path,
+    relative: &str,
+    content: &str,
+) -> Result<String, String> {
+    let mut board: BoardFile =
+        serde_json::from_str(content).map_err(|e| format!("Invalid file format: {}", e))?;
+    let stamp = chrono::Local::now().format("%Y-%m-%d %H%M");
+    board.board.id = document::create_id("board");
+    board.board.name = format!("{} (conflict {})", board.board.name, stamp);
+    let path = document_path(root, relative)?;
+    let copy = path.with_file_name(format!(
+        "{} (conflict {}){}",
+        document::document_stem(&path),
+        stamp,
+        DOCUMENT_EXTENSION
+    ));
+    document::write_board(&copy, &board)?;
+    events::file_changed(app, &copy, ChangeKind::Created);
+    Ok(workspace::relative_path(root, &copy))
+}
+
+fn fetch(stream: &mut ClientStream, path: &str) -> Result<Option<String>, String> {
+    match request(
+        stream,
+        &Message::Get {
+            path: path.to_string(),
+        },
+    )? {
+        Message::File { content } => Ok(content),
+        _ => Err(invalid_message()),
+    }
+}
+
+fn upload(stream: &mut ClientStream, path: &str, content: String) -> Result<(), String> {
+    match request(
+        stream,
+        &Message::Put {
+            path: path.to_string(),
+            content,
+        },
+    )? {
+        Message::Ok => Ok(()),
+        _ => Err(invalid_message()),
+    }
+}
+
+fn hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn read_local(root: &Path, path: &str) -> Result<String, String> {
+    fs::read_to_string(document_path(root, path)?)
+        .map_err(|e| format!("Failed to read document: {}", e))
+}
+
+/// Send the local copy of `path` to the peer, returning its hash
+fn push(stream: &mut ClientStream, root: &Path, path: &str) -> Result<String, String> {
+    let content = read_local(root, path)?;
+    let hash = hash(&content);
+    upload(stream, path, content)?;
+    Ok(hash)
+}
+
+/// Replace the local copy of `path` with the peer's, returning its hash; `None` when the peer
+/// no longer has it
+fn pull(
+    app: &AppHandle,
+    stream: &mut ClientStream,
+    root: &Path,
+    path: &str,
+) -> Result<Option<String>, String> {
+    let Some(content) = fetch(stream, path)? else {
+        return Ok(None);
+    };
+    write_document(app, root, path, &content)?;
+    Ok(Some(hash(&content)))
+}
+
+/// Client side of [`sync_with_peer`]. Both devices must have the same workspace open; each
+/// document then takes the step [`plan`] gives it, and changes on both sides keep the newer
+/// copy with the older saved as a conflict copy.
+fn sync(app: &AppHandle, id: &str) -> Result<SyncReport, String> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    read_only::ensure_writable(&root)?;
+    let identity = identity(app)?;
+    let peer = find_peer(app, id)?;
+    hooks::before_sync(app, &root, &peer.name)?;
+    let address = resolve_address(app, id, peer.last_address.as_deref())?;
+    let mut stream = connect(address, Some(peer.fingerprint.clone()))?;
+    let secret = BASE64
+        .decode(&peer.secret)
+        .map_err(|e| format!("Invalid paired peer: {}", e))?;
+    let proof = hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA256, &secret),
+        &exporter(&stream.conn)?,
+    );
+    saves::flush_all(app);
+    let local = manifest(&root)?;
+    let mut workspace_id = workspace_id(&root)?;
+    send(
+        &mut stream,
+        &Message::Hello {
+            device_id: identity.device_id.clone(),
+            proof: BASE64.encode(proof.as_ref()),
+            workspace: workspace_id.clone(),
+            empty: local.is_empty(),
+        },
+    )?;
+    let (peer_workspace, remote) = match receive(&mut stream)? {
+        Message::Manifest { workspace, entries } => (workspace, entries),
+        Message::Error { message } => return Err(format!("Peer refused: {}", message)),
+        _ => return Err(invalid_message()),
+    };
+    if match_workspace(
+        &workspace_id,
+        local.is_empty(),
+        &peer_workspace,
+        remote.is_empty(),
+    )? {
+        workspace_id = adopt_workspace_id(&root, &peer_workspace)?;
+    }
+    let base_name = base_config(&workspace_id, id);
+    let base: SyncBase = workspace::read_config(&root, &base_name)?;
+
+    let mut report = SyncReport::default();
+    let mut synced = BTreeMap::new();
+    for (path, step) in plan(&local, &remote, &base) {
+        let path = &path;
+        match step {
+            Step::Same => {
+                synced.insert(path.clone(), local[path].hash.clone());
+            }
+            Step::DeleteHere => {
+                delete_document(app, &root, path)?;
+                report.deleted_here += 1;
+            }
+            Step::DeleteThere => {
+                request(&mut stream, &Message::Delete { path: path.clone() })?;
+                report.deleted_there += 1;
+            }
+            Step::Send => {
+                synced.insert(path.clone(), push(&mut stream, &root, path)?);
+                report.sent += 1;
+            }
+            Step::Receive => {
+                if let Some(hash) = pull(app, &mut stream, &root, path)? {
+                    synced.insert(path.clone(), hash);
+                    report.received += 1;
+                }
+            }
+            Step::Conflict => {
+                let Some(their_content) = fetch(&mut stream, path)? else {
+                    continue;
+                };
+                let my_content = read_local(&root, path)?;
+                let (newer, older) = if remote[path].modified > local[path].modified {
+                    write_document(app, &root, path, &their_content)?;
+                    (their_content, my_content)
+                } else {
+                    upload(&mut stream, path, my_content.clone())?;
+                    (my_content, their_content)
+                };
+                synced.insert(path.clone(), hash(&newer));
+                let copy = write_conflict_copy(app, &root, path, &older)?;
+                synced.insert(copy.clone(), push(&mut stream, &root, &copy)?);
+                report.conflicts.push(path.clone());
+            }
+        }
+    }
+
+    request(
+        &mut stream,
+        &Message::Done {
+            synced: synced.clone(),
+        },
+    )?;
+    workspace::write_config(&root, &base_name, &SyncBase { documents: synced })?;
+    update_peer(app, id, |peer| {
+        peer.last_sync = Some(document::now_millis());
+        peer.last_address = Some(address.to_string());
+    });
+    tracing::info!(
+        received = report.received,
+        sent = report.sent,
+        conflicts = report.conflicts.len(),
+        "Synced with peer"
+    );
+    bridge::publish(
+        app,
+        &root,
+        "sync/finished",
+        serde_json::json!({ "peer": id, "report": &report }),
+    );
+    Ok(report)
+}
+
+/// Step for every document either side has or had at the last sync, given `base`, the hashes
+/// both had then: a change on one side is copied to the other, a deletion on one side deletes
+/// the other's unchanged copy, and changes on both are a conflict
+fn plan(
+    local: &BTreeMap<String, Entry>,
+    remote: &BTreeMap<String, Entry>,
+    base: &SyncBase,
+) -> Vec<(String, Step)> {
+    let paths: BTreeSet<&String> = local
+        .keys()
+        .chain(remote.keys())
+        .chain(base.documents.keys())
+        .collect();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let previous = base.documents.get(path);
+            let step = match (local.get(path), remote.get(path)) {
+                (None, None) => return None,
+                (Some(mine), Some(theirs)) if mine.hash == theirs.hash => Step::Same,
+                (Some(mine), None) if previous == Some(&mine.hash) => Step::DeleteHere,
+                (None, Some(theirs)) if previous == Some(&theirs.hash) => Step::DeleteThere,
+                (Some(_), None) => Step::Send,
+                (Some(_), Some(theirs)) if previous == Some(&theirs.hash) => Step::Send,
+                (None, Some(_)) => Step::Receive,
+                (Some(mine), Some(_)) if previous == Some(&mine.hash) => Step::Receive,
+                (Some(_), Some(_)) => Step::Conflict,
+            };
+            Some((path.clone(), step))
+        })
+        .collect()
+}
+
+/// Client side of [`pair_peer`]
+fn pair(app: &AppHandle, id: &str) -> Result<Peer, String> {
+    let identity = identity(app)?;
+    let address = resolve_address(app, id, None)?;
+    let mut stream = connect(address, None)?;
+    let peer_fingerprint = stream
+        .conn
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+        .map(|certificate| fingerprint(certificate))
+        .ok_or("Failed to connect to peer: no certificate")?;
+    let nonce = random_base64();
+    send(
+        &mut stream,
+        &Message::PairCommit {
+            device_id: identity.device_id.clone(),
+            name: identity.name.clone(),
+            fingerprint: fingerprint(&identity.certificate),
+            commitment: format!("{:x}", Sha256::digest(&nonce)),
+        },
+    )?;
+    let (peer_id, name, peer_nonce) = match receive(&mut stream)? {
+        Message::PairNonce {
+            device_id,
+            name,
+            nonce,
+        } => (device_id, name, nonce),
+        Message::Error { message } => return Err(format!("Peer refused: {}", message)),
+        _ => return Err(invalid_message()),
+    };
+    if peer_id != id {
+        return Err(format!(
+            "Pairing failed: expected {} but reached {}",
+            id, peer_id
+        ));
+    }
+    send(
+        &mut stream,
+        &Message::PairReveal {
+            nonce: nonce.clone(),
+        },
+    )?;
+    let code = pairing_code(&exporter(&stream.conn)?, &nonce, &peer_nonce);
+    let _ = stream
+        .sock
+        .set_read_timeout(Some(PAIR_TIMEOUT + IO_TIMEOUT));
+    let accepted = await_confirmation(app, &peer_id, &name, code)?;
+    send(&mut stream, &Message::PairDecision { accepted })?;
+    let peer_accepted = match receive(&mut stream)? {
+        Message::PairDecision { accepted } => accepted,
+        _ => return Err(invalid_message()),
+    };
+    if !accepted {
+        return Err("Pairing cancelled".to_string());
+    }
+    if !peer_accepted {
+        return Err("Pairing cancelled on the other device".to_string());
+    }
+    let Message::PairSecret { secret } = receive(&mut stream)? else {
+        return Err(invalid_message());
+    };
+    let record = PeerRecord {
+        id: peer_id,
+        name,
+        fingerprint: peer_fingerprint,
+        secret,
+        last_address: Some(address.to_string()),
+        last_sync: None,
+    };
+    let peer = Peer {
+        id: record.id.clone(),
+        name: record.name.clone(),
+        last_sync: None,
+    };
+    remember_peer(app, record)?;
+    tracing::info!("Peer paired");
+    Ok(peer)
+}
+
+/// Whether this device can be found and synced with, and how it appears to others
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_lan_sync_status(app: AppHandle) -> Result<LanSyncStatus, Error> {
+    let identity = identity(&app)?;
+    let port = state(&app)?.port;
+    Ok(LanSyncStatus {
+        enabled: ENABLED.load(Ordering::Relaxed),
+        device_id: identity.device_id.clone(),
+        name: identity.name.clone(),
+        port,
+    })
+}
+
+/// Turn LAN sync on or off. While on, this device answers mDNS discovery for
+/// `_inkfinite._tcp` and accepts pairing and sync connections.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_lan_sync_enabled(app: AppHandle, enabled: bool) -> Result<LanSyncStatus, Error> {
+    if enabled {
+        listen(&app)?;
+    } else {
+        ENABLED.store(false, Ordering::Relaxed);
+    }
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(ENABLED_KEY, serde_json::json!(enabled));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    get_lan_sync_status(app)
+}
+
+/// Devices on the local network with LAN sync turned on
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn discover_peers(app: AppHandle) -> Result<Vec<DiscoveredPeer>, Error> {
+    let own = identity(&app)?.device_id.clone();
+    let found = tauri::async_runtime::spawn_blocking(|| mdns::browse(SERVICE, DISCOVERY_TIMEOUT))
+        .await
+        .map_err(|e| format!("Discovery task failed: {}", e))??;
+    let paired = load_peers(&app);
+    let mut state = state(&app)?;
+    Ok(found
+        .into_iter()
+        .filter(|found| found.instance != own)
+        .map(|found| {
+            state
+                .discovered
+                .insert(found.instance.clone(), found.address);
+            DiscoveredPeer {
+                paired: paired.iter().any(|peer| peer.id == found.instance),
+                name: found
+                    .txt
+                    .get("name")
+                    .cloned()
+                    .unwrap_or_else(|| found.instance.clone()),
+                address: found.address.to_string(),
+                id: found.instance,
+            }
+        })
+        .collect())
+}
+
+/// Pair with a discovered device. Both devices get a `peer:pairing` event with the same six
+/// digit code; the pairing completes once the code is confirmed with [`confirm_pairing`] on
+/// both.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn pair_peer(app: AppHandle, id: String) -> Result<Peer, Error> {
+    tauri::async_runtime::spawn_blocking(move || pair(&app, &id))
+        .await
+        .map_err(|e| format!("Pairing task failed: {}", e))?
+        .map_err(Error::from)
+}
+
+/// Accept or reject the code shown by a `peer:pairing` event
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn confirm_pairing(app: AppHandle, peer: String, accept: bool) -> Result<(), Error> {
+    let sender = state(&app)?
+        .pending
+        .remove(&peer)
+        .ok_or_else(|| Error::not_found("Pairing request not found").with_context(&peer))?;
+    let _ = sender.send(accept);
+    Ok(())
+}
+
+/// Devices this one is paired with
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn list_paired_peers(app: AppHandle) -> Vec<Peer> {
+    load_peers(&app)
+        .into_iter()
+        .map(|peer| Peer {
+            id: peer.id,
+            name: peer.name,
+            last_sync: peer.last_sync,
+        })
+        .collect()
+}
+
+/// Forget a paired device; it has to be paired again before syncing. Returns `false` when it
+/// was not paired.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn unpair_peer(app: AppHandle, id: String) -> Result<bool, Error> {
+    let mut peers = load_peers(&app);
+    let before = peers.len();
+    peers.retain(|peer| peer.id != id);
+    if peers.len() == before {
+        return Ok(false);
+    }
+    save_peers(&app, &peers)?;
+    Ok(true)
+}
+
+/// Sync the open workspace's documents with the workspace open on a paired device, directly
+/// over TLS. Vault documents are not synced.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn sync_with_peer(app: AppHandle, id: String) -> Result<SyncReport, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        event_bus::publish(&app, BusEvent::SyncStarted { peer: id.clone() });
+        let result = sync(&app, &id);
+        let peer = id;
+        event_bus::publish(
+            &app,
+            match &result {
+                Ok(report) => BusEvent::SyncFinished {
+                    peer,
+                    report: report.clone(),
+                },
+                Err(error) => BusEvent::SyncFailed {
+                    peer,
+                    error: error.clone(),
+                },
+            },
+        );
+        result.map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Sync task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(documents: &[(&str, &str)]) -> BTreeMap<String, Entry> {
+        documents
+            .iter()
+            .map(|(path, hash)| {
+                let entry = Entry {
+                    hash: hash.to_string(),
+                    modified: 0,
+                };
+                (path.to_string(), entry)
+            })
+            .collect()
+    }
+
+    fn base(documents: &[(&str, &str)]) -> SyncBase {
+        SyncBase {
+            documents: documents
+                .iter()
+                .map(|(path, hash)| (path.to_string(), hash.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn the_same_workspace_syncs() {
+        assert_eq!(match_workspace("a", false, "a", false), Ok(false));
+    }
+
+    #[test]
+    fn an_empty_workspace_takes_the_peers_id() {
+        assert_eq!(match_workspace("new", true, "a", false), Ok(true));
+        assert_eq!(match_workspace("a", false, "new", true), Ok(false));
+    }
+
+    #[test]
+    fn a_different_workspace_is_refused() {
+        assert!(match_workspace("a", false, "b", false).is_err());
+    }
+
+    #[test]
+    fn bases_are_kept_per_workspace() {
+        assert_ne!(base_config("a", "peer"), base_config("b", "peer"));
+    }
+
+    #[test]
+    fn a_workspace_id_mismatch_deletes_nothing() {
+        let local = entries(&[("notes.inkfinite.json", "1"), ("plan.inkfinite.json", "2")]);
+        let remote = entries(&[("other.inkfinite.json", "3")]);
+        // Against the base of the last sync, the documents the peer lacks would be deleted
+        let last = base(&[("notes.inkfinite.json", "1"), ("plan.inkfinite.json", "2")]);
+        assert!(plan(&local, &remote, &last)
+            .iter()
+            .any(|(_, step)| *step == Step::DeleteHere));
+
+        // A peer with another workspace is refused, and no base is shared with it
+        assert!(match_workspace("a", false, "b", false).is_err());
+        let steps = plan(&local, &remote, &SyncBase::default());
+        assert!(steps
+            .iter()
+            .all(|(_, step)| !matches!(step, Step::DeleteHere | Step::DeleteThere)));
+    }
+
+    #[test]
+    fn changes_and_deletions_are_planned_against_the_base() {
+        let local = entries(&[("same", "1"), ("mine", "2b"), ("both", "3b"), ("kept", "4")]);
+        let remote = entries(&[("same", "1"), ("mine", "2"), ("both", "3c"), ("new", "5")]);
+        let last = base(&[
+            ("same", "1"),
+            ("mine", "2"),
+            ("both", "3"),
+            ("kept", "4"),
+            ("gone", "6"),
+        ]);
+        let steps: BTreeMap<String, Step> = plan(&local, &remote, &last).into_iter().collect();
+        assert_eq!(steps["same"], Step::Same);
+        assert_eq!(steps["mine"], Step::Send);
+        assert_eq!(steps["both"], Step::Conflict);
+        assert_eq!(steps["kept"], Step::DeleteHere);
+        assert_eq!(steps["new"], Step::Receive);
+        assert!(!steps.contains_key("gone"));
+    }
+}
@@ -1,8 +1,155 @@
+mod analytics;
+mod archive;
+mod asset_gc;
+mod assets;
+mod attachments;
+mod audio;
+mod audit;
+mod automation;
+mod biometric;
+mod blocking;
+mod blocks;
+mod bootstrap;
+mod bridge;
+mod bulk;
+mod calendar;
+mod cancel;
+mod catalog;
+#[cfg(desktop)]
+mod chat;
+mod chunks;
+mod clipboard;
+mod collab;
+mod conditions;
+mod confirm;
+#[cfg(desktop)]
+mod context_menu;
+mod crash;
+mod csl;
+mod daily_notes;
+mod deep_link;
+mod diagnostics;
+mod dir_cache;
+mod drag_out;
+mod duplicates;
+mod email;
+#[cfg(desktop)]
+mod embeddings;
+mod error;
+mod event_bus;
+mod events;
+mod exports;
+mod external_edit;
+mod figures;
+mod file_open;
+mod focus;
+mod gist;
+mod grammar;
+mod handoff;
+mod headless;
+mod hooks;
+mod hotkeys;
+mod http;
+mod inbox;
+mod ink;
+mod insights;
+mod jobs;
+mod journal;
+mod kanban;
+mod keybindings;
+mod lan_sync;
+mod licensing;
+mod link_index;
+#[cfg(desktop)]
+mod llm;
+mod local_api;
+mod localize;
+mod lock;
+mod logging;
+mod macros;
+mod mdns;
+#[cfg(desktop)]
+mod menu;
+mod messages;
+mod metadata;
+mod ocr;
+mod optimize;
+mod outline;
+mod packages;
+mod pandoc;
+mod paths;
+mod pdf;
+mod perf;
+#[cfg(desktop)]
+mod plugins;
+mod power;
+mod preview;
+mod profile;
+#[cfg(desktop)]
+mod prompts;
+#[cfg(target_os = "android")]
+mod provider;
+#[cfg(desktop)]
+mod providers;
+mod publish;
+mod read_only;
+#[cfg(desktop)]
+mod recents;
+mod references;
+#[cfg(desktop)]
+mod related;
+mod reminders;
+mod retag;
+mod safe_mode;
+mod sanitize;
+mod saves;
+mod scan;
+mod screenshot;
+mod search;
+mod secrets;
+mod session;
+mod settings;
+#[cfg(mobile)]
+mod share;
+mod shred;
+mod site;
+mod spellcheck;
+mod startup;
+mod stats;
+mod svg;
+#[cfg(desktop)]
+mod tagging;
+mod tasks;
+mod templates;
+mod theme;
+mod thumbnails;
+mod tools;
+mod transcribe;
+mod tray;
+#[cfg(desktop)]
+mod updater;
+mod vault;
+#[cfg(desktop)]
+mod vectors;
+mod video;
+mod webhooks;
+mod websocket;
+mod windows;
+mod workspace;
+mod workspaces;
+mod x_callback;
+
+use audit::{AuditAction, AuditSource};
+use error::Error;
+use inkfinite_core::{crypto, document};
 use std::fs;
-use std::path::{Path, PathBuf};
-use tauri::AppHandle;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+/// Files [`read_directory`] lists when no pattern is given
+const DEFAULT_PATTERN: &str = "*.inkfinite.json";
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct FileEntry {
     pub path: String,
     pub name: String,
@@ -11,26 +158,71 @@ pub struct FileEntry {
 
 /// Read directory contents and return matching files
 #[tauri::command]
-fn read_directory(directory: String, pattern: Option<String>) -> Result<Vec<FileEntry>, String> {
-    let path = Path::new(&directory);
+#[tracing::instrument(level = "debug", skip_all, err)]
+async fn read_directory(
+    app: AppHandle,
+    directory: String,
+    pattern: Option<String>,
+) -> Result<Vec<FileEntry>, Error> {
+    paths::check(&app, &directory, paths::Scope::Read)?;
+    blocking::run("read directory", move || {
+        let pattern = pattern.unwrap_or_else(|| DEFAULT_PATTERN.to_string());
+        let cache = app.state::<dir_cache::DirectoryCache>();
+        if vault::is_locked(Path::new(&directory)) {
+            return Ok(Vec::new());
+        }
+        if let Some(entries) = cache.get(Path::new(&directory), &pattern) {
+            return Ok::<_, Error>(entries);
+        }
+        let entries = list_directory(&directory, &pattern)?;
+        cache.insert(Path::new(&directory), &pattern, &entries);
+        Ok(entries)
+    })
+    .await
+}
+
+/// Re-read a directory, replacing any cached listing
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+async fn refresh_directory(
+    app: AppHandle,
+    directory: String,
+    pattern: Option<String>,
+) -> Result<Vec<FileEntry>, Error> {
+    paths::check(&app, &directory, paths::Scope::Read)?;
+    blocking::run("read directory", move || {
+        let pattern = pattern.unwrap_or_else(|| DEFAULT_PATTERN.to_string());
+        let entries = list_directory(&directory, &pattern)?;
+        app.state::<dir_cache::DirectoryCache>()
+            .insert(Path::new(&directory), &pattern, &entries);
+        Ok::<_, Error>(entries)
+    })
+    .await
+}
+
+fn list_directory(directory: &str, pattern: &str) -> Result<Vec<FileEntry>, Error> {
+    let path = Path::new(directory);
     if !path.exists() {
-        return Err(format!("Directory does not exist: {}", directory));
+        return Err(Error::not_found("Directory does not exist").with_context(directory));
     }
     if !path.is_dir() {
-        return Err(format!("Path is not a directory: {}", directory));
+        return Err(Error::invalid_path("Path is not a directory").with_context(directory));
+    }
+    if vault::is_locked(path) {
+        return Ok(Vec::new());
     }
 
-    let entries = fs::read_dir(path).map_err(|e| format!("Failed to read directory: {}", e))?;
+    let entries = fs::read_dir(path)
+        .map_err(|e| Error::io("Failed to read directory", e).with_context(directory))?;
 
     let mut results = Vec::new();
-    let pattern = pattern.unwrap_or_else(|| "*.inkfinite.json".to_string());
 
     for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let entry = entry.map_err(|e| Error::io("Failed to read entry", e))?;
         let entry_path = entry.path();
-        let metadata = entry
-            .metadata()
-            .map_err(|e| format!("Failed to read metadata: {}", e))?;
+        let metadata = entry.metadata().map_err(|e| {
+            Error::io("Failed to read metadata", e).with_context(entry_path.display())
+        })?;
 
         let name = entry.file_name().to_string_lossy().to_string();
 
@@ -40,7 +232,7 @@ fn read_directory(directory: String, pattern: Option<String>) -> Result<Vec<File
                 if !name.contains(&pattern_without_star) {
                     continue;
                 }
-            } else if !name.ends_with(&pattern) {
+            } else if !name.ends_with(pattern) {
                 continue;
             }
         }
@@ -68,63 +260,589 @@ fn read_directory(directory: String, pattern: Option<String>) -> Result<Vec<File
 
 /// Rename a file
 #[tauri::command]
-fn rename_file(old_path: String, new_path: String) -> Result<(), String> {
-    let old = Path::new(&old_path);
-    let new = Path::new(&new_path);
+#[tracing::instrument(level = "debug", skip_all, err)]
+async fn rename_file(app: AppHandle, old_path: String, new_path: String) -> Result<(), Error> {
+    paths::check(&app, &old_path, paths::Scope::Write)?;
+    paths::check(&app, &new_path, paths::Scope::Write)?;
+    blocking::run("rename file", move || {
+        let old = Path::new(&old_path);
+        let new = Path::new(&new_path);
 
-    if !old.exists() {
-        return Err(format!("Source file does not exist: {}", old_path));
-    }
+        if !old.exists() {
+            return Err(Error::not_found("Source file does not exist").with_context(&old_path));
+        }
+        if new.exists() {
+            return Err(
+                Error::conflict("A file with that name already exists").with_context(&new_path)
+            );
+        }
 
-    fs::rename(old, new).map_err(|e| format!("Failed to rename file: {}", e))?;
+        vault::rename(old, new).map_err(|e| Error::from(e).with_context(&old_path))?;
+        events::file_renamed(&app, old, new);
+        audit::record_current(&app, AuditAction::Rename, AuditSource::Ui, &[old, new]);
 
-    Ok(())
+        Ok(())
+    })
+    .await
 }
 
 /// Delete a file
 #[tauri::command]
-fn delete_file(file_path: String) -> Result<(), String> {
-    let path = Path::new(&file_path);
+#[tracing::instrument(level = "debug", skip_all, err)]
+async fn delete_file(app: AppHandle, file_path: String) -> Result<(), Error> {
+    paths::check(&app, &file_path, paths::Scope::Write)?;
+    blocking::run("delete file", move || {
+        let path = Path::new(&file_path);
 
-    if !path.exists() {
-        return Err(format!("File does not exist: {}", file_path));
-    }
+        if !path.exists() {
+            return Err(Error::not_found("File does not exist").with_context(&file_path));
+        }
 
-    if path.is_dir() {
-        return Err(format!("Path is a directory, not a file: {}", file_path));
-    }
+        if path.is_dir() {
+            return Err(
+                Error::invalid_path("Path is a directory, not a file").with_context(&file_path)
+            );
+        }
 
-    fs::remove_file(path).map_err(|e| format!("Failed to delete file: {}", e))?;
+        fs::remove_file(path)
+            .map_err(|e| Error::io("Failed to delete file", e).with_context(&file_path))?;
+        events::file_changed(&app, path, events::ChangeKind::Deleted);
+        audit::record_current(&app, AuditAction::Delete, AuditSource::Ui, &[path]);
 
-    Ok(())
+        Ok(())
+    })
+    .await
 }
 
 /// Pick a workspace directory using the system folder picker
 #[tauri::command]
-async fn pick_workspace_directory(app: AppHandle) -> Result<Option<String>, String> {
-    use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+#[tracing::instrument(level = "debug", skip_all, err)]
+async fn pick_workspace_directory(app: AppHandle) -> Result<Option<String>, Error> {
+    use tauri_plugin_dialog::DialogExt;
 
     let result = app.dialog().file().blocking_pick_folder();
 
     match result {
-        Some(path) => Ok(Some(path.to_string_lossy().to_string())),
+        Some(path) => Ok(Some(path.to_string())),
         None => Ok(None),
     }
 }
 
+/// Run a `--headless <job file>` launch to completion without starting the app, returning the
+/// exit status; `None` when the arguments ask for the app
+pub fn run_headless(args: &[String]) -> Option<i32> {
+    headless::run(args)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    startup::launched();
+    let mut builder = tauri::Builder::default();
+    // Must be registered first so a second launch exits before other plugins start
+    #[cfg(desktop)]
+    {
+        builder = builder
+            .plugin(tauri_plugin_single_instance::init(
+                deep_link::on_second_instance,
+            ))
+            .menu(menu::build)
+            .on_menu_event(menu::handle_event)
+            .manage(context_menu::ContextMenuState::default())
+            .manage(plugins::Plugins::default())
+            .manage(llm::LocalLlm::default())
+            .manage(embeddings::Embedder::default())
+            .manage(vectors::VectorIndex::default())
+            .manage(related::RelatedCache::default())
+            .manage(updater::Updates::default());
+    }
+    #[cfg(mobile)]
+    {
+        builder = builder.plugin(tauri_plugin_biometric::init());
+    }
+
+    builder
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::default().build())
-        .invoke_handler(tauri::generate_handler![
-            read_directory,
-            rename_file,
-            delete_file,
-            pick_workspace_directory
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .manage(audio::AudioRecorder::default())
+        .manage(tray::TrayState::default())
+        .manage(deep_link::PendingNavigation::default())
+        .manage(windows::WindowRegistry::default())
+        .manage(spellcheck::SpellChecker::default())
+        .manage(grammar::GrammarServer::default())
+        .manage(external_edit::ExternalEdits::default())
+        .manage(jobs::Jobs::default())
+        .manage(dir_cache::DirectoryCache::default())
+        .manage(chunks::ChunkStreams::default())
+        .manage(saves::SaveCoordinator::default())
+        .manage(blocks::BlockIndexes::default())
+        .manage(link_index::LinkIndex::default())
+        .manage(catalog::Catalog::default())
+        .manage(cancel::Operations::default())
+        .manage(startup::Startup::default())
+        .manage(lock::Keyring::default())
+        .manage(confirm::Confirmations::default())
+        .manage(lan_sync::LanSync::default())
+        .manage(collab::Collaborations::default())
+        .manage(local_api::LocalApi::default())
+        .manage(webhooks::WebhookQueue::default())
+        .manage(hooks::HookQueue::default())
+        .manage(bridge::EventBridge::default())
+        .manage(event_bus::EventBus::default())
+        .manage(focus::FocusSessions::default())
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::CloseRequested { .. } => {
+                saves::flush_all(window.app_handle());
+                if let Some(window) = window.get_webview_window(window.label()) {
+                    session::save_geometry(&window);
+                }
+            }
+            tauri::WindowEvent::Focused(false) => saves::flush_all(window.app_handle()),
+            tauri::WindowEvent::Focused(true) => {
+                let state = window
+                    .state::<windows::WindowRegistry>()
+                    .get(window.label());
+                handoff::publish(window.app_handle(), state.document.as_deref());
+            }
+            tauri::WindowEvent::ThemeChanged(_) => theme::changed(window.app_handle()),
+            tauri::WindowEvent::Destroyed => {
+                workspaces::window_closed(window.app_handle(), window.label());
+                event_bus::window_closed(window.app_handle(), window.label());
+                #[cfg(desktop)]
+                updater::window_closed(window.app_handle(), window.label());
+            }
+            _ => {}
+        })
+        .setup(|app| {
+            // Logging must not keep the app from starting
+            if let Err(error) = logging::init(app.handle()) {
+                eprintln!("{}", error);
+            }
+            crash::init(app.handle());
+            safe_mode::init(app.handle());
+            read_only::load(app.handle());
+            settings::load(app.handle());
+            messages::load(app.handle());
+            exports::start_scheduler(app.handle().clone());
+            asset_gc::start(app.handle().clone());
+            reminders::start(app.handle().clone());
+            focus::start(app.handle().clone());
+            calendar::start(app.handle().clone());
+            theme::start(app.handle().clone());
+            lock::start(app.handle().clone());
+            lan_sync::start(app.handle().clone());
+            local_api::start(app.handle().clone());
+            webhooks::start(app.handle().clone());
+            hooks::start(app.handle().clone());
+            bridge::start(app.handle().clone());
+            event_bus::start(app.handle().clone());
+            saves::start(app.handle().clone());
+            analytics::start(app.handle().clone());
+            deep_link::init(app.handle())?;
+            handoff::init(app.handle());
+            #[cfg(desktop)]
+            {
+                tray::create(app.handle())?;
+                recents::update(app.handle());
+                app.handle()
+                    .plugin(tauri_plugin_global_shortcut::Builder::new().build())?;
+                hotkeys::register_all(app.handle());
+                plugins::start(app.handle().clone());
+                app.handle()
+                    .plugin(tauri_plugin_updater::Builder::new().build())?;
+                updater::start(app.handle().clone());
+            }
+            startup::setup_finished(app.handle());
+            startup::warm_up(app.handle().clone());
+            Ok(())
+        })
+        // Wrapped to record request sizes, which command spans skip along with the arguments,
+        // and activity for auto-lock
+        .invoke_handler({
+            let handler: fn(tauri::ipc::Invoke) -> bool = tauri::generate_handler![
+                read_directory,
+                refresh_directory,
+                rename_file,
+                delete_file,
+                chunks::read_file_chunks,
+                chunks::ack_file_chunks,
+                chunks::cancel_file_read,
+                blocks::open_indexed_document,
+                blocks::load_blocks,
+                cancel::cancel_operation,
+                catalog::list_workspace_documents,
+                catalog::get_workspace_stats,
+                catalog::rebuild_workspace_cache,
+                outline::extract_outline,
+                link_index::link_suggestions,
+                link_index::set_document_aliases,
+                tasks::query_tasks,
+                tasks::toggle_task,
+                kanban::board_view,
+                kanban::move_card,
+                archive::get_archive_config,
+                archive::set_archive_config,
+                archive::archive_document,
+                archive::unarchive_document,
+                archive::list_archived,
+                duplicates::find_duplicate_documents,
+                duplicates::merge_documents,
+                retag::rename_tag,
+                retag::merge_tags,
+                insights::document_insights,
+                insights::stale_documents,
+                insights::workspace_insights,
+                stats::writing_stats,
+                stats::get_writing_goals,
+                stats::set_writing_goals,
+                saves::write_document,
+                saves::read_document,
+                saves::flush_documents,
+                saves::get_save_delay,
+                saves::set_save_delay,
+                logging::get_log_tail,
+                logging::set_log_level,
+                crash::list_crash_reports,
+                crash::get_crash_report,
+                crash::submit_crash_report,
+                crash::delete_crash_report,
+                perf::get_performance_report,
+                perf::profile_operation,
+                analytics::track_feature,
+                analytics::get_usage_dashboard,
+                analytics::export_usage_analytics,
+                analytics::upload_usage_analytics,
+                analytics::clear_usage_analytics,
+                startup::startup_report,
+                diagnostics::run_diagnostics,
+                safe_mode::get_safe_mode,
+                safe_mode::set_safe_mode_component,
+                safe_mode::exit_safe_mode,
+                licensing::get_license_status,
+                licensing::activate_license,
+                licensing::deactivate_license,
+                pick_workspace_directory,
+                bootstrap::bootstrap_workspace,
+                pandoc::get_pandoc_info,
+                pandoc::export_via_pandoc,
+                pandoc::import_via_pandoc,
+                site::publish_static_site,
+                exports::list_export_rules,
+                exports::save_export_rule,
+                exports::delete_export_rule,
+                exports::run_export_rule,
+                clipboard::save_clipboard_image,
+                assets::import_asset,
+                svg::sanitize_svg,
+                asset_gc::gc_status,
+                asset_gc::set_gc_policy,
+                asset_gc::run_asset_gc,
+                localize::localize_remote_assets,
+                metadata::get_privacy_settings,
+                metadata::set_privacy_settings,
+                metadata::strip_asset_metadata,
+                optimize::get_image_settings,
+                optimize::set_image_settings,
+                optimize::optimize_existing_assets,
+                attachments::attach_file,
+                attachments::list_attachments,
+                attachments::detach,
+                ocr::ocr_asset,
+                ink::recognize_ink,
+                search::search_workspace,
+                scan::scan_workspace,
+                thumbnails::get_thumbnail,
+                thumbnails::get_thumbnail_data,
+                thumbnails::clear_thumbnail_cache,
+                video::video_info,
+                pdf::render_pdf_page,
+                pdf::pdf_info,
+                audio::start_audio_recording,
+                audio::stop_audio_recording,
+                transcribe::transcribe_audio,
+                screenshot::capture_screenshot,
+                drag_out::start_drag,
+                inbox::get_inbox_document,
+                inbox::set_inbox_document,
+                inbox::quick_capture,
+                tray::refresh_tray,
+                tray::set_tray_sync_status,
+                hotkeys::get_hotkeys,
+                hotkeys::set_hotkeys,
+                keybindings::get_keybindings,
+                keybindings::set_keybindings,
+                keybindings::resolve_shortcut_conflicts,
+                deep_link::take_pending_navigation,
+                windows::open_document_window,
+                windows::list_windows,
+                windows::get_window_state,
+                windows::set_window_state,
+                workspaces::open_workspace,
+                workspaces::close_workspace,
+                workspaces::list_open_workspaces,
+                #[cfg(desktop)]
+                menu::refresh_menu,
+                spellcheck::list_words,
+                spellcheck::add_word,
+                spellcheck::remove_word,
+                spellcheck::spellcheck,
+                spellcheck::list_languages,
+                grammar::check_text,
+                grammar::get_grammar_config,
+                grammar::set_grammar_config,
+                grammar::ignore_grammar_issue,
+                preview::render_preview,
+                preview::render_preview_data,
+                reminders::list_upcoming_reminders,
+                reminders::snooze_reminder,
+                reminders::complete_reminder,
+                reminders::set_reminder,
+                focus::start_focus_session,
+                focus::get_focus_session,
+                focus::pause_focus_session,
+                focus::stop_focus_session,
+                focus::focus_history,
+                external_edit::get_external_editor,
+                external_edit::set_external_editor,
+                external_edit::edit_externally,
+                external_edit::finish_external_edit,
+                session::save_session,
+                session::restore_session,
+                theme::get_system_theme,
+                conditions::get_system_conditions,
+                conditions::get_condition_policy,
+                conditions::set_condition_policy,
+                jobs::enqueue_job,
+                jobs::list_jobs,
+                jobs::cancel_job,
+                lock::get_lock_status,
+                lock::set_lock_passphrase,
+                lock::lock_workspace,
+                lock::unlock_workspace,
+                lock::set_auto_lock,
+                secrets::store_secret,
+                secrets::get_secret,
+                secrets::delete_secret,
+                secrets::list_secrets,
+                vault::create_vault,
+                vault::unlock_vault,
+                vault::lock_vault,
+                vault::list_vaults,
+                audit::query_audit_log,
+                bulk::preview_delete_files,
+                bulk::delete_files,
+                bulk::preview_replace_in_workspace,
+                bulk::replace_in_workspace,
+                shred::secure_delete,
+                read_only::get_workspace_readonly,
+                read_only::set_workspace_readonly,
+                settings::get_settings_schema,
+                settings::get_settings,
+                settings::update_settings,
+                messages::get_locale,
+                messages::set_locale,
+                profile::export_app_profile,
+                profile::import_app_profile,
+                sanitize::sanitize_html,
+                sanitize::get_sanitize_policy,
+                sanitize::set_sanitize_policy,
+                biometric::get_biometric_status,
+                biometric::enable_biometric_unlock,
+                biometric::disable_biometric_unlock,
+                biometric::unlock_with_biometric,
+                lan_sync::get_lan_sync_status,
+                lan_sync::set_lan_sync_enabled,
+                lan_sync::discover_peers,
+                lan_sync::pair_peer,
+                lan_sync::confirm_pairing,
+                lan_sync::list_paired_peers,
+                lan_sync::unpair_peer,
+                lan_sync::sync_with_peer,
+                collab::start_collaboration,
+                collab::get_collab_document,
+                collab::apply_collab_changes,
+                collab::update_presence,
+                collab::list_collab_peers,
+                collab::stop_collaboration,
+                publish::publish_document,
+                publish::unpublish_document,
+                publish::list_publications,
+                local_api::get_local_api_status,
+                local_api::set_local_api_enabled,
+                local_api::regenerate_local_api_token,
+                email::email_document,
+                email::get_email_account,
+                email::set_email_account,
+                calendar::list_calendar_feeds,
+                calendar::add_calendar_feed,
+                calendar::remove_calendar_feed,
+                calendar::refresh_calendars,
+                calendar::list_calendar_events,
+                calendar::get_daily_agenda,
+                calendar::export_reminder_calendar,
+                references::get_reference_library,
+                references::import_references,
+                references::refresh_references,
+                references::search_references,
+                references::list_citation_styles,
+                references::format_citation,
+                references::format_bibliography,
+                figures::render_math,
+                figures::render_diagram,
+                references::download_citation_style,
+                references::delete_citation_style,
+                gist::publish_gist,
+                gist::unlink_gist,
+                webhooks::list_webhooks,
+                webhooks::save_webhook,
+                webhooks::remove_webhook,
+                webhooks::test_webhook,
+                webhooks::list_webhook_deliveries,
+                hooks::list_hooks,
+                hooks::save_hook,
+                hooks::remove_hook,
+                hooks::run_hook,
+                hooks::list_hook_runs,
+                bridge::get_event_bridge,
+                bridge::set_event_bridge,
+                #[cfg(desktop)]
+                plugins::list_plugins,
+                #[cfg(desktop)]
+                plugins::set_plugin_enabled,
+                #[cfg(desktop)]
+                plugins::reload_plugins,
+                #[cfg(desktop)]
+                plugins::list_plugin_commands,
+                #[cfg(desktop)]
+                plugins::run_plugin_command,
+                #[cfg(desktop)]
+                plugins::export_with_plugin,
+                automation::run_automation,
+                templates::list_templates,
+                templates::read_template,
+                templates::save_template,
+                templates::delete_template,
+                templates::preview_template,
+                templates::create_from_template,
+                daily_notes::open_daily_note,
+                daily_notes::list_daily_notes,
+                daily_notes::get_daily_notes_config,
+                daily_notes::set_daily_notes_config,
+                journal::get_todays_prompt,
+                journal::get_journal_streak,
+                journal::get_journal_config,
+                journal::set_journal_config,
+                macros::list_macros,
+                macros::read_macro,
+                macros::save_macro,
+                macros::delete_macro,
+                macros::run_macro,
+                event_bus::list_event_topics,
+                event_bus::subscribe_events,
+                event_bus::unsubscribe_events,
+                packages::list_packages,
+                packages::fetch_package_index,
+                packages::install_package,
+                packages::update_package,
+                packages::remove_package,
+                packages::get_package_keys,
+                packages::set_package_keys,
+                #[cfg(desktop)]
+                llm::summarize_document,
+                #[cfg(desktop)]
+                llm::rewrite_selection,
+                #[cfg(desktop)]
+                llm::generate_outline,
+                #[cfg(desktop)]
+                llm::list_llm_models,
+                #[cfg(desktop)]
+                llm::set_llm_model,
+                #[cfg(desktop)]
+                llm::download_llm_model,
+                #[cfg(desktop)]
+                llm::delete_llm_model,
+                #[cfg(desktop)]
+                llm::get_model_compute,
+                #[cfg(desktop)]
+                llm::set_model_compute,
+                #[cfg(desktop)]
+                embeddings::list_available_models,
+                #[cfg(desktop)]
+                embeddings::download_model,
+                #[cfg(desktop)]
+                embeddings::set_embedding_model,
+                #[cfg(desktop)]
+                embeddings::delete_model,
+                #[cfg(desktop)]
+                providers::list_ai_providers,
+                #[cfg(desktop)]
+                providers::save_ai_provider,
+                #[cfg(desktop)]
+                providers::delete_ai_provider,
+                #[cfg(desktop)]
+                providers::get_ai_policy,
+                #[cfg(desktop)]
+                providers::set_ai_policy,
+                #[cfg(desktop)]
+                providers::ai_complete,
+                #[cfg(desktop)]
+                tagging::suggest_tags,
+                #[cfg(desktop)]
+                tagging::list_untagged_documents,
+                #[cfg(desktop)]
+                tagging::apply_tags,
+                #[cfg(desktop)]
+                prompts::list_prompt_templates,
+                #[cfg(desktop)]
+                prompts::save_prompt_template,
+                #[cfg(desktop)]
+                prompts::delete_prompt_template,
+                #[cfg(desktop)]
+                prompts::run_prompt_template,
+                #[cfg(desktop)]
+                vectors::update_embeddings_index,
+                #[cfg(desktop)]
+                vectors::get_embeddings_index_status,
+                #[cfg(desktop)]
+                chat::ask_workspace,
+                #[cfg(desktop)]
+                related::related_documents,
+                #[cfg(desktop)]
+                updater::check_for_updates,
+                #[cfg(desktop)]
+                updater::get_update_status,
+                #[cfg(desktop)]
+                updater::set_unsaved_changes,
+                #[cfg(desktop)]
+                updater::restart_to_update,
+                #[cfg(desktop)]
+                context_menu::show_context_menu
+            ];
+            move |invoke| {
+                let app = invoke.message.webview_ref().app_handle();
+                if let Err(error) = lock::check_command(app, invoke.message.command()) {
+                    invoke.resolver.reject(error);
+                    return true;
+                }
+                perf::record_payload(invoke.message.command(), invoke.message.payload());
+                analytics::record_use(invoke.message.command());
+                handler(invoke)
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| match event {
+            tauri::RunEvent::Exit => {
+                saves::flush_all(app);
+                let _ = analytics::flush(app);
+                grammar::stop(app);
+            }
+            // Finder delivers documents opened with the app as open events rather than arguments
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            tauri::RunEvent::Opened { urls } => file_open::open_urls(app, &urls),
+            _ => {}
+        });
 }
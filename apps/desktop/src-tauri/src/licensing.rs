@@ -0,0 +1,251 @@
+use crate::error::Error;
+use crate::{document, workspace};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_store::StoreExt;
+
+/// Ed25519 public key, base64, that license keys are signed with, set by release builds; other
+/// builds cannot be licensed
+const PUBLIC_KEY: Option<&str> = option_env!("INKFINITE_LICENSE_PUBKEY");
+/// Store key of this device's activation
+const LICENSE_KEY: &str = "license";
+/// Store key of the id this installation activates licenses under
+const DEVICE_KEY: &str = "deviceId";
+const CHANGED_EVENT: &str = "license:changed";
+/// How long paid features keep working after a license expires, so a renewal can catch up
+const GRACE_MILLIS: i64 = 14 * 24 * 60 * 60 * 1000;
+
+/// Features a license can unlock. None is gated yet; the frontend reads them as capability
+/// flags so a feature can be gated without another release of the backend.
+pub const FEATURES: &[&str] = &["sync", "publish", "collaboration", "ai", "themes"];
+
+/// What a license key grants, signed by the issuer
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Claims {
+    id: String,
+    licensee: String,
+    plan: String,
+    features: BTreeSet<String>,
+    issued_at: i64,
+    /// Perpetual licenses have none
+    expires_at: Option<i64>,
+}
+
+/// The license activated on this device
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Activation {
+    key: String,
+    device_id: String,
+    activated_at: i64,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum LicenseState {
+    Unlicensed,
+    Active,
+    /// Expired, but still within the grace period
+    Grace,
+    Expired,
+    /// The stored key no longer verifies or was activated on another device
+    Invalid,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LicenseStatus {
+    pub state: LicenseState,
+    pub license_id: Option<String>,
+    pub licensee: Option<String>,
+    pub plan: Option<String>,
+    pub expires_at: Option<i64>,
+    pub activated_at: Option<i64>,
+    pub device_id: String,
+    /// Whether this build can verify license keys at all
+    pub supported: bool,
+    /// Every feature in [`FEATURES`] and whether it is unlocked
+    pub capabilities: BTreeMap<&'static str, bool>,
+    /// Why the stored license is not in effect
+    pub message: Option<String>,
+}
+
+/// Check a key of the form `<claims>.<signature>`, both base64url, against [`PUBLIC_KEY`]
+fn verify(key: &str) -> Result<Claims, String> {
+    let public_key =
+        PUBLIC_KEY.ok_or_else(|| "Licensing is not supported in this build".to_string())?;
+    let public_key = base64::engine::general_purpose::STANDARD
+        .decode(public_key.trim())
+        .map_err(|e| format!("Invalid license public key: {}", e))?;
+    let key: String = key.chars().filter(|c| !c.is_whitespace()).collect();
+    let (claims, signature) = key
+        .split_once('.')
+        .ok_or_else(|| "Invalid license key".to_string())?;
+    let signature = BASE64
+        .decode(signature)
+        .map_err(|_| "Invalid license key".to_string())?;
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(claims.as_bytes(), &signature)
+        .map_err(|_| "Invalid license key: the signature does not match".to_string())?;
+    let claims = BASE64
+        .decode(claims)
+        .map_err(|_| "Invalid license key".to_string())?;
+    serde_json::from_slice(&claims).map_err(|e| format!("Invalid license key: {}", e))
+}
+
+/// The id licenses are activated under, created the first time it is needed
+fn device_id(app: &AppHandle) -> Result<String, String> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    if let Some(id) = store
+        .get(DEVICE_KEY)
+        .and_then(|v| v.as_str().map(String::from))
+    {
+        return Ok(id);
+    }
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    store.set(DEVICE_KEY, id.clone());
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(id)
+}
+
+fn activation(app: &AppHandle) -> Option<Activation> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(LICENSE_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+}
+
+fn unlicensed(device_id: String, message: Option<String>) -> LicenseStatus {
+    LicenseStatus {
+        state: LicenseState::Unlicensed,
+        license_id: None,
+        licensee: None,
+        plan: None,
+        expires_at: None,
+        activated_at: None,
+        device_id,
+        supported: PUBLIC_KEY.is_some(),
+        capabilities: FEATURES.iter().map(|feature| (*feature, false)).collect(),
+        message,
+    }
+}
+
+/// Verify the stored activation again; nothing is cached, so an edited store or a clock past
+/// the expiry takes effect on the next check
+fn status(app: &AppHandle) -> Result<LicenseStatus, String> {
+    let device_id = device_id(app)?;
+    let Some(activation) = activation(app) else {
+        return Ok(unlicensed(device_id, None));
+    };
+    let claims = match verify(&activation.key) {
+        Ok(claims) => claims,
+        Err(error) => {
+            let mut status = unlicensed(device_id, Some(error));
+            status.state = LicenseState::Invalid;
+            return Ok(status);
+        }
+    };
+    let now = document::now_millis();
+    let (state, message) = if activation.device_id != device_id {
+        (
+            LicenseState::Invalid,
+            Some("The license was activated on another device".to_string()),
+        )
+    } else {
+        match claims.expires_at {
+            Some(expires) if now >= expires + GRACE_MILLIS => (
+                LicenseState::Expired,
+                Some("The license has expired".to_string()),
+            ),
+            Some(expires) if now >= expires => (
+                LicenseState::Grace,
+                Some("The license has expired; renew it to keep paid features".to_string()),
+            ),
+            _ => (LicenseState::Active, None),
+        }
+    };
+    let unlocked = matches!(state, LicenseState::Active | LicenseState::Grace);
+    Ok(LicenseStatus {
+        state,
+        capabilities: FEATURES
+            .iter()
+            .map(|feature| (*feature, unlocked && claims.features.contains(*feature)))
+            .collect(),
+        license_id: Some(claims.id),
+        licensee: Some(claims.licensee),
+        plan: Some(claims.plan),
+        expires_at: claims.expires_at,
+        activated_at: Some(activation.activated_at),
+        device_id,
+        supported: PUBLIC_KEY.is_some(),
+        message,
+    })
+}
+
+/// The license on this device, whether it is in effect and the capability flags it unlocks
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_license_status(app: AppHandle) -> Result<LicenseStatus, Error> {
+    Ok(status(&app)?)
+}
+
+/// Verify a license key offline and activate it on this device, replacing any license
+/// activated before. Emits `license:changed`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn activate_license(app: AppHandle, key: String) -> Result<LicenseStatus, Error> {
+    let claims = verify(&key)?;
+    if claims
+        .expires_at
+        .is_some_and(|expires| document::now_millis() >= expires + GRACE_MILLIS)
+    {
+        return Err("Invalid license key: it has expired".into());
+    }
+    let activation = Activation {
+        key: key.split_whitespace().collect(),
+        device_id: device_id(&app)?,
+        activated_at: document::now_millis(),
+    };
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(&activation)
+        .map_err(|e| format!("Failed to serialize license: {}", e))?;
+    store.set(LICENSE_KEY, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    tracing::info!(license = %claims.id, plan = %claims.plan, "License activated");
+
+    let status = status(&app)?;
+    let _ = app.emit(CHANGED_EVENT, status.clone());
+    Ok(status)
+}
+
+/// Remove the license from this device so it can be activated on another; returns `false` when
+/// none was activated. Emits `license:changed`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn deactivate_license(app: AppHandle) -> Result<bool, Error> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    if !store.delete(LICENSE_KEY) {
+        return Ok(false);
+    }
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    tracing::info!("License deactivated");
+    let _ = app.emit(CHANGED_EVENT, status(&app)?);
+    Ok(true)
+}
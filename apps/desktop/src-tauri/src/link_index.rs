@@ -0,0 +1,427 @@
+use crate::catalog;
+use crate::document;
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::outline::Heading;
+use crate::paths;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+/// How much a match on each kind of name counts, so a title beats an alias or heading that
+/// matches as well
+const DOCUMENT_WEIGHT: f64 = 1.0;
+const ALIAS_WEIGHT: f64 = 0.95;
+const HEADING_WEIGHT: f64 = 0.85;
+
+/// Managed state caching, per workspace, the names `[[links]]` can point at: document titles,
+/// aliases and headings. A snapshot is rebuilt once the metadata cache has changed.
+#[derive(Default)]
+pub struct LinkIndex(Mutex<HashMap<PathBuf, Arc<Snapshot>>>);
+
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SuggestionKind {
+    Document,
+    Alias,
+    Heading,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkSuggestion {
+    pub kind: SuggestionKind,
+    /// The document's id, which stays the same when it is renamed or moved
+    pub id: String,
+    /// Workspace-relative path
+    pub path: String,
+    pub title: String,
+    pub alias: Option<String>,
+    pub heading: Option<String>,
+    pub anchor: Option<String>,
+    pub level: Option<u8>,
+    /// What goes between `[[` and `]]`: `Title`, `Title|Alias` or `Title#Heading`
+    pub link: String,
+    /// Between 0 and 1
+    pub score: f64,
+}
+
+struct Target {
+    id: String,
+    path: String,
+    title: String,
+    modified: i64,
+}
+
+/// A name a link can use
+struct Name {
+    target: usize,
+    kind: SuggestionKind,
+    text: String,
+    /// Lowercased with runs of whitespace as one space
+    normalized: String,
+    anchor: Option<String>,
+    level: Option<u8>,
+}
+
+struct Snapshot {
+    revision: u64,
+    targets: Vec<Target>,
+    names: Vec<Name>,
+    /// Each name under its whole text and under every word it contains, so a lookup is a range
+    /// of keys starting with the query
+    prefixes: BTreeMap<String, Vec<usize>>,
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// How well `query` matches `name`: whole name, start of the name, start of one of its words,
+/// then the query's letters in order with gaps between them
+fn score(query: &str, name: &str) -> Option<f64> {
+    if query.is_empty() {
+        return Some(0.5);
+    }
+    if name == query {
+        return Some(1.0);
+    }
+    // Shorter names are closer to what was typed
+    let closeness = query.len() as f64 / name.len().max(1) as f64;
+    if name.starts_with(query) {
+        return Some(0.8 + 0.1 * closeness);
+    }
+    if name
+        .match_indices(' ')
+        .any(|(at, _)| name[at + 1..].starts_with(query))
+    {
+        return Some(0.65 + 0.1 * closeness);
+    }
+    let mut gaps = 0;
+    let mut chars = name.chars();
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let mut skipped = 0;
+        loop {
+            match chars.next() {
+                Some(c) if c == wanted => break,
+                Some(_) => skipped += 1,
+                None => return None,
+            }
+        }
+        gaps += skipped.min(8);
+    }
+    let length = query.chars().count() as f64;
+    Some(0.5 * length / (length + gaps as f64))
+}
+
+fn build(app: &AppHandle, root: &Path, revision: u64) -> Result<Snapshot, String> {
+    let db = catalog::database(app, root)?;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let read = |sql: &str| -> Result<Vec<(String, String)>, String> {
+        let mut statement = db
+            .prepare(sql)
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        Ok(rows.flatten().collect())
+    };
+
+    let mut statement = db
+        .prepare("SELECT path, id, name, modified FROM documents ORDER BY path")
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let targets: Vec<Target> = statement
+        .query_map([], |row| {
+            Ok(Target {
+                path: row.get(0)?,
+                id: row.get(1)?,
+                title: row.get(2)?,
+                modified: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?
+        .flatten()
+        .collect();
+    let by_path: HashMap<&str, usize> = targets
+        .iter()
+        .enumerate()
+        .map(|(index, target)| (target.path.as_str(), index))
+        .collect();
+
+    let mut names: Vec<Name> = targets
+        .iter()
+        .enumerate()
+        .map(|(index, target)| Name {
+            target: index,
+            kind: SuggestionKind::Document,
+            text: target.title.clone(),
+            normalized: normalize(&target.title),
+            anchor: None,
+            level: None,
+        })
+        .collect();
+    for (path, alias) in read("SELECT path, alias FROM aliases ORDER BY path, alias")? {
+        if let Some(&target) = by_path.get(path.as_str()) {
+            names.push(Name {
+                target,
+                kind: SuggestionKind::Alias,
+                normalized: normalize(&alias),
+                text: alias,
+                anchor: None,
+                level: None,
+            });
+        }
+    }
+    for (path, headings) in read("SELECT path, headings FROM outlines")? {
+        let Some(&target) = by_path.get(path.as_str()) else {
+            continue;
+        };
+        let headings: Vec<Heading> = serde_json::from_str(&headings).unwrap_or_default();
+        for heading in headings.into_iter().filter(|h| !h.text.is_empty()) {
+            names.push(Name {
+                target,
+                kind: SuggestionKind::Heading,
+                normalized: normalize(&heading.text),
+                text: heading.text,
+                anchor: Some(heading.anchor),
+                level: Some(heading.level),
+            });
+        }
+    }
+
+    let mut prefixes: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, name) in names.iter().enumerate() {
+        let starts =
+            std::iter::once(0).chain(name.normalized.match_indices(' ').map(|(at, _)| at + 1));
+        for start in starts {
+            prefixes
+                .entry(name.normalized[start..].to_string())
+                .or_default()
+                .push(index);
+        }
+    }
+    Ok(Snapshot {
+        revision,
+        targets,
+        names,
+        prefixes,
+    })
+}
+
+/// Drop the workspace's snapshot
+pub fn evict(app: &AppHandle, root: &Path) {
+    if let Ok(mut snapshots) = app.state::<LinkIndex>().0.lock() {
+        snapshots.remove(root);
+    }
+}
+
+/// The workspace's snapshot, rebuilt when it is out of date
+fn snapshot(app: &AppHandle, root: &Path) -> Result<Arc<Snapshot>, String> {
+    let revision = catalog::revision(app, root)?;
+    let index = app.state::<LinkIndex>();
+    if let Some(snapshot) = index
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to read link index: {}", e))?
+        .get(root)
+        .filter(|snapshot| snapshot.revision == revision)
+    {
+        return Ok(snapshot.clone());
+    }
+    // Built without the lock so other workspaces are answered meanwhile
+    let snapshot = Arc::new(build(app, root, revision)?);
+    index
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to read link index: {}", e))?
+        .insert(root.to_path_buf(), snapshot.clone());
+    Ok(snapshot)
+}
+
+impl Snapshot {
+    /// Names whose text or one of whose words starts with `query`
+    fn starting_with(&self, query: &str) -> Vec<usize> {
+        let mut found: Vec<usize> = self
+            .prefixes
+            .range(query.to_string()..)
+            .take_while(|(key, _)| key.starts_with(query))
+            .flat_map(|(_, names)| names.iter().copied())
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    fn suggest(&self, prefix: &str, limit: usize) -> Vec<LinkSuggestion> {
+        let prefix = prefix.trim_start_matches("[[");
+        let prefix = prefix.split(['|', ']']).next().unwrap_or_default();
+        let (document, heading) = match prefix.split_once('#') {
+            Some((document, heading)) => (normalize(document), Some(normalize(heading))),
+            None => (normalize(prefix), None),
+        };
+
+        let mut scored: Vec<(f64, usize)> = match &heading {
+            // `Title#heading`: the headings of the documents named so, or of any document
+            // when no title is given
+            Some(heading) => {
+                let documents: Vec<usize> = match document.is_empty() {
+                    true => Vec::new(),
+                    false => {
+                        let exact: Vec<usize> = self
+                            .names
+                            .iter()
+                            .filter(|name| name.kind != SuggestionKind::Heading)
+                            .filter(|name| name.normalized == document)
+                            .map(|name| name.target)
+                            .collect();
+                        match exact.is_empty() {
+                            true => self
+                                .starting_with(&document)
+                                .into_iter()
+                                .filter(|&i| self.names[i].kind != SuggestionKind::Heading)
+                                .map(|i| self.names[i].target)
+                                .collect(),
+                            false => exact,
+                        }
+                    }
+                };
+                self.names
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, name)| name.kind == SuggestionKind::Heading)
+                    .filter(|(_, name)| documents.is_empty() || documents.contains(&name.target))
+                    .filter(|_| document.is_empty() || !documents.is_empty())
+                    .filter_map(|(index, name)| Some((score(heading, &name.normalized)?, index)))
+                    .collect()
+            }
+            None => {
+                let mut found: Vec<(f64, usize)> = self
+                    .starting_with(&document)
+                    .into_iter()
+                    .filter_map(|index| {
+                        Some((score(&document, &self.names[index].normalized)?, index))
+                    })
+                    .collect();
+                // Letters in order, typed loosely, only when too little starts with the query
+                if found.len() < limit && !document.is_empty() {
+                    let seen: HashSet<usize> = found.iter().map(|(_, index)| *index).collect();
+                    found.extend(
+                        self.names
+                            .iter()
+                            .enumerate()
+                            .filter(|(index, _)| !seen.contains(index))
+                            .filter_map(|(index, name)| {
+                                Some((score(&document, &name.normalized)?, index))
+                            }),
+                    );
+                }
+                found
+            }
+        };
+
+        for (score, index) in &mut scored {
+            *score *= match self.names[*index].kind {
+                SuggestionKind::Document => DOCUMENT_WEIGHT,
+                SuggestionKind::Alias => ALIAS_WEIGHT,
+                SuggestionKind::Heading => HEADING_WEIGHT,
+            };
+        }
+        // Best first, then the most recently changed document, then the shorter name
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            let (a, b) = (&self.names[*a], &self.names[*b]);
+            b_score
+                .total_cmp(a_score)
+                .then(
+                    self.targets[b.target]
+                        .modified
+                        .cmp(&self.targets[a.target].modified),
+                )
+                .then(a.text.len().cmp(&b.text.len()))
+                .then(a.text.cmp(&b.text))
+        });
+
+        // A document matching by title and by alias is listed once, under its best match
+        let mut listed: HashSet<(usize, Option<&str>)> = HashSet::new();
+        let mut suggestions = Vec::new();
+        for (score, index) in scored {
+            let name = &self.names[index];
+            let key = (name.target, name.anchor.as_deref());
+            if !listed.insert(key) {
+                continue;
+            }
+            let target = &self.targets[name.target];
+            let link = match name.kind {
+                SuggestionKind::Document => target.title.clone(),
+                SuggestionKind::Alias => format!("{}|{}", target.title, name.text),
+                SuggestionKind::Heading => format!("{}#{}", target.title, name.text),
+            };
+            suggestions.push(LinkSuggestion {
+                kind: name.kind,
+                id: target.id.clone(),
+                path: target.path.clone(),
+                title: target.title.clone(),
+                alias: (name.kind == SuggestionKind::Alias).then(|| name.text.clone()),
+                heading: (name.kind == SuggestionKind::Heading).then(|| name.text.clone()),
+                anchor: name.anchor.clone(),
+                level: name.level,
+                link,
+                score,
+            });
+            if suggestions.len() == limit {
+                break;
+            }
+        }
+        suggestions
+    }
+}
+
+/// Documents and headings to suggest while typing a `[[link]]`, best first. `prefix` is what
+/// follows `[[`; `Title#` lists that document's headings.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn link_suggestions(
+    app: AppHandle,
+    prefix: String,
+    limit: Option<usize>,
+    workspace: Option<String>,
+) -> Result<Vec<LinkSuggestion>, Error> {
+    let root = paths::workspace_or_current(&app, workspace.as_deref(), paths::Scope::Read)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || {
+        let snapshot = snapshot(&app, &root)?;
+        Ok(snapshot.suggest(&prefix, limit))
+    })
+    .await
+    .map_err(|e| format!("Link suggestions task failed: {}", e))?
+}
+
+/// Set the other names `[[links]]` may use for a document
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_document_aliases(
+    app: AppHandle,
+    path: String,
+    aliases: Vec<String>,
+) -> Result<(), Error> {
+    paths::check(&app, &path, paths::Scope::Write)?;
+    let path = Path::new(&path);
+    let mut board = document::read_board(path)?;
+    let mut seen = HashSet::new();
+    board.board.aliases = aliases
+        .iter()
+        .map(|alias| alias.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|alias| !alias.is_empty() && seen.insert(alias.to_lowercase()))
+        .collect();
+    board.board.updated_at = document::now_millis();
+    document::write_board(path, &board)?;
+    events::file_changed(&app, path, ChangeKind::Modified);
+    Ok(())
+}
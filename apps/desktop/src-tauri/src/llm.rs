@@ -0,0 +1,643 @@
+use crate::cancel::{self, CancelToken};
+use crate::document;
+use crate::error::Error;
+use crate::{http, paths, power, workspace};
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+use llama_cpp_2::sampling::LlamaSampler;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+/// GGUF models live in the app data dir as `models/llm/<name>.gguf`
+const MODELS_DIR: &str = "models/llm";
+const MODEL_EXTENSION: &str = "gguf";
+/// Store key of the model used when a command names none
+const MODEL_KEY: &str = "llmModel";
+const CONTEXT_TOKENS: usize = 8192;
+const BATCH_TOKENS: usize = 512;
+const MAX_NEW_TOKENS: usize = 1024;
+/// Longer documents are cut here so the prompt and the reply fit the context
+const MAX_INPUT_CHARS: usize = 20_000;
+const TEMPERATURE: f32 = 0.7;
+const TOP_P: f32 = 0.9;
+/// Store key of the compute preference for local models
+const COMPUTE_KEY: &str = "llmCompute";
+/// More layers than any model has, so all of them are offloaded
+const ALL_LAYERS: u32 = 1000;
+/// Emitted with each piece of generated text
+const TOKEN_EVENT: &str = "llm:token";
+const DOWNLOAD_EVENT: &str = "llm:download-progress";
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TokenEvent {
+    op_id: Option<String>,
+    text: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DownloadEvent {
+    name: String,
+    received: u64,
+    total: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmModel {
+    pub name: String,
+    pub size: u64,
+    /// Used when a command names no model
+    pub default: bool,
+}
+
+/// Managed state: the llama.cpp backend, the last model loaded, and a lock so one generation
+/// runs at a time
+#[derive(Default)]
+pub struct LocalLlm {
+    backend: Mutex<Option<Arc<LlamaBackend>>>,
+    model: Mutex<Option<(PathBuf, Arc<LlamaModel>)>>,
+    running: Mutex<()>,
+}
+
+impl LocalLlm {
+    /// The llama.cpp backend, started on first use and shared by every local model
+    pub fn backend(&self) -> Result<Arc<LlamaBackend>, String> {
+        let mut backend = self
+            .backend
+            .lock()
+            .map_err(|e| format!("Failed to start llama.cpp: {}", e))?;
+        if let Some(backend) = backend.as_ref() {
+            return Ok(backend.clone());
+        }
+        let mut created =
+            LlamaBackend::init().map_err(|e| format!("Failed to start llama.cpp: {}", e))?;
+        created.void_logs();
+        let created = Arc::new(created);
+        *backend = Some(created.clone());
+        Ok(created)
+    }
+
+    /// The model at `path`, kept loaded until another one is asked for
+    fn model(
+        &self,
+        backend: &LlamaBackend,
+        path: &Path,
+        params: &LlamaModelParams,
+    ) -> Result<Arc<LlamaModel>, String> {
+        let mut model = self
+            .model
+            .lock()
+            .map_err(|e| format!("Failed to load model: {}", e))?;
+        if let Some((_, model)) = model.as_ref().filter(|(loaded, _)| loaded == path) {
+            return Ok(model.clone());
+        }
+        // Drop the previous model first so two are never in memory at once
+        *model = None;
+        let loaded = LlamaModel::load_from_file(backend, path, params)
+            .map_err(|e| format!("Failed to load model: {}", e))?;
+        let loaded = Arc::new(loaded);
+        *model = Some((path.to_path_buf(), loaded.clone()));
+        Ok(loaded)
+    }
+}
+
+/// Where local models run: `auto` offloads to the GPU (Metal on macOS, CUDA or Vulkan
+/// elsewhere) when this build of llama.cpp has one, and falls back to the CPU
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Compute {
+    #[default]
+    Auto,
+    Cpu,
+    Gpu,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComputeStatus {
+    pub preference: Compute,
+    pub gpu_available: bool,
+    /// `cpu`, `metal` or `gpu`
+    pub active: &'static str,
+}
+
+fn compute(app: &AppHandle) -> Compute {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(COMPUTE_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn uses_gpu(app: &AppHandle, backend: &LlamaBackend) -> bool {
+    compute(app) != Compute::Cpu && backend.supports_gpu_offload()
+}
+
+/// Load parameters for a model under the compute preference
+pub fn model_params(app: &AppHandle, backend: &LlamaBackend) -> LlamaModelParams {
+    let layers = if uses_gpu(app, backend) {
+        ALL_LAYERS
+    } else {
+        0
+    };
+    LlamaModelParams::default().with_n_gpu_layers(layers)
+}
+
+fn models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?
+        .join(MODELS_DIR))
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid model name: {}", name));
+    }
+    Ok(())
+}
+
+fn installed(app: &AppHandle) -> Result<Vec<(String, u64)>, String> {
+    let Ok(entries) = fs::read_dir(models_dir(app)?) else {
+        return Ok(Vec::new());
+    };
+    let mut models: Vec<(String, u64)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == MODEL_EXTENSION))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            Some((name, path.metadata().ok()?.len()))
+        })
+        .collect();
+    models.sort();
+    Ok(models)
+}
+
+fn default_model(app: &AppHandle) -> Option<String> {
+    app.store(workspace::STORE_NAME)
+        .ok()?
+        .get(MODEL_KEY)?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// `name`, the default model, or the only model installed
+fn model_path(app: &AppHandle, name: Option<&str>) -> Result<PathBuf, String> {
+    let models = installed(app)?;
+    let name = match name.map(str::to_string).or_else(|| default_model(app)) {
+        Some(name) => name,
+        None => match models.as_slice() {
+            [(name, _)] => name.clone(),
+            [] => {
+                return Err(
+                    "No language model is installed. Download a GGUF model first".to_string(),
+                )
+            }
+            _ => return Err("Choose a language model: several are installed".to_string()),
+        },
+    };
+    check_name(&name)?;
+    let path = models_dir(app)?.join(format!("{}.{}", name, MODEL_EXTENSION));
+    if !path.is_file() {
+        return Err(format!("Language model not found: {}", name));
+    }
+    Ok(path)
+}
+
+/// Take the complete UTF-8 text from `pending`, leaving a character split across tokens
+fn take_text(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(error) if error.error_len().is_none() => error.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+    pending.drain(..valid);
+    text
+}
+
+/// Send a piece of generated text to the frontend as an `llm:token` event
+pub fn emit_token(app: &AppHandle, op_id: &Option<String>, text: &str) {
+    let _ = app.emit(
+        TOKEN_EVENT,
+        TokenEvent {
+            op_id: op_id.clone(),
+            text: text.to_string(),
+        },
+    );
+}
+
+/// Run the chat prompt through a local model, emitting `llm:token` events as text is generated,
+/// and return the whole reply. Nothing leaves the device.
+pub fn generate(
+    app: &AppHandle,
+    model: Option<&str>,
+    instruction: &str,
+    input: &str,
+    op_id: Option<String>,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let state = app.state::<LocalLlm>();
+    let _running = state
+        .running
+        .lock()
+        .map_err(|e| format!("Generation failed: {}", e))?;
+    let _awake = power::prevent_sleep("Generating text");
+    let path = model_path(app, model)?;
+    let backend = state.backend()?;
+    let model = state.model(&backend, &path, &model_params(app, &backend))?;
+
+    let input = match input.char_indices().nth(MAX_INPUT_CHARS) {
+        Some((end, _)) => &input[..end],
+        None => input,
+    };
+    let template = model
+        .chat_template(None)
+        .map_err(|e| format!("Model has no chat template: {}", e))?;
+    let messages = [("system", instruction), ("user", input)]
+        .into_iter()
+        .map(|(role, content)| LlamaChatMessage::new(role.to_string(), content.to_string()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid prompt: {}", e))?;
+    let prompt = model
+        .apply_chat_template(&template, &messages, true)
+        .map_err(|e| format!("Failed to build prompt: {}", e))?;
+    // Chat templates start with the model's BOS token themselves
+    let tokens = model
+        .str_to_token(&prompt, AddBos::Never)
+        .map_err(|e| format!("Failed to tokenize prompt: {}", e))?;
+    if tokens.len() + MAX_NEW_TOKENS > CONTEXT_TOKENS {
+        return Err("Invalid input: the text is too long for the model".to_string());
+    }
+
+    let failed = |e: &dyn std::fmt::Display| format!("Generation failed: {}", e);
+    let params = LlamaContextParams::default()
+        .with_n_ctx(NonZeroU32::new(CONTEXT_TOKENS as u32))
+        .with_n_batch(BATCH_TOKENS as u32);
+    let mut context = model
+        .new_context(&backend, params)
+        .map_err(|e| failed(&e))?;
+    let mut batch = LlamaBatch::new(BATCH_TOKENS, 1);
+    let mut position = 0;
+    for chunk in tokens.chunks(BATCH_TOKENS) {
+        batch.clear();
+        for token in chunk {
+            let last = position as usize == tokens.len() - 1;
+            batch
+                .add(*token, position, &[0], last)
+                .map_err(|e| failed(&e))?;
+            position += 1;
+        }
+        context.decode(&mut batch).map_err(|e| failed(&e))?;
+    }
+
+    let mut sampler = LlamaSampler::chain_simple([
+        LlamaSampler::top_p(TOP_P, 1),
+        LlamaSampler::temp(TEMPERATURE),
+        LlamaSampler::dist(document::now_millis() as u32),
+    ]);
+    let mut reply = String::new();
+    let mut pending = Vec::new();
+    for _ in 0..MAX_NEW_TOKENS {
+        if cancel.is_cancelled() {
+            return Err(cancel::CANCELLED.to_string());
+        }
+        let token = sampler.sample(&context, batch.n_tokens() - 1);
+        if model.is_eog_token(token) {
+            break;
+        }
+        let bytes = model
+            .token_to_bytes(token, Special::Plaintext)
+            .map_err(|e| failed(&e))?;
+        pending.extend(bytes);
+        let text = take_text(&mut pending);
+        if !text.is_empty() {
+            emit_token(app, &op_id, &text);
+            reply.push_str(&text);
+        }
+        batch.clear();
+        batch
+            .add(token, position, &[0], true)
+            .map_err(|e| failed(&e))?;
+        position += 1;
+        context.decode(&mut batch).map_err(|e| failed(&e))?;
+    }
+    Ok(reply.trim().to_string())
+}
+
+fn read_markdown(app: &AppHandle, path: &str) -> Result<String, Error> {
+    let path = paths::check(app, path, paths::Scope::Read)?;
+    Ok(document::read_board(&path)?.to_markdown())
+}
+
+/// Run `generate` off the async runtime with its own cancellable operation
+async fn run(
+    app: AppHandle,
+    model: Option<String>,
+    instruction: String,
+    input: String,
+    op_id: Option<String>,
+) -> Result<String, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = cancel::begin(&app, op_id.clone());
+        generate(
+            &app,
+            model.as_deref(),
+            &instruction,
+            &input,
+            op_id,
+            operation.token(),
+        )
+        .map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Generation task failed: {}", e))?
+}
+
+/// Summarize a document with a local model, streaming the summary as `llm:token` events
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn summarize_document(
+    app: AppHandle,
+    path: String,
+    model: Option<String>,
+    op_id: Option<String>,
+) -> Result<String, Error> {
+    let markdown = read_markdown(&app, &path)?;
+    let instruction = "You are a writing assistant. Summarize the user's document in a few \
+        short paragraphs or bullet points, in the document's language. Reply with the summary \
+        only.";
+    run(app, model, instruction.to_string(), markdown, op_id).await
+}
+
+/// Rewrite text in a style: `concise`, `formal`, `casual`, `simple`, `grammar`, or any other
+/// description of how it should read
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn rewrite_selection(
+    app: AppHandle,
+    text: String,
+    style: String,
+    model: Option<String>,
+    op_id: Option<String>,
+) -> Result<String, Error> {
+    if text.trim().is_empty() {
+        return Err("Invalid selection: there is no text to rewrite".into());
+    }
+    let how = match style.trim() {
+        "concise" => "to be shorter and more concise".to_string(),
+        "formal" => "in a formal tone".to_string(),
+        "casual" => "in a casual, friendly tone".to_string(),
+        "simple" => "in plain, simple language".to_string(),
+        "grammar" => "fixing spelling and grammar without otherwise changing it".to_string(),
+        other => format!("in this style: {}", other),
+    };
+    let instruction = format!(
+        "You are a writing assistant. Rewrite the user's text {}. Keep its meaning, language and \
+         Markdown formatting. Reply with the rewritten text only.",
+        how
+    );
+    run(app, model, instruction, text, op_id).await
+}
+
+/// Draft a Markdown outline of a document's structure and main points
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn generate_outline(
+    app: AppHandle,
+    path: String,
+    model: Option<String>,
+    op_id: Option<String>,
+) -> Result<String, Error> {
+    let markdown = read_markdown(&app, &path)?;
+    let instruction = "You are a writing assistant. Write an outline of the user's document as \
+        a nested Markdown list of its sections and main points, in the document's language. \
+        Reply with the outline only.";
+    run(app, model, instruction.to_string(), markdown, op_id).await
+}
+
+/// Installed GGUF models
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_llm_models(app: AppHandle) -> Result<Vec<LlmModel>, Error> {
+    let default = default_model(&app);
+    Ok(installed(&app)?
+        .into_iter()
+        .map(|(name, size)| LlmModel {
+            default: default.as_ref() == Some(&name),
+            name,
+            size,
+        })
+        .collect())
+}
+
+/// Choose the model commands use when they name none; `None` clears the choice
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_llm_model(app: AppHandle, name: Option<String>) -> Result<(), Error> {
+    if name.is_some() {
+        model_path(&app, name.as_deref())?;
+    }
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(MODEL_KEY, json!(name));
+    Ok(store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_model_compute(app: AppHandle) -> Result<ComputeStatus, Error> {
+    let backend = app.state::<LocalLlm>().backend()?;
+    let gpu_available = backend.supports_gpu_offload();
+    let active = match uses_gpu(&app, &backend) {
+        false => "cpu",
+        true if cfg!(target_os = "macos") => "metal",
+        true => "gpu",
+    };
+    Ok(ComputeStatus {
+        preference: compute(&app),
+        gpu_available,
+        active,
+    })
+}
+
+/// Choose where local models run; loaded models are dropped so the next use reloads them
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_model_compute(app: AppHandle, compute: Compute) -> Result<ComputeStatus, Error> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(COMPUTE_KEY, json!(compute));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    if let Ok(mut model) = app.state::<LocalLlm>().model.lock() {
+        *model = None;
+    }
+    crate::embeddings::unload(&app);
+    get_model_compute(app)
+}
+
+/// Download a GGUF model into the models folder, reporting `llm:download-progress`, and check it
+/// against `sha256` when given. This is the only network use of the local model commands.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn download_llm_model(
+    app: AppHandle,
+    url: String,
+    name: Option<String>,
+    sha256: Option<String>,
+    op_id: Option<String>,
+) -> Result<LlmModel, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = cancel::begin(&app, op_id);
+        download(&app, &url, name, sha256, operation.token()).map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Download task failed: {}", e))?
+}
+
+fn download(
+    app: &AppHandle,
+    url: &str,
+    name: Option<String>,
+    sha256: Option<String>,
+    cancel: &CancelToken,
+) -> Result<LlmModel, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid model URL: {}", e))?;
+    if !matches!(parsed.scheme(), "https" | "http") {
+        return Err(format!("Invalid model URL: {}", url));
+    }
+    let name = match name {
+        Some(name) => name,
+        None => parsed
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(|file| file.trim_end_matches(".gguf").to_string())
+            .unwrap_or_default(),
+    };
+    check_name(&name)?;
+    let dir = models_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+    let target = dir.join(format!("{}.{}", name, MODEL_EXTENSION));
+
+    let received = fetch(
+        url,
+        &target,
+        sha256.as_deref(),
+        cancel,
+        |received, total| {
+            let _ = app.emit(
+                DOWNLOAD_EVENT,
+                DownloadEvent {
+                    name: name.clone(),
+                    received,
+                    total,
+                },
+            );
+        },
+    )?;
+    tracing::info!(%name, bytes = received, "Language model downloaded");
+    Ok(LlmModel {
+        default: default_model(app).as_ref() == Some(&name),
+        name,
+        size: received,
+    })
+}
+
+/// Stream `url` into `target` through a `.part` file, calling `progress` with the bytes received
+/// and the expected total, and check the result against `expected` SHA-256 when given
+pub fn fetch(
+    url: &str,
+    target: &Path,
+    expected: Option<&str>,
+    cancel: &CancelToken,
+    progress: impl Fn(u64, Option<u64>),
+) -> Result<u64, String> {
+    let mut partial = target.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    let mut response = http::download_agent()
+        .get(url)
+        .call()
+        .map_err(|e| format!("Request failed: {}", e))?;
+    let total = response
+        .headers()
+        .get("content-length")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let mut reader = response.body_mut().with_config().limit(u64::MAX).reader();
+    let mut file =
+        fs::File::create(&partial).map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    let mut received = 0;
+    let result = loop {
+        if cancel.is_cancelled() {
+            break Err(cancel::CANCELLED.to_string());
+        }
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(read) => read,
+            Err(e) => break Err(format!("Download failed: {}", e)),
+        };
+        if let Err(e) = file.write_all(&buffer[..read]) {
+            break Err(format!("Failed to write file: {}", e));
+        }
+        hasher.update(&buffer[..read]);
+        received += read as u64;
+        progress(received, total);
+    };
+    drop(file);
+    let result = result.and_then(|()| match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(&format!("{:x}", hasher.finalize())) => {
+            Err("Invalid model: its hash does not match".to_string())
+        }
+        _ => fs::rename(&partial, target).map_err(|e| format!("Failed to save model: {}", e)),
+    });
+    if let Err(error) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(error);
+    }
+    Ok(received)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_llm_model(app: AppHandle, name: String) -> Result<bool, Error> {
+    check_name(&name)?;
+    let path = models_dir(&app)?.join(format!("{}.{}", name, MODEL_EXTENSION));
+    if let Ok(mut model) = app.state::<LocalLlm>().model.lock() {
+        if model.as_ref().is_some_and(|(loaded, _)| loaded == &path) {
+            *model = None;
+        }
+    }
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete model: {}", e).into()),
+    }
+}
@@ -0,0 +1,558 @@
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::error::{Error, ErrorCode};
+use crate::events::{self, ChangeKind};
+use crate::{calendar, crypto, inbox, lock, paths, sanitize, search, workspace};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+const ENABLED_KEY: &str = "localApi";
+const PORT_KEY: &str = "localApiPort";
+const TOKEN_KEY: &str = "localApiToken";
+const DEFAULT_PORT: u16 = 27427;
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_HEADER: usize = 16 * 1024;
+const MAX_HEADERS: usize = 32;
+const MAX_BODY: usize = 1 << 20;
+const CALENDAR_PATH: &str = "/v1/reminders.ics";
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalApiStatus {
+    pub enabled: bool,
+    /// Whether the server is listening; `false` while enabled means the port was taken
+    pub running: bool,
+    pub port: u16,
+    /// Sent by clients as `Authorization: Bearer <token>`, and by `x-callback-url` links as `token`
+    pub token: String,
+    pub url: String,
+}
+
+struct Running {
+    port: u16,
+    stop: Arc<AtomicBool>,
+}
+
+/// The automation server while it is listening
+#[derive(Default)]
+pub struct LocalApi(Mutex<Option<Running>>);
+
+enum Body {
+    Empty,
+    Json(Value),
+    /// An ICS feed, for calendar apps subscribed to the reminders
+    Calendar(String),
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| Error::from(format!("Invalid request body: {}", e)))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRequest {
+    pub text: String,
+    /// Creates a document with this name; without it the text goes to the inbox
+    pub title: Option<String>,
+    /// Folder for the new document, relative to the workspace
+    pub folder: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppendRequest {
+    /// Document path, relative to the workspace or absolute
+    pub path: String,
+    pub text: String,
+}
+
+fn setting(app: &AppHandle, key: &str) -> Option<Value> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(key))
+}
+
+fn port(app: &AppHandle) -> u16 {
+    setting(app, PORT_KEY)
+        .and_then(|value| value.as_u64())
+        .and_then(|port| u16::try_from(port).ok())
+        .unwrap_or(DEFAULT_PORT)
+}
+
+/// The access token, created on first use
+fn token(app: &AppHandle) -> Result<String, String> {
+    if let Some(token) =
+        setting(app, TOKEN_KEY).and_then(|value| value.as_str().map(str::to_string))
+    {
+        return Ok(token);
+    }
+    new_token(app)
+}
+
+/// Whether `given` is the access token, which also authorizes `x-callback-url` links
+pub fn authorized(app: &AppHandle, given: &str) -> bool {
+    token(app).is_ok_and(|token| {
+        Sha256::digest(given.trim().as_bytes()) == Sha256::digest(token.as_bytes())
+    })
+}
+
+fn new_token(app: &AppHandle) -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    crypto::fill_random(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(TOKEN_KEY, json!(token));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(token)
+}
+
+/// Start the server if it was enabled
+pub fn start(app: AppHandle) {
+    let enabled = setting(&app, ENABLED_KEY)
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
+    if enabled {
+        if let Err(error) = listen(&app, port(&app)) {
+            tracing::warn!(%error, "Failed to start the local API");
+        }
+    }
+}
+
+/// Listen on `port` of the loopback interface, replacing a server on another port
+fn listen(app: &AppHandle, port: u16) -> Result<(), String> {
+    let state = app.state::<LocalApi>();
+    let mut running = state
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to start the local API: {}", e))?;
+    if running.as_ref().is_some_and(|running| running.port == port) {
+        return Ok(());
+    }
+    if let Some(previous) = running.take() {
+        shut_down(previous);
+    }
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+    let stop = Arc::new(AtomicBool::new(false));
+    *running = Some(Running {
+        port,
+        stop: stop.clone(),
+    });
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let app = app.clone();
+            std::thread::spawn(move || {
+                if let Err(error) = handle(&app, stream) {
+                    tracing::debug!(%error, "Local API request failed");
+                }
+            });
+        }
+    });
+    tracing::info!(port, "Local API listening");
+    Ok(())
+}
+
+fn shut_down(running: Running) {
+    running.stop.store(true, Ordering::SeqCst);
+    // Wake the accept loop so it sees the flag and drops the listener
+    let _ = TcpStream::connect((Ipv4Addr::LOCALHOST, running.port));
+}
+
+fn handle(app: &AppHandle, mut stream: TcpStream) -> Result<(), String> {
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+    let (status, body) = match read_request(&mut stream) {
+        Ok(request) => respond(app, &request),
+        Err(error) => (400, Body::Json(json!({ "error": Error::from(error) }))),
+    };
+    let (content_type, body) = match body {
+        Body::Empty => ("application/json", String::new()),
+        Body::Json(body) => ("application/json", body.to_string()),
+        Body::Calendar(body) => ("text/calendar; charset=utf-8", body),
+    };
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Error",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    );
+    stream
+        .write_all(response.as_bytes())
+        .map_err(|e| format!("Failed to send response: {}", e))
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let read = stream
+            .read(&mut chunk)
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        if read == 0 {
+            return Err("Invalid request: connection closed".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed = httparse::Request::new(&mut headers);
+        let length = match parsed.parse(&buffer) {
+            Ok(httparse::Status::Complete(length)) => length,
+            Ok(httparse::Status::Partial) if buffer.len() <= MAX_HEADER => continue,
+            Ok(httparse::Status::Partial) => return Err("Invalid request: header too large".into()),
+            Err(e) => return Err(format!("Invalid request: {}", e)),
+        };
+        let (path, query) = parsed
+            .path
+            .unwrap_or("/")
+            .split_once('?')
+            .unwrap_or((parsed.path.unwrap_or("/"), ""));
+        let mut request = Request {
+            method: parsed.method.unwrap_or_default().to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers: parsed
+                .headers
+                .iter()
+                .map(|header| {
+                    let value = String::from_utf8_lossy(header.value).to_string();
+                    (header.name.to_string(), value)
+                })
+                .collect(),
+            body: buffer[length..].to_vec(),
+        };
+        let expected: usize = request
+            .header("content-length")
+            .map(|value| value.trim().parse())
+            .transpose()
+            .map_err(|_| "Invalid request: bad Content-Length".to_string())?
+            .unwrap_or(0);
+        if expected > MAX_BODY {
+            return Err("Invalid request: body too large".to_string());
+        }
+        while request.body.len() < expected {
+            let read = stream
+                .read(&mut chunk)
+                .map_err(|e| format!("Failed to read request: {}", e))?;
+            if read == 0 {
+                return Err("Invalid request: body cut short".to_string());
+            }
+            request.body.extend_from_slice(&chunk[..read]);
+        }
+        request.body.truncate(expected);
+        return Ok(request);
+    }
+}
+
+fn status_for(code: ErrorCode) -> u16 {
+    match code {
+        ErrorCode::NotFound => 404,
+        ErrorCode::PermissionDenied => 403,
+        ErrorCode::Conflict | ErrorCode::ReadOnly => 409,
+        ErrorCode::Locked => 423,
+        ErrorCode::InvalidPath | ErrorCode::InvalidData => 400,
+        ErrorCode::Unavailable => 503,
+        ErrorCode::Timeout => 504,
+        _ => 500,
+    }
+}
+
+fn respond(app: &AppHandle, request: &Request) -> (u16, Body) {
+    if request.method == "OPTIONS" {
+        return (204, Body::Empty);
+    }
+    // Only loopback names, so a web page cannot reach the server through DNS rebinding
+    let local = request
+        .header("host")
+        .and_then(|host| host.rsplit_once(':'))
+        .is_some_and(|(name, port)| {
+            matches!(name, "127.0.0.1" | "localhost") && port.parse() == Ok(self::port(app))
+        });
+    if !local {
+        let error = Error::new(ErrorCode::PermissionDenied, "Invalid Host header");
+        return (403, Body::Json(json!({ "error": error })));
+    }
+    let mut given = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .to_string();
+    // Calendar apps cannot send headers, so the feed also takes the token in the query
+    if request.path == CALENDAR_PATH {
+        if let Some((_, token)) =
+            url::form_urlencoded::parse(request.query.as_bytes()).find(|(key, _)| key == "token")
+        {
+            given = token.to_string();
+        }
+    }
+    if !authorized(app, &given) {
+        let error = Error::new(ErrorCode::PermissionDenied, "Missing or wrong access token");
+        return (401, Body::Json(json!({ "error": error })));
+    }
+    if let Err(error) = lock::ensure_unlocked(app) {
+        return (
+            status_for(error.code),
+            Body::Json(json!({ "error": error })),
+        );
+    }
+
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", CALENDAR_PATH) => {
+            let feed = workspace::current_root(app)
+                .ok_or_else(|| "No workspace is open".to_string())
+                .and_then(|root| calendar::reminder_feed(&root));
+            return match feed {
+                Ok(feed) => (200, Body::Calendar(feed)),
+                Err(error) => {
+                    let error = Error::from(error);
+                    (
+                        status_for(error.code),
+                        Body::Json(json!({ "error": error })),
+                    )
+                }
+            };
+        }
+        ("GET", "/v1/status") => Ok((200, status(app))),
+        ("POST", "/v1/notes") => request
+            .json()
+            .and_then(|note| create_note(app, note))
+            .map(|body| (201, body)),
+        ("POST", "/v1/append") => request
+            .json()
+            .and_then(|append| append_text(app, append))
+            .map(|body| (200, body)),
+        ("GET", "/v1/search") => run_search(app, &request.query).map(|body| (200, body)),
+        _ => Err(Error::not_found("No such endpoint").with_context(&request.path)),
+    };
+    match result {
+        Ok((status, body)) => (status, Body::Json(body)),
+        Err(error) => (
+            status_for(error.code),
+            Body::Json(json!({ "error": error })),
+        ),
+    }
+}
+
+fn status(app: &AppHandle) -> Value {
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "workspace": workspace::current_root(app).map(|root| root.to_string_lossy().to_string()),
+    })
+}
+
+fn clean(app: &AppHandle, text: &str) -> String {
+    let policy = workspace::current_root(app)
+        .map(|root| sanitize::load_policy(&root))
+        .unwrap_or_default();
+    // Text from scripts and browser extensions may carry markup
+    sanitize::clean_markdown(&policy, text)
+}
+
+pub fn create_note(app: &AppHandle, note: NoteRequest) -> Result<Value, Error> {
+    let markdown = clean(app, note.text.trim());
+    let Some(title) = note.title.filter(|title| !title.trim().is_empty()) else {
+        let path = inbox::capture(app, &markdown)?;
+        return Ok(json!({ "path": path.to_string_lossy() }));
+    };
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let dir = match note.folder {
+        Some(folder) => {
+            let dir = paths::check(
+                app,
+                &root.join(folder).to_string_lossy(),
+                paths::Scope::Write,
+            )?;
+            if !dir.is_dir() {
+                return Err(Error::not_found("Folder does not exist").with_context(dir.display()));
+            }
+            dir
+        }
+        None => root,
+    };
+    let path = document::unique_path(&dir, &file_stem(&title), DOCUMENT_EXTENSION);
+    let mut board = BoardFile::new(title.trim());
+    board.push_markdown(&markdown);
+    document::write_board(&path, &board)?;
+    events::file_changed(app, &path, ChangeKind::Created);
+    Ok(json!({ "path": path.to_string_lossy() }))
+}
+
+/// Strip characters file systems reject from a document name
+pub fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .take(80)
+        .collect();
+    let stem = stem.trim().trim_matches('.');
+    if stem.is_empty() {
+        "Note".to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+pub fn append_text(app: &AppHandle, append: AppendRequest) -> Result<Value, Error> {
+    let markdown = clean(app, append.text.trim());
+    if markdown.trim().is_empty() {
+        return Err("Invalid request: nothing to append".into());
+    }
+    let path = match workspace::current_root(app) {
+        Some(root) if Path::new(&append.path).is_relative() => root.join(&append.path),
+        _ => Path::new(&append.path).to_path_buf(),
+    };
+    let path = paths::check(app, &path.to_string_lossy(), paths::Scope::Write)?;
+    let mut board = document::read_board(&path)?;
+    board.push_markdown(&markdown);
+    board.board.updated_at = document::now_millis();
+    document::write_board(&path, &board)?;
+    events::file_changed(app, &path, ChangeKind::Modified);
+    Ok(json!({ "path": path.to_string_lossy() }))
+}
+
+fn run_search(app: &AppHandle, query: &str) -> Result<Value, Error> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let mut text = String::new();
+    let mut limit = None;
+    let mut tag = None;
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "q" => text = value.to_string(),
+            "limit" => limit = value.parse().ok(),
+            "tag" => tag = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    let hits = search::search_documents(
+        app.clone(),
+        root.to_string_lossy().to_string(),
+        text,
+        limit,
+        tag,
+        None,
+        None,
+    )?;
+    Ok(json!({ "hits": hits }))
+}
+
+/// Whether the automation API is on, where it listens and the token clients need
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_local_api_status(app: AppHandle) -> Result<LocalApiStatus, Error> {
+    let port = port(&app);
+    let running = app
+        .state::<LocalApi>()
+        .0
+        .lock()
+        .is_ok_and(|running| running.as_ref().is_some_and(|running| running.port == port));
+    Ok(LocalApiStatus {
+        enabled: setting(&app, ENABLED_KEY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false),
+        running,
+        port,
+        token: token(&app)?,
+        url: format!("http://127.0.0.1:{}/v1", port),
+    })
+}
+
+/// Turn the automation API on or off, optionally moving it to `port`.
+///
+/// It listens on 127.0.0.1 only and every request needs the token. The endpoints are
+/// `GET /v1/status`, `POST /v1/notes` (`{ text, title?, folder? }`), `POST /v1/append`
+/// (`{ path, text }`), `GET /v1/search?q=&limit=&tag=` and `GET /v1/reminders.ics?token=`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_local_api_enabled(
+    app: AppHandle,
+    enabled: bool,
+    port: Option<u16>,
+) -> Result<LocalApiStatus, Error> {
+    let port = port.unwrap_or_else(|| self::port(&app));
+    if enabled {
+        listen(&app, port)?;
+    } else if let Some(running) = app
+        .state::<LocalApi>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to stop the local API: {}", e))?
+        .take()
+    {
+        shut_down(running);
+    }
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(ENABLED_KEY, json!(enabled));
+    store.set(PORT_KEY, json!(port));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    get_local_api_status(app)
+}
+
+/// Replace the access token, locking out every client that had the old one
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn regenerate_local_api_token(app: AppHandle) -> Result<LocalApiStatus, Error> {
+    new_token(&app)?;
+    get_local_api_status(app)
+}
@@ -0,0 +1,352 @@
+use crate::assets::{self, ASSETS_DIR};
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::{blocking, events, http, paths};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::AppHandle;
+
+/// Largest remote image that will be downloaded
+const MAX_IMAGE_BYTES: u64 = 25 * 1024 * 1024;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizedImage {
+    pub url: String,
+    /// Reference written into the document, relative to the document's folder
+    pub path: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizeFailure {
+    pub url: String,
+    pub error: String,
+}
+
+#[derive(serde::Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizeReport {
+    pub downloaded: Vec<LocalizedImage>,
+    pub failed: Vec<LocalizeFailure>,
+    pub document_changed: bool,
+}
+
+/// Download remote images referenced by a document into the assets folder and point the document at them
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn localize_remote_assets(
+    app: AppHandle,
+    doc_path: String,
+) -> Result<LocalizeReport, Error> {
+    paths::check(&app, &doc_path, paths::Scope::Write)?;
+    blocking::run_long("download remote images", move || localize(&app, &doc_path)).await
+}
+
+fn localize(app: &AppHandle, doc_path: &str) -> Result<LocalizeReport, Error> {
+    let path = PathBuf::from(doc_path);
+    let mut board = document::read_board(&path)?;
+    let doc_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let assets_dir = assets::default_dir(app).unwrap_or_else(|_| doc_dir.join(ASSETS_DIR));
+
+    let urls = remote_images(&board);
+    let mut report = LocalizeReport::default();
+    if urls.is_empty() {
+        return Ok(report);
+    }
+
+    let agent = http::agent();
+    let mut replacements = HashMap::new();
+    for url in urls {
+        match download(&agent, &url, &assets_dir) {
+            Ok(saved) => {
+                let reference = relative_to(&doc_dir, &saved);
+                replacements.insert(url.clone(), reference.clone());
+                report.downloaded.push(LocalizedImage {
+                    url,
+                    path: reference,
+                });
+            }
+            Err(error) => report.failed.push(LocalizeFailure { url, error }),
+        }
+    }
+
+    if !replacements.is_empty() {
+        rewrite(&mut board, &replacements);
+        board.board.updated_at = document::now_millis();
+        document::write_board(&path, &board)?;
+        events::file_changed(app, &path, events::ChangeKind::Modified);
+        report.document_changed = true;
+    }
+
+    Ok(report)
+}
+
+/// Distinct `http(s)` image URLs from Markdown image syntax and inline `<img>` tags
+fn remote_images(board: &BoardFile) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut push = |url: &str| {
+        let url = url.trim();
+        if is_remote(url) && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    };
+
+    for shape in board.doc.shapes.values() {
+        let Some(md) = shape
+            .get("props")
+            .and_then(|props| props.get("md"))
+            .and_then(Value::as_str)
+        else {
+            continue;
+        };
+        for event in Parser::new_ext(md, Options::all()) {
+            match event {
+                Event::Start(Tag::Image { dest_url, .. }) => push(&dest_url),
+                Event::Html(html) | Event::InlineHtml(html) => {
+                    for src in img_sources(&html) {
+                        push(src);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    urls
+}
+
+fn is_remote(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// `src` attribute values of `<img>` tags in an HTML fragment
+fn img_sources(html: &str) -> Vec<&str> {
+    let lower = html.to_ascii_lowercase();
+    let mut sources = Vec::new();
+    for (start, _) in lower.match_indices("<img") {
+        let tag_end = lower[start..]
+            .find('>')
+            .map(|i| start + i)
+            .unwrap_or(lower.len());
+        let Some(at) = lower[start..tag_end].find("src=") else {
+            continue;
+        };
+        let value_start = start + at + 4;
+        let rest = &html[value_start..tag_end];
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..].split(quote).next(),
+            _ => rest.split(|c: char| c.is_whitespace() || c == '/').next(),
+        };
+        if let Some(value) = value {
+            sources.push(value);
+        }
+    }
+    sources
+}
+
+fn download(agent: &ureq::Agent, url: &str, dir: &Path) -> Result<PathBuf, String> {
+    let mut response = agent
+        .get(url)
+        .call()
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let extension = extension_for(&content_type, url)
+        .ok_or_else(|| format!("Not an image (content type '{}')", content_type))?;
+
+    let bytes = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_IMAGE_BYTES)
+        .read_to_vec()
+        .map_err(|e| format!("Download failed: {}", e))?;
+
+    let stem = url_stem(url);
+    crate::read_only::ensure_writable(dir)?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let path = document::unique_path(dir, &stem, &format!(".{}", extension));
+    let bytes = if extension == "svg" {
+        let content = String::from_utf8(bytes).map_err(|_| "SVG is not valid UTF-8".to_string())?;
+        crate::svg::sanitize(&content)?.0.into_bytes()
+    } else {
+        bytes
+    };
+    fs::write(&path, bytes).map_err(|e| format!("Failed to write asset: {}", e))?;
+    Ok(path)
+}
+
+/// Extension from the response type, falling back to the URL for generic binary responses
+fn extension_for(content_type: &str, url: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    let from_mime = match mime {
+        "image/png" => Some("png"),
+        "image/jpeg" | "image/jpg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/avif" => Some("avif"),
+        "image/svg+xml" => Some("svg"),
+        "image/bmp" => Some("bmp"),
+        _ => None,
+    };
+    if from_mime.is_some() || (!mime.is_empty() && mime != "application/octet-stream") {
+        return from_mime;
+    }
+
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    match path.rsplit('.').next()?.to_ascii_lowercase().as_str() {
+        "png" => Some("png"),
+        "jpg" | "jpeg" => Some("jpg"),
+        "gif" => Some("gif"),
+        "webp" => Some("webp"),
+        "avif" => Some("avif"),
+        "svg" => Some("svg"),
+        _ => None,
+    }
+}
+
+/// File stem from the URL's last path segment, limited to filesystem-safe characters
+fn url_stem(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let segment = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    let stem = segment.split('.').next().unwrap_or_default();
+    let clean: String = stem
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .take(64)
+        .collect();
+    if clean.is_empty() {
+        "remote".to_string()
+    } else {
+        clean
+    }
+}
+
+/// Path from `dir` to `target` with forward slashes, as Markdown references expect
+pub fn relative_to(dir: &Path, target: &Path) -> String {
+    let dir: Vec<Component> = dir.components().collect();
+    let target_parts: Vec<Component> = target.components().collect();
+    let common = dir
+        .iter()
+        .zip(&target_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); dir.len() - common];
+    parts.extend(
+        target_parts[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+fn rewrite(board: &mut BoardFile, replacements: &HashMap<String, String>) {
+    for shape in board.doc.shapes.values_mut() {
+        let Some(Value::String(md)) = shape.get_mut("props").and_then(|props| props.get_mut("md"))
+        else {
+            continue;
+        };
+        for (url, local) in replacements {
+            if md.contains(url.as_str()) {
+                *md = replace_destination(md, url, local);
+            }
+        }
+    }
+}
+
+/// Replace `url` where it is a whole image destination or `src` value, so a URL that starts
+/// another one, such as `img.png` in `img.png?size=2`, leaves the longer one alone
+fn replace_destination(text: &str, url: &str, local: &str) -> String {
+    let starts = |c: char| c.is_whitespace() || "(<\"'=".contains(c);
+    let ends = |c: char| c.is_whitespace() || ")>\"'".contains(c);
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(url) {
+        let before = rest[..at]
+            .chars()
+            .next_back()
+            .or_else(|| out.chars().next_back());
+        let after = rest[at + url.len()..].chars().next();
+        out.push_str(&rest[..at]);
+        match before.is_some_and(starts) && after.is_none_or(ends) {
+            true => out.push_str(local),
+            false => out.push_str(url),
+        }
+        rest = &rest[at + url.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_url_that_starts_another_leaves_the_longer_one_alone() {
+        let md = "![a](https://x.io/img.png) ![b](https://x.io/img.png?x=1)";
+        let mut replacements: Vec<(&str, &str)> = vec![
+            ("https://x.io/img.png", "assets/img.png"),
+            ("https://x.io/img.png?x=1", "assets/img-1.png"),
+        ];
+        for _ in 0..2 {
+            let text = replacements
+                .iter()
+                .fold(md.to_string(), |text, (url, local)| {
+                    replace_destination(&text, url, local)
+                });
+            assert_eq!(text, "![a](assets/img.png) ![b](assets/img-1.png)");
+            replacements.reverse();
+        }
+    }
+
+    #[test]
+    fn html_sources_and_titled_images_are_replaced() {
+        let md = "<img src=\"https://x.io/a.png\"> ![t](https://x.io/a.png \"Title\")";
+        assert_eq!(
+            replace_destination(md, "https://x.io/a.png", "assets/a.png"),
+            "<img src=\"assets/a.png\"> ![t](assets/a.png \"Title\")"
+        );
+        assert_eq!(img_sources("<IMG alt=x SRC='b.png'>"), ["b.png"]);
+    }
+
+    #[test]
+    fn references_are_relative_to_the_document() {
+        assert_eq!(
+            relative_to(Path::new("/ws/notes/deep"), Path::new("/ws/assets/a.png")),
+            "../../assets/a.png"
+        );
+        assert_eq!(
+            relative_to(Path::new("/ws"), Path::new("/ws/assets/a.png")),
+            "assets/a.png"
+        );
+    }
+
+    #[test]
+    fn extensions_come_from_the_type_or_the_url() {
+        assert_eq!(
+            extension_for("image/jpeg; charset=x", "https://a/b"),
+            Some("jpg")
+        );
+        assert_eq!(
+            extension_for("application/octet-stream", "https://a/b.PNG?x"),
+            Some("png")
+        );
+        assert_eq!(extension_for("text/html", "https://a/b.png"), None);
+        assert_eq!(url_stem("https://a/b/photo.large.jpg?x=1"), "photo");
+        assert_eq!(url_stem("https://a/"), "a");
+    }
+}
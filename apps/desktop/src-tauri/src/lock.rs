@@ -0,0 +1,359 @@
+use crate::crypto::{self, SecretKey};
+use crate::document::now_millis;
+use crate::error::{Error, ErrorCode};
+use crate::event_bus::{self, BusEvent};
+use crate::{vault, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const CONFIG: &str = "lock";
+/// Id of the workspace key in the [`Keyring`]
+pub const WORKSPACE_KEY: &str = "workspace";
+/// How often the auto-lock timer checks for inactivity
+const TICK: Duration = Duration::from_secs(15);
+
+/// Commands that still run while the workspace is locked, so it can be unlocked and pending
+/// saves are not lost; everything else fails with [`ErrorCode::Locked`]
+const ALLOWED_WHILE_LOCKED: &[&str] = &[
+    "get_lock_status",
+    "lock_workspace",
+    "unlock_workspace",
+    "get_biometric_status",
+    "unlock_with_biometric",
+    "flush_documents",
+    "get_locale",
+    "get_system_theme",
+    "list_windows",
+    "get_window_state",
+    "set_window_state",
+    "get_safe_mode",
+    "get_update_status",
+    "set_unsaved_changes",
+];
+/// Commands the interface runs on its own, such as status polls and saves on blur, which do
+/// not postpone auto-lock
+const BACKGROUND_COMMANDS: &[&str] = &[
+    "get_lock_status",
+    "get_biometric_status",
+    "get_update_status",
+    "get_system_conditions",
+    "get_system_theme",
+    "get_focus_session",
+    "get_local_api_status",
+    "get_lan_sync_status",
+    "get_embeddings_index_status",
+    "get_performance_report",
+    "get_log_tail",
+    "list_jobs",
+    "list_open_workspaces",
+    "list_collab_peers",
+    "get_collab_document",
+    "update_presence",
+    "ack_file_chunks",
+    "flush_documents",
+    "save_session",
+    "get_window_state",
+    "set_window_state",
+    "refresh_tray",
+    "set_tray_sync_status",
+    "track_feature",
+];
+
+/// When the user last ran a command, as Unix milliseconds
+static LAST_ACTIVITY: AtomicI64 = AtomicI64::new(0);
+/// Whether a workspace has a passphrase, remembered for the last one asked about so commands
+/// do not read its lock settings on every call
+static CONFIGURED: Mutex<Option<(PathBuf, bool)>> = Mutex::new(None);
+
+/// `.inkfinite/lock.json`; the passphrase itself is never stored
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct LockConfig {
+    /// Argon2 salt for the passphrase, base64
+    salt: Option<String>,
+    /// Random workspace key sealed with the passphrase key, base64, so changing the passphrase
+    /// does not re-encrypt anything
+    wrapped_key: Option<String>,
+    /// Lock after this many minutes without a command the user started
+    auto_lock_minutes: Option<u32>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    /// Whether the workspace has a passphrase
+    pub configured: bool,
+    pub locked: bool,
+    pub auto_lock_minutes: Option<u32>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LockedEvent {
+    /// `manual`, `inactivity`, `workspaceChanged` or `workspaceClosed`
+    reason: &'static str,
+}
+
+#[derive(Default)]
+struct Unlocked {
+    /// Workspace the keys belong to
+    root: Option<PathBuf>,
+    keys: HashMap<String, SecretKey>,
+}
+
+/// Managed state holding the keys for encrypted content while the workspace is unlocked
+#[derive(Default)]
+pub struct Keyring(Mutex<Unlocked>);
+
+/// Note that the user did something, postponing auto-lock
+pub fn record_activity() {
+    LAST_ACTIVITY.store(now_millis(), Ordering::Relaxed);
+}
+
+/// Gate a command called from the webview: refused while the workspace is locked unless it is
+/// needed to unlock, and counted as activity unless the interface runs it on its own
+pub fn check_command(app: &AppHandle, command: &str) -> Result<(), Error> {
+    if !ALLOWED_WHILE_LOCKED.contains(&command) {
+        ensure_unlocked(app)?;
+    }
+    if !BACKGROUND_COMMANDS.contains(&command) {
+        record_activity();
+    }
+    Ok(())
+}
+
+/// Fail with [`ErrorCode::Locked`] while the open workspace has a passphrase and is locked
+pub fn ensure_unlocked(app: &AppHandle) -> Result<(), Error> {
+    if is_locked(app) {
+        return Err(Error::new(ErrorCode::Locked, "Workspace is locked"));
+    }
+    Ok(())
+}
+
+/// Whether the open workspace has a passphrase and has not been unlocked
+pub fn is_locked(app: &AppHandle) -> bool {
+    let Some(root) = workspace::current_root(app) else {
+        return false;
+    };
+    key(app, WORKSPACE_KEY).is_err() && configured(&root)
+}
+
+fn configured(root: &Path) -> bool {
+    if let Ok(cached) = CONFIGURED.lock() {
+        if let Some((cached_root, configured)) = cached.as_ref() {
+            if cached_root == root {
+                return *configured;
+            }
+        }
+    }
+    let configured = workspace::read_config::<LockConfig>(root, CONFIG)
+        .is_ok_and(|config| config.wrapped_key.is_some());
+    remember_configured(root, configured);
+    configured
+}
+
+fn remember_configured(root: &Path, configured: bool) {
+    if let Ok(mut cached) = CONFIGURED.lock() {
+        *cached = Some((root.to_path_buf(), configured));
+    }
+}
+
+/// Key `id` of the open workspace, failing while it is locked
+pub fn key(app: &AppHandle, id: &str) -> Result<SecretKey, String> {
+    let root = current_root(app)?;
+    let keyring = app.state::<Keyring>();
+    let unlocked = keyring
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to read keys: {}", e))?;
+    match unlocked.keys.get(id) {
+        Some(key) if unlocked.root.as_ref() == Some(&root) => Ok(key.clone()),
+        _ => Err("Workspace is locked".to_string()),
+    }
+}
+
+/// Keep `key` in memory as `id` until the workspace locks
+pub fn insert_key(app: &AppHandle, id: &str, key: SecretKey) -> Result<(), String> {
+    let root = current_root(app)?;
+    let keyring = app.state::<Keyring>();
+    let mut unlocked = keyring
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to store key: {}", e))?;
+    if unlocked.root.as_ref() != Some(&root) {
+        unlocked.keys.clear();
+        unlocked.root = Some(root);
+    }
+    unlocked.keys.insert(id.to_string(), key);
+    Ok(())
+}
+
+/// Unlock the open workspace with its key, emitting `workspace:unlocked`
+pub fn unlock_with_key(app: &AppHandle, key: SecretKey) -> Result<(), String> {
+    insert_key(app, WORKSPACE_KEY, key)?;
+    record_activity();
+    let _ = app.emit("workspace:unlocked", ());
+    event_bus::publish(app, BusEvent::WorkspaceUnlocked {});
+    Ok(())
+}
+
+/// Drop every key, vault keys included, and tell the UI with a `workspace:locked` event
+fn lock(app: &AppHandle, reason: &'static str) {
+    let had_keys = match app.state::<Keyring>().0.lock() {
+        Ok(mut unlocked) => {
+            let had_keys = !unlocked.keys.is_empty();
+            *unlocked = Unlocked::default();
+            had_keys
+        }
+        Err(_) => false,
+    };
+    let had_vaults = vault::lock_all();
+    if had_keys || had_vaults || reason == "manual" {
+        tracing::info!(reason, "Workspace locked");
+        let _ = app.emit("workspace:locked", LockedEvent { reason });
+        let reason = reason.to_string();
+        event_bus::publish(app, BusEvent::WorkspaceLocked { reason });
+    }
+}
+
+/// Lock when the workspace the keys were unlocked for is closed
+pub fn workspace_closed(app: &AppHandle, root: &Path) {
+    let unlocked_for = app
+        .state::<Keyring>()
+        .0
+        .lock()
+        .is_ok_and(|unlocked| unlocked.root.as_deref() == Some(root));
+    if unlocked_for {
+        lock(app, "workspaceClosed");
+    }
+}
+
+fn current_root(app: &AppHandle) -> Result<PathBuf, String> {
+    workspace::current_root(app).ok_or_else(|| "No workspace is open".to_string())
+}
+
+fn unwrap_key(config: &LockConfig, passphrase: &str) -> Result<SecretKey, String> {
+    let (Some(salt), Some(wrapped)) = (&config.salt, &config.wrapped_key) else {
+        return Err("Workspace has no passphrase".to_string());
+    };
+    let salt = BASE64
+        .decode(salt)
+        .map_err(|e| format!("Invalid lock settings: {}", e))?;
+    let wrapped = BASE64
+        .decode(wrapped)
+        .map_err(|e| format!("Invalid lock settings: {}", e))?;
+    let passphrase_key = crypto::derive_key(passphrase, &salt)?;
+    let key =
+        crypto::open(&passphrase_key, &wrapped).map_err(|_| "Wrong passphrase".to_string())?;
+    let key: [u8; crypto::KEY_LEN] = key
+        .as_slice()
+        .try_into()
+        .map_err(|_| "Invalid lock settings: bad key length".to_string())?;
+    Ok(SecretKey::new(key))
+}
+
+/// Lock when the user ran no command for the workspace's auto-lock period, and when the workspace
+/// changes under keys unlocked for another one
+pub fn start(app: AppHandle) {
+    record_activity();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK);
+        let unlocked_root = match app.state::<Keyring>().0.lock() {
+            Ok(unlocked) if !unlocked.keys.is_empty() => unlocked.root.clone(),
+            _ => None,
+        };
+        if unlocked_root.is_none() && !vault::any_unlocked() {
+            continue;
+        }
+        let root = workspace::current_root(&app);
+        if unlocked_root.is_some() && root != unlocked_root {
+            lock(&app, "workspaceChanged");
+            continue;
+        }
+        let Some(root) = root else {
+            continue;
+        };
+        let minutes = workspace::read_config::<LockConfig>(&root, CONFIG)
+            .ok()
+            .and_then(|config| config.auto_lock_minutes);
+        let idle = now_millis() - LAST_ACTIVITY.load(Ordering::Relaxed);
+        if minutes.is_some_and(|minutes| idle >= i64::from(minutes) * 60_000) {
+            lock(&app, "inactivity");
+        }
+    });
+}
+
+/// Whether the open workspace has a passphrase and is locked
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_lock_status(app: AppHandle) -> Result<LockStatus, Error> {
+    let root = current_root(&app)?;
+    let config: LockConfig = workspace::read_config(&root, CONFIG)?;
+    Ok(LockStatus {
+        configured: config.wrapped_key.is_some(),
+        locked: key(&app, WORKSPACE_KEY).is_err(),
+        auto_lock_minutes: config.auto_lock_minutes,
+    })
+}
+
+/// Set or change the workspace passphrase; changing it needs the current one.
+///
+/// The workspace stays unlocked afterwards.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_lock_passphrase(
+    app: AppHandle,
+    passphrase: String,
+    current_passphrase: Option<String>,
+) -> Result<(), Error> {
+    if passphrase.is_empty() {
+        return Err("Invalid passphrase: empty".into());
+    }
+    let root = current_root(&app)?;
+    let mut config: LockConfig = workspace::read_config(&root, CONFIG)?;
+    let key = match (&config.wrapped_key, current_passphrase) {
+        (None, _) => crypto::random_key(),
+        (Some(_), Some(current)) => unwrap_key(&config, &current)?,
+        (Some(_), None) => return Err("Wrong passphrase: the current one is required".into()),
+    };
+    let salt = crypto::random_salt();
+    let wrapped = crypto::seal(&crypto::derive_key(&passphrase, &salt)?, key.as_ref())?;
+    config.salt = Some(BASE64.encode(salt));
+    config.wrapped_key = Some(BASE64.encode(wrapped));
+    workspace::write_config(&root, CONFIG, &config)?;
+    remember_configured(&root, true);
+    Ok(insert_key(&app, WORKSPACE_KEY, key)?)
+}
+
+/// Drop every key from memory and emit `workspace:locked`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn lock_workspace(app: AppHandle) {
+    lock(&app, "manual");
+}
+
+/// Unlock the open workspace, emitting `workspace:unlocked`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn unlock_workspace(app: AppHandle, passphrase: String) -> Result<(), Error> {
+    let root = current_root(&app)?;
+    let config: LockConfig = workspace::read_config(&root, CONFIG)?;
+    let key = unwrap_key(&config, &passphrase)?;
+    Ok(unlock_with_key(&app, key)?)
+}
+
+/// Lock after `minutes` without activity, or never with `None`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_auto_lock(app: AppHandle, minutes: Option<u32>) -> Result<(), Error> {
+    let root = current_root(&app)?;
+    let mut config: LockConfig = workspace::read_config(&root, CONFIG)?;
+    config.auto_lock_minutes = minutes.filter(|&minutes| minutes > 0);
+    Ok(workspace::write_config(&root, CONFIG, &config)?)
+}
@@ -0,0 +1,161 @@
+use crate::error::Error;
+use crate::perf;
+use crate::workspace;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+const LOG_DIR: &str = "logs";
+const LOG_PREFIX: &str = "inkfinite";
+const LOG_SUFFIX: &str = "log";
+/// Daily files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 7;
+const LEVEL_KEY: &str = "logLevel";
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
+/// Bytes read from the end of the log per step while looking for enough lines
+const TAIL_STEP: u64 = 64 * 1024;
+
+static LEVEL: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+/// Flushes buffered lines when dropped, so it lives for the whole process
+static WRITER: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Log to a daily-rotated file under the app data dir at the level saved in settings
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let dir = log_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_PREFIX)
+        .filename_suffix(LOG_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let _ = WRITER.set(guard);
+
+    let level = app
+        .store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(LEVEL_KEY))
+        .and_then(|value| value.as_str().and_then(|level| level.parse().ok()))
+        .unwrap_or(DEFAULT_LEVEL);
+    let (filter, handle) = reload::Layer::new(level);
+    let _ = LEVEL.set(handle);
+
+    tracing_subscriber::registry()
+        .with(
+            fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                // Commands are instrumented at debug level, so timings appear only when asked for
+                .with_span_events(FmtSpan::CLOSE)
+                .with_filter(filter),
+        )
+        // Filtered separately so performance reports cover every call at any log level
+        .with(perf::Timings.with_filter(filter_fn(perf::is_command)))
+        .try_init()
+        .map_err(|e| format!("Failed to start logging: {}", e))?;
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!("{}", info);
+        default_hook(info);
+    }));
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "Starting");
+    Ok(())
+}
+
+/// The last `lines` lines of the current log file, oldest first
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_log_tail(app: AppHandle, lines: Option<usize>) -> Result<Vec<String>, Error> {
+    let Some(path) = latest_log(&log_dir(&app)?) else {
+        return Ok(Vec::new());
+    };
+    Ok(tail(&path, lines.unwrap_or(200))?)
+}
+
+/// Change how much is logged: `error`, `warn`, `info`, `debug` or `trace`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_log_level(app: AppHandle, level: String) -> Result<(), Error> {
+    let filter: LevelFilter = level
+        .parse()
+        .map_err(|_| format!("Invalid log level: {}", level))?;
+    if let Some(handle) = LEVEL.get() {
+        handle
+            .modify(|current| *current = filter)
+            .map_err(|e| format!("Failed to change log level: {}", e))?;
+    }
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(LEVEL_KEY, filter.to_string().to_lowercase());
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    tracing::info!(level = %filter, "Log level changed");
+    Ok(())
+}
+
+fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(LOG_DIR))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// Date-stamped names sort chronologically, so the last is the current file
+fn latest_log(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(LOG_PREFIX))
+        })
+        .max()
+}
+
+/// Read backwards from the end until `count` lines are found
+fn tail(path: &Path, count: usize) -> Result<Vec<String>, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open log: {}", e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read log: {}", e))?
+        .len();
+    let mut start = size;
+    let mut buffer = Vec::new();
+    while start > 0 && buffer.iter().filter(|&&b| b == b'\n').count() <= count {
+        let step = TAIL_STEP.min(start);
+        start -= step;
+        let mut chunk = vec![0; step as usize];
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_exact(&mut chunk))
+            .map_err(|e| format!("Failed to read log: {}", e))?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+    }
+    let text = String::from_utf8_lossy(&buffer);
+    let lines: Vec<&str> = text.lines().collect();
+    // The first line may be cut off unless the whole file was read
+    let skip = usize::from(start > 0);
+    Ok(lines[skip.min(lines.len())..]
+        .iter()
+        .rev()
+        .take(count)
+        .rev()
+        .map(|line| line.to_string())
+        .collect())
+}
@@ -0,0 +1,361 @@
+use crate::cancel::{self, CancelToken};
+use crate::document;
+use crate::error::Error;
+use crate::{catalog, local_api, paths, read_only, search, vault, workspace};
+use mlua::{
+    HookTriggers, Lua, LuaOptions, LuaSerdeExt, StdLib, Value as LuaValue, Variadic, VmState,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::AppHandle;
+
+const MACRO_EXTENSION: &str = "lua";
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+/// Instructions between checks for cancellation and the instruction budget
+const CHECK_EVERY: u32 = 10_000;
+/// Enough for text processing over a large document; runaway loops stop here
+const MAX_INSTRUCTIONS: u64 = 500_000_000;
+/// Lines of `print` output kept for the result
+const MAX_OUTPUT: usize = 1000;
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacroInfo {
+    pub name: String,
+    /// Leading `--` comment lines of the macro
+    pub description: Option<String>,
+}
+
+/// What the editor had open when the macro was run
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MacroContext {
+    pub path: Option<String>,
+    pub selection: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacroRun {
+    /// Value the macro returned
+    pub value: Value,
+    pub output: Vec<String>,
+    /// Text passed to `ink.insert`, in order, for the editor to insert at the cursor
+    pub inserted: Vec<String>,
+}
+
+fn dir(root: &Path) -> PathBuf {
+    workspace::internal_dir(root).join("macros")
+}
+
+fn macro_path(root: &Path, name: &str) -> PathBuf {
+    dir(root).join(format!(
+        "{}.{}",
+        local_api::file_stem(name),
+        MACRO_EXTENSION
+    ))
+}
+
+fn description(source: &str) -> Option<String> {
+    let lines: Vec<&str> = source
+        .lines()
+        .map_while(|line| line.trim().strip_prefix("--"))
+        .map(str::trim)
+        .collect();
+    Some(lines.join(" ").trim().to_string()).filter(|text| !text.is_empty())
+}
+
+/// State shared by the functions of the `ink` table
+struct Host {
+    app: AppHandle,
+    root: PathBuf,
+    context: MacroContext,
+}
+
+impl Host {
+    /// The open document; vaults are off limits
+    fn document(&self, lua: &Lua) -> mlua::Result<LuaValue> {
+        let Some(path) = &self.context.path else {
+            return Ok(LuaValue::Nil);
+        };
+        let fail = |error: String| mlua::Error::runtime(error);
+        let path =
+            paths::check(&self.app, path, paths::Scope::Read).map_err(|e| fail(e.message))?;
+        if !path.starts_with(&self.root) {
+            return Err(fail(format!("Invalid path: {}", path.display())));
+        }
+        if vault::vault_of(&path).is_some() {
+            return Err(fail("Macros cannot read documents in a vault".to_string()));
+        }
+        let board = document::read_board(&path).map_err(fail)?;
+        let markdown = board.to_markdown();
+        lua.to_value(&json!({
+            "path": workspace::relative_path(&self.root, &path),
+            "name": board.board.name,
+            "tags": catalog::tags(&markdown),
+            "markdown": markdown,
+        }))
+    }
+
+    fn search(&self, lua: &Lua, query: &str, limit: Option<usize>) -> mlua::Result<LuaValue> {
+        let hits = search::search_documents(
+            self.app.clone(),
+            self.root.to_string_lossy().to_string(),
+            query.to_string(),
+            Some(limit.unwrap_or(DEFAULT_SEARCH_LIMIT).max(1)),
+            None,
+            None,
+            None,
+        )
+        .map_err(|e| mlua::Error::runtime(e.message))?;
+        // The search command includes unlocked vault documents
+        let hits: Vec<_> = hits
+            .into_iter()
+            .filter(|hit| vault::vault_of(&self.root.join(&hit.path)).is_none())
+            .collect();
+        lua.to_value(&hits)
+    }
+}
+
+/// A Lua state without `io`, `os`, `package` or `debug`, with the `ink` table and a `print`
+/// that collects its output
+fn state(
+    host: Host,
+    cancel: &CancelToken,
+    output: &Arc<Mutex<Vec<String>>>,
+    inserted: &Arc<Mutex<Vec<String>>>,
+) -> mlua::Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8,
+        LuaOptions::new(),
+    )?;
+    lua.set_memory_limit(MAX_MEMORY)?;
+    let cancel = cancel.clone();
+    let executed = AtomicU64::new(0);
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(CHECK_EVERY),
+        move |_, _| {
+            if cancel.is_cancelled() {
+                return Err(mlua::Error::runtime(cancel::CANCELLED));
+            }
+            let total = executed.fetch_add(CHECK_EVERY as u64, Ordering::Relaxed);
+            if total >= MAX_INSTRUCTIONS {
+                return Err(mlua::Error::runtime("Macro ran too long"));
+            }
+            Ok(VmState::Continue)
+        },
+    )?;
+
+    let globals = lua.globals();
+    let lines = output.clone();
+    globals.set(
+        "print",
+        lua.create_function(move |lua, values: Variadic<LuaValue>| {
+            let line: Vec<String> = values
+                .into_iter()
+                .map(|value| display(lua, value))
+                .collect();
+            if let Ok(mut lines) = lines.lock() {
+                if lines.len() < MAX_OUTPUT {
+                    lines.push(line.join("\t"));
+                }
+            }
+            Ok(())
+        })?,
+    )?;
+
+    let host = Arc::new(host);
+    let ink = lua.create_table()?;
+    let h = host.clone();
+    ink.set(
+        "document",
+        lua.create_function(move |lua, ()| h.document(lua))?,
+    )?;
+    let h = host.clone();
+    ink.set(
+        "selection",
+        lua.create_function(move |_, ()| Ok(h.context.selection.clone()))?,
+    )?;
+    let texts = inserted.clone();
+    ink.set(
+        "insert",
+        lua.create_function(move |_, text: String| {
+            if let Ok(mut texts) = texts.lock() {
+                texts.push(text);
+            }
+            Ok(())
+        })?,
+    )?;
+    let h = host;
+    ink.set(
+        "search",
+        lua.create_function(move |lua, (query, limit): (String, Option<usize>)| {
+            h.search(lua, &query, limit)
+        })?,
+    )?;
+    globals.set("ink", ink)?;
+    Ok(lua)
+}
+
+/// How `print` shows a value: strings as they are, tables as JSON
+fn display(lua: &Lua, value: LuaValue) -> String {
+    match value {
+        LuaValue::Nil => "nil".to_string(),
+        LuaValue::String(text) => text.to_string_lossy(),
+        other => {
+            let name = other.type_name();
+            match lua.from_value::<Value>(other) {
+                Ok(Value::String(text)) => text,
+                Ok(value) => value.to_string(),
+                Err(_) => name.to_string(),
+            }
+        }
+    }
+}
+
+fn run(
+    app: &AppHandle,
+    root: PathBuf,
+    name: &str,
+    args: Value,
+    context: MacroContext,
+    cancel: &CancelToken,
+) -> Result<MacroRun, String> {
+    let source = fs::read_to_string(macro_path(&root, name)).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("Macro not found: {}", name),
+        _ => format!("Failed to read macro: {}", e),
+    })?;
+    let host = Host {
+        app: app.clone(),
+        root,
+        context,
+    };
+    let output = Arc::default();
+    let inserted = Arc::default();
+    let lua = state(host, cancel, &output, &inserted)
+        .map_err(|e| format!("Failed to start macro: {}", e))?;
+
+    let args = lua
+        .to_value(&args)
+        .map_err(|e| format!("Invalid args: {}", e))?;
+    lua.globals()
+        .set("args", args)
+        .map_err(|e| format!("Invalid args: {}", e))?;
+    let value = match lua.load(&source).set_name(name).eval::<LuaValue>() {
+        Ok(value) => value,
+        Err(_) if cancel.is_cancelled() => return Err(cancel::CANCELLED.to_string()),
+        Err(error) => return Err(format!("Macro failed: {}", error)),
+    };
+    let value: Value = lua
+        .from_value(value)
+        .map_err(|e| format!("Failed to convert macro result: {}", e))?;
+
+    let take =
+        |lines: &Arc<Mutex<Vec<String>>>| lines.lock().map(|l| l.clone()).unwrap_or_default();
+    Ok(MacroRun {
+        value,
+        output: take(&output),
+        inserted: take(&inserted),
+    })
+}
+
+/// Macros saved in the workspace's `.inkfinite/macros`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_macros(app: AppHandle, workspace: String) -> Result<Vec<MacroInfo>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let Ok(entries) = fs::read_dir(dir(&root)) else {
+        return Ok(Vec::new());
+    };
+    let mut macros: Vec<MacroInfo> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == MACRO_EXTENSION))
+        .map(|path| MacroInfo {
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            description: fs::read_to_string(&path)
+                .ok()
+                .and_then(|source| description(&source)),
+        })
+        .collect();
+    macros.sort_by_key(|info| info.name.to_lowercase());
+    Ok(macros)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn read_macro(app: AppHandle, workspace: String, name: String) -> Result<String, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    fs::read_to_string(macro_path(&root, &name)).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => Error::not_found("Macro not found").with_context(&name),
+        _ => format!("Failed to read macro: {}", e).into(),
+    })
+}
+
+/// Save a macro; returns its name as stored
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_macro(
+    app: AppHandle,
+    workspace: String,
+    name: String,
+    source: String,
+) -> Result<String, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    let name = local_api::file_stem(&name);
+    fs::create_dir_all(dir(&root)).map_err(|e| format!("Failed to create folder: {}", e))?;
+    fs::write(macro_path(&root, &name), source)
+        .map_err(|e| format!("Failed to write macro: {}", e))?;
+    Ok(name)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_macro(app: AppHandle, workspace: String, name: String) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    match fs::remove_file(macro_path(&root, &name)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete macro: {}", e).into()),
+    }
+}
+
+/// Run a saved Lua macro of the open workspace, with `args` bound to the `args` global.
+/// Macros get the `table`, `string`, `math` and `utf8` libraries and an `ink` table with
+/// `document()`, `selection()`, `insert(text)` and `search(query[, limit])`; they cannot touch
+/// files, and inserted text is returned for the editor to apply.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn run_macro(
+    app: AppHandle,
+    name: String,
+    args: Option<Value>,
+    context: Option<MacroContext>,
+    op_id: Option<String>,
+) -> Result<MacroRun, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let root = workspace::current_root(&app).ok_or("No workspace is open")?;
+        let operation = cancel::begin(&app, op_id);
+        run(
+            &app,
+            root,
+            &name,
+            args.unwrap_or(Value::Null),
+            context.unwrap_or_default(),
+            operation.token(),
+        )
+        .map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Macro task failed: {}", e))?
+}
@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(status) = desktop_lib::run_headless(&args) {
+        std::process::exit(status);
+    }
     desktop_lib::run()
 }
@@ -0,0 +1,400 @@
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const PORT: u16 = 5353;
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set on records only this host answers for, and on questions asking for a unicast reply
+const CACHE_FLUSH: u16 = 0x8000;
+/// Seconds other hosts may cache the records for
+const TTL: u32 = 120;
+/// Queries are repeated once in case the first is lost
+const QUERY_INTERVAL: Duration = Duration::from_millis(800);
+
+/// A service instance this host advertises with multicast DNS (RFC 6762) and DNS-SD records
+/// (RFC 6763)
+#[derive(Clone)]
+pub struct Service {
+    /// Service type, such as `_inkfinite._tcp.local`
+    pub kind: String,
+    /// Instance label, unique on the network
+    pub instance: String,
+    pub port: u16,
+    pub txt: Vec<(String, String)>,
+}
+
+/// A service instance found while browsing
+pub struct Found {
+    pub instance: String,
+    pub address: SocketAddr,
+    pub txt: HashMap<String, String>,
+}
+
+enum Data {
+    Ptr(String),
+    Srv { port: u16, target: String },
+    Txt(Vec<String>),
+    A(Ipv4Addr),
+    Other,
+}
+
+struct Record {
+    name: String,
+    data: Data,
+}
+
+struct Message {
+    id: u16,
+    is_response: bool,
+    questions: Vec<(String, u16)>,
+    records: Vec<Record>,
+}
+
+/// Answer queries for `service` on a background thread for as long as the app runs, while
+/// `enabled` returns true
+pub fn advertise(service: Service, enabled: fn() -> bool) -> Result<(), String> {
+    let socket = multicast_socket()?;
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; 9000];
+        loop {
+            let Ok((len, source)) = socket.recv_from(&mut buffer) else {
+                continue;
+            };
+            if !enabled() {
+                continue;
+            }
+            let Some(query) = parse(&buffer[..len]) else {
+                continue;
+            };
+            let asked = query.questions.iter().any(|(name, kind)| {
+                name.eq_ignore_ascii_case(&service.kind) && matches!(*kind, TYPE_PTR | TYPE_ANY)
+            });
+            if query.is_response || !asked {
+                continue;
+            }
+            // Queries from a port other than 5353 come from simple resolvers that expect a
+            // direct reply echoing the query (RFC 6762 section 6.7)
+            let legacy = source.port() != PORT;
+            let reply = response(&service, &query, legacy, local_address(source.ip()));
+            let target = if legacy {
+                source
+            } else {
+                SocketAddr::from((GROUP, PORT))
+            };
+            if let Err(error) = socket.send_to(&reply, target) {
+                tracing::debug!(%error, "Failed to answer mDNS query");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Instances of service `kind` that answer within `timeout`
+pub fn browse(kind: &str, timeout: Duration) -> Result<Vec<Found>, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .map_err(|e| format!("Failed to open network socket: {}", e))?;
+    let query = query(kind);
+    let deadline = Instant::now() + timeout;
+    let mut next_query = Instant::now();
+    let mut instances: Vec<String> = Vec::new();
+    let mut services: HashMap<String, (u16, String)> = HashMap::new();
+    let mut texts: HashMap<String, Vec<String>> = HashMap::new();
+    let mut hosts: HashMap<String, Ipv4Addr> = HashMap::new();
+    let mut sources: HashMap<String, IpAddr> = HashMap::new();
+    let mut buffer = [0u8; 9000];
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        if now >= next_query {
+            socket
+                .send_to(&query, (GROUP, PORT))
+                .map_err(|e| format!("Failed to send mDNS query: {}", e))?;
+            next_query = now + QUERY_INTERVAL;
+        }
+        let wait = deadline.min(next_query).saturating_duration_since(now);
+        let _ = socket.set_read_timeout(Some(wait.max(Duration::from_millis(1))));
+        let Ok((len, source)) = socket.recv_from(&mut buffer) else {
+            continue;
+        };
+        let Some(message) = parse(&buffer[..len]) else {
+            continue;
+        };
+        for record in message.records {
+            match record.data {
+                Data::Ptr(target) if record.name.eq_ignore_ascii_case(kind) => {
+                    sources.insert(target.to_ascii_lowercase(), source.ip());
+                    if !instances
+                        .iter()
+                        .any(|known| known.eq_ignore_ascii_case(&target))
+                    {
+                        instances.push(target);
+                    }
+                }
+                Data::Srv { port, target } => {
+                    services.insert(record.name.to_ascii_lowercase(), (port, target));
+                }
+                Data::Txt(entries) => {
+                    texts.insert(record.name.to_ascii_lowercase(), entries);
+                }
+                Data::A(address) => {
+                    hosts.insert(record.name.to_ascii_lowercase(), address);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let suffix = format!(".{}", kind);
+    Ok(instances
+        .into_iter()
+        .filter_map(|full| {
+            let key = full.to_ascii_lowercase();
+            let (port, target) = services.get(&key)?;
+            let ip = hosts
+                .get(&target.to_ascii_lowercase())
+                .map(|address| IpAddr::V4(*address))
+                .or_else(|| sources.get(&key).copied())?;
+            let txt = texts
+                .get(&key)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.split_once('='))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            let instance = full.strip_suffix(&suffix).unwrap_or(&full).to_string();
+            Some(Found {
+                instance,
+                address: SocketAddr::new(ip, *port),
+                txt,
+            })
+        })
+        .collect())
+}
+
+/// A socket on the mDNS port that other responders on this host can share
+fn multicast_socket() -> Result<UdpSocket, String> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+        .map_err(|e| format!("Failed to open network socket: {}", e))?;
+    socket
+        .set_reuse_address(true)
+        .map_err(|e| format!("Failed to open network socket: {}", e))?;
+    #[cfg(unix)]
+    socket
+        .set_reuse_port(true)
+        .map_err(|e| format!("Failed to open network socket: {}", e))?;
+    socket
+        .bind(&SockAddr::from(SocketAddrV4::new(
+            Ipv4Addr::UNSPECIFIED,
+            PORT,
+        )))
+        .map_err(|e| format!("Failed to listen for mDNS: {}", e))?;
+    socket
+        .join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED)
+        .map_err(|e| format!("Failed to join mDNS group: {}", e))?;
+    Ok(socket.into())
+}
+
+/// This host's address on the interface that reaches `peer`
+fn local_address(peer: IpAddr) -> Option<Ipv4Addr> {
+    let target = match peer {
+        IpAddr::V4(address) if !address.is_unspecified() => address,
+        _ => GROUP,
+    };
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    // Connecting a UDP socket sends nothing; it only picks the route
+    socket.connect((target, PORT)).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(address) if !address.is_unspecified() => Some(address),
+        _ => None,
+    }
+}
+
+fn query(kind: &str) -> Vec<u8> {
+    let mut packet = Vec::new();
+    let mut id = [0u8; 2];
+    crate::crypto::fill_random(&mut id);
+    packet.extend_from_slice(&id);
+    packet.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    write_name(&mut packet, kind);
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    packet
+}
+
+fn response(
+    service: &Service,
+    query: &Message,
+    legacy: bool,
+    address: Option<Ipv4Addr>,
+) -> Vec<u8> {
+    let full = format!("{}.{}", service.instance, service.kind);
+    let host = format!("{}.local", service.instance);
+    // Legacy replies must not set the cache-flush bit and must echo the question
+    let unique = if legacy {
+        CLASS_IN
+    } else {
+        CLASS_IN | CACHE_FLUSH
+    };
+    let additional = 2 + u16::from(address.is_some());
+
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&if legacy { query.id } else { 0 }.to_be_bytes());
+    packet.extend_from_slice(&0x8400u16.to_be_bytes());
+    packet.extend_from_slice(&u16::from(legacy).to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(&additional.to_be_bytes());
+    if legacy {
+        write_name(&mut packet, &service.kind);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    }
+
+    let mut ptr = Vec::new();
+    write_name(&mut ptr, &full);
+    write_record(&mut packet, &service.kind, TYPE_PTR, CLASS_IN, &ptr);
+
+    let mut srv = Vec::new();
+    srv.extend_from_slice(&[0, 0, 0, 0]);
+    srv.extend_from_slice(&service.port.to_be_bytes());
+    write_name(&mut srv, &host);
+    write_record(&mut packet, &full, TYPE_SRV, unique, &srv);
+
+    let mut txt = Vec::new();
+    for (key, value) in &service.txt {
+        let entry = format!("{}={}", key, value);
+        let entry = &entry.as_bytes()[..entry.len().min(255)];
+        txt.push(entry.len() as u8);
+        txt.extend_from_slice(entry);
+    }
+    if txt.is_empty() {
+        txt.push(0);
+    }
+    write_record(&mut packet, &full, TYPE_TXT, unique, &txt);
+
+    if let Some(address) = address {
+        write_record(&mut packet, &host, TYPE_A, unique, &address.octets());
+    }
+    packet
+}
+
+fn write_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label);
+    }
+    packet.push(0);
+}
+
+fn write_record(packet: &mut Vec<u8>, name: &str, kind: u16, class: u16, data: &[u8]) {
+    write_name(packet, name);
+    packet.extend_from_slice(&kind.to_be_bytes());
+    packet.extend_from_slice(&class.to_be_bytes());
+    packet.extend_from_slice(&TTL.to_be_bytes());
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(data);
+}
+
+fn parse(packet: &[u8]) -> Option<Message> {
+    let id = read_u16(packet, 0)?;
+    let flags = read_u16(packet, 2)?;
+    let questions = read_u16(packet, 4)?;
+    let records = [6, 8, 10]
+        .iter()
+        .map(|&at| read_u16(packet, at).map(usize::from))
+        .sum::<Option<usize>>()?;
+
+    let mut at = 12;
+    let mut message = Message {
+        id,
+        is_response: flags & 0x8000 != 0,
+        questions: Vec::new(),
+        records: Vec::new(),
+    };
+    for _ in 0..questions {
+        let name = read_name(packet, &mut at)?;
+        let kind = read_u16(packet, at)?;
+        at += 4;
+        message.questions.push((name, kind));
+    }
+    for _ in 0..records {
+        let name = read_name(packet, &mut at)?;
+        let kind = read_u16(packet, at)?;
+        let len = usize::from(read_u16(packet, at + 8)?);
+        let start = at + 10;
+        let end = start.checked_add(len).filter(|&end| end <= packet.len())?;
+        let data = match kind {
+            TYPE_PTR => Data::Ptr(read_name(packet, &mut start.clone())?),
+            TYPE_SRV => Data::Srv {
+                port: read_u16(packet, start + 4)?,
+                target: read_name(packet, &mut (start + 6))?,
+            },
+            TYPE_TXT => {
+                let mut entries = Vec::new();
+                let mut offset = start;
+                while offset < end {
+                    let entry_len = usize::from(packet[offset]);
+                    let entry = packet.get(offset + 1..offset + 1 + entry_len)?;
+                    entries.push(String::from_utf8_lossy(entry).to_string());
+                    offset += 1 + entry_len;
+                }
+                Data::Txt(entries)
+            }
+            TYPE_A if len == 4 => Data::A(Ipv4Addr::new(
+                packet[start],
+                packet[start + 1],
+                packet[start + 2],
+                packet[start + 3],
+            )),
+            _ => Data::Other,
+        };
+        message.records.push(Record { name, data });
+        at = end;
+    }
+    Some(message)
+}
+
+fn read_u16(packet: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]))
+}
+
+/// Read a possibly compressed name at `at`, advancing past it
+fn read_name(packet: &[u8], at: &mut usize) -> Option<String> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut offset = *at;
+    let mut jumped = false;
+    // Bounds the number of compression pointers followed, so loops cannot hang
+    for _ in 0..128 {
+        let len = *packet.get(offset)?;
+        if len == 0 {
+            if !jumped {
+                *at = offset + 1;
+            }
+            return Some(labels.join("."));
+        }
+        if len & 0xC0 == 0xC0 {
+            let pointer = usize::from(read_u16(packet, offset)? & 0x3FFF);
+            if !jumped {
+                *at = offset + 2;
+            }
+            jumped = true;
+            offset = pointer;
+            continue;
+        }
+        let label = packet.get(offset + 1..offset + 1 + usize::from(len))?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        offset += 1 + usize::from(len);
+    }
+    None
+}
@@ -0,0 +1,250 @@
+use crate::error::Error;
+use crate::workspace;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+const FILE_MENU: &str = "file";
+const OPEN_RECENT: &str = "open-recent";
+/// Recent documents listed under File > Open Recent
+const MAX_RECENT: usize = 10;
+
+/// Accelerators of the custom menu items, by item id
+pub const ACCELERATORS: &[(&str, &str)] = &[
+    ("new-document", "CmdOrCtrl+N"),
+    ("new-window", "CmdOrCtrl+Shift+N"),
+    ("open", "CmdOrCtrl+O"),
+    ("open-workspace", "CmdOrCtrl+Shift+O"),
+    ("save", "CmdOrCtrl+S"),
+    ("save-as", "CmdOrCtrl+Shift+S"),
+    ("export", "CmdOrCtrl+E"),
+    ("find", "CmdOrCtrl+F"),
+    ("search-workspace", "CmdOrCtrl+Shift+F"),
+    ("toggle-sidebar", "CmdOrCtrl+\\"),
+    ("zoom-in", "CmdOrCtrl+="),
+    ("zoom-out", "CmdOrCtrl+-"),
+    ("zoom-reset", "CmdOrCtrl+0"),
+    ("settings", "CmdOrCtrl+,"),
+];
+
+/// Shortcuts the predefined items get from the platform, by item
+pub const PREDEFINED_ACCELERATORS: &[(&str, &str)] = &[
+    ("undo", "CmdOrCtrl+Z"),
+    ("redo", "CmdOrCtrl+Shift+Z"),
+    ("cut", "CmdOrCtrl+X"),
+    ("copy", "CmdOrCtrl+C"),
+    ("paste", "CmdOrCtrl+V"),
+    ("select-all", "CmdOrCtrl+A"),
+    ("close-window", "CmdOrCtrl+W"),
+    ("quit", "CmdOrCtrl+Q"),
+    ("minimize", "CmdOrCtrl+M"),
+    #[cfg(target_os = "macos")]
+    ("fullscreen", "Ctrl+Cmd+F"),
+    #[cfg(not(target_os = "macos"))]
+    ("fullscreen", "F11"),
+];
+
+fn accelerator(id: &str) -> Option<&'static str> {
+    ACCELERATORS
+        .iter()
+        .find(|(item, _)| *item == id)
+        .map(|(_, accelerator)| *accelerator)
+}
+
+/// Build the application menu bar; custom items are routed to the frontend by [`handle_event`]
+pub fn build(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let item = |id: &str, text: &str| MenuItem::with_id(app, id, text, true, accelerator(id));
+
+    let open_recent = Submenu::with_id(app, OPEN_RECENT, "Open Recent", true)?;
+    fill_recent(app, &open_recent)?;
+
+    let file = Submenu::with_id_and_items(
+        app,
+        FILE_MENU,
+        "File",
+        true,
+        &[
+            &item("new-document", "New Document")?,
+            &item("new-window", "New Window")?,
+            &item("open", "Open…")?,
+            &open_recent,
+            &item("open-workspace", "Open Workspace…")?,
+            &PredefinedMenuItem::separator(app)?,
+            &item("save", "Save")?,
+            &item("save-as", "Save As…")?,
+            &item("export", "Export…")?,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::close_window(app, None)?,
+            // macOS keeps Quit in the app menu
+            #[cfg(not(target_os = "macos"))]
+            &PredefinedMenuItem::quit(app, None)?,
+        ],
+    )?;
+
+    let edit = Submenu::with_items(
+        app,
+        "Edit",
+        true,
+        &[
+            &PredefinedMenuItem::undo(app, None)?,
+            &PredefinedMenuItem::redo(app, None)?,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::cut(app, None)?,
+            &PredefinedMenuItem::copy(app, None)?,
+            &PredefinedMenuItem::paste(app, None)?,
+            &PredefinedMenuItem::select_all(app, None)?,
+            &PredefinedMenuItem::separator(app)?,
+            &item("find", "Find…")?,
+            &item("search-workspace", "Search Workspace…")?,
+        ],
+    )?;
+
+    let view = Submenu::with_items(
+        app,
+        "View",
+        true,
+        &[
+            &item("toggle-sidebar", "Toggle Sidebar")?,
+            &PredefinedMenuItem::separator(app)?,
+            &item("zoom-in", "Zoom In")?,
+            &item("zoom-out", "Zoom Out")?,
+            &item("zoom-reset", "Actual Size")?,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::fullscreen(app, None)?,
+        ],
+    )?;
+
+    let window = Submenu::with_items(
+        app,
+        "Window",
+        true,
+        &[
+            &PredefinedMenuItem::minimize(app, None)?,
+            &PredefinedMenuItem::maximize(app, None)?,
+        ],
+    )?;
+
+    #[cfg(target_os = "macos")]
+    {
+        let app_menu = Submenu::with_items(
+            app,
+            "Inkfinite",
+            true,
+            &[
+                &PredefinedMenuItem::about(app, None, None)?,
+                &PredefinedMenuItem::separator(app)?,
+                &item("settings", "Settings…")?,
+                &PredefinedMenuItem::separator(app)?,
+                &PredefinedMenuItem::services(app, None)?,
+                &PredefinedMenuItem::separator(app)?,
+                &PredefinedMenuItem::hide(app, None)?,
+                &PredefinedMenuItem::quit(app, None)?,
+            ],
+        )?;
+        Menu::with_items(app, &[&app_menu, &file, &edit, &view, &window])
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        edit.append_items(&[
+            &PredefinedMenuItem::separator(app)?,
+            &item("settings", "Settings…")?,
+        ])?;
+        Menu::with_items(app, &[&file, &edit, &view, &window])
+    }
+}
+
+/// Forward custom menu items to the focused window as `menu:<id>` events
+pub fn handle_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    if crate::context_menu::handle_event(app, id) {
+        return;
+    }
+
+    if id == "clear-recent" {
+        let _ = workspace::clear_recent_files(app);
+        let _ = refresh_menu(app.clone());
+        return;
+    }
+    if let Some(index) = id
+        .strip_prefix("recent:")
+        .and_then(|index| index.parse::<usize>().ok())
+    {
+        if let Some(file) = workspace::recent_files(app).get(index) {
+            emit_to_focused(app, "menu:open-recent", file.path.clone());
+        }
+        return;
+    }
+    emit_to_focused(app, &format!("menu:{}", id), ());
+}
+
+/// Rebuild File > Open Recent, the tray menu, and the jump list or dock menu after the recent
+/// files list changes
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn refresh_menu(app: AppHandle) -> Result<(), Error> {
+    let submenu = app
+        .menu()
+        .and_then(|menu| menu.get(FILE_MENU))
+        .and_then(|file| file.as_submenu().and_then(|file| file.get(OPEN_RECENT)))
+        .and_then(|recent| recent.as_submenu().cloned());
+
+    if let Some(submenu) = submenu {
+        let count = submenu
+            .items()
+            .map_err(|e| format!("Failed to read menu: {}", e))?
+            .len();
+        for _ in 0..count {
+            submenu
+                .remove_at(0)
+                .map_err(|e| format!("Failed to update menu: {}", e))?;
+        }
+        fill_recent(&app, &submenu).map_err(|e| format!("Failed to update menu: {}", e))?;
+    }
+
+    crate::recents::update(&app);
+    crate::tray::refresh_tray(app)
+}
+
+fn fill_recent(app: &AppHandle, submenu: &Submenu<Wry>) -> tauri::Result<()> {
+    let files = workspace::recent_files(app);
+    if files.is_empty() {
+        return submenu.append(&MenuItem::with_id(
+            app,
+            "recent-empty",
+            "No Recent Documents",
+            false,
+            None::<&str>,
+        )?);
+    }
+
+    for (index, file) in files.iter().take(MAX_RECENT).enumerate() {
+        let name = file
+            .name
+            .clone()
+            .unwrap_or_else(|| crate::document::document_stem(std::path::Path::new(&file.path)));
+        submenu.append(&MenuItem::with_id(
+            app,
+            format!("recent:{}", index),
+            name,
+            true,
+            None::<&str>,
+        )?)?;
+    }
+    submenu.append(&PredefinedMenuItem::separator(app)?)?;
+    submenu.append(&MenuItem::with_id(
+        app,
+        "clear-recent",
+        "Clear Menu",
+        true,
+        None::<&str>,
+    )?)
+}
+
+fn emit_to_focused<S: serde::Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    let label = app
+        .webview_windows()
+        .into_iter()
+        .find(|(_, window)| window.is_focused().unwrap_or(false))
+        .map(|(label, _)| label)
+        .unwrap_or_else(|| crate::windows::MAIN_WINDOW.to_string());
+    let _ = app.emit_to(label.as_str(), event, payload);
+}
@@ -0,0 +1,158 @@
+use crate::error::{Error, ErrorCode};
+use crate::settings;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LazyLock;
+use tauri::AppHandle;
+use unic_langid::LanguageIdentifier;
+
+/// Catalogs built into the app; the first is the fallback for anything another one lacks
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+    ("fr", include_str!("../locales/fr.ftl")),
+];
+
+static BUNDLES: LazyLock<Vec<FluentBundle<FluentResource>>> = LazyLock::new(|| {
+    CATALOGS
+        .iter()
+        .map(|(tag, source)| {
+            let language: LanguageIdentifier = tag.parse().unwrap_or_default();
+            let mut bundle = FluentBundle::new_concurrent(vec![language]);
+            // Notifications and documents are plain text, where isolation marks show up as junk
+            bundle.set_use_isolating(false);
+            let resource =
+                FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, errors)| {
+                    tracing::warn!(locale = tag, ?errors, "Message catalog has errors");
+                    resource
+                });
+            if let Err(errors) = bundle.add_resource(resource) {
+                tracing::warn!(locale = tag, ?errors, "Message catalog has duplicates");
+            }
+            bundle
+        })
+        .collect()
+});
+
+/// Index into `CATALOGS` of the language messages are formatted in
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// Catalog messages are formatted with
+    pub locale: &'static str,
+    /// Language asked for in settings; the system language when unset
+    pub requested: Option<String>,
+    pub available: Vec<&'static str>,
+}
+
+/// Catalog for a BCP 47 tag, matched on the language alone
+fn negotiate(tag: &str) -> Option<usize> {
+    let language: LanguageIdentifier = tag.replace('_', "-").parse().ok()?;
+    CATALOGS
+        .iter()
+        .position(|(catalog, _)| *catalog == language.language.as_str())
+}
+
+/// Format the messages in the language from settings, or the system's when none is set
+pub fn load(app: &AppHandle) {
+    let requested = settings::current(app, None)
+        .language
+        .or_else(sys_locale::get_locale);
+    let index = requested.as_deref().and_then(negotiate).unwrap_or(0);
+    CURRENT.store(index, Ordering::Relaxed);
+    tracing::debug!(locale = CATALOGS[index].0, "Message locale set");
+}
+
+fn lookup(id: &str, attribute: Option<&str>, args: Option<&FluentArgs>) -> Option<String> {
+    let current = CURRENT.load(Ordering::Relaxed);
+    [current, 0].into_iter().find_map(|index| {
+        let bundle = &BUNDLES[index];
+        let message = bundle.get_message(id)?;
+        let pattern = match attribute {
+            Some(attribute) => message.get_attribute(attribute)?.value(),
+            None => message.value()?,
+        };
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, args, &mut errors);
+        if !errors.is_empty() {
+            tracing::warn!(id, ?errors, "Failed to format message");
+        }
+        Some(text.into_owned())
+    })
+}
+
+/// A message from the catalog in the current language, with `args` filled in; the id itself
+/// if no catalog has it
+pub fn format(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let mut fluent = FluentArgs::new();
+    for (name, value) in args {
+        fluent.set(*name, value.clone());
+    }
+    lookup(id, None, Some(&fluent)).unwrap_or_else(|| id.to_string())
+}
+
+/// A message without arguments
+pub fn text(id: &str) -> String {
+    format(id, &[])
+}
+
+/// What the frontend shows for an error: the code's message, naming the context when there is
+/// one. The English `message` stays as it is for logs.
+pub fn error(code: ErrorCode, context: Option<&str>) -> String {
+    let id = match code {
+        ErrorCode::NotFound => "error-not-found",
+        ErrorCode::PermissionDenied => "error-permission-denied",
+        ErrorCode::Conflict => "error-conflict",
+        ErrorCode::InvalidPath => "error-invalid-path",
+        ErrorCode::InvalidData => "error-invalid-data",
+        ErrorCode::Timeout => "error-timeout",
+        ErrorCode::Cancelled => "error-cancelled",
+        ErrorCode::Unavailable => "error-unavailable",
+        ErrorCode::ReadOnly => "error-read-only",
+        ErrorCode::Locked => "error-locked",
+        ErrorCode::Internal => "error-internal",
+    };
+    let with_context = context.and_then(|context| {
+        let mut args = FluentArgs::new();
+        args.set("context", context);
+        lookup(id, Some("with-context"), Some(&args))
+    });
+    with_context
+        .or_else(|| lookup(id, None, None))
+        .unwrap_or_else(|| id.to_string())
+}
+
+fn info(app: &AppHandle) -> LocaleInfo {
+    LocaleInfo {
+        locale: CATALOGS[CURRENT.load(Ordering::Relaxed)].0,
+        requested: settings::current(app, None).language,
+        available: CATALOGS.iter().map(|(tag, _)| *tag).collect(),
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_locale(app: AppHandle) -> LocaleInfo {
+    info(&app)
+}
+
+/// Set the language of backend messages, saved as the `language` setting; `None` follows the
+/// system language
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_locale(app: AppHandle, locale: Option<String>) -> Result<LocaleInfo, Error> {
+    if let Some(locale) = &locale {
+        locale
+            .replace('_', "-")
+            .parse::<LanguageIdentifier>()
+            .map_err(|_| format!("Invalid locale: {}", locale))?;
+    }
+    let mut changes = serde_json::Map::new();
+    changes.insert("language".to_string(), serde_json::json!(locale));
+    settings::update_settings(app.clone(), changes, None)?;
+    Ok(info(&app))
+}
@@ -0,0 +1,266 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::error::Error;
+use crate::paths;
+use crate::workspace;
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use tauri::AppHandle;
+
+const PRIVACY_CONFIG: &str = "privacy";
+/// Quality used when a JPEG must be re-encoded to bake in its EXIF orientation
+const REENCODE_QUALITY: u8 = 92;
+
+/// Per-workspace privacy defaults, stored in `.inkfinite/privacy.json`
+#[derive(serde::Serialize, serde::Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrivacySettings {
+    /// Remove EXIF, XMP, and text metadata from images as they are imported
+    pub strip_image_metadata: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StripFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(serde::Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StripReport {
+    pub stripped: usize,
+    pub unchanged: usize,
+    pub bytes_removed: u64,
+    pub failed: Vec<StripFailure>,
+}
+
+pub fn load_privacy(root: &Path) -> PrivacySettings {
+    workspace::read_config(root, PRIVACY_CONFIG).unwrap_or_default()
+}
+
+/// Read privacy settings for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_privacy_settings(app: AppHandle, workspace: String) -> PrivacySettings {
+    if paths::check_workspace(&app, &workspace, paths::Scope::Read).is_err() {
+        return PrivacySettings::default();
+    }
+    load_privacy(Path::new(&workspace))
+}
+
+/// Update privacy settings for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_privacy_settings(
+    app: AppHandle,
+    workspace: String,
+    settings: PrivacySettings,
+) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    Ok(workspace::write_config(
+        Path::new(&workspace),
+        PRIVACY_CONFIG,
+        &settings,
+    )?)
+}
+
+/// Remove metadata from image files in place
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn strip_asset_metadata(app: AppHandle, paths: Vec<String>) -> StripReport {
+    let mut report = StripReport::default();
+
+    for path in paths {
+        let result = paths::check(&app, &path, paths::Scope::Write)
+            .map_err(|e| e.message)
+            .and_then(|_| fs::read(&path).map_err(|e| format!("Failed to read file: {}", e)))
+            .and_then(|bytes| Ok((strip(&bytes)?, bytes.len())));
+
+        match result {
+            Ok((Some(stripped), before)) => {
+                if let Err(e) = fs::write(&path, &stripped) {
+                    report.failed.push(StripFailure {
+                        path,
+                        error: format!("Failed to write file: {}", e),
+                    });
+                    continue;
+                }
+                audit::record_current(
+                    &app,
+                    AuditAction::Overwrite,
+                    AuditSource::Ui,
+                    &[Path::new(&path)],
+                );
+                report.stripped += 1;
+                report.bytes_removed += before.saturating_sub(stripped.len()) as u64;
+            }
+            Ok((None, _)) => report.unchanged += 1,
+            Err(error) => report.failed.push(StripFailure { path, error }),
+        }
+    }
+
+    report
+}
+
+/// Strip metadata from an encoded image, returning `None` when there was nothing to remove.
+///
+/// Metadata blocks are dropped without re-encoding. Images with a non-default EXIF orientation
+/// are decoded and re-encoded instead so they keep displaying upright.
+pub fn strip(bytes: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let format = image::guess_format(bytes).map_err(|e| format!("Unknown image format: {}", e))?;
+    if !matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP
+    ) {
+        return Err(format!(
+            "Metadata stripping is not supported for {:?}",
+            format
+        ));
+    }
+
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+    if orientation != Orientation::NoTransforms {
+        let mut image = DynamicImage::from_decoder(decoder)
+            .map_err(|e| format!("Failed to decode image: {}", e))?;
+        image.apply_orientation(orientation);
+        return reencode(&image, format).map(Some);
+    }
+
+    let stripped = match format {
+        ImageFormat::Jpeg => strip_jpeg(bytes)?,
+        ImageFormat::Png => strip_png(bytes)?,
+        _ => strip_webp(bytes)?,
+    };
+    Ok((stripped.len() != bytes.len()).then_some(stripped))
+}
+
+fn reencode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let result = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, REENCODE_QUALITY)),
+        ImageFormat::WebP => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_to(&mut Cursor::new(&mut out), ImageFormat::WebP),
+        other => image.write_to(&mut Cursor::new(&mut out), other),
+    };
+    result.map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(out)
+}
+
+/// Drop APP1 (EXIF/XMP), APP3-APP13, and comment segments; keep JFIF, ICC profiles, and Adobe color info
+fn strip_jpeg(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    const INVALID: &str = "Invalid JPEG structure";
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(&bytes[..2]);
+    let mut at = 2;
+
+    while at + 4 <= bytes.len() {
+        if bytes[at] != 0xFF {
+            return Err(INVALID.to_string());
+        }
+        let marker = bytes[at + 1];
+        // Markers may be preceded by any number of 0xFF fill bytes
+        if marker == 0xFF {
+            at += 1;
+            continue;
+        }
+        // Start of scan: the rest is entropy-coded image data
+        if marker == 0xDA {
+            out.extend_from_slice(&bytes[at..]);
+            return Ok(out);
+        }
+        let length = u16::from_be_bytes([bytes[at + 2], bytes[at + 3]]) as usize;
+        let end = at + 2 + length;
+        if length < 2 || end > bytes.len() {
+            return Err(INVALID.to_string());
+        }
+
+        let segment = &bytes[at..end];
+        let is_icc = marker == 0xE2 && segment[4..].starts_with(b"ICC_PROFILE\0");
+        let drop = match marker {
+            0xE1 | 0xE3..=0xED | 0xFE => true,
+            0xE2 => !is_icc,
+            _ => false,
+        };
+        if !drop {
+            out.extend_from_slice(segment);
+        }
+        at = end;
+    }
+
+    Err(INVALID.to_string())
+}
+
+/// Drop EXIF, text, and timestamp chunks
+fn strip_png(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    const SIGNATURE: usize = 8;
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(&bytes[..SIGNATURE]);
+    let mut at = SIGNATURE;
+
+    while at + 12 <= bytes.len() {
+        let length =
+            u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize;
+        let end = at + 12 + length;
+        if end > bytes.len() {
+            return Err("Invalid PNG structure".to_string());
+        }
+        let kind = &bytes[at + 4..at + 8];
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            out.extend_from_slice(&bytes[at..end]);
+        }
+        at = end;
+    }
+
+    Ok(out)
+}
+
+/// Drop EXIF and XMP chunks from a RIFF container, clearing their flags in the VP8X header
+fn strip_webp(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    const HEADER: usize = 12;
+    const XMP_FLAG: u8 = 0x04;
+    const EXIF_FLAG: u8 = 0x08;
+
+    if bytes.len() < HEADER || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WEBP" {
+        return Err("Invalid WebP structure".to_string());
+    }
+
+    let mut chunks = Vec::with_capacity(bytes.len());
+    let mut at = HEADER;
+    while at + 8 <= bytes.len() {
+        let kind = &bytes[at..at + 4];
+        let size = u32::from_le_bytes([bytes[at + 4], bytes[at + 5], bytes[at + 6], bytes[at + 7]])
+            as usize;
+        // Chunks are padded to an even length
+        let end = (at + 8 + size + (size & 1)).min(bytes.len());
+        if at + 8 + size > bytes.len() {
+            return Err("Invalid WebP structure".to_string());
+        }
+
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" if size > 0 => {
+                let start = chunks.len();
+                chunks.extend_from_slice(&bytes[at..end]);
+                chunks[start + 8] &= !(XMP_FLAG | EXIF_FLAG);
+            }
+            _ => chunks.extend_from_slice(&bytes[at..end]),
+        }
+        at = end;
+    }
+
+    let mut out = Vec::with_capacity(HEADER + chunks.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&((chunks.len() + 4) as u32).to_le_bytes());
+    out.extend_from_slice(b"WEBP");
+    out.extend_from_slice(&chunks);
+    Ok(out)
+}
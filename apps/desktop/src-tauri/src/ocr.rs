@@ -0,0 +1,172 @@
+use crate::error::Error;
+use crate::{blocking, events, paths, read_only, search, tools, workspace};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::AppHandle;
+
+const TESSERACT_MISSING: &str =
+    "OCR requires Tesseract. Install it from https://tesseract-ocr.github.io and try again.";
+const OCR_DIR: &str = "ocr";
+const DEFAULT_LANGUAGE: &str = "eng";
+/// Rasterization resolution for scanned PDFs; Tesseract works best around 300 DPI
+const PDF_DPI: u32 = 300;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrResult {
+    pub text: String,
+    /// Where the extracted text was stored
+    pub text_path: String,
+    pub pages: usize,
+}
+
+/// Extract text from an image or scanned PDF, store it alongside the asset, and index it for search
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn ocr_asset(
+    app: AppHandle,
+    path: String,
+    language: Option<String>,
+) -> Result<OcrResult, Error> {
+    blocking::run_long("recognize text", move || {
+        recognize_asset(app, path, language)
+    })
+    .await
+}
+
+/// The recognition behind [`ocr_asset`], run on the calling thread
+pub fn recognize_asset(
+    app: AppHandle,
+    path: String,
+    language: Option<String>,
+) -> Result<OcrResult, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    let source = Path::new(&path);
+    if !source.is_file() {
+        return Err(format!("File does not exist: {}", path).into());
+    }
+
+    let language = language_code(language)?;
+    let tesseract = tesseract(&app)?;
+
+    let is_pdf = source
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+
+    let (text, pages) = if is_pdf {
+        recognize_pdf(&app, &tesseract, source, &language)?
+    } else {
+        (recognize(&tesseract, source, &language)?, 1)
+    };
+
+    let root = workspace::current_root(&app).filter(|root| source.starts_with(root));
+    let text_path = text_path(root.as_deref(), source);
+    read_only::ensure_writable(&text_path)?;
+    if let Some(parent) = text_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    fs::write(&text_path, &text).map_err(|e| format!("Failed to write OCR text: {}", e))?;
+
+    if let Some(root) = root {
+        search::index_text(&root, source, "ocr", &text)?;
+        events::index_updated(&app, source, "ocr");
+    }
+
+    Ok(OcrResult {
+        text,
+        text_path: text_path.to_string_lossy().to_string(),
+        pages,
+    })
+}
+
+/// The Tesseract language code to use, `eng` when none is given
+pub fn language_code(language: Option<String>) -> Result<String, String> {
+    let language = language.unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+    // Tesseract takes languages as `eng` or `eng+deu`
+    if language.is_empty()
+        || !language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+')
+    {
+        return Err(format!("Invalid OCR language: {}", language));
+    }
+    Ok(language)
+}
+
+pub fn tesseract(app: &AppHandle) -> Result<PathBuf, String> {
+    tools::find_binary(app, "tesseract", "--version").ok_or_else(|| TESSERACT_MISSING.to_string())
+}
+
+/// `.inkfinite/ocr/<relative path>.txt` inside a workspace, `<file>.ocr.txt` next to it otherwise
+pub fn text_path(root: Option<&Path>, source: &Path) -> PathBuf {
+    match root {
+        Some(root) => workspace::internal_dir(root)
+            .join(OCR_DIR)
+            .join(format!("{}.txt", workspace::relative_path(root, source))),
+        None => {
+            let mut name = source.file_name().unwrap_or_default().to_os_string();
+            name.push(".ocr.txt");
+            source.with_file_name(name)
+        }
+    }
+}
+
+pub fn recognize(tesseract: &Path, image: &Path, language: &str) -> Result<String, String> {
+    let output = Command::new(tesseract)
+        .arg(image)
+        .arg("stdout")
+        .args(["-l", language])
+        .output()
+        .map_err(|e| format!("Failed to run tesseract: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Rasterize every page with poppler's `pdftoppm`, then recognize each one
+fn recognize_pdf(
+    app: &AppHandle,
+    tesseract: &Path,
+    source: &Path,
+    language: &str,
+) -> Result<(String, usize), String> {
+    let pdftoppm = tools::find_binary(app, "pdftoppm", "-v")
+        .ok_or_else(|| "Scanned PDFs require poppler (pdftoppm) to be installed".to_string())?;
+
+    let scratch = tools::scratch_dir("ocr")?;
+    let result = (|| {
+        let status = Command::new(&pdftoppm)
+            .args(["-png", "-r"])
+            .arg(PDF_DPI.to_string())
+            .arg(source)
+            .arg(scratch.join("page"))
+            .status()
+            .map_err(|e| format!("Failed to run pdftoppm: {}", e))?;
+        if !status.success() {
+            return Err("Failed to rasterize PDF".to_string());
+        }
+
+        let mut pages: Vec<PathBuf> = fs::read_dir(&scratch)
+            .map_err(|e| format!("Failed to read directory: {}", e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        // pdftoppm zero-pads page numbers, so lexical order is page order
+        pages.sort();
+
+        let texts = pages
+            .iter()
+            .map(|page| recognize(tesseract, page, language))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((texts.join("\n\n"), pages.len()))
+    })();
+
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
@@ -0,0 +1,352 @@
+use crate::assets::{RasterFormat, ASSETS_DIR};
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::jobs::JobContext;
+use crate::{archive, paths, power, read_only, workspace};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const SETTINGS_CONFIG: &str = "image-settings";
+
+/// Per-workspace image import settings, stored in `.inkfinite/image-settings.json`
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImageSettings {
+    /// Run imported raster images through the pipeline
+    pub optimize: bool,
+    /// Longest edge in pixels; larger images are scaled down
+    pub max_dimension: Option<u32>,
+    /// Target encoding; `None` keeps the original format
+    pub format: Option<RasterFormat>,
+    /// Lossy quality (1-100) for JPEG and AVIF output
+    pub quality: u8,
+    /// Always re-encode so EXIF and other metadata are dropped
+    pub strip_metadata: bool,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        ImageSettings {
+            optimize: false,
+            max_dimension: Some(2560),
+            format: None,
+            quality: 80,
+            strip_metadata: false,
+        }
+    }
+}
+
+/// Result of running one image through the pipeline
+pub struct Optimized {
+    pub bytes: Vec<u8>,
+    pub extension: &'static str,
+}
+
+#[derive(serde::Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizeReport {
+    pub processed: usize,
+    pub skipped: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// Assets whose extension changed, as `[old, new]` workspace-relative paths
+    pub renamed: Vec<(String, String)>,
+    pub failed: Vec<String>,
+    pub dry_run: bool,
+}
+
+pub fn load_settings(root: &Path) -> ImageSettings {
+    workspace::read_config(root, SETTINGS_CONFIG).unwrap_or_default()
+}
+
+/// Read image import settings for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_image_settings(app: AppHandle, workspace: String) -> ImageSettings {
+    if paths::check_workspace(&app, &workspace, paths::Scope::Read).is_err() {
+        return ImageSettings::default();
+    }
+    load_settings(Path::new(&workspace))
+}
+
+/// Update image import settings for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_image_settings(
+    app: AppHandle,
+    workspace: String,
+    settings: ImageSettings,
+) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    if !(1..=100).contains(&settings.quality) {
+        return Err(format!("Quality must be between 1 and 100: {}", settings.quality).into());
+    }
+    Ok(workspace::write_config(
+        Path::new(&workspace),
+        SETTINGS_CONFIG,
+        &settings,
+    )?)
+}
+
+/// Whether a file extension is a raster format the pipeline can decode
+pub fn is_raster(path: &Path) -> bool {
+    matches!(
+        path.extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .as_deref(),
+        Some("png" | "jpg" | "jpeg" | "webp" | "gif" | "bmp")
+    )
+}
+
+/// Resize and re-encode an image according to `settings`.
+///
+/// Returns `None` when the pipeline would not improve the file, so callers keep the original.
+pub fn optimize(bytes: &[u8], settings: &ImageSettings) -> Result<Option<Optimized>, String> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    let source_format = reader.format();
+    // Animated GIFs would lose their frames when re-encoded
+    if source_format == Some(ImageFormat::Gif) {
+        return Ok(None);
+    }
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    // Re-encoding drops EXIF, so bake the orientation into the pixels first
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    image.apply_orientation(orientation);
+
+    let resized = match settings.max_dimension {
+        Some(max) if image.width() > max || image.height() > max => {
+            image = image.resize(max, max, image::imageops::FilterType::Lanczos3);
+            true
+        }
+        _ => false,
+    };
+
+    let target = settings.format.map(Target::Raster).or(match source_format {
+        Some(ImageFormat::Jpeg) => Some(Target::Jpeg),
+        Some(ImageFormat::Png) => Some(Target::Raster(RasterFormat::Png)),
+        Some(ImageFormat::WebP) => Some(Target::Raster(RasterFormat::Webp)),
+        _ => None,
+    });
+    let Some(target) = target else {
+        return Ok(None);
+    };
+
+    let converted = settings.format.is_some() && Some(target.image_format()) != source_format;
+    let required = resized || converted || settings.strip_metadata;
+    // Re-encoding a lossy image only to save bytes would lose quality again on every run
+    if !required && source_format == Some(ImageFormat::Jpeg) && target.is_lossy() {
+        return Ok(None);
+    }
+    let encoded = encode(&image, target, settings.quality)?;
+
+    if required || encoded.len() < bytes.len() {
+        Ok(Some(Optimized {
+            bytes: encoded,
+            extension: target.extension(),
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Raster(RasterFormat),
+    Jpeg,
+}
+
+impl Target {
+    fn extension(self) -> &'static str {
+        match self {
+            Target::Raster(format) => format.extension(),
+            Target::Jpeg => "jpg",
+        }
+    }
+
+    fn is_lossy(self) -> bool {
+        matches!(self, Target::Jpeg | Target::Raster(RasterFormat::Avif))
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Target::Raster(format) => format.image_format(),
+            Target::Jpeg => ImageFormat::Jpeg,
+        }
+    }
+}
+
+fn encode(image: &DynamicImage, target: Target, quality: u8) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let result = match target {
+        Target::Raster(RasterFormat::Png) => image.write_with_encoder(
+            PngEncoder::new_with_quality(&mut out, CompressionType::Best, FilterType::Adaptive),
+        ),
+        // The image crate only ships a lossless WebP encoder
+        Target::Raster(RasterFormat::Webp) => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut out)),
+        Target::Raster(RasterFormat::Avif) => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(AvifEncoder::new_with_speed_quality(&mut out, 6, quality)),
+        Target::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, quality)),
+    };
+    result.map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(out)
+}
+
+/// Run every raster image in the workspace assets folder through the pipeline
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn optimize_existing_assets(
+    app: AppHandle,
+    workspace: String,
+    dry_run: Option<bool>,
+) -> Result<OptimizeReport, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(optimize_assets(
+        &app,
+        Path::new(&workspace),
+        dry_run.unwrap_or(false),
+        None,
+    )?)
+}
+
+/// [`optimize_existing_assets`], reporting progress to `job` and stopping early when it is
+/// cancelled. Images already rewritten keep their updated references.
+pub fn optimize_assets(
+    app: &AppHandle,
+    root: &Path,
+    dry_run: bool,
+    job: Option<&JobContext>,
+) -> Result<OptimizeReport, String> {
+    if !dry_run {
+        read_only::ensure_writable(root)?;
+    }
+    let _awake = power::prevent_sleep("Optimizing images");
+    // The `optimize` flag only gates imports; the bulk command is an explicit request
+    let settings = load_settings(root);
+
+    let mut report = OptimizeReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let assets_dir = root.join(ASSETS_DIR);
+    if !assets_dir.is_dir() {
+        return Ok(report);
+    }
+
+    let source = if job.is_some() {
+        AuditSource::Batch
+    } else {
+        AuditSource::Ui
+    };
+    let mut images = Vec::new();
+    for entry in
+        fs::read_dir(&assets_dir).map_err(|e| format!("Failed to read directory: {}", e))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        if path.is_file() && is_raster(&path) {
+            images.push(path);
+        }
+    }
+
+    let total = images.len();
+    for (index, path) in images.into_iter().enumerate() {
+        if let Some(job) = job {
+            if job.is_cancelled() {
+                break;
+            }
+            job.progress(
+                index as f32 / total as f32,
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string()),
+            );
+        }
+
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                report.failed.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+
+        let optimized = match optimize(&bytes, &settings) {
+            Ok(Some(optimized)) => optimized,
+            Ok(None) => {
+                report.skipped += 1;
+                continue;
+            }
+            Err(error) => {
+                report.failed.push(format!("{}: {}", path.display(), error));
+                continue;
+            }
+        };
+
+        report.processed += 1;
+        report.bytes_before += bytes.len() as u64;
+        report.bytes_after += optimized.bytes.len() as u64;
+
+        // A changed extension takes a free name so an existing asset is never overwritten
+        let mut target = path.with_extension(optimized.extension);
+        if target != path {
+            target = crate::document::unique_path(
+                &assets_dir,
+                &path.file_stem().unwrap_or_default().to_string_lossy(),
+                &format!(".{}", optimized.extension),
+            );
+        }
+        let renamed = (
+            workspace::relative_path(root, &path),
+            workspace::relative_path(root, &target),
+        );
+        if report.dry_run {
+            if target != path {
+                report.renamed.push(renamed);
+            }
+            continue;
+        }
+
+        if let Err(e) = fs::write(&target, &optimized.bytes) {
+            report.failed.push(format!("{}: {}", target.display(), e));
+            continue;
+        }
+        if target != path {
+            report.renamed.push(renamed);
+            let _ = fs::remove_file(&path);
+            audit::record(root, AuditAction::Rename, source, &[&path, &target]);
+        }
+        audit::record(root, AuditAction::Overwrite, source, &[&target]);
+    }
+
+    if !report.dry_run && !report.renamed.is_empty() {
+        for document in workspace::rewrite_references(root, &report.renamed)? {
+            events::file_changed(app, &document, ChangeKind::Modified);
+        }
+        let moves: HashMap<PathBuf, PathBuf> = report
+            .renamed
+            .iter()
+            .map(|(from, to)| (root.join(from), root.join(to)))
+            .collect();
+        archive::redirect_all(app, root, &moves)?;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,377 @@
+use crate::catalog::{self, Database};
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::{paths, vault, workspace};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// A heading in a document, in reading order
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Heading {
+    /// 1 for `#`, up to 6
+    pub level: u8,
+    /// The heading without Markdown formatting
+    pub text: String,
+    /// Unique within the document, from `{#id}` when the heading sets one
+    pub anchor: String,
+    /// The markdown block holding the heading
+    pub block_id: String,
+    pub page_id: String,
+    /// Line within the block, from 0
+    pub line: usize,
+    /// Where the heading line starts in the block's Markdown, in UTF-16 code units
+    pub offset: usize,
+    /// Position of the block on the canvas
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineNode {
+    #[serde(flatten)]
+    pub heading: Heading,
+    pub children: Vec<OutlineNode>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outline {
+    /// Headings nested under the nearest heading above them of a lower level
+    pub headings: Vec<OutlineNode>,
+    pub count: usize,
+    /// A nested Markdown list linking to each heading, ready to insert as a table of contents
+    pub toc: String,
+    /// Whether it came from the metadata cache rather than a fresh read
+    pub cached: bool,
+}
+
+/// `{#id}` at the end of a heading, as Pandoc writes custom anchors
+fn custom_anchor(text: &str) -> (&str, Option<&str>) {
+    let trimmed = text.trim_end();
+    let Some(start) = trimmed.strip_suffix('}').and_then(|rest| rest.rfind('{')) else {
+        return (text, None);
+    };
+    let id = trimmed[start + 1..trimmed.len() - 1]
+        .split_whitespace()
+        .find_map(|attribute| attribute.strip_prefix('#'))
+        .filter(|id| !id.is_empty());
+    match id {
+        Some(id) => (trimmed[..start].trim_end(), Some(id)),
+        None => (text, None),
+    }
+}
+
+/// Heading text without emphasis, code spans, or link targets
+fn plain(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some(link) = rest.strip_prefix("[[") {
+            if let Some(end) = link.find("]]") {
+                let inner = &link[..end];
+                out.push_str(inner.split_once('|').map_or(inner, |(_, label)| label));
+                rest = &link[end + 2..];
+                continue;
+            }
+        }
+        if let Some(label) = rest.strip_prefix('[').or_else(|| rest.strip_prefix("![")) {
+            let target = label
+                .find("](")
+                .and_then(|end| Some((end, label[end..].find(')')? + end)));
+            if let Some((end, close)) = target {
+                out.push_str(&plain(&label[..end]));
+                rest = &label[close + 1..];
+                continue;
+            }
+        }
+        rest = &rest[c.len_utf8()..];
+        // Emphasis markers go, but not underscores or asterisks inside a word
+        let inside_word =
+            out.ends_with(char::is_alphanumeric) && rest.starts_with(char::is_alphanumeric);
+        if !matches!(c, '*' | '_' | '`') || (c != '`' && inside_word) {
+            out.push(c);
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A GitHub-style anchor: lowercase words joined by hyphens
+fn slug(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `# Heading` with up to three spaces before it, returning the level and the text
+fn atx(line: &str) -> Option<(u8, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = &line[indent..];
+    let level = rest.len() - rest.trim_start_matches('#').len();
+    let text = &rest[level..];
+    if indent > 3
+        || !(1..=6).contains(&level)
+        || !(text.is_empty() || text.starts_with([' ', '\t']))
+    {
+        return None;
+    }
+    // A closing run of `#` after a space is not part of the text
+    let text = text.trim();
+    let closed = text.trim_end_matches('#');
+    let text = match closed.is_empty() || closed.ends_with([' ', '\t']) {
+        true => closed.trim_end(),
+        false => text,
+    };
+    Some((level as u8, text))
+}
+
+/// `===` or `---` under a paragraph line, making it a level 1 or 2 heading
+fn setext(line: &str) -> Option<u8> {
+    let line = line.trim();
+    let level = match line.chars().next()? {
+        '=' => 1,
+        '-' => 2,
+        _ => return None,
+    };
+    let marker = if level == 1 { '=' } else { '-' };
+    line.chars().all(|c| c == marker).then_some(level)
+}
+
+/// Whether a line can be the text of a setext heading, rather than a list item, quote or fence
+fn paragraph_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let list = trimmed
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .strip_prefix(['.', ')'])
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        && trimmed.starts_with(|c: char| c.is_ascii_digit());
+    !trimmed.is_empty()
+        && line.len() - trimmed.len() <= 3
+        && !trimmed.starts_with(['>', '#', '|', '<'])
+        && !["- ", "* ", "+ ", "```", "~~~"]
+            .iter()
+            .any(|prefix| trimmed.starts_with(prefix))
+        && !list
+}
+
+/// Headings of one markdown block, skipping fenced code: level, text, line and UTF-16 offset
+fn block_headings(markdown: &str) -> Vec<(u8, String, usize, usize)> {
+    let mut headings = Vec::new();
+    let mut fence: Option<String> = None;
+    let mut offset = 0;
+    let mut previous: Option<(&str, usize)> = None;
+    for (number, line) in markdown.lines().enumerate() {
+        let start = offset;
+        offset += line.encode_utf16().count() + 1;
+        let trimmed = line.trim_start();
+        if let Some(open) = &fence {
+            if trimmed.starts_with(open.as_str())
+                && trimmed.trim_start_matches(open.as_str()).trim().is_empty()
+            {
+                fence = None;
+            }
+            continue;
+        }
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        if let Some(marker) = marker {
+            // A fence closes with at least as many of the same character as opened it
+            let run = &marker[..1];
+            let length = trimmed.len() - trimmed.trim_start_matches(run).len();
+            fence = Some(run.repeat(length));
+            previous = None;
+            continue;
+        }
+        if let Some((level, text)) = atx(line) {
+            headings.push((level, text.to_string(), number, start));
+            previous = None;
+            continue;
+        }
+        if let (Some(level), Some((text, line_start))) = (setext(line), previous) {
+            headings.push((level, text.trim().to_string(), number - 1, line_start));
+            previous = None;
+            continue;
+        }
+        // Only the last line of a paragraph becomes the heading; a longer paragraph above an
+        // underline is rare in notes
+        previous = paragraph_line(line).then_some((line, start));
+    }
+    headings
+}
+
+/// Every heading in the document's markdown blocks, top to bottom on each page
+pub fn headings(board: &BoardFile) -> Vec<Heading> {
+    let mut anchors: HashMap<String, usize> = HashMap::new();
+    let mut headings = Vec::new();
+    for page in board.pages() {
+        let mut shapes: Vec<_> = board
+            .page_shapes(&page)
+            .into_iter()
+            .filter(|shape| shape.kind == "markdown")
+            .collect();
+        shapes.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+        for shape in shapes {
+            let Some(markdown) = shape.text() else {
+                continue;
+            };
+            for (level, text, line, offset) in block_headings(markdown) {
+                let (text, id) = custom_anchor(&text);
+                let text = plain(text);
+                let base = id.map_or_else(|| slug(&text), str::to_string);
+                // Repeated headings get `-1`, `-2`, ... as GitHub numbers them
+                let uses = anchors.entry(base.clone()).or_default();
+                let anchor = match *uses {
+                    0 => base.clone(),
+                    n => format!("{}-{}", base, n),
+                };
+                *uses += 1;
+                headings.push(Heading {
+                    level,
+                    text,
+                    anchor,
+                    block_id: shape.id.clone(),
+                    page_id: page.id.clone(),
+                    line,
+                    offset,
+                    x: shape.x,
+                    y: shape.y,
+                });
+            }
+        }
+    }
+    headings
+}
+
+/// Nest headings under the nearest one above them with a lower level
+fn tree(headings: Vec<Heading>) -> Vec<OutlineNode> {
+    let mut roots: Vec<OutlineNode> = Vec::new();
+    let mut stack: Vec<OutlineNode> = Vec::new();
+    let close = |stack: &mut Vec<OutlineNode>, roots: &mut Vec<OutlineNode>| {
+        if let Some(node) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => roots.push(node),
+            }
+        }
+    };
+    for heading in headings {
+        while stack
+            .last()
+            .is_some_and(|open| open.heading.level >= heading.level)
+        {
+            close(&mut stack, &mut roots);
+        }
+        stack.push(OutlineNode {
+            heading,
+            children: Vec::new(),
+        });
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+fn toc(nodes: &[OutlineNode], depth: usize, out: &mut String) {
+    for node in nodes {
+        let text = node.heading.text.replace('[', "\\[").replace(']', "\\]");
+        out.push_str(&format!(
+            "{}- [{}](#{})\n",
+            "  ".repeat(depth),
+            text,
+            node.heading.anchor
+        ));
+        toc(&node.children, depth + 1, out);
+    }
+}
+
+fn cached(db: &Database, path: &str, stamp: (u64, i64)) -> Result<Option<Vec<Heading>>, String> {
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let headings: Option<String> = db
+        .query_row(
+            "SELECT headings FROM outlines WHERE path = ?1 AND size = ?2 AND modified = ?3",
+            params![path, stamp.0, stamp.1],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    Ok(headings.and_then(|headings| serde_json::from_str(&headings).ok()))
+}
+
+/// Keep the outline for the document's current version; documents the cache does not list yet
+/// are skipped and cached on a later call
+fn store(db: &Database, path: &str, stamp: (u64, i64), headings: &[Heading]) -> Result<(), String> {
+    let headings = serde_json::to_string(headings)
+        .map_err(|e| format!("Failed to serialize outline: {}", e))?;
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    db.execute(
+        "INSERT OR REPLACE INTO outlines (path, size, modified, headings)
+         SELECT ?1, ?2, ?3, ?4 WHERE EXISTS (SELECT 1 FROM documents WHERE path = ?1)",
+        params![path, stamp.0, stamp.1, headings],
+    )
+    .map_err(|e| format!("Failed to write metadata cache: {}", e))?;
+    Ok(())
+}
+
+/// The document's headings from the metadata cache, or read from the file and cached.
+///
+/// Vault documents and documents outside an open workspace are read every time, as the cache
+/// does not hold them.
+fn outline(app: &AppHandle, path: &Path) -> Result<(Vec<Heading>, bool), String> {
+    let stamp = catalog::stamp(path)
+        .ok_or_else(|| format!("Document does not exist: {}", path.display()))?;
+    let open = catalog::containing(app, path).filter(|_| vault::vault_of(path).is_none());
+    if let Some((root, db)) = &open {
+        let relative = workspace::relative_path(root, path);
+        match cached(db, &relative, stamp) {
+            Ok(Some(headings)) => return Ok((headings, true)),
+            Ok(None) => {}
+            Err(error) => tracing::warn!(%error, "Failed to read cached outline"),
+        }
+    }
+    let headings = headings(&document::read_board(path)?);
+    if let Some((root, db)) = &open {
+        let relative = workspace::relative_path(root, path);
+        if let Err(error) = store(db, &relative, stamp, &headings) {
+            tracing::warn!(%error, "Failed to cache outline");
+        }
+    }
+    Ok((headings, false))
+}
+
+/// The document's heading hierarchy with the block and position of each heading, for the
+/// outline sidebar and table of contents insertion
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn extract_outline(app: AppHandle, path: String) -> Result<Outline, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let (headings, cached) = outline(&app, Path::new(&path))?;
+        let count = headings.len();
+        let headings = tree(headings);
+        let mut markdown = String::new();
+        toc(&headings, 0, &mut markdown);
+        Ok(Outline {
+            headings,
+            count,
+            toc: markdown,
+            cached,
+        })
+    })
+    .await
+    .map_err(|e| format!("Outline task failed: {}", e))?
+}
@@ -0,0 +1,670 @@
+use crate::error::Error;
+use crate::{document, http, local_api, paths, read_only, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+/// Store key of the packages installed for the app
+const INVENTORY_KEY: &str = "packages";
+/// Store key of the Ed25519 public keys, base64, that package signatures are checked against
+const KEYS_KEY: &str = "packageKeys";
+/// Packages installed for one workspace, `.inkfinite/packages.json`
+const CONFIG: &str = "packages";
+const PACKAGE_FORMAT: u32 = 1;
+const MAX_PACKAGE_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_INDEX_BYTES: u64 = 4 * 1024 * 1024;
+const THEMES_DIR: &str = "themes";
+const PLUGIN_MANIFEST: &str = "plugin.json";
+const THEME_MANIFEST: &str = "theme.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageKind {
+    Plugin,
+    Theme,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageScope {
+    /// Installed in the app data folder, available in every workspace
+    App,
+    /// Installed in the workspace's `.inkfinite` folder, travelling with it
+    Workspace,
+}
+
+/// Where a package is downloaded from
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PackageSource {
+    /// A package file; its SHA-256 hash, hex, or an Ed25519 signature of it, base64, from a
+    /// trusted key is required
+    Url {
+        url: String,
+        sha256: Option<String>,
+        signature: Option<String>,
+    },
+    /// A package listed in a registry index, at `version` or the newest one
+    Registry {
+        index: String,
+        id: String,
+        version: Option<String>,
+    },
+}
+
+/// A package file: JSON naming the package and holding each of its files, base64-encoded
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageFile {
+    format: u32,
+    kind: PackageKind,
+    id: String,
+    version: String,
+    /// Path inside the package, such as `plugin.wasm`, to the file's content
+    files: BTreeMap<String, String>,
+}
+
+/// An entry of a registry index, `{ "packages": [...] }`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexEntry {
+    pub id: String,
+    pub kind: PackageKind,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Package file, relative to the index unless absolute
+    pub url: String,
+    pub sha256: String,
+    pub signature: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Index {
+    packages: Vec<IndexEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledPackage {
+    pub id: String,
+    pub kind: PackageKind,
+    pub name: String,
+    pub version: String,
+    pub scope: PackageScope,
+    pub source: PackageSource,
+    pub sha256: String,
+    /// Whether a trusted key signed the package file
+    pub signed: bool,
+    pub installed_at: i64,
+    /// Folder the package's files are in
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Inventory {
+    packages: Vec<InstalledPackage>,
+}
+
+/// An inventory and the folders its packages are installed into
+struct Location {
+    scope: PackageScope,
+    /// Workspace of a workspace inventory
+    root: Option<PathBuf>,
+}
+
+/// Every folder packages are installed into, for the app and for `root`
+pub fn install_dirs(app: &AppHandle, root: Option<&Path>) -> Vec<PathBuf> {
+    let mut locations = vec![Location {
+        scope: PackageScope::App,
+        root: None,
+    }];
+    if let Some(root) = root {
+        locations.push(Location {
+            scope: PackageScope::Workspace,
+            root: Some(root.to_path_buf()),
+        });
+    }
+    locations
+        .iter()
+        .flat_map(|location| {
+            [PackageKind::Plugin, PackageKind::Theme]
+                .into_iter()
+                .filter_map(|kind| location.dir(app, kind).ok())
+        })
+        .collect()
+}
+
+impl Location {
+    fn new(
+        app: &AppHandle,
+        scope: PackageScope,
+        workspace: Option<&str>,
+    ) -> Result<Location, Error> {
+        let root = match scope {
+            PackageScope::App => None,
+            PackageScope::Workspace => {
+                let workspace =
+                    workspace.ok_or("Invalid package scope: a workspace is required")?;
+                Some(paths::check_workspace(app, workspace, paths::Scope::Read)?)
+            }
+        };
+        Ok(Location { scope, root })
+    }
+
+    fn dir(&self, app: &AppHandle, kind: PackageKind) -> Result<PathBuf, String> {
+        if let Some(root) = &self.root {
+            read_only::ensure_writable(root)?;
+        }
+        match (kind, &self.root) {
+            #[cfg(desktop)]
+            (PackageKind::Plugin, None) => crate::plugins::plugins_dir(app),
+            #[cfg(desktop)]
+            (PackageKind::Plugin, Some(root)) => Ok(crate::plugins::workspace_plugins_dir(root)),
+            #[cfg(mobile)]
+            (PackageKind::Plugin, _) => {
+                Err("Plugin packages are not supported on this platform".to_string())
+            }
+            (PackageKind::Theme, None) => Ok(app
+                .path()
+                .app_data_dir()
+                .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+                .join(THEMES_DIR)),
+            (PackageKind::Theme, Some(root)) => Ok(workspace::internal_dir(root).join(THEMES_DIR)),
+        }
+    }
+
+    fn load(&self, app: &AppHandle) -> Result<Vec<InstalledPackage>, String> {
+        match &self.root {
+            Some(root) => Ok(workspace::read_config::<Inventory>(root, CONFIG)?.packages),
+            None => Ok(app
+                .store(workspace::STORE_NAME)
+                .map_err(|e| format!("Failed to open settings: {}", e))?
+                .get(INVENTORY_KEY)
+                .and_then(|value| serde_json::from_value(value).ok())
+                .unwrap_or_default()),
+        }
+    }
+
+    fn save(&self, app: &AppHandle, packages: Vec<InstalledPackage>) -> Result<(), String> {
+        match &self.root {
+            Some(root) => workspace::write_config(root, CONFIG, &Inventory { packages }),
+            None => {
+                let store = app
+                    .store(workspace::STORE_NAME)
+                    .map_err(|e| format!("Failed to open settings: {}", e))?;
+                store.set(INVENTORY_KEY, json!(packages));
+                store
+                    .save()
+                    .map_err(|e| format!("Failed to save settings: {}", e))
+            }
+        }
+    }
+}
+
+/// A package moved into place, with the version it replaced kept until the install is committed
+struct Swap {
+    target: PathBuf,
+    previous: Option<PathBuf>,
+}
+
+impl Swap {
+    fn commit(self) {
+        if let Some(previous) = self.previous {
+            let _ = fs::remove_dir_all(previous);
+        }
+    }
+
+    fn rollback(self) {
+        let _ = fs::remove_dir_all(&self.target);
+        if let Some(previous) = self.previous {
+            if let Err(error) = fs::rename(&previous, &self.target) {
+                tracing::warn!(%error, "Failed to restore the previous package version");
+            }
+        }
+    }
+}
+
+fn trusted_keys(app: &AppHandle) -> Vec<String> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(KEYS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn fetch(url: &str, limit: u64) -> Result<Vec<u8>, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid package URL: {}", e))?;
+    if !matches!(parsed.scheme(), "https" | "http") {
+        return Err(format!("Invalid package URL: {}", url));
+    }
+    http::agent()
+        .get(url)
+        .call()
+        .map_err(|e| format!("Request failed: {}", e))?
+        .body_mut()
+        .with_config()
+        .limit(limit)
+        .read_to_vec()
+        .map_err(|e| format!("Download failed: {}", e))
+}
+
+fn fetch_index(url: &str) -> Result<Vec<IndexEntry>, String> {
+    let bytes = fetch(url, MAX_INDEX_BYTES)?;
+    let index: Index =
+        serde_json::from_slice(&bytes).map_err(|e| format!("Invalid package index: {}", e))?;
+    Ok(index.packages)
+}
+
+/// Numeric parts of a version, so `1.10.0` sorts after `1.9.2`
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-', '+'])
+        .map(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().unwrap_or(0)
+        })
+        .collect()
+}
+
+/// The package file URL and how to check it
+struct Resolved {
+    url: String,
+    sha256: Option<String>,
+    signature: Option<String>,
+    /// Version the source names, when known before downloading
+    version: Option<String>,
+}
+
+fn resolve(source: &PackageSource) -> Result<Resolved, String> {
+    match source {
+        PackageSource::Url {
+            url,
+            sha256,
+            signature,
+        } => Ok(Resolved {
+            url: url.clone(),
+            sha256: sha256.clone(),
+            signature: signature.clone(),
+            version: None,
+        }),
+        PackageSource::Registry { index, id, version } => {
+            let entry = fetch_index(index)?
+                .into_iter()
+                .filter(|entry| &entry.id == id)
+                .filter(|entry| {
+                    version
+                        .as_ref()
+                        .is_none_or(|version| &entry.version == version)
+                })
+                .max_by_key(|entry| version_key(&entry.version))
+                .ok_or_else(|| format!("Package not found in index: {}", id))?;
+            let url = url::Url::parse(index)
+                .and_then(|base| base.join(&entry.url))
+                .map_err(|e| format!("Invalid package URL: {}", e))?;
+            Ok(Resolved {
+                url: url.to_string(),
+                sha256: Some(entry.sha256),
+                signature: entry.signature,
+                version: Some(entry.version),
+            })
+        }
+    }
+}
+
+/// Check the download against the expected hash and signature; returns its hash and whether
+/// it was signed
+fn verify(app: &AppHandle, bytes: &[u8], resolved: &Resolved) -> Result<(String, bool), String> {
+    let digest = format!("{:x}", Sha256::digest(bytes));
+    if resolved.sha256.is_none() && resolved.signature.is_none() {
+        return Err("Invalid package: a sha256 hash or a signature is required".to_string());
+    }
+    if let Some(expected) = &resolved.sha256 {
+        if !expected.trim().eq_ignore_ascii_case(&digest) {
+            return Err("Invalid package: its hash does not match".to_string());
+        }
+    }
+    let Some(signature) = &resolved.signature else {
+        return Ok((digest, false));
+    };
+    let signature = BASE64
+        .decode(signature.trim())
+        .map_err(|e| format!("Invalid package signature: {}", e))?;
+    let signed = trusted_keys(app).iter().any(|key| {
+        BASE64.decode(key).is_ok_and(|key| {
+            UnparsedPublicKey::new(&ED25519, key)
+                .verify(bytes, &signature)
+                .is_ok()
+        })
+    });
+    if !signed {
+        return Err("Invalid package: it is not signed by a trusted key".to_string());
+    }
+    Ok((digest, true))
+}
+
+/// Paths inside a package with their content
+type Files = Vec<(PathBuf, Vec<u8>)>;
+
+/// Parse a package file and check its id, paths and manifest; returns it with its name and the
+/// decoded files
+fn unpack(bytes: &[u8]) -> Result<(PackageFile, String, Files), String> {
+    let package: PackageFile =
+        serde_json::from_slice(bytes).map_err(|e| format!("Invalid package: {}", e))?;
+    if package.format != PACKAGE_FORMAT {
+        return Err(format!(
+            "Invalid package: format {} is not supported",
+            package.format
+        ));
+    }
+    if package.id.is_empty() || local_api::file_stem(&package.id) != package.id {
+        return Err(format!("Invalid package id: {}", package.id));
+    }
+    let mut files = Vec::new();
+    for (name, content) in &package.files {
+        let path = Path::new(name);
+        if !path
+            .components()
+            .all(|part| matches!(part, Component::Normal(_)))
+        {
+            return Err(format!("Invalid package file path: {}", name));
+        }
+        let content = BASE64
+            .decode(content)
+            .map_err(|e| format!("Invalid package file {}: {}", name, e))?;
+        files.push((path.to_path_buf(), content));
+    }
+
+    let manifest_name = match package.kind {
+        PackageKind::Plugin => PLUGIN_MANIFEST,
+        PackageKind::Theme => THEME_MANIFEST,
+    };
+    let manifest = files
+        .iter()
+        .find(|(path, _)| path == Path::new(manifest_name))
+        .ok_or_else(|| format!("Invalid package: {} is missing", manifest_name))?;
+    let manifest: Value = serde_json::from_slice(&manifest.1)
+        .map_err(|e| format!("Invalid {}: {}", manifest_name, e))?;
+    if package.kind == PackageKind::Plugin && manifest["id"] != json!(package.id) {
+        return Err(format!(
+            "Invalid package: the id in {} does not match",
+            manifest_name
+        ));
+    }
+    let name = manifest["name"].as_str().unwrap_or(&package.id).to_string();
+    Ok((package, name, files))
+}
+
+/// Write the files next to `dir/<id>`, then swap them in, keeping what was there
+fn swap_in(dir: &Path, id: &str, files: &Files) -> Result<Swap, String> {
+    let staging = dir.join(format!(".{}.staging", id));
+    let previous = dir.join(format!(".{}.previous", id));
+    let target = dir.join(id);
+    for stale in [&staging, &previous] {
+        if stale.exists() {
+            fs::remove_dir_all(stale).map_err(|e| format!("Failed to clean up: {}", e))?;
+        }
+    }
+    let written = files.iter().try_for_each(|(path, content)| {
+        let output = staging.join(path);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        fs::write(&output, content).map_err(|e| format!("Failed to write file: {}", e))
+    });
+    if let Err(error) = written {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(error);
+    }
+
+    let previous = if target.exists() {
+        fs::rename(&target, &previous)
+            .map_err(|e| format!("Failed to replace the installed version: {}", e))?;
+        Some(previous)
+    } else {
+        None
+    };
+    if let Err(error) = fs::rename(&staging, &target) {
+        let _ = fs::remove_dir_all(&staging);
+        if let Some(previous) = &previous {
+            let _ = fs::rename(previous, &target);
+        }
+        return Err(format!("Failed to install package: {}", error));
+    }
+    Ok(Swap { target, previous })
+}
+
+/// Restart plugins after a plugin package changed; errors when `id` fails to load
+fn reload_plugins(app: &AppHandle, kind: PackageKind, id: &str) -> Result<(), String> {
+    #[cfg(desktop)]
+    if kind == PackageKind::Plugin {
+        if let Some(error) = crate::plugins::reload(app, id)? {
+            return Err(error);
+        }
+    }
+    #[cfg(mobile)]
+    let _ = (app, kind, id);
+    Ok(())
+}
+
+/// Download, check and install a package, replacing any installed version; the previous
+/// version and inventory are restored when any step fails
+fn install(
+    app: &AppHandle,
+    location: &Location,
+    source: PackageSource,
+) -> Result<InstalledPackage, String> {
+    let resolved = resolve(&source)?;
+    let bytes = fetch(&resolved.url, MAX_PACKAGE_BYTES)?;
+    let (sha256, signed) = verify(app, &bytes, &resolved)?;
+    let (package, name, files) = unpack(&bytes)?;
+    if let (Some(listed), PackageSource::Registry { id, .. }) = (&resolved.version, &source) {
+        if id != &package.id || listed != &package.version {
+            return Err("Invalid package: it does not match its index entry".to_string());
+        }
+    }
+
+    let dir = location.dir(app, package.kind)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+    let before = location.load(app)?;
+    let swap = swap_in(&dir, &package.id, &files)?;
+    let installed = InstalledPackage {
+        id: package.id.clone(),
+        kind: package.kind,
+        name,
+        version: package.version,
+        scope: location.scope,
+        source,
+        sha256,
+        signed,
+        installed_at: document::now_millis(),
+        path: swap.target.to_string_lossy().to_string(),
+    };
+
+    let mut packages: Vec<InstalledPackage> = before
+        .iter()
+        .filter(|other| other.id != installed.id || other.kind != installed.kind)
+        .cloned()
+        .collect();
+    packages.push(installed.clone());
+    let result = location
+        .save(app, packages)
+        .and_then(|()| reload_plugins(app, installed.kind, &installed.id));
+    match result {
+        Ok(()) => {
+            swap.commit();
+            tracing::info!(id = %installed.id, version = %installed.version, "Package installed");
+            Ok(installed)
+        }
+        Err(error) => {
+            swap.rollback();
+            if let Err(error) = location.save(app, before) {
+                tracing::warn!(%error, "Failed to restore the package inventory");
+            }
+            let _ = reload_plugins(app, installed.kind, &installed.id);
+            Err(format!("Failed to install package: {}", error))
+        }
+    }
+}
+
+/// Packages installed for the app and, when given, for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_packages(
+    app: AppHandle,
+    workspace: Option<String>,
+) -> Result<Vec<InstalledPackage>, Error> {
+    let mut packages = Location::new(&app, PackageScope::App, None)?.load(&app)?;
+    if let Some(workspace) = workspace {
+        let location = Location::new(&app, PackageScope::Workspace, Some(&workspace))?;
+        packages.extend(location.load(&app)?);
+    }
+    Ok(packages)
+}
+
+/// Packages listed by a registry index
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn fetch_package_index(url: String) -> Result<Vec<IndexEntry>, Error> {
+    tauri::async_runtime::spawn_blocking(move || fetch_index(&url).map_err(Error::from))
+        .await
+        .map_err(|e| format!("Package task failed: {}", e))?
+}
+
+/// Install a plugin or theme package for the app or for `workspace`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn install_package(
+    app: AppHandle,
+    source: PackageSource,
+    scope: PackageScope,
+    workspace: Option<String>,
+) -> Result<InstalledPackage, Error> {
+    let location = Location::new(&app, scope, workspace.as_deref())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        install(&app, &location, source).map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Package task failed: {}", e))?
+}
+
+/// Update an installed package from `source`, or from the registry it was installed from to the
+/// newest version listed; returns the package unchanged when it is already the newest
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn update_package(
+    app: AppHandle,
+    id: String,
+    kind: PackageKind,
+    scope: PackageScope,
+    workspace: Option<String>,
+    source: Option<PackageSource>,
+) -> Result<InstalledPackage, Error> {
+    let location = Location::new(&app, scope, workspace.as_deref())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let installed = location
+            .load(&app)?
+            .into_iter()
+            .find(|package| package.id == id && package.kind == kind)
+            .ok_or_else(|| Error::not_found("Package not found").with_context(&id))?;
+        let source = match (source, &installed.source) {
+            (Some(source), _) => source,
+            (None, PackageSource::Registry { index, id, .. }) => {
+                let newest = fetch_index(index)?
+                    .into_iter()
+                    .filter(|entry| &entry.id == id)
+                    .max_by_key(|entry| version_key(&entry.version))
+                    .ok_or_else(|| format!("Package not found in index: {}", id))?;
+                if version_key(&newest.version) <= version_key(&installed.version) {
+                    return Ok(installed);
+                }
+                PackageSource::Registry {
+                    index: index.clone(),
+                    id: id.clone(),
+                    version: Some(newest.version),
+                }
+            }
+            (None, PackageSource::Url { .. }) => {
+                return Err(Error::from(
+                    "Updating a package installed from a URL requires a new source",
+                ))
+            }
+        };
+        Ok(install(&app, &location, source)?)
+    })
+    .await
+    .map_err(|e| format!("Package task failed: {}", e))?
+}
+
+/// Uninstall a package; uninstalling a plugin also revokes what was granted to it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn remove_package(
+    app: AppHandle,
+    id: String,
+    kind: PackageKind,
+    scope: PackageScope,
+    workspace: Option<String>,
+) -> Result<bool, Error> {
+    let location = Location::new(&app, scope, workspace.as_deref())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut packages = location.load(&app)?;
+        let Some(index) = packages
+            .iter()
+            .position(|package| package.id == id && package.kind == kind)
+        else {
+            return Ok(false);
+        };
+        let package = packages.remove(index);
+        let path = Path::new(&package.path);
+        if path.exists() {
+            fs::remove_dir_all(path).map_err(|e| format!("Failed to remove package: {}", e))?;
+        }
+        location.save(&app, packages)?;
+        #[cfg(desktop)]
+        if kind == PackageKind::Plugin {
+            crate::plugins::revoke(&app, &id)?;
+            crate::plugins::reload(&app, &id)?;
+        }
+        Ok(true)
+    })
+    .await
+    .map_err(|e| format!("Package task failed: {}", e))?
+}
+
+/// Public keys, base64, trusted to sign packages
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_package_keys(app: AppHandle) -> Result<Vec<String>, Error> {
+    Ok(trusted_keys(&app))
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_package_keys(app: AppHandle, keys: Vec<String>) -> Result<(), Error> {
+    for key in &keys {
+        if !BASE64.decode(key).is_ok_and(|key| key.len() == 32) {
+            return Err(format!("Invalid package key: {}", key).into());
+        }
+    }
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(KEYS_KEY, json!(keys));
+    Ok(store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?)
+}
@@ -0,0 +1,169 @@
+use crate::cancel::{self, CancelToken};
+use crate::document;
+use crate::error::Error;
+use crate::{blocking, events, figures, paths, power, read_only, sanitize, tools, workspace};
+use inkfinite_core::import;
+use inkfinite_core::pandoc::{read_markdown, run as run_pandoc, PANDOC_MISSING};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+pub use inkfinite_core::pandoc::extension_for;
+
+#[derive(serde::Serialize)]
+pub struct PandocInfo {
+    pub path: String,
+    pub version: String,
+}
+
+/// Locate a pandoc binary: bundled next to the app resources first, then `PATH`
+fn find_pandoc(app: &AppHandle) -> Option<PathBuf> {
+    tools::find_binary(app, "pandoc", "--version")
+}
+
+/// The pandoc binary, or an error explaining how to install it
+pub fn require_pandoc(app: &AppHandle) -> Result<PathBuf, String> {
+    find_pandoc(app).ok_or_else(|| PANDOC_MISSING.to_string())
+}
+
+/// Report the pandoc binary in use, or `None` when it is unavailable
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_pandoc_info(app: AppHandle) -> Option<PandocInfo> {
+    let pandoc = find_pandoc(&app)?;
+    let version = run_pandoc(&pandoc, &["--version"], None, &CancelToken::default()).ok()?;
+
+    Some(PandocInfo {
+        path: pandoc.to_string_lossy().to_string(),
+        version: version.lines().next().unwrap_or_default().to_string(),
+    })
+}
+
+/// Convert Markdown to `format` with pandoc, writing the result to `output`; diagram code
+/// blocks are rendered to images first
+pub fn convert_markdown(
+    app: &AppHandle,
+    markdown: &str,
+    title: &str,
+    format: &str,
+    output: &Path,
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    inkfinite_core::pandoc::convert_markdown(
+        &require_pandoc(app)?,
+        &figures::embed_diagrams(app, markdown),
+        title,
+        format,
+        output,
+        cancel,
+    )
+}
+
+/// Export a document to any pandoc output format, using Markdown as the interchange format
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn export_via_pandoc(
+    app: AppHandle,
+    path: String,
+    target_format: String,
+    destination: Option<String>,
+    op_id: Option<String>,
+) -> Result<String, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    let source = PathBuf::from(&path);
+    let output = match destination {
+        Some(destination) => {
+            paths::check(&app, &destination, paths::Scope::Export)?;
+            PathBuf::from(destination)
+        }
+        None => {
+            let dir = output_dir(&app, &source)?;
+            document::unique_path(
+                &dir,
+                &document::document_stem(&source),
+                &format!(".{}", extension_for(&target_format)),
+            )
+        }
+    };
+    require_pandoc(&app)?;
+    let operation = cancel::begin(&app, op_id);
+    blocking::run_long("export document", move || {
+        let _awake = power::prevent_sleep("Exporting document");
+        let board = document::read_board(&source)?;
+        convert_markdown(
+            &app,
+            &board.to_markdown(),
+            &board.board.name,
+            &target_format,
+            &output,
+            operation.token(),
+        )?;
+        Ok::<_, String>(output.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Folder of `source`, where conversions without a destination are written, checked for
+/// writing
+fn output_dir(app: &AppHandle, source: &Path) -> Result<PathBuf, Error> {
+    let dir = source.parent().unwrap_or(Path::new("."));
+    paths::check(app, &dir.to_string_lossy(), paths::Scope::Write)?;
+    read_only::ensure_writable(dir)?;
+    Ok(dir.to_path_buf())
+}
+
+/// Import any pandoc-readable file as a new document next to the source file
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn import_via_pandoc(
+    app: AppHandle,
+    file: String,
+    from_format: Option<String>,
+    op_id: Option<String>,
+) -> Result<String, Error> {
+    paths::check(&app, &file, paths::Scope::Read)?;
+    let source = PathBuf::from(&file);
+    let dir = output_dir(&app, &source)?;
+    let pandoc = require_pandoc(&app)?;
+    let operation = cancel::begin(&app, op_id);
+    blocking::run_long("import document", move || {
+        import_file(
+            &app,
+            &pandoc,
+            &source,
+            &dir,
+            from_format.as_deref(),
+            operation.token(),
+        )
+    })
+    .await
+}
+
+fn import_file(
+    app: &AppHandle,
+    pandoc: &Path,
+    source: &Path,
+    dir: &Path,
+    from_format: Option<&str>,
+    cancel: &CancelToken,
+) -> Result<String, Error> {
+    if !source.is_file() {
+        return Err(format!("File does not exist: {}", source.display()).into());
+    }
+
+    let markdown = read_markdown(pandoc, source, from_format, cancel)?;
+    cancel.check()?;
+
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Imported".to_string());
+    let root = workspace::current_root(app).filter(|root| source.starts_with(root));
+    let policy = root
+        .map(|root| sanitize::load_policy(&root))
+        .unwrap_or_default();
+    // Pandoc keeps raw HTML from HTML, EPUB and DOCX sources in its Markdown
+    let output = import::write_document(dir, &stem, &markdown, &policy)?;
+    events::file_changed(app, &output, events::ChangeKind::Created);
+
+    Ok(output.to_string_lossy().to_string())
+}
@@ -0,0 +1,411 @@
+use crate::error::Error;
+use crate::{read_only, workspace};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+pub use inkfinite_core::paths::resolve;
+
+/// What a command does with a path it was given, declared where the command checks it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// Only reads; anything the user could open is allowed
+    Read,
+    /// Creates, changes or removes files; must stay inside the open workspace unless it is a
+    /// document the user opened from elsewhere
+    Write,
+    /// Writes to a destination the user picked, such as an export target, which may be
+    /// anywhere
+    Export,
+}
+
+/// Path syntax the checks follow; a parameter so every platform's rules can be tested anywhere
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Style {
+    Unix,
+    /// Like Unix, but case-insensitive and with the data volume firmlinked into `/`
+    MacOs,
+    /// Drive letters, UNC shares and `\\?\` verbatim prefixes; case-insensitive
+    Windows,
+}
+
+const STYLE: Style = if cfg!(windows) {
+    Style::Windows
+} else if cfg!(target_os = "macos") {
+    Style::MacOs
+} else {
+    Style::Unix
+};
+
+/// Where macOS keeps writable folders that also appear at the root through firmlinks
+const DATA_VOLUME: &str = "/System/Volumes/Data";
+
+/// Firmlinked folders, from `/usr/share/firmlinks`
+const FIRMLINKS: &[&str] = &[
+    "/AppleInternal",
+    "/Applications",
+    "/Library",
+    "/System/Library/Caches",
+    "/System/Library/Assets",
+    "/System/Library/PreinstalledAssets",
+    "/System/Library/AssetsV2",
+    "/System/Library/PreinstalledAssetsV2",
+    "/System/Library/CoreServices/CoreTypes.bundle/Contents/Library",
+    "/System/Library/Speech",
+    "/Users",
+    "/Volumes",
+    "/cores",
+    "/opt",
+    "/private",
+    "/usr/local",
+    "/usr/libexec/cups",
+    "/usr/share/snmp",
+];
+
+/// Validate a path a command was given and resolve it, following symlinks.
+///
+/// Relative paths and `..` components are refused outright. A path that looks like it is in
+/// the workspace but resolves outside it through a symlink is refused for every scope. Without
+/// an open workspace, only the syntax is checked.
+pub fn check(app: &AppHandle, path: &str, scope: Scope) -> Result<PathBuf, Error> {
+    validate(path, STYLE).map_err(|e| Error::invalid_path(e).with_context(path))?;
+    let Some(root) = workspace::current_root(app) else {
+        return Ok(resolve(Path::new(path))?);
+    };
+    let (resolved, inside) = locate(&root, path)?;
+    if scope == Scope::Write && !inside && !opened(app, &resolved) {
+        return Err(Error::invalid_path("Path is outside the workspace").with_context(path));
+    }
+    if scope != Scope::Read {
+        read_only::ensure_writable(&resolved)?;
+    }
+    Ok(resolved)
+}
+
+/// Validate a workspace folder given to a command and resolve it.
+///
+/// The folder must be a workspace open in some window or the current one, so the webview
+/// cannot point workspace commands at arbitrary folders. Scopes other than [`Scope::Read`]
+/// also refuse read-only workspaces.
+pub fn check_workspace(app: &AppHandle, workspace: &str, scope: Scope) -> Result<PathBuf, Error> {
+    let resolved = check_folder(workspace)?;
+    let open = workspace::open_roots(app)
+        .iter()
+        .any(|root| resolve(root).is_ok_and(|root| root == resolved));
+    if !open {
+        return Err(Error::invalid_path("Workspace is not open").with_context(workspace));
+    }
+    if scope != Scope::Read {
+        read_only::ensure_writable(&resolved)?;
+    }
+    Ok(resolved)
+}
+
+/// [`check_workspace`] for an optional workspace argument, which falls back to the current one
+pub fn workspace_or_current(
+    app: &AppHandle,
+    workspace: Option<&str>,
+    scope: Scope,
+) -> Result<PathBuf, Error> {
+    match workspace {
+        Some(workspace) => check_workspace(app, workspace, scope),
+        None => {
+            let root = resolve(&workspace::current_root(app).ok_or("No workspace is open")?)?;
+            if scope != Scope::Read {
+                read_only::ensure_writable(&root)?;
+            }
+            Ok(root)
+        }
+    }
+}
+
+/// Validate a folder about to be opened as a workspace, which must exist
+pub fn check_folder(workspace: &str) -> Result<PathBuf, Error> {
+    validate(workspace, STYLE).map_err(|e| Error::invalid_path(e).with_context(workspace))?;
+    let resolved = resolve(Path::new(workspace))?;
+    if !resolved.is_dir() {
+        return Err(Error::not_found("Workspace does not exist").with_context(workspace));
+    }
+    Ok(resolved)
+}
+
+/// Resolve `path` and report whether it is inside `root`, refusing symlink escapes
+fn locate(root: &Path, path: &str) -> Result<(PathBuf, bool), Error> {
+    let resolved = resolve(Path::new(path))?;
+    let canonical_root = resolve(root)?;
+    let root_text = root.to_string_lossy();
+    let canonical_text = canonical_root.to_string_lossy();
+    let inside = contains(&canonical_text, &resolved.to_string_lossy(), STYLE);
+    let looks_inside = contains(&root_text, path, STYLE) || contains(&canonical_text, path, STYLE);
+    if looks_inside && !inside {
+        return Err(
+            Error::invalid_path("Path resolves outside the workspace through a symlink")
+                .with_context(path),
+        );
+    }
+    Ok((resolved, inside))
+}
+
+/// Whether `path` is a recent document, which the user opened themselves
+fn opened(app: &AppHandle, path: &Path) -> bool {
+    workspace::recent_files(app)
+        .iter()
+        .any(|file| resolve(Path::new(&file.path)).is_ok_and(|recent| recent == path))
+}
+
+/// Check the syntax of a path before touching the filesystem
+fn validate(path: &str, style: Style) -> Result<(), String> {
+    if path.is_empty() {
+        return Err("Invalid path: empty".to_string());
+    }
+    if path.contains('\0') {
+        return Err("Invalid path: contains a null byte".to_string());
+    }
+    if style == Style::Windows && (path.starts_with(r"\\.\") || path.starts_with("//./")) {
+        return Err("Invalid path: device paths are not allowed".to_string());
+    }
+    if !is_absolute(path, style) {
+        return Err("Invalid path: must be absolute".to_string());
+    }
+    if split(path, style).any(|part| part == "..") {
+        return Err("Invalid path: `..` is not allowed".to_string());
+    }
+    Ok(())
+}
+
+fn is_absolute(path: &str, style: Style) -> bool {
+    match style {
+        Style::Unix | Style::MacOs => path.starts_with('/'),
+        Style::Windows => {
+            let bytes = path.as_bytes();
+            path.starts_with(r"\\")
+                || path.starts_with("//")
+                || (bytes.len() >= 3
+                    && bytes[0].is_ascii_alphabetic()
+                    && bytes[1] == b':'
+                    && matches!(bytes[2], b'\\' | b'/'))
+        }
+    }
+}
+
+fn split(path: &str, style: Style) -> impl Iterator<Item = &str> {
+    let separators: &[char] = match style {
+        Style::Unix | Style::MacOs => &['/'],
+        Style::Windows => &['/', '\\'],
+    };
+    path.split(separators)
+}
+
+/// Whether `path` is `root` or inside it, comparing the forms the OS treats as the same file
+fn contains(root: &str, path: &str, style: Style) -> bool {
+    let root = comparable(root, style);
+    let path = comparable(path, style);
+    path.starts_with(&root)
+}
+
+/// Components of `path` with aliases removed and case folded where the platform ignores it
+fn comparable(path: &str, style: Style) -> Vec<String> {
+    let path = match style {
+        Style::Unix => path.to_string(),
+        Style::MacOs => strip_firmlink(path).to_string(),
+        Style::Windows => strip_verbatim(&path.replace('/', "\\")),
+    };
+    let mut parts: Vec<String> = split(&path, style)
+        .filter(|part| !part.is_empty() && *part != ".")
+        .map(|part| match style {
+            Style::Unix => part.to_string(),
+            Style::MacOs | Style::Windows => part.to_lowercase(),
+        })
+        .collect();
+    // Keep `\\server\share` apart from a `server\share` folder on the current drive
+    if style == Style::Windows && path.starts_with(r"\\") {
+        parts.insert(0, r"\\".to_string());
+    }
+    parts
+}
+
+/// Undo the `\\?\` prefix `canonicalize` adds on Windows, so `\\?\UNC\server\share` compares
+/// equal to `\\server\share` and `\\?\C:\` to `C:\`. Volume GUID paths have no other form and
+/// are kept.
+fn strip_verbatim(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        return format!(r"\\{}", rest);
+    }
+    if let Some(rest) = path.strip_prefix(r"\\?\") {
+        let bytes = rest.as_bytes();
+        if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+            return rest.to_string();
+        }
+    }
+    path.to_string()
+}
+
+/// Map `/System/Volumes/Data/Users/...` to `/Users/...`; both name the same file on macOS
+fn strip_firmlink(path: &str) -> &str {
+    match path.strip_prefix(DATA_VOLUME) {
+        Some(rest)
+            if FIRMLINKS.iter().any(|link| {
+                rest.strip_prefix(link)
+                    .is_some_and(|tail| tail.is_empty() || tail.starts_with('/'))
+            }) =>
+        {
+            rest
+        }
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbatim_unc_paths_compare_equal_to_plain_unc_paths() {
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\server\share\notes"),
+            r"\\server\share\notes"
+        );
+        assert!(contains(
+            r"\\server\share\notes",
+            r"\\?\UNC\server\share\notes\board.inkfinite.json",
+            Style::Windows
+        ));
+        assert!(contains(
+            r"\\SERVER\Share\Notes",
+            r"\\server\share\notes\sub\board.inkfinite.json",
+            Style::Windows
+        ));
+    }
+
+    #[test]
+    fn unc_paths_are_scoped_to_their_share() {
+        assert!(!contains(
+            r"\\server\share\notes",
+            r"\\server\other\notes\board.inkfinite.json",
+            Style::Windows
+        ));
+        assert!(!contains(
+            r"\\server\share",
+            r"C:\server\share\board.inkfinite.json",
+            Style::Windows
+        ));
+        assert!(!contains(
+            r"\\server\share\notes",
+            r"\\server\share\notes-old\board.inkfinite.json",
+            Style::Windows
+        ));
+    }
+
+    #[test]
+    fn verbatim_drive_paths_lose_their_prefix() {
+        assert_eq!(strip_verbatim(r"\\?\C:\Users\me"), r"C:\Users\me");
+        assert!(contains(
+            r"c:/Users/me/Notes",
+            r"\\?\C:\Users\me\notes\a.inkfinite.json",
+            Style::Windows
+        ));
+        let volume = r"\\?\Volume{26a21bda-a627-11d7-9931-806e6f6e6963}\notes";
+        assert_eq!(strip_verbatim(volume), volume);
+    }
+
+    #[test]
+    fn windows_traversal_and_device_paths_are_refused() {
+        assert!(validate(r"\\server\share\notes\..\secret", Style::Windows).is_err());
+        assert!(validate(r"C:\notes/../secret", Style::Windows).is_err());
+        assert!(validate(r"\\?\UNC\server\share\..\x", Style::Windows).is_err());
+        assert!(validate(r"\\.\PhysicalDrive0", Style::Windows).is_err());
+        assert!(validate(r"notes\board.inkfinite.json", Style::Windows).is_err());
+        assert!(validate(r"C:notes", Style::Windows).is_err());
+        assert!(validate(r"\\server\share\notes\..board", Style::Windows).is_ok());
+        assert!(validate(r"C:\notes\board.inkfinite.json", Style::Windows).is_ok());
+    }
+
+    #[test]
+    fn firmlinked_paths_compare_equal_to_root_paths() {
+        assert_eq!(
+            strip_firmlink("/System/Volumes/Data/Users/me/Notes"),
+            "/Users/me/Notes"
+        );
+        assert!(contains(
+            "/Users/me/Notes",
+            "/System/Volumes/Data/Users/me/Notes/board.inkfinite.json",
+            Style::MacOs
+        ));
+        assert!(contains(
+            "/System/Volumes/Data/Users/me/Notes",
+            "/Users/me/notes/board.inkfinite.json",
+            Style::MacOs
+        ));
+        assert!(contains(
+            "/usr/local/notes",
+            "/System/Volumes/Data/usr/local/notes/a.inkfinite.json",
+            Style::MacOs
+        ));
+    }
+
+    #[test]
+    fn only_firmlinked_folders_are_aliased() {
+        assert_eq!(
+            strip_firmlink("/System/Volumes/Data/secret"),
+            "/System/Volumes/Data/secret"
+        );
+        assert_eq!(
+            strip_firmlink("/System/Volumes/Data/Usersx/me"),
+            "/System/Volumes/Data/Usersx/me"
+        );
+        assert!(!contains(
+            "/secret",
+            "/System/Volumes/Data/secret/a.inkfinite.json",
+            Style::MacOs
+        ));
+        assert!(!contains(
+            "/Users/me/Notes",
+            "/System/Volumes/Data/usr/local/Users/me/Notes/a.inkfinite.json",
+            Style::MacOs
+        ));
+    }
+
+    #[test]
+    fn unix_paths_are_case_sensitive_and_split_on_slashes_only() {
+        assert!(!contains("/home/Me/notes", "/home/me/notes/a", Style::Unix));
+        assert!(contains(
+            "/home/me/notes/",
+            "/home/me/./notes/a",
+            Style::Unix
+        ));
+        assert!(!contains(
+            "/home/me/notes",
+            "/home/me/notes2/a",
+            Style::Unix
+        ));
+        assert!(validate(r"/home/me/..\notes", Style::Unix).is_ok());
+        assert!(validate("/home/me/../notes", Style::Unix).is_err());
+        assert!(validate("notes/a", Style::Unix).is_err());
+        assert!(validate("/home/me/a\0b", Style::Unix).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_the_workspace_are_refused() {
+        let base = std::env::temp_dir().join(format!("inkfinite-paths-{}", std::process::id()));
+        let root = base.join("workspace");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+
+        let escaped = root.join("link").join("secret.txt");
+        assert!(locate(&root, &escaped.to_string_lossy()).is_err());
+        let created = root.join("link").join("new.txt");
+        assert!(locate(&root, &created.to_string_lossy()).is_err());
+
+        let (_, inside) = locate(&root, &root.join("docs/new.txt").to_string_lossy()).unwrap();
+        assert!(inside);
+        let (resolved, inside) = locate(&root, &outside.to_string_lossy()).unwrap();
+        assert!(!inside);
+        assert_eq!(resolved, outside.canonicalize().unwrap());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
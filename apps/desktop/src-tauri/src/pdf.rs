@@ -0,0 +1,185 @@
+use crate::error::Error;
+use crate::{blocking, paths, thumbnails};
+use pdfium_render::prelude::*;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+const PDFIUM_MISSING: &str =
+    "PDF rendering requires the pdfium library. Reinstall the app or install pdfium system-wide.";
+const DEFAULT_DPI: u32 = 144;
+const MIN_DPI: u32 = 36;
+const MAX_DPI: u32 = 600;
+/// PDF user space is 72 points per inch
+const POINTS_PER_INCH: f32 = 72.0;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedPage {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub page: u16,
+    pub page_count: u16,
+    pub cached: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfInfo {
+    pub page_count: u16,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub outline: Vec<OutlineItem>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineItem {
+    pub title: String,
+    /// One-based page the entry points at, when it has a destination
+    pub page: Option<u16>,
+    pub children: Vec<OutlineItem>,
+}
+
+/// Bind to pdfium: bundled next to the app resources first, then the system library
+fn load_pdfium(app: &AppHandle) -> Result<Pdfium, String> {
+    let bundled = app
+        .path()
+        .resource_dir()
+        .ok()
+        .map(|dir| Pdfium::pdfium_platform_library_name_at_path(&dir.join("binaries")))
+        .filter(|path| path.is_file())
+        .and_then(|path| Pdfium::bind_to_library(path).ok());
+
+    bundled
+        .or_else(|| Pdfium::bind_to_system_library().ok())
+        .map(Pdfium::new)
+        .ok_or_else(|| PDFIUM_MISSING.to_string())
+}
+
+fn open<'a>(pdfium: &'a Pdfium, path: &Path) -> Result<PdfDocument<'a>, String> {
+    if !path.is_file() {
+        return Err(format!("File does not exist: {}", path.display()));
+    }
+    pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|e| format!("Failed to open PDF: {}", e))
+}
+
+/// Rasterize one page (one-based) of a PDF to a cached PNG
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn render_pdf_page(
+    app: AppHandle,
+    asset_path: String,
+    page: u16,
+    dpi: Option<u32>,
+) -> Result<RenderedPage, Error> {
+    paths::check(&app, &asset_path, paths::Scope::Read)?;
+    let dpi = dpi.unwrap_or(DEFAULT_DPI).clamp(MIN_DPI, MAX_DPI);
+    blocking::run_long("render PDF page", move || {
+        render_page(&app, Path::new(&asset_path), page, dpi)
+    })
+    .await
+}
+
+fn render_page(app: &AppHandle, source: &Path, page: u16, dpi: u32) -> Result<RenderedPage, Error> {
+    let pdfium = load_pdfium(app)?;
+    let document = open(&pdfium, source)?;
+    let page_count = document.pages().len();
+    if page == 0 || page > page_count {
+        return Err(format!(
+            "Page {} is out of range (document has {} pages)",
+            page, page_count
+        )
+        .into());
+    }
+
+    let cache_dir = thumbnails::cache_dir(app, source)?;
+    let key = thumbnails::cache_key(source, &format!("pdf-page-{}@{}", page, dpi))?;
+    let target = cache_dir.join(format!("{}.png", key));
+
+    if target.is_file() {
+        let (width, height) = image::image_dimensions(&target)
+            .map_err(|e| format!("Failed to read rendered page: {}", e))?;
+        return Ok(RenderedPage {
+            path: target.to_string_lossy().to_string(),
+            width,
+            height,
+            page,
+            page_count,
+            cached: true,
+        });
+    }
+
+    let pdf_page = document
+        .pages()
+        .get(page - 1)
+        .map_err(|e| format!("Failed to load page: {}", e))?;
+    let config = PdfRenderConfig::new().scale_page_by_factor(dpi as f32 / POINTS_PER_INCH);
+    let image = pdf_page
+        .render_with_config(&config)
+        .map_err(|e| format!("Failed to render page: {}", e))?
+        .as_image();
+
+    fs::create_dir_all(&cache_dir).map_err(|e| format!("Failed to create cache: {}", e))?;
+    image
+        .save_with_format(&target, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to write rendered page: {}", e))?;
+
+    Ok(RenderedPage {
+        path: target.to_string_lossy().to_string(),
+        width: image.width(),
+        height: image.height(),
+        page,
+        page_count,
+        cached: false,
+    })
+}
+
+/// Page count, basic metadata, and the bookmark outline of a PDF
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn pdf_info(app: AppHandle, asset_path: String) -> Result<PdfInfo, Error> {
+    paths::check(&app, &asset_path, paths::Scope::Read)?;
+    let pdfium = load_pdfium(&app)?;
+    let document = open(&pdfium, Path::new(&asset_path))?;
+
+    let metadata = document.metadata();
+    let tag = |kind| {
+        metadata
+            .get(kind)
+            .map(|tag| tag.value().trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let outline = match document.bookmarks().root() {
+        Some(root) => std::iter::once(root.clone())
+            .chain(root.iter_siblings())
+            .map(|bookmark| outline_item(&bookmark))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(PdfInfo {
+        page_count: document.pages().len(),
+        title: tag(PdfDocumentMetadataTagType::Title),
+        author: tag(PdfDocumentMetadataTagType::Author),
+        outline,
+    })
+}
+
+fn outline_item(bookmark: &PdfBookmark) -> OutlineItem {
+    OutlineItem {
+        title: bookmark.title().unwrap_or_default(),
+        page: bookmark
+            .destination()
+            .and_then(|destination| destination.page_index().ok())
+            .map(|index| index + 1),
+        children: bookmark
+            .iter_direct_children()
+            .map(|child| outline_item(&child))
+            .collect(),
+    }
+}
@@ -0,0 +1,407 @@
+use crate::document;
+use crate::error::Error;
+use crate::search;
+use crate::workspace;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::ipc::InvokeBody;
+use tauri::AppHandle;
+use tracing::span::{Attributes, Id};
+use tracing::{Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Upper bounds of the duration buckets, in milliseconds
+const DURATION_BUCKETS: &[u64] = &[1, 2, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+/// Upper bounds of the payload size buckets, in bytes
+const PAYLOAD_BUCKETS: &[u64] = &[
+    256,
+    1024,
+    4 * 1024,
+    16 * 1024,
+    64 * 1024,
+    256 * 1024,
+    1024 * 1024,
+    4 * 1024 * 1024,
+    16 * 1024 * 1024,
+];
+/// Documents listed by name in a `readDocuments` profile
+const SLOWEST_DOCUMENTS: usize = 10;
+
+static STATS: Mutex<BTreeMap<String, Stats>> = Mutex::new(BTreeMap::new());
+static SINCE: Mutex<Option<i64>> = Mutex::new(None);
+
+struct Histogram {
+    bounds: &'static [u64],
+    /// One count per bound plus a final overflow bucket
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [u64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+        }
+    }
+
+    fn record(&mut self, value: u64) {
+        let bucket = self.bounds.partition_point(|&bound| bound < value);
+        self.counts[bucket] += 1;
+    }
+
+    /// Upper bound of the bucket holding the `fraction` quantile; `None` past the last bound
+    fn quantile(&self, fraction: f64) -> Option<u64> {
+        let total: u64 = self.counts.iter().sum();
+        let target = (total as f64 * fraction).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return self.bounds.get(bucket).copied();
+            }
+        }
+        None
+    }
+
+    fn buckets(&self) -> Vec<Bucket> {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(bucket, &count)| Bucket {
+                le: self.bounds.get(bucket).copied(),
+                count,
+            })
+            .collect()
+    }
+}
+
+struct Stats {
+    calls: u64,
+    errors: u64,
+    total_micros: u64,
+    max_micros: u64,
+    durations: Histogram,
+    payloads: u64,
+    payload_bytes: u64,
+    payload_sizes: Histogram,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            calls: 0,
+            errors: 0,
+            total_micros: 0,
+            max_micros: 0,
+            durations: Histogram::new(DURATION_BUCKETS),
+            payloads: 0,
+            payload_bytes: 0,
+            payload_sizes: Histogram::new(PAYLOAD_BUCKETS),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bucket {
+    /// Inclusive upper bound; `None` for the overflow bucket
+    pub le: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandReport {
+    pub command: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Upper bound of the bucket holding the median, `None` if it is past the last bucket
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub durations: Vec<Bucket>,
+    /// Request payloads seen by the IPC handler; nested calls have none
+    pub payloads: u64,
+    pub payload_bytes: u64,
+    pub payload_sizes: Vec<Bucket>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceReport {
+    /// When collection started, or was last reset, in epoch milliseconds
+    pub since: Option<i64>,
+    /// Slowest commands by total time first
+    pub commands: Vec<CommandReport>,
+}
+
+/// Tracing layer that times every instrumented command, whatever the log level
+pub struct Timings;
+
+/// Start time and outcome kept on each command span
+struct Timing {
+    started: Instant,
+    failed: bool,
+}
+
+impl<S> Layer<S> for Timings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Timing {
+                started: Instant::now(),
+                failed: false,
+            });
+        }
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        // `#[instrument(err)]` reports a returned error as an error event inside the span
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+        if let Some(span) = ctx.event_span(event) {
+            if let Some(timing) = span.extensions_mut().get_mut::<Timing>() {
+                timing.failed = true;
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<Timing>() else {
+            return;
+        };
+        let micros = timing.started.elapsed().as_micros() as u64;
+        crate::analytics::record_timing(span.name(), micros, timing.failed);
+        with_stats(span.name(), |stats| {
+            stats.calls += 1;
+            stats.errors += u64::from(timing.failed);
+            stats.total_micros += micros;
+            stats.max_micros = stats.max_micros.max(micros);
+            stats.durations.record(micros.div_ceil(1000));
+        });
+    }
+}
+
+/// Whether `Timings` should see a callsite: spans from this crate, which are the commands
+pub fn is_command(metadata: &tracing::Metadata<'_>) -> bool {
+    metadata.is_span() && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+}
+
+/// Record the size of a command's request payload, called from the IPC handler
+pub fn record_payload(command: &str, payload: &InvokeBody) {
+    let bytes = match payload {
+        InvokeBody::Json(value) => {
+            let mut counter = ByteCounter(0);
+            let _ = serde_json::to_writer(&mut counter, value);
+            counter.0
+        }
+        InvokeBody::Raw(data) => data.len() as u64,
+    };
+    with_stats(command, |stats| {
+        stats.payloads += 1;
+        stats.payload_bytes += bytes;
+        stats.payload_sizes.record(bytes);
+    });
+}
+
+/// Sizes serialized JSON without keeping it
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn with_stats(command: &str, update: impl FnOnce(&mut Stats)) {
+    let Ok(mut stats) = STATS.lock() else {
+        return;
+    };
+    if let Ok(mut since) = SINCE.lock() {
+        since.get_or_insert_with(document::now_millis);
+    }
+    update(stats.entry(command.to_string()).or_default());
+}
+
+/// Timings and payload sizes per command since launch, optionally clearing them afterwards
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_performance_report(reset: Option<bool>) -> Result<PerformanceReport, Error> {
+    let mut stats = STATS
+        .lock()
+        .map_err(|e| format!("Failed to read timings: {}", e))?;
+    let mut since = SINCE
+        .lock()
+        .map_err(|e| format!("Failed to read timings: {}", e))?;
+    let mut commands: Vec<CommandReport> = stats
+        .iter()
+        .map(|(command, stats)| CommandReport {
+            command: command.clone(),
+            calls: stats.calls,
+            errors: stats.errors,
+            total_ms: millis(stats.total_micros),
+            mean_ms: millis(stats.total_micros) / stats.calls.max(1) as f64,
+            max_ms: millis(stats.max_micros),
+            p50_ms: stats.durations.quantile(0.5),
+            p95_ms: stats.durations.quantile(0.95),
+            durations: stats.durations.buckets(),
+            payloads: stats.payloads,
+            payload_bytes: stats.payload_bytes,
+            payload_sizes: stats.payload_sizes.buckets(),
+        })
+        .collect();
+    commands.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    let report = PerformanceReport {
+        since: *since,
+        commands,
+    };
+    if reset.unwrap_or(false) {
+        stats.clear();
+        *since = None;
+    }
+    Ok(report)
+}
+
+fn millis(micros: u64) -> f64 {
+    micros as f64 / 1000.0
+}
+
+/// Workspace operations that can be timed step by step with [`profile_operation`]
+#[derive(serde::Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum Operation {
+    /// Walk the workspace for documents
+    ListDocuments { workspace: String },
+    /// Walk the workspace, then read and parse every document
+    ReadDocuments { workspace: String },
+    /// Run a workspace search as the search box would
+    Search { workspace: String, query: String },
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileStep {
+    pub label: String,
+    pub duration_ms: f64,
+    pub items: usize,
+    pub bytes: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowDocument {
+    /// Workspace-relative path
+    pub path: String,
+    pub bytes: u64,
+    pub duration_ms: f64,
+    /// Set when the document failed to parse
+    pub error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationProfile {
+    pub total_ms: f64,
+    pub steps: Vec<ProfileStep>,
+    /// Documents that took longest to read, for `readDocuments`
+    pub slowest: Vec<SlowDocument>,
+}
+
+/// Run a workspace operation once and report how long each step took
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn profile_operation(app: AppHandle, op: Operation) -> Result<OperationProfile, Error> {
+    tauri::async_runtime::spawn_blocking(move || profile(&app, op))
+        .await
+        .map_err(|e| format!("Profiling task failed: {}", e))?
+}
+
+fn profile(app: &AppHandle, op: Operation) -> Result<OperationProfile, Error> {
+    let started = Instant::now();
+    let mut steps = Vec::new();
+    let mut slowest = Vec::new();
+    match op {
+        Operation::ListDocuments { workspace } => {
+            let (_, step) = walk(Path::new(&workspace))?;
+            steps.push(step);
+        }
+        Operation::ReadDocuments { workspace } => {
+            let root = Path::new(&workspace);
+            let (documents, step) = walk(root)?;
+            steps.push(step);
+
+            let read_started = Instant::now();
+            let mut bytes = 0;
+            for path in &documents {
+                let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+                bytes += size;
+                let document_started = Instant::now();
+                let result = document::read_board(path);
+                slowest.push(SlowDocument {
+                    path: workspace::relative_path(root, path),
+                    bytes: size,
+                    duration_ms: elapsed_ms(document_started),
+                    error: result.err(),
+                });
+            }
+            steps.push(ProfileStep {
+                label: "read documents".to_string(),
+                duration_ms: elapsed_ms(read_started),
+                items: documents.len(),
+                bytes,
+            });
+            slowest.sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+            slowest.truncate(SLOWEST_DOCUMENTS);
+        }
+        Operation::Search { workspace, query } => {
+            let search_started = Instant::now();
+            let hits =
+                search::search_documents(app.clone(), workspace, query, None, None, None, None)?;
+            steps.push(ProfileStep {
+                label: "search".to_string(),
+                duration_ms: elapsed_ms(search_started),
+                items: hits.len(),
+                bytes: 0,
+            });
+        }
+    }
+    Ok(OperationProfile {
+        total_ms: elapsed_ms(started),
+        steps,
+        slowest,
+    })
+}
+
+fn walk(root: &Path) -> Result<(Vec<std::path::PathBuf>, ProfileStep), String> {
+    let started = Instant::now();
+    let documents = workspace::list_documents(root)?;
+    let step = ProfileStep {
+        label: "list documents".to_string(),
+        duration_ms: elapsed_ms(started),
+        items: documents.len(),
+        bytes: 0,
+    };
+    Ok((documents, step))
+}
+
+fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
@@ -0,0 +1,811 @@
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::event_bus::{self, Envelope};
+use crate::events::{self, ChangeKind};
+use crate::{paths, safe_mode, vault, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+use wasmtime::{
+    Caller, Config, Engine, Extern, Instance, Linker, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+const PLUGINS_DIR: &str = "plugins";
+const MANIFEST: &str = "plugin.json";
+const DEFAULT_ENTRY: &str = "plugin.wasm";
+/// Plugin id to the capabilities the user granted; a plugin without an entry is disabled
+const GRANTS_KEY: &str = "plugins";
+/// Import module the host API is linked under
+const HOST_MODULE: &str = "inkfinite";
+/// Emitted when plugins are loaded or unloaded, so menus and palettes can be rebuilt
+const CHANGED_EVENT: &str = "plugins:changed";
+/// Instructions a single call may run before it is stopped
+const FUEL: u64 = 2_000_000_000;
+const MAX_MEMORY: usize = 64 << 20;
+/// Longest file extension an export format may register
+const MAX_EXTENSION: usize = 10;
+
+/// What a plugin may do through the host API; every call is checked against the grant
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    ReadDocuments,
+    WriteDocuments,
+    /// Add commands to the command palette
+    Commands,
+    ExportFormats,
+    /// Receive event bus events matching the manifest's `events` through an `on_event` export
+    Events,
+}
+
+/// `plugins/<id>/plugin.json`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginManifest {
+    /// Must match the plugin's folder name
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub capabilities: BTreeSet<Capability>,
+    /// WebAssembly module, relative to the plugin folder; `plugin.wasm` when omitted
+    pub entry: Option<String>,
+    /// Event bus filters such as `document.*`, see [`crate::event_bus`]
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginCommand {
+    /// Filled in by the host
+    #[serde(default)]
+    pub plugin: String,
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginExportFormat {
+    /// Filled in by the host
+    #[serde(default)]
+    pub plugin: String,
+    pub id: String,
+    pub name: String,
+    /// File extension without the dot
+    pub extension: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    #[serde(flatten)]
+    pub manifest: PluginManifest,
+    pub granted: BTreeSet<Capability>,
+    /// Granted everything it asks for and loaded without errors
+    pub loaded: bool,
+    pub error: Option<String>,
+    pub commands: Vec<PluginCommand>,
+    pub export_formats: Vec<PluginExportFormat>,
+}
+
+/// Per-instance state seen by host functions
+struct Host {
+    app: AppHandle,
+    plugin: String,
+    granted: BTreeSet<Capability>,
+    limits: StoreLimits,
+    /// Commands and formats may only be registered from `init`
+    initializing: bool,
+    commands: Vec<PluginCommand>,
+    formats: Vec<PluginExportFormat>,
+}
+
+struct Loaded {
+    store: Store<Host>,
+    instance: Instance,
+}
+
+struct Slot {
+    manifest: PluginManifest,
+    dir: PathBuf,
+    granted: BTreeSet<Capability>,
+    error: Option<String>,
+    commands: Vec<PluginCommand>,
+    formats: Vec<PluginExportFormat>,
+    loaded: Option<Arc<Mutex<Loaded>>>,
+}
+
+/// Managed state: the plugins found in the plugins folder and their instances
+#[derive(Default)]
+pub struct Plugins {
+    engine: Mutex<Option<Engine>>,
+    slots: Mutex<BTreeMap<String, Slot>>,
+}
+
+pub fn plugins_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join(PLUGINS_DIR))
+}
+
+/// Plugins installed for the open workspace only, in `.inkfinite/plugins`
+pub fn workspace_plugins_dir(root: &Path) -> PathBuf {
+    workspace::internal_dir(root).join(PLUGINS_DIR)
+}
+
+/// Folders plugins are loaded from; an app plugin wins over a workspace plugin with its id
+fn plugin_dirs(app: &AppHandle) -> Result<Vec<PathBuf>, String> {
+    let mut dirs = vec![plugins_dir(app)?];
+    dirs.extend(workspace::current_root(app).map(|root| workspace_plugins_dir(&root)));
+    Ok(dirs)
+}
+
+fn grants(app: &AppHandle) -> BTreeMap<String, BTreeSet<Capability>> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(GRANTS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn save_grants(
+    app: &AppHandle,
+    grants: &BTreeMap<String, BTreeSet<Capability>>,
+) -> Result<(), String> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(GRANTS_KEY, json!(grants));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// Forget what was granted to a plugin that was uninstalled
+pub fn revoke(app: &AppHandle, id: &str) -> Result<(), String> {
+    let mut grants = grants(app);
+    if grants.remove(id).is_none() {
+        return Ok(());
+    }
+    save_grants(app, &grants)
+}
+
+fn engine(plugins: &Plugins) -> Result<Engine, String> {
+    let mut engine = plugins
+        .engine
+        .lock()
+        .map_err(|e| format!("Failed to load plugins: {}", e))?;
+    if let Some(engine) = engine.as_ref() {
+        return Ok(engine.clone());
+    }
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    let created =
+        Engine::new(&config).map_err(|e| format!("Failed to start plugin runtime: {}", e))?;
+    *engine = Some(created.clone());
+    Ok(created)
+}
+
+fn read_manifest(dir: &Path) -> Result<PluginManifest, String> {
+    let content = fs::read_to_string(dir.join(MANIFEST))
+        .map_err(|e| format!("Failed to read {}: {}", MANIFEST, e))?;
+    let manifest: PluginManifest =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", MANIFEST, e))?;
+    let folder = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string());
+    if folder.as_deref() != Some(manifest.id.as_str()) {
+        return Err(format!(
+            "Invalid {}: id {} does not match the folder name",
+            MANIFEST, manifest.id
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Rescan the plugins folder and instantiate every plugin granted all it asks for
+fn load_all(app: &AppHandle) -> Result<(), String> {
+    let grants = grants(app);
+    let mut slots = BTreeMap::new();
+    let entries = plugin_dirs(app)?
+        .into_iter()
+        .flat_map(|dir| fs::read_dir(dir).into_iter().flatten().flatten());
+    for entry in entries {
+        let path = entry.path();
+        if !path.is_dir() || slots.contains_key(&entry.file_name().to_string_lossy().to_string()) {
+            continue;
+        }
+        let manifest = match read_manifest(&path) {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                tracing::warn!(%error, "Skipping plugin");
+                continue;
+            }
+        };
+        let granted = grants.get(&manifest.id).cloned().unwrap_or_default();
+        let mut slot = Slot {
+            manifest,
+            dir: path,
+            granted,
+            error: None,
+            commands: Vec::new(),
+            formats: Vec::new(),
+            loaded: None,
+        };
+        if grants.contains_key(&slot.manifest.id) {
+            if slot.manifest.capabilities.is_subset(&slot.granted) {
+                if let Err(error) = instantiate(app, &mut slot) {
+                    tracing::warn!(%error, plugin = %slot.manifest.id, "Failed to load plugin");
+                    slot.error = Some(error);
+                }
+            } else {
+                slot.error = Some("Plugin asks for capabilities that were not granted".to_string());
+            }
+        }
+        slots.insert(slot.manifest.id.clone(), slot);
+    }
+
+    let plugins = app.state::<Plugins>();
+    *plugins
+        .slots
+        .lock()
+        .map_err(|e| format!("Failed to load plugins: {}", e))? = slots;
+    let _ = app.emit(CHANGED_EVENT, ());
+    Ok(())
+}
+
+fn instantiate(app: &AppHandle, slot: &mut Slot) -> Result<(), String> {
+    let engine = engine(&app.state::<Plugins>())?;
+    let entry = slot.manifest.entry.as_deref().unwrap_or(DEFAULT_ENTRY);
+    let entry = contained(&slot.dir, entry).ok_or("Invalid plugin entry path")?;
+    let module = Module::from_file(&engine, &entry)
+        .map_err(|e| format!("Failed to compile plugin: {}", e))?;
+
+    let host = Host {
+        app: app.clone(),
+        plugin: slot.manifest.id.clone(),
+        granted: slot.granted.clone(),
+        limits: StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .instances(1)
+            .build(),
+        initializing: true,
+        commands: Vec::new(),
+        formats: Vec::new(),
+    };
+    let mut store = Store::new(&engine, host);
+    store.limiter(|host| &mut host.limits);
+    store
+        .set_fuel(FUEL)
+        .map_err(|e| format!("Failed to load plugin: {}", e))?;
+    // Only the host API is linked, so anything else the module imports fails here
+    let instance = linker(&engine)?
+        .instantiate(&mut store, &module)
+        .map_err(|e| format!("Failed to load plugin: {}", e))?;
+    if let Ok(init) = instance.get_typed_func::<(), ()>(&mut store, "init") {
+        init.call(&mut store, ())
+            .map_err(|e| format!("Plugin failed to start: {}", e))?;
+    }
+    store.data_mut().initializing = false;
+
+    slot.commands = store.data().commands.clone();
+    slot.formats = store.data().formats.clone();
+    slot.loaded = Some(Arc::new(Mutex::new(Loaded { store, instance })));
+    tracing::info!(plugin = %slot.manifest.id, "Plugin loaded");
+    Ok(())
+}
+
+/// `relative` inside `dir`, refusing absolute paths and `..`
+fn contained(dir: &Path, relative: &str) -> Option<PathBuf> {
+    let relative = Path::new(relative);
+    relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| dir.join(relative))
+}
+
+/// Host functions, all under the `inkfinite` import module. Strings are passed as a pointer and
+/// a length into the plugin's memory; strings returned to the plugin are copied into memory from
+/// its `alloc(len) -> ptr` export and returned as `ptr << 32 | len`. Negative results are errors:
+/// `-1` when the capability was not granted, `-2` when the call failed.
+fn linker(engine: &Engine) -> Result<Linker<Host>, String> {
+    let mut linker = Linker::new(engine);
+    let link = |e: wasmtime::Error| format!("Failed to set up plugin runtime: {}", e);
+
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "log",
+            |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> wasmtime::Result<()> {
+                let message = read_string(&mut caller, ptr, len)?;
+                tracing::info!(plugin = %caller.data().plugin, "{}", message);
+                Ok(())
+            },
+        )
+        .map_err(link)?;
+
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "register_command",
+            |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> wasmtime::Result<i32> {
+                if !allowed(&caller, Capability::Commands) || !caller.data().initializing {
+                    return Ok(-1);
+                }
+                let input = read_string(&mut caller, ptr, len)?;
+                let Ok(mut command) = serde_json::from_str::<PluginCommand>(&input) else {
+                    return Ok(-2);
+                };
+                command.plugin = caller.data().plugin.clone();
+                caller.data_mut().commands.push(command);
+                Ok(0)
+            },
+        )
+        .map_err(link)?;
+
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "register_export_format",
+            |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> wasmtime::Result<i32> {
+                if !allowed(&caller, Capability::ExportFormats) || !caller.data().initializing {
+                    return Ok(-1);
+                }
+                let input = read_string(&mut caller, ptr, len)?;
+                let Ok(mut format) = serde_json::from_str::<PluginExportFormat>(&input) else {
+                    return Ok(-2);
+                };
+                // The extension becomes part of the exported file's name
+                if !valid_extension(&format.extension) {
+                    return Ok(-2);
+                }
+                format.plugin = caller.data().plugin.clone();
+                caller.data_mut().formats.push(format);
+                Ok(0)
+            },
+        )
+        .map_err(link)?;
+
+    // Workspace-relative paths of the open workspace's documents, as a JSON array
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "list_documents",
+            |mut caller: Caller<'_, Host>| -> wasmtime::Result<i64> {
+                if !allowed(&caller, Capability::ReadDocuments) {
+                    return Ok(-1);
+                }
+                let Some(root) = workspace::current_root(&caller.data().app) else {
+                    return Ok(-2);
+                };
+                let Ok(documents) = workspace::list_documents(&root) else {
+                    return Ok(-2);
+                };
+                let documents: Vec<String> = documents
+                    .iter()
+                    .filter(|path| vault::vault_of(path).is_none())
+                    .map(|path| workspace::relative_path(&root, path))
+                    .collect();
+                write_string(&mut caller, &json!(documents).to_string())
+            },
+        )
+        .map_err(link)?;
+
+    // A document's text as Markdown
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "read_document",
+            |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> wasmtime::Result<i64> {
+                if !allowed(&caller, Capability::ReadDocuments) {
+                    return Ok(-1);
+                }
+                let relative = read_string(&mut caller, ptr, len)?;
+                let markdown = document_path(&caller.data().app, &relative, paths::Scope::Read)
+                    .and_then(|path| document::read_board(&path))
+                    .map(|board| board.to_markdown());
+                match markdown {
+                    Ok(markdown) => write_string(&mut caller, &markdown),
+                    Err(error) => {
+                        tracing::debug!(%error, "Plugin could not read a document");
+                        Ok(-2)
+                    }
+                }
+            },
+        )
+        .map_err(link)?;
+
+    // Append Markdown to a document as a new block, creating the document when it is missing
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "write_document",
+            |mut caller: Caller<'_, Host>,
+             path_ptr: i32,
+             path_len: i32,
+             ptr: i32,
+             len: i32|
+             -> wasmtime::Result<i32> {
+                if !allowed(&caller, Capability::WriteDocuments) {
+                    return Ok(-1);
+                }
+                let relative = read_string(&mut caller, path_ptr, path_len)?;
+                let markdown = read_string(&mut caller, ptr, len)?;
+                let app = caller.data().app.clone();
+                match write_document(&app, &relative, &markdown) {
+                    Ok(()) => Ok(0),
+                    Err(error) => {
+                        tracing::debug!(%error, "Plugin could not write a document");
+                        Ok(-2)
+                    }
+                }
+            },
+        )
+        .map_err(link)?;
+
+    Ok(linker)
+}
+
+fn allowed(caller: &Caller<'_, Host>, capability: Capability) -> bool {
+    caller.data().granted.contains(&capability)
+}
+
+/// Short and alphanumeric, so it cannot carry a path out of the export folder
+fn valid_extension(extension: &str) -> bool {
+    !extension.is_empty()
+        && extension.len() <= MAX_EXTENSION
+        && extension.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn read_string(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> wasmtime::Result<String> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("plugin does not export its memory"))?;
+    let mut buffer = vec![0u8; len.max(0) as usize];
+    memory.read(&*caller, ptr as u32 as usize, &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+fn write_string(caller: &mut Caller<'_, Host>, text: &str) -> wasmtime::Result<i64> {
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| wasmtime::Error::msg("plugin does not export alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, text.len() as i32)?;
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("plugin does not export its memory"))?;
+    memory.write(&mut *caller, ptr as u32 as usize, text.as_bytes())?;
+    Ok(pack(ptr, text.len()))
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    ((ptr as u32 as i64) << 32) | len as u32 as i64
+}
+
+/// A document of the open workspace from a workspace-relative path; vaults are off limits
+fn document_path(app: &AppHandle, relative: &str, scope: paths::Scope) -> Result<PathBuf, String> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let mut path = contained(&root, relative).ok_or("Invalid document path")?;
+    if !path.to_string_lossy().ends_with(DOCUMENT_EXTENSION) {
+        path = PathBuf::from(format!("{}{}", path.to_string_lossy(), DOCUMENT_EXTENSION));
+    }
+    if vault::vault_of(&path).is_some() {
+        return Err("Plugins cannot open documents in a vault".to_string());
+    }
+    paths::check(app, &path.to_string_lossy(), scope).map_err(|e| e.message)?;
+    Ok(path)
+}
+
+fn write_document(app: &AppHandle, relative: &str, markdown: &str) -> Result<(), String> {
+    let path = document_path(app, relative, paths::Scope::Write)?;
+    let (board, kind) = if path.exists() {
+        let mut board = document::read_board(&path)?;
+        board.push_markdown(markdown);
+        (board, ChangeKind::Modified)
+    } else {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        let name = document::document_stem(&path);
+        (
+            BoardFile::from_markdown(&name, markdown),
+            ChangeKind::Created,
+        )
+    };
+    document::write_board(&path, &board)?;
+    events::file_changed(app, &path, kind);
+    Ok(())
+}
+
+/// Call a `(ptr, len) -> packed` export with `input` and return what it wrote
+fn call(loaded: &mut Loaded, export: &str, input: &[u8]) -> Result<Vec<u8>, String> {
+    let failed = |e: wasmtime::Error| format!("Plugin failed: {}", e);
+    let Loaded { store, instance } = loaded;
+    store.set_fuel(FUEL).map_err(failed)?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&mut *store, "alloc")
+        .map_err(failed)?;
+    let function = instance
+        .get_typed_func::<(i32, i32), i64>(&mut *store, export)
+        .map_err(|_| format!("Plugin does not support {}", export))?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or("Plugin does not export its memory")?;
+
+    let ptr = alloc
+        .call(&mut *store, input.len() as i32)
+        .map_err(failed)?;
+    memory
+        .write(&mut *store, ptr as u32 as usize, input)
+        .map_err(|e| format!("Plugin failed: {}", e))?;
+    let packed = function
+        .call(&mut *store, (ptr, input.len() as i32))
+        .map_err(failed)?;
+    if packed < 0 {
+        return Err(format!("Plugin failed: {} returned {}", export, packed));
+    }
+    let mut output = vec![0u8; (packed & 0xffff_ffff) as usize];
+    memory
+        .read(&*store, (packed >> 32) as usize, &mut output)
+        .map_err(|e| format!("Plugin failed: {}", e))?;
+    Ok(output)
+}
+
+fn loaded(app: &AppHandle, plugin: &str) -> Result<Arc<Mutex<Loaded>>, String> {
+    app.state::<Plugins>()
+        .slots
+        .lock()
+        .map_err(|e| format!("Failed to read plugins: {}", e))?
+        .get(plugin)
+        .and_then(|slot| slot.loaded.clone())
+        .ok_or_else(|| format!("Plugin not found: {}", plugin))
+}
+
+/// Render Markdown with a plugin's export format, for [`crate::exports`] and
+/// [`export_with_plugin`]. `format` is `<plugin>/<format>`; returns the bytes and the file
+/// extension.
+pub fn export(
+    app: &AppHandle,
+    format: &str,
+    name: &str,
+    markdown: &str,
+) -> Result<(Vec<u8>, String), String> {
+    let (plugin, id) = format
+        .split_once('/')
+        .ok_or_else(|| format!("Invalid plugin export format: {}", format))?;
+    let extension = {
+        let plugins = app.state::<Plugins>();
+        let slots = plugins
+            .slots
+            .lock()
+            .map_err(|e| format!("Failed to read plugins: {}", e))?;
+        slots
+            .get(plugin)
+            .and_then(|slot| slot.formats.iter().find(|candidate| candidate.id == id))
+            .map(|format| format.extension.clone())
+            .ok_or_else(|| format!("Export format not found: {}", format))?
+    };
+    let input = json!({ "format": id, "name": name, "markdown": markdown }).to_string();
+    let loaded = loaded(app, plugin)?;
+    let mut loaded = loaded.lock().map_err(|e| format!("Plugin failed: {}", e))?;
+    Ok((call(&mut loaded, "export", input.as_bytes())?, extension))
+}
+
+/// Pass an event bus event as JSON to the `on_event` export of every loaded plugin granted
+/// [`Capability::Events`] whose manifest subscribes to its topic
+pub fn deliver_event(app: &AppHandle, topic: &str, envelope: &Envelope) {
+    let subscribed: Vec<(String, Arc<Mutex<Loaded>>)> = {
+        let plugins = app.state::<Plugins>();
+        let Ok(slots) = plugins.slots.lock() else {
+            return;
+        };
+        slots
+            .values()
+            .filter(|slot| slot.granted.contains(&Capability::Events))
+            .filter(|slot| event_bus::matches(&slot.manifest.events, topic))
+            .filter_map(|slot| Some((slot.manifest.id.clone(), slot.loaded.clone()?)))
+            .collect()
+    };
+    if subscribed.is_empty() {
+        return;
+    }
+    let input = json!(envelope).to_string();
+    for (plugin, loaded) in subscribed {
+        let Ok(mut loaded) = loaded.lock() else {
+            continue;
+        };
+        let Loaded { store, instance } = &mut *loaded;
+        if instance.get_func(&mut *store, "on_event").is_none() {
+            continue;
+        }
+        if let Err(error) = call(&mut loaded, "on_event", input.as_bytes()) {
+            tracing::warn!(%error, %plugin, topic, "Plugin failed to handle event");
+        }
+    }
+}
+
+/// Rescan the plugins folders and restart every enabled plugin; returns why the plugin `id`
+/// failed to load, if it did
+pub fn reload(app: &AppHandle, id: &str) -> Result<Option<String>, String> {
+    load_all(app)?;
+    let plugins = app.state::<Plugins>();
+    let slots = plugins
+        .slots
+        .lock()
+        .map_err(|e| format!("Failed to read plugins: {}", e))?;
+    Ok(slots.get(id).and_then(|slot| slot.error.clone()))
+}
+
+/// Load enabled plugins in the background
+pub fn start(app: AppHandle) {
+    if safe_mode::disabled(safe_mode::Component::Plugins) {
+        tracing::info!("Plugins are disabled in safe mode");
+        return;
+    }
+    std::thread::spawn(move || {
+        if let Err(error) = load_all(&app) {
+            tracing::warn!(%error, "Failed to load plugins");
+        }
+    });
+}
+
+/// Plugins in the plugins folder, with what they ask for and what they registered
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_plugins(app: AppHandle) -> Result<Vec<PluginInfo>, Error> {
+    let plugins = app.state::<Plugins>();
+    let slots = plugins
+        .slots
+        .lock()
+        .map_err(|e| format!("Failed to read plugins: {}", e))?;
+    Ok(slots
+        .values()
+        .map(|slot| PluginInfo {
+            manifest: slot.manifest.clone(),
+            granted: slot.granted.clone(),
+            loaded: slot.loaded.is_some(),
+            error: slot.error.clone(),
+            commands: slot.commands.clone(),
+            export_formats: slot.formats.clone(),
+        })
+        .collect())
+}
+
+/// Enable a plugin, granting every capability its manifest asks for, or disable it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn set_plugin_enabled(app: AppHandle, id: String, enabled: bool) -> Result<(), Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let manifest = plugin_dirs(&app)?
+            .iter()
+            .filter_map(|dir| contained(dir, &id))
+            .find(|dir| dir.is_dir())
+            .ok_or_else(|| format!("Plugin not found: {}", id))
+            .and_then(|dir| read_manifest(&dir))?;
+        let mut grants = grants(&app);
+        if enabled {
+            grants.insert(id, manifest.capabilities);
+        } else {
+            grants.remove(&id);
+        }
+        save_grants(&app, &grants)?;
+        Ok::<_, Error>(load_all(&app)?)
+    })
+    .await
+    .map_err(|e| format!("Plugin task failed: {}", e))?
+}
+
+/// Rescan the plugins folder and restart every enabled plugin
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn reload_plugins(app: AppHandle) -> Result<(), Error> {
+    Ok(tauri::async_runtime::spawn_blocking(move || load_all(&app))
+        .await
+        .map_err(|e| format!("Plugin task failed: {}", e))??)
+}
+
+/// Commands registered by loaded plugins, for the command palette
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_plugin_commands(app: AppHandle) -> Result<Vec<PluginCommand>, Error> {
+    let plugins = app.state::<Plugins>();
+    let slots = plugins
+        .slots
+        .lock()
+        .map_err(|e| format!("Failed to read plugins: {}", e))?;
+    Ok(slots
+        .values()
+        .filter(|slot| slot.loaded.is_some())
+        .flat_map(|slot| slot.commands.iter().cloned())
+        .collect())
+}
+
+/// Run a plugin command. The plugin's `run_command` export receives `{ command, context }` as
+/// JSON and may return JSON, which is passed back as is.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn run_plugin_command(
+    app: AppHandle,
+    plugin: String,
+    command: String,
+    context: Option<Value>,
+) -> Result<Value, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let loaded = loaded(&app, &plugin)?;
+        let mut loaded = loaded.lock().map_err(|e| format!("Plugin failed: {}", e))?;
+        if !loaded
+            .store
+            .data()
+            .commands
+            .iter()
+            .any(|registered| registered.id == command)
+        {
+            return Err(Error::from(format!(
+                "Plugin command not found: {}",
+                command
+            )));
+        }
+        let input = json!({ "command": command, "context": context }).to_string();
+        let output = call(&mut loaded, "run_command", input.as_bytes())?;
+        if output.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_slice(&output).map_err(|e| format!("Invalid plugin reply: {}", e))?)
+    })
+    .await
+    .map_err(|e| format!("Plugin task failed: {}", e))?
+}
+
+/// Export a document with a plugin's format (`<plugin>/<format>`), next to the document unless
+/// a destination is given; returns the path written
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn export_with_plugin(
+    app: AppHandle,
+    path: String,
+    format: String,
+    destination: Option<String>,
+) -> Result<String, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    if let Some(destination) = &destination {
+        paths::check(&app, destination, paths::Scope::Export)?;
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let source = Path::new(&path);
+        if vault::vault_of(source).is_some() {
+            return Err(Error::from(
+                "Plugin exports are not available for documents in a vault",
+            ));
+        }
+        let board = document::read_board(source)?;
+        let (bytes, extension) = export(&app, &format, &board.board.name, &board.to_markdown())?;
+        let output = match destination {
+            Some(destination) => PathBuf::from(destination),
+            None => document::unique_path(
+                source.parent().unwrap_or(Path::new(".")),
+                &document::document_stem(source),
+                &format!(".{}", extension),
+            ),
+        };
+        fs::write(&output, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+        Ok(output.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| format!("Plugin task failed: {}", e))?
+}
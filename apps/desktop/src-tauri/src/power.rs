@@ -0,0 +1,141 @@
+use std::sync::Mutex;
+
+/// Inhibition held while any [`SleepGuard`] is alive, with the number of guards sharing it
+static ACTIVE: Mutex<(usize, Option<Inhibitor>)> = Mutex::new((0, None));
+
+/// Keeps the system from sleeping until dropped, so an early return, an error, or a cancelled
+/// job releases the lock the same way completion does
+#[must_use = "the system may sleep as soon as the guard is dropped"]
+pub struct SleepGuard(());
+
+/// Hold an OS sleep-inhibition lock for a long-running job.
+///
+/// Overlapping jobs share one lock, taken by the first guard and released with the last.
+/// Failing to take it is not an error; the job simply runs without it.
+pub fn prevent_sleep(reason: &str) -> SleepGuard {
+    if let Ok(mut active) = ACTIVE.lock() {
+        if active.0 == 0 {
+            active.1 = Inhibitor::acquire(reason);
+        }
+        active.0 += 1;
+    }
+    SleepGuard(())
+}
+
+impl Drop for SleepGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE.lock() {
+            active.0 = active.0.saturating_sub(1);
+            if active.0 == 0 {
+                active.1 = None;
+            }
+        }
+    }
+}
+
+/// Power request released when dropped
+#[cfg(windows)]
+struct Inhibitor(windows::Win32::Foundation::HANDLE);
+
+// The request handle is only used to clear and close the request, which any thread may do
+#[cfg(windows)]
+unsafe impl Send for Inhibitor {}
+
+#[cfg(windows)]
+impl Inhibitor {
+    fn acquire(reason: &str) -> Option<Self> {
+        use windows::core::PWSTR;
+        use windows::Win32::System::Power::{
+            PowerCreateRequest, PowerRequestSystemRequired, PowerSetRequest,
+        };
+        use windows::Win32::System::Threading::{
+            POWER_REQUEST_CONTEXT_SIMPLE_STRING, REASON_CONTEXT, REASON_CONTEXT_0,
+        };
+
+        let mut reason: Vec<u16> = reason.encode_utf16().chain([0]).collect();
+        let context = REASON_CONTEXT {
+            Version: windows::Win32::System::SystemServices::POWER_REQUEST_CONTEXT_VERSION,
+            Flags: POWER_REQUEST_CONTEXT_SIMPLE_STRING,
+            Reason: REASON_CONTEXT_0 {
+                SimpleReasonString: PWSTR(reason.as_mut_ptr()),
+            },
+        };
+        unsafe {
+            let request = PowerCreateRequest(&context).ok()?;
+            let inhibitor = Self(request);
+            PowerSetRequest(request, PowerRequestSystemRequired).ok()?;
+            Some(inhibitor)
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        use windows::Win32::Foundation::CloseHandle;
+        use windows::Win32::System::Power::{PowerClearRequest, PowerRequestSystemRequired};
+
+        unsafe {
+            let _ = PowerClearRequest(self.0, PowerRequestSystemRequired);
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+/// Helper process holding the assertion for as long as it runs
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+struct Inhibitor(std::process::Child);
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+impl Inhibitor {
+    /// `caffeinate` also exits when this process does, so a crash never leaves the lock held
+    #[cfg(target_os = "macos")]
+    fn acquire(_reason: &str) -> Option<Self> {
+        std::process::Command::new("caffeinate")
+            .arg("-i")
+            .arg("-w")
+            .arg(std::process::id().to_string())
+            .spawn()
+            .ok()
+            .map(Self)
+    }
+
+    /// The inhibitor lasts until `cat` sees its stdin close, either on release or when this
+    /// process exits
+    #[cfg(target_os = "linux")]
+    fn acquire(reason: &str) -> Option<Self> {
+        use std::process::{Command, Stdio};
+
+        Command::new("systemd-inhibit")
+            .args(["--what=sleep:idle", "--who=Inkfinite", "--mode=block"])
+            .arg(format!("--why={}", reason))
+            .arg("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()
+            .map(Self)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        drop(self.0.stdin.take());
+        #[cfg(target_os = "macos")]
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Mobile platforms suspend apps on their own terms, so there is nothing to hold
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+struct Inhibitor;
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+impl Inhibitor {
+    fn acquire(_reason: &str) -> Option<Self> {
+        None
+    }
+}
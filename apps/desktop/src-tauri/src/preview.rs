@@ -0,0 +1,140 @@
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::site::escape;
+use crate::{blocking, paths};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use std::path::Path;
+use tauri::ipc::Response;
+use tauri::AppHandle;
+
+/// Render a document as a standalone HTML page, as shown by the Quick Look extension
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn render_preview(app: AppHandle, path: String) -> Result<String, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    Ok(render_html(Path::new(&path))?)
+}
+
+/// The preview page as raw UTF-8 bytes, skipping JSON string encoding for large documents
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn render_preview_data(app: AppHandle, path: String) -> Result<Response, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    blocking::run("render preview", move || {
+        render_html(Path::new(&path)).map(|html| Response::new(html.into_bytes()))
+    })
+    .await
+}
+
+/// Self-contained preview of a document's text, with local images referenced by `file://` URL
+pub fn render_html(path: &Path) -> Result<String, String> {
+    let board = document::read_board(path)?;
+    let doc_dir = path.parent().unwrap_or(Path::new("."));
+    let body = render_markdown(&board.to_markdown(), doc_dir);
+    Ok(layout(&board, &body))
+}
+
+fn render_markdown(markdown: &str, doc_dir: &Path) -> String {
+    let parser = Parser::new_ext(markdown, Options::all()).map(|event| match event {
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = file_url(&dest_url, doc_dir)
+                .map(CowStr::from)
+                .unwrap_or(dest_url);
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        other => other,
+    });
+
+    let mut body = String::new();
+    html::push_html(&mut body, parser);
+    body
+}
+
+fn file_url(url: &str, doc_dir: &Path) -> Option<String> {
+    if url.contains("://") || url.starts_with("data:") {
+        return None;
+    }
+    let path = doc_dir.join(url).canonicalize().ok()?;
+    url::Url::from_file_path(path).ok().map(String::from)
+}
+
+fn layout(board: &BoardFile, body: &str) -> String {
+    let pages = board.pages();
+    let shapes: usize = pages.iter().map(|page| board.page_shapes(page).len()).sum();
+    let updated = chrono::DateTime::from_timestamp_millis(board.board.updated_at)
+        .map(|date| {
+            date.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default();
+
+    format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<main>\n{}</main>\n\
+         <footer>{} {} · {} {} · Updated {}</footer>\n</body>\n</html>\n",
+        escape(&board.board.name),
+        PREVIEW_CSS,
+        body,
+        pages.len(),
+        if pages.len() == 1 { "page" } else { "pages" },
+        shapes,
+        if shapes == 1 { "shape" } else { "shapes" },
+        escape(&updated)
+    )
+}
+
+const PREVIEW_CSS: &str = "body{font-family:-apple-system,system-ui,sans-serif;line-height:1.5;\
+color:#1f2933;background:#fff;margin:0;padding:1.5rem 2rem}img{max-width:100%}\
+pre{overflow:auto;padding:.75rem;background:#f3f4f6}\
+footer{margin-top:2rem;color:#6b7280;font-size:.8rem}\
+@media (prefers-color-scheme:dark){body{color:#e5e7eb;background:#1e1e1e}\
+pre{background:#2a2a2a}footer{color:#9ca3af}}";
+
+/// C entry point linked into the Quick Look extension; free the result with
+/// [`inkfinite_preview_free`]. Returns null when the document cannot be read.
+///
+/// # Safety
+///
+/// `path` must be a valid NUL-terminated UTF-8 string.
+#[cfg(target_os = "macos")]
+#[no_mangle]
+pub unsafe extern "C" fn inkfinite_preview_html(
+    path: *const std::ffi::c_char,
+) -> *mut std::ffi::c_char {
+    use std::ffi::{CStr, CString};
+
+    if path.is_null() {
+        return std::ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return std::ptr::null_mut();
+    };
+    render_html(Path::new(path))
+        .ok()
+        .and_then(|html| CString::new(html).ok())
+        .map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Release a string returned by [`inkfinite_preview_html`]
+///
+/// # Safety
+///
+/// `html` must come from [`inkfinite_preview_html`] and not be freed twice.
+#[cfg(target_os = "macos")]
+#[no_mangle]
+pub unsafe extern "C" fn inkfinite_preview_free(html: *mut std::ffi::c_char) {
+    if !html.is_null() {
+        drop(std::ffi::CString::from_raw(html));
+    }
+}
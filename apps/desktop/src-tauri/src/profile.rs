@@ -0,0 +1,285 @@
+use crate::error::Error;
+use crate::spellcheck::{self, CustomDictionary};
+use crate::{document, paths, read_only, settings, templates, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+const PROFILE_FORMAT: u32 = 1;
+const MAX_PROFILE_BYTES: u64 = 64 * 1024 * 1024;
+const DICTIONARY_EXTENSIONS: &[&str] = &["dic", "aff"];
+
+/// Store entries that are preferences rather than facts about this machine, what it trusts or
+/// its credentials. API keys live in the OS keychain and never reach the store; `savedSearches`
+/// is kept by the frontend.
+const PORTABLE_KEYS: &[&str] = &[
+    "settings",
+    "hotkeys",
+    "keybindings",
+    "externalEditor",
+    "saveDelayMs",
+    "logLevel",
+    "aiProviders",
+    "savedSearches",
+];
+
+/// A portable bundle of preferences, in the same shape as a package file: files travel as
+/// base64 by name
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileFile {
+    format: u32,
+    app_version: String,
+    created_at: i64,
+    store: BTreeMap<String, Value>,
+    templates: BTreeMap<String, String>,
+    dictionaries: BTreeMap<String, String>,
+    /// Words of the workspace's custom dictionary
+    words: BTreeSet<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileSummary {
+    pub path: String,
+    /// Store entries exported or imported
+    pub settings: Vec<String>,
+    pub templates: Vec<String>,
+    pub dictionaries: Vec<String>,
+    pub words: usize,
+    /// Templates and dictionaries left alone because one with the same name exists
+    pub skipped: Vec<String>,
+}
+
+fn dictionaries_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(spellcheck::DICTIONARIES_DIR))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// Files in `dir` with one of `extensions`, base64 by file name
+fn read_files(dir: &Path, extensions: &[&str]) -> Result<BTreeMap<String, String>, String> {
+    let mut files = BTreeMap::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(files);
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let matches = path
+            .extension()
+            .is_some_and(|ext| extensions.iter().any(|wanted| ext == *wanted));
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !matches || !path.is_file() {
+            continue;
+        }
+        let bytes = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        files.insert(name.to_string(), BASE64.encode(bytes));
+    }
+    Ok(files)
+}
+
+/// Write the bundled files into `dir`, skipping names that exist unless `overwrite` is set.
+/// Returns the names written and the names skipped.
+fn write_files(
+    dir: &Path,
+    files: &BTreeMap<String, String>,
+    extensions: &[&str],
+    overwrite: bool,
+) -> Result<(Vec<String>, Vec<String>), String> {
+    let (mut written, mut skipped) = (Vec::new(), Vec::new());
+    for (name, content) in files {
+        let file = Path::new(name);
+        let valid = file.file_name().and_then(|n| n.to_str()) == Some(name.as_str())
+            && file
+                .extension()
+                .is_some_and(|ext| extensions.iter().any(|wanted| ext == *wanted));
+        if !valid {
+            return Err(format!("Invalid profile: bad file name {}", name));
+        }
+        let path = dir.join(name);
+        if path.exists() && !overwrite {
+            skipped.push(name.clone());
+            continue;
+        }
+        let bytes = BASE64
+            .decode(content)
+            .map_err(|e| format!("Invalid profile: {}: {}", name, e))?;
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+        fs::write(&path, bytes).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+        written.push(name.clone());
+    }
+    Ok((written, skipped))
+}
+
+fn workspace_root(app: &AppHandle, workspace: Option<&str>) -> Result<Option<PathBuf>, Error> {
+    match workspace {
+        Some(workspace) => paths::check_workspace(app, workspace, paths::Scope::Read).map(Some),
+        None => Ok(workspace::current_root(app)),
+    }
+}
+
+/// Bundle settings, keybindings, hotkeys and saved searches with the workspace's templates and
+/// custom words and the installed spelling dictionaries into one file at `destination`.
+///
+/// Secrets, paired devices, trust decisions and machine-specific paths are left out.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn export_app_profile(
+    app: AppHandle,
+    destination: String,
+    workspace: Option<String>,
+) -> Result<ProfileSummary, Error> {
+    let path = paths::check(&app, &destination, paths::Scope::Export)?;
+    let root = workspace_root(&app, workspace.as_deref())?;
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+
+    let profile = ProfileFile {
+        format: PROFILE_FORMAT,
+        app_version: app.package_info().version.to_string(),
+        created_at: document::now_millis(),
+        store: PORTABLE_KEYS
+            .iter()
+            .filter_map(|key| Some((key.to_string(), store.get(key)?)))
+            .collect(),
+        templates: match &root {
+            Some(root) => read_files(&templates::dir(root), &[templates::TEMPLATE_EXTENSION])?,
+            None => BTreeMap::new(),
+        },
+        dictionaries: read_files(&dictionaries_dir(&app)?, DICTIONARY_EXTENSIONS)?,
+        words: match &root {
+            Some(root) => {
+                workspace::read_config::<CustomDictionary>(root, spellcheck::DICTIONARY_CONFIG)?
+                    .words
+            }
+            None => BTreeSet::new(),
+        },
+    };
+    let json = serde_json::to_vec_pretty(&profile)
+        .map_err(|e| format!("Failed to serialize profile: {}", e))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    fs::write(&path, json).map_err(|e| format!("Failed to write profile: {}", e))?;
+    tracing::info!(path = %path.display(), "Profile exported");
+
+    Ok(ProfileSummary {
+        path: path.to_string_lossy().to_string(),
+        settings: profile.store.keys().cloned().collect(),
+        templates: profile.templates.keys().cloned().collect(),
+        dictionaries: profile.dictionaries.keys().cloned().collect(),
+        words: profile.words.len(),
+        skipped: Vec::new(),
+    })
+}
+
+/// Set this machine up from a file written by [`export_app_profile`].
+///
+/// Preferences are replaced and applied at once; templates go into the workspace and custom
+/// words are added to its dictionary. Existing templates and dictionaries are kept unless
+/// `overwrite` is set. Emits `profile:imported`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn import_app_profile(
+    app: AppHandle,
+    file: String,
+    workspace: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<ProfileSummary, Error> {
+    let path = paths::check(&app, &file, paths::Scope::Read)?;
+    let overwrite = overwrite.unwrap_or(false);
+    let mut json = Vec::new();
+    fs::File::open(&path)
+        .and_then(|file| file.take(MAX_PROFILE_BYTES + 1).read_to_end(&mut json))
+        .map_err(|e| format!("Failed to read profile: {}", e))?;
+    if json.len() as u64 > MAX_PROFILE_BYTES {
+        return Err("Invalid profile: file is too large".into());
+    }
+    let profile: ProfileFile =
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid profile: {}", e))?;
+    if profile.format != PROFILE_FORMAT {
+        return Err(format!(
+            "Invalid profile: format {} is not supported",
+            profile.format
+        )
+        .into());
+    }
+    let root = workspace_root(&app, workspace.as_deref())?;
+    if root.is_none() && (!profile.templates.is_empty() || !profile.words.is_empty()) {
+        return Err("Importing templates and custom words requires an open workspace".into());
+    }
+
+    let mut skipped = Vec::new();
+    let mut templates = Vec::new();
+    let mut words = 0;
+    if let Some(root) = &root {
+        read_only::ensure_writable(root)?;
+        let (written, kept) = write_files(
+            &templates::dir(root),
+            &profile.templates,
+            &[templates::TEMPLATE_EXTENSION],
+            overwrite,
+        )?;
+        templates = written;
+        skipped.extend(kept);
+
+        if !profile.words.is_empty() {
+            let mut dictionary: CustomDictionary =
+                workspace::read_config(root, spellcheck::DICTIONARY_CONFIG)?;
+            let before = dictionary.words.len();
+            dictionary.words.extend(profile.words.iter().cloned());
+            words = dictionary.words.len() - before;
+            workspace::write_config(root, spellcheck::DICTIONARY_CONFIG, &dictionary)?;
+        }
+    }
+    let (dictionaries, kept) = write_files(
+        &dictionaries_dir(&app)?,
+        &profile.dictionaries,
+        DICTIONARY_EXTENSIONS,
+        overwrite,
+    )?;
+    skipped.extend(kept);
+
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let imported: Vec<String> = profile
+        .store
+        .into_iter()
+        .filter(|(key, _)| PORTABLE_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| {
+            store.set(key.clone(), value);
+            key
+        })
+        .collect();
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+
+    // Take the new preferences into use without a restart
+    settings::load(&app);
+    crate::messages::load(&app);
+    crate::hotkeys::register_all(&app);
+    tracing::info!(path = %path.display(), "Profile imported");
+
+    let summary = ProfileSummary {
+        path: path.to_string_lossy().to_string(),
+        settings: imported,
+        templates,
+        dictionaries,
+        words,
+        skipped,
+    };
+    let _ = app.emit("profile:imported", &summary);
+    Ok(summary)
+}
@@ -0,0 +1,314 @@
+use crate::cancel;
+use crate::document;
+use crate::error::Error;
+use crate::providers::{self, AiCompletion, AiRequest};
+use crate::{catalog, local_api, paths, read_only, workspace};
+use inkfinite_core::template::{self, Prompt, PromptKind, Template};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const PROMPT_EXTENSION: &str = "json";
+
+/// A reusable AI prompt, saved as `.inkfinite/prompts/<id>.json`.
+///
+/// `system` and `prompt` are templates in the document template syntax, with `{{selection}}`,
+/// `{{document}}`, `{{title}}`, `{{path}}` and `{{tags}}` from the editor and `variables` filled
+/// in by the user.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub system: Option<String>,
+    pub prompt: String,
+    #[serde(default)]
+    pub variables: Vec<Prompt>,
+    /// Provider to use instead of the workspace default
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplateInfo {
+    pub id: String,
+    #[serde(flatten)]
+    pub template: PromptTemplate,
+    /// Shipped with the app; saving one with the same id replaces it in this workspace
+    pub built_in: bool,
+}
+
+/// What the editor has open when a prompt is run
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptContext {
+    pub path: Option<String>,
+    pub selection: Option<String>,
+    /// Values for the template's variables
+    #[serde(default)]
+    pub values: Value,
+    /// Overrides the template's provider
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+fn variable(name: &str, label: &str, default: Option<&str>) -> Prompt {
+    Prompt {
+        name: name.to_string(),
+        label: Some(label.to_string()),
+        kind: PromptKind::Text,
+        options: Vec::new(),
+        default: default.map(str::to_string),
+        required: true,
+    }
+}
+
+fn built_ins() -> Vec<(&'static str, PromptTemplate)> {
+    let template = |name: &str, description: &str, system: &str, prompt: &str| PromptTemplate {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        system: Some(system.to_string()),
+        prompt: prompt.to_string(),
+        variables: Vec::new(),
+        provider: None,
+        temperature: Some(0.3),
+        max_tokens: None,
+    };
+    let mut translate = template(
+        "Translate",
+        "Translate the selection, or the document, into another language",
+        "You are a translator. Translate the user's text into {{language}}, keeping its meaning, \
+         tone and Markdown formatting. Reply with the translation only.",
+        "{{#if selection}}{{selection}}{{else}}{{document}}{{/if}}",
+    );
+    translate.variables = vec![variable("language", "Language", Some("English"))];
+    vec![
+        (
+            "summarize-meeting",
+            template(
+                "Summarize meeting",
+                "Summarize meeting notes into decisions, discussion and next steps",
+                "You are an assistant that summarizes meeting notes. Reply in Markdown with the \
+                 sections Summary, Decisions and Next steps, in the language of the notes.",
+                "Meeting notes \"{{title}}\":\n\n{{#if selection}}{{selection}}{{else}}{{document}}{{/if}}",
+            ),
+        ),
+        (
+            "extract-action-items",
+            template(
+                "Extract action items",
+                "List the tasks in the text as a Markdown checklist",
+                "You extract action items. Reply with only a Markdown checklist (`- [ ] task`), \
+                 one line per task, naming the owner and due date when the text gives them.",
+                "{{#if selection}}{{selection}}{{else}}{{document}}{{/if}}",
+            ),
+        ),
+        ("translate", translate),
+    ]
+}
+
+fn dir(root: &Path) -> PathBuf {
+    workspace::internal_dir(root).join("prompts")
+}
+
+fn prompt_path(root: &Path, id: &str) -> PathBuf {
+    dir(root).join(format!("{}.{}", local_api::file_stem(id), PROMPT_EXTENSION))
+}
+
+/// Check both templates parse, so a broken one is reported on save rather than on run
+fn check(prompt: &PromptTemplate) -> Result<(), String> {
+    if prompt.name.trim().is_empty() || prompt.prompt.trim().is_empty() {
+        return Err("Invalid prompt template: a name and prompt are required".to_string());
+    }
+    Template::parse(&prompt.prompt, &prompt.name)?;
+    if let Some(system) = &prompt.system {
+        Template::parse(system, &prompt.name)?;
+    }
+    Ok(())
+}
+
+/// The saved template `id`, or the built-in one
+fn load(root: &Path, id: &str) -> Result<PromptTemplate, String> {
+    match fs::read_to_string(prompt_path(root, id)) {
+        Ok(source) => {
+            serde_json::from_str(&source).map_err(|e| format!("Invalid prompt template: {}", e))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => built_ins()
+            .into_iter()
+            .find(|(built_in, _)| *built_in == id)
+            .map(|(_, prompt)| prompt)
+            .ok_or_else(|| format!("Prompt template not found: {}", id)),
+        Err(e) => Err(format!("Failed to read prompt template: {}", e)),
+    }
+}
+
+/// Render the system and user prompts, returning them with the document the content came from
+fn render(
+    app: &AppHandle,
+    root: &Path,
+    prompt: &PromptTemplate,
+    context: &PromptContext,
+) -> Result<(Option<String>, String, Option<String>), String> {
+    let shape = Template {
+        name: prompt.name.clone(),
+        description: None,
+        title: None,
+        folder: None,
+        prompts: prompt.variables.clone(),
+        body: String::new(),
+    };
+    let mut values = shape.values(&context.values)?;
+    let mut source = None;
+    if let Value::Object(map) = &mut values {
+        map.insert("selection".into(), json!(context.selection));
+        if let Some(path) = &context.path {
+            let resolved = paths::check(app, path, paths::Scope::Read).map_err(|e| e.message)?;
+            let board = document::read_board(&resolved)?;
+            let markdown = board.to_markdown();
+            let tags: Vec<String> = catalog::tags(&markdown).into_iter().collect();
+            map.insert("title".into(), json!(board.board.name));
+            map.insert(
+                "path".into(),
+                json!(workspace::relative_path(root, &resolved)),
+            );
+            map.insert("tags".into(), json!(tags.join(", ")));
+            map.insert("document".into(), json!(markdown));
+            source = Some(resolved.to_string_lossy().to_string());
+        }
+    }
+    let now = chrono::Local::now();
+    let system = match &prompt.system {
+        Some(system) => Some(template::render(system, &values, now)?.text),
+        None => None,
+    };
+    let text = template::render(&prompt.prompt, &values, now)?.text;
+    if text.trim().is_empty() {
+        return Err("Invalid prompt: there is no text to send".to_string());
+    }
+    Ok((system, text, source))
+}
+
+/// Built-in prompt templates and those saved in the workspace's `.inkfinite/prompts`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_prompt_templates(
+    app: AppHandle,
+    workspace: String,
+) -> Result<Vec<PromptTemplateInfo>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let mut templates: Vec<PromptTemplateInfo> = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir(&root)) {
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_none_or(|ext| ext != PROMPT_EXTENSION) {
+                continue;
+            }
+            let id = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match load(&root, &id) {
+                Ok(template) => templates.push(PromptTemplateInfo {
+                    id,
+                    template,
+                    built_in: false,
+                }),
+                Err(error) => tracing::warn!("Skipping prompt {}: {}", path.display(), error),
+            }
+        }
+    }
+    for (id, template) in built_ins() {
+        if !templates.iter().any(|saved| saved.id == id) {
+            templates.push(PromptTemplateInfo {
+                id: id.to_string(),
+                template,
+                built_in: true,
+            });
+        }
+    }
+    templates.sort_by_key(|info| info.template.name.to_lowercase());
+    Ok(templates)
+}
+
+/// Save a prompt template after checking its syntax; returns its id
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_prompt_template(
+    app: AppHandle,
+    workspace: String,
+    id: String,
+    template: PromptTemplate,
+) -> Result<String, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    check(&template)?;
+    let id = local_api::file_stem(&id);
+    fs::create_dir_all(dir(&root)).map_err(|e| format!("Failed to create folder: {}", e))?;
+    let json = serde_json::to_string_pretty(&template)
+        .map_err(|e| format!("Failed to serialize prompt template: {}", e))?;
+    fs::write(prompt_path(&root, &id), json)
+        .map_err(|e| format!("Failed to write prompt template: {}", e))?;
+    Ok(id)
+}
+
+/// Delete a saved prompt template; a built-in one it replaced comes back
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_prompt_template(
+    app: AppHandle,
+    workspace: String,
+    id: String,
+) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    match fs::remove_file(prompt_path(&root, &id)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete prompt template: {}", e).into()),
+    }
+}
+
+/// Fill a prompt template from the open document and selection and send it to the provider
+/// layer, streaming `llm:token` events
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn run_prompt_template(
+    app: AppHandle,
+    id: String,
+    context: Option<PromptContext>,
+    op_id: Option<String>,
+) -> Result<AiCompletion, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let root = workspace::current_root(&app).ok_or("No workspace is open")?;
+        let context = context.unwrap_or_default();
+        let prompt = load(&root, &id)?;
+        let (system, text, source) = render(&app, &root, &prompt, &context)?;
+        let request = AiRequest {
+            provider: context.provider.or(prompt.provider),
+            model: context.model,
+            system,
+            prompt: text,
+            documents: Vec::new(),
+            source,
+            max_tokens: prompt.max_tokens,
+            temperature: prompt.temperature,
+        };
+        let operation = cancel::begin(&app, op_id.clone());
+        Ok(providers::complete(
+            &app,
+            request,
+            op_id,
+            operation.token(),
+        )?)
+    })
+    .await
+    .map_err(|e| format!("Prompt task failed: {}", e))?
+}
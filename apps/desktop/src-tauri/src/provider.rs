@@ -0,0 +1,310 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::{read_only, vault, workspace};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File or folder listed to the system document picker
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderEntry {
+    /// Absolute path, used as the document id
+    pub path: String,
+    /// Board name for documents, folder name otherwise
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: i64,
+}
+
+/// Workspace chosen in the app, read from the settings store the app keeps in `data_dir` along
+/// with the read-only workspaces.
+///
+/// The provider can run while the app itself is not, so it reads the store file directly
+/// instead of going through the store plugin.
+pub fn workspace_root(data_dir: &Path) -> Result<PathBuf, String> {
+    let content = fs::read_to_string(data_dir.join(workspace::STORE_NAME))
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+    let settings: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid settings: {}", e))?;
+    // Loaded here too, since the app may not be running to load them
+    let read_only_roots = settings
+        .get(read_only::STORE_KEY)
+        .and_then(|value| serde_json::from_value::<Vec<PathBuf>>(value.clone()).ok())
+        .unwrap_or_default();
+    inkfinite_core::read_only::set_roots(read_only_roots);
+    settings
+        .get(workspace::WORKSPACE_DIR_KEY)
+        .and_then(|value| value.as_str())
+        .map(PathBuf::from)
+        .filter(|root| root.is_dir())
+        .ok_or_else(|| "No workspace is open".to_string())
+}
+
+/// Documents and folders directly inside `dir`, hiding internal and other files and locked vaults
+pub fn list(root: &Path, dir: &Path) -> Result<Vec<ProviderEntry>, String> {
+    let dir = contained(root, dir)?;
+    if vault::is_locked(&dir) {
+        return Err(format!("Vault is locked: {}", dir.display()));
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name.starts_with('.') {
+            continue;
+        }
+        let is_dir = path.is_dir();
+        if !is_dir && !file_name.ends_with(DOCUMENT_EXTENSION) {
+            continue;
+        }
+        if is_dir && vault::is_locked(&path) {
+            continue;
+        }
+        entries.push(describe(&path, is_dir)?);
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then(a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+/// Describe a single document or folder
+pub fn stat(root: &Path, path: &Path) -> Result<ProviderEntry, String> {
+    let path = contained(root, path)?;
+    if vault::is_locked(&path) {
+        return Err(format!("Vault is locked: {}", path.display()));
+    }
+    describe(&path, path.is_dir())
+}
+
+/// Document content as another app should see it: validated and re-serialized
+pub fn read(root: &Path, path: &Path) -> Result<String, String> {
+    let board = document::read_board(&contained(root, path)?)?;
+    serde_json::to_string_pretty(&board).map_err(|e| format!("Failed to serialize document: {}", e))
+}
+
+/// Save content written by another app, rejecting anything that is not a valid document
+pub fn write(root: &Path, path: &Path, content: &str) -> Result<(), String> {
+    let path = contained(root, path)?;
+    let mut board: BoardFile =
+        serde_json::from_str(content).map_err(|e| format!("Invalid file format: {}", e))?;
+    board.board.updated_at = document::now_millis();
+    document::write_board(&path, &board)
+}
+
+/// Create an empty document in `dir`, returning its path
+pub fn create(root: &Path, dir: &Path, name: &str) -> Result<String, String> {
+    let dir = contained(root, dir)?;
+    read_only::ensure_writable(&dir)?;
+    let name = name.strip_suffix(DOCUMENT_EXTENSION).unwrap_or(name).trim();
+    let name = if name.is_empty() { "Untitled" } else { name };
+    if name.contains(['/', '\\']) {
+        return Err(format!("Invalid document name: {}", name));
+    }
+    let path = document::unique_path(&dir, name, DOCUMENT_EXTENSION);
+    document::write_board(&path, &BoardFile::new(name))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+pub fn delete(root: &Path, path: &Path) -> Result<(), String> {
+    let path = contained(root, path)?;
+    read_only::ensure_writable(&path)?;
+    if path == root.canonicalize().unwrap_or_default() {
+        return Err("Cannot delete the workspace".to_string());
+    }
+    // Other apps see vaults as plain folders, so a whole vault is only removed from the app
+    if path.is_dir() && !vault::vaults(&path).is_empty() {
+        return Err(format!(
+            "Cannot delete a folder with a vault: {}",
+            path.display()
+        ));
+    }
+    if vault::is_locked(&path) {
+        return Err(format!("Vault is locked: {}", path.display()));
+    }
+    if path.is_dir() {
+        fs::remove_dir_all(&path)
+    } else {
+        fs::remove_file(&path)
+    }
+    .map_err(|e| format!("Failed to delete: {}", e))?;
+    audit::record(root, AuditAction::Delete, AuditSource::Ui, &[&path]);
+    Ok(())
+}
+
+/// Resolve `path`, refusing anything outside the workspace
+fn contained(root: &Path, path: &Path) -> Result<PathBuf, String> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve workspace: {}", e))?;
+    let resolved = path
+        .canonicalize()
+        .map_err(|e| format!("File does not exist: {} ({})", path.display(), e))?;
+    if !resolved.starts_with(&root) {
+        return Err(format!("Path is outside the workspace: {}", path.display()));
+    }
+    Ok(resolved)
+}
+
+fn describe(path: &Path, is_dir: bool) -> Result<ProviderEntry, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| elapsed.as_millis() as i64);
+    let name = if is_dir {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    } else {
+        document::read_board(path)
+            .map(|board| board.board.name)
+            .unwrap_or_else(|_| document::document_stem(path))
+    };
+    Ok(ProviderEntry {
+        path: path.to_string_lossy().to_string(),
+        name,
+        is_dir,
+        size: if is_dir { 0 } else { metadata.len() },
+        modified,
+    })
+}
+
+/// JNI entry points for `InkfiniteDocumentsProvider` in `android/`; errors are thrown to
+/// Kotlin as `java.io.IOException`
+#[cfg(target_os = "android")]
+mod jni_exports {
+    use super::*;
+    use jni::objects::{JClass, JString};
+    use jni::sys::jstring;
+    use jni::JNIEnv;
+
+    fn string(env: &mut JNIEnv<'_>, value: &JString<'_>) -> Result<String, String> {
+        env.get_string(value)
+            .map(String::from)
+            .map_err(|e| format!("Invalid string argument: {}", e))
+    }
+
+    fn respond(env: &mut JNIEnv<'_>, result: Result<String, String>) -> jstring {
+        let result = result.and_then(|value| {
+            env.new_string(value)
+                .map_err(|e| format!("Failed to return string: {}", e))
+        });
+        match result {
+            Ok(value) => value.into_raw(),
+            Err(error) => {
+                let _ = env.throw_new("java/io/IOException", error);
+                std::ptr::null_mut()
+            }
+        }
+    }
+
+    fn json<T: serde::Serialize>(value: Result<T, String>) -> Result<String, String> {
+        value.and_then(|value| {
+            serde_json::to_string(&value).map_err(|e| format!("Failed to serialize: {}", e))
+        })
+    }
+
+    #[no_mangle]
+    pub extern "system" fn Java_org_stormlightlabs_inkfinite_InkfiniteDocumentsProvider_nativeRoot(
+        mut env: JNIEnv<'_>,
+        _class: JClass<'_>,
+        data_dir: JString<'_>,
+    ) -> jstring {
+        let result = string(&mut env, &data_dir)
+            .and_then(|data_dir| workspace_root(Path::new(&data_dir)))
+            .map(|root| root.to_string_lossy().to_string());
+        respond(&mut env, result)
+    }
+
+    #[no_mangle]
+    pub extern "system" fn Java_org_stormlightlabs_inkfinite_InkfiniteDocumentsProvider_nativeList(
+        mut env: JNIEnv<'_>,
+        _class: JClass<'_>,
+        root: JString<'_>,
+        dir: JString<'_>,
+    ) -> jstring {
+        let result = string(&mut env, &root).and_then(|root| {
+            let dir = string(&mut env, &dir)?;
+            json(list(Path::new(&root), Path::new(&dir)))
+        });
+        respond(&mut env, result)
+    }
+
+    #[no_mangle]
+    pub extern "system" fn Java_org_stormlightlabs_inkfinite_InkfiniteDocumentsProvider_nativeStat(
+        mut env: JNIEnv<'_>,
+        _class: JClass<'_>,
+        root: JString<'_>,
+        path: JString<'_>,
+    ) -> jstring {
+        let result = string(&mut env, &root).and_then(|root| {
+            let path = string(&mut env, &path)?;
+            json(stat(Path::new(&root), Path::new(&path)))
+        });
+        respond(&mut env, result)
+    }
+
+    #[no_mangle]
+    pub extern "system" fn Java_org_stormlightlabs_inkfinite_InkfiniteDocumentsProvider_nativeRead(
+        mut env: JNIEnv<'_>,
+        _class: JClass<'_>,
+        root: JString<'_>,
+        path: JString<'_>,
+    ) -> jstring {
+        let result = string(&mut env, &root).and_then(|root| {
+            let path = string(&mut env, &path)?;
+            read(Path::new(&root), Path::new(&path))
+        });
+        respond(&mut env, result)
+    }
+
+    /// Returns the path written
+    #[no_mangle]
+    pub extern "system" fn Java_org_stormlightlabs_inkfinite_InkfiniteDocumentsProvider_nativeWrite(
+        mut env: JNIEnv<'_>,
+        _class: JClass<'_>,
+        root: JString<'_>,
+        path: JString<'_>,
+        content: JString<'_>,
+    ) -> jstring {
+        let result = string(&mut env, &root).and_then(|root| {
+            let path = string(&mut env, &path)?;
+            let content = string(&mut env, &content)?;
+            write(Path::new(&root), Path::new(&path), &content).map(|_| path)
+        });
+        respond(&mut env, result)
+    }
+
+    #[no_mangle]
+    pub extern "system" fn Java_org_stormlightlabs_inkfinite_InkfiniteDocumentsProvider_nativeCreate(
+        mut env: JNIEnv<'_>,
+        _class: JClass<'_>,
+        root: JString<'_>,
+        dir: JString<'_>,
+        name: JString<'_>,
+    ) -> jstring {
+        let result = string(&mut env, &root).and_then(|root| {
+            let dir = string(&mut env, &dir)?;
+            let name = string(&mut env, &name)?;
+            create(Path::new(&root), Path::new(&dir), &name)
+        });
+        respond(&mut env, result)
+    }
+
+    /// Returns the path deleted
+    #[no_mangle]
+    pub extern "system" fn Java_org_stormlightlabs_inkfinite_InkfiniteDocumentsProvider_nativeDelete(
+        mut env: JNIEnv<'_>,
+        _class: JClass<'_>,
+        root: JString<'_>,
+        path: JString<'_>,
+    ) -> jstring {
+        let result = string(&mut env, &root).and_then(|root| {
+            let path = string(&mut env, &path)?;
+            delete(Path::new(&root), Path::new(&path)).map(|_| path)
+        });
+        respond(&mut env, result)
+    }
+}
@@ -0,0 +1,493 @@
+use crate::cancel::{self, CancelToken};
+use crate::document::{self, create_id};
+use crate::error::Error;
+use crate::{catalog, http, llm, paths, read_only, secrets, vault, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// Store key of the configured remote providers
+const PROVIDERS_KEY: &str = "aiProviders";
+/// `.inkfinite/ai.json`
+const POLICY_CONFIG: &str = "ai";
+/// Id of the on-device model provider from [`crate::llm`]
+pub const LOCAL: &str = "local";
+
+/// An OpenAI-compatible endpoint; its API key is kept in the OS keychain
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderConfig {
+    /// Assigned when the provider is first saved
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Such as `https://api.openai.com/v1`; `/chat/completions` is appended
+    pub base_url: String,
+    pub model: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provider {
+    #[serde(flatten)]
+    pub config: ProviderConfig,
+    pub has_key: bool,
+    /// Whether content leaves the device; endpoints on this machine are not remote
+    pub remote: bool,
+}
+
+/// Which content a workspace allows to be sent to remote providers, in `.inkfinite/ai.json`.
+/// Nothing is sent until `allowRemote` is set; vault documents are never sent.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AiPolicy {
+    pub allow_remote: bool,
+    /// Remote providers that may be used; every configured one when empty
+    pub providers: Vec<String>,
+    /// Workspace-relative folders whose documents stay on the device
+    pub exclude_folders: Vec<String>,
+    /// Documents carrying any of these `#tags` stay on the device
+    pub exclude_tags: Vec<String>,
+    /// Provider used when a request names none; `local` when unset
+    pub default_provider: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiRequest {
+    /// The workspace's default provider when omitted
+    pub provider: Option<String>,
+    /// Overrides the provider's model
+    pub model: Option<String>,
+    pub system: Option<String>,
+    pub prompt: String,
+    /// Documents whose Markdown is appended to the prompt
+    #[serde(default)]
+    pub documents: Vec<String>,
+    /// Document the prompt text came from, such as a selection's, checked against the policy
+    pub source: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiCompletion {
+    pub provider: String,
+    pub model: Option<String>,
+    pub text: String,
+    pub remote: bool,
+}
+
+fn key_name(id: &str) -> String {
+    format!("aiProvider:{}", id)
+}
+
+fn load(app: &AppHandle) -> Vec<ProviderConfig> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(PROVIDERS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, providers: &[ProviderConfig]) -> Result<(), String> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(PROVIDERS_KEY, json!(providers));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// Endpoints on loopback run on this machine, such as a local Ollama server
+fn is_remote(base_url: &str) -> bool {
+    let host = url::Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string));
+    !matches!(
+        host.as_deref(),
+        Some("localhost" | "127.0.0.1" | "[::1]" | "::1")
+    )
+}
+
+fn check_url(base_url: &str) -> Result<(), String> {
+    let url = url::Url::parse(base_url).map_err(|e| format!("Invalid base URL: {}", e))?;
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if !is_remote(base_url) => Ok(()),
+        "http" => Err("Invalid base URL: remote providers must use https".to_string()),
+        _ => Err(format!("Invalid base URL: {}", base_url)),
+    }
+}
+
+fn read_policy(root: &Path) -> Result<AiPolicy, String> {
+    workspace::read_config(root, POLICY_CONFIG)
+}
+
+/// A document to include, or the reason the policy keeps it on the device
+fn shareable(
+    root: Option<&Path>,
+    policy: &AiPolicy,
+    path: &Path,
+    markdown: &str,
+) -> Result<(), String> {
+    let refused = |reason: &str| {
+        Err(format!(
+            "Invalid request: {} cannot be sent to a remote provider ({})",
+            path.display(),
+            reason
+        ))
+    };
+    if vault::vault_of(path).is_some() {
+        return refused("it is in a vault");
+    }
+    let Some(root) = root.filter(|root| path.starts_with(root)) else {
+        return refused("it is outside the open workspace");
+    };
+    let relative = workspace::relative_path(root, path);
+    let excluded = policy.exclude_folders.iter().any(|folder| {
+        let folder = folder.trim_matches('/');
+        !folder.is_empty()
+            && relative
+                .strip_prefix(folder)
+                .is_some_and(|rest| rest.starts_with('/'))
+    });
+    if excluded {
+        return refused("its folder is excluded");
+    }
+    let tags = catalog::tags(markdown);
+    if let Some(tag) = policy
+        .exclude_tags
+        .iter()
+        .find(|tag| tags.contains(tag.trim_start_matches('#')))
+    {
+        return refused(&format!("it is tagged #{}", tag.trim_start_matches('#')));
+    }
+    Ok(())
+}
+
+/// Where a request goes and the workspace policy it is held to
+pub struct Route {
+    pub id: String,
+    provider: Option<ProviderConfig>,
+    /// Whether content leaves the device
+    pub remote: bool,
+    root: Option<PathBuf>,
+    policy: AiPolicy,
+}
+
+impl Route {
+    /// The `requested` provider, or the open workspace's default
+    pub fn resolve(app: &AppHandle, requested: Option<&str>) -> Result<Route, String> {
+        let root = workspace::current_root(app);
+        let policy = match &root {
+            Some(root) => read_policy(root)?,
+            None => AiPolicy::default(),
+        };
+        let id = requested
+            .map(str::to_string)
+            .or_else(|| policy.default_provider.clone())
+            .unwrap_or_else(|| LOCAL.to_string());
+        let provider = match id.as_str() {
+            LOCAL => None,
+            _ => Some(
+                load(app)
+                    .into_iter()
+                    .find(|provider| provider.id == id)
+                    .ok_or_else(|| format!("AI provider not found: {}", id))?,
+            ),
+        };
+        let remote = provider
+            .as_ref()
+            .is_some_and(|provider| is_remote(&provider.base_url));
+        Ok(Route {
+            id,
+            provider,
+            remote,
+            root,
+            policy,
+        })
+    }
+
+    /// Whether the workspace lets content go to this provider at all
+    pub fn check_allowed(&self) -> Result<(), String> {
+        if !self.remote {
+            return Ok(());
+        }
+        if self.root.is_some() && !self.policy.allow_remote {
+            return Err(
+                "Invalid request: this workspace does not allow remote AI providers".to_string(),
+            );
+        }
+        if !self.policy.providers.is_empty() && !self.policy.providers.contains(&self.id) {
+            return Err(format!(
+                "Invalid request: this workspace does not allow the provider {}",
+                self.id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether content of the document at `path` may be included in a request
+    pub fn allows(&self, path: &Path) -> bool {
+        if !self.remote {
+            return true;
+        }
+        self.check_allowed().is_ok()
+            && document::read_board(path).is_ok_and(|board| {
+                shareable(
+                    self.root.as_deref(),
+                    &self.policy,
+                    path,
+                    &board.to_markdown(),
+                )
+                .is_ok()
+            })
+    }
+}
+
+/// Stream a chat completion from an OpenAI-compatible endpoint, emitting `llm:token` events
+fn complete_remote(
+    app: &AppHandle,
+    provider: &ProviderConfig,
+    model: &str,
+    messages: Vec<Value>,
+    request: &AiRequest,
+    op_id: &Option<String>,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+        "stream": true,
+    });
+    if let Some(max_tokens) = request.max_tokens {
+        body["max_tokens"] = json!(max_tokens);
+    }
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    let url = format!(
+        "{}/chat/completions",
+        provider.base_url.trim_end_matches('/')
+    );
+    let mut call = http::download_agent()
+        .post(&url)
+        .header("content-type", "application/json")
+        .header("accept", "text/event-stream");
+    if let Some(key) = secrets::get_app(&key_name(&provider.id))? {
+        call = call.header("authorization", format!("Bearer {}", key));
+    }
+    let mut response = call
+        .send(body.to_string())
+        .map_err(|e| format!("Request to {} failed: {}", provider.name, e))?;
+    let reader = BufReader::new(response.body_mut().as_reader());
+
+    let mut reply = String::new();
+    for line in reader.lines() {
+        if cancel.is_cancelled() {
+            return Err(cancel::CANCELLED.to_string());
+        }
+        let line = line.map_err(|e| format!("Failed to read reply: {}", e))?;
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            continue;
+        };
+        if data == "[DONE]" {
+            break;
+        }
+        let chunk: Value =
+            serde_json::from_str(data).map_err(|e| format!("Invalid reply: {}", e))?;
+        if let Some(text) = chunk["choices"][0]["delta"]["content"].as_str() {
+            llm::emit_token(app, op_id, text);
+            reply.push_str(text);
+        }
+    }
+    Ok(reply.trim().to_string())
+}
+
+/// Route a request to its provider under the open workspace's policy
+pub fn complete(
+    app: &AppHandle,
+    request: AiRequest,
+    op_id: Option<String>,
+    cancel: &CancelToken,
+) -> Result<AiCompletion, String> {
+    let route = Route::resolve(app, request.provider.as_deref())?;
+    let mut documents: Vec<(PathBuf, String)> = Vec::new();
+    for path in &request.documents {
+        let path = paths::check(app, path, paths::Scope::Read).map_err(|e| e.message)?;
+        let markdown = document::read_board(&path)?.to_markdown();
+        documents.push((path, markdown));
+    }
+    if route.remote {
+        route.check_allowed()?;
+        if let Some(source) = &request.source {
+            let path = paths::check(app, source, paths::Scope::Read).map_err(|e| e.message)?;
+            let markdown = document::read_board(&path)?.to_markdown();
+            shareable(route.root.as_deref(), &route.policy, &path, &markdown)?;
+        }
+        for (path, markdown) in &documents {
+            shareable(route.root.as_deref(), &route.policy, path, markdown)?;
+        }
+    }
+    let Route {
+        provider,
+        remote,
+        root,
+        ..
+    } = route;
+
+    let mut prompt = request.prompt.clone();
+    for (path, markdown) in &documents {
+        let name = root
+            .as_deref()
+            .map(|root| workspace::relative_path(root, path))
+            .unwrap_or_else(|| path.display().to_string());
+        prompt.push_str(&format!("\n\n--- {} ---\n{}", name, markdown));
+    }
+
+    let Some(provider) = provider else {
+        let text = llm::generate(
+            app,
+            request.model.as_deref(),
+            request.system.as_deref().unwrap_or_default(),
+            &prompt,
+            op_id,
+            cancel,
+        )?;
+        return Ok(AiCompletion {
+            provider: LOCAL.to_string(),
+            model: request.model,
+            text,
+            remote: false,
+        });
+    };
+    let mut messages = Vec::new();
+    if let Some(system) = &request.system {
+        messages.push(json!({"role": "system", "content": system}));
+    }
+    messages.push(json!({"role": "user", "content": prompt}));
+    let model = request.model.clone().unwrap_or(provider.model.clone());
+    tracing::info!(provider = %provider.id, remote, "Sending AI request");
+    let text = complete_remote(app, &provider, &model, messages, &request, &op_id, cancel)?;
+    Ok(AiCompletion {
+        provider: provider.id,
+        model: Some(model),
+        text,
+        remote,
+    })
+}
+
+/// The on-device provider and the configured endpoints
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_ai_providers(app: AppHandle) -> Result<Vec<Provider>, Error> {
+    let mut providers = vec![Provider {
+        config: ProviderConfig {
+            id: LOCAL.to_string(),
+            name: "On-device model".to_string(),
+            base_url: String::new(),
+            model: String::new(),
+        },
+        has_key: false,
+        remote: false,
+    }];
+    for config in load(&app) {
+        providers.push(Provider {
+            has_key: secrets::get_app(&key_name(&config.id))?.is_some(),
+            remote: is_remote(&config.base_url),
+            config,
+        });
+    }
+    Ok(providers)
+}
+
+/// Add or update a provider; `api_key` replaces the stored key, and an empty one removes it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_ai_provider(
+    app: AppHandle,
+    mut provider: ProviderConfig,
+    api_key: Option<String>,
+) -> Result<Provider, Error> {
+    if provider.id == LOCAL {
+        return Err("Invalid provider: the on-device provider is built in".into());
+    }
+    if provider.name.trim().is_empty() || provider.model.trim().is_empty() {
+        return Err("Invalid provider: a name and model are required".into());
+    }
+    check_url(&provider.base_url)?;
+    if provider.id.is_empty() {
+        provider.id = create_id("provider");
+    }
+    let mut providers = load(&app);
+    match providers.iter_mut().find(|p| p.id == provider.id) {
+        Some(existing) => *existing = provider.clone(),
+        None => providers.push(provider.clone()),
+    }
+    save(&app, &providers)?;
+    match api_key.as_deref().map(str::trim) {
+        Some("") => {
+            secrets::delete_app(&key_name(&provider.id))?;
+        }
+        Some(key) => secrets::store_app(&key_name(&provider.id), key)?,
+        None => {}
+    }
+    Ok(Provider {
+        has_key: secrets::get_app(&key_name(&provider.id))?.is_some(),
+        remote: is_remote(&provider.base_url),
+        config: provider,
+    })
+}
+
+/// Remove a provider and its key; `false` when it did not exist
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_ai_provider(app: AppHandle, id: String) -> Result<bool, Error> {
+    let mut providers = load(&app);
+    let count = providers.len();
+    providers.retain(|provider| provider.id != id);
+    if providers.len() == count {
+        return Ok(false);
+    }
+    save(&app, &providers)?;
+    secrets::delete_app(&key_name(&id))?;
+    Ok(true)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_ai_policy(app: AppHandle, workspace: String) -> Result<AiPolicy, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(read_policy(&root)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_ai_policy(app: AppHandle, workspace: String, policy: AiPolicy) -> Result<(), Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    Ok(workspace::write_config(&root, POLICY_CONFIG, &policy)?)
+}
+
+/// Complete a prompt with the on-device model or a configured provider, streaming `llm:token`
+/// events. Remote providers only receive content the open workspace's policy allows.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn ai_complete(
+    app: AppHandle,
+    request: AiRequest,
+    op_id: Option<String>,
+) -> Result<AiCompletion, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = cancel::begin(&app, op_id.clone());
+        complete(&app, request, op_id, operation.token()).map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("AI task failed: {}", e))?
+}
@@ -0,0 +1,554 @@
+use crate::document::{self, now_millis, BoardFile};
+use crate::error::Error;
+use crate::sanitize::{self, SanitizePolicy};
+use crate::site::{self, escape};
+use crate::{http, paths, read_only, secrets, vault, workspace};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+use url::Url;
+
+const CONFIG: &str = "published";
+const CONTENT_TYPE: &str = "text/html; charset=utf-8";
+const NETLIFY_API: &str = "https://api.netlify.com/api/v1";
+
+/// Where a page is uploaded. Credentials are names of workspace secrets (see
+/// [`crate::secrets`]), so this can be kept alongside the publication.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PublishTarget {
+    /// A server of your own: the page is `PUT` to `<url>/<file>` and removed with `DELETE`. A
+    /// JSON reply with a `url` field overrides the public URL.
+    Endpoint {
+        url: String,
+        /// Where `<file>` is served from, when not `url`
+        public_url: Option<String>,
+        /// Secret holding a bearer token
+        token_secret: Option<String>,
+    },
+    /// An S3 bucket, or an S3-compatible store at `endpoint`; the bucket must allow public reads
+    S3 {
+        bucket: String,
+        region: String,
+        /// Path-style endpoint such as `https://minio.example.com`; AWS when omitted
+        endpoint: Option<String>,
+        /// Key prefix, such as `notes/`
+        prefix: Option<String>,
+        /// Where objects are served from, such as a CDN, when not the bucket URL
+        public_url: Option<String>,
+        access_key_id: String,
+        /// Secret holding the secret access key
+        access_key_secret: String,
+    },
+    /// A Netlify site, deployed with the file digest API. Files already on the site are kept.
+    Netlify {
+        site_id: String,
+        /// Secret holding a personal access token
+        token_secret: String,
+    },
+}
+
+/// A published document, kept in `.inkfinite/published.json` by board id
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Publication {
+    pub document: String,
+    /// Workspace-relative path when last published
+    pub path: String,
+    pub name: String,
+    pub target: PublishTarget,
+    /// File name on the target, kept across updates so the link stays the same
+    pub file: String,
+    pub url: String,
+    /// SHA-1 of the uploaded page, hex
+    pub hash: String,
+    pub published_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Published {
+    documents: BTreeMap<String, Publication>,
+}
+
+fn secret(root: &Path, name: &str) -> Result<String, String> {
+    secrets::get(root, name)?.ok_or_else(|| format!("Secret not found: {}", name))
+}
+
+/// Render a document as a single HTML page with its local images inlined, cleaned with the
+/// workspace's sanitize policy
+pub fn render(root: &Path, path: &Path, board: &BoardFile, policy: &SanitizePolicy) -> String {
+    let doc_dir = path.parent().unwrap_or(root);
+    let markdown = board.to_markdown();
+    let parser = Parser::new_ext(&markdown, Options::all()).map(|event| match event {
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = inline_image(root, &dest_url, doc_dir)
+                .map(CowStr::from)
+                .unwrap_or(dest_url);
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        other => other,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, parser);
+    let body = sanitize::clean_with_schemes(policy, &body, &["data"]);
+
+    format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta property=\"og:title\" content=\"{}\">\n<title>{}</title>\n<style>{}</style>\n\
+         </head>\n<body>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape(&board.board.name),
+        escape(&board.board.name),
+        site::BASE_CSS,
+        body
+    )
+}
+
+/// `data:` URL for a local image inside the workspace
+fn inline_image(root: &Path, url: &str, doc_dir: &Path) -> Option<String> {
+    if url.contains("://") || url.starts_with("data:") || url.starts_with('/') {
+        return None;
+    }
+    let source = doc_dir.join(url).canonicalize().ok()?;
+    if !source.starts_with(root.canonicalize().ok()?) {
+        return None;
+    }
+    let extension = source.extension()?.to_string_lossy().to_ascii_lowercase();
+    let mime = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    };
+    let bytes = fs::read(&source).ok()?;
+    Some(format!("data:{};base64,{}", mime, BASE64.encode(bytes)))
+}
+
+/// Upload `page` as `file`, returning its public URL
+fn upload(root: &Path, target: &PublishTarget, file: &str, page: &[u8]) -> Result<String, String> {
+    let agent = http::agent();
+    match target {
+        PublishTarget::Endpoint {
+            url,
+            public_url,
+            token_secret,
+        } => {
+            let location = format!("{}/{}", url.trim_end_matches('/'), encode(file));
+            let mut request = agent.put(&location).header("content-type", CONTENT_TYPE);
+            if let Some(name) = token_secret {
+                request =
+                    request.header("authorization", format!("Bearer {}", secret(root, name)?));
+            }
+            let mut response = request
+                .send(page)
+                .map_err(|e| format!("Failed to upload page: {}", e))?;
+            let reply = response.body_mut().read_to_string().unwrap_or_default();
+            let url = serde_json::from_str::<serde_json::Value>(&reply)
+                .ok()
+                .and_then(|reply| reply.get("url")?.as_str().map(str::to_string));
+            Ok(match (url, public_url) {
+                (Some(url), _) => url,
+                (None, Some(base)) => format!("{}/{}", base.trim_end_matches('/'), encode(file)),
+                (None, None) => location,
+            })
+        }
+        PublishTarget::S3 { public_url, .. } => {
+            let object = s3_request(root, target, "PUT", file, page)?;
+            Ok(match public_url {
+                Some(base) => format!(
+                    "{}/{}",
+                    base.trim_end_matches('/'),
+                    object_key(target, file)
+                ),
+                None => object,
+            })
+        }
+        PublishTarget::Netlify {
+            site_id,
+            token_secret,
+        } => {
+            let token = secret(root, token_secret)?;
+            let hash = sha1_hex(page);
+            let mut files = netlify_files(&agent, site_id, &token)?;
+            files.insert(format!("/{}", file), hash.clone());
+            let deploy = netlify_deploy(&agent, site_id, &token, &files)?;
+            if deploy.required.contains(&hash) {
+                agent
+                    .put(&format!(
+                        "{}/deploys/{}/files/{}",
+                        NETLIFY_API,
+                        deploy.id,
+                        encode(file)
+                    ))
+                    .header("authorization", format!("Bearer {}", token))
+                    .header("content-type", "application/octet-stream")
+                    .send(page)
+                    .map_err(|e| format!("Failed to upload page: {}", e))?;
+            }
+            let base = deploy.ssl_url.or(deploy.url).unwrap_or_default();
+            Ok(format!("{}/{}", base.trim_end_matches('/'), encode(file)))
+        }
+    }
+}
+
+/// Take `file` down; one that is already gone counts as removed
+fn remove(root: &Path, target: &PublishTarget, file: &str) -> Result<(), String> {
+    let agent = http::agent();
+    match target {
+        PublishTarget::Endpoint {
+            url, token_secret, ..
+        } => {
+            let location = format!("{}/{}", url.trim_end_matches('/'), encode(file));
+            let mut request = agent.delete(&location);
+            if let Some(name) = token_secret {
+                request =
+                    request.header("authorization", format!("Bearer {}", secret(root, name)?));
+            }
+            match request.call() {
+                Ok(_) | Err(ureq::Error::StatusCode(404)) => Ok(()),
+                Err(e) => Err(format!("Failed to remove page: {}", e)),
+            }
+        }
+        PublishTarget::S3 { .. } => s3_request(root, target, "DELETE", file, &[]).map(|_| ()),
+        PublishTarget::Netlify {
+            site_id,
+            token_secret,
+        } => {
+            let token = secret(root, token_secret)?;
+            let mut files = netlify_files(&agent, site_id, &token)?;
+            if files.remove(&format!("/{}", file)).is_some() {
+                netlify_deploy(&agent, site_id, &token, &files)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Percent-encode everything but unreserved characters and `/`, as SigV4 expects
+fn encode(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn object_key(target: &PublishTarget, file: &str) -> String {
+    let prefix = match target {
+        PublishTarget::S3 {
+            prefix: Some(prefix),
+            ..
+        } => prefix.trim_matches('/'),
+        _ => "",
+    };
+    if prefix.is_empty() {
+        encode(file)
+    } else {
+        format!("{}/{}", encode(prefix), encode(file))
+    }
+}
+
+fn sha1_hex(bytes: &[u8]) -> String {
+    format!("{:x}", sha1::Sha1::digest(bytes))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+        .as_ref()
+        .to_vec()
+}
+
+/// Send an AWS Signature Version 4 signed request for `file`, returning the object URL
+fn s3_request(
+    root: &Path,
+    target: &PublishTarget,
+    method: &str,
+    file: &str,
+    body: &[u8],
+) -> Result<String, String> {
+    let PublishTarget::S3 {
+        bucket,
+        region,
+        endpoint,
+        access_key_id,
+        access_key_secret,
+        ..
+    } = target
+    else {
+        return Err("Invalid publish target: not S3".to_string());
+    };
+    let key = object_key(target, file);
+    let location = match endpoint {
+        Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+        None => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key),
+    };
+    let url = Url::parse(&location).map_err(|e| format!("Invalid publish target: {}", e))?;
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let now = chrono::Utc::now();
+    let date = now.format("%Y%m%d").to_string();
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let payload = format!("{:x}", Sha256::digest(body));
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", payload.clone()),
+        ("x-amz-date", timestamp.clone()),
+    ];
+    if method == "PUT" {
+        headers.insert(0, ("content-type", CONTENT_TYPE.to_string()));
+    }
+    let signed = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        url.path(),
+        headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect::<String>(),
+        signed,
+        payload
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+        timestamp,
+        scope,
+        Sha256::digest(canonical.as_bytes())
+    );
+    let mut signing = format!("AWS4{}", secret(root, access_key_secret)?).into_bytes();
+    for part in [date.as_str(), region.as_str(), "s3", "aws4_request"] {
+        signing = hmac_sha256(&signing, part);
+    }
+    let signature: String = hmac_sha256(&signing, &to_sign)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key_id, scope, signed, signature
+    );
+
+    let agent = http::agent();
+    let result = if method == "PUT" {
+        let mut request = agent
+            .put(url.as_str())
+            .header("authorization", authorization);
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+        request.send(body)
+    } else {
+        let mut request = agent
+            .delete(url.as_str())
+            .header("authorization", authorization);
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+        request.call()
+    };
+    result.map_err(|e| format!("Failed to reach S3: {}", e))?;
+    Ok(location)
+}
+
+#[derive(Deserialize)]
+struct NetlifyFile {
+    path: String,
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct NetlifyDeploy {
+    id: String,
+    #[serde(default)]
+    required: Vec<String>,
+    ssl_url: Option<String>,
+    url: Option<String>,
+}
+
+/// Paths and SHA-1 digests of the files on a Netlify site's current deploy
+fn netlify_files(
+    agent: &ureq::Agent,
+    site: &str,
+    token: &str,
+) -> Result<BTreeMap<String, String>, String> {
+    let mut response = agent
+        .get(&format!("{}/sites/{}/files", NETLIFY_API, site))
+        .header("authorization", format!("Bearer {}", token))
+        .call()
+        .map_err(|e| format!("Failed to list site files: {}", e))?;
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to list site files: {}", e))?;
+    let files: Vec<NetlifyFile> =
+        serde_json::from_str(&body).map_err(|e| format!("Invalid Netlify reply: {}", e))?;
+    Ok(files
+        .into_iter()
+        .map(|file| (file.path, file.sha))
+        .collect())
+}
+
+/// Deploy exactly `files`; Netlify replies with the digests it does not have yet
+fn netlify_deploy(
+    agent: &ureq::Agent,
+    site: &str,
+    token: &str,
+    files: &BTreeMap<String, String>,
+) -> Result<NetlifyDeploy, String> {
+    let request = serde_json::to_vec(&serde_json::json!({ "files": files }))
+        .map_err(|e| format!("Failed to serialize deploy: {}", e))?;
+    let mut response = agent
+        .post(&format!("{}/sites/{}/deploys", NETLIFY_API, site))
+        .header("authorization", format!("Bearer {}", token))
+        .header("content-type", "application/json")
+        .send(&request[..])
+        .map_err(|e| format!("Failed to deploy site: {}", e))?;
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to deploy site: {}", e))?;
+    serde_json::from_str(&body).map_err(|e| format!("Invalid Netlify reply: {}", e))
+}
+
+fn publish(
+    app: &AppHandle,
+    path: &str,
+    server_config: Option<PublishTarget>,
+) -> Result<Publication, Error> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let resolved = paths::check(app, path, paths::Scope::Read)?;
+    read_only::ensure_writable(&root)?;
+    if vault::vault_of(&resolved).is_some() {
+        return Err("Publishing is not available for documents in a vault".into());
+    }
+    let board = document::read_board(&resolved)?;
+    let mut published: Published = workspace::read_config(&root, CONFIG)?;
+    let existing = published.documents.get(&board.board.id).cloned();
+    let target = server_config
+        .or_else(|| {
+            existing
+                .as_ref()
+                .map(|publication| publication.target.clone())
+        })
+        .ok_or("Document is not published yet: choose where to publish it")?;
+    // Updates keep the file name so the link stays the same
+    let file = existing
+        .as_ref()
+        .map(|publication| publication.file.clone())
+        .unwrap_or_else(|| {
+            let id = board.board.id.rsplit(':').next().unwrap_or_default();
+            format!(
+                "{}-{}.html",
+                site::slugify(&board.board.name),
+                &id[..id.len().min(8)]
+            )
+        });
+
+    let page = render(&root, &resolved, &board, &sanitize::load_policy(&root));
+    let url = upload(&root, &target, &file, page.as_bytes())?;
+    let now = now_millis();
+    let publication = Publication {
+        document: board.board.id.clone(),
+        path: workspace::relative_path(&root, &resolved),
+        name: board.board.name.clone(),
+        target,
+        file,
+        url,
+        hash: sha1_hex(page.as_bytes()),
+        published_at: existing.map_or(now, |publication| publication.published_at),
+        updated_at: now,
+    };
+    published
+        .documents
+        .insert(publication.document.clone(), publication.clone());
+    workspace::write_config(&root, CONFIG, &published)?;
+    tracing::info!("Document published");
+    Ok(publication)
+}
+
+fn unpublish(app: &AppHandle, path: &str) -> Result<bool, Error> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let resolved = paths::check(app, path, paths::Scope::Read)?;
+    read_only::ensure_writable(&root)?;
+    let board = document::read_board(&resolved)?;
+    let mut published: Published = workspace::read_config(&root, CONFIG)?;
+    let Some(publication) = published.documents.get(&board.board.id) else {
+        return Ok(false);
+    };
+    remove(&root, &publication.target, &publication.file)?;
+    published.documents.remove(&board.board.id);
+    workspace::write_config(&root, CONFIG, &published)?;
+    Ok(true)
+}
+
+/// Render the document at `path` to HTML and upload it to `server_config`, returning where it
+/// can be read. Publishing again updates the same link; without `server_config` the previous
+/// target is reused.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn publish_document(
+    app: AppHandle,
+    path: String,
+    server_config: Option<PublishTarget>,
+) -> Result<Publication, Error> {
+    tauri::async_runtime::spawn_blocking(move || publish(&app, &path, server_config))
+        .await
+        .map_err(|e| format!("Publish task failed: {}", e))?
+}
+
+/// Take a published document down; returns `false` when it was not published
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn unpublish_document(app: AppHandle, path: String) -> Result<bool, Error> {
+    tauri::async_runtime::spawn_blocking(move || unpublish(&app, &path))
+        .await
+        .map_err(|e| format!("Unpublish task failed: {}", e))?
+}
+
+/// Documents published from the open workspace, most recently updated first
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_publications(app: AppHandle) -> Result<Vec<Publication>, Error> {
+    let root = workspace::current_root(&app).ok_or("No workspace is open")?;
+    let published: Published = workspace::read_config(&root, CONFIG)?;
+    let mut publications: Vec<Publication> = published.documents.into_values().collect();
+    publications.sort_by_key(|publication| std::cmp::Reverse(publication.updated_at));
+    Ok(publications)
+}
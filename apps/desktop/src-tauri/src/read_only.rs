@@ -0,0 +1,95 @@
+use crate::error::Error;
+use crate::{messages, paths, workspace};
+use inkfinite_core::read_only;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_store::StoreExt;
+
+pub use inkfinite_core::read_only::{ensure_writable, is_read_only};
+
+/// Settings key holding the read-only workspace paths
+pub const STORE_KEY: &str = "readOnlyWorkspaces";
+
+/// Load the read-only workspaces from settings
+pub fn load(app: &AppHandle) {
+    let roots: Vec<PathBuf> = app
+        .store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(STORE_KEY))
+        .and_then(|value| serde_json::from_value::<Vec<String>>(value).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    read_only::set_roots(roots);
+}
+
+/// Whether a workspace was marked read-only
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_workspace_readonly(app: AppHandle, workspace: String) -> bool {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)
+        .is_ok_and(|root| is_read_only(&root))
+}
+
+/// Mark a workspace read-only, or writable again, after the user confirms in a native dialog
+/// so no script or stray call can flip it. Returns whether the workspace is now read-only.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn set_workspace_readonly(
+    app: AppHandle,
+    workspace: String,
+    read_only: bool,
+) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    if is_read_only(&root) == read_only {
+        return Ok(read_only);
+    }
+    let (title, message) = if read_only {
+        (
+            messages::text("read-only-on-title"),
+            messages::text("read-only-on-message"),
+        )
+    } else {
+        (
+            messages::text("read-only-off-title"),
+            messages::text("read-only-off-message"),
+        )
+    };
+    let dialog = app.clone();
+    let confirmed = tauri::async_runtime::spawn_blocking(move || {
+        dialog
+            .dialog()
+            .message(format!("{}\n\n{}", message, workspace))
+            .title(title)
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancel)
+            .blocking_show()
+    })
+    .await
+    .map_err(|e| format!("Failed to show dialog: {}", e))?;
+    if !confirmed {
+        return Ok(!read_only);
+    }
+
+    let mut roots = read_only::roots();
+    roots.retain(|existing| existing != &root);
+    if read_only {
+        roots.push(root);
+    }
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let stored: Vec<String> = roots
+        .iter()
+        .map(|root| root.to_string_lossy().to_string())
+        .collect();
+    store.set(STORE_KEY, serde_json::json!(stored));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    read_only::set_roots(roots);
+    tracing::info!(read_only, "Workspace read-only mode changed");
+    Ok(read_only)
+}
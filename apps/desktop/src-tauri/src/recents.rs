@@ -0,0 +1,194 @@
+#[cfg(any(windows, target_os = "macos"))]
+use crate::workspace::{self, RecentFile};
+use tauri::AppHandle;
+
+/// Recent documents shown in the taskbar jump list and dock menu
+#[cfg(any(windows, target_os = "macos"))]
+const MAX_RECENT: usize = 10;
+
+/// Mirror the recent files list into the Windows jump list and the macOS dock menu.
+///
+/// Both open entries through the same path as documents launched from the file manager, so
+/// the workspace is switched before the webview receives `deep-link:navigate`.
+#[cfg(any(windows, target_os = "macos"))]
+pub fn update(app: &AppHandle) {
+    let files: Vec<RecentFile> = workspace::recent_files(app)
+        .into_iter()
+        .take(MAX_RECENT)
+        .collect();
+
+    #[cfg(windows)]
+    let _ = jump_list::update(&files);
+    #[cfg(target_os = "macos")]
+    dock::update(app, files);
+}
+
+/// Other platforms have no shell-level recents to keep in sync
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn update(_app: &AppHandle) {}
+
+#[cfg(any(windows, target_os = "macos"))]
+fn display_name(file: &RecentFile) -> String {
+    file.name
+        .clone()
+        .unwrap_or_else(|| crate::document::document_stem(std::path::Path::new(&file.path)))
+}
+
+/// Jump list entries relaunch the app with the document path; the single-instance plugin
+/// forwards that to the running instance
+#[cfg(windows)]
+mod jump_list {
+    use super::display_name;
+    use crate::workspace::RecentFile;
+    use windows::core::{Interface, Result, HSTRING, PWSTR};
+    use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+    use windows::Win32::System::Com::StructuredStorage::PROPVARIANT;
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+    };
+    use windows::Win32::System::Variant::VT_LPWSTR;
+    use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+    use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+    use windows::Win32::UI::Shell::{
+        DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink,
+    };
+
+    pub fn update(files: &[RecentFile]) -> Result<()> {
+        let Ok(exe) = std::env::current_exe() else {
+            return Ok(());
+        };
+        let exe = HSTRING::from(exe.as_path());
+
+        unsafe {
+            // COM may already be initialized on this thread; either way the calls below work
+            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+            let list: ICustomDestinationList =
+                CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+            let mut slots = 0u32;
+            let _removed: IObjectArray = list.BeginList(&mut slots)?;
+
+            let items: IObjectCollection =
+                CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+            for file in files.iter().take(slots as usize) {
+                let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+                link.SetPath(&exe)?;
+                link.SetArguments(&HSTRING::from(format!("\"{}\"", file.path)))?;
+                link.SetDescription(&HSTRING::from(file.path.as_str()))?;
+                link.SetIconLocation(&exe, 0)?;
+
+                // Custom categories take their label from PKEY_Title, which must be VT_LPWSTR
+                let mut title: Vec<u16> = display_name(file).encode_utf16().chain([0]).collect();
+                let mut value = PROPVARIANT::default();
+                (*value.Anonymous.Anonymous).vt = VT_LPWSTR;
+                (*value.Anonymous.Anonymous).Anonymous.pwszVal = PWSTR(title.as_mut_ptr());
+                let store: IPropertyStore = link.cast()?;
+                store.SetValue(&PKEY_Title, &value)?;
+                store.Commit()?;
+
+                items.AddObject(&link)?;
+            }
+
+            let array: IObjectArray = items.cast()?;
+            list.AppendCategory(&HSTRING::from("Recent Documents"), &array)?;
+            list.CommitList()
+        }
+    }
+}
+
+/// AppKit asks the app delegate for the dock menu, so the delegate tao installs is taught to
+/// answer `applicationDockMenu:` and to open the chosen entry
+#[cfg(target_os = "macos")]
+mod dock {
+    use super::display_name;
+    use crate::workspace::RecentFile;
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyClass, AnyObject, Imp, Sel};
+    use objc2::{sel, MainThreadMarker};
+    use objc2_app_kit::{NSApplication, NSMenu, NSMenuItem};
+    use objc2_foundation::NSString;
+    use std::sync::{Mutex, Once, OnceLock};
+    use tauri::AppHandle;
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+    /// Documents behind the dock menu entries, indexed by item tag
+    static ENTRIES: Mutex<Vec<RecentFile>> = Mutex::new(Vec::new());
+    static INSTALL: Once = Once::new();
+
+    pub fn update(app: &AppHandle, files: Vec<RecentFile>) {
+        let _ = APP.set(app.clone());
+        if let Ok(mut entries) = ENTRIES.lock() {
+            *entries = files;
+        }
+        let _ = app.run_on_main_thread(|| INSTALL.call_once(install));
+    }
+
+    fn install() {
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        let Some(delegate) = NSApplication::sharedApplication(mtm).delegate() else {
+            return;
+        };
+        let object: &AnyObject = delegate.as_ref();
+        let class = object.class() as *const AnyClass as *mut AnyClass;
+
+        unsafe {
+            objc2::ffi::class_addMethod(
+                class,
+                sel!(applicationDockMenu:),
+                std::mem::transmute::<
+                    extern "C-unwind" fn(&AnyObject, Sel, &AnyObject) -> *mut NSMenu,
+                    Imp,
+                >(dock_menu),
+                c"@@:@".as_ptr(),
+            );
+            objc2::ffi::class_addMethod(
+                class,
+                sel!(inkfiniteOpenRecent:),
+                std::mem::transmute::<extern "C-unwind" fn(&AnyObject, Sel, &NSMenuItem), Imp>(
+                    open_recent,
+                ),
+                c"v@:@".as_ptr(),
+            );
+        }
+    }
+
+    extern "C-unwind" fn dock_menu(this: &AnyObject, _cmd: Sel, _app: &AnyObject) -> *mut NSMenu {
+        // AppKit only asks for the dock menu on the main thread
+        let mtm = unsafe { MainThreadMarker::new_unchecked() };
+        let menu = NSMenu::new(mtm);
+        let entries = ENTRIES
+            .lock()
+            .map(|entries| entries.clone())
+            .unwrap_or_default();
+
+        for (index, file) in entries.iter().enumerate() {
+            let item = unsafe {
+                NSMenuItem::initWithTitle_action_keyEquivalent(
+                    mtm.alloc(),
+                    &NSString::from_str(&display_name(file)),
+                    Some(sel!(inkfiniteOpenRecent:)),
+                    &NSString::new(),
+                )
+            };
+            item.setTag(index as isize);
+            unsafe { item.setTarget(Some(this)) };
+            menu.addItem(&item);
+        }
+        Retained::autorelease_return(menu)
+    }
+
+    extern "C-unwind" fn open_recent(_this: &AnyObject, _cmd: Sel, sender: &NSMenuItem) {
+        let Some(app) = APP.get() else {
+            return;
+        };
+        let path = ENTRIES.lock().ok().and_then(|entries| {
+            entries
+                .get(sender.tag() as usize)
+                .map(|file| file.path.clone())
+        });
+        if let Some(path) = path {
+            crate::file_open::open_document(app, std::path::Path::new(&path));
+        }
+    }
+}
@@ -0,0 +1,1596 @@
+use crate::csl::{self, CslStyle};
+use crate::document;
+use crate::error::Error;
+use crate::{http, paths, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+use url::Url;
+
+const CONFIG: &str = "references";
+/// Zotero's local API, served by the desktop app while it runs (Zotero 7 and later)
+const ZOTERO_URL: &str = "http://127.0.0.1:23119";
+const ZOTERO_PAGE: usize = 100;
+const DEFAULT_LIMIT: usize = 20;
+
+/// Where a workspace's library comes from, so it can be refreshed
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LibrarySource {
+    /// A `.bib` BibTeX/BibLaTeX file or a CSL-JSON `.json` export
+    File { path: String },
+    /// The local Zotero app, at `url` when not the default port
+    Zotero { url: Option<String> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Name {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given: Option<String>,
+    /// An organisation, or a name that should not be split
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub literal: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CslDate {
+    #[serde(rename = "date-parts", default, skip_serializing_if = "Vec::is_empty")]
+    pub date_parts: Vec<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub literal: Option<String>,
+}
+
+impl CslDate {
+    fn year(&self) -> Option<String> {
+        let first = self.date_parts.first().and_then(|parts| parts.first());
+        match first {
+            Some(Value::Number(year)) => Some(year.to_string()),
+            Some(Value::String(year)) => Some(year.clone()),
+            _ => self
+                .raw
+                .as_deref()
+                .or(self.literal.as_deref())
+                .and_then(|raw| raw.get(..4))
+                .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+                .map(str::to_string),
+        }
+    }
+}
+
+/// A library item in CSL-JSON form, with the fields the built-in styles use and every other
+/// CSL variable kept for downloaded styles
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Reference {
+    /// Citation key
+    #[serde(deserialize_with = "text")]
+    pub id: String,
+    /// CSL item type, such as `article-journal`, `book` or `chapter`
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub author: Vec<Name>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub editor: Vec<Name>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued: Option<CslDate>,
+    #[serde(
+        rename = "container-title",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub container_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    #[serde(
+        rename = "publisher-place",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub publisher_place: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub volume: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub issue: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub page: Option<String>,
+    #[serde(rename = "DOI", default, skip_serializing_if = "Option::is_none")]
+    pub doi: Option<String>,
+    #[serde(rename = "URL", default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(rename = "ISBN", default, skip_serializing_if = "Option::is_none")]
+    pub isbn: Option<String>,
+    #[serde(rename = "abstract", default, skip_serializing_if = "Option::is_none")]
+    pub abstract_text: Option<String>,
+    /// Other CSL variables, such as `edition`, `translator` or `accessed`
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A string that CSL-JSON exports sometimes write as a number
+fn text<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(text) => Ok(text),
+        Value::Number(number) => Ok(number.to_string()),
+        other => Err(serde::de::Error::custom(format!(
+            "expected a string, found {}",
+            other
+        ))),
+    }
+}
+
+fn optional_text<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => text(value).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// The cached library in `.inkfinite/references.json`
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Library {
+    source: Option<LibrarySource>,
+    updated_at: Option<i64>,
+    items: Vec<Reference>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryInfo {
+    pub source: Option<LibrarySource>,
+    pub updated_at: Option<i64>,
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct CitationStyle {
+    pub id: String,
+    pub name: String,
+    /// A CSL style downloaded into the local cache rather than one built in
+    pub downloaded: bool,
+}
+
+#[derive(Serialize)]
+pub struct BibliographyEntry {
+    pub key: String,
+    /// The entry in Markdown
+    pub text: String,
+}
+
+#[derive(Serialize)]
+pub struct Bibliography {
+    pub style: String,
+    /// Entries in the style's order
+    pub entries: Vec<BibliographyEntry>,
+    /// The entries as one Markdown block, one paragraph each
+    pub markdown: String,
+    /// Keys cited in the document that are not in the library
+    pub missing: Vec<String>,
+}
+
+/// A formatted citation in Markdown
+#[derive(Serialize)]
+pub struct Citation {
+    /// In-text form, such as `(Smith & Jones, 2020, p. 4)`
+    pub inline: String,
+    /// Entry for the reference list
+    pub bibliography: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Apa,
+    Chicago,
+    Mla,
+    Harvard,
+}
+
+/// Formats references with a built-in style or a downloaded CSL style
+enum Formatter {
+    Builtin(Style),
+    Csl(Box<CslStyle>),
+}
+
+const STYLES: &[(&str, &str, Style)] = &[
+    ("apa", "APA 7th edition", Style::Apa),
+    ("chicago-author-date", "Chicago author-date", Style::Chicago),
+    ("mla", "MLA 9th edition", Style::Mla),
+    ("harvard", "Harvard (Cite Them Right)", Style::Harvard),
+];
+
+fn load(root: &Path) -> Result<Library, String> {
+    workspace::read_config(root, CONFIG)
+}
+
+fn info(library: &Library) -> LibraryInfo {
+    LibraryInfo {
+        source: library.source.clone(),
+        updated_at: library.updated_at,
+        count: library.items.len(),
+    }
+}
+
+/// Items of a CSL-JSON export, which is an array or, from Zotero, `{ "items": [...] }`
+fn parse_csl_json(text: &str) -> Result<Vec<Reference>, String> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| format!("Invalid CSL-JSON: {}", e))?;
+    let items = match value {
+        Value::Object(mut object) => object.remove("items").unwrap_or_default(),
+        other => other,
+    };
+    let Value::Array(items) = items else {
+        return Err("Invalid CSL-JSON: expected an array of items".to_string());
+    };
+    // Skip items that do not fit rather than failing the whole library
+    Ok(items
+        .into_iter()
+        .filter_map(|item| serde_json::from_value::<Reference>(item).ok())
+        .filter(|item| !item.id.is_empty())
+        .collect())
+}
+
+/// Minimal BibTeX/BibLaTeX reader: entries, `@string` macros and `#` concatenation
+struct BibParser<'a> {
+    text: &'a [u8],
+    at: usize,
+    strings: HashMap<String, String>,
+}
+
+impl BibParser<'_> {
+    fn skip_space(&mut self) {
+        while self.text.get(self.at).is_some_and(u8::is_ascii_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.at).copied()
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.at;
+        while self
+            .peek()
+            .is_some_and(|b| !b.is_ascii_whitespace() && !b"{}(),=#\"".contains(&b))
+        {
+            self.at += 1;
+        }
+        String::from_utf8_lossy(&self.text[start..self.at]).to_string()
+    }
+
+    /// Text up to the brace closing the one just consumed, keeping nested braces
+    fn braced(&mut self) -> String {
+        let start = self.at;
+        let mut depth = 1;
+        while let Some(b) = self.peek() {
+            match b {
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let text = String::from_utf8_lossy(&self.text[start..self.at]);
+                        self.at += 1;
+                        return text.to_string();
+                    }
+                }
+                _ => {}
+            }
+            self.at += 1;
+        }
+        String::from_utf8_lossy(&self.text[start..]).to_string()
+    }
+
+    fn quoted(&mut self) -> String {
+        let start = self.at;
+        let mut depth = 0;
+        while let Some(b) = self.peek() {
+            match b {
+                b'{' => depth += 1,
+                b'}' => depth -= 1,
+                b'"' if depth == 0 => {
+                    let text = String::from_utf8_lossy(&self.text[start..self.at]);
+                    self.at += 1;
+                    return text.to_string();
+                }
+                _ => {}
+            }
+            self.at += 1;
+        }
+        String::from_utf8_lossy(&self.text[start..]).to_string()
+    }
+
+    /// A field value: braced or quoted text, a number or a macro, joined with `#`
+    fn value(&mut self) -> String {
+        let mut value = String::new();
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some(b'{') => {
+                    self.at += 1;
+                    value.push_str(&self.braced());
+                }
+                Some(b'"') => {
+                    self.at += 1;
+                    value.push_str(&self.quoted());
+                }
+                Some(_) => {
+                    let word = self.identifier();
+                    match self.strings.get(&word.to_ascii_lowercase()) {
+                        Some(text) => value.push_str(text),
+                        None => value.push_str(&word),
+                    }
+                }
+                None => break,
+            }
+            self.skip_space();
+            if self.peek() == Some(b'#') {
+                self.at += 1;
+            } else {
+                break;
+            }
+        }
+        value
+    }
+
+    /// `name = value` pairs up to `close`, lower-casing names
+    fn fields(&mut self, close: u8) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        loop {
+            self.skip_space();
+            match self.peek() {
+                None => break,
+                Some(b) if b == close => {
+                    self.at += 1;
+                    break;
+                }
+                Some(b',') => {
+                    self.at += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let name = self.identifier().to_ascii_lowercase();
+            self.skip_space();
+            if name.is_empty() || self.peek() != Some(b'=') {
+                // Not a field; step over the byte so a malformed entry cannot stall the parser
+                self.at += 1;
+                continue;
+            }
+            self.at += 1;
+            fields.push((name, self.value()));
+        }
+        fields
+    }
+
+    fn entries(&mut self) -> Vec<Reference> {
+        let months = [
+            "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+        ];
+        for (i, month) in months.iter().enumerate() {
+            self.strings.insert(month.to_string(), (i + 1).to_string());
+        }
+
+        let mut entries = Vec::new();
+        while let Some(offset) = self.text[self.at..].iter().position(|&b| b == b'@') {
+            self.at += offset + 1;
+            let kind = self.identifier().to_ascii_lowercase();
+            self.skip_space();
+            let close = match self.peek() {
+                Some(b'{') => b'}',
+                Some(b'(') => b')',
+                _ => continue,
+            };
+            self.at += 1;
+            match kind.as_str() {
+                "comment" | "preamble" => {
+                    self.braced();
+                }
+                "string" => {
+                    for (name, value) in self.fields(close) {
+                        self.strings.insert(name, value);
+                    }
+                }
+                _ => {
+                    self.skip_space();
+                    let key = self.identifier();
+                    let fields = self.fields(close);
+                    if !key.is_empty() {
+                        entries.push(from_bibtex(&kind, key, fields));
+                    }
+                }
+            }
+        }
+        entries
+    }
+}
+
+fn parse_bibtex(text: &str) -> Vec<Reference> {
+    BibParser {
+        text: text.as_bytes(),
+        at: 0,
+        strings: HashMap::new(),
+    }
+    .entries()
+}
+
+/// Plain text for a LaTeX-encoded value: common accents, escapes and dashes, braces removed
+fn clean_latex(text: &str) -> String {
+    /// Accent commands, the letters they apply to and the accented forms, position by position
+    const ACCENTS: &[(char, &str, &str)] = &[
+        ('"', "aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+        ('\'', "aeiouycnszAEIOUYCNSZ", "áéíóúýćńśźÁÉÍÓÚÝĆŃŚŹ"),
+        ('`', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+        ('^', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+        ('~', "anoANO", "ãñõÃÑÕ"),
+        ('c', "csCS", "çşÇŞ"),
+        ('v', "csznrCSZNR", "čšžňřČŠŽŇŘ"),
+    ];
+    let chars: Vec<char> = text.chars().collect();
+    let mut plain = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '{' | '}' => i += 1,
+            '~' => {
+                plain.push('\u{a0}');
+                i += 1;
+            }
+            '\\' => {
+                let next = chars.get(i + 1).copied();
+                let accent =
+                    next.and_then(|next| ACCENTS.iter().find(|(mark, _, _)| *mark == next));
+                // `\c c` is an accent but `\cite` is a command
+                let letter_command = next.is_some_and(char::is_alphabetic)
+                    && chars.get(i + 2).is_some_and(char::is_ascii_alphabetic);
+                if let Some((_, from, to)) = accent.filter(|_| !letter_command) {
+                    let mut j = i + 2;
+                    while chars.get(j).is_some_and(|c| *c == '{' || *c == ' ') {
+                        j += 1;
+                    }
+                    if let Some(letter) = chars.get(j) {
+                        match from.chars().position(|f| f == *letter) {
+                            Some(index) => plain.extend(to.chars().nth(index)),
+                            None => plain.push(*letter),
+                        }
+                        i = j + 1;
+                        continue;
+                    }
+                }
+                let command: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .collect();
+                match command.as_str() {
+                    "ss" => plain.push('ß'),
+                    "o" => plain.push('ø'),
+                    "O" => plain.push('Ø'),
+                    "aa" => plain.push('å'),
+                    "AA" => plain.push('Å'),
+                    "ae" => plain.push('æ'),
+                    "l" => plain.push('ł'),
+                    "L" => plain.push('Ł'),
+                    "TeX" => plain.push_str("TeX"),
+                    "LaTeX" => plain.push_str("LaTeX"),
+                    // An escaped symbol such as `\&` or `\%`
+                    "" => plain.extend(next),
+                    // Formatting commands like `\emph` keep only their argument
+                    _ => {}
+                }
+                i += command.chars().count().max(1) + 1;
+                // Spaces after a command word only end it, as in `Stra\ss e`
+                if !command.is_empty() {
+                    while chars.get(i).is_some_and(|c| *c == ' ') {
+                        i += 1;
+                    }
+                }
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                let em = chars.get(i + 2) == Some(&'-');
+                plain.push(if em { '—' } else { '–' });
+                i += if em { 3 } else { 2 };
+            }
+            c if c.is_whitespace() => {
+                if !plain.ends_with(' ') {
+                    plain.push(' ');
+                }
+                i += 1;
+            }
+            c => {
+                plain.push(c);
+                i += 1;
+            }
+        }
+    }
+    plain.trim().to_string()
+}
+
+/// Split at top-level occurrences of `separator`, ignoring any inside braces
+fn split_top(text: &str, separator: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let lower = text.to_ascii_lowercase();
+    let mut i = 0;
+    while i < text.len() {
+        match text.as_bytes()[i] {
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            _ if depth == 0 && lower[i..].starts_with(separator) => {
+                parts.push(text[start..i].to_string());
+                i += separator.len();
+                start = i;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    parts.push(text[start..].to_string());
+    parts
+}
+
+/// BibTeX names: `First von Last`, `von Last, First` or `{Organisation}`, joined by `and`
+fn parse_names(value: &str) -> Vec<Name> {
+    split_top(value.trim(), " and ")
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            if name.starts_with('{') && name.ends_with('}') && split_top(&name, " ").len() == 1 {
+                return Name {
+                    literal: Some(clean_latex(&name)),
+                    ..Default::default()
+                };
+            }
+            let parts = split_top(&name, ",");
+            if parts.len() > 1 {
+                return Name {
+                    family: Some(clean_latex(&parts[0])),
+                    given: parts.last().map(|given| clean_latex(given)),
+                    literal: None,
+                };
+            }
+            let words: Vec<String> = split_top(&name, " ")
+                .into_iter()
+                .filter(|word| !word.is_empty())
+                .collect();
+            // The family name starts at the first lower-case particle, or is the last word
+            let split = words[..words.len() - 1]
+                .iter()
+                .position(|word| word.starts_with(|c: char| c.is_lowercase()))
+                .unwrap_or(words.len() - 1);
+            Name {
+                family: Some(clean_latex(&words[split..].join(" "))),
+                given: Some(clean_latex(&words[..split].join(" "))).filter(|g| !g.is_empty()),
+                literal: None,
+            }
+        })
+        .collect()
+}
+
+fn from_bibtex(kind: &str, key: String, fields: Vec<(String, String)>) -> Reference {
+    let fields: HashMap<String, String> = fields.into_iter().collect();
+    let text = |name: &str| {
+        fields
+            .get(name)
+            .map(|value| clean_latex(value))
+            .filter(|value| !value.is_empty())
+    };
+    let pick = |names: &[&str]| names.iter().find_map(|name| text(name));
+
+    let issued = match text("date") {
+        Some(date) => {
+            let parts: Vec<Value> = date
+                .split(['-', '/'])
+                .filter_map(|part| part.trim().parse::<u32>().ok())
+                .map(Value::from)
+                .collect();
+            Some(CslDate {
+                date_parts: if parts.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![parts]
+                },
+                raw: Some(date),
+                literal: None,
+            })
+        }
+        None => text("year").map(|year| {
+            let mut parts = vec![year
+                .parse::<u32>()
+                .map(Value::from)
+                .unwrap_or(Value::from(year.clone()))];
+            if let Some(month) = text("month").and_then(|month| month.parse::<u32>().ok()) {
+                parts.push(Value::from(month));
+            }
+            CslDate {
+                date_parts: vec![parts],
+                raw: None,
+                literal: None,
+            }
+        }),
+    };
+    let kind = match kind {
+        "article" => "article-journal",
+        "book" | "mvbook" => "book",
+        "inbook" | "incollection" | "inreference" => "chapter",
+        "inproceedings" | "conference" => "paper-conference",
+        "phdthesis" | "mastersthesis" | "thesis" => "thesis",
+        "techreport" | "report" => "report",
+        "online" | "electronic" | "www" => "webpage",
+        "manual" | "booklet" | "unpublished" | "misc" => "document",
+        _ => "document",
+    };
+    Reference {
+        id: key,
+        kind: kind.to_string(),
+        title: text("title").unwrap_or_default(),
+        author: fields
+            .get("author")
+            .map(|a| parse_names(a))
+            .unwrap_or_default(),
+        editor: fields
+            .get("editor")
+            .map(|e| parse_names(e))
+            .unwrap_or_default(),
+        issued,
+        container_title: pick(&["journaltitle", "journal", "booktitle"]),
+        publisher: pick(&["publisher", "school", "institution", "organization"]),
+        publisher_place: pick(&["location", "address"]),
+        volume: text("volume"),
+        issue: pick(&["number", "issue"]),
+        page: text("pages"),
+        doi: text("doi"),
+        url: text("url"),
+        isbn: text("isbn"),
+        abstract_text: text("abstract"),
+        other: [
+            ("edition", text("edition")),
+            ("collection-title", text("series")),
+            ("number-of-pages", text("pagetotal")),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.to_string(), Value::String(value?))))
+        .collect(),
+    }
+}
+
+fn fetch_zotero(url: Option<&str>) -> Result<Vec<Reference>, String> {
+    let base = Url::parse(url.unwrap_or(ZOTERO_URL))
+        .and_then(|base| base.join("/api/users/0/items/top"))
+        .map_err(|e| format!("Invalid Zotero URL: {}", e))?;
+    let agent = http::agent();
+    let mut items = Vec::new();
+    loop {
+        let mut page = base.clone();
+        page.query_pairs_mut()
+            .append_pair("format", "csljson")
+            .append_pair("limit", &ZOTERO_PAGE.to_string())
+            .append_pair("start", &items.len().to_string());
+        let body = agent
+            .get(page.as_str())
+            .call()
+            .map_err(|e| {
+                format!(
+                    "Failed to reach Zotero (is it running with the local API enabled?): {}",
+                    e
+                )
+            })?
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| format!("Failed to read from Zotero: {}", e))?;
+        let batch = parse_csl_json(&body)?;
+        let done = batch.len() < ZOTERO_PAGE;
+        items.extend(batch);
+        if done {
+            return Ok(items);
+        }
+    }
+}
+
+fn read_source(source: &LibrarySource) -> Result<Vec<Reference>, String> {
+    match source {
+        LibrarySource::File { path } => {
+            let text =
+                fs::read_to_string(path).map_err(|e| format!("Failed to read library: {}", e))?;
+            let json = Path::new(path)
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+            if json {
+                parse_csl_json(&text)
+            } else {
+                Ok(parse_bibtex(&text))
+            }
+        }
+        LibrarySource::Zotero { url } => fetch_zotero(url.as_deref()),
+    }
+}
+
+fn import(root: &Path, source: LibrarySource) -> Result<LibraryInfo, String> {
+    let mut items = read_source(&source)?;
+    items.sort_by(|a, b| a.id.cmp(&b.id));
+    items.dedup_by(|a, b| a.id == b.id);
+    let library = Library {
+        source: Some(source),
+        updated_at: Some(document::now_millis()),
+        items,
+    };
+    workspace::write_config(root, CONFIG, &library)?;
+    Ok(info(&library))
+}
+
+/// How well `reference` matches every term, weighting authors and titles; `None` on a miss
+fn score(reference: &Reference, terms: &[String]) -> Option<u32> {
+    let authors = reference
+        .author
+        .iter()
+        .chain(&reference.editor)
+        .map(|name| {
+            [&name.family, &name.given, &name.literal]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let title = reference.title.to_lowercase();
+    let rest = [
+        Some(&reference.id),
+        reference.container_title.as_ref(),
+        reference.publisher.as_ref(),
+        reference.doi.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|text| text.to_lowercase())
+    .collect::<Vec<_>>()
+    .join(" ");
+    let year = reference
+        .issued
+        .as_ref()
+        .and_then(CslDate::year)
+        .unwrap_or_default();
+
+    let mut total = 0;
+    for term in terms {
+        total += if reference.id.eq_ignore_ascii_case(term) {
+            10
+        } else if authors.contains(term.as_str()) {
+            4
+        } else if title.contains(term.as_str()) {
+            3
+        } else if year == *term {
+            2
+        } else if rest.contains(term.as_str()) {
+            1
+        } else {
+            return None;
+        };
+    }
+    Some(total)
+}
+
+fn family(name: &Name) -> String {
+    name.family
+        .clone()
+        .or_else(|| name.literal.clone())
+        .or_else(|| name.given.clone())
+        .unwrap_or_default()
+}
+
+/// Initials of given names, as in `J. A.`, joined without spaces when `tight`
+fn initials(given: &str, tight: bool) -> String {
+    given
+        .split([' ', '\u{a0}'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.split('-')
+                .filter_map(|piece| piece.chars().next())
+                .map(|c| format!("{}.", c))
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect::<Vec<_>>()
+        .join(if tight { "" } else { " " })
+}
+
+/// A name as the style lists it: `Family, G.` in APA and Harvard, otherwise `Family, Given`
+/// when `inverted` and `Given Family` when not
+fn full_name(name: &Name, style: Style, inverted: bool) -> String {
+    let family = family(name);
+    let Some(given) = name.given.as_deref().filter(|_| name.family.is_some()) else {
+        return family;
+    };
+    match style {
+        Style::Apa => format!("{}, {}", family, initials(given, false)),
+        Style::Harvard => format!("{}, {}", family, initials(given, true)),
+        _ if inverted => format!("{}, {}", family, given),
+        _ => format!("{} {}", given, family),
+    }
+}
+
+/// Editors in running text, as in `J. Smith` (APA) or `John Smith`
+fn editor_names(names: &[Name], style: Style) -> String {
+    let names: Vec<String> = names
+        .iter()
+        .map(|name| match (&name.given, style) {
+            (Some(given), Style::Apa) if name.family.is_some() => {
+                format!("{} {}", initials(given, false), family(name))
+            }
+            _ => full_name(name, style, false),
+        })
+        .collect();
+    join_list(&names, if style == Style::Apa { "&" } else { "and" }, true)
+}
+
+/// `a and b`, or `a, b, and c` with a serial comma
+fn join_list(items: &[String], and: &str, serial: bool) -> String {
+    match items {
+        [] => String::new(),
+        [one] => one.clone(),
+        [first, second] => format!("{} {} {}", first, and, second),
+        [rest @ .., last] => format!(
+            "{}{} {} {}",
+            rest.join(", "),
+            if serial { "," } else { "" },
+            and,
+            last
+        ),
+    }
+}
+
+/// Authors or editors leading a reference list entry
+fn author_list(names: &[Name], style: Style) -> String {
+    let formatted: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| full_name(name, style, i == 0))
+        .collect();
+    let and = if style == Style::Apa { "&" } else { "and" };
+    match (style, formatted.as_slice()) {
+        (Style::Apa, [first @ .., _, last]) if first.len() >= 19 => {
+            format!("{}, … {}", first[..19].join(", "), last)
+        }
+        (Style::Mla, [first, _, _, ..]) => format!("{}, et al.", first),
+        (Style::Harvard, _) => join_list(&formatted, and, false),
+        // The first name is inverted, so a comma comes before the conjunction even for two
+        (_, [first, second]) => format!("{}, {} {}", first, and, second),
+        _ => join_list(&formatted, and, true),
+    }
+}
+
+/// Escape Markdown emphasis characters in item text
+pub fn md(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('*', "\\*")
+        .replace('_', "\\_")
+}
+
+fn italic(text: &str) -> String {
+    format!("*{}*", md(text))
+}
+
+/// Append `.` unless the text already ends in punctuation, looking past closing italics
+fn period(text: &str) -> String {
+    if text.trim_end_matches('*').ends_with(['.', '?', '!']) {
+        text.to_string()
+    } else {
+        format!("{}.", text)
+    }
+}
+
+pub fn pages(page: &str) -> String {
+    page.replace("--", "–").replace('-', "–")
+}
+
+fn page_label(page: &str) -> String {
+    if page.contains(['-', '–', ',']) {
+        format!("pp. {}", pages(page))
+    } else {
+        format!("p. {}", page)
+    }
+}
+
+fn plural(names: &[Name]) -> &'static str {
+    if names.len() > 1 {
+        "s"
+    } else {
+        ""
+    }
+}
+
+/// Whether the title is a work of its own, set in italics, rather than part of a container
+fn is_standalone(reference: &Reference) -> bool {
+    matches!(
+        reference.kind.as_str(),
+        "book" | "thesis" | "report" | "document" | "webpage" | "dataset" | "software"
+    )
+}
+
+fn inline(reference: &Reference, style: Style, locator: Option<&str>) -> String {
+    let names = if reference.author.is_empty() {
+        &reference.editor
+    } else {
+        &reference.author
+    };
+    let families: Vec<String> = names.iter().map(|name| md(&family(name))).collect();
+    let who = match (style, families.as_slice()) {
+        (_, []) => {
+            let short = reference
+                .title
+                .split_whitespace()
+                .take(4)
+                .collect::<Vec<_>>()
+                .join(" ");
+            if is_standalone(reference) {
+                italic(&short)
+            } else {
+                format!("“{}”", md(&short))
+            }
+        }
+        (Style::Apa, [first, second]) => format!("{} & {}", first, second),
+        (Style::Chicago, [_, _, _]) => join_list(&families, "and", true),
+        (Style::Harvard, [_, _, _]) => join_list(&families, "and", false),
+        (_, [first, _, _, ..]) => format!("{} et al.", first),
+        _ => join_list(&families, "and", false),
+    };
+    let year = reference
+        .issued
+        .as_ref()
+        .and_then(CslDate::year)
+        .unwrap_or_else(|| "n.d.".to_string());
+    let locator = locator.map(str::trim).filter(|locator| !locator.is_empty());
+    match (style, locator) {
+        (Style::Apa | Style::Harvard, Some(locator)) => {
+            format!("({}, {}, {})", who, year, page_label(locator))
+        }
+        (Style::Apa | Style::Harvard, None) => format!("({}, {})", who, year),
+        (Style::Chicago, Some(locator)) => format!("({} {}, {})", who, year, pages(locator)),
+        (Style::Chicago, None) => format!("({} {})", who, year),
+        (Style::Mla, Some(locator)) => format!("({} {})", who, pages(locator)),
+        (Style::Mla, None) => format!("({})", who),
+    }
+}
+
+fn bibliography(reference: &Reference, style: Style) -> String {
+    let year = reference.issued.as_ref().and_then(CslDate::year);
+    let title = reference.title.trim();
+    let standalone = is_standalone(reference);
+    let chapter = matches!(reference.kind.as_str(), "chapter" | "paper-conference");
+    let container = reference.container_title.as_deref();
+    let editors = &reference.editor;
+    // Without authors, the editors of a whole book lead the entry
+    let lead = if !reference.author.is_empty() {
+        Some(md(&author_list(&reference.author, style)))
+    } else if !editors.is_empty() && !chapter {
+        let names = md(&author_list(editors, style));
+        Some(match style {
+            Style::Apa => format!("{} (Ed{}.)", names, plural(editors)),
+            Style::Harvard => format!("{} (ed{}.)", names, plural(editors)),
+            _ => format!("{}, editor{}", names, plural(editors)),
+        })
+    } else {
+        None
+    };
+    let quoted = |open: &str, close: &str| format!("{}{}{}", open, md(title), close);
+    let publisher = match (
+        reference.publisher_place.as_deref(),
+        reference.publisher.as_deref(),
+    ) {
+        _ if !(standalone || chapter) => None,
+        (Some(place), Some(publisher)) if style != Style::Apa && style != Style::Mla => {
+            Some(format!("{}: {}", md(place), md(publisher)))
+        }
+        (_, Some(publisher)) => Some(md(publisher)),
+        _ => None,
+    };
+    let mut parts: Vec<String> = Vec::new();
+
+    match style {
+        Style::Apa => {
+            let date = format!("({}).", year.as_deref().unwrap_or("n.d."));
+            let title = if standalone {
+                period(&italic(title))
+            } else {
+                period(&md(title))
+            };
+            match lead {
+                Some(lead) => parts.extend([format!("{} {}", period(&lead), date), title]),
+                None => parts.push(format!("{} {}", title, date)),
+            }
+            match container {
+                Some(container) if chapter => {
+                    let mut within = String::from("In ");
+                    if !editors.is_empty() {
+                        within.push_str(&format!(
+                            "{} (Ed{}.), ",
+                            md(&editor_names(editors, style)),
+                            plural(editors)
+                        ));
+                    }
+                    within.push_str(&italic(container));
+                    if let Some(page) = &reference.page {
+                        within.push_str(&format!(" (pp. {})", pages(page)));
+                    }
+                    parts.push(format!("{}.", within));
+                }
+                Some(container) => {
+                    let mut source = italic(container);
+                    if let Some(volume) = &reference.volume {
+                        source.push_str(&format!(", {}", italic(volume)));
+                    }
+                    if let Some(issue) = &reference.issue {
+                        source.push_str(&format!("({})", md(issue)));
+                    }
+                    if let Some(page) = &reference.page {
+                        source.push_str(&format!(", {}", pages(page)));
+                    }
+                    parts.push(format!("{}.", source));
+                }
+                None => {}
+            }
+            parts.extend(publisher.map(|publisher| period(&publisher)));
+        }
+        Style::Chicago => {
+            parts.extend(lead.map(|lead| period(&lead)));
+            parts.push(format!("{}.", year.as_deref().unwrap_or("n.d.")));
+            parts.push(if standalone {
+                period(&italic(title))
+            } else {
+                quoted("“", ".”")
+            });
+            match container {
+                Some(container) if chapter => {
+                    let mut within = format!("In {}", italic(container));
+                    if !editors.is_empty() {
+                        within.push_str(&format!(
+                            ", edited by {}",
+                            md(&editor_names(editors, style))
+                        ));
+                    }
+                    if let Some(page) = &reference.page {
+                        within.push_str(&format!(", {}", pages(page)));
+                    }
+                    parts.push(format!("{}.", within));
+                }
+                Some(container) => {
+                    let mut source = italic(container);
+                    if let Some(volume) = &reference.volume {
+                        source.push_str(&format!(" {}", md(volume)));
+                    }
+                    if let Some(issue) = &reference.issue {
+                        source.push_str(&format!(" ({})", md(issue)));
+                    }
+                    if let Some(page) = &reference.page {
+                        source.push_str(&format!(": {}", pages(page)));
+                    }
+                    parts.push(format!("{}.", source));
+                }
+                None => {}
+            }
+            parts.extend(publisher.map(|publisher| period(&publisher)));
+        }
+        Style::Mla => {
+            parts.extend(lead.map(|lead| period(&lead)));
+            parts.push(if standalone {
+                period(&italic(title))
+            } else {
+                quoted("“", ".”")
+            });
+            let mut source: Vec<String> = Vec::new();
+            source.extend(container.map(italic));
+            if chapter && !editors.is_empty() {
+                source.push(format!("edited by {}", md(&editor_names(editors, style))));
+            }
+            source.extend(
+                reference
+                    .volume
+                    .as_ref()
+                    .map(|volume| format!("vol. {}", md(volume))),
+            );
+            source.extend(
+                reference
+                    .issue
+                    .as_ref()
+                    .map(|issue| format!("no. {}", md(issue))),
+            );
+            source.extend(publisher);
+            source.extend(year.clone());
+            source.extend(reference.page.as_deref().map(page_label));
+            if !source.is_empty() {
+                parts.push(format!("{}.", source.join(", ")));
+            }
+        }
+        Style::Harvard => {
+            let date = format!("({})", year.as_deref().unwrap_or("no date"));
+            let title = if standalone {
+                italic(title)
+            } else {
+                quoted("‘", "’")
+            };
+            let mut line = match lead {
+                Some(lead) => format!("{} {} {}", lead, date, title),
+                None => format!("{} {}", title, date),
+            };
+            match container {
+                Some(container) if chapter => {
+                    line.push_str(", in ");
+                    if !editors.is_empty() {
+                        line.push_str(&format!(
+                            "{} (ed{}.) ",
+                            md(&author_list(editors, style)),
+                            plural(editors)
+                        ));
+                    }
+                    line.push_str(&italic(container));
+                }
+                Some(container) => {
+                    line.push_str(&format!(", {}", italic(container)));
+                    if let Some(volume) = &reference.volume {
+                        line.push_str(&format!(", {}", md(volume)));
+                    }
+                    if let Some(issue) = &reference.issue {
+                        line.push_str(&format!("({})", md(issue)));
+                    }
+                }
+                None => {}
+            }
+            if let Some(page) = &reference.page {
+                line.push_str(&format!(", {}", page_label(page)));
+            }
+            parts.push(format!("{}.", line));
+            parts.extend(publisher.map(|publisher| period(&publisher)));
+        }
+    }
+
+    let doi = reference
+        .doi
+        .as_deref()
+        .map(|doi| doi.trim().trim_start_matches("https://doi.org/"));
+    match (style, doi, reference.url.as_deref()) {
+        (Style::Harvard, Some(doi), _) => parts.push(format!("doi:{}.", doi)),
+        (Style::Harvard, None, Some(url)) => parts.push(format!("Available at: {}.", url)),
+        (Style::Mla, Some(doi), _) => parts.push(format!("https://doi.org/{}.", doi)),
+        (Style::Mla, None, Some(url)) => parts.push(format!("{}.", url)),
+        (_, Some(doi), _) => parts.push(format!("https://doi.org/{}", doi)),
+        (_, None, Some(url)) => parts.push(url.to_string()),
+        _ => {}
+    }
+    parts.join(" ")
+}
+
+/// Source and size of the workspace's cached reference library
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_reference_library(app: AppHandle, workspace: String) -> Result<LibraryInfo, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(info(&load(&root)?))
+}
+
+/// Replace the workspace's library with one read from a BibTeX or CSL-JSON file, or fetched
+/// from the local Zotero app, and cache it in the workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn import_references(
+    app: tauri::AppHandle,
+    workspace: String,
+    source: LibrarySource,
+) -> Result<LibraryInfo, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    if let LibrarySource::File { path } = &source {
+        paths::check(&app, path, paths::Scope::Read)?;
+    }
+    Ok(
+        tauri::async_runtime::spawn_blocking(move || import(&root, source))
+            .await
+            .map_err(|e| format!("Import task failed: {}", e))??,
+    )
+}
+
+/// Read the library again from where it was imported
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn refresh_references(app: AppHandle, workspace: String) -> Result<LibraryInfo, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let source = load(&root)?
+        .source
+        .ok_or_else(|| Error::not_found("No reference library has been imported"))?;
+    Ok(
+        tauri::async_runtime::spawn_blocking(move || import(&root, source))
+            .await
+            .map_err(|e| format!("Import task failed: {}", e))??,
+    )
+}
+
+/// References matching every word of `query` across keys, authors, titles, years and
+/// containers, best matches first
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn search_references(
+    app: AppHandle,
+    workspace: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<Reference>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let library = load(&root)?;
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| term.trim_start_matches('@').to_lowercase())
+        .filter(|term| !term.is_empty())
+        .collect();
+    let mut hits: Vec<(u32, Reference)> = library
+        .items
+        .into_iter()
+        .filter_map(|reference| Some((score(&reference, &terms)?, reference)))
+        .collect();
+    hits.sort_by(|(a, x), (b, y)| b.cmp(a).then_with(|| x.id.cmp(&y.id)));
+    Ok(hits
+        .into_iter()
+        .take(limit.unwrap_or(DEFAULT_LIMIT))
+        .map(|(_, reference)| reference)
+        .collect())
+}
+
+/// Citation styles `format_citation` and `format_bibliography` accept: the built-in ones and
+/// those downloaded, which take the place of a built-in style with the same id
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn list_citation_styles(app: AppHandle) -> Vec<CitationStyle> {
+    let downloaded = csl::downloaded(&app);
+    let mut styles: Vec<CitationStyle> = STYLES
+        .iter()
+        .filter(|(id, _, _)| !downloaded.iter().any(|(other, _)| other == id))
+        .map(|(id, name, _)| CitationStyle {
+            id: id.to_string(),
+            name: name.to_string(),
+            downloaded: false,
+        })
+        .collect();
+    styles.extend(downloaded.into_iter().map(|(id, name)| CitationStyle {
+        id,
+        name,
+        downloaded: true,
+    }));
+    styles
+}
+
+/// Download a CSL style by its id in the CSL style repository, such as `ieee` or `nature`, and
+/// keep it for offline use
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn download_citation_style(app: AppHandle, id: String) -> Result<CitationStyle, Error> {
+    let (id, name) = tauri::async_runtime::spawn_blocking(move || csl::download(&app, &id))
+        .await
+        .map_err(|e| format!("Download task failed: {}", e))??;
+    Ok(CitationStyle {
+        id,
+        name,
+        downloaded: true,
+    })
+}
+
+/// Delete a downloaded CSL style; returns `false` when it was not downloaded
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_citation_style(app: AppHandle, id: String) -> Result<bool, Error> {
+    Ok(csl::remove(&app, &id)?)
+}
+
+fn formatter(app: &AppHandle, id: &str) -> Result<Formatter, Error> {
+    let id = id.trim().to_lowercase();
+    if let Some(style) = csl::load(app, &id)? {
+        return Ok(Formatter::Csl(Box::new(style)));
+    }
+    match STYLES.iter().find(|(style, _, _)| *style == id) {
+        Some((_, _, style)) => Ok(Formatter::Builtin(*style)),
+        None => Err(format!("Citation style is not supported: {}", id).into()),
+    }
+}
+
+/// Citation keys in Pandoc form, `[@key]`, `[@a; @b]`, `@key` or `@{key}`, in order of first
+/// use; code is skipped and email addresses are not keys
+pub fn cited_keys(markdown: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut seen = HashSet::new();
+    let mut fenced = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fenced = !fenced;
+            continue;
+        }
+        if fenced {
+            continue;
+        }
+        // Odd segments are inside code spans
+        for text in line.split('`').step_by(2) {
+            for (at, _) in text.match_indices('@') {
+                let before = text[..at].chars().next_back();
+                if before.is_some_and(|c| c.is_alphanumeric() || c == '@' || c == '\\') {
+                    continue;
+                }
+                let rest = &text[at + 1..];
+                let key = match rest.strip_prefix('{') {
+                    Some(braced) => braced.split_once('}').map(|(key, _)| key.trim()),
+                    None => {
+                        let end = rest
+                            .find(|c: char| !(c.is_alphanumeric() || "_:.#$%&-+?<>~/".contains(c)))
+                            .unwrap_or(rest.len());
+                        // Punctuation at the end belongs to the sentence
+                        Some(rest[..end].trim_end_matches(|c: char| !c.is_alphanumeric()))
+                    }
+                };
+                let Some(key) = key.filter(|key| {
+                    key.chars()
+                        .next()
+                        .is_some_and(|c| c.is_alphanumeric() || c == '_')
+                }) else {
+                    continue;
+                };
+                if seen.insert(key.to_string()) {
+                    keys.push(key.to_string());
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// Format the reference `key` in `style`, in text and for the reference list, as Markdown.
+/// `locator` is a page or page range cited.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn format_citation(
+    app: AppHandle,
+    workspace: String,
+    key: String,
+    style: String,
+    locator: Option<String>,
+) -> Result<Citation, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let formatter = formatter(&app, &style)?;
+    let key = key.trim().trim_start_matches('@');
+    let library = load(&root)?;
+    let reference = library
+        .items
+        .iter()
+        .find(|reference| reference.id == key)
+        .ok_or_else(|| Error::not_found("Reference not found").with_context(key))?;
+    Ok(match formatter {
+        Formatter::Builtin(style) => Citation {
+            inline: inline(reference, style, locator.as_deref()),
+            bibliography: bibliography(reference, style),
+        },
+        Formatter::Csl(style) => Citation {
+            inline: style.citation(reference, locator.as_deref())?,
+            bibliography: style
+                .bibliography(&[reference])?
+                .into_iter()
+                .map(|(_, text)| text)
+                .next()
+                .unwrap_or_default(),
+        },
+    })
+}
+
+/// The reference list for every key cited in the document `doc`, formatted in `style` as
+/// Markdown; keys not found in the library are listed separately
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn format_bibliography(
+    app: AppHandle,
+    workspace: String,
+    doc: String,
+    style: String,
+) -> Result<Bibliography, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let path = paths::check(&app, &doc, paths::Scope::Read)?;
+    let formatter = formatter(&app, &style)?;
+    let markdown = document::read_board(&path)?.to_markdown();
+    let library = load(&root)?;
+    let by_key: HashMap<&str, &Reference> = library
+        .items
+        .iter()
+        .map(|reference| (reference.id.as_str(), reference))
+        .collect();
+    let (mut cited, mut missing) = (Vec::new(), Vec::new());
+    for key in cited_keys(&markdown) {
+        match by_key.get(key.as_str()) {
+            Some(reference) => cited.push(*reference),
+            None => missing.push(key),
+        }
+    }
+
+    let entries: Vec<(String, String)> = match formatter {
+        // The built-in styles all list entries alphabetically, which is the order of their text
+        Formatter::Builtin(style) => {
+            let mut entries: Vec<(String, String)> = cited
+                .iter()
+                .map(|reference| (reference.id.clone(), bibliography(reference, style)))
+                .collect();
+            entries.sort_by_cached_key(|(_, text)| text.replace('*', "").to_lowercase());
+            entries
+        }
+        Formatter::Csl(style) => style.bibliography(&cited)?,
+    };
+    let markdown = entries
+        .iter()
+        .map(|(_, text)| text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(Bibliography {
+        style: style.trim().to_lowercase(),
+        entries: entries
+            .into_iter()
+            .map(|(key, text)| BibliographyEntry { key, text })
+            .collect(),
+        markdown,
+        missing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(value: &str) -> Vec<(Option<String>, Option<String>, Option<String>)> {
+        parse_names(value)
+            .into_iter()
+            .map(|name| (name.family, name.given, name.literal))
+            .collect()
+    }
+
+    fn some(text: &str) -> Option<String> {
+        Some(text.to_string())
+    }
+
+    #[test]
+    fn csl_json_reads_arrays_and_zotero_objects() {
+        let array = r#"[{"id": "a", "type": "book", "title": "A"}, {"id": 7, "title": "B"}]"#;
+        let items = parse_csl_json(array).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].kind, "book");
+        assert_eq!(items[1].id, "7");
+
+        let zotero = r#"{"items": [{"id": "z", "volume": 12, "edition": "2"}]}"#;
+        let items = parse_csl_json(zotero).unwrap();
+        assert_eq!(items[0].volume.as_deref(), Some("12"));
+        assert_eq!(items[0].other["edition"], "2");
+    }
+
+    #[test]
+    fn csl_json_skips_items_that_do_not_fit() {
+        let items = parse_csl_json(r#"[{"id": ""}, {"title": "no id"}, {"id": "ok"}]"#).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, "ok");
+        assert!(parse_csl_json(r#"{"id": "a"}"#).is_err());
+        assert!(parse_csl_json("not json").is_err());
+    }
+
+    #[test]
+    fn bibtex_entries_are_mapped_to_csl() {
+        let items = parse_bibtex(
+            r#"@Article{knuth84,
+                author = {Donald E. Knuth},
+                title = {Literate {P}rogramming},
+                journal = "The Computer Journal",
+                year = 1984, month = may,
+                volume = {27}, number = {2}, pages = {97--111},
+            }"#,
+        );
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.id, "knuth84");
+        assert_eq!(item.kind, "article-journal");
+        assert_eq!(item.title, "Literate Programming");
+        assert_eq!(
+            item.container_title.as_deref(),
+            Some("The Computer Journal")
+        );
+        assert_eq!(item.issue.as_deref(), Some("2"));
+        assert_eq!(item.page.as_deref(), Some("97–111"));
+        let issued = item.issued.as_ref().unwrap();
+        assert_eq!(issued.date_parts, [vec![Value::from(1984), Value::from(5)]]);
+        assert_eq!(issued.year().as_deref(), Some("1984"));
+    }
+
+    #[test]
+    fn bibtex_macros_and_concatenation_are_expanded() {
+        let items = parse_bibtex(
+            r#"@comment{ignored @book{nope, title = {x}}}
+            @string{pub = "Addison-Wesley"}
+            @book(taocp, title = "The Art of " # {Computer Programming}, publisher = pub,
+                  date = {1997-07})"#,
+        );
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "The Art of Computer Programming");
+        assert_eq!(items[0].publisher.as_deref(), Some("Addison-Wesley"));
+        let issued = items[0].issued.as_ref().unwrap();
+        assert_eq!(issued.date_parts, [vec![Value::from(1997), Value::from(7)]]);
+        assert_eq!(issued.raw.as_deref(), Some("1997-07"));
+    }
+
+    #[test]
+    fn malformed_bibtex_does_not_stall() {
+        let items = parse_bibtex("@misc{a, = broken, title = {T}} @misc{b, title = {unclosed");
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title, "T");
+    }
+
+    #[test]
+    fn latex_accents_and_escapes_become_plain_text() {
+        assert_eq!(clean_latex(r#"Erd\H{o}s and G\"{o}del"#), "Erdos and Gödel");
+        assert_eq!(clean_latex(r"Caf\'e \& Stra\ss e"), "Café & Straße");
+        assert_eq!(clean_latex(r"\c{c}a \v{s}koda \emph{x}"), "ça škoda x");
+        assert_eq!(clean_latex("1--2 and a---b"), "1–2 and a—b");
+        assert_eq!(
+            clean_latex("Dr.~Who  {was}\n here"),
+            "Dr.\u{a0}Who was here"
+        );
+    }
+
+    #[test]
+    fn bibtex_names_in_each_form() {
+        assert_eq!(
+            names("Ludwig van Beethoven and Bach, Johann Sebastian"),
+            [
+                (some("van Beethoven"), some("Ludwig"), None),
+                (some("Bach"), some("Johann Sebastian"), None),
+            ]
+        );
+        assert_eq!(
+            names("{World Health Organization} and Plato"),
+            [
+                (None, None, some("World Health Organization")),
+                (some("Plato"), None, None),
+            ]
+        );
+        assert_eq!(
+            names("{Barnes and Noble}"),
+            [(None, None, some("Barnes and Noble"))]
+        );
+    }
+
+    #[test]
+    fn cited_keys_in_order_of_first_use() {
+        let markdown = "As [@smith2020; @doe, p. 4] show, @smith2020 and @{odd key}.\n\
+                        Mail me at a@example.com or see `@code`.\n\
+                        ```\n@fenced\n```\n\
+                        Ends with @last.";
+        assert_eq!(
+            cited_keys(markdown),
+            ["smith2020", "doe", "odd key", "last"]
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use crate::document;
+use crate::error::Error;
+use crate::{catalog, paths, vectors, workspace};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_LIMIT: usize = 8;
+/// Suggestions ranked and kept per document
+const RANKED: usize = 50;
+const LINK_WEIGHT: f64 = 0.4;
+const TAG_WEIGHT: f64 = 0.25;
+const SIMILARITY_WEIGHT: f64 = 0.35;
+/// Cosine similarity any two notes in one language tend to reach; only what is above it counts
+const SIMILARITY_FLOOR: f32 = 0.3;
+
+/// Managed state caching, per workspace, the link graph, tags and document vectors along with
+/// the suggestions already ranked from them. A snapshot is rebuilt once the metadata cache or
+/// the embeddings index has changed.
+#[derive(Default)]
+pub struct RelatedCache(Mutex<HashMap<PathBuf, Arc<Snapshot>>>);
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedDocument {
+    /// Workspace-relative path
+    pub path: String,
+    pub name: String,
+    /// Between 0 and 1
+    pub score: f64,
+    /// Links between the two documents: 1 when one links to the other, 2 through a third
+    pub hops: Option<u8>,
+    pub shared_tags: Vec<String>,
+    /// Cosine similarity of the documents' embeddings, when both are indexed
+    pub similarity: Option<f32>,
+}
+
+struct Node {
+    path: String,
+    name: String,
+    tags: BTreeSet<String>,
+}
+
+struct Snapshot {
+    revision: String,
+    nodes: Vec<Node>,
+    by_path: HashMap<String, usize>,
+    /// Documents linking to or linked from each node
+    neighbours: Vec<BTreeSet<usize>>,
+    vectors: HashMap<String, Vec<f32>>,
+    ranked: Mutex<HashMap<usize, Arc<Vec<RelatedDocument>>>>,
+}
+
+/// Fingerprint of what suggestions are computed from
+fn revision(app: &AppHandle, root: &Path, entries: &[catalog::CatalogEntry]) -> String {
+    let stamps: i64 = entries.iter().map(|entry| entry.modified).sum();
+    let embeddings = vectors::revision(app, root).unwrap_or_default();
+    format!("{}:{}:{}", entries.len(), stamps, embeddings)
+}
+
+fn build(
+    app: &AppHandle,
+    root: &Path,
+    entries: Vec<catalog::CatalogEntry>,
+    revision: String,
+) -> Snapshot {
+    // `[[links]]` name a document by its title or file name, lowercased
+    let mut titles: HashMap<String, usize> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        titles.entry(entry.name.to_lowercase()).or_insert(index);
+        titles
+            .entry(document::document_stem(Path::new(&entry.path)).to_lowercase())
+            .or_insert(index);
+    }
+    let mut neighbours = vec![BTreeSet::new(); entries.len()];
+    for (index, entry) in entries.iter().enumerate() {
+        for target in entry.links.iter().filter_map(|link| titles.get(link)) {
+            if *target != index {
+                neighbours[index].insert(*target);
+                neighbours[*target].insert(index);
+            }
+        }
+    }
+    // Similarity is left out until an embedding model is set up
+    let vectors = vectors::document_vectors(app, root).unwrap_or_default();
+    let nodes: Vec<Node> = entries
+        .into_iter()
+        .map(|entry| Node {
+            path: entry.path,
+            name: entry.name,
+            tags: entry.tags.into_iter().collect(),
+        })
+        .collect();
+    Snapshot {
+        revision,
+        by_path: nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.path.clone(), index))
+            .collect(),
+        nodes,
+        neighbours,
+        vectors,
+        ranked: Mutex::new(HashMap::new()),
+    }
+}
+
+/// Drop the workspace's snapshot
+pub fn evict(app: &AppHandle, root: &Path) {
+    if let Ok(mut snapshots) = app.state::<RelatedCache>().0.lock() {
+        snapshots.remove(root);
+    }
+}
+
+/// The workspace's snapshot, rebuilt when it is out of date
+fn snapshot(app: &AppHandle, root: &Path) -> Result<Arc<Snapshot>, String> {
+    let entries = catalog::documents(app, root)?;
+    let revision = revision(app, root, &entries);
+    let cache = app.state::<RelatedCache>();
+    if let Some(snapshot) = cache
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to read related documents: {}", e))?
+        .get(root)
+        .filter(|snapshot| snapshot.revision == revision)
+    {
+        return Ok(snapshot.clone());
+    }
+    // Built without the lock so other workspaces are answered meanwhile
+    let snapshot = Arc::new(build(app, root, entries, revision));
+    cache
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to read related documents: {}", e))?
+        .insert(root.to_path_buf(), snapshot.clone());
+    Ok(snapshot)
+}
+
+impl Snapshot {
+    fn hops(&self, from: usize, to: usize) -> Option<u8> {
+        let direct = &self.neighbours[from];
+        if direct.contains(&to) {
+            Some(1)
+        } else if direct
+            .iter()
+            .any(|middle| self.neighbours[*middle].contains(&to))
+        {
+            Some(2)
+        } else {
+            None
+        }
+    }
+
+    fn similarity(&self, from: usize, to: usize) -> Option<f32> {
+        let a = self.vectors.get(&self.nodes[from].path)?;
+        let b = self.vectors.get(&self.nodes[to].path)?;
+        Some(vectors::dot(a, b))
+    }
+
+    fn rank(&self, from: usize) -> Vec<RelatedDocument> {
+        let tags = &self.nodes[from].tags;
+        let mut related: Vec<RelatedDocument> = (0..self.nodes.len())
+            .filter(|to| *to != from)
+            .filter_map(|to| {
+                let node = &self.nodes[to];
+                let hops = self.hops(from, to);
+                let shared_tags: Vec<String> = tags.intersection(&node.tags).cloned().collect();
+                let similarity = self.similarity(from, to);
+
+                let link = match hops {
+                    Some(1) => 1.0,
+                    Some(_) => 0.5,
+                    None => 0.0,
+                };
+                let union = tags.union(&node.tags).count();
+                let tag = match union {
+                    0 => 0.0,
+                    union => shared_tags.len() as f64 / union as f64,
+                };
+                let close = similarity.map_or(0.0, |similarity| {
+                    ((similarity - SIMILARITY_FLOOR) / (1.0 - SIMILARITY_FLOOR)).max(0.0) as f64
+                });
+                let score = LINK_WEIGHT * link + TAG_WEIGHT * tag + SIMILARITY_WEIGHT * close;
+                (score > 0.0).then(|| RelatedDocument {
+                    path: node.path.clone(),
+                    name: node.name.clone(),
+                    score,
+                    hops,
+                    shared_tags,
+                    similarity,
+                })
+            })
+            .collect();
+        related.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.name.cmp(&b.name)));
+        related.truncate(RANKED);
+        related
+    }
+
+    /// Suggestions for a workspace-relative path, ranked once per snapshot
+    fn related(&self, path: &str) -> Result<Arc<Vec<RelatedDocument>>, String> {
+        let Some(&index) = self.by_path.get(path) else {
+            return Ok(Arc::new(Vec::new()));
+        };
+        let mut ranked = self
+            .ranked
+            .lock()
+            .map_err(|e| format!("Failed to read related documents: {}", e))?;
+        Ok(ranked
+            .entry(index)
+            .or_insert_with(|| Arc::new(self.rank(index)))
+            .clone())
+    }
+}
+
+/// Documents related to `path` by links, shared tags and, once the embeddings index is built,
+/// similar content, best first. Results are cached until the workspace changes.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn related_documents(
+    app: AppHandle,
+    path: String,
+    k: Option<usize>,
+) -> Result<Vec<RelatedDocument>, Error> {
+    let resolved = paths::check(&app, &path, paths::Scope::Read)?;
+    let root = workspace::current_root(&app)
+        .filter(|root| resolved.starts_with(root))
+        .ok_or("Invalid path: the document is not in the open workspace")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let snapshot = snapshot(&app, &root)?;
+        let related = snapshot.related(&workspace::relative_path(&root, &resolved))?;
+        Ok(related
+            .iter()
+            .take(k.unwrap_or(DEFAULT_LIMIT))
+            .cloned()
+            .collect())
+    })
+    .await
+    .map_err(|e| format!("Related documents task failed: {}", e))?
+}
@@ -0,0 +1,334 @@
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::workspace;
+use crate::{bridge, messages, paths};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+
+const STATE_CONFIG: &str = "reminders";
+/// Shape prop holding a block's reminder time
+const SHAPE_PROP: &str = "reminderAt";
+const TICK: std::time::Duration = std::time::Duration::from_secs(30);
+/// More reminders than this firing at once, as after waking from sleep, share one notification
+const MAX_NOTIFICATIONS: usize = 3;
+
+/// Snooze and completion state kept in `.inkfinite/reminders.json`, keyed by reminder id.
+///
+/// State only applies while the document still carries the same `due_at`; moving a reminder
+/// to a new time starts it afresh.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReminderState {
+    pub due_at: i64,
+    pub snoozed_until: Option<i64>,
+    pub notified_at: Option<i64>,
+    pub completed_at: Option<i64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct ReminderStore {
+    reminders: BTreeMap<String, ReminderState>,
+}
+
+/// A pending reminder on a document (`shape_id` unset) or on one of its blocks
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    /// `<board id>` for documents, `<board id>:<shape id>` for blocks
+    pub id: String,
+    pub path: String,
+    pub shape_id: Option<String>,
+    pub title: String,
+    pub due_at: i64,
+    pub snoozed_until: Option<i64>,
+    /// When the reminder fires, after any snooze
+    pub fires_at: i64,
+    pub notified: bool,
+}
+
+/// Incomplete reminders in a workspace ordered by when they fire, optionally limited to the
+/// next `within_hours`; overdue reminders are always included
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_upcoming_reminders(
+    app: AppHandle,
+    workspace: String,
+    within_hours: Option<u32>,
+) -> Result<Vec<Reminder>, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let root = Path::new(&workspace);
+    let store: ReminderStore = workspace::read_config(root, STATE_CONFIG)?;
+    let horizon = within_hours.map(|hours| document::now_millis() + i64::from(hours) * 3_600_000);
+
+    let mut reminders: Vec<Reminder> = pending(root, &store)?
+        .into_iter()
+        .filter(|reminder| horizon.is_none_or(|horizon| reminder.fires_at <= horizon))
+        .collect();
+    reminders.sort_by_key(|reminder| reminder.fires_at);
+    Ok(reminders)
+}
+
+/// Push a reminder back by `minutes` from now
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn snooze_reminder(
+    app: AppHandle,
+    workspace: String,
+    id: String,
+    minutes: u32,
+) -> Result<Reminder, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    if minutes == 0 {
+        return Err("Snooze must be at least one minute".into());
+    }
+    let until = document::now_millis() + i64::from(minutes) * 60_000;
+    Ok(update_state(Path::new(&workspace), &id, |state| {
+        state.snoozed_until = Some(until);
+        state.notified_at = None;
+    })?)
+}
+
+/// Mark a reminder done so it no longer fires or lists as upcoming
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn complete_reminder(app: AppHandle, workspace: String, id: String) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    Ok(update_state(Path::new(&workspace), &id, |state| {
+        state.completed_at = Some(document::now_millis());
+    })
+    .map(|_| ())?)
+}
+
+/// Set or clear (`at: None`) the reminder on a document, or on one of its blocks
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_reminder(
+    app: AppHandle,
+    path: String,
+    shape_id: Option<String>,
+    at: Option<i64>,
+) -> Result<(), Error> {
+    paths::check(&app, &path, paths::Scope::Write)?;
+    let path = Path::new(&path);
+    let mut board = document::read_board(path)?;
+    match &shape_id {
+        None => board.board.reminder_at = at,
+        Some(shape_id) => {
+            let props = board
+                .doc
+                .shapes
+                .get_mut(shape_id)
+                .and_then(|shape| shape.get_mut("props"))
+                .and_then(Value::as_object_mut)
+                .ok_or_else(|| format!("Shape does not exist: {}", shape_id))?;
+            match at {
+                Some(at) => props.insert(SHAPE_PROP.to_string(), Value::from(at)),
+                None => props.remove(SHAPE_PROP),
+            };
+        }
+    }
+    board.board.updated_at = document::now_millis();
+    document::write_board(path, &board)?;
+    events::file_changed(&app, path, ChangeKind::Modified);
+    Ok(())
+}
+
+/// Fire reminders for the current workspace as native notifications.
+///
+/// Each tick compares against the wall clock rather than sleeping until the next reminder, so
+/// anything that came due while the machine slept fires on the first tick after waking.
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        if let Some(root) = workspace::current_root(&app) {
+            if let Err(error) = fire_due(&app, &root) {
+                workspace::append_log(&root, STATE_CONFIG, &format!("failed: {}", error));
+            }
+        }
+        std::thread::sleep(TICK);
+    });
+}
+
+fn fire_due(app: &AppHandle, root: &Path) -> Result<(), String> {
+    let mut store: ReminderStore = workspace::read_config(root, STATE_CONFIG)?;
+    let found = scan(root)?;
+    let now = document::now_millis();
+
+    let due: Vec<Reminder> = pending_from(&found, &store)
+        .into_iter()
+        .filter(|reminder| !reminder.notified && reminder.fires_at <= now)
+        .collect();
+
+    // Drop state for reminders that were removed from their documents
+    let before = store.reminders.len();
+    store
+        .reminders
+        .retain(|id, _| found.iter().any(|reminder| &reminder.id == id));
+    if due.is_empty() {
+        if store.reminders.len() != before {
+            workspace::write_config(root, STATE_CONFIG, &store)?;
+        }
+        return Ok(());
+    }
+
+    for reminder in &due {
+        state_for(&mut store, &reminder.id, reminder.due_at).notified_at = Some(now);
+    }
+    workspace::write_config(root, STATE_CONFIG, &store)?;
+
+    if due.len() > MAX_NOTIFICATIONS {
+        notify(
+            app,
+            &messages::format("reminders-due-title", &[("count", due.len().into())]),
+            &due.iter()
+                .map(|reminder| reminder.title.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+    } else {
+        for reminder in &due {
+            notify(app, &reminder.title, &document_name(&reminder.path));
+        }
+    }
+    let _ = app.emit("reminder:due", &due);
+    bridge::publish(app, root, "reminder/due", json!(due));
+    Ok(())
+}
+
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    let _ = app.notification().builder().title(title).body(body).show();
+}
+
+fn update_state(
+    root: &Path,
+    id: &str,
+    change: impl FnOnce(&mut ReminderState),
+) -> Result<Reminder, String> {
+    let mut store: ReminderStore = workspace::read_config(root, STATE_CONFIG)?;
+    let found = scan(root)?
+        .into_iter()
+        .find(|reminder| reminder.id == id)
+        .ok_or_else(|| format!("Reminder does not exist: {}", id))?;
+
+    change(state_for(&mut store, id, found.due_at));
+    workspace::write_config(root, STATE_CONFIG, &store)?;
+
+    Ok(apply(found, Some(&store.reminders[id])))
+}
+
+/// Stored state for a reminder, reset when the document has moved it to another time
+fn state_for<'a>(store: &'a mut ReminderStore, id: &str, due_at: i64) -> &'a mut ReminderState {
+    let state = store.reminders.entry(id.to_string()).or_default();
+    if state.due_at != due_at {
+        *state = ReminderState {
+            due_at,
+            ..Default::default()
+        };
+    }
+    state
+}
+
+/// Incomplete reminders in a workspace, in no particular order
+pub fn pending_in(root: &Path) -> Result<Vec<Reminder>, String> {
+    let store: ReminderStore = workspace::read_config(root, STATE_CONFIG)?;
+    pending(root, &store)
+}
+
+fn pending(root: &Path, store: &ReminderStore) -> Result<Vec<Reminder>, String> {
+    Ok(pending_from(&scan(root)?, store))
+}
+
+fn pending_from(found: &[Reminder], store: &ReminderStore) -> Vec<Reminder> {
+    found
+        .iter()
+        .cloned()
+        .map(|reminder| {
+            let state = store
+                .reminders
+                .get(&reminder.id)
+                .filter(|state| state.due_at == reminder.due_at);
+            (
+                state.and_then(|state| state.completed_at),
+                apply(reminder, state),
+            )
+        })
+        .filter(|(completed, _)| completed.is_none())
+        .map(|(_, reminder)| reminder)
+        .collect()
+}
+
+fn apply(mut reminder: Reminder, state: Option<&ReminderState>) -> Reminder {
+    if let Some(state) = state {
+        reminder.snoozed_until = state.snoozed_until;
+        reminder.fires_at = state.snoozed_until.unwrap_or(reminder.due_at);
+        reminder.notified = state
+            .notified_at
+            .is_some_and(|notified| notified >= reminder.fires_at);
+    }
+    reminder
+}
+
+/// Reminders carried by every document in a workspace, before any snooze or completion
+fn scan(root: &Path) -> Result<Vec<Reminder>, String> {
+    let mut found = Vec::new();
+    for path in workspace::list_documents(root)? {
+        if let Ok(board) = document::read_board(&path) {
+            collect(&path, &board, &mut found);
+        }
+    }
+    Ok(found)
+}
+
+fn collect(path: &Path, board: &BoardFile, found: &mut Vec<Reminder>) {
+    let reminder = |id: String, shape_id: Option<String>, title: String, due_at: i64| Reminder {
+        id,
+        path: path.to_string_lossy().to_string(),
+        shape_id,
+        title,
+        due_at,
+        snoozed_until: None,
+        fires_at: due_at,
+        notified: false,
+    };
+
+    if let Some(due_at) = board.board.reminder_at {
+        found.push(reminder(
+            board.board.id.clone(),
+            None,
+            board.board.name.clone(),
+            due_at,
+        ));
+    }
+    for page in board.pages() {
+        for shape in board.page_shapes(&page) {
+            let Some(due_at) = shape.props.get(SHAPE_PROP).and_then(Value::as_i64) else {
+                continue;
+            };
+            let title = shape
+                .text()
+                .and_then(|text| text.lines().map(str::trim).find(|line| !line.is_empty()))
+                .map(|line| {
+                    line.trim_start_matches('#')
+                        .trim()
+                        .chars()
+                        .take(80)
+                        .collect()
+                })
+                .unwrap_or_else(|| board.board.name.clone());
+            found.push(reminder(
+                format!("{}:{}", board.board.id, shape.id),
+                Some(shape.id.clone()),
+                title,
+                due_at,
+            ));
+        }
+    }
+}
+
+fn document_name(path: &str) -> String {
+    document::document_stem(Path::new(path))
+}
@@ -0,0 +1,247 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::document;
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{catalog, paths, read_only, vault, workspace};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetagChange {
+    /// Workspace-relative path
+    pub path: String,
+    pub name: String,
+    /// Tags rewritten in the document
+    pub replacements: usize,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RetagReport {
+    pub documents: Vec<RetagChange>,
+    pub replacements: usize,
+    /// Whether the report is a preview and nothing was written
+    pub dry_run: bool,
+}
+
+/// `tag` under `renames`, which map a tag along with the tags nested under it (`#old/child`)
+fn renamed(tag: &str, renames: &[(String, String)]) -> Option<String> {
+    renames.iter().find_map(|(from, to)| match tag == from {
+        true => Some(to.clone()),
+        false => tag
+            .strip_prefix(from.as_str())
+            .filter(|rest| rest.starts_with('/'))
+            .map(|rest| format!("{}{}", to, rest)),
+    })
+}
+
+/// Rewrite the `#tags` in `text` that `renames` covers, reading tags as `catalog::tags` does.
+/// A tag renamed to one the text already has is dropped. Returns the text and the number of
+/// tags rewritten.
+fn rewrite_tags(text: &str, renames: &[(String, String)]) -> (String, usize) {
+    let mut present: BTreeSet<String> = catalog::tags(text)
+        .into_iter()
+        .filter(|tag| renamed(tag, renames).is_none())
+        .collect();
+    let mut out = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let start = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+        let (space, tail) = rest.split_at(start);
+        let end = tail.find(char::is_whitespace).unwrap_or(tail.len());
+        let (word, tail) = tail.split_at(end);
+        rest = tail;
+
+        let replacement = word.strip_prefix('#').and_then(|body| {
+            let core = body.trim_end_matches(|c: char| !c.is_alphanumeric());
+            let tag = core.to_lowercase();
+            let valid = tag.chars().next().is_some_and(char::is_alphabetic)
+                && tag
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/'));
+            let to = renamed(&tag, renames).filter(|_| valid)?;
+            Some((to, &body[core.len()..]))
+        });
+        let Some((to, trailing)) = replacement else {
+            out.push_str(space);
+            out.push_str(word);
+            continue;
+        };
+        count += 1;
+        match present.insert(to.clone()) {
+            true => {
+                out.push_str(space);
+                out.push('#');
+                out.push_str(&to);
+            }
+            // The repeat goes with the space before it, unless that breaks a line
+            false if space.contains('\n') => out.push_str(space),
+            false => {}
+        }
+        out.push_str(trailing);
+    }
+    (out, count)
+}
+
+/// Apply `renames` to every document carrying one of the tags. All documents are read and
+/// rewritten in memory first; if one then fails to save, those already saved are restored,
+/// so a rename never lands halfway.
+fn retag(
+    app: &AppHandle,
+    root: &Path,
+    renames: &[(String, String)],
+    dry_run: bool,
+) -> Result<RetagReport, String> {
+    let mut candidates: Vec<PathBuf> = catalog::documents(app, root)?
+        .into_iter()
+        .filter(|entry| entry.tags.iter().any(|tag| renamed(tag, renames).is_some()))
+        .map(|entry| root.join(entry.path))
+        .collect();
+    // The cache leaves vault documents out
+    candidates.extend(vault::unlocked_documents(root));
+    candidates.sort();
+    candidates.dedup();
+
+    let mut staged = Vec::new();
+    for path in candidates {
+        let mut board = document::read_board(&path)?;
+        let mut replacements = 0;
+        for shape in board
+            .pages()
+            .iter()
+            .flat_map(|page| board.page_shapes(page))
+            .collect::<Vec<_>>()
+        {
+            let Some(text) = shape.text() else {
+                continue;
+            };
+            let (updated, count) = rewrite_tags(text, renames);
+            if count > 0 {
+                board.set_text(&shape.id, &updated);
+                replacements += count;
+            }
+        }
+        if replacements == 0 {
+            continue;
+        }
+        read_only::ensure_writable(&path)?;
+        let original = fs::read(&path).map_err(|e| format!("Failed to read document: {}", e))?;
+        board.board.updated_at = document::now_millis();
+        staged.push((path, original, board, replacements));
+    }
+
+    let report = RetagReport {
+        documents: staged
+            .iter()
+            .map(|(path, _, board, replacements)| RetagChange {
+                path: workspace::relative_path(root, path),
+                name: board.board.name.clone(),
+                replacements: *replacements,
+            })
+            .collect(),
+        replacements: staged.iter().map(|(.., replacements)| replacements).sum(),
+        dry_run,
+    };
+    if dry_run || staged.is_empty() {
+        return Ok(report);
+    }
+
+    for (index, (path, _, board, _)) in staged.iter().enumerate() {
+        if let Err(error) = document::write_board(path, board) {
+            for (path, original, ..) in &staged[..index] {
+                if let Err(e) = fs::write(path, original) {
+                    tracing::warn!(error = %e, path = %path.display(), "Failed to restore document");
+                }
+            }
+            return Err(error);
+        }
+    }
+    let changed: Vec<&Path> = staged.iter().map(|(path, ..)| path.as_path()).collect();
+    for path in &changed {
+        events::file_changed(app, path, ChangeKind::Modified);
+    }
+    let detail = renames
+        .iter()
+        .map(|(from, to)| format!("#{} -> #{}", from, to))
+        .collect::<Vec<_>>()
+        .join(", ");
+    audit::record_detail(
+        root,
+        AuditAction::Retag,
+        AuditSource::Ui,
+        &changed,
+        Some(detail),
+    );
+    Ok(report)
+}
+
+/// `tag` as `catalog::tags` would read it back, or `None` when it is not a valid tag
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').replace(' ', "-");
+    catalog::tags(&format!("#{}", tag)).into_iter().next()
+}
+
+fn valid_tag(tag: &str) -> Result<String, Error> {
+    Ok(normalize(tag).ok_or_else(|| format!("Invalid tag: {}", tag))?)
+}
+
+/// Rename a tag, and the tags nested under it, in every document that has it. With
+/// `dry_run`, report what would change without writing anything.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn rename_tag(
+    app: AppHandle,
+    workspace: String,
+    old: String,
+    new: String,
+    dry_run: Option<bool>,
+) -> Result<RetagReport, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let (old, new) = (valid_tag(&old)?, valid_tag(&new)?);
+    if old == new {
+        return Err("Invalid tag rename: the new name is the same as the old one".into());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        Ok(retag(&app, &root, &[(old, new)], dry_run.unwrap_or(false))?)
+    })
+    .await
+    .map_err(|e| format!("Tag rename task failed: {}", e))?
+}
+
+/// Replace several tags with `target` in every document, dropping repeats where a document
+/// had more than one of them. With `dry_run`, report what would change without writing
+/// anything.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn merge_tags(
+    app: AppHandle,
+    workspace: String,
+    sources: Vec<String>,
+    target: String,
+    dry_run: Option<bool>,
+) -> Result<RetagReport, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let target = valid_tag(&target)?;
+    let mut renames = Vec::new();
+    for source in &sources {
+        let source = valid_tag(source)?;
+        if source != target && !renames.iter().any(|(from, _)| *from == source) {
+            renames.push((source, target.clone()));
+        }
+    }
+    if renames.is_empty() {
+        return Err("Invalid tag merge: no tags other than the target to merge".into());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        Ok(retag(&app, &root, &renames, dry_run.unwrap_or(false))?)
+    })
+    .await
+    .map_err(|e| format!("Tag merge task failed: {}", e))?
+}
@@ -0,0 +1,198 @@
+use crate::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Launch flag that starts in safe mode
+const FLAG: &str = "--safe-mode";
+/// Environment variable that overrides the flag: `1` starts in safe mode, `0` never does. The
+/// restart out of safe mode sets `0`, since a restart keeps the original arguments.
+const ENV: &str = "INKFINITE_SAFE_MODE";
+/// Launches in a row that ended before the app ran for [`STABLE_AFTER`] before safe mode starts
+/// on its own
+const MAX_FAILED_LAUNCHES: u32 = 3;
+/// Running this long counts as a successful launch
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+/// File in the app data dir counting launches that have not yet counted as successful
+const LAUNCHES_FILE: &str = "unfinished-launches";
+const CHANGED_EVENT: &str = "safe-mode:changed";
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Components still disabled, as [`Component`] bits
+static DISABLED: AtomicU8 = AtomicU8::new(0);
+static REASON: OnceLock<Reason> = OnceLock::new();
+
+/// What safe mode turns off
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Component {
+    /// Plugins are not loaded
+    Plugins,
+    /// The metadata cache is rebuilt in memory instead of read from disk
+    Index,
+    /// Stored settings are ignored in favor of the defaults
+    Settings,
+}
+
+const COMPONENTS: [Component; 3] = [Component::Plugins, Component::Index, Component::Settings];
+
+impl Component {
+    fn bit(self) -> u8 {
+        match self {
+            Component::Plugins => 1,
+            Component::Index => 2,
+            Component::Settings => 4,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Reason {
+    /// Started with `--safe-mode` or `INKFINITE_SAFE_MODE=1`
+    Flag,
+    /// Earlier launches kept failing
+    FailedLaunches,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeModeStatus {
+    pub active: bool,
+    pub reason: Option<Reason>,
+    /// Components still turned off
+    pub disabled: Vec<Component>,
+}
+
+/// Whether `component` is turned off by safe mode
+pub fn disabled(component: Component) -> bool {
+    DISABLED.load(Ordering::Relaxed) & component.bit() != 0
+}
+
+fn launches_file(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(LAUNCHES_FILE))
+}
+
+fn status() -> SafeModeStatus {
+    SafeModeStatus {
+        active: ACTIVE.load(Ordering::Relaxed),
+        reason: REASON.get().copied(),
+        disabled: COMPONENTS
+            .into_iter()
+            .filter(|component| disabled(*component))
+            .collect(),
+    }
+}
+
+/// Decide whether this launch runs in safe mode, before anything safe mode turns off starts.
+///
+/// Each launch is counted until it has run for a while; once [`MAX_FAILED_LAUNCHES`] in a row
+/// failed, or with `--safe-mode`, every component starts disabled.
+pub fn init(app: &AppHandle) {
+    let file = launches_file(app);
+    let failed = file
+        .as_ref()
+        .and_then(|file| fs::read_to_string(file).ok())
+        .and_then(|text| text.trim().parse::<u32>().ok())
+        .unwrap_or(0);
+    let forced = match std::env::var(ENV).as_deref() {
+        Ok("1") => Some(true),
+        Ok("0") => Some(false),
+        _ => None,
+    };
+    let reason = if forced == Some(false) {
+        None
+    } else if forced == Some(true) || std::env::args().any(|arg| arg == FLAG) {
+        Some(Reason::Flag)
+    } else if failed >= MAX_FAILED_LAUNCHES {
+        Some(Reason::FailedLaunches)
+    } else {
+        None
+    };
+    if let Some(reason) = reason {
+        let _ = REASON.set(reason);
+        ACTIVE.store(true, Ordering::Relaxed);
+        let all = COMPONENTS
+            .iter()
+            .fold(0, |bits, component| bits | component.bit());
+        DISABLED.store(all, Ordering::Relaxed);
+        tracing::warn!(?reason, failed, "Starting in safe mode");
+    }
+
+    let Some(file) = file else {
+        return;
+    };
+    if let Some(dir) = file.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Err(error) = fs::write(&file, (failed + 1).to_string()) {
+        tracing::warn!(%error, "Failed to record launch");
+    }
+    std::thread::spawn(move || {
+        std::thread::sleep(STABLE_AFTER);
+        let _ = fs::remove_file(&file);
+    });
+}
+
+/// Turn a component back on, or off again, to find which one keeps the app from starting.
+///
+/// Plugins are loaded, the metadata cache is reopened from disk and stored settings apply at
+/// once. Plugins already loaded stay loaded when turned off again, until the next launch.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_safe_mode_component(
+    app: AppHandle,
+    component: Component,
+    enabled: bool,
+) -> Result<SafeModeStatus, Error> {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return Err("Changing components requires safe mode".into());
+    }
+    if enabled != disabled(component) {
+        return Ok(status());
+    }
+    match enabled {
+        true => DISABLED.fetch_and(!component.bit(), Ordering::Relaxed),
+        false => DISABLED.fetch_or(component.bit(), Ordering::Relaxed),
+    };
+    tracing::info!(?component, enabled, "Safe mode component changed");
+    match component {
+        #[cfg(desktop)]
+        Component::Plugins if enabled => crate::plugins::start(app.clone()),
+        Component::Index => crate::catalog::close_all(&app),
+        Component::Settings => {
+            crate::messages::load(&app);
+            crate::hotkeys::register_all(&app);
+        }
+        _ => {}
+    }
+    let status = status();
+    let _ = app.emit(CHANGED_EVENT, status.clone());
+    Ok(status)
+}
+
+/// Whether this launch runs in safe mode, why, and what is still turned off
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_safe_mode() -> SafeModeStatus {
+    status()
+}
+
+/// Restart, leaving safe mode
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn exit_safe_mode(app: AppHandle) {
+    if let Some(file) = launches_file(&app) {
+        let _ = fs::remove_file(file);
+    }
+    crate::saves::flush_all(&app);
+    tracing::info!("Leaving safe mode");
+    std::env::set_var(ENV, "0");
+    app.restart()
+}
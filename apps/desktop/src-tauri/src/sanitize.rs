@@ -0,0 +1,44 @@
+use crate::error::Error;
+use crate::{paths, workspace};
+use std::path::Path;
+use tauri::AppHandle;
+
+pub use inkfinite_core::sanitize::{
+    clean, clean_markdown, clean_with_schemes, load_policy, save_policy, SanitizePolicy,
+};
+
+/// The policy of the open workspace, or the defaults without one
+fn current_policy(app: &AppHandle) -> SanitizePolicy {
+    workspace::current_root(app)
+        .map(|root| load_policy(&root))
+        .unwrap_or_default()
+}
+
+/// Sanitize HTML from a paste or import with the open workspace's allowlist
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn sanitize_html(app: AppHandle, input: String) -> String {
+    clean(&current_policy(&app), &input)
+}
+
+/// Read the sanitization allowlist for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_sanitize_policy(app: AppHandle, workspace: String) -> SanitizePolicy {
+    if paths::check_workspace(&app, &workspace, paths::Scope::Read).is_err() {
+        return SanitizePolicy::default();
+    }
+    load_policy(Path::new(&workspace))
+}
+
+/// Update the sanitization allowlist for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_sanitize_policy(
+    app: AppHandle,
+    workspace: String,
+    policy: SanitizePolicy,
+) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    Ok(save_policy(Path::new(&workspace), &policy)?)
+}
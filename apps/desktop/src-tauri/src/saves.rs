@@ -0,0 +1,247 @@
+use crate::blocking;
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{event_bus, hooks, insights, journal, paths, stats, vault, workspace};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::ipc::Response;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+const DELAY_KEY: &str = "saveDelayMs";
+const DEFAULT_DELAY_MS: u64 = 1000;
+const MAX_DELAY_MS: u64 = 10_000;
+/// Continuous edits are still written at least this often
+const MAX_WAIT: Duration = Duration::from_secs(5);
+
+struct PendingSave {
+    content: String,
+    first: Instant,
+    last: Instant,
+}
+
+impl PendingSave {
+    fn due(&self, delay: Duration) -> Instant {
+        (self.last + delay).min(self.first + MAX_WAIT)
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SaveFailure {
+    path: String,
+    error: String,
+}
+
+/// Managed state coalescing document writes that arrive in quick succession
+pub struct SaveCoordinator {
+    pending: Mutex<HashMap<PathBuf, PendingSave>>,
+    wake: Condvar,
+    delay_ms: AtomicU64,
+    /// Held while writing so an older save never lands after a newer one
+    writing: Mutex<()>,
+}
+
+impl Default for SaveCoordinator {
+    fn default() -> Self {
+        SaveCoordinator {
+            pending: Mutex::new(HashMap::new()),
+            wake: Condvar::new(),
+            delay_ms: AtomicU64::new(DEFAULT_DELAY_MS),
+            writing: Mutex::new(()),
+        }
+    }
+}
+
+impl SaveCoordinator {
+    fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms.load(Ordering::Relaxed))
+    }
+}
+
+/// Save a document once edits pause for the configured delay, replacing any write still
+/// waiting for the same document. Failed writes are reported as `document:save-failed`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn write_document(
+    app: AppHandle,
+    saves: State<'_, SaveCoordinator>,
+    path: String,
+    content: String,
+) -> Result<(), Error> {
+    paths::check(&app, &path, paths::Scope::Write)?;
+    let now = Instant::now();
+    let mut pending = saves
+        .pending
+        .lock()
+        .map_err(|e| format!("Failed to queue save: {}", e))?;
+    let path = PathBuf::from(path);
+    let first = pending.get(&path).map_or(now, |save| save.first);
+    pending.insert(
+        path,
+        PendingSave {
+            content,
+            first,
+            last: now,
+        },
+    );
+    saves.wake.notify_all();
+    Ok(())
+}
+
+/// Write pending saves now, for one document or all of them
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn flush_documents(app: AppHandle, path: Option<String>) {
+    match path {
+        Some(path) => flush(&app, |pending, _| pending == &PathBuf::from(&path)),
+        None => flush_all(&app),
+    }
+}
+
+/// Read a document as raw bytes rather than a JSON string, after writing any save still pending
+/// for it, so the frontend decodes it without an extra layer of escaping
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn read_document(app: AppHandle, path: String) -> Result<Response, Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    blocking::run("read document", move || {
+        let path = PathBuf::from(path);
+        flush(&app, |pending, _| pending == &path);
+        let content = std::fs::read(&path)
+            .map_err(|e| Error::io("Failed to read file", e).with_context(path.display()))?;
+        let content = vault::unseal(&path, &content)?.into_owned();
+        insights::document_opened(&app, &path);
+        Ok::<_, Error>(Response::new(content))
+    })
+    .await
+}
+
+/// How long [`write_document`] waits for edits to pause, in milliseconds
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_save_delay(saves: State<'_, SaveCoordinator>) -> u64 {
+    saves.delay_ms.load(Ordering::Relaxed)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_save_delay(
+    app: AppHandle,
+    saves: State<'_, SaveCoordinator>,
+    delay_ms: u64,
+) -> Result<(), Error> {
+    let delay_ms = delay_ms.min(MAX_DELAY_MS);
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(DELAY_KEY, delay_ms);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    saves.delay_ms.store(delay_ms, Ordering::Relaxed);
+    saves.wake.notify_all();
+    Ok(())
+}
+
+/// Drop the pending save for `path` without writing it, for a document about to be deleted
+pub fn discard(app: &AppHandle, path: &Path) {
+    if let Ok(mut pending) = app.state::<SaveCoordinator>().pending.lock() {
+        pending.remove(path);
+    }
+}
+
+/// Write every pending save; called on focus loss, window close and quit
+pub fn flush_all(app: &AppHandle) {
+    flush(app, |_, _| true);
+}
+
+/// Write the pending saves of documents inside `root`, for a workspace being closed
+pub fn flush_under(app: &AppHandle, root: &Path) {
+    flush(app, |path, _| path.starts_with(root));
+}
+
+/// Load the saved delay and write pending saves as they come due
+pub fn start(app: AppHandle) {
+    let saves = app.state::<SaveCoordinator>();
+    if let Some(delay) = app
+        .store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(DELAY_KEY))
+        .and_then(|value| value.as_u64())
+    {
+        saves
+            .delay_ms
+            .store(delay.min(MAX_DELAY_MS), Ordering::Relaxed);
+    }
+
+    std::thread::spawn(move || loop {
+        let saves = app.state::<SaveCoordinator>();
+        let Ok(pending) = saves.pending.lock() else {
+            return;
+        };
+        let delay = saves.delay();
+        let next = pending.values().map(|save| save.due(delay)).min();
+        let wait = next.map_or(Duration::from_secs(3600), |due| {
+            due.saturating_duration_since(Instant::now())
+        });
+        if !wait.is_zero() {
+            let _ = saves.wake.wait_timeout(pending, wait);
+            continue;
+        }
+        drop(pending);
+
+        let now = Instant::now();
+        flush(&app, |_, save| save.due(delay) <= now);
+    });
+}
+
+fn flush(app: &AppHandle, select: impl Fn(&PathBuf, &PendingSave) -> bool) {
+    let saves = app.state::<SaveCoordinator>();
+    let Ok(_writing) = saves.writing.lock() else {
+        return;
+    };
+    let ready: Vec<(PathBuf, PendingSave)> = {
+        let Ok(mut pending) = saves.pending.lock() else {
+            return;
+        };
+        let paths: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(path, save)| select(path, save))
+            .map(|(path, _)| path.clone())
+            .collect();
+        paths
+            .into_iter()
+            .filter_map(|path| pending.remove(&path).map(|save| (path, save)))
+            .collect()
+    };
+
+    for (path, save) in ready {
+        let result = serde_json::from_str::<BoardFile>(&save.content)
+            .map_err(|e| format!("Invalid file format: {}", e))
+            .and_then(|board| document::write_board(&path, &board).map(|_| board));
+        match result {
+            Ok(board) => {
+                // Before the cache re-reads the document, so it still holds the old word count
+                stats::document_saved(app, &path, &board);
+                journal::document_saved(app, &path);
+                events::file_changed(app, &path, ChangeKind::Modified);
+                hooks::document_saved(app, &path);
+                event_bus::document_saved(app, &path);
+            }
+            Err(error) => {
+                let _ = app.emit(
+                    "document:save-failed",
+                    SaveFailure {
+                        path: path.to_string_lossy().to_string(),
+                        error,
+                    },
+                );
+            }
+        }
+    }
+}
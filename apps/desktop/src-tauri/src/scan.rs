@@ -0,0 +1,207 @@
+use crate::cancel::{self, CancelToken};
+use crate::document::DOCUMENT_EXTENSION;
+use crate::error::Error;
+use crate::paths;
+use jwalk::{Parallelism, WalkDir};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+/// Emitted with each batch of entries while [`scan_workspace`] runs
+pub const SCAN_PROGRESS: &str = "workspace:scan-progress";
+/// Entries described per progress event
+const BATCH_SIZE: usize = 256;
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannedEntry {
+    pub path: String,
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: i64,
+    /// SHA-256 of a document's contents, when hashing was requested
+    pub hash: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ScanProgress {
+    entries: Vec<ScannedEntry>,
+    /// Entries described so far, including this batch
+    scanned: usize,
+}
+
+#[derive(serde::Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSummary {
+    pub documents: usize,
+    pub directories: usize,
+    pub bytes: u64,
+    pub failed: Vec<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Walk the workspace in parallel, emitting documents and folders as `workspace:scan-progress`
+/// batches so the file tree can fill in before the scan finishes.
+///
+/// `threads` bounds both directory reading and hashing; it defaults to half the available cores.
+/// Hidden folders are skipped, as everywhere else in the workspace.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn scan_workspace(
+    app: AppHandle,
+    workspace: String,
+    threads: Option<usize>,
+    hash: Option<bool>,
+    op_id: Option<String>,
+) -> Result<ScanSummary, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let operation = cancel::begin(&app, op_id);
+    tauri::async_runtime::spawn_blocking(move || {
+        scan(
+            &app,
+            Path::new(&workspace),
+            threads.unwrap_or_else(default_threads).max(1),
+            hash.unwrap_or(true),
+            operation.token(),
+        )
+    })
+    .await
+    .map_err(|e| format!("Scan task failed: {}", e))?
+    .map_err(Error::from)
+}
+
+fn scan(
+    app: &AppHandle,
+    root: &Path,
+    threads: usize,
+    hash: bool,
+    cancel: &CancelToken,
+) -> Result<ScanSummary, String> {
+    if !root.is_dir() {
+        return Err(format!("Directory does not exist: {}", root.display()));
+    }
+    let started = Instant::now();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("scan-{}", index))
+        .build()
+        .map(Arc::new)
+        .map_err(|e| format!("Failed to start scan threads: {}", e))?;
+
+    let walk = WalkDir::new(root)
+        .skip_hidden(true)
+        .parallelism(Parallelism::RayonExistingPool {
+            pool: pool.clone(),
+            // The iterating thread is not part of the pool, so it cannot starve it
+            busy_timeout: None,
+        });
+
+    let mut summary = ScanSummary::default();
+    let mut scanned = 0;
+    let mut batch = Vec::new();
+    let mut flush = |batch: &mut Vec<(PathBuf, bool)>, summary: &mut ScanSummary| {
+        let described: Vec<_> = pool.install(|| {
+            batch
+                .par_iter()
+                .map(|(path, is_dir)| {
+                    cancel.check()?;
+                    describe(path, *is_dir, hash)
+                })
+                .collect()
+        });
+        batch.clear();
+
+        let mut entries = Vec::with_capacity(described.len());
+        for result in described {
+            match result {
+                Ok(entry) => {
+                    if entry.is_dir {
+                        summary.directories += 1;
+                    } else {
+                        summary.documents += 1;
+                        summary.bytes += entry.size;
+                    }
+                    entries.push(entry);
+                }
+                Err(error) => summary.failed.push(error),
+            }
+        }
+        scanned += entries.len();
+        let _ = app.emit(SCAN_PROGRESS, ScanProgress { entries, scanned });
+    };
+
+    for entry in walk {
+        cancel.check()?;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                summary.failed.push(error.to_string());
+                continue;
+            }
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+        let is_dir = entry.file_type().is_dir();
+        if !is_dir
+            && !entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(DOCUMENT_EXTENSION)
+        {
+            continue;
+        }
+        batch.push((entry.path(), is_dir));
+        if batch.len() >= BATCH_SIZE {
+            flush(&mut batch, &mut summary);
+        }
+    }
+    if !batch.is_empty() {
+        flush(&mut batch, &mut summary);
+    }
+    // Entries skipped in the last batch were counted as failures rather than reported
+    cancel.check()?;
+
+    summary.elapsed_ms = started.elapsed().as_millis() as u64;
+    Ok(summary)
+}
+
+fn describe(path: &Path, is_dir: bool, hash: bool) -> Result<ScannedEntry, String> {
+    let metadata = fs::metadata(path)
+        .map_err(|e| format!("{}: Failed to read metadata: {}", path.display(), e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| elapsed.as_millis() as i64);
+    let hash = if hash && !is_dir {
+        let bytes = fs::read(path)
+            .map_err(|e| format!("{}: Failed to read file: {}", path.display(), e))?;
+        Some(format!("{:x}", Sha256::digest(&bytes)))
+    } else {
+        None
+    };
+    Ok(ScannedEntry {
+        path: path.to_string_lossy().to_string(),
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        is_dir,
+        size: if is_dir { 0 } else { metadata.len() },
+        modified,
+        hash,
+    })
+}
+
+fn default_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|cores| (cores.get() / 2).max(1))
+        .unwrap_or(2)
+}
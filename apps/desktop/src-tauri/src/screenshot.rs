@@ -0,0 +1,274 @@
+use crate::assets::{self, SavedAsset, ASSETS_DIR};
+use crate::error::Error;
+use crate::paths;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// What part of the screen to capture
+#[derive(serde::Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureMode {
+    #[default]
+    Full,
+    /// Let the user pick a window
+    Window,
+    /// Let the user drag a rectangle
+    Region,
+}
+
+/// Capture the screen with the OS screenshot tool and save it into a document's assets folder.
+///
+/// Interactive modes wait for the user; cancelling the selection returns an error.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn capture_screenshot(
+    app: AppHandle,
+    mode: Option<CaptureMode>,
+    document: Option<String>,
+) -> Result<SavedAsset, Error> {
+    if let Some(document) = &document {
+        paths::check(&app, document, paths::Scope::Write)?;
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        capture(&app, mode.unwrap_or_default(), document.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Screenshot task failed: {}", e))?
+    .map_err(Error::from)
+}
+
+pub fn capture(
+    app: &AppHandle,
+    mode: CaptureMode,
+    document: Option<&str>,
+) -> Result<SavedAsset, String> {
+    let destination = match document {
+        Some(document) => destination_for(app, Path::new(document)),
+        None => assets::default_dir(app)?,
+    };
+    let path = assets::timestamped_path(&destination, "screenshot", "png")?;
+
+    if let Err(error) = platform::capture(mode, &path) {
+        let _ = std::fs::remove_file(&path);
+        return Err(error);
+    }
+    // Most tools exit successfully without writing anything when the selection is cancelled
+    if std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0) == 0 {
+        let _ = std::fs::remove_file(&path);
+        return Err("Screenshot was cancelled".to_string());
+    }
+
+    Ok(assets::describe(app, &path))
+}
+
+/// The workspace assets folder, or an `assets` folder beside documents outside a workspace
+fn destination_for(app: &AppHandle, document: &Path) -> PathBuf {
+    crate::workspace::current_root(app)
+        .filter(|root| document.starts_with(root))
+        .map(|root| root.join(ASSETS_DIR))
+        .unwrap_or_else(|| document.parent().unwrap_or(Path::new(".")).join(ASSETS_DIR))
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::CaptureMode;
+    use std::path::Path;
+    use std::process::Command;
+
+    pub fn capture(mode: CaptureMode, path: &Path) -> Result<(), String> {
+        let mut command = Command::new("screencapture");
+        command.arg("-x");
+        match mode {
+            CaptureMode::Full => {}
+            CaptureMode::Window => {
+                command.args(["-i", "-w"]);
+            }
+            CaptureMode::Region => {
+                command.args(["-i", "-s"]);
+            }
+        }
+        let status = command
+            .arg(path)
+            .status()
+            .map_err(|e| format!("Failed to run screencapture: {}", e))?;
+        // screencapture exits with 1 when an interactive capture is cancelled
+        if !status.success() && path.exists() {
+            return Err("screencapture failed".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(desktop, unix, not(target_os = "macos")))]
+mod platform {
+    use super::CaptureMode;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    pub fn capture(mode: CaptureMode, path: &Path) -> Result<(), String> {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        if wayland && available("grim") {
+            return grim(mode, path);
+        }
+        if available("gnome-screenshot") {
+            let flag = match mode {
+                CaptureMode::Full => None,
+                CaptureMode::Window => Some("-w"),
+                CaptureMode::Region => Some("-a"),
+            };
+            return run(
+                Command::new("gnome-screenshot")
+                    .args(flag)
+                    .arg("-f")
+                    .arg(path),
+                path,
+            );
+        }
+        if available("spectacle") {
+            let flag = match mode {
+                CaptureMode::Full => "-f",
+                CaptureMode::Window => "-a",
+                CaptureMode::Region => "-r",
+            };
+            return run(
+                Command::new("spectacle")
+                    .args(["-b", "-n", flag, "-o"])
+                    .arg(path),
+                path,
+            );
+        }
+        if !wayland && available("scrot") {
+            // Clicking a window during a selection captures that window
+            let flag = match mode {
+                CaptureMode::Full => None,
+                CaptureMode::Window | CaptureMode::Region => Some("-s"),
+            };
+            return run(Command::new("scrot").args(flag).arg("-o").arg(path), path);
+        }
+        Err(
+            "No screenshot tool found; install grim, gnome-screenshot, spectacle, or scrot"
+                .to_string(),
+        )
+    }
+
+    fn grim(mode: CaptureMode, path: &Path) -> Result<(), String> {
+        let mut command = Command::new("grim");
+        if !matches!(mode, CaptureMode::Full) {
+            // grim cannot pick windows; slurp's selection covers both interactive modes
+            let output = Command::new("slurp")
+                .output()
+                .map_err(|e| format!("Failed to run slurp: {}", e))?;
+            if !output.status.success() {
+                return Ok(());
+            }
+            command
+                .arg("-g")
+                .arg(String::from_utf8_lossy(&output.stdout).trim());
+        }
+        run(command.arg(path), path)
+    }
+
+    fn run(command: &mut Command, path: &Path) -> Result<(), String> {
+        let output = command
+            .output()
+            .map_err(|e| format!("Failed to run screenshot tool: {}", e))?;
+        // A non-zero exit without output is a cancelled selection, reported by the caller
+        if !output.status.success() && path.exists() {
+            return Err(format!(
+                "Screenshot failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    fn available(name: &str) -> bool {
+        Command::new("which")
+            .arg(name)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::CaptureMode;
+    use std::path::Path;
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    /// How long to wait for the user to finish a Snipping Tool selection
+    const SELECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub fn capture(mode: CaptureMode, path: &Path) -> Result<(), String> {
+        match mode {
+            CaptureMode::Full => full_screen(path),
+            CaptureMode::Window | CaptureMode::Region => snip(path),
+        }
+    }
+
+    /// Copy the whole virtual desktop with System.Drawing
+    fn full_screen(path: &Path) -> Result<(), String> {
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms,System.Drawing; \
+             $b = [System.Windows.Forms.SystemInformation]::VirtualScreen; \
+             $bmp = New-Object System.Drawing.Bitmap $b.Width, $b.Height; \
+             $g = [System.Drawing.Graphics]::FromImage($bmp); \
+             $g.CopyFromScreen($b.Left, $b.Top, 0, 0, $bmp.Size); \
+             $bmp.Save('{}', [System.Drawing.Imaging.ImageFormat]::Png)",
+            path.to_string_lossy().replace('\'', "''")
+        );
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .output()
+            .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Screenshot failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Open the Snipping Tool overlay and wait for its result on the clipboard
+    fn snip(path: &Path) -> Result<(), String> {
+        let mut clipboard =
+            arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {}", e))?;
+        let _ = clipboard.clear();
+
+        Command::new("explorer")
+            .arg("ms-screenclip:")
+            .status()
+            .map_err(|e| format!("Failed to open Snipping Tool: {}", e))?;
+
+        let started = Instant::now();
+        while started.elapsed() < SELECTION_TIMEOUT {
+            if let Ok(data) = clipboard.get_image() {
+                let image = image::RgbaImage::from_raw(
+                    data.width as u32,
+                    data.height as u32,
+                    data.bytes.into_owned(),
+                )
+                .ok_or_else(|| "Screenshot has an unexpected size".to_string())?;
+                return image
+                    .save_with_format(path, image::ImageFormat::Png)
+                    .map_err(|e| format!("Failed to encode image: {}", e));
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(mobile)]
+mod platform {
+    use super::CaptureMode;
+    use std::path::Path;
+
+    pub fn capture(_mode: CaptureMode, _path: &Path) -> Result<(), String> {
+        Err("Screenshots are not supported on this platform".to_string())
+    }
+}
@@ -0,0 +1,79 @@
+use crate::archive;
+use crate::blocking;
+use crate::cancel;
+use crate::catalog;
+use crate::error::Error;
+use crate::paths;
+use crate::vault;
+use crate::workspace;
+use inkfinite_core::search;
+use std::path::Path;
+use tauri::AppHandle;
+
+pub use inkfinite_core::search::{index_text, remove_text, warm, SearchHit, DEFAULT_LIMIT};
+
+/// Case-insensitive full-text search over workspace documents and indexed asset text.
+///
+/// With `tag`, only documents carrying that tag are searched, and asset text is skipped. Vault
+/// documents are searched only while their vault is unlocked, and never by tag. Documents in
+/// the archive folder are skipped unless `include_archived` is set.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn search_workspace(
+    app: AppHandle,
+    workspace: String,
+    query: String,
+    limit: Option<usize>,
+    tag: Option<String>,
+    op_id: Option<String>,
+    include_archived: Option<bool>,
+) -> Result<Vec<SearchHit>, Error> {
+    blocking::run_long("search workspace", move || {
+        search_documents(app, workspace, query, limit, tag, op_id, include_archived)
+    })
+    .await
+}
+
+/// The search behind [`search_workspace`], run on the calling thread
+pub fn search_documents(
+    app: AppHandle,
+    workspace: String,
+    query: String,
+    limit: Option<usize>,
+    tag: Option<String>,
+    op_id: Option<String>,
+    include_archived: Option<bool>,
+) -> Result<Vec<SearchHit>, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let operation = cancel::begin(&app, op_id);
+    let root = Path::new(&workspace);
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // The metadata cache saves walking the workspace; it is only required for tag filters
+    let mut documents = match catalog::document_paths(&app, root, tag.as_deref()) {
+        Ok(documents) => documents,
+        Err(_) if tag.is_none() => workspace::list_documents(root)?,
+        Err(error) => return Err(error.into()),
+    };
+    // The cache leaves vault documents out
+    if tag.is_none() {
+        documents.extend(vault::unlocked_documents(root));
+        documents.sort();
+        documents.dedup();
+    }
+    if !include_archived.unwrap_or(false) {
+        let folder = archive::folder(root);
+        documents
+            .retain(|path| !archive::is_archived(&folder, &workspace::relative_path(root, path)));
+    }
+    Ok(search::search(
+        root,
+        &documents,
+        &query,
+        limit.unwrap_or(DEFAULT_LIMIT),
+        tag.is_none(),
+        operation.token(),
+    )?)
+}
@@ -0,0 +1,171 @@
+use crate::error::Error;
+use crate::{paths, workspace};
+#[cfg(not(target_os = "android"))]
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Service name the OS keychain files every entry under
+#[cfg(not(target_os = "android"))]
+const SERVICE: &str = "inkfinite";
+/// Names of the secrets stored for a workspace, since keychains cannot list them; never values
+const INDEX_CONFIG: &str = "secrets";
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct SecretIndex {
+    names: BTreeSet<String>,
+}
+
+/// Keychain entry for secret `name` of the workspace at `root`, such as a sync token.
+///
+/// Entries are keyed by a hash of the workspace path so names do not collide across workspaces.
+#[cfg(not(target_os = "android"))]
+fn entry(root: &Path, name: &str) -> Result<keyring::Entry, String> {
+    if name.trim().is_empty() {
+        return Err("Invalid secret name: empty".to_string());
+    }
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let digest = format!("{:x}", Sha256::digest(root.to_string_lossy().as_bytes()));
+    keyring::Entry::new(SERVICE, &format!("{}:{}", &digest[..16], name))
+        .map_err(|e| format!("Failed to open keychain: {}", e))
+}
+
+/// Save a secret in the OS keychain, replacing any previous value
+#[cfg(not(target_os = "android"))]
+pub fn store(root: &Path, name: &str, value: &str) -> Result<(), String> {
+    entry(root, name)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to store secret: {}", e))?;
+    update_index(root, |names| {
+        names.insert(name.to_string());
+    })
+}
+
+/// Read a secret from the OS keychain, `None` when it was never stored
+#[cfg(not(target_os = "android"))]
+pub fn get(root: &Path, name: &str) -> Result<Option<String>, String> {
+    match entry(root, name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read secret: {}", e)),
+    }
+}
+
+/// Remove a secret from the OS keychain, returning `false` when there was none
+#[cfg(not(target_os = "android"))]
+pub fn delete(root: &Path, name: &str) -> Result<bool, String> {
+    let deleted = match entry(root, name)?.delete_credential() {
+        Ok(()) => true,
+        Err(keyring::Error::NoEntry) => false,
+        Err(e) => return Err(format!("Failed to delete secret: {}", e)),
+    };
+    update_index(root, |names| {
+        names.remove(name);
+    })?;
+    Ok(deleted)
+}
+
+/// Keychain entry for an app-wide secret such as an AI provider key, shared by every workspace
+#[cfg(desktop)]
+fn app_entry(name: &str) -> Result<keyring::Entry, String> {
+    if name.trim().is_empty() {
+        return Err("Invalid secret name: empty".to_string());
+    }
+    keyring::Entry::new(SERVICE, &format!("app:{}", name))
+        .map_err(|e| format!("Failed to open keychain: {}", e))
+}
+
+#[cfg(desktop)]
+pub fn store_app(name: &str, value: &str) -> Result<(), String> {
+    app_entry(name)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to store secret: {}", e))
+}
+
+#[cfg(desktop)]
+pub fn get_app(name: &str) -> Result<Option<String>, String> {
+    match app_entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read secret: {}", e)),
+    }
+}
+
+#[cfg(desktop)]
+pub fn delete_app(name: &str) -> Result<bool, String> {
+    match app_entry(name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!("Failed to delete secret: {}", e)),
+    }
+}
+
+#[cfg(target_os = "android")]
+pub fn store(_root: &Path, _name: &str, _value: &str) -> Result<(), String> {
+    Err("A keychain is not supported on this platform".to_string())
+}
+
+#[cfg(target_os = "android")]
+pub fn get(_root: &Path, _name: &str) -> Result<Option<String>, String> {
+    Err("A keychain is not supported on this platform".to_string())
+}
+
+#[cfg(target_os = "android")]
+pub fn delete(_root: &Path, _name: &str) -> Result<bool, String> {
+    Err("A keychain is not supported on this platform".to_string())
+}
+
+/// Names of the secrets stored for the workspace at `root`
+pub fn names(root: &Path) -> Result<BTreeSet<String>, String> {
+    let index: SecretIndex = workspace::read_config(root, INDEX_CONFIG)?;
+    Ok(index.names)
+}
+
+#[cfg(not(target_os = "android"))]
+fn update_index(root: &Path, change: impl FnOnce(&mut BTreeSet<String>)) -> Result<(), String> {
+    let mut index: SecretIndex = workspace::read_config(root, INDEX_CONFIG)?;
+    change(&mut index.names);
+    workspace::write_config(root, INDEX_CONFIG, &index)
+}
+
+/// Store a credential, API key or passphrase for a workspace in the OS keychain
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn store_secret(
+    app: AppHandle,
+    workspace: String,
+    name: String,
+    value: String,
+) -> Result<(), Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    Ok(store(&root, &name, &value)?)
+}
+
+/// Read a workspace secret from the OS keychain
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_secret(
+    app: AppHandle,
+    workspace: String,
+    name: String,
+) -> Result<Option<String>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(get(&root, &name)?)
+}
+
+/// Remove a workspace secret from the OS keychain; `false` when it did not exist
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_secret(app: AppHandle, workspace: String, name: String) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    Ok(delete(&root, &name)?)
+}
+
+/// Names of the secrets stored for a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_secrets(app: AppHandle, workspace: String) -> Result<Vec<String>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(names(&root)?.into_iter().collect())
+}
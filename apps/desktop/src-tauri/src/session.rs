@@ -0,0 +1,184 @@
+use crate::error::Error;
+use crate::paths;
+use crate::windows::WindowRegistry;
+use crate::workspace;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, WebviewWindow};
+use tauri_plugin_store::StoreExt;
+
+const SESSIONS_KEY: &str = "sessions";
+
+/// Window placement in physical pixels
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+    pub fullscreen: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SidebarState {
+    pub open: bool,
+    pub width: Option<f64>,
+    /// Panel shown in the sidebar, as chosen by the frontend
+    pub panel: Option<String>,
+}
+
+impl Default for SidebarState {
+    fn default() -> Self {
+        Self {
+            open: true,
+            width: None,
+            panel: None,
+        }
+    }
+}
+
+/// Layout of the window last used for a workspace
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Session {
+    pub geometry: Option<WindowGeometry>,
+    pub zoom: f64,
+    /// Paths of the documents open as tabs
+    pub tabs: Vec<String>,
+    pub active_tab: Option<String>,
+    pub sidebar: SidebarState,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            geometry: None,
+            zoom: 1.0,
+            tabs: Vec::new(),
+            active_tab: None,
+            sidebar: SidebarState::default(),
+        }
+    }
+}
+
+/// Save the calling window's layout for a workspace; its geometry is read from the window
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_session(
+    window: WebviewWindow,
+    workspace: String,
+    session: Session,
+) -> Result<(), Error> {
+    paths::check_workspace(window.app_handle(), &workspace, paths::Scope::Read)?;
+    let session = Session {
+        geometry: geometry(&window).or(session.geometry),
+        ..session
+    };
+    Ok(update(window.app_handle(), &workspace, |saved| {
+        *saved = session
+    })?)
+}
+
+/// Apply the saved geometry and zoom for a workspace to the calling window and return the
+/// session so the frontend can reopen its tabs and sidebar
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn restore_session(window: WebviewWindow, workspace: String) -> Session {
+    let session = sessions(window.app_handle())
+        .remove(&workspace)
+        .unwrap_or_default();
+
+    if let Some(geometry) = session
+        .geometry
+        .filter(|geometry| on_screen(&window, geometry))
+    {
+        let _ = window.set_size(PhysicalSize::new(geometry.width, geometry.height));
+        let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+        if geometry.fullscreen {
+            let _ = window.set_fullscreen(true);
+        } else if geometry.maximized {
+            let _ = window.maximize();
+        }
+    }
+    if session.zoom > 0.0 {
+        let _ = window.set_zoom(session.zoom);
+    }
+    session
+}
+
+/// Record a closing window's geometry against the workspace it shows, keeping the rest of
+/// the saved session
+pub fn save_geometry(window: &WebviewWindow) {
+    let app = window.app_handle();
+    let Some(workspace) = app
+        .state::<WindowRegistry>()
+        .get(window.label())
+        .workspace
+        .or_else(|| workspace::current_root(app).map(|root| root.to_string_lossy().to_string()))
+    else {
+        return;
+    };
+    if let Some(geometry) = geometry(window) {
+        let _ = update(app, &workspace, |saved| saved.geometry = Some(geometry));
+    }
+}
+
+fn geometry(window: &WebviewWindow) -> Option<WindowGeometry> {
+    let maximized = window.is_maximized().unwrap_or(false);
+    let fullscreen = window.is_fullscreen().unwrap_or(false);
+    let position = window.outer_position().ok()?;
+    let size = window.inner_size().ok()?;
+    Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized,
+        fullscreen,
+    })
+}
+
+/// Whether the window's top-left corner lands on a connected monitor, so a session saved
+/// with a display that has since been unplugged does not open off screen
+fn on_screen(window: &WebviewWindow, geometry: &WindowGeometry) -> bool {
+    if geometry.width == 0 || geometry.height == 0 {
+        return false;
+    }
+    window.available_monitors().is_ok_and(|monitors| {
+        monitors.iter().any(|monitor| {
+            let position = monitor.position();
+            let size = monitor.size();
+            geometry.x >= position.x
+                && geometry.y >= position.y
+                && i64::from(geometry.x) < i64::from(position.x) + i64::from(size.width)
+                && i64::from(geometry.y) < i64::from(position.y) + i64::from(size.height)
+        })
+    })
+}
+
+fn sessions(app: &AppHandle) -> HashMap<String, Session> {
+    app.store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(SESSIONS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn update(
+    app: &AppHandle,
+    workspace: &str,
+    change: impl FnOnce(&mut Session),
+) -> Result<(), String> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let mut all = sessions(app);
+    change(all.entry(workspace.to_string()).or_default());
+    let value = serde_json::to_value(&all).map_err(|e| format!("Failed to save session: {}", e))?;
+    store.set(SESSIONS_KEY, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
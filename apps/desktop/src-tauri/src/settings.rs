@@ -0,0 +1,466 @@
+use crate::error::Error;
+use crate::{messages, paths, safe_mode, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_store::StoreExt;
+
+const STORE_KEY: &str = "settings";
+/// Per-workspace overrides in `.inkfinite/settings.json`
+const WORKSPACE_CONFIG: &str = "settings";
+const VERSION_KEY: &str = "version";
+/// Bumped when stored settings change shape; older ones are migrated on load
+const VERSION: u64 = 1;
+/// Emitted to every window after settings change
+const CHANGED_EVENT: &str = "settings:changed";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    Stable,
+    Beta,
+}
+
+/// App settings with every value filled in
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub theme: Theme,
+    /// BCP 47 tag of the interface language; the system language when unset
+    pub language: Option<String>,
+    pub font_family: String,
+    pub font_size: u32,
+    pub line_height: f64,
+    pub spellcheck: bool,
+    /// LanguageTool server to check grammar with instead of the bundled one
+    pub grammar_server: Option<String>,
+    pub snap_to_grid: bool,
+    pub grid_size: u32,
+    pub reduced_motion: bool,
+    /// Ask before deleting documents and folders
+    pub confirm_delete: bool,
+    pub update_channel: UpdateChannel,
+    /// Check for and download updates in the background
+    pub auto_update: bool,
+    /// Count feature use and command timings on this device
+    pub usage_analytics: bool,
+    /// Allow usage analytics to be uploaded when asked to; nothing is ever sent on its own
+    pub share_usage_analytics: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: Theme::Dark,
+            language: None,
+            font_family: "Inter".to_string(),
+            font_size: 16,
+            line_height: 1.5,
+            spellcheck: true,
+            grammar_server: None,
+            snap_to_grid: false,
+            grid_size: 20,
+            reduced_motion: false,
+            confirm_delete: true,
+            update_channel: UpdateChannel::Stable,
+            auto_update: true,
+            usage_analytics: true,
+            share_usage_analytics: false,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Choice,
+}
+
+/// What a setting accepts
+struct Spec {
+    key: &'static str,
+    kind: Kind,
+    description: &'static str,
+    range: Option<(f64, f64)>,
+    options: &'static [&'static str],
+    /// Whether a workspace may override it
+    workspace: bool,
+}
+
+const fn spec(key: &'static str, kind: Kind, description: &'static str) -> Spec {
+    Spec {
+        key,
+        kind,
+        description,
+        range: None,
+        options: &[],
+        workspace: false,
+    }
+}
+
+const SPECS: &[Spec] = &[
+    Spec {
+        options: &["system", "light", "dark"],
+        ..spec("theme", Kind::Choice, "Color theme")
+    },
+    spec("language", Kind::String, "Interface language"),
+    Spec {
+        workspace: true,
+        ..spec("fontFamily", Kind::String, "Editor font")
+    },
+    Spec {
+        range: Some((8.0, 48.0)),
+        workspace: true,
+        ..spec("fontSize", Kind::Integer, "Editor font size in points")
+    },
+    Spec {
+        range: Some((1.0, 3.0)),
+        workspace: true,
+        ..spec("lineHeight", Kind::Number, "Editor line height")
+    },
+    Spec {
+        workspace: true,
+        ..spec("spellcheck", Kind::Boolean, "Check spelling while typing")
+    },
+    spec(
+        "grammarServer",
+        Kind::String,
+        "LanguageTool server to check grammar with",
+    ),
+    Spec {
+        workspace: true,
+        ..spec(
+            "snapToGrid",
+            Kind::Boolean,
+            "Snap shapes to the canvas grid",
+        )
+    },
+    Spec {
+        range: Some((4.0, 200.0)),
+        workspace: true,
+        ..spec("gridSize", Kind::Integer, "Canvas grid spacing")
+    },
+    spec("reducedMotion", Kind::Boolean, "Turn off animations"),
+    spec(
+        "confirmDelete",
+        Kind::Boolean,
+        "Ask before deleting documents and folders",
+    ),
+    Spec {
+        options: &["stable", "beta"],
+        ..spec("updateChannel", Kind::Choice, "Which releases to update to")
+    },
+    spec(
+        "autoUpdate",
+        Kind::Boolean,
+        "Download updates in the background",
+    ),
+    spec(
+        "usageAnalytics",
+        Kind::Boolean,
+        "Count feature use and command timings on this device",
+    ),
+    spec(
+        "shareUsageAnalytics",
+        Kind::Boolean,
+        "Allow uploading usage analytics when asked to",
+    ),
+];
+
+/// A setting as described to the settings screen
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingInfo {
+    pub key: &'static str,
+    pub kind: Kind,
+    pub description: &'static str,
+    pub default: Value,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub options: &'static [&'static str],
+    /// Whether a workspace may override it
+    pub workspace: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedSettings {
+    pub settings: Settings,
+    /// Keys the workspace overrides
+    pub overridden: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SettingsChanged {
+    /// Workspace whose overrides changed; global settings when unset
+    workspace: Option<String>,
+    changed: Vec<String>,
+}
+
+impl Spec {
+    fn find(key: &str) -> Option<&'static Spec> {
+        SPECS.iter().find(|spec| spec.key == key)
+    }
+
+    fn check(&self, value: &Value) -> Result<(), String> {
+        let (valid, expected) = match self.kind {
+            Kind::Boolean => (value.is_boolean(), "true or false"),
+            Kind::Integer => (value.is_u64(), "a whole number"),
+            Kind::Number => (value.is_number(), "a number"),
+            Kind::String => (value.is_string(), "text"),
+            Kind::Choice => (
+                value
+                    .as_str()
+                    .is_some_and(|value| self.options.contains(&value)),
+                "one of the listed options",
+            ),
+        };
+        if !valid {
+            return Err(format!(
+                "Invalid setting {}: expected {}",
+                self.key, expected
+            ));
+        }
+        if let (Some((min, max)), Some(number)) = (self.range, value.as_f64()) {
+            if number < min || number > max {
+                return Err(format!(
+                    "Invalid setting {}: must be between {} and {}",
+                    self.key, min, max
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check changes before they are stored; `null` resets a setting
+fn validate(changes: &Map<String, Value>, per_workspace: bool) -> Result<(), String> {
+    for (key, value) in changes {
+        let spec =
+            Spec::find(key).ok_or_else(|| format!("Invalid setting: unknown key {}", key))?;
+        if per_workspace && !spec.workspace {
+            return Err(format!(
+                "Invalid setting {}: it cannot be set per workspace",
+                key
+            ));
+        }
+        if !value.is_null() {
+            spec.check(value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Stored values without the version, leaving out anything a hand edit broke
+fn values(mut stored: Map<String, Value>) -> Map<String, Value> {
+    stored.remove(VERSION_KEY);
+    stored.retain(
+        |key, value| match Spec::find(key).map(|spec| spec.check(value)) {
+            Some(Ok(())) => true,
+            Some(Err(error)) => {
+                tracing::warn!(%error, "Ignoring stored setting");
+                false
+            }
+            None => false,
+        },
+    );
+    stored
+}
+
+fn global(app: &AppHandle) -> Map<String, Value> {
+    if safe_mode::disabled(safe_mode::Component::Settings) {
+        return Map::new();
+    }
+    match app
+        .store(workspace::STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(STORE_KEY))
+    {
+        Some(Value::Object(stored)) => values(stored),
+        _ => Map::new(),
+    }
+}
+
+fn overrides(root: &Path) -> Result<Map<String, Value>, String> {
+    let mut stored: Map<String, Value> = workspace::read_config(root, WORKSPACE_CONFIG)?;
+    stored
+        .retain(|key, _| key == VERSION_KEY || Spec::find(key).is_some_and(|spec| spec.workspace));
+    Ok(values(stored))
+}
+
+fn resolve(global: Map<String, Value>, overrides: Map<String, Value>) -> ResolvedSettings {
+    let mut merged = match serde_json::to_value(Settings::default()) {
+        Ok(Value::Object(defaults)) => defaults,
+        _ => Map::new(),
+    };
+    let overridden = overrides.keys().cloned().collect();
+    merged.extend(global);
+    merged.extend(overrides);
+    ResolvedSettings {
+        settings: serde_json::from_value(Value::Object(merged)).unwrap_or_default(),
+        overridden,
+    }
+}
+
+/// Effective settings for the backend: the global ones with a workspace's overrides on top
+pub fn current(app: &AppHandle, root: Option<&Path>) -> Settings {
+    let overrides = root
+        .and_then(|root| overrides(root).ok())
+        .unwrap_or_default();
+    resolve(global(app), overrides).settings
+}
+
+/// Move settings written by older versions into the current layout, at startup
+pub fn load(app: &AppHandle) {
+    if let Err(error) = migrate(app) {
+        tracing::warn!(%error, "Failed to migrate settings");
+    }
+}
+
+/// Version the stored settings were last migrated to, when it is older than this build's
+pub fn pending_migration(app: &AppHandle) -> Option<u64> {
+    let store = app.store(workspace::STORE_NAME).ok()?;
+    let version = store
+        .get(STORE_KEY)
+        .and_then(|stored| stored.get(VERSION_KEY).and_then(Value::as_u64))
+        .unwrap_or(0);
+    (version < VERSION).then_some(version)
+}
+
+fn migrate(app: &AppHandle) -> Result<(), String> {
+    let store = app
+        .store(workspace::STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let mut stored = match store.get(STORE_KEY) {
+        Some(Value::Object(stored)) => stored,
+        _ => Map::new(),
+    };
+    let version = stored.get(VERSION_KEY).and_then(Value::as_u64).unwrap_or(0);
+    if version >= VERSION {
+        return Ok(());
+    }
+    if version < 1 {
+        // Settings used to be loose keys at the top of the store
+        for spec in SPECS {
+            if let Some(value) = store.get(spec.key) {
+                if spec.check(&value).is_ok() {
+                    stored.entry(spec.key).or_insert(value);
+                }
+                store.delete(spec.key);
+            }
+        }
+    }
+    tracing::info!(from = version, to = VERSION, "Migrated settings");
+    stored.insert(VERSION_KEY.to_string(), Value::from(VERSION));
+    store.set(STORE_KEY, Value::Object(stored));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// Apply changes to a stored layer, dropping keys set to `null`
+fn apply(stored: &mut Map<String, Value>, changes: &Map<String, Value>) {
+    for (key, value) in changes {
+        match value {
+            Value::Null => stored.remove(key),
+            value => stored.insert(key.clone(), value.clone()),
+        };
+    }
+    stored.insert(VERSION_KEY.to_string(), Value::from(VERSION));
+}
+
+/// Every setting with its type, default, limits and whether a workspace may override it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_settings_schema() -> Vec<SettingInfo> {
+    let defaults = serde_json::to_value(Settings::default()).unwrap_or_default();
+    SPECS
+        .iter()
+        .map(|spec| SettingInfo {
+            key: spec.key,
+            kind: spec.kind,
+            description: spec.description,
+            default: defaults[spec.key].clone(),
+            minimum: spec.range.map(|(min, _)| min),
+            maximum: spec.range.map(|(_, max)| max),
+            options: spec.options,
+            workspace: spec.workspace,
+        })
+        .collect()
+}
+
+/// Effective settings, with the overrides of `workspace` when one is given
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_settings(app: AppHandle, workspace: Option<String>) -> Result<ResolvedSettings, Error> {
+    let overrides = match &workspace {
+        Some(workspace) => overrides(&paths::check_workspace(
+            &app,
+            workspace,
+            paths::Scope::Read,
+        )?)?,
+        None => Map::new(),
+    };
+    Ok(resolve(global(&app), overrides))
+}
+
+/// Validate and store changes to the global settings, or to the overrides of `workspace`.
+/// A `null` value resets the setting. Emits `settings:changed`.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn update_settings(
+    app: AppHandle,
+    changes: Map<String, Value>,
+    workspace: Option<String>,
+) -> Result<ResolvedSettings, Error> {
+    validate(&changes, workspace.is_some())?;
+    let resolved = match &workspace {
+        Some(workspace) => {
+            let root = paths::check_workspace(&app, workspace, paths::Scope::Write)?;
+            let mut stored: Map<String, Value> = workspace::read_config(&root, WORKSPACE_CONFIG)?;
+            apply(&mut stored, &changes);
+            workspace::write_config(&root, WORKSPACE_CONFIG, &stored)?;
+            resolve(global(&app), overrides(&root)?)
+        }
+        None => {
+            let store = app
+                .store(workspace::STORE_NAME)
+                .map_err(|e| format!("Failed to open settings: {}", e))?;
+            let mut stored = match store.get(STORE_KEY) {
+                Some(Value::Object(stored)) => stored,
+                _ => Map::new(),
+            };
+            apply(&mut stored, &changes);
+            store.set(STORE_KEY, Value::Object(stored));
+            store
+                .save()
+                .map_err(|e| format!("Failed to save settings: {}", e))?;
+            if changes.contains_key("language") {
+                messages::load(&app);
+            }
+            resolve(global(&app), Map::new())
+        }
+    };
+    let _ = app.emit(
+        CHANGED_EVENT,
+        SettingsChanged {
+            workspace,
+            changed: changes.keys().cloned().collect(),
+        },
+    );
+    Ok(resolved)
+}
@@ -0,0 +1,139 @@
+use crate::deep_link::Navigation;
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::events::{self, ChangeKind};
+use crate::{assets, inbox, messages, sanitize, workspace};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use url::Url;
+
+/// Content another app shared, as forwarded by the native share target
+struct Shared {
+    title: Option<String>,
+    text: Option<String>,
+    url: Option<String>,
+    /// Image the share target copied into the app's cache directory
+    file: Option<PathBuf>,
+    /// Create a new document instead of appending to the inbox
+    as_document: bool,
+}
+
+/// Handle `inkfinite://share?text=&url=&title=&file=&as=document`, which the Android share
+/// intent and the iOS share extension open with whatever was shared.
+///
+/// Shares are appended to the inbox unless `as=document` asks for a new document; either way
+/// the app then navigates to the document that received it. Failures are reported as
+/// `share:failed`.
+pub fn receive(app: &AppHandle, url: &Url) -> Option<Navigation> {
+    let param = |key: &str| {
+        url.query_pairs()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let shared = Shared {
+        title: param("title"),
+        text: param("text"),
+        url: param("url"),
+        file: param("file").map(PathBuf::from),
+        as_document: param("as").as_deref() == Some("document"),
+    };
+
+    match save(app, &shared) {
+        Ok(path) => Some(Navigation::OpenFile {
+            path: path.to_string_lossy().to_string(),
+            workspace: workspace::current_root(app)
+                .map(|root| root.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        }),
+        Err(error) => {
+            let _ = app.emit("share:failed", error);
+            None
+        }
+    }
+}
+
+fn save(app: &AppHandle, shared: &Shared) -> Result<PathBuf, String> {
+    let mut parts = Vec::new();
+    if let Some(text) = &shared.text {
+        // Browsers often share the page URL as the text as well
+        if shared.url.as_ref() != Some(text) {
+            parts.push(text.clone());
+        }
+    }
+    if let Some(url) = &shared.url {
+        parts.push(match &shared.title {
+            Some(title) => format!("[{}]({})", title.replace(['[', ']'], ""), url),
+            None => format!("<{}>", url),
+        });
+    }
+    if let Some(file) = &shared.file {
+        let asset = import_image(app, file)?;
+        parts.push(format!("![]({})", asset));
+    }
+    if parts.is_empty() {
+        return Err("Nothing was shared".to_string());
+    }
+    let policy = workspace::current_root(app)
+        .map(|root| sanitize::load_policy(&root))
+        .unwrap_or_default();
+    // Shared text often comes from a web page and may carry its markup
+    let markdown = sanitize::clean_markdown(&policy, &parts.join("\n\n"));
+
+    if !shared.as_document {
+        return inbox::capture(app, &markdown);
+    }
+
+    let root = workspace::current_root(app).ok_or_else(|| "No workspace is open".to_string())?;
+    let name = shared.title.clone().unwrap_or_else(|| {
+        let date = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+        messages::format("shared-document-title", &[("date", date.into())])
+    });
+    let path = document::unique_path(&root, &file_stem(&name), DOCUMENT_EXTENSION);
+    let mut board = BoardFile::new(&name);
+    board.push_markdown(&markdown);
+    document::write_board(&path, &board)?;
+    events::file_changed(app, &path, ChangeKind::Created);
+    Ok(path)
+}
+
+/// Move a shared image into the workspace assets, returning the path to reference it by.
+///
+/// Only files in the app's own cache are accepted, so a crafted link cannot copy arbitrary
+/// files into the workspace.
+fn import_image(app: &AppHandle, file: &Path) -> Result<String, String> {
+    let cache = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))?;
+    let cache = cache.canonicalize().unwrap_or(cache);
+    let file = file
+        .canonicalize()
+        .map_err(|e| format!("Failed to read shared file: {}", e))?;
+    if !file.starts_with(&cache) {
+        return Err("Shared file is outside the app cache".to_string());
+    }
+
+    let asset = assets::import_asset(app.clone(), file.to_string_lossy().to_string(), None, None)
+        .map_err(|e| e.message)?;
+    let _ = std::fs::remove_file(&file);
+    Ok(asset.relative_path)
+}
+
+/// Strip characters file systems reject from a document name
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .take(80)
+        .collect();
+    let stem = stem.trim().trim_matches('.');
+    if stem.is_empty() {
+        "Shared".to_string()
+    } else {
+        stem.to_string()
+    }
+}
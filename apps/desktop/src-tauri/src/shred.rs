@@ -0,0 +1,182 @@
+use crate::asset_gc::TRASH_DIR;
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{crypto, ocr, paths, saves, search, thumbnails, workspace};
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const CHUNK: usize = 1 << 20;
+
+/// Why the old contents may still be recoverable after [`secure_delete`]
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Caveat {
+    /// Solid-state drives remap writes, so earlier copies can linger in blocks the drive has
+    /// not reused yet; this cannot be detected, so it is always reported
+    SolidState,
+    /// The file system writes changes elsewhere rather than in place (APFS, Btrfs, ZFS), so the
+    /// overwrite never touched the original blocks
+    CopyOnWrite,
+    /// Backups, sync services and file system snapshots taken earlier keep their copies
+    Backups,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecureDeleteReport {
+    pub bytes_overwritten: u64,
+    /// Copies and derived files removed along with it, such as OCR text and trashed copies;
+    /// workspace-relative when inside the workspace
+    pub purged: Vec<String>,
+    pub thumbnails_purged: usize,
+    /// Whether the file's text was dropped from the search index
+    pub unindexed: bool,
+    pub caveats: Vec<Caveat>,
+}
+
+/// Overwrite a file with random data before unlinking it, then remove what was derived from it:
+/// OCR text, search index text, cached thumbnails, trashed copies and any pending save.
+///
+/// The report lists [`Caveat`]s that apply on this platform, since overwriting cannot always
+/// reach the original blocks.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn secure_delete(app: AppHandle, path: String) -> Result<SecureDeleteReport, Error> {
+    paths::check(&app, &path, paths::Scope::Write)?;
+    let file = PathBuf::from(&path);
+    if !file.exists() {
+        return Err(Error::not_found("File does not exist").with_context(&path));
+    }
+    if file.is_dir() {
+        return Err(Error::invalid_path("Path is a directory, not a file").with_context(&path));
+    }
+
+    saves::discard(&app, &file);
+    let root = workspace::current_root(&app).filter(|root| file.starts_with(root));
+    let caveats = caveats(&file);
+    // Thumbnail keys include the modification time, so they are found before overwriting
+    let thumbnails_purged = thumbnails::purge(&app, &file).unwrap_or(0);
+    let bytes_overwritten = shred(&file)?;
+    audit::record_current(&app, AuditAction::Delete, AuditSource::Ui, &[&file]);
+    events::file_changed(&app, &file, ChangeKind::Deleted);
+
+    let mut report = SecureDeleteReport {
+        bytes_overwritten,
+        purged: Vec::new(),
+        thumbnails_purged,
+        unindexed: false,
+        caveats,
+    };
+    let text = ocr::text_path(root.as_deref(), &file);
+    if text.is_file() && shred(&text).is_ok() {
+        report.purged.push(match &root {
+            Some(root) => workspace::relative_path(root, &text),
+            None => text.to_string_lossy().to_string(),
+        });
+    }
+    let Some(root) = root else {
+        return Ok(report);
+    };
+    report.unindexed = search::remove_text(&root, &file)?;
+    if report.unindexed {
+        events::index_updated(&app, &file, "deleted");
+    }
+    let relative = workspace::relative_path(&root, &file);
+    let trash = workspace::internal_dir(&root).join(TRASH_DIR);
+    for batch in fs::read_dir(&trash).into_iter().flatten().flatten() {
+        let copy = batch.path().join(&relative);
+        if copy.is_file() && shred(&copy).is_ok() {
+            report.purged.push(workspace::relative_path(&root, &copy));
+        }
+    }
+    Ok(report)
+}
+
+/// Overwrite `path` with one pass of random data, flush it to disk, then truncate, rename and
+/// remove it so neither contents nor name stay in the directory. Returns the bytes overwritten.
+fn shred(path: &Path) -> Result<u64, String> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+    let len = file
+        .metadata()
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to overwrite file: {}", e))?;
+    let mut buffer = vec![0u8; CHUNK];
+    let mut remaining = len;
+    while remaining > 0 {
+        let step = remaining.min(CHUNK as u64) as usize;
+        crypto::fill_random(&mut buffer[..step]);
+        file.write_all(&buffer[..step])
+            .map_err(|e| format!("Failed to overwrite file: {}", e))?;
+        remaining -= step as u64;
+    }
+    file.sync_all()
+        .and_then(|_| file.set_len(0))
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to overwrite file: {}", e))?;
+    drop(file);
+
+    let renamed = path.with_file_name(uuid::Uuid::new_v4().simple().to_string());
+    let target = match fs::rename(path, &renamed) {
+        Ok(()) => renamed,
+        Err(_) => path.to_path_buf(),
+    };
+    fs::remove_file(&target).map_err(|e| format!("Failed to delete file: {}", e))?;
+    Ok(len)
+}
+
+fn caveats(path: &Path) -> Vec<Caveat> {
+    let mut caveats = vec![Caveat::SolidState];
+    if copy_on_write(path) {
+        caveats.push(Caveat::CopyOnWrite);
+    }
+    caveats.push(Caveat::Backups);
+    caveats
+}
+
+/// APFS is copy-on-write and the default on every supported macOS
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn copy_on_write(_path: &Path) -> bool {
+    true
+}
+
+/// Look up the file system of the longest mount point containing `path`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn copy_on_write(path: &Path) -> bool {
+    let path = path
+        .parent()
+        .and_then(|dir| dir.canonicalize().ok())
+        .unwrap_or_else(|| path.to_path_buf());
+    let Ok(mounts) = fs::read_to_string("/proc/self/mounts") else {
+        return false;
+    };
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = fields.next()?.replace("\\040", " ");
+            let kind = fields.next()?;
+            path.starts_with(&mount_point)
+                .then_some((mount_point, kind))
+        })
+        .max_by_key(|(mount_point, _)| mount_point.len())
+        .is_some_and(|(_, kind)| matches!(kind, "btrfs" | "zfs" | "bcachefs"))
+}
+
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "android"
+)))]
+fn copy_on_write(_path: &Path) -> bool {
+    false
+}
@@ -0,0 +1,424 @@
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::{paths, power, vault, workspace};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const ASSETS_DIR: &str = "assets";
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteOptions {
+    /// Site title used in the index and feed; defaults to the folder name
+    pub title: Option<String>,
+    /// Absolute URL the site is served from, required for valid RSS links
+    pub base_url: Option<String>,
+    /// Subset of document paths to publish; all documents in the folder when omitted
+    pub documents: Option<Vec<String>>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteReport {
+    pub pages: Vec<String>,
+    pub assets: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+struct Page {
+    source: PathBuf,
+    board: BoardFile,
+    slug: String,
+}
+
+/// Render documents in a workspace folder to a static HTML site with an index and RSS feed
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn publish_static_site(
+    app: AppHandle,
+    folder: String,
+    destination: String,
+    theme: Option<String>,
+    options: Option<SiteOptions>,
+) -> Result<SiteReport, Error> {
+    paths::check(&app, &folder, paths::Scope::Read)?;
+    paths::check(&app, &destination, paths::Scope::Export)?;
+    let root = Path::new(&folder);
+    if !root.is_dir() {
+        return Err(format!("Directory does not exist: {}", folder).into());
+    }
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve folder: {}", e))?;
+
+    let _awake = power::prevent_sleep("Publishing site");
+    let options = options.unwrap_or_default();
+    let out = PathBuf::from(&destination);
+    fs::create_dir_all(&out).map_err(|e| format!("Failed to create destination: {}", e))?;
+
+    let sources = match &options.documents {
+        Some(paths) => paths.iter().map(PathBuf::from).collect(),
+        None => workspace::list_documents(root)?,
+    };
+
+    let mut report = SiteReport {
+        pages: Vec::new(),
+        assets: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut pages = Vec::new();
+    let mut slugs: HashMap<String, usize> = HashMap::new();
+
+    for source in sources {
+        let inside = source
+            .canonicalize()
+            .is_ok_and(|path| path.starts_with(&canonical_root));
+        if !inside {
+            report.skipped.push(source.to_string_lossy().to_string());
+            continue;
+        }
+        match document::read_board(&source) {
+            Ok(board) => {
+                let base = slugify(&board.board.name);
+                let count = slugs.entry(base.clone()).or_insert(0);
+                *count += 1;
+                let slug = if *count == 1 {
+                    base
+                } else {
+                    format!("{}-{}", base, count)
+                };
+                pages.push(Page {
+                    source,
+                    board,
+                    slug,
+                });
+            }
+            Err(_) => report.skipped.push(source.to_string_lossy().to_string()),
+        }
+    }
+
+    pages.sort_by_key(|page| std::cmp::Reverse(page.board.board.updated_at));
+
+    let links: HashMap<String, String> = pages
+        .iter()
+        .map(|page| (page.board.board.name.to_lowercase(), page.slug.clone()))
+        .collect();
+
+    let site_title = options.title.clone().unwrap_or_else(|| {
+        root.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Notes".to_string())
+    });
+
+    for page in &pages {
+        let markdown = resolve_wiki_links(&page.board.to_markdown(), &links);
+        let doc_dir = page.source.parent().unwrap_or(root);
+        let body = render_markdown(
+            &markdown,
+            doc_dir,
+            &canonical_root,
+            &out,
+            &mut report.assets,
+        );
+        let title = format!("{} · {}", page.board.board.name, site_title);
+        let html = layout(&title, &site_title, &body);
+        let file = format!("{}.html", page.slug);
+        fs::write(out.join(&file), html).map_err(|e| format!("Failed to write page: {}", e))?;
+        report.pages.push(file);
+    }
+
+    let index = render_index(&site_title, &pages);
+    fs::write(out.join("index.html"), index)
+        .map_err(|e| format!("Failed to write index: {}", e))?;
+    fs::write(
+        out.join("feed.xml"),
+        render_feed(&site_title, options.base_url.as_deref(), &pages),
+    )
+    .map_err(|e| format!("Failed to write feed: {}", e))?;
+    write_theme(&out, theme.as_deref())?;
+
+    let shared_assets = root.join(ASSETS_DIR);
+    if shared_assets.is_dir() {
+        copy_dir(
+            &shared_assets,
+            &out.join(ASSETS_DIR),
+            &out,
+            &mut report.assets,
+        )?;
+    }
+
+    Ok(report)
+}
+
+/// Lowercase, dash-separated file name safe for any static host
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.ends_with('-') && !slug.is_empty() {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-').to_string();
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Turn `[[Title]]` and `[[Title|Label]]` into links to published pages
+fn resolve_wiki_links(markdown: &str, links: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        let (target, label) = inner.split_once('|').unwrap_or((inner, inner));
+
+        out.push_str(&rest[..start]);
+        match links.get(&target.trim().to_lowercase()) {
+            Some(slug) => out.push_str(&format!("[{}]({}.html)", label.trim(), slug)),
+            None => out.push_str(label.trim()),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Render Markdown to HTML, copying relative images into the site's assets folder
+fn render_markdown(
+    markdown: &str,
+    doc_dir: &Path,
+    root: &Path,
+    out: &Path,
+    copied: &mut Vec<String>,
+) -> String {
+    let parser = Parser::new_ext(markdown, Options::all()).map(|event| match event {
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = localize_image(&dest_url, doc_dir, root, out, copied)
+                .map(CowStr::from)
+                .unwrap_or(dest_url);
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        other => other,
+    });
+
+    let mut body = String::new();
+    html::push_html(&mut body, parser);
+    body
+}
+
+/// Site path of a relative image, copying it into the assets folder. Images outside the
+/// published folder `root` (canonical) or in a locked vault are not published. Images from
+/// the shared assets folder keep their path there, which is copied whole; others get a name
+/// tagged with a hash of where they came from, so same-named images never overwrite each other.
+fn localize_image(
+    url: &str,
+    doc_dir: &Path,
+    root: &Path,
+    out: &Path,
+    copied: &mut Vec<String>,
+) -> Option<String> {
+    if url.contains("://") || url.starts_with("data:") || url.starts_with('/') {
+        return None;
+    }
+
+    let source = doc_dir.join(url).canonicalize().ok()?;
+    if !source.starts_with(root) || !source.is_file() || vault::is_locked(&source) {
+        return None;
+    }
+    if let Ok(shared) = source.strip_prefix(root.join(ASSETS_DIR)) {
+        let shared = shared.to_string_lossy().replace('\\', "/");
+        return Some(format!("{}/{}", ASSETS_DIR, shared));
+    }
+
+    let tag = format!("{:x}", Sha256::digest(source.to_string_lossy().as_bytes()));
+    let stem = source.file_stem()?.to_string_lossy();
+    let file_name = match source.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, &tag[..8], extension.to_string_lossy()),
+        None => format!("{}-{}", stem, &tag[..8]),
+    };
+    let relative = format!("{}/{}", ASSETS_DIR, file_name);
+    if !copied.contains(&relative) {
+        let target_dir = out.join(ASSETS_DIR);
+        fs::create_dir_all(&target_dir).ok()?;
+        fs::copy(&source, target_dir.join(&file_name)).ok()?;
+        copied.push(relative.clone());
+    }
+    Some(relative)
+}
+
+fn copy_dir(
+    from: &Path,
+    to: &Path,
+    site_root: &Path,
+    copied: &mut Vec<String>,
+) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    for entry in fs::read_dir(from).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        let target = to.join(entry.file_name());
+        if path.is_dir() {
+            copy_dir(&path, &target, site_root, copied)?;
+        } else {
+            fs::copy(&path, &target).map_err(|e| format!("Failed to copy asset: {}", e))?;
+            copied.push(workspace::relative_path(site_root, &target));
+        }
+    }
+
+    Ok(())
+}
+
+fn layout(title: &str, site_title: &str, body: &str) -> String {
+    format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<link rel=\"stylesheet\" href=\"style.css\">\n\
+         <link rel=\"alternate\" type=\"application/rss+xml\" href=\"feed.xml\">\n</head>\n\
+         <body>\n<nav><a href=\"index.html\">{}</a></nav>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape(title),
+        escape(site_title),
+        body
+    )
+}
+
+fn render_index(site_title: &str, pages: &[Page]) -> String {
+    let mut list = String::from("<h1>");
+    list.push_str(&escape(site_title));
+    list.push_str("</h1>\n<ul class=\"index\">\n");
+    for page in pages {
+        list.push_str(&format!(
+            "<li><a href=\"{}.html\">{}</a> <time>{}</time></li>\n",
+            page.slug,
+            escape(&page.board.board.name),
+            format_date(page.board.board.updated_at, "%Y-%m-%d")
+        ));
+    }
+    list.push_str("</ul>\n");
+
+    layout(site_title, site_title, &list)
+}
+
+fn render_feed(site_title: &str, base_url: Option<&str>, pages: &[Page]) -> String {
+    let base = base_url.unwrap_or("").trim_end_matches('/');
+    let mut items = String::new();
+    for page in pages {
+        let link = if base.is_empty() {
+            format!("{}.html", page.slug)
+        } else {
+            format!("{}/{}.html", base, page.slug)
+        };
+        items.push_str(&format!(
+            "<item><title>{}</title><link>{}</link><guid>{}</guid><pubDate>{}</pubDate></item>\n",
+            escape(&page.board.board.name),
+            escape(&link),
+            escape(&page.board.board.id),
+            format_date(page.board.board.updated_at, "%a, %d %b %Y %H:%M:%S +0000")
+        ));
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n<channel>\n\
+         <title>{}</title>\n<link>{}</link>\n<description>{}</description>\n{}</channel>\n</rss>\n",
+        escape(site_title),
+        escape(if base.is_empty() { "index.html" } else { base }),
+        escape(site_title),
+        items
+    )
+}
+
+fn write_theme(out: &Path, theme: Option<&str>) -> Result<(), String> {
+    let css = match theme {
+        Some(path) if path.ends_with(".css") => {
+            fs::read_to_string(path).map_err(|e| format!("Failed to read theme: {}", e))?
+        }
+        Some("dark") => format!("{}{}", BASE_CSS, DARK_CSS),
+        _ => BASE_CSS.to_string(),
+    };
+    fs::write(out.join("style.css"), css).map_err(|e| format!("Failed to write theme: {}", e))
+}
+
+fn format_date(millis: i64, format: &str) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .map(|date| date.format(format).to_string())
+        .unwrap_or_default()
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub const BASE_CSS: &str =
+    "body{font-family:Inter,system-ui,sans-serif;line-height:1.6;color:#1f2933;\
+background:#fff;max-width:42rem;margin:0 auto;padding:2rem 1rem}\
+nav{margin-bottom:2rem}a{color:#2563eb}img{max-width:100%}\
+pre{overflow:auto;padding:1rem;background:#f3f4f6}.index time{color:#6b7280;font-size:.875rem}\n";
+
+const DARK_CSS: &str =
+    "body{color:#e5e7eb;background:#111827}a{color:#93c5fd}pre{background:#1f2937}\n";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_are_published_only_from_the_folder_without_collisions() {
+        let base = std::env::temp_dir().join(format!("inkfinite-site-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let root = base.join("notes");
+        let out = base.join("out");
+        for dir in ["a", "b", "assets"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("logo.png"), dir).unwrap();
+        }
+        fs::write(base.join("secret.png"), "secret").unwrap();
+        let root = root.canonicalize().unwrap();
+        let mut copied = Vec::new();
+
+        let doc_dir = root.join("a");
+        let a = localize_image("logo.png", &doc_dir, &root, &out, &mut copied).unwrap();
+        let b = localize_image("../b/logo.png", &doc_dir, &root, &out, &mut copied).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(fs::read_to_string(out.join(&a)).unwrap(), "a");
+        assert_eq!(fs::read_to_string(out.join(&b)).unwrap(), "b");
+        assert_eq!(
+            localize_image("logo.png", &doc_dir, &root, &out, &mut copied),
+            Some(a)
+        );
+        assert_eq!(copied.len(), 2);
+
+        let shared = localize_image("../assets/logo.png", &doc_dir, &root, &out, &mut copied);
+        assert_eq!(shared.as_deref(), Some("assets/logo.png"));
+        assert_eq!(
+            localize_image("../../secret.png", &doc_dir, &root, &out, &mut copied),
+            None
+        );
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::error::Error;
+use crate::paths;
+use crate::workspace;
+use spellbook::Dictionary;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+pub const DICTIONARY_CONFIG: &str = "dictionary";
+pub const DICTIONARIES_DIR: &str = "dictionaries";
+const DEFAULT_LANGUAGE: &str = "en_US";
+/// Suggestions returned for each misspelled word
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Words a workspace accepts on top of the language dictionary, stored in `.inkfinite/dictionary.json`
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CustomDictionary {
+    pub words: BTreeSet<String>,
+}
+
+/// Hunspell dictionaries loaded so far, keyed by language
+#[derive(Default)]
+pub struct SpellChecker(Mutex<HashMap<String, Arc<Dictionary>>>);
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Misspelling {
+    pub word: String,
+    /// Offsets in UTF-16 code units, matching JavaScript string indices
+    pub start: usize,
+    pub end: usize,
+    pub suggestions: Vec<String>,
+}
+
+/// Words in a workspace's custom dictionary, sorted
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_words(app: AppHandle, workspace: String) -> Result<Vec<String>, Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let dictionary: CustomDictionary =
+        workspace::read_config(Path::new(&workspace), DICTIONARY_CONFIG)?;
+    Ok(dictionary.words.into_iter().collect())
+}
+
+/// Accept a word in a workspace so spellcheck no longer flags it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn add_word(app: AppHandle, workspace: String, word: String) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let word = word.trim();
+    if word.is_empty() || word.chars().any(char::is_whitespace) {
+        return Err(format!("Not a single word: {:?}", word).into());
+    }
+    Ok(update(&workspace, |words| {
+        words.insert(word.to_string());
+    })?)
+}
+
+/// Remove a word from a workspace's custom dictionary
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn remove_word(app: AppHandle, workspace: String, word: String) -> Result<(), Error> {
+    paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    Ok(update(&workspace, |words| {
+        words.remove(word.trim());
+    })?)
+}
+
+/// Check text against a Hunspell dictionary and the workspace's custom words.
+///
+/// `language` names a dictionary such as `en_US`, found as `<language>.aff` and `<language>.dic`
+/// in the app's `dictionaries` folder or the system Hunspell folders.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn spellcheck(
+    app: AppHandle,
+    text: String,
+    language: Option<String>,
+    workspace: Option<String>,
+) -> Result<Vec<Misspelling>, Error> {
+    if let Some(workspace) = &workspace {
+        paths::check_workspace(&app, workspace, paths::Scope::Read)?;
+    }
+    let language = language.unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+    tauri::async_runtime::spawn_blocking(move || {
+        let dictionary = load_dictionary(&app, &app.state::<SpellChecker>(), &language)?;
+        let custom: CustomDictionary = match workspace {
+            Some(workspace) => workspace::read_config(Path::new(&workspace), DICTIONARY_CONFIG)?,
+            None => CustomDictionary::default(),
+        };
+        let custom: BTreeSet<String> = custom.words.iter().map(|w| w.to_lowercase()).collect();
+
+        let mut misspellings = Vec::new();
+        for (word, start, end) in words(&text) {
+            if custom.contains(&word.to_lowercase()) || dictionary.check(word) {
+                continue;
+            }
+            let mut suggestions = Vec::new();
+            dictionary.suggest(word, &mut suggestions);
+            suggestions.truncate(MAX_SUGGESTIONS);
+            misspellings.push(Misspelling {
+                word: word.to_string(),
+                start,
+                end,
+                suggestions,
+            });
+        }
+        Ok(misspellings)
+    })
+    .await
+    .map_err(|e| format!("Spellcheck failed: {}", e))?
+}
+
+/// Languages with a Hunspell dictionary available to [`spellcheck`]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn list_languages(app: AppHandle) -> Vec<String> {
+    let mut languages = BTreeSet::new();
+    for dir in search_dirs(&app) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_some_and(|ext| ext == "dic")
+                && path.with_extension("aff").is_file()
+            {
+                if let Some(stem) = path.file_stem() {
+                    languages.insert(stem.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+    languages.into_iter().collect()
+}
+
+fn update(workspace: &str, change: impl FnOnce(&mut BTreeSet<String>)) -> Result<(), String> {
+    let root = Path::new(workspace);
+    let mut dictionary: CustomDictionary = workspace::read_config(root, DICTIONARY_CONFIG)?;
+    change(&mut dictionary.words);
+    workspace::write_config(root, DICTIONARY_CONFIG, &dictionary)
+}
+
+fn load_dictionary(
+    app: &AppHandle,
+    checker: &SpellChecker,
+    language: &str,
+) -> Result<Arc<Dictionary>, String> {
+    if language.is_empty()
+        || !language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format!("Invalid language: {}", language));
+    }
+    if let Some(dictionary) = checker
+        .0
+        .lock()
+        .ok()
+        .and_then(|loaded| loaded.get(language).cloned())
+    {
+        return Ok(dictionary);
+    }
+
+    let dic = search_dirs(app)
+        .into_iter()
+        .map(|dir| dir.join(format!("{}.dic", language)))
+        .find(|dic| dic.is_file() && dic.with_extension("aff").is_file())
+        .ok_or_else(|| format!("No Hunspell dictionary installed for {}", language))?;
+    let aff = std::fs::read_to_string(dic.with_extension("aff"))
+        .map_err(|e| format!("Failed to read dictionary: {}", e))?;
+    let words =
+        std::fs::read_to_string(&dic).map_err(|e| format!("Failed to read dictionary: {}", e))?;
+    let dictionary = Arc::new(
+        Dictionary::new(&aff, &words)
+            .map_err(|e| format!("Failed to parse dictionary {}: {}", language, e))?,
+    );
+
+    if let Ok(mut loaded) = checker.0.lock() {
+        loaded.insert(language.to_string(), dictionary.clone());
+    }
+    Ok(dictionary)
+}
+
+/// Dictionary folders: user-installed, bundled, then the platform's shared Hunspell folders
+fn search_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    // Windows has no shared Hunspell folder
+    #[cfg_attr(windows, allow(unused_mut))]
+    let mut dirs: Vec<PathBuf> = [app.path().app_data_dir(), app.path().resource_dir()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(DICTIONARIES_DIR))
+        .collect();
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(home) = app.path().home_dir() {
+            dirs.push(home.join("Library/Spelling"));
+        }
+        dirs.push(PathBuf::from("/Library/Spelling"));
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        dirs.push(PathBuf::from("/usr/share/hunspell"));
+        dirs.push(PathBuf::from("/usr/share/myspell/dicts"));
+        dirs.push(PathBuf::from("/usr/share/myspell"));
+    }
+    dirs
+}
+
+/// Words in text with their UTF-16 start and end offsets.
+///
+/// Apostrophes inside a word are kept so contractions check as one word; words containing
+/// digits and all-caps acronyms are skipped.
+fn words(text: &str) -> Vec<(&str, usize, usize)> {
+    let mut words = Vec::new();
+    let mut start: Option<(usize, usize)> = None;
+    let mut offset = 0;
+
+    let push = |words: &mut Vec<_>, from: (usize, usize), to: usize| {
+        let word = text[from.0..to].trim_end_matches(['\'', '’']);
+        let end = from.1 + word.encode_utf16().count();
+        let acronym = word.chars().count() > 1 && !word.chars().any(char::is_lowercase);
+        if !word.is_empty() && !acronym && !word.chars().any(|c| c.is_numeric()) {
+            words.push((word, from.1, end));
+        }
+    };
+
+    for (index, c) in text.char_indices() {
+        let in_word = c.is_alphanumeric() || (start.is_some() && (c == '\'' || c == '’'));
+        match (in_word, start) {
+            (true, None) => start = Some((index, offset)),
+            (false, Some(from)) => {
+                push(&mut words, from, index);
+                start = None;
+            }
+            _ => {}
+        }
+        offset += c.len_utf16();
+    }
+    if let Some(from) = start {
+        push(&mut words, from, text.len());
+    }
+    words
+}
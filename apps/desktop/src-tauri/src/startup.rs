@@ -0,0 +1,120 @@
+use crate::blocks::BlockIndexes;
+use crate::{catalog, safe_mode, search, workspace};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
+
+/// Recent documents read ahead at launch
+const PRIMED_DOCUMENTS: usize = 5;
+
+static LAUNCHED: OnceLock<Instant> = OnceLock::new();
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Phase {
+    pub name: String,
+    /// Milliseconds after launch the phase started
+    pub started_ms: f64,
+    pub duration_ms: f64,
+    /// Documents or entries the phase handled
+    pub items: usize,
+    pub error: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupReport {
+    /// Milliseconds from launch until setup finished and windows could open
+    pub setup_ms: Option<f64>,
+    /// Background warm-up steps in the order they ran
+    pub phases: Vec<Phase>,
+    /// Whether the warm-up has finished
+    pub warmed_up: bool,
+}
+
+/// Managed state collecting startup timings
+#[derive(Default)]
+pub struct Startup(Mutex<StartupReport>);
+
+/// Note the launch time; called first thing so every phase is measured from it
+pub fn launched() {
+    LAUNCHED.get_or_init(Instant::now);
+}
+
+fn since_launch(at: Instant) -> f64 {
+    let launched = *LAUNCHED.get_or_init(Instant::now);
+    at.saturating_duration_since(launched).as_secs_f64() * 1000.0
+}
+
+/// Record that setup finished
+pub fn setup_finished(app: &AppHandle) {
+    if let Ok(mut report) = app.state::<Startup>().0.lock() {
+        report.setup_ms = Some(since_launch(Instant::now()));
+    }
+}
+
+/// Run `work` as a named phase, recording how long it took and how many items it handled
+fn phase(app: &AppHandle, name: &str, work: impl FnOnce() -> Result<usize, String>) {
+    let started = Instant::now();
+    let result = work();
+    let phase = Phase {
+        name: name.to_string(),
+        started_ms: since_launch(started),
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+        items: *result.as_ref().unwrap_or(&0),
+        error: result.err(),
+    };
+    tracing::debug!(
+        phase = %phase.name,
+        duration_ms = phase.duration_ms,
+        items = phase.items,
+        "Startup phase finished"
+    );
+    if let Ok(mut report) = app.state::<Startup>().0.lock() {
+        report.phases.push(phase);
+    }
+}
+
+/// Warm caches in the background so the first listing, open and search after launch are fast.
+///
+/// Opens the workspace metadata cache, indexes the blocks of the most recent documents, which
+/// also leaves them in the OS file cache, then reads the search index.
+pub fn warm_up(app: AppHandle) {
+    std::thread::spawn(move || {
+        let root = workspace::current_root(&app);
+        if let Some(root) = &root {
+            phase(&app, "metadata cache", || {
+                Ok(catalog::document_paths(&app, root, None)?.len())
+            });
+        }
+        phase(&app, "recent documents", || {
+            let indexes = app.state::<BlockIndexes>();
+            let recent = workspace::recent_files(&app);
+            let mut primed = 0;
+            for file in recent.iter().take(PRIMED_DOCUMENTS) {
+                if indexes.prime(Path::new(&file.path)).is_ok() {
+                    primed += 1;
+                }
+            }
+            Ok(primed)
+        });
+        if let Some(root) = root.filter(|_| !safe_mode::disabled(safe_mode::Component::Index)) {
+            phase(&app, "search index", || search::warm(&root));
+        }
+        if let Ok(mut report) = app.state::<Startup>().0.lock() {
+            report.warmed_up = true;
+        }
+    });
+}
+
+/// Launch and warm-up timings, for tuning startup
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn startup_report(startup: State<'_, Startup>) -> StartupReport {
+    startup
+        .0
+        .lock()
+        .map(|report| report.clone())
+        .unwrap_or_default()
+}
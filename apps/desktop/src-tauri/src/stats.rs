@@ -0,0 +1,457 @@
+use crate::catalog::{self, Database};
+use crate::document::BoardFile;
+use crate::error::Error;
+use crate::{paths, vault, workspace};
+use chrono::{Duration, Local, NaiveDate};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Component, Path};
+use tauri::AppHandle;
+
+/// Goals for one workspace, `.inkfinite/goals.json`
+const GOALS_CONFIG: &str = "goals";
+const DEFAULT_DAYS: i64 = 30;
+/// Longest range returned day by day
+const MAX_DAYS: i64 = 3660;
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WritingGoals {
+    /// Net words to write each day
+    pub daily: Option<u64>,
+    /// Workspace-relative folder to its goal
+    #[serde(default)]
+    pub projects: BTreeMap<String, ProjectGoal>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectGoal {
+    /// Words the documents in the folder should add up to
+    pub target: u64,
+    /// `YYYY-MM-DD`, the last day to reach the target on
+    pub deadline: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateRange {
+    /// `YYYY-MM-DD`, inclusive
+    pub from: String,
+    /// `YYYY-MM-DD`, inclusive
+    pub to: String,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DayStats {
+    /// `YYYY-MM-DD`, local time
+    pub day: String,
+    pub added: u64,
+    pub removed: u64,
+    pub net: i64,
+    /// Documents written in that day
+    pub documents: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStats {
+    /// Workspace-relative path
+    pub path: String,
+    pub added: u64,
+    pub removed: u64,
+    pub net: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritingStats {
+    pub from: String,
+    pub to: String,
+    pub added: u64,
+    pub removed: u64,
+    pub net: i64,
+    /// Every day in the range, including those without writing
+    pub days: Vec<DayStats>,
+    /// Most words written first
+    pub documents: Vec<DocumentStats>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectProgress {
+    pub folder: String,
+    pub target: u64,
+    pub deadline: Option<String>,
+    /// Words in the folder's documents now
+    pub words: u64,
+    /// Net words written in the folder today
+    pub today: i64,
+    /// Between 0 and 1
+    pub progress: f64,
+    /// Words a day still needed to reach the target by the deadline
+    pub per_day: Option<u64>,
+    pub days_left: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProgress {
+    pub goals: WritingGoals,
+    /// Net words written today
+    pub today: i64,
+    /// Whether today's net words reached the daily goal
+    pub met: bool,
+    /// Days in a row the daily goal was met, up to today; a day not yet met does not break it
+    pub streak: u32,
+    pub longest_streak: u32,
+    pub projects: Vec<ProjectProgress>,
+}
+
+/// Workspace-relative paths with a number of words
+type WordCounts<T> = Vec<(String, T)>;
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+fn parse_day(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, DATE_FORMAT).map_err(|_| format!("Invalid date: {}", value))
+}
+
+/// Record the words a save added or removed against the count the cache held before it.
+///
+/// Only saves from the editor are counted: changes made outside the app update the cache
+/// without being credited as writing. Vault documents are left out like everywhere else in
+/// the cache.
+pub fn document_saved(app: &AppHandle, path: &Path, board: &BoardFile) {
+    if vault::vault_of(path).is_some() {
+        return;
+    }
+    let open = catalog::containing(app, path).or_else(|| {
+        let root = workspace::current_root(app).filter(|root| path.starts_with(root))?;
+        let db = catalog::database(app, &root).ok()?;
+        Some((root, db))
+    });
+    let Some((root, db)) = open else {
+        return;
+    };
+    let words = board.to_markdown().split_whitespace().count() as i64;
+    if let Err(error) = record(&db, &workspace::relative_path(&root, path), words) {
+        tracing::warn!(%error, "Failed to record writing stats");
+    }
+}
+
+fn record(db: &Database, path: &str, words: i64) -> Result<(), String> {
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to write writing stats: {}", e))?;
+    let before: i64 = db
+        .query_row(
+            "SELECT word_count FROM documents WHERE path = ?1",
+            [path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?
+        .unwrap_or(0);
+    let delta = words - before;
+    if delta == 0 {
+        return Ok(());
+    }
+    db.execute(
+        "INSERT INTO writing (day, path, added, removed) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (day, path) DO UPDATE SET
+             added = added + excluded.added,
+             removed = removed + excluded.removed",
+        params![
+            today().format(DATE_FORMAT).to_string(),
+            path,
+            delta.max(0),
+            (-delta).max(0)
+        ],
+    )
+    .map_err(|e| format!("Failed to write writing stats: {}", e))?;
+    Ok(())
+}
+
+/// Net words written per day, for every day with any writing
+fn daily_net(db: &Database) -> Result<HashMap<NaiveDate, i64>, String> {
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read writing stats: {}", e))?;
+    let mut statement = db
+        .prepare("SELECT day, SUM(added) - SUM(removed) FROM writing GROUP BY day")
+        .map_err(|e| format!("Failed to read writing stats: {}", e))?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| format!("Failed to read writing stats: {}", e))?;
+    Ok(rows
+        .flatten()
+        .filter_map(|(day, net)| Some((parse_day(&day).ok()?, net)))
+        .collect())
+}
+
+/// Current and longest run of consecutive days in `days`; a current run that has not reached
+/// today yet still counts up to yesterday
+pub fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> (u32, u32) {
+    let (mut longest, mut run) = (0, 0);
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        run = match previous {
+            Some(previous) if *day - previous == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+
+    let mut current = 0;
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    while days.contains(&day) {
+        current += 1;
+        day -= Duration::days(1);
+    }
+    (current, longest)
+}
+
+/// Whether `folder` is a plain workspace-relative path, without `..` or a root
+fn valid_folder(folder: &str) -> bool {
+    !folder.is_empty()
+        && Path::new(folder)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn within(path: &str, folder: &str) -> bool {
+    let folder = folder.trim_end_matches('/');
+    path.strip_prefix(folder)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn progress(app: &AppHandle, root: &Path, goals: WritingGoals) -> Result<GoalProgress, String> {
+    let db = catalog::database(app, root)?;
+    let net = daily_net(&db)?;
+    let today = today();
+    let written = net.get(&today).copied().unwrap_or(0);
+    let goal = goals.daily.map_or(1, |daily| daily.max(1) as i64);
+    let met: BTreeSet<NaiveDate> = net
+        .iter()
+        .filter(|(_, words)| **words >= goal)
+        .map(|(day, _)| *day)
+        .collect();
+    let (streak, longest_streak) = streaks(&met, today);
+
+    let (documents, written_today): (WordCounts<u64>, WordCounts<i64>) = {
+        let db = db
+            .lock()
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        let mut statement = db
+            .prepare("SELECT path, word_count FROM documents")
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+        let documents = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to read metadata cache: {}", e))?
+            .flatten()
+            .collect();
+        let mut statement = db
+            .prepare("SELECT path, added - removed FROM writing WHERE day = ?1")
+            .map_err(|e| format!("Failed to read writing stats: {}", e))?;
+        let written = statement
+            .query_map([today.format(DATE_FORMAT).to_string()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .map_err(|e| format!("Failed to read writing stats: {}", e))?
+            .flatten()
+            .collect();
+        (documents, written)
+    };
+    let projects = goals
+        .projects
+        .iter()
+        .map(|(folder, goal)| {
+            let words: u64 = documents
+                .iter()
+                .filter(|(path, _)| within(path, folder))
+                .map(|(_, words)| words)
+                .sum();
+            let days_left = goal
+                .deadline
+                .as_deref()
+                .and_then(|deadline| parse_day(deadline).ok())
+                .map(|deadline| (deadline - today).num_days() + 1);
+            let remaining = goal.target.saturating_sub(words);
+            ProjectProgress {
+                folder: folder.clone(),
+                target: goal.target,
+                deadline: goal.deadline.clone(),
+                words,
+                today: written_today
+                    .iter()
+                    .filter(|(path, _)| within(path, folder))
+                    .map(|(_, net)| net)
+                    .sum(),
+                progress: match goal.target {
+                    0 => 1.0,
+                    target => (words as f64 / target as f64).min(1.0),
+                },
+                per_day: days_left
+                    .filter(|days| *days > 0)
+                    .map(|days| remaining.div_ceil(days as u64)),
+                days_left,
+            }
+        })
+        .collect();
+
+    Ok(GoalProgress {
+        met: goals.daily.is_some_and(|daily| written >= daily as i64),
+        today: written,
+        streak,
+        longest_streak,
+        projects,
+        goals,
+    })
+}
+
+/// Words added and removed per day and per document over `range`, the last 30 days by default
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn writing_stats(
+    app: AppHandle,
+    workspace: String,
+    range: Option<DateRange>,
+) -> Result<WritingStats, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let (from, to) = match range {
+        Some(range) => (parse_day(&range.from)?, parse_day(&range.to)?),
+        None => (today() - Duration::days(DEFAULT_DAYS - 1), today()),
+    };
+    if to < from {
+        return Err("Invalid range: it ends before it starts".into());
+    }
+    if (to - from).num_days() >= MAX_DAYS {
+        return Err(format!("Invalid range: longer than {} days", MAX_DAYS).into());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let (from_text, to_text) = (
+            from.format(DATE_FORMAT).to_string(),
+            to.format(DATE_FORMAT).to_string(),
+        );
+        let db = catalog::database(&app, &root)?;
+        let db = db
+            .lock()
+            .map_err(|e| format!("Failed to read writing stats: {}", e))?;
+        let mut statement = db
+            .prepare(
+                "SELECT day, path, added, removed FROM writing
+                 WHERE day BETWEEN ?1 AND ?2",
+            )
+            .map_err(|e| format!("Failed to read writing stats: {}", e))?;
+        let rows: Vec<(String, String, u64, u64)> = statement
+            .query_map([&from_text, &to_text], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .map_err(|e| format!("Failed to read writing stats: {}", e))?
+            .flatten()
+            .collect();
+
+        let mut days: BTreeMap<String, DayStats> = from
+            .iter_days()
+            .take_while(|day| *day <= to)
+            .map(|day| {
+                let day = day.format(DATE_FORMAT).to_string();
+                (
+                    day.clone(),
+                    DayStats {
+                        day,
+                        ..DayStats::default()
+                    },
+                )
+            })
+            .collect();
+        let mut documents: BTreeMap<String, DocumentStats> = BTreeMap::new();
+        for (day, path, added, removed) in rows {
+            let net = added as i64 - removed as i64;
+            if let Some(stats) = days.get_mut(&day) {
+                stats.added += added;
+                stats.removed += removed;
+                stats.net += net;
+                stats.documents += 1;
+            }
+            let document = documents
+                .entry(path.clone())
+                .or_insert_with(|| DocumentStats {
+                    path,
+                    added: 0,
+                    removed: 0,
+                    net: 0,
+                });
+            document.added += added;
+            document.removed += removed;
+            document.net += net;
+        }
+        let mut documents: Vec<DocumentStats> = documents.into_values().collect();
+        documents.sort_by_key(|document| std::cmp::Reverse(document.added));
+
+        let days: Vec<DayStats> = days.into_values().collect();
+        Ok(WritingStats {
+            from: from_text,
+            to: to_text,
+            added: days.iter().map(|day| day.added).sum(),
+            removed: days.iter().map(|day| day.removed).sum(),
+            net: days.iter().map(|day| day.net).sum(),
+            days,
+            documents,
+        })
+    })
+    .await
+    .map_err(|e| format!("Stats task failed: {}", e))?
+}
+
+/// The workspace's daily and project goals with today's words, the streak and how far each
+/// project is from its target
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn get_writing_goals(app: AppHandle, workspace: String) -> Result<GoalProgress, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let goals: WritingGoals = workspace::read_config(&root, GOALS_CONFIG)?;
+        Ok(progress(&app, &root, goals)?)
+    })
+    .await
+    .map_err(|e| format!("Stats task failed: {}", e))?
+}
+
+/// Replace the workspace's goals; project folders are workspace-relative
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn set_writing_goals(
+    app: AppHandle,
+    workspace: String,
+    goals: WritingGoals,
+) -> Result<GoalProgress, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    for (folder, goal) in &goals.projects {
+        if !valid_folder(folder) {
+            return Err(format!("Invalid project folder: {}", folder).into());
+        }
+        if let Some(deadline) = &goal.deadline {
+            parse_day(deadline)?;
+        }
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        workspace::write_config(&root, GOALS_CONFIG, &goals)?;
+        Ok(progress(&app, &root, goals)?)
+    })
+    .await
+    .map_err(|e| format!("Stats task failed: {}", e))?
+}
@@ -0,0 +1,263 @@
+use crate::audit::{self, AuditAction, AuditSource};
+use crate::error::Error;
+use crate::paths;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Elements dropped together with everything inside them
+const BLOCKED_ELEMENTS: &[&[u8]] = &[
+    b"script",
+    b"foreignObject",
+    b"iframe",
+    b"embed",
+    b"object",
+    b"handler",
+    b"listener",
+];
+
+/// Animation elements that can retarget `href` and event attributes at runtime
+const BLOCKED_ANIMATIONS: &[&[u8]] = &[b"set", b"animate"];
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SanitizeReport {
+    /// Elements, attributes, and CSS references removed
+    pub removed: usize,
+    pub changed: bool,
+}
+
+/// Whether a path looks like an SVG file
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("svg"))
+}
+
+/// Strip scripts, foreign content, event handlers, and remote references from an SVG document
+pub fn sanitize(input: &str) -> Result<(String, usize), String> {
+    let mut reader = Reader::from_str(input);
+    let mut writer = Writer::new(Vec::new());
+    let mut removed = 0;
+    // Depth inside a blocked element; everything is skipped while non-zero
+    let mut skipping = 0usize;
+    let mut in_style = false;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid SVG at byte {}: {}", reader.error_position(), e))?;
+
+        if skipping > 0 {
+            match event {
+                Event::Start(_) => skipping += 1,
+                Event::End(_) => skipping -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            continue;
+        }
+
+        let output = match event {
+            Event::Eof => break,
+            Event::Start(element) => {
+                if is_blocked(&element) {
+                    removed += 1;
+                    skipping = 1;
+                    continue;
+                }
+                in_style = element.local_name().as_ref() == b"style";
+                Event::Start(clean_attributes(&element, &mut removed))
+            }
+            Event::Empty(element) => {
+                if is_blocked(&element) {
+                    removed += 1;
+                    continue;
+                }
+                Event::Empty(clean_attributes(&element, &mut removed))
+            }
+            Event::End(element) => {
+                in_style = false;
+                Event::End(element)
+            }
+            Event::Text(text) if in_style => {
+                let css = text
+                    .decode()
+                    .map_err(|e| format!("Invalid SVG text: {}", e))?;
+                let (css, count) = strip_remote_css(&css);
+                removed += count;
+                Event::Text(BytesText::from_escaped(css))
+            }
+            Event::CData(data) if in_style => {
+                let css = String::from_utf8_lossy(&data).to_string();
+                let (css, count) = strip_remote_css(&css);
+                removed += count;
+                Event::CData(quick_xml::events::BytesCData::new(css))
+            }
+            // Doctypes can declare entities and processing instructions can pull in stylesheets
+            Event::DocType(_) | Event::PI(_) => {
+                removed += 1;
+                continue;
+            }
+            other => other,
+        };
+
+        writer
+            .write_event(output)
+            .map_err(|e| format!("Failed to write SVG: {}", e))?;
+    }
+
+    let output = String::from_utf8(writer.into_inner())
+        .map_err(|e| format!("Failed to write SVG: {}", e))?;
+    Ok((output, removed))
+}
+
+/// Sanitize an SVG asset in place
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn sanitize_svg(app: AppHandle, path: String) -> Result<SanitizeReport, Error> {
+    paths::check(&app, &path, paths::Scope::Write)?;
+    let path = Path::new(&path);
+    if !is_svg(path) {
+        return Err(format!("Not an SVG file: {}", path.display()).into());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (sanitized, removed) = sanitize(&content)?;
+
+    if removed > 0 {
+        fs::write(path, &sanitized).map_err(|e| format!("Failed to write file: {}", e))?;
+        audit::record_current(&app, AuditAction::Overwrite, AuditSource::Ui, &[path]);
+    }
+
+    Ok(SanitizeReport {
+        removed,
+        changed: removed > 0,
+    })
+}
+
+fn is_blocked(element: &BytesStart) -> bool {
+    let name = element.local_name();
+    if BLOCKED_ELEMENTS
+        .iter()
+        .any(|blocked| name.as_ref().eq_ignore_ascii_case(blocked))
+    {
+        return true;
+    }
+    if !BLOCKED_ANIMATIONS
+        .iter()
+        .any(|blocked| name.as_ref().eq_ignore_ascii_case(blocked))
+    {
+        return false;
+    }
+    // Animations are fine unless they target links or event handlers
+    element
+        .attributes()
+        .with_checks(false)
+        .flatten()
+        .filter(|attribute| attribute.key.local_name().as_ref() == b"attributeName")
+        .any(|attribute| {
+            let target = String::from_utf8_lossy(&attribute.value).to_ascii_lowercase();
+            target.ends_with("href") || target.starts_with("on")
+        })
+}
+
+fn clean_attributes(element: &BytesStart, removed: &mut usize) -> BytesStart<'static> {
+    let mut kept: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+
+    for attribute in element.attributes().with_checks(false).flatten() {
+        let key = attribute.key.as_ref().to_vec();
+        let local = attribute.key.local_name().as_ref().to_ascii_lowercase();
+        let value = attribute
+            .unescape_value()
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+
+        if local.starts_with(b"on") {
+            *removed += 1;
+            continue;
+        }
+        if local == b"href" && !is_local_reference(&value) {
+            *removed += 1;
+            continue;
+        }
+        if local == b"style" {
+            let (css, count) = strip_remote_css(&value);
+            if count > 0 {
+                *removed += count;
+                kept.push((
+                    key,
+                    quick_xml::escape::escape(css.as_str()).as_bytes().to_vec(),
+                ));
+                continue;
+            }
+        }
+        if value.to_ascii_lowercase().contains("url(") && has_remote_url(&value) {
+            *removed += 1;
+            continue;
+        }
+        kept.push((key, attribute.value.to_vec()));
+    }
+
+    let mut clean = element.to_owned();
+    clean.clear_attributes();
+    for (key, value) in &kept {
+        clean.push_attribute((key.as_slice(), value.as_slice()));
+    }
+    clean
+}
+
+/// Fragment links and embedded raster data are safe; anything else can reach the network or run code
+fn is_local_reference(value: &str) -> bool {
+    let value = value.trim().to_ascii_lowercase();
+    value.starts_with('#')
+        || value.starts_with("data:image/png")
+        || value.starts_with("data:image/jpeg")
+        || value.starts_with("data:image/gif")
+        || value.starts_with("data:image/webp")
+}
+
+fn has_remote_url(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    lower.match_indices("url(").any(|(at, _)| {
+        let target = lower[at + 4..].trim_start().trim_start_matches(['"', '\'']);
+        !target.starts_with('#')
+    })
+}
+
+/// Remove `@import` rules and `url(...)` values that do not point inside the document
+fn strip_remote_css(css: &str) -> (String, usize) {
+    let mut removed = 0;
+    let mut out = String::with_capacity(css.len());
+
+    for statement in css.split_inclusive(';') {
+        if statement
+            .trim_start()
+            .to_ascii_lowercase()
+            .starts_with("@import")
+        {
+            removed += 1;
+            continue;
+        }
+        out.push_str(statement);
+    }
+
+    let mut result = String::with_capacity(out.len());
+    let mut rest = out.as_str();
+    while let Some(at) = rest.to_ascii_lowercase().find("url(") {
+        let (before, after) = rest.split_at(at);
+        result.push_str(before);
+        let end = after.find(')').map(|i| i + 1).unwrap_or(after.len());
+        let (call, remainder) = after.split_at(end);
+        if has_remote_url(call) {
+            removed += 1;
+            result.push_str("none");
+        } else {
+            result.push_str(call);
+        }
+        rest = remainder;
+    }
+    result.push_str(rest);
+
+    (result, removed)
+}
@@ -0,0 +1,185 @@
+use crate::cancel;
+use crate::document;
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::providers::{self, AiRequest};
+use crate::{catalog, paths, read_only, retag, workspace};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use tauri::AppHandle;
+
+/// Tags of the workspace shown to the model, most used first
+const VOCABULARY_LIMIT: usize = 200;
+const DEFAULT_LIMIT: usize = 8;
+/// Suggestions below this confidence are dropped
+const MIN_CONFIDENCE: f64 = 0.2;
+const MAX_REPLY_TOKENS: u32 = 400;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TagSuggestion {
+    pub tag: String,
+    /// Between 0 and 1, as judged by the model
+    pub confidence: f64,
+    /// Whether the tag is already used elsewhere in the workspace
+    pub existing: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagAssignment {
+    pub path: String,
+    pub tags: Vec<String>,
+}
+
+/// The JSON array in a model reply, which may be wrapped in prose or a code fence
+fn parse_reply(reply: &str) -> Vec<(String, f64)> {
+    let (Some(start), Some(end)) = (reply.find('['), reply.rfind(']')) else {
+        return Vec::new();
+    };
+    let Some(Ok(Value::Array(items))) = reply.get(start..=end).map(serde_json::from_str) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Value::String(tag) => Some((tag.clone(), 0.5)),
+            Value::Object(_) => Some((
+                item["tag"].as_str()?.to_string(),
+                item["confidence"].as_f64().unwrap_or(0.5).clamp(0.0, 1.0),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Ranked tags for a document, preferring the workspace's existing vocabulary
+fn suggest(
+    app: &AppHandle,
+    path: &str,
+    provider: Option<String>,
+    limit: usize,
+    op_id: Option<String>,
+    cancel: &cancel::CancelToken,
+) -> Result<Vec<TagSuggestion>, String> {
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    let resolved = paths::check(app, path, paths::Scope::Read).map_err(|e| e.message)?;
+    let current = catalog::tags(&document::read_board(&resolved)?.to_markdown());
+    let vocabulary: Vec<String> = catalog::tag_counts(app, &root)?
+        .into_iter()
+        .take(VOCABULARY_LIMIT)
+        .map(|count| count.tag)
+        .collect();
+    let known: BTreeSet<&str> = vocabulary.iter().map(String::as_str).collect();
+
+    let listed = match vocabulary.is_empty() {
+        true => "The workspace has no tags yet.".to_string(),
+        false => format!(
+            "Tags already used in the workspace: {}.",
+            vocabulary.join(", ")
+        ),
+    };
+    let system = format!(
+        "You suggest tags for notes. {} Prefer these existing tags, and only invent a new one \
+         when none fits. Tags are short, lowercase, and use hyphens instead of spaces. Reply with \
+         only a JSON array of up to {} objects like {{\"tag\": \"name\", \"confidence\": 0.8}}, \
+         most relevant first.",
+        listed, limit
+    );
+    let request = AiRequest {
+        provider,
+        model: None,
+        system: Some(system),
+        prompt: "Suggest tags for this document.".to_string(),
+        documents: vec![resolved.to_string_lossy().to_string()],
+        source: None,
+        max_tokens: Some(MAX_REPLY_TOKENS),
+        temperature: Some(0.2),
+    };
+    let reply = providers::complete(app, request, op_id, cancel)?.text;
+
+    let mut seen = BTreeSet::new();
+    let mut suggestions: Vec<TagSuggestion> = parse_reply(&reply)
+        .into_iter()
+        .filter(|(_, confidence)| *confidence >= MIN_CONFIDENCE)
+        .filter_map(|(tag, confidence)| Some((retag::normalize(&tag)?, confidence)))
+        .filter(|(tag, _)| !current.contains(tag) && seen.insert(tag.clone()))
+        .map(|(tag, confidence)| TagSuggestion {
+            existing: known.contains(tag.as_str()),
+            tag,
+            confidence,
+        })
+        .collect();
+    // Existing tags rank ahead of new ones the model was equally sure of
+    suggestions.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then(b.existing.cmp(&a.existing))
+    });
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
+
+/// Suggest tags for a document with the on-device model or a configured provider, ranked by
+/// confidence. Tags the document already has are left out.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn suggest_tags(
+    app: AppHandle,
+    doc: String,
+    provider: Option<String>,
+    limit: Option<usize>,
+    op_id: Option<String>,
+) -> Result<Vec<TagSuggestion>, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = cancel::begin(&app, op_id.clone());
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).max(1);
+        suggest(&app, &doc, provider, limit, op_id, operation.token()).map_err(Error::from)
+    })
+    .await
+    .map_err(|e| format!("Tagging task failed: {}", e))?
+}
+
+/// Workspace-relative paths of documents with no tags, for tagging in bulk
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn list_untagged_documents(
+    app: AppHandle,
+    workspace: String,
+) -> Result<Vec<String>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || Ok(catalog::untagged_paths(&app, &root)?))
+        .await
+        .map_err(|e| format!("Listing task failed: {}", e))?
+}
+
+/// Add tags to documents as a line of `#tags` at the end; returns how many documents changed
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn apply_tags(app: AppHandle, assignments: Vec<TagAssignment>) -> Result<usize, Error> {
+    let mut changed = 0;
+    for assignment in assignments {
+        let path = paths::check(&app, &assignment.path, paths::Scope::Write)?;
+        read_only::ensure_writable(&path)?;
+        let mut board = document::read_board(&path)?;
+        let current = catalog::tags(&board.to_markdown());
+        let mut added = BTreeSet::new();
+        for tag in &assignment.tags {
+            let tag = retag::normalize(tag).ok_or_else(|| format!("Invalid tag: {}", tag))?;
+            if !current.contains(&tag) {
+                added.insert(tag);
+            }
+        }
+        if added.is_empty() {
+            continue;
+        }
+        let line: Vec<String> = added.iter().map(|tag| format!("#{}", tag)).collect();
+        board.push_markdown(&line.join(" "));
+        board.board.updated_at = document::now_millis();
+        document::write_board(&path, &board)?;
+        events::file_changed(&app, &path, ChangeKind::Modified);
+        changed += 1;
+    }
+    Ok(changed)
+}
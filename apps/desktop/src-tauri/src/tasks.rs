@@ -0,0 +1,424 @@
+use crate::catalog::{self, Database};
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::paths;
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_LIMIT: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low = 1,
+    Medium = 2,
+    High = 3,
+}
+
+impl Priority {
+    fn from_rank(rank: i64) -> Option<Priority> {
+        match rank {
+            1 => Some(Priority::Low),
+            2 => Some(Priority::Medium),
+            3 => Some(Priority::High),
+            _ => None,
+        }
+    }
+
+    /// `!high`, `priority:high`, `!!!` and the emoji the Tasks plugin for Obsidian writes
+    fn from_token(token: &str) -> Option<Priority> {
+        let token = token.to_lowercase();
+        let name = token
+            .strip_prefix('!')
+            .or_else(|| token.strip_prefix("priority:"));
+        match (token.as_str(), name) {
+            ("⏫" | "🔺" | "!!!", _) | (_, Some("high")) => Some(Priority::High),
+            ("🔼" | "!!", _) | (_, Some("medium")) => Some(Priority::Medium),
+            ("🔽" | "!", _) | (_, Some("low")) => Some(Priority::Low),
+            _ => None,
+        }
+    }
+}
+
+/// A `- [ ]` or `- [x]` line in a text or markdown block
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTask {
+    pub block_id: String,
+    pub page_id: String,
+    /// Line within the block, from 0
+    pub line: usize,
+    /// The task without its checkbox, due date or priority
+    pub text: String,
+    pub done: bool,
+    /// `YYYY-MM-DD`, from `due:2024-05-01`, `@due(2024-05-01)` or `📅 2024-05-01`
+    pub due: Option<String>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    #[serde(flatten)]
+    pub task: BlockTask,
+    /// Workspace-relative path of the document
+    pub path: String,
+    /// Title of the document
+    pub name: String,
+    /// Open with a due date before today
+    pub overdue: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TaskFilter {
+    pub done: Option<bool>,
+    /// Tasks carrying this `#tag`
+    pub tag: Option<String>,
+    /// A document or folder, workspace-relative
+    pub path: Option<String>,
+    /// `YYYY-MM-DD`; both ends are included
+    pub due_after: Option<String>,
+    pub due_before: Option<String>,
+    /// Only tasks with (`true`) or without (`false`) a due date
+    pub has_due: Option<bool>,
+    pub overdue: Option<bool>,
+    /// This priority or higher
+    pub priority: Option<Priority>,
+    /// Words the task text contains, in any case
+    pub text: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// The checkbox of a task line: where `[` is and whether it is checked
+fn checkbox(line: &str) -> Option<(usize, bool)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let marker = match rest.chars().next()? {
+        '-' | '*' | '+' => 1,
+        c if c.is_ascii_digit() => {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            match rest[digits..].starts_with(['.', ')']) {
+                true => digits + 1,
+                false => return None,
+            }
+        }
+        _ => return None,
+    };
+    let after = rest[marker..].strip_prefix(' ')?;
+    let done = match after.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    if !after[3..].is_empty() && !after[3..].starts_with(' ') {
+        return None;
+    }
+    Some((indent + marker + 1, done))
+}
+
+fn date(value: &str) -> Option<String> {
+    NaiveDate::parse_from_str(value, DATE_FORMAT)
+        .ok()
+        .map(|date| date.format(DATE_FORMAT).to_string())
+}
+
+/// Split a task's text into what it says and its due date and priority
+fn metadata(text: &str) -> (String, Option<String>, Option<Priority>) {
+    let mut words = Vec::new();
+    let mut due = None;
+    let mut priority = None;
+    let mut tokens = text.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        let parsed = token
+            .strip_prefix("due:")
+            .or_else(|| {
+                token
+                    .strip_prefix("@due(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+            })
+            .and_then(date);
+        if parsed.is_some() {
+            due = parsed;
+            continue;
+        }
+        if token == "📅" {
+            if let Some(parsed) = tokens.peek().and_then(|next| date(next)) {
+                due = Some(parsed);
+                tokens.next();
+                continue;
+            }
+        }
+        if let Some(parsed) = Priority::from_token(token) {
+            priority = Some(parsed);
+            continue;
+        }
+        words.push(token);
+    }
+    (words.join(" "), due, priority)
+}
+
+/// Tasks in one block's text, skipping fenced code
+fn block_tasks(text: &str, block_id: &str, page_id: &str) -> Vec<BlockTask> {
+    let mut tasks = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (number, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            (None, Some(marker)) => fence = Some(marker),
+            _ => {}
+        }
+        if fence.is_some() || marker.is_some() {
+            continue;
+        }
+        let Some((start, done)) = checkbox(line) else {
+            continue;
+        };
+        let body = line[start + 3..].trim();
+        let (text, due, priority) = metadata(body);
+        tasks.push(BlockTask {
+            block_id: block_id.to_string(),
+            page_id: page_id.to_string(),
+            line: number,
+            text,
+            done,
+            due,
+            priority,
+            tags: catalog::tags(body).into_iter().collect(),
+        });
+    }
+    tasks
+}
+
+/// Every task in the document's text and markdown blocks
+pub fn extract(board: &BoardFile) -> Vec<BlockTask> {
+    board
+        .pages()
+        .iter()
+        .flat_map(|page| {
+            board.page_shapes(page).into_iter().flat_map(|shape| {
+                shape
+                    .text()
+                    .map(|text| block_tasks(text, &shape.id, &page.id))
+                    .unwrap_or_default()
+            })
+        })
+        .collect()
+}
+
+/// Replace the cached tasks of the document at `path`
+pub fn store(db: &Connection, path: &str, tasks: &[BlockTask]) -> rusqlite::Result<()> {
+    db.execute("DELETE FROM tasks WHERE path = ?1", [path])?;
+    for task in tasks {
+        db.execute(
+            "INSERT OR REPLACE INTO tasks
+             (path, block_id, line, page_id, text, done, due, priority, tags)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                path,
+                task.block_id,
+                task.line,
+                task.page_id,
+                task.text,
+                task.done,
+                task.due,
+                task.priority.map(|priority| priority as i64),
+                task.tags.join(" "),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Cached tasks matching `filter`, open ones first, then by due date and priority
+pub fn query(db: &Database, filter: &TaskFilter) -> Result<Vec<Task>, String> {
+    let db = db
+        .lock()
+        .map_err(|e| format!("Failed to read metadata cache: {}", e))?;
+    let today = Local::now().date_naive().format(DATE_FORMAT).to_string();
+    let folder = filter
+        .path
+        .as_deref()
+        .map(|path| path.trim_matches('/').to_string())
+        .filter(|path| !path.is_empty());
+    let overdue_before = filter.overdue.filter(|overdue| *overdue).map(|_| &today);
+    let mut statement = db
+        .prepare(
+            "SELECT t.path, d.name, t.block_id, t.line, t.page_id, t.text, t.done, t.due,
+                    t.priority, t.tags
+             FROM tasks t JOIN documents d ON d.path = t.path
+             WHERE (?1 IS NULL OR t.done = ?1)
+               AND (?2 IS NULL OR t.path = ?2 OR substr(t.path, 1, length(?2) + 1) = ?2 || '/')
+               AND (?3 IS NULL OR t.due >= ?3)
+               AND (?4 IS NULL OR t.due <= ?4)
+               AND (?5 IS NULL OR (t.due IS NOT NULL) = ?5)
+               AND (?6 IS NULL OR t.priority >= ?6)
+               AND (?7 IS NULL OR (t.done = 0 AND t.due < ?7))
+             ORDER BY t.done, t.due IS NULL, t.due, t.priority IS NULL, t.priority DESC,
+                      t.path, t.block_id, t.line",
+        )
+        .map_err(|e| format!("Failed to read tasks: {}", e))?;
+    let rows = statement
+        .query_map(
+            params![
+                filter.done,
+                folder,
+                filter.due_after,
+                filter.due_before,
+                filter.has_due,
+                filter.priority.map(|priority| priority as i64),
+                overdue_before,
+            ],
+            |row| {
+                let tags: String = row.get(9)?;
+                let due: Option<String> = row.get(7)?;
+                let done: bool = row.get(6)?;
+                Ok(Task {
+                    path: row.get(0)?,
+                    name: row.get(1)?,
+                    overdue: !done && due.as_deref().is_some_and(|due| due < today.as_str()),
+                    task: BlockTask {
+                        block_id: row.get(2)?,
+                        line: row.get(3)?,
+                        page_id: row.get(4)?,
+                        text: row.get(5)?,
+                        done,
+                        due,
+                        priority: row.get::<_, Option<i64>>(8)?.and_then(Priority::from_rank),
+                        tags: tags.split_whitespace().map(str::to_string).collect(),
+                    },
+                })
+            },
+        )
+        .map_err(|e| format!("Failed to read tasks: {}", e))?;
+
+    let tag = filter
+        .tag
+        .as_deref()
+        .map(|tag| tag.trim_start_matches('#').to_lowercase());
+    let words: Vec<String> = filter
+        .text
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+    let overdue = filter.overdue;
+    Ok(rows
+        .flatten()
+        .filter(|task| tag.as_ref().is_none_or(|tag| task.task.tags.contains(tag)))
+        .filter(|task| overdue != Some(false) || !task.overdue)
+        .filter(|task| {
+            let text = task.task.text.to_lowercase();
+            words.iter().all(|word| text.contains(word))
+        })
+        .take(filter.limit.unwrap_or(DEFAULT_LIMIT))
+        .collect())
+}
+
+/// Tasks from every document in the workspace, from the metadata cache: open ones first, then
+/// by due date and priority. The cache follows changes to documents, so a listing re-queried
+/// on `file:changed` stays current.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn query_tasks(
+    app: AppHandle,
+    filter: Option<TaskFilter>,
+    workspace: Option<String>,
+) -> Result<Vec<Task>, Error> {
+    let root = paths::workspace_or_current(&app, workspace.as_deref(), paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = catalog::database(&app, &root)?;
+        Ok(query(&db, &filter.unwrap_or_default())?)
+    })
+    .await
+    .map_err(|e| format!("Task query failed: {}", e))?
+}
+
+/// A task line with its checkbox set to `done`
+pub fn set_checked(line: &str, done: bool) -> String {
+    match checkbox(line) {
+        Some((start, _)) => {
+            let mark = if done { "[x]" } else { "[ ]" };
+            format!("{}{}{}", &line[..start], mark, &line[start + 3..])
+        }
+        None => line.to_string(),
+    }
+}
+
+/// Rewrite one task line in a block of the document at `path` and save it, returning the task
+/// as it reads afterwards; `line` picks the task when the block has more than one
+pub fn edit_task(
+    app: &AppHandle,
+    path: &Path,
+    block_id: &str,
+    line: Option<usize>,
+    edit: impl FnOnce(&BlockTask, &str) -> String,
+) -> Result<BlockTask, Error> {
+    let mut board = document::read_board(path)?;
+    let shape = board
+        .pages()
+        .iter()
+        .flat_map(|page| board.page_shapes(page))
+        .find(|shape| shape.id == block_id)
+        .ok_or_else(|| Error::not_found("Block does not exist").with_context(block_id))?;
+    let text = shape.text().unwrap_or_default().to_string();
+    let tasks = block_tasks(&text, &shape.id, &shape.page_id);
+    let task = match line {
+        Some(line) => tasks.into_iter().find(|task| task.line == line),
+        None if tasks.len() == 1 => tasks.into_iter().next(),
+        None if tasks.is_empty() => None,
+        None => {
+            return Err(
+                "Invalid task: the block has more than one task, so a line is needed".into(),
+            )
+        }
+    }
+    .ok_or_else(|| Error::not_found("Task does not exist").with_context(block_id))?;
+
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let edited = edit(&task, &lines[task.line]);
+    if edited == lines[task.line] {
+        return Ok(task);
+    }
+    lines[task.line] = edited;
+    let mut updated = lines.join("\n");
+    if text.ends_with('\n') {
+        updated.push('\n');
+    }
+    board.set_text(block_id, &updated);
+    board.board.updated_at = document::now_millis();
+    document::write_board(path, &board)?;
+    events::file_changed(app, path, ChangeKind::Modified);
+    block_tasks(&updated, &shape.id, &shape.page_id)
+        .into_iter()
+        .find(|edited| edited.line == task.line)
+        .ok_or_else(|| Error::from("Invalid task: the edit removed its checkbox"))
+}
+
+/// Check or uncheck a task in a block of `doc`; `line` picks the task when the block has more
+/// than one, and `done` sets the state instead of flipping it
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn toggle_task(
+    app: AppHandle,
+    doc: String,
+    block_id: String,
+    line: Option<usize>,
+    done: Option<bool>,
+) -> Result<BlockTask, Error> {
+    paths::check(&app, &doc, paths::Scope::Write)?;
+    edit_task(&app, Path::new(&doc), &block_id, line, |task, content| {
+        set_checked(content, done.unwrap_or(!task.done))
+    })
+}
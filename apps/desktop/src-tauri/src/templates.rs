@@ -0,0 +1,289 @@
+use crate::document::{self, BoardFile, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{local_api, paths, read_only, sanitize, vault, workspace};
+use chrono::{DateTime, Local};
+use inkfinite_core::template::{self, Prompt, Template};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::AppHandle;
+
+pub const TEMPLATE_EXTENSION: &str = "md";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateInfo {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub prompts: Vec<Prompt>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplatePreview {
+    /// Name the created document will get
+    pub name: String,
+    pub folder: Option<String>,
+    pub markdown: String,
+    /// Names used by the template that had no value
+    pub missing: Vec<String>,
+}
+
+pub fn dir(root: &Path) -> PathBuf {
+    workspace::internal_dir(root).join("templates")
+}
+
+fn template_path(root: &Path, id: &str) -> PathBuf {
+    dir(root).join(format!(
+        "{}.{}",
+        local_api::file_stem(id),
+        TEMPLATE_EXTENSION
+    ))
+}
+
+fn load(root: &Path, id: &str) -> Result<Template, String> {
+    let path = template_path(root, id);
+    let source = fs::read_to_string(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("Template not found: {}", id),
+        _ => format!("Failed to read template: {}", e),
+    })?;
+    Template::parse(&source, id)
+}
+
+/// Render the template's name, folder and body as of `now`; `name` overrides its `title`
+fn render(
+    root: &Path,
+    template: &Template,
+    values: &Value,
+    name: Option<&str>,
+    now: DateTime<Local>,
+) -> Result<TemplatePreview, String> {
+    let mut values = template.values(values)?;
+    let title = name.map(str::to_string).or_else(|| {
+        values
+            .get("title")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    });
+    if let Value::Object(map) = &mut values {
+        let defaults = [
+            ("clipboard", Value::String(clipboard_text())),
+            (
+                "workspace",
+                json!(root.file_name().map(|name| name.to_string_lossy())),
+            ),
+            ("template", Value::String(template.name.clone())),
+        ];
+        for (key, value) in defaults {
+            map.entry(key).or_insert(value);
+        }
+    }
+
+    let mut missing = std::collections::BTreeSet::new();
+    let name = match (title, &template.title) {
+        (Some(title), _) => title,
+        (None, Some(source)) => {
+            let rendered = template::render(source, &values, now)?;
+            missing.extend(rendered.missing);
+            rendered.text.trim().to_string()
+        }
+        (None, None) => template.name.clone(),
+    };
+    if let Value::Object(map) = &mut values {
+        map.entry("title")
+            .or_insert_with(|| Value::String(name.clone()));
+    }
+    let folder = match &template.folder {
+        Some(source) => {
+            let rendered = template::render(source, &values, now)?;
+            missing.extend(rendered.missing);
+            Some(rendered.text.trim().trim_matches(['/', '\\']).to_string())
+                .filter(|folder| !folder.is_empty())
+        }
+        None => None,
+    };
+    let body = template::render(&template.body, &values, now)?;
+    missing.extend(body.missing);
+    let policy = sanitize::load_policy(root);
+    Ok(TemplatePreview {
+        name,
+        folder,
+        markdown: sanitize::clean_markdown(&policy, &body.text),
+        missing: missing.into_iter().collect(),
+    })
+}
+
+/// Render a saved template without prompting, with dates as of `now`, for documents that
+/// belong to another day
+pub fn render_at(
+    root: &Path,
+    id: &str,
+    name: &str,
+    now: DateTime<Local>,
+) -> Result<TemplatePreview, String> {
+    let template = load(root, id)?;
+    render(root, &template, &Value::Null, Some(name), now)
+}
+
+#[cfg(desktop)]
+fn clipboard_text() -> String {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .unwrap_or_default()
+}
+
+/// Clipboard text is not available on mobile builds, so `{{clipboard}}` renders empty
+#[cfg(mobile)]
+fn clipboard_text() -> String {
+    String::new()
+}
+
+/// Templates saved in the workspace's `.inkfinite/templates`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_templates(app: AppHandle, workspace: String) -> Result<Vec<TemplateInfo>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let Ok(entries) = fs::read_dir(dir(&root)) else {
+        return Ok(Vec::new());
+    };
+    let mut templates = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != TEMPLATE_EXTENSION) {
+            continue;
+        }
+        let id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match load(&root, &id) {
+            Ok(template) => templates.push(TemplateInfo {
+                id,
+                name: template.name,
+                description: template.description,
+                prompts: template.prompts,
+            }),
+            Err(error) => tracing::warn!("Skipping template {}: {}", path.display(), error),
+        }
+    }
+    templates.sort_by_key(|template| template.name.to_lowercase());
+    Ok(templates)
+}
+
+/// Read a template's source for editing
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn read_template(app: AppHandle, workspace: String, id: String) -> Result<String, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    fs::read_to_string(template_path(&root, &id)).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => Error::not_found("Template not found").with_context(&id),
+        _ => format!("Failed to read template: {}", e).into(),
+    })
+}
+
+/// Save a template after checking its syntax; returns its id
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_template(
+    app: AppHandle,
+    workspace: String,
+    id: String,
+    source: String,
+) -> Result<String, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    let id = local_api::file_stem(&id);
+    Template::parse(&source, &id)?;
+    fs::create_dir_all(dir(&root)).map_err(|e| format!("Failed to create folder: {}", e))?;
+    fs::write(template_path(&root, &id), source)
+        .map_err(|e| format!("Failed to write template: {}", e))?;
+    Ok(id)
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn delete_template(app: AppHandle, workspace: String, id: String) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    read_only::ensure_writable(&root)?;
+    match fs::remove_file(template_path(&root, &id)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete template: {}", e).into()),
+    }
+}
+
+/// Evaluate a saved template, or unsaved `source` while it is being edited, without writing
+/// anything
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn preview_template(
+    app: AppHandle,
+    workspace: String,
+    id: Option<String>,
+    source: Option<String>,
+    values: Option<Value>,
+    name: Option<String>,
+) -> Result<TemplatePreview, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let template = match (source, id) {
+        (Some(source), id) => Template::parse(&source, id.as_deref().unwrap_or("Untitled"))?,
+        (None, Some(id)) => load(&root, &id)?,
+        (None, None) => return Err("Invalid template: an id or a source is required".into()),
+    };
+    let values = values.unwrap_or(Value::Null);
+    Ok(render(
+        &root,
+        &template,
+        &values,
+        name.as_deref(),
+        Local::now(),
+    )?)
+}
+
+/// Create a document from a template, in `folder` or the template's own folder, and return its
+/// path
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn create_from_template(
+    app: AppHandle,
+    workspace: String,
+    id: String,
+    values: Option<Value>,
+    name: Option<String>,
+    folder: Option<String>,
+) -> Result<String, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let template = load(&root, &id)?;
+    let values = values.unwrap_or(Value::Null);
+    let preview = render(&root, &template, &values, name.as_deref(), Local::now())?;
+
+    let folder = folder.or(preview.folder).unwrap_or_default();
+    let relative = Path::new(folder.trim_start_matches(['/', '\\']));
+    if relative
+        .components()
+        .any(|part| !matches!(part, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Error::invalid_path("Invalid folder").with_context(&folder));
+    }
+    let dir = root.join(relative);
+    if vault::vault_of(&dir).is_some() {
+        return Err("Templates cannot create documents in a vault".into());
+    }
+    let dir = paths::check(&app, &dir.to_string_lossy(), paths::Scope::Write)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+
+    let path = document::unique_path(
+        &dir,
+        &local_api::file_stem(&preview.name),
+        DOCUMENT_EXTENSION,
+    );
+    document::write_board(
+        &path,
+        &BoardFile::from_markdown(&preview.name, &preview.markdown),
+    )?;
+    events::file_changed(&app, &path, ChangeKind::Created);
+    Ok(path.to_string_lossy().to_string())
+}
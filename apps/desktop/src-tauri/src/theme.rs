@@ -0,0 +1,204 @@
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often the accent color is re-read; appearance changes also arrive as window events
+const POLL: std::time::Duration = std::time::Duration::from_secs(5);
+/// Accent used when the OS does not report one
+const DEFAULT_ACCENT: Rgb = Rgb(0x3b, 0x82, 0xf6);
+
+/// Last theme emitted, so unchanged polls stay quiet
+static LAST: Mutex<Option<SystemTheme>> = Mutex::new(None);
+
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    Light,
+    Dark,
+}
+
+/// Colors resolved from the OS appearance, as `#rrggbb`
+#[derive(serde::Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Palette {
+    pub background: String,
+    pub surface: String,
+    pub border: String,
+    pub text: String,
+    pub muted_text: String,
+    pub accent: String,
+    /// Readable text color on top of `accent`
+    pub accent_text: String,
+}
+
+/// Payload of `theme:changed`
+#[derive(serde::Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemTheme {
+    pub mode: ColorMode,
+    /// Accent chosen in the OS settings, if it exposes one
+    pub system_accent: Option<String>,
+    pub palette: Palette,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Rgb(u8, u8, u8);
+
+impl Rgb {
+    fn hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+
+    /// Relative luminance per WCAG
+    fn luminance(self) -> f64 {
+        let channel = |c: u8| {
+            let c = f64::from(c) / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * channel(self.0) + 0.7152 * channel(self.1) + 0.0722 * channel(self.2)
+    }
+}
+
+/// Current OS appearance and accent color with the palette the UI should use
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_system_theme(app: AppHandle) -> SystemTheme {
+    resolve(&app)
+}
+
+/// Watch for accent color changes, emitting `theme:changed` whenever the resolved theme differs
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        changed(&app);
+        std::thread::sleep(POLL);
+    });
+}
+
+/// Emit `theme:changed` if the resolved theme differs from the last one sent; called for
+/// window theme events as well as by the watcher
+pub fn changed(app: &AppHandle) {
+    let theme = resolve(app);
+    let Ok(mut last) = LAST.lock() else {
+        return;
+    };
+    if last.as_ref() == Some(&theme) {
+        return;
+    }
+    // The first resolve only records the starting point; the UI reads it with get_system_theme
+    let first = last.is_none();
+    *last = Some(theme.clone());
+    drop(last);
+    if !first {
+        let _ = app.emit("theme:changed", theme);
+    }
+}
+
+fn resolve(app: &AppHandle) -> SystemTheme {
+    let mode = app
+        .webview_windows()
+        .values()
+        .next()
+        .and_then(|window| window.theme().ok())
+        .map_or(ColorMode::Light, |theme| match theme {
+            tauri::Theme::Dark => ColorMode::Dark,
+            _ => ColorMode::Light,
+        });
+    let system_accent = accent_color();
+    SystemTheme {
+        mode,
+        system_accent: system_accent.map(Rgb::hex),
+        palette: palette(mode, system_accent.unwrap_or(DEFAULT_ACCENT)),
+    }
+}
+
+fn palette(mode: ColorMode, accent: Rgb) -> Palette {
+    let (background, surface, border, text, muted_text) = match mode {
+        ColorMode::Light => ("#ffffff", "#f3f4f6", "#e5e7eb", "#1f2933", "#6b7280"),
+        ColorMode::Dark => ("#1e1e1e", "#2a2a2a", "#3a3a3a", "#e5e7eb", "#9ca3af"),
+    };
+    // White text needs a contrast ratio of at least 4.5:1, i.e. an accent this dark
+    let accent_text = if accent.luminance() <= 0.179 {
+        "#ffffff"
+    } else {
+        "#000000"
+    };
+    Palette {
+        background: background.to_string(),
+        surface: surface.to_string(),
+        border: border.to_string(),
+        text: text.to_string(),
+        muted_text: muted_text.to_string(),
+        accent: accent.hex(),
+        accent_text: accent_text.to_string(),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn accent_color() -> Option<Rgb> {
+    use objc2_app_kit::{NSColor, NSColorSpace};
+
+    let color =
+        NSColor::controlAccentColor().colorUsingColorSpace(&NSColorSpace::sRGBColorSpace())?;
+    Some(from_unit(
+        color.redComponent(),
+        color.greenComponent(),
+        color.blueComponent(),
+    ))
+}
+
+#[cfg(windows)]
+fn accent_color() -> Option<Rgb> {
+    use windows::UI::ViewManagement::{UIColorType, UISettings};
+
+    let color = UISettings::new()
+        .and_then(|settings| settings.GetColorValue(UIColorType::Accent))
+        .ok()?;
+    Some(Rgb(color.R, color.G, color.B))
+}
+
+/// Read from the desktop portal, which answers `(<<(r, g, b)>>,)` with each channel in 0–1;
+/// values outside that range mean no accent is set
+#[cfg(target_os = "linux")]
+fn accent_color() -> Option<Rgb> {
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.portal.Desktop",
+            "--object-path",
+            "/org/freedesktop/portal/desktop",
+            "--method",
+            "org.freedesktop.portal.Settings.Read",
+            "org.freedesktop.appearance",
+            "accent-color",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let channels: Vec<f64> = text
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == 'e'))
+        .filter_map(|part| part.parse().ok())
+        .collect();
+    match channels[..] {
+        [r, g, b, ..] if [r, g, b].iter().all(|c| (0.0..=1.0).contains(c)) => {
+            Some(from_unit(r, g, b))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows, target_os = "linux")))]
+fn accent_color() -> Option<Rgb> {
+    None
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn from_unit(r: f64, g: f64, b: f64) -> Rgb {
+    let channel = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgb(channel(r), channel(g), channel(b))
+}
@@ -0,0 +1,192 @@
+use crate::blocking;
+use crate::error::Error;
+use crate::{paths, read_only, video, workspace};
+use image::ImageReader;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::ipc::Response;
+use tauri::{AppHandle, Manager};
+
+const THUMBNAILS_DIR: &str = "thumbnails";
+const MIN_SIZE: u32 = 16;
+const MAX_SIZE: u32 = 2048;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Thumbnail {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub cached: bool,
+}
+
+/// Return a cached downscaled preview of an asset, generating it on first request
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_thumbnail(app: AppHandle, asset_path: String, size: u32) -> Result<Thumbnail, Error> {
+    paths::check(&app, &asset_path, paths::Scope::Read)?;
+    Ok(thumbnail(&app, &asset_path, size)?)
+}
+
+/// The thumbnail's PNG bytes as a raw response, for webviews that cannot load cache paths
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn get_thumbnail_data(
+    app: AppHandle,
+    asset_path: String,
+    size: u32,
+) -> Result<Response, Error> {
+    paths::check(&app, &asset_path, paths::Scope::Read)?;
+    blocking::run("create thumbnail", move || {
+        let thumbnail = thumbnail(&app, &asset_path, size)?;
+        fs::read(&thumbnail.path)
+            .map(Response::new)
+            .map_err(|e| format!("Failed to read thumbnail: {}", e))
+    })
+    .await
+}
+
+fn thumbnail(app: &AppHandle, asset_path: &str, size: u32) -> Result<Thumbnail, String> {
+    let source = Path::new(asset_path);
+    if !source.is_file() {
+        return Err(format!("File does not exist: {}", asset_path));
+    }
+    let size = size.clamp(MIN_SIZE, MAX_SIZE);
+
+    let cache_dir = cache_dir(app, source)?;
+    let target = cache_dir.join(format!("{}.png", cache_key(source, &size.to_string())?));
+
+    if target.is_file() {
+        let (width, height) = image::image_dimensions(&target)
+            .map_err(|e| format!("Failed to read thumbnail: {}", e))?;
+        return Ok(Thumbnail {
+            path: target.to_string_lossy().to_string(),
+            width,
+            height,
+            cached: true,
+        });
+    }
+
+    fs::create_dir_all(&cache_dir).map_err(|e| format!("Failed to create cache: {}", e))?;
+    let (width, height) = match video::is_video(source) {
+        true => video::poster_frame(app, source, &target, size)?,
+        false => generate(source, &target, size)?,
+    };
+
+    Ok(Thumbnail {
+        path: target.to_string_lossy().to_string(),
+        width,
+        height,
+        cached: false,
+    })
+}
+
+/// Delete all cached thumbnails for the current workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn clear_thumbnail_cache(app: AppHandle) -> Result<(), Error> {
+    let Some(root) = workspace::current_root(&app) else {
+        return Ok(());
+    };
+    let dir = workspace::internal_dir(&root).join(THUMBNAILS_DIR);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear cache: {}", e))?;
+    }
+    Ok(())
+}
+
+/// `.inkfinite/thumbnails` for assets inside a writable workspace, the app cache dir otherwise
+pub fn cache_dir(app: &AppHandle, source: &Path) -> Result<PathBuf, String> {
+    if let Some(root) = workspace::current_root(app)
+        .filter(|root| source.starts_with(root) && !read_only::is_read_only(root))
+    {
+        return Ok(workspace::internal_dir(&root).join(THUMBNAILS_DIR));
+    }
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(THUMBNAILS_DIR))
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))
+}
+
+/// Remove every size of cached thumbnail for `source`, returning how many there were.
+///
+/// Must run before `source` changes, since keys include its modification time.
+pub fn purge(app: &AppHandle, source: &Path) -> Result<usize, String> {
+    let dir = cache_dir(app, source)?;
+    let mut removed = 0;
+    for size in MIN_SIZE..=MAX_SIZE {
+        let target = dir.join(format!("{}.png", cache_key(source, &size.to_string())?));
+        if fs::remove_file(target).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Key derived from path, render variant, and modification time so edits invalidate the cache
+pub fn cache_key(source: &Path, variant: &str) -> Result<String, String> {
+    let metadata = fs::metadata(source).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+
+    let mut hasher = Sha256::new();
+    hasher.update(source.to_string_lossy().as_bytes());
+    hasher.update(variant.as_bytes());
+    hasher.update(modified.to_le_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+fn generate(source: &Path, target: &Path, size: u32) -> Result<(u32, u32), String> {
+    let extension = source
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if extension == "pdf" {
+        return render_pdf_first_page(source, target, size);
+    }
+
+    let image = ImageReader::open(source)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("Failed to open image: {}", e))?
+        .decode()
+        .map_err(|e| format!("Unsupported asset type: {}", e))?;
+
+    let thumbnail = image.thumbnail(size, size);
+    thumbnail
+        .save_with_format(target, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+
+    Ok((thumbnail.width(), thumbnail.height()))
+}
+
+/// Rasterize the first PDF page with poppler's `pdftoppm`
+fn render_pdf_first_page(source: &Path, target: &Path, size: u32) -> Result<(u32, u32), String> {
+    let prefix = target.with_extension("");
+    let status = Command::new("pdftoppm")
+        .args(["-png", "-singlefile", "-f", "1", "-l", "1", "-scale-to"])
+        .arg(size.to_string())
+        .arg(source)
+        .arg(&prefix)
+        .status()
+        .map_err(|_| "PDF previews require poppler (pdftoppm) to be installed".to_string())?;
+
+    if !status.success() {
+        return Err("Failed to render PDF page".to_string());
+    }
+
+    image::image_dimensions(target).map_err(|e| format!("Failed to read thumbnail: {}", e))
+}
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, Manager};
+
+/// Locate an external tool: bundled next to the app resources first, then `PATH`.
+///
+/// `probe` is an argument the tool accepts to exit successfully, such as `--version`.
+pub fn find_binary(app: &AppHandle, name: &str, probe: &str) -> Option<PathBuf> {
+    let binary = if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+
+    let bundled = app
+        .path()
+        .resource_dir()
+        .ok()
+        .map(|dir| dir.join("binaries").join(&binary))
+        .filter(|path| path.is_file());
+
+    bundled.or_else(|| {
+        Command::new(&binary)
+            .arg(probe)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .ok()
+            .filter(|status| status.success())
+            .map(|_| PathBuf::from(&binary))
+    })
+}
+
+/// Fresh scratch directory under the system temp folder; callers remove it when done
+pub fn scratch_dir(prefix: &str) -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(format!("inkfinite-{}-{}", prefix, uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    Ok(dir)
+}
@@ -0,0 +1,340 @@
+use crate::document::{self, BoardFile};
+use crate::error::Error;
+use crate::events::{self, ChangeKind};
+use crate::{paths, power, search, tools, workspace};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Emitter, Manager};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+const MODELS_DIR: &str = "models";
+const DEFAULT_MODEL: &str = "base";
+/// whisper.cpp expects 16 kHz mono samples
+const SAMPLE_RATE: u32 = 16_000;
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptSegment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SegmentEvent {
+    asset_path: String,
+    #[serde(flatten)]
+    segment: TranscriptSegment,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent {
+    asset_path: String,
+    percent: i32,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+    /// Document the transcript was written to
+    pub document_path: String,
+}
+
+/// Transcribe an audio asset offline with whisper.cpp.
+///
+/// Segments are emitted as `transcription:segment` events while decoding runs. The transcript is
+/// appended as a block to `document` when given, otherwise written as a new document.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn transcribe_audio(
+    app: AppHandle,
+    asset_path: String,
+    language: Option<String>,
+    model: Option<String>,
+    document: Option<String>,
+) -> Result<Transcript, Error> {
+    paths::check(&app, &asset_path, paths::Scope::Read)?;
+    if let Some(document) = &document {
+        paths::check(&app, document, paths::Scope::Write)?;
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        transcribe(&app, &asset_path, language, model, document)
+    })
+    .await
+    .map_err(|e| format!("Transcription task failed: {}", e))?
+    .map_err(Error::from)
+}
+
+fn transcribe(
+    app: &AppHandle,
+    asset_path: &str,
+    language: Option<String>,
+    model: Option<String>,
+    document: Option<String>,
+) -> Result<Transcript, String> {
+    let source = Path::new(asset_path);
+    if !source.is_file() {
+        return Err(format!("File does not exist: {}", asset_path));
+    }
+
+    let _awake = power::prevent_sleep("Transcribing audio");
+    let model_path = model_path(app, model.as_deref().unwrap_or(DEFAULT_MODEL))?;
+    let samples = decode_audio(app, source)?;
+
+    let context = WhisperContext::new_with_params(
+        &model_path.to_string_lossy(),
+        WhisperContextParameters::default(),
+    )
+    .map_err(|e| format!("Failed to load whisper model: {}", e))?;
+    let mut state = context
+        .create_state()
+        .map_err(|e| format!("Failed to initialize whisper: {}", e))?;
+
+    let language = language.unwrap_or_else(|| "auto".to_string());
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_language(Some(&language));
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+
+    let segment_app = app.clone();
+    let segment_asset = asset_path.to_string();
+    params.set_segment_callback_safe(move |data: whisper_rs::SegmentCallbackData| {
+        let _ = segment_app.emit(
+            "transcription:segment",
+            SegmentEvent {
+                asset_path: segment_asset.clone(),
+                segment: TranscriptSegment {
+                    start_ms: data.start_timestamp * 10,
+                    end_ms: data.end_timestamp * 10,
+                    text: data.text.trim().to_string(),
+                },
+            },
+        );
+    });
+
+    let progress_app = app.clone();
+    let progress_asset = asset_path.to_string();
+    params.set_progress_callback_safe(move |percent: i32| {
+        let _ = progress_app.emit(
+            "transcription:progress",
+            ProgressEvent {
+                asset_path: progress_asset.clone(),
+                percent,
+            },
+        );
+    });
+
+    state
+        .full(params, &samples)
+        .map_err(|e| format!("Transcription failed: {}", e))?;
+
+    // whisper.cpp timestamps are in centiseconds
+    let segments: Vec<TranscriptSegment> = state
+        .as_iter()
+        .map(|segment| TranscriptSegment {
+            start_ms: segment.start_timestamp() * 10,
+            end_ms: segment.end_timestamp() * 10,
+            text: segment
+                .to_str_lossy()
+                .map(|text| text.trim().to_string())
+                .unwrap_or_default(),
+        })
+        .filter(|segment| !segment.text.is_empty())
+        .collect();
+
+    let text = segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let document_path = write_transcript(source, &segments, document.as_deref())?;
+    let kind = if document.is_some() {
+        ChangeKind::Modified
+    } else {
+        ChangeKind::Created
+    };
+    events::file_changed(app, &document_path, kind);
+    if let Some(root) = workspace::current_root(app).filter(|root| source.starts_with(root)) {
+        search::index_text(&root, source, "transcript", &text)?;
+        events::index_updated(app, source, "transcript");
+    }
+
+    Ok(Transcript {
+        text,
+        segments,
+        document_path: document_path.to_string_lossy().to_string(),
+    })
+}
+
+/// Models live in the app data dir as `models/ggml-<name>.bin`
+fn model_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid model name: {}", name));
+    }
+    let path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?
+        .join(MODELS_DIR)
+        .join(format!("ggml-{}.bin", name));
+    if !path.is_file() {
+        return Err(format!(
+            "Whisper model '{}' is not installed. Download ggml-{}.bin into {}",
+            name,
+            name,
+            path.parent().unwrap_or(&path).display()
+        ));
+    }
+    Ok(path)
+}
+
+/// Decode any audio file to 16 kHz mono f32 samples, via ffmpeg when available
+fn decode_audio(app: &AppHandle, source: &Path) -> Result<Vec<f32>, String> {
+    if let Some(ffmpeg) = tools::find_binary(app, "ffmpeg", "-version") {
+        let output = Command::new(ffmpeg)
+            .args(["-loglevel", "error", "-i"])
+            .arg(source)
+            .args(["-f", "f32le", "-ac", "1", "-ar"])
+            .arg(SAMPLE_RATE.to_string())
+            .arg("-")
+            .output()
+            .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to decode audio: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        return Ok(output
+            .stdout
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect());
+    }
+
+    let is_wav = source
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("wav"));
+    if !is_wav {
+        return Err("Transcribing compressed audio requires ffmpeg to be installed".to_string());
+    }
+    decode_wav(source)
+}
+
+/// Fallback WAV reader: downmix to mono and linearly resample to 16 kHz
+fn decode_wav(source: &Path) -> Result<Vec<f32>, String> {
+    let mut reader =
+        hound::WavReader::open(source).map_err(|e| format!("Failed to read WAV: {}", e))?;
+    let spec = reader.spec();
+
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read WAV: {}", e))?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("Failed to read WAV: {}", e))?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    if spec.sample_rate == SAMPLE_RATE || mono.is_empty() {
+        return Ok(mono);
+    }
+
+    let ratio = spec.sample_rate as f64 / SAMPLE_RATE as f64;
+    let length = (mono.len() as f64 / ratio) as usize;
+    Ok((0..length)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = mono[index.min(mono.len() - 1)];
+            let next = mono[(index + 1).min(mono.len() - 1)];
+            current + (next - current) * fraction
+        })
+        .collect())
+}
+
+fn write_transcript(
+    source: &Path,
+    segments: &[TranscriptSegment],
+    document: Option<&str>,
+) -> Result<PathBuf, String> {
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let body = segments
+        .iter()
+        .map(|segment| format!("**[{}]** {}", timestamp(segment.start_ms), segment.text))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let markdown = format!("### Transcript: {}\n\n{}", name, body);
+
+    match document {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            let mut board = document::read_board(&path)?;
+            board.push_markdown(&markdown);
+            board.board.updated_at = document::now_millis();
+            document::write_board(&path, &board)?;
+            Ok(path)
+        }
+        None => {
+            let stem = format!(
+                "{} transcript",
+                source
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            );
+            // Assets usually sit in `assets/`, so place the document beside that folder
+            let dir = source
+                .parent()
+                .filter(|dir| {
+                    dir.file_name()
+                        .is_some_and(|n| n == crate::assets::ASSETS_DIR)
+                })
+                .and_then(Path::parent)
+                .or_else(|| source.parent())
+                .unwrap_or(Path::new("."));
+            let path = document::unique_path(dir, &stem, document::DOCUMENT_EXTENSION);
+            document::write_board(&path, &BoardFile::from_markdown(&stem, &markdown))?;
+            Ok(path)
+        }
+    }
+}
+
+fn timestamp(ms: i64) -> String {
+    let seconds = ms / 1000;
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
@@ -0,0 +1,208 @@
+use crate::error::Error;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+#[cfg(desktop)]
+const TRAY_ID: &str = "main";
+/// Recent documents listed in the tray menu
+#[cfg(desktop)]
+const MAX_RECENT: usize = 8;
+
+/// Managed state for tray details that are pushed from elsewhere rather than read from disk
+pub struct TrayState {
+    sync_status: Mutex<String>,
+}
+
+impl Default for TrayState {
+    fn default() -> Self {
+        TrayState {
+            sync_status: Mutex::new("Sync off".to_string()),
+        }
+    }
+}
+
+/// Create the tray icon; menu clicks are handled here or forwarded to the frontend as `tray:*` events
+#[cfg(desktop)]
+pub fn create(app: &AppHandle) -> tauri::Result<()> {
+    use tauri::tray::TrayIconBuilder;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Inkfinite")
+        .menu(&build_menu(app)?)
+        .show_menu_on_left_click(true)
+        .on_menu_event(handle_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Rebuild the tray menu, e.g. after the recent documents list changes
+#[cfg(desktop)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn refresh_tray(app: AppHandle) -> Result<(), Error> {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return Ok(());
+    };
+    let menu = build_menu(&app).map_err(|e| format!("Failed to build tray menu: {}", e))?;
+    Ok(tray
+        .set_menu(Some(menu))
+        .map_err(|e| format!("Failed to update tray menu: {}", e))?)
+}
+
+/// Show a sync status line in the tray menu
+#[cfg(desktop)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_tray_sync_status(
+    app: AppHandle,
+    state: tauri::State<'_, TrayState>,
+    status: String,
+) -> Result<(), Error> {
+    *state
+        .sync_status
+        .lock()
+        .map_err(|_| "Tray state is poisoned".to_string())? = status;
+    refresh_tray(app)
+}
+
+/// There is no tray on mobile builds
+#[cfg(mobile)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn refresh_tray(_app: AppHandle) -> Result<(), Error> {
+    Ok(())
+}
+
+/// There is no tray on mobile builds
+#[cfg(mobile)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_tray_sync_status(
+    _app: AppHandle,
+    _state: tauri::State<'_, TrayState>,
+    _status: String,
+) -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(desktop)]
+fn build_menu(app: &AppHandle) -> tauri::Result<tauri::menu::Menu<tauri::Wry>> {
+    use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
+    use tauri::Manager;
+
+    let recent = Submenu::with_id(app, "recent", "Recent Documents", true)?;
+    let files = crate::workspace::recent_files(app);
+    if files.is_empty() {
+        recent.append(&MenuItem::with_id(
+            app,
+            "recent-empty",
+            "No recent documents",
+            false,
+            None::<&str>,
+        )?)?;
+    }
+    for (index, file) in files.iter().take(MAX_RECENT).enumerate() {
+        let name = file
+            .name
+            .clone()
+            .unwrap_or_else(|| crate::document::document_stem(std::path::Path::new(&file.path)));
+        recent.append(&MenuItem::with_id(
+            app,
+            format!("recent:{}", index),
+            name,
+            true,
+            None::<&str>,
+        )?)?;
+    }
+
+    let sync_status = app
+        .state::<TrayState>()
+        .sync_status
+        .lock()
+        .map(|status| status.clone())
+        .unwrap_or_default();
+    let visible = app
+        .get_webview_window(crate::windows::MAIN_WINDOW)
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(true);
+
+    Menu::with_items(
+        app,
+        &[
+            &MenuItem::with_id(app, "quick-note", "New Quick Note", true, None::<&str>)?,
+            &recent,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, "sync-status", sync_status, false, None::<&str>)?,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(
+                app,
+                "toggle-window",
+                if visible {
+                    "Hide Window"
+                } else {
+                    "Show Window"
+                },
+                true,
+                None::<&str>,
+            )?,
+            &PredefinedMenuItem::quit(app, None)?,
+        ],
+    )
+}
+
+#[cfg(desktop)]
+fn handle_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
+    use tauri::Emitter;
+
+    let id = event.id().as_ref();
+    match id {
+        "quick-note" => {
+            show_main_window(app);
+            let _ = app.emit("tray:new-quick-note", ());
+        }
+        "toggle-window" => {
+            toggle_main_window(app);
+            let _ = refresh_tray(app.clone());
+        }
+        _ => {
+            let Some(index) = id
+                .strip_prefix("recent:")
+                .and_then(|index| index.parse::<usize>().ok())
+            else {
+                return;
+            };
+            if let Some(file) = crate::workspace::recent_files(app).get(index) {
+                show_main_window(app);
+                let _ = app.emit("tray:open-document", file.path.clone());
+            }
+        }
+    }
+}
+
+#[cfg(desktop)]
+pub fn show_main_window(app: &AppHandle) {
+    use tauri::Manager;
+
+    if let Some(window) = app.get_webview_window(crate::windows::MAIN_WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(desktop)]
+pub fn toggle_main_window(app: &AppHandle) {
+    use tauri::Manager;
+
+    let Some(window) = app.get_webview_window(crate::windows::MAIN_WINDOW) else {
+        return;
+    };
+    if window.is_visible().unwrap_or(false) {
+        let _ = window.hide();
+    } else {
+        show_main_window(app);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::error::Error;
+use crate::saves;
+use crate::settings::{self, UpdateChannel};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+const STABLE_ENDPOINT: &str =
+    "https://github.com/stormlightlabs/inkfinite/releases/latest/download/latest.json";
+/// Beta builds are attached to a rolling `beta` release
+const BETA_ENDPOINT: &str =
+    "https://github.com/stormlightlabs/inkfinite/releases/download/beta/latest.json";
+/// Minisign public key that update bundles are signed with, set by release builds; other builds
+/// do not update
+const PUBLIC_KEY: Option<&str> = option_env!("INKFINITE_UPDATER_PUBKEY");
+const FIRST_CHECK: Duration = Duration::from_secs(60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+const AVAILABLE_EVENT: &str = "updater:available";
+const PROGRESS_EVENT: &str = "updater:progress";
+/// The update is downloaded and verified; the UI can offer "restart to update"
+const READY_EVENT: &str = "updater:ready";
+/// A restart is waiting for windows with unsaved changes
+const DEFERRED_EVENT: &str = "updater:deferred";
+const FAILED_EVENT: &str = "updater:failed";
+
+/// Managed state tracking the update being downloaded or waiting to be installed
+#[derive(Default)]
+pub struct Updates(Mutex<State>);
+
+#[derive(Default)]
+struct State {
+    downloading: Option<String>,
+    ready: Option<(UpdateInfo, Update, Vec<u8>)>,
+    /// Windows that reported unsaved changes
+    unsaved: BTreeSet<String>,
+    restart_requested: bool,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    /// Release notes in Markdown
+    pub notes: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateStatus {
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    pub enabled: bool,
+    /// Version being downloaded
+    pub downloading: Option<String>,
+    /// Downloaded update waiting for a restart
+    pub ready: Option<UpdateInfo>,
+    pub restart_requested: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum RestartOutcome {
+    /// Nothing has been downloaded
+    NoUpdate,
+    /// Waiting for these windows to save; the restart happens once they have
+    Deferred { windows: Vec<String> },
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+    version: String,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Failure {
+    version: Option<String>,
+    error: String,
+}
+
+fn lock(app: &AppHandle) -> Result<std::sync::MutexGuard<'_, State>, String> {
+    app.state::<Updates>()
+        .inner()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to read update state: {}", e))
+}
+
+fn channel(app: &AppHandle) -> UpdateChannel {
+    settings::current(app, None).update_channel
+}
+
+/// Ask the channel's endpoint for a newer release
+async fn check(app: &AppHandle) -> Result<Option<(UpdateInfo, Update)>, String> {
+    let public_key =
+        PUBLIC_KEY.ok_or_else(|| "Updates are not supported in this build".to_string())?;
+    let channel = channel(app);
+    let endpoint = match channel {
+        UpdateChannel::Stable => STABLE_ENDPOINT,
+        UpdateChannel::Beta => BETA_ENDPOINT,
+    };
+    let endpoint = url::Url::parse(endpoint).map_err(|e| format!("Invalid endpoint: {}", e))?;
+    let updater = app
+        .updater_builder()
+        .pubkey(public_key)
+        .endpoints(vec![endpoint])
+        .and_then(|builder| builder.build())
+        .map_err(|e| format!("Failed to set up updates: {}", e))?;
+    let update = updater
+        .check()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+    Ok(update.map(|update| {
+        let info = UpdateInfo {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            channel,
+            notes: update.body.clone(),
+        };
+        (info, update)
+    }))
+}
+
+/// Download an update in the background. The plugin checks the bundle's signature against the
+/// public key before handing it over, so a tampered download is never kept.
+fn download(app: &AppHandle, info: UpdateInfo, update: Update) {
+    {
+        let Ok(mut state) = lock(app) else {
+            return;
+        };
+        let current = state.ready.as_ref().map(|(ready, ..)| &ready.version);
+        if state.downloading.as_ref() == Some(&info.version) || current == Some(&info.version) {
+            return;
+        }
+        state.downloading = Some(info.version.clone());
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut downloaded = 0u64;
+        let mut reported = Instant::now();
+        let result = update
+            .download(
+                |chunk, total| {
+                    downloaded += chunk as u64;
+                    if reported.elapsed() >= PROGRESS_INTERVAL {
+                        reported = Instant::now();
+                        let _ = app.emit(
+                            PROGRESS_EVENT,
+                            Progress {
+                                version: info.version.clone(),
+                                downloaded,
+                                total,
+                            },
+                        );
+                    }
+                },
+                || {},
+            )
+            .await;
+        let Ok(mut state) = lock(&app) else {
+            return;
+        };
+        state.downloading = None;
+        match result {
+            Ok(bytes) => {
+                tracing::info!(version = %info.version, "Update downloaded");
+                let _ = app.emit(READY_EVENT, info.clone());
+                state.ready = Some((info, update, bytes));
+            }
+            Err(error) => {
+                tracing::warn!(%error, "Failed to download update");
+                let _ = app.emit(
+                    FAILED_EVENT,
+                    Failure {
+                        version: Some(info.version),
+                        error: format!("Failed to download update: {}", error),
+                    },
+                );
+            }
+        }
+    });
+}
+
+/// Install the downloaded update and restart once pending saves are written, unless a window
+/// still has unsaved changes
+fn restart(app: &AppHandle, force: bool) -> Result<RestartOutcome, String> {
+    saves::flush_all(app);
+    let mut state = lock(app)?;
+    if state.ready.is_none() {
+        return Ok(RestartOutcome::NoUpdate);
+    }
+    let waiting: Vec<String> = state.unsaved.iter().cloned().collect();
+    if !waiting.is_empty() && !force {
+        state.restart_requested = true;
+        let _ = app.emit(DEFERRED_EVENT, waiting.clone());
+        return Ok(RestartOutcome::Deferred { windows: waiting });
+    }
+    let Some((info, update, bytes)) = state.ready.take() else {
+        return Ok(RestartOutcome::NoUpdate);
+    };
+    drop(state);
+    update
+        .install(&bytes)
+        .map_err(|e| format!("Failed to install update: {}", e))?;
+    tracing::info!(version = %info.version, "Update installed, restarting");
+    app.restart()
+}
+
+/// Forget a closed window's unsaved changes, going ahead with a deferred restart if it was the
+/// last one
+pub fn window_closed(app: &AppHandle, label: &str) {
+    let resume = lock(app).is_ok_and(|mut state| {
+        state.unsaved.remove(label) && state.restart_requested && state.unsaved.is_empty()
+    });
+    if resume {
+        if let Err(error) = restart(app, false) {
+            tracing::warn!(%error, "Failed to restart to update");
+        }
+    }
+}
+
+/// Check for updates in the background while `autoUpdate` is on, downloading any found
+pub fn start(app: AppHandle) {
+    if PUBLIC_KEY.is_none() {
+        return;
+    }
+    std::thread::spawn(move || {
+        std::thread::sleep(FIRST_CHECK);
+        loop {
+            if settings::current(&app, None).auto_update {
+                match tauri::async_runtime::block_on(check(&app)) {
+                    Ok(Some((info, update))) => {
+                        let _ = app.emit(AVAILABLE_EVENT, info.clone());
+                        download(&app, info, update);
+                    }
+                    Ok(None) => {}
+                    Err(error) => tracing::warn!(%error, "Background update check failed"),
+                }
+            }
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+/// Check the configured channel for a newer release and start downloading it in the
+/// background, reporting `updater:progress` and then `updater:ready`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn check_for_updates(app: AppHandle) -> Result<Option<UpdateInfo>, Error> {
+    let Some((info, update)) = check(&app).await? else {
+        return Ok(None);
+    };
+    let _ = app.emit(AVAILABLE_EVENT, info.clone());
+    download(&app, info.clone(), update);
+    Ok(Some(info))
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_update_status(app: AppHandle) -> Result<UpdateStatus, Error> {
+    let state = lock(&app)?;
+    Ok(UpdateStatus {
+        current_version: app.package_info().version.to_string(),
+        channel: channel(&app),
+        enabled: PUBLIC_KEY.is_some(),
+        downloading: state.downloading.clone(),
+        ready: state.ready.as_ref().map(|(info, ..)| info.clone()),
+        restart_requested: state.restart_requested,
+    })
+}
+
+/// Report whether a window has unsaved changes. A deferred restart goes ahead once none do.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn set_unsaved_changes(
+    app: AppHandle,
+    window: WebviewWindow,
+    unsaved: bool,
+) -> Result<(), Error> {
+    let mut state = lock(&app)?;
+    match unsaved {
+        true => state.unsaved.insert(window.label().to_string()),
+        false => state.unsaved.remove(window.label()),
+    };
+    let resume = state.restart_requested && state.unsaved.is_empty();
+    drop(state);
+    if resume {
+        restart(&app, false)?;
+    }
+    Ok(())
+}
+
+/// Install the downloaded update and restart. With unsaved changes the restart is deferred
+/// until every window reports it has saved, unless `force` is set.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn restart_to_update(app: AppHandle, force: Option<bool>) -> Result<RestartOutcome, Error> {
+    Ok(restart(&app, force.unwrap_or(false))?)
+}
@@ -0,0 +1,113 @@
+use crate::crypto::SecretKey;
+use crate::error::Error;
+use crate::{paths, workspace};
+use inkfinite_core::vault::{insert_key, passphrase_key};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+pub use inkfinite_core::vault::{
+    any_unlocked, is_locked, key, lock_all, rename, unlocked_documents, unseal, vault_of, MARKER,
+};
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultInfo {
+    pub path: String,
+    pub locked: bool,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct VaultEvent {
+    path: String,
+}
+
+/// Unlock the vault at `path` with its key, emitting `vault:unlocked`
+pub fn unlock_with_key(app: &AppHandle, path: String, key: SecretKey) -> Result<(), String> {
+    insert_key(Path::new(&path), key)?;
+    let _ = app.emit("vault:unlocked", VaultEvent { path });
+    Ok(())
+}
+
+/// Make `path` a vault, encrypting the documents already in it. The vault starts unlocked.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn create_vault(app: AppHandle, path: String, passphrase: String) -> Result<VaultInfo, Error> {
+    paths::check(&app, &path, paths::Scope::Write)?;
+    if passphrase.is_empty() {
+        return Err("Invalid passphrase: empty".into());
+    }
+    let dir = Path::new(&path);
+    if let Some(existing) = vault_of(dir) {
+        return Err(
+            Error::conflict("Folder is already in a vault").with_context(existing.display())
+        );
+    }
+    inkfinite_core::vault::create(dir, &passphrase)?;
+    let _ = app.emit("vault:unlocked", VaultEvent { path: path.clone() });
+    Ok(VaultInfo {
+        path,
+        locked: false,
+    })
+}
+
+/// Vaults in the open workspace and whether each is locked
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_vaults(app: AppHandle) -> Result<Vec<VaultInfo>, Error> {
+    let root = workspace::current_root(&app).ok_or("No workspace is open")?;
+    Ok(vaults(&root)
+        .into_iter()
+        .map(|dir| VaultInfo {
+            locked: key(&dir).is_none(),
+            path: dir.to_string_lossy().to_string(),
+        })
+        .collect())
+}
+
+/// Vault folders under `root`, sorted by path
+pub fn vaults(root: &Path) -> Vec<PathBuf> {
+    // Markers are hidden files, so only hidden folders are skipped
+    let walk = jwalk::WalkDir::new(root)
+        .skip_hidden(false)
+        .process_read_dir(|_, _, _, children| {
+            children.retain(|child| {
+                child.as_ref().is_ok_and(|child| {
+                    !child.file_type().is_dir()
+                        || !child.file_name().to_string_lossy().starts_with('.')
+                })
+            });
+        });
+    let mut vaults: Vec<PathBuf> = walk
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy() == MARKER)
+        .map(|entry| entry.parent_path().to_path_buf())
+        .collect();
+    vaults.sort();
+    vaults
+}
+
+/// Whether any vault under `root` is locked
+pub fn any_locked(root: &Path) -> bool {
+    vaults(root).iter().any(|vault| key(vault).is_none())
+}
+
+/// Unlock a vault so its documents can be listed, opened, saved and searched
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn unlock_vault(app: AppHandle, path: String, passphrase: String) -> Result<(), Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    let key = passphrase_key(Path::new(&path), &passphrase)?;
+    Ok(unlock_with_key(&app, path, key)?)
+}
+
+/// Drop a vault's key from memory, hiding its documents again
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn lock_vault(app: AppHandle, path: String) -> Result<(), Error> {
+    paths::check(&app, &path, paths::Scope::Read)?;
+    inkfinite_core::vault::remove_key(Path::new(&path))?;
+    let _ = app.emit("vault:locked", VaultEvent { path });
+    Ok(())
+}
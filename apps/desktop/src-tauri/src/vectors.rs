@@ -0,0 +1,430 @@
+use crate::cancel::{self, CancelToken};
+use crate::document;
+use crate::embeddings::{self, Purpose};
+use crate::error::Error;
+use crate::{paths, vault, workspace};
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Emitter, Manager};
+
+const INDEX_DIR: &str = "embeddings";
+/// Bumped when the tables or the chunking change; older indexes are dropped and rebuilt
+const SCHEMA_VERSION: i64 = 1;
+const SCHEMA: &str = "
+    CREATE TABLE files (
+        path TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        modified INTEGER NOT NULL,
+        model TEXT NOT NULL
+    );
+    CREATE TABLE chunks (
+        path TEXT NOT NULL REFERENCES files(path) ON DELETE CASCADE,
+        ordinal INTEGER NOT NULL,
+        heading TEXT,
+        text TEXT NOT NULL,
+        vector BLOB NOT NULL,
+        PRIMARY KEY (path, ordinal)
+    );
+";
+/// Paragraphs are grouped into chunks of about this many characters
+const CHUNK_CHARS: usize = 1200;
+/// Chunks embedded per model call
+const EMBED_BATCH: usize = 16;
+const PROGRESS_EVENT: &str = "embeddings:index-progress";
+
+/// Managed state so only one pass updates an index at a time
+#[derive(Default)]
+pub struct VectorIndex {
+    syncing: Mutex<()>,
+}
+
+/// A passage of a document and how close it is to a query
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Hit {
+    /// Workspace-relative path
+    pub path: String,
+    pub name: String,
+    /// Heading the passage sits under
+    pub heading: Option<String>,
+    pub text: String,
+    /// Cosine similarity, up to 1
+    pub score: f32,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStatus {
+    pub model: Option<String>,
+    pub documents: u64,
+    pub chunks: u64,
+    /// Documents re-embedded by this call
+    pub updated: usize,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+    op_id: Option<String>,
+    done: usize,
+    total: usize,
+}
+
+/// Per-workspace index under the app cache dir, named by a hash of the workspace path
+fn database_path(app: &AppHandle, root: &Path) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))?
+        .join(INDEX_DIR);
+    let digest = format!("{:x}", Sha256::digest(root.to_string_lossy().as_bytes()));
+    Ok(dir.join(format!("{}.sqlite", &digest[..16])))
+}
+
+fn connect(app: &AppHandle, root: &Path) -> Result<Connection, String> {
+    let path = database_path(app, root)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    }
+    let failed = |e: rusqlite::Error| format!("Failed to open embeddings index: {}", e);
+    let connection = Connection::open(&path).map_err(failed)?;
+    connection
+        .execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+        .map_err(failed)?;
+    let version: i64 = connection
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(failed)?;
+    if version != SCHEMA_VERSION {
+        connection
+            .execute_batch(&format!(
+                "DROP TABLE IF EXISTS chunks; DROP TABLE IF EXISTS files; {} \
+                 PRAGMA user_version = {};",
+                SCHEMA, SCHEMA_VERSION
+            ))
+            .map_err(failed)?;
+    }
+    Ok(connection)
+}
+
+fn modified(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64)
+}
+
+/// `# Heading` lines; `#tag` words are not headings
+fn heading(paragraph: &str) -> Option<String> {
+    let line = paragraph.lines().next()?.trim();
+    let title = line.trim_start_matches('#');
+    (line.starts_with('#') && title.starts_with(' ')).then(|| title.trim().to_string())
+}
+
+/// Split Markdown into passages of whole paragraphs, each with the heading it sits under
+fn chunks(markdown: &str) -> Vec<(Option<String>, String)> {
+    let mut chunks = Vec::new();
+    let mut current_heading = None;
+    let mut current = String::new();
+    let mut flush = |heading: &Option<String>, current: &mut String| {
+        if !current.trim().is_empty() {
+            chunks.push((heading.clone(), current.trim().to_string()));
+        }
+        current.clear();
+    };
+    for paragraph in markdown.split("\n\n").map(str::trim) {
+        if paragraph.is_empty() {
+            continue;
+        }
+        if let Some(title) = heading(paragraph) {
+            flush(&current_heading, &mut current);
+            current_heading = Some(title);
+        }
+        if !current.is_empty() && current.len() + paragraph.len() > CHUNK_CHARS {
+            flush(&current_heading, &mut current);
+        }
+        // A paragraph longer than a chunk is cut at character boundaries
+        let mut rest = paragraph;
+        while rest.len() > CHUNK_CHARS {
+            let end = rest
+                .char_indices()
+                .map(|(index, _)| index)
+                .take_while(|index| *index <= CHUNK_CHARS)
+                .last()
+                .unwrap_or(rest.len());
+            current.push_str(&rest[..end]);
+            flush(&current_heading, &mut current);
+            rest = &rest[end..];
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(rest);
+    }
+    flush(&current_heading, &mut current);
+    chunks
+}
+
+fn encode(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Embed one document and replace its rows
+fn index_document(
+    app: &AppHandle,
+    db: &mut Connection,
+    model: &str,
+    relative: &str,
+    path: &Path,
+    stamp: i64,
+) -> Result<(), String> {
+    let board = document::read_board(path)?;
+    let passages = chunks(&board.to_markdown());
+    let mut vectors = Vec::with_capacity(passages.len());
+    for batch in passages.chunks(EMBED_BATCH) {
+        let texts: Vec<String> = batch
+            .iter()
+            .map(|(heading, text)| match heading {
+                Some(heading) => format!("{}\n{}", heading, text),
+                None => text.clone(),
+            })
+            .collect();
+        vectors.extend(embeddings::embed(app, model, &texts, Purpose::Document)?);
+    }
+    let failed = |e: rusqlite::Error| format!("Failed to update embeddings index: {}", e);
+    let tx = db.transaction().map_err(failed)?;
+    tx.execute("DELETE FROM files WHERE path = ?1", [relative])
+        .map_err(failed)?;
+    tx.execute(
+        "INSERT INTO files (path, name, modified, model) VALUES (?1, ?2, ?3, ?4)",
+        params![relative, board.board.name, stamp, model],
+    )
+    .map_err(failed)?;
+    for (ordinal, ((heading, text), vector)) in passages.iter().zip(&vectors).enumerate() {
+        tx.execute(
+            "INSERT INTO chunks (path, ordinal, heading, text, vector) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![relative, ordinal as i64, heading, text, encode(vector)],
+        )
+        .map_err(failed)?;
+    }
+    tx.commit().map_err(failed)
+}
+
+/// Bring the workspace's index in line with the disk and the active model, embedding only
+/// documents that changed. Vault documents are never indexed. Returns how many were embedded.
+pub fn sync(
+    app: &AppHandle,
+    root: &Path,
+    op_id: &Option<String>,
+    cancel: &CancelToken,
+) -> Result<usize, String> {
+    let model = embeddings::active_model(app)?;
+    let state = app.state::<VectorIndex>();
+    let _syncing = state
+        .syncing
+        .lock()
+        .map_err(|e| format!("Failed to update embeddings index: {}", e))?;
+    let mut db = connect(app, root)?;
+    let failed = |e: rusqlite::Error| format!("Failed to read embeddings index: {}", e);
+
+    let on_disk: HashMap<String, (PathBuf, i64)> = workspace::list_documents(root)?
+        .into_iter()
+        .filter(|path| vault::vault_of(path).is_none())
+        .filter_map(|path| {
+            let stamp = modified(&path)?;
+            Some((workspace::relative_path(root, &path), (path, stamp)))
+        })
+        .collect();
+    let indexed: HashMap<String, (i64, String)> = {
+        let mut statement = db
+            .prepare("SELECT path, modified, model FROM files")
+            .map_err(failed)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(failed)?;
+        rows.into_iter().collect()
+    };
+    for path in indexed.keys().filter(|path| !on_disk.contains_key(*path)) {
+        db.execute("DELETE FROM files WHERE path = ?1", [path])
+            .map_err(failed)?;
+    }
+
+    let mut stale: Vec<(&String, &(PathBuf, i64))> = on_disk
+        .iter()
+        .filter(|(relative, (_, stamp))| {
+            indexed
+                .get(*relative)
+                .is_none_or(|(indexed, indexed_model)| indexed != stamp || *indexed_model != model)
+        })
+        .collect();
+    stale.sort_by(|a, b| a.0.cmp(b.0));
+    let total = stale.len();
+    for (done, (relative, (path, stamp))) in stale.into_iter().enumerate() {
+        if cancel.is_cancelled() {
+            return Err(cancel::CANCELLED.to_string());
+        }
+        if let Err(error) = index_document(app, &mut db, &model, relative, path, *stamp) {
+            tracing::warn!(path = %relative, %error, "Skipping document in embeddings index");
+        }
+        let _ = app.emit(
+            PROGRESS_EVENT,
+            Progress {
+                op_id: op_id.clone(),
+                done: done + 1,
+                total,
+            },
+        );
+    }
+    Ok(total)
+}
+
+/// The `limit` passages closest to `query` among documents `include` accepts, best first
+pub fn search(
+    app: &AppHandle,
+    root: &Path,
+    query: &str,
+    limit: usize,
+    include: impl Fn(&str) -> bool,
+) -> Result<Vec<Hit>, String> {
+    let model = embeddings::active_model(app)?;
+    let query = embeddings::embed(app, &model, &[query.to_string()], Purpose::Query)?
+        .pop()
+        .unwrap_or_default();
+    let db = connect(app, root)?;
+    let failed = |e: rusqlite::Error| format!("Failed to read embeddings index: {}", e);
+    let mut statement = db
+        .prepare(
+            "SELECT c.path, f.name, c.heading, c.text, c.vector FROM chunks c
+             JOIN files f ON f.path = c.path WHERE f.model = ?1",
+        )
+        .map_err(failed)?;
+    let mut hits: Vec<Hit> = statement
+        .query_map([&model], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Vec<u8>>(4)?,
+            ))
+        })
+        .map_err(failed)?
+        .filter_map(Result::ok)
+        .filter(|(path, ..)| include(path))
+        .map(|(path, name, heading, text, vector)| Hit {
+            score: dot(&query, &decode(&vector)),
+            path,
+            name,
+            heading,
+            text,
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+/// Changes whenever documents are embedded, dropped or embedded with another model
+pub fn revision(app: &AppHandle, root: &Path) -> Result<String, String> {
+    let model = embeddings::active_model(app)?;
+    let db = connect(app, root)?;
+    let (documents, stamps): (u64, i64) = db
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(modified), 0) FROM files WHERE model = ?1",
+            [&model],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to read embeddings index: {}", e))?;
+    Ok(format!("{}:{}:{}", model, documents, stamps))
+}
+
+/// The normalized mean of each indexed document's passages, by workspace-relative path
+pub fn document_vectors(app: &AppHandle, root: &Path) -> Result<HashMap<String, Vec<f32>>, String> {
+    let model = embeddings::active_model(app)?;
+    let db = connect(app, root)?;
+    let failed = |e: rusqlite::Error| format!("Failed to read embeddings index: {}", e);
+    let mut statement = db
+        .prepare(
+            "SELECT c.path, c.vector FROM chunks c
+             JOIN files f ON f.path = c.path WHERE f.model = ?1",
+        )
+        .map_err(failed)?;
+    let mut sums: HashMap<String, Vec<f32>> = HashMap::new();
+    let rows = statement
+        .query_map([&model], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })
+        .map_err(failed)?;
+    for (path, vector) in rows.flatten() {
+        let vector = decode(&vector);
+        let sum = sums.entry(path).or_insert_with(|| vec![0.0; vector.len()]);
+        sum.iter_mut().zip(&vector).for_each(|(sum, x)| *sum += x);
+    }
+    for sum in sums.values_mut() {
+        let norm = dot(sum, sum).sqrt();
+        if norm > 0.0 {
+            sum.iter_mut().for_each(|x| *x /= norm);
+        }
+    }
+    Ok(sums)
+}
+
+fn status(app: &AppHandle, root: &Path, updated: usize) -> Result<IndexStatus, String> {
+    let db = connect(app, root)?;
+    let (documents, chunks) = db
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM files), (SELECT COUNT(*) FROM chunks)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to read embeddings index: {}", e))?;
+    Ok(IndexStatus {
+        model: embeddings::active_model(app).ok(),
+        documents,
+        chunks,
+        updated,
+    })
+}
+
+/// Embed the workspace's changed documents with the active model, reporting
+/// `embeddings:index-progress`
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn update_embeddings_index(
+    app: AppHandle,
+    workspace: String,
+    op_id: Option<String>,
+) -> Result<IndexStatus, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = cancel::begin(&app, op_id.clone());
+        let updated = sync(&app, &root, &op_id, operation.token())?;
+        Ok(status(&app, &root, updated)?)
+    })
+    .await
+    .map_err(|e| format!("Indexing task failed: {}", e))?
+}
+
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn get_embeddings_index_status(
+    app: AppHandle,
+    workspace: String,
+) -> Result<IndexStatus, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(status(&app, &root, 0)?)
+}
@@ -0,0 +1,157 @@
+use crate::error::Error;
+use crate::{paths, tools};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+use tauri::AppHandle;
+
+const FFMPEG_MISSING: &str = "Video previews require ffmpeg (ffprobe) to be installed";
+/// File extensions previewed as video
+const EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "webm", "mkv", "avi", "ogv", "3gp"];
+/// Poster frames are taken this far in, so fades from black are skipped
+const POSTER_OFFSET: f64 = 0.1;
+/// Latest point a poster frame is taken from, in seconds
+const MAX_POSTER_SECONDS: f64 = 5.0;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoInfo {
+    /// Seconds
+    pub duration: Option<f64>,
+    /// Display size, with any rotation applied
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub frame_rate: Option<f64>,
+    /// Bits per second
+    pub bit_rate: Option<u64>,
+    pub container: Option<String>,
+}
+
+/// Whether `path` has a video file extension
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// Duration, dimensions and codecs of a video asset, read with ffprobe
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn video_info(app: AppHandle, asset_path: String) -> Result<VideoInfo, Error> {
+    paths::check(&app, &asset_path, paths::Scope::Read)?;
+    let source = Path::new(&asset_path);
+    if !source.is_file() {
+        return Err(Error::not_found("File does not exist").with_context(&asset_path));
+    }
+    Ok(probe(&app, source)?)
+}
+
+fn probe(app: &AppHandle, source: &Path) -> Result<VideoInfo, String> {
+    let ffprobe = tools::find_binary(app, "ffprobe", "-version").ok_or(FFMPEG_MISSING)?;
+    let output = Command::new(ffprobe)
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ])
+        .arg(source)
+        .output()
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to read video: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let probe: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Invalid ffprobe output: {}", e))?;
+
+    let streams = probe["streams"].as_array().cloned().unwrap_or_default();
+    let stream = |kind: &str| {
+        streams
+            .iter()
+            .find(|stream| stream["codec_type"] == kind)
+            .cloned()
+    };
+    let video = stream("video").ok_or_else(|| format!("Not a video file: {}", source.display()))?;
+    let audio = stream("audio");
+    let format = &probe["format"];
+    // ffprobe reports numbers as strings
+    let number = |value: &Value| value.as_str().and_then(|s| s.parse::<f64>().ok());
+
+    let (mut width, mut height) = (
+        video["width"].as_u64().map(|w| w as u32),
+        video["height"].as_u64().map(|h| h as u32),
+    );
+    if rotation(&video).is_some_and(|degrees| degrees.abs() % 180 == 90) {
+        std::mem::swap(&mut width, &mut height);
+    }
+
+    Ok(VideoInfo {
+        duration: number(&format["duration"]).or_else(|| number(&video["duration"])),
+        width,
+        height,
+        video_codec: video["codec_name"].as_str().map(str::to_string),
+        audio_codec: audio.and_then(|a| a["codec_name"].as_str().map(str::to_string)),
+        frame_rate: video["avg_frame_rate"]
+            .as_str()
+            .and_then(frame_rate)
+            .or_else(|| video["r_frame_rate"].as_str().and_then(frame_rate)),
+        bit_rate: number(&format["bit_rate"]).map(|rate| rate as u64),
+        container: format["format_name"].as_str().map(str::to_string),
+    })
+}
+
+/// Rotation from the stream's display matrix or its older `rotate` tag
+fn rotation(video: &Value) -> Option<i64> {
+    let side_data = video["side_data_list"]
+        .as_array()
+        .and_then(|list| list.iter().find_map(|data| data["rotation"].as_i64()));
+    side_data.or_else(|| video["tags"]["rotate"].as_str()?.parse().ok())
+}
+
+/// `30000/1001` as frames per second; `0/0` for streams without a fixed rate
+fn frame_rate(rate: &str) -> Option<f64> {
+    let (frames, seconds) = rate.split_once('/')?;
+    let (frames, seconds) = (frames.parse::<f64>().ok()?, seconds.parse::<f64>().ok()?);
+    (frames > 0.0 && seconds > 0.0).then(|| frames / seconds)
+}
+
+/// Extract a poster frame with ffmpeg into `target` as PNG, scaled to fit within `size`
+pub fn poster_frame(
+    app: &AppHandle,
+    source: &Path,
+    target: &Path,
+    size: u32,
+) -> Result<(u32, u32), String> {
+    let ffmpeg = tools::find_binary(app, "ffmpeg", "-version").ok_or(FFMPEG_MISSING)?;
+    let at = probe(app, source)
+        .ok()
+        .and_then(|info| info.duration)
+        .map(|duration| (duration * POSTER_OFFSET).min(MAX_POSTER_SECONDS))
+        .unwrap_or(0.0);
+
+    let status = Command::new(&ffmpeg)
+        .args(["-y", "-loglevel", "error", "-ss"])
+        .arg(format!("{:.3}", at))
+        .arg("-i")
+        .arg(source)
+        .args(["-frames:v", "1", "-vf"])
+        .arg(format!(
+            "scale={0}:{0}:force_original_aspect_ratio=decrease",
+            size
+        ))
+        .arg(target)
+        .status()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !status.success() {
+        return Err("Failed to extract a video frame".to_string());
+    }
+    image::image_dimensions(target).map_err(|e| format!("Failed to read thumbnail: {}", e))
+}
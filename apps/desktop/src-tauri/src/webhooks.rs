@@ -0,0 +1,542 @@
+use crate::document::{self, create_id, now_millis, DOCUMENT_EXTENSION};
+use crate::error::Error;
+use crate::events::ChangeKind;
+use crate::{catalog, http, paths, read_only, secrets, vault, workspace};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use url::Url;
+
+const CONFIG: &str = "webhooks";
+/// `.inkfinite/logs/webhooks.jsonl`, one line per delivery attempt
+const LOG_FILE: &str = "webhooks.jsonl";
+const DEFAULT_LIMIT: usize = 200;
+const DEFAULT_CONTENT_TYPE: &str = "application/json";
+/// Changes to the same document closer together than this are delivered once
+const SETTLE: Duration = Duration::from_secs(10);
+/// Wait before each retry; a delivery is given up after the last one fails
+const RETRY_DELAYS: [Duration; 4] = [
+    Duration::from_secs(30),
+    Duration::from_secs(120),
+    Duration::from_secs(600),
+    Duration::from_secs(3600),
+];
+
+/// Which documents a rule applies to; every document when empty
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebhookFilter {
+    /// Only documents carrying this `#tag`. Deletions never match, as the content is gone.
+    pub tag: Option<String>,
+    /// Only documents under this workspace-relative folder
+    pub folder: Option<String>,
+}
+
+/// A rule in `.inkfinite/webhooks.json`: when `events` happen to a document matching `filter`,
+/// `POST` to `url`.
+///
+/// `template` is the request body, with `{{event}}`, `{{path}}`, `{{from}}`, `{{name}}`,
+/// `{{markdown}}`, `{{tags}}` and `{{at}}` replaced; values are JSON-escaped when the content type
+/// is JSON. Without a template the body is a JSON description of the event.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookRule {
+    /// Assigned when the rule is first saved
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// Every event when empty
+    #[serde(default)]
+    pub events: Vec<ChangeKind>,
+    #[serde(default)]
+    pub filter: WebhookFilter,
+    pub url: String,
+    pub template: Option<String>,
+    /// `application/json` when omitted
+    pub content_type: Option<String>,
+    /// Secret (see [`crate::secrets`]) whose value signs each body with HMAC-SHA256, sent as
+    /// `X-Inkfinite-Signature: sha256=<hex>`
+    pub signing_secret: Option<String>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Rules {
+    rules: Vec<WebhookRule>,
+}
+
+/// One attempt to deliver an event, as kept in the delivery log
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    /// Unix milliseconds
+    pub at: i64,
+    pub rule: String,
+    pub event: ChangeKind,
+    /// Workspace-relative path of the document
+    pub path: String,
+    /// Starts at 1
+    pub attempt: u32,
+    /// HTTP status of the reply, when there was one
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    /// Whether another attempt is scheduled
+    pub retrying: bool,
+}
+
+struct Pending {
+    root: PathBuf,
+    rule: WebhookRule,
+    event: ChangeKind,
+    path: PathBuf,
+    from: Option<PathBuf>,
+    due: Instant,
+    attempt: u32,
+    /// Rendered on the first attempt and reused by retries
+    body: Option<String>,
+}
+
+/// Managed state holding deliveries waiting to be sent or retried
+#[derive(Default)]
+pub struct WebhookQueue {
+    pending: Mutex<Vec<Pending>>,
+    wake: Condvar,
+}
+
+/// Queue deliveries for the rules of the open workspace matching a change to `path`
+pub fn file_changed(app: &AppHandle, path: &Path, kind: ChangeKind, from: Option<&Path>) {
+    let Some(root) = workspace::current_root(app) else {
+        return;
+    };
+    if !path.starts_with(&root)
+        || !path.to_string_lossy().ends_with(DOCUMENT_EXTENSION)
+        || vault::vault_of(path).is_some()
+    {
+        return;
+    }
+    let rules = match workspace::read_config::<Rules>(&root, CONFIG) {
+        Ok(rules) => rules.rules,
+        Err(error) => {
+            tracing::warn!(%error, "Failed to read webhook rules");
+            return;
+        }
+    };
+    let relative = workspace::relative_path(&root, path);
+
+    let queue = app.state::<WebhookQueue>();
+    let Ok(mut pending) = queue.pending.lock() else {
+        return;
+    };
+    let due = Instant::now() + SETTLE;
+    for rule in rules
+        .into_iter()
+        .filter(|rule| matches(rule, kind, &relative))
+    {
+        let waiting = pending.iter_mut().find(|waiting| {
+            waiting.attempt == 0
+                && waiting.rule.id == rule.id
+                && waiting.event == kind
+                && waiting.path == path
+        });
+        match waiting {
+            Some(waiting) => {
+                waiting.rule = rule;
+                waiting.due = due;
+            }
+            None => pending.push(Pending {
+                root: root.clone(),
+                rule,
+                event: kind,
+                path: path.to_path_buf(),
+                from: from.map(Path::to_path_buf),
+                due,
+                attempt: 0,
+                body: None,
+            }),
+        }
+    }
+    queue.wake.notify_all();
+}
+
+/// Event and folder checks; the tag filter needs the content and is checked on delivery
+fn matches(rule: &WebhookRule, kind: ChangeKind, relative: &str) -> bool {
+    let folder = rule
+        .filter
+        .folder
+        .as_deref()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty());
+    rule.enabled
+        && (rule.events.is_empty() || rule.events.contains(&kind))
+        && !(rule.filter.tag.is_some() && kind == ChangeKind::Deleted)
+        && folder.is_none_or(|folder| {
+            relative
+                .strip_prefix(folder)
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+}
+
+/// Send deliveries as they come due, rescheduling failures that may succeed later
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let queue = app.state::<WebhookQueue>();
+        let Ok(mut pending) = queue.pending.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        let next = pending.iter().map(|delivery| delivery.due).min();
+        let wait = next.map_or(Duration::from_secs(3600), |due| {
+            due.saturating_duration_since(now)
+        });
+        if !wait.is_zero() {
+            let _ = queue.wake.wait_timeout(pending, wait);
+            continue;
+        }
+        let due: Vec<Pending> = {
+            let (due, waiting) = pending.drain(..).partition(|delivery| delivery.due <= now);
+            *pending = waiting;
+            due
+        };
+        drop(pending);
+
+        for delivery in due {
+            if let Some(retry) = attempt(delivery) {
+                if let Ok(mut pending) = queue.pending.lock() {
+                    pending.push(retry);
+                }
+            }
+        }
+    });
+}
+
+/// Try one delivery and log it, returning it rescheduled when it should be retried
+fn attempt(mut delivery: Pending) -> Option<Pending> {
+    let body = match delivery.body.take() {
+        Some(body) => body,
+        None => match render(&delivery) {
+            Ok(Some(body)) => body,
+            // No longer tagged as the rule requires
+            Ok(None) => return None,
+            Err(error) => {
+                log(&delivery, None, Some(error), false);
+                return None;
+            }
+        },
+    };
+    delivery.attempt += 1;
+
+    let (status, error, retry) = match send(&delivery.root, &delivery.rule, delivery.event, &body) {
+        Ok(status) => (Some(status), None, false),
+        Err((status, error)) => {
+            // Client errors other than timeouts and rate limits will fail the same way again
+            let permanent = status.is_some_and(|status| {
+                (400..500).contains(&status) && status != 408 && status != 429
+            });
+            (status, Some(error), !permanent)
+        }
+    };
+    let delay = RETRY_DELAYS
+        .get(delivery.attempt as usize - 1)
+        .filter(|_| retry);
+    log(&delivery, status, error, delay.is_some());
+    let delay = delay?;
+    delivery.due = Instant::now() + *delay;
+    delivery.body = Some(body);
+    Some(delivery)
+}
+
+/// Request body for a delivery, or `None` when the document lacks the rule's tag
+fn render(delivery: &Pending) -> Result<Option<String>, String> {
+    let (name, markdown) = if delivery.event == ChangeKind::Deleted {
+        (document::document_stem(&delivery.path), String::new())
+    } else {
+        let board = document::read_board(&delivery.path)?;
+        (board.board.name.clone(), board.to_markdown())
+    };
+    let tags: Vec<String> = catalog::tags(&markdown).into_iter().collect();
+    if let Some(tag) = &delivery.rule.filter.tag {
+        if !tags.contains(&tag.trim_start_matches('#').to_lowercase()) {
+            return Ok(None);
+        }
+    }
+
+    let relative = |path: &Path| workspace::relative_path(&delivery.root, path);
+    let event = delivery.event.as_str().to_string();
+    let Some(template) = &delivery.rule.template else {
+        return Ok(Some(
+            serde_json::json!({
+                "event": event,
+                "path": relative(&delivery.path),
+                "from": delivery.from.as_deref().map(relative),
+                "name": name,
+                "tags": tags,
+                "markdown": markdown,
+                "at": now_millis(),
+            })
+            .to_string(),
+        ));
+    };
+
+    let json = content_type(&delivery.rule).contains("json");
+    let value = |key: &str| -> Option<String> {
+        let value = match key {
+            "event" => event.clone(),
+            "path" => relative(&delivery.path),
+            "from" => delivery.from.as_deref().map(relative).unwrap_or_default(),
+            "name" => name.clone(),
+            "markdown" => markdown.clone(),
+            "tags" => tags.join(", "),
+            "at" => chrono::Local::now().to_rfc3339(),
+            _ => return None,
+        };
+        if !json {
+            return Some(value);
+        }
+        let quoted = serde_json::Value::String(value).to_string();
+        Some(quoted[1..quoted.len() - 1].to_string())
+    };
+
+    // Unknown placeholders are left as they are
+    let mut body = String::with_capacity(template.len() + markdown.len());
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find("{{") {
+        body.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after
+            .find("}}")
+            .and_then(|end| Some((value(after[..end].trim())?, end)))
+        {
+            Some((value, end)) => {
+                body.push_str(&value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                body.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    body.push_str(rest);
+    Ok(Some(body))
+}
+
+fn content_type(rule: &WebhookRule) -> &str {
+    rule.content_type
+        .as_deref()
+        .filter(|content_type| !content_type.trim().is_empty())
+        .unwrap_or(DEFAULT_CONTENT_TYPE)
+}
+
+/// `POST` a body to the rule's URL, returning the reply status, or the status (when there was
+/// a reply) and the error
+fn send(
+    root: &Path,
+    rule: &WebhookRule,
+    event: ChangeKind,
+    body: &str,
+) -> Result<u16, (Option<u16>, String)> {
+    let mut request = http::agent()
+        .post(&rule.url)
+        .header("content-type", content_type(rule))
+        .header("x-inkfinite-event", event.as_str());
+    if let Some(name) = &rule.signing_secret {
+        let key = secrets::get(root, name)
+            .map_err(|e| (None, e))?
+            .ok_or_else(|| (None, format!("Secret not found: {}", name)))?;
+        let signature = hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes()),
+            body.as_bytes(),
+        );
+        let hex: String = signature
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        request = request.header("x-inkfinite-signature", format!("sha256={}", hex));
+    }
+    match request.send(body) {
+        Ok(response) => Ok(response.status().as_u16()),
+        Err(ureq::Error::StatusCode(status)) => Err((
+            Some(status),
+            format!("Webhook replied with HTTP {}", status),
+        )),
+        Err(e) => Err((None, format!("Failed to deliver webhook: {}", e))),
+    }
+}
+
+fn log(delivery: &Pending, status: Option<u16>, error: Option<String>, retrying: bool) {
+    if let Some(error) = &error {
+        tracing::warn!(%error, attempt = delivery.attempt, "Webhook delivery failed");
+    }
+    let entry = WebhookDelivery {
+        at: now_millis(),
+        rule: delivery.rule.id.clone(),
+        event: delivery.event,
+        path: workspace::relative_path(&delivery.root, &delivery.path),
+        attempt: delivery.attempt,
+        status,
+        error,
+        retrying,
+    };
+    if let Err(error) = append(&delivery.root, &entry) {
+        tracing::warn!(%error, "Failed to write webhook log");
+    }
+}
+
+fn append(root: &Path, entry: &WebhookDelivery) -> Result<(), String> {
+    read_only::ensure_writable(root)?;
+    let dir = workspace::internal_dir(root).join("logs");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log folder: {}", e))?;
+    let line =
+        serde_json::to_string(entry).map_err(|e| format!("Failed to serialize entry: {}", e))?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(LOG_FILE))
+        .map_err(|e| format!("Failed to open webhook log: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write webhook log: {}", e))
+}
+
+/// Webhook rules of a workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_webhooks(app: AppHandle, workspace: String) -> Result<Vec<WebhookRule>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    Ok(workspace::read_config::<Rules>(&root, CONFIG)?.rules)
+}
+
+/// Add a rule, or replace the one with the same id; returns the rule with its id assigned
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn save_webhook(
+    app: AppHandle,
+    workspace: String,
+    mut rule: WebhookRule,
+) -> Result<WebhookRule, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let url = Url::parse(&rule.url).map_err(|e| format!("Invalid webhook URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Invalid webhook URL: {}", rule.url).into());
+    }
+    if rule.name.trim().is_empty() {
+        return Err("Invalid webhook: a name is required".into());
+    }
+    let mut rules: Rules = workspace::read_config(&root, CONFIG)?;
+    if rule.id.is_empty() {
+        rule.id = create_id("webhook");
+    }
+    match rules
+        .rules
+        .iter_mut()
+        .find(|existing| existing.id == rule.id)
+    {
+        Some(existing) => *existing = rule.clone(),
+        None => rules.rules.push(rule.clone()),
+    }
+    workspace::write_config(&root, CONFIG, &rules)?;
+    Ok(rule)
+}
+
+/// Delete a rule; deliveries already queued for it are still sent. Returns `false` when there
+/// was no such rule.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn remove_webhook(app: AppHandle, workspace: String, id: String) -> Result<bool, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Write)?;
+    let mut rules: Rules = workspace::read_config(&root, CONFIG)?;
+    let before = rules.rules.len();
+    rules.rules.retain(|rule| rule.id != id);
+    if rules.rules.len() == before {
+        return Ok(false);
+    }
+    workspace::write_config(&root, CONFIG, &rules)?;
+    Ok(true)
+}
+
+/// Deliver a rule once now for the document at `path`, as if it had been saved, ignoring the
+/// rule's filters. The attempt is logged but never retried.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn test_webhook(
+    app: AppHandle,
+    workspace: String,
+    id: String,
+    path: String,
+) -> Result<WebhookDelivery, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let path = paths::check(&app, &path, paths::Scope::Read)?;
+    let rules: Rules = workspace::read_config(&root, CONFIG)?;
+    let mut rule = rules
+        .rules
+        .into_iter()
+        .find(|rule| rule.id == id)
+        .ok_or_else(|| format!("Webhook not found: {}", id))?;
+    rule.filter = WebhookFilter::default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let delivery = Pending {
+            root,
+            rule,
+            event: ChangeKind::Modified,
+            path,
+            from: None,
+            due: Instant::now(),
+            attempt: 1,
+            body: None,
+        };
+        let body = render(&delivery)?.unwrap_or_default();
+        let (status, error) = match send(&delivery.root, &delivery.rule, delivery.event, &body) {
+            Ok(status) => (Some(status), None),
+            Err((status, error)) => (status, Some(error)),
+        };
+        log(&delivery, status, error.clone(), false);
+        Ok(WebhookDelivery {
+            at: now_millis(),
+            rule: delivery.rule.id,
+            event: delivery.event,
+            path: workspace::relative_path(&delivery.root, &delivery.path),
+            attempt: delivery.attempt,
+            status,
+            error,
+            retrying: false,
+        })
+    })
+    .await
+    .map_err(|e| format!("Webhook task failed: {}", e))?
+}
+
+/// Delivery attempts, newest first, optionally for one rule; `limit` defaults to 200
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn list_webhook_deliveries(
+    app: AppHandle,
+    workspace: String,
+    rule: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<WebhookDelivery>, Error> {
+    let root = paths::check_workspace(&app, &workspace, paths::Scope::Read)?;
+    let file = match fs::File::open(workspace::internal_dir(&root).join("logs").join(LOG_FILE)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::io("Failed to read webhook log", e)),
+    };
+    // Lines cut short by a crash are skipped
+    let mut entries: Vec<WebhookDelivery> = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<WebhookDelivery>(&line).ok())
+        .filter(|entry| rule.as_ref().is_none_or(|rule| &entry.rule == rule))
+        .collect();
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(entries)
+}
@@ -0,0 +1,301 @@
+use crate::crypto;
+use crate::http::{self, Stream};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use url::Url;
+
+/// Appended to the handshake key to derive `Sec-WebSocket-Accept` (RFC 6455 section 4.2.2)
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_HEADER: usize = 16 * 1024;
+const MAX_MESSAGE: usize = 64 << 20;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xa;
+
+/// Client end of a WebSocket connection (RFC 6455), over plain TCP for `ws:` URLs and TLS
+/// checked against the Mozilla roots for `wss:`.
+///
+/// Reads and writes happen on the thread that owns it: [`WebSocket::poll`] waits at most the
+/// poll interval, so the same loop can send in between.
+pub struct WebSocket {
+    stream: Stream,
+    /// Bytes read but not yet parsed into a frame
+    buffer: Vec<u8>,
+    /// Opcode and payload of a fragmented message still being received
+    fragments: Option<(u8, Vec<u8>)>,
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// Open a WebSocket to `url`, which must be `ws:` or `wss:`
+pub fn connect(url: &Url) -> Result<WebSocket, String> {
+    let secure = match url.scheme() {
+        "wss" => true,
+        "ws" => false,
+        scheme => return Err(format!("Invalid relay URL: unsupported scheme {}", scheme)),
+    };
+    let host = url
+        .host_str()
+        .ok_or("Invalid relay URL: missing host")?
+        .to_string();
+    let port = url
+        .port_or_known_default()
+        .ok_or("Invalid relay URL: missing port")?;
+    let address = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve relay: {}", e))?
+        .next()
+        .ok_or_else(|| format!("Failed to resolve relay: {}", host))?;
+    let tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to relay: {}", e))?;
+    let _ = tcp.set_nodelay(true);
+    let _ = tcp.set_read_timeout(Some(CONNECT_TIMEOUT));
+    let _ = tcp.set_write_timeout(Some(WRITE_TIMEOUT));
+    let mut stream = if secure {
+        Stream::Tls(Box::new(http::tls(&host, tcp)?))
+    } else {
+        Stream::Plain(tcp)
+    };
+
+    let mut nonce = [0u8; 16];
+    crypto::fill_random(&mut nonce);
+    let key = BASE64.encode(nonce);
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let host_header = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.clone(),
+    };
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\nUser-Agent: Inkfinite/{}\r\n\r\n",
+        target,
+        host_header,
+        key,
+        env!("CARGO_PKG_VERSION")
+    );
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("Failed to connect to relay: {}", e))?;
+
+    let (head, rest) = read_head(&mut stream)?;
+    let mut lines = head.split("\r\n");
+    let status = lines.next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(format!("Relay refused the connection: {}", status));
+    }
+    let accept = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-accept"))
+        .map(|(_, value)| value.trim().to_string());
+    let expected = BASE64.encode(Sha1::digest(format!("{}{}", key, ACCEPT_GUID).as_bytes()));
+    if accept.as_deref() != Some(expected.as_str()) {
+        return Err("Relay refused the connection: invalid Sec-WebSocket-Accept".to_string());
+    }
+    Ok(WebSocket {
+        stream,
+        buffer: rest,
+        fragments: None,
+    })
+}
+
+/// Read the HTTP response head, returning it and any bytes that followed it
+fn read_head(stream: &mut Stream) -> Result<(String, Vec<u8>), String> {
+    let mut received = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        if let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = received.split_off(end + 4);
+            received.truncate(end);
+            return Ok((String::from_utf8_lossy(&received).to_string(), rest));
+        }
+        if received.len() > MAX_HEADER {
+            return Err("Relay refused the connection: response header too large".to_string());
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) => return Err("Relay closed the connection during the handshake".to_string()),
+            Ok(n) => received.extend_from_slice(&chunk[..n]),
+            Err(e) => return Err(format!("Failed to connect to relay: {}", e)),
+        }
+    }
+}
+
+/// Parse one frame from the front of `buffer`, returning it and its length, or `None` until
+/// all of it has arrived
+fn parse_frame(buffer: &[u8]) -> Result<Option<(Frame, usize)>, String> {
+    if buffer.len() < 2 {
+        return Ok(None);
+    }
+    let fin = buffer[0] & 0x80 != 0;
+    let opcode = buffer[0] & 0x0f;
+    let masked = buffer[1] & 0x80 != 0;
+    let (length, mut offset) = match buffer[1] & 0x7f {
+        126 if buffer.len() >= 4 => (u16::from_be_bytes([buffer[2], buffer[3]]) as u64, 4),
+        127 if buffer.len() >= 10 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&buffer[2..10]);
+            (u64::from_be_bytes(bytes), 10)
+        }
+        126 | 127 => return Ok(None),
+        length => (length as u64, 2),
+    };
+    if length > MAX_MESSAGE as u64 {
+        return Err(format!("Relay sent a frame of {} bytes", length));
+    }
+    let mask = if masked {
+        if buffer.len() < offset + 4 {
+            return Ok(None);
+        }
+        offset += 4;
+        Some([
+            buffer[offset - 4],
+            buffer[offset - 3],
+            buffer[offset - 2],
+            buffer[offset - 1],
+        ])
+    } else {
+        None
+    };
+    let end = offset + length as usize;
+    if buffer.len() < end {
+        return Ok(None);
+    }
+    let mut payload = buffer[offset..end].to_vec();
+    if let Some(mask) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Ok(Some((
+        Frame {
+            fin,
+            opcode,
+            payload,
+        },
+        end,
+    )))
+}
+
+impl WebSocket {
+    /// How long [`WebSocket::poll`] waits for data before returning `None`
+    pub fn set_poll_interval(&self, interval: Duration) {
+        let _ = self.stream.tcp().set_read_timeout(Some(interval));
+    }
+
+    pub fn send_text(&mut self, text: &str) -> Result<(), String> {
+        self.send(OP_TEXT, text.as_bytes())
+    }
+
+    /// Send a close frame; the connection should be dropped afterwards
+    pub fn close(&mut self) {
+        let _ = self.send(OP_CLOSE, &1000u16.to_be_bytes());
+    }
+
+    /// Client frames are always masked (RFC 6455 section 5.3)
+    fn send(&mut self, opcode: u8, payload: &[u8]) -> Result<(), String> {
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(0x80 | opcode);
+        match payload.len() {
+            length if length < 126 => frame.push(0x80 | length as u8),
+            length if length <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+        let mut mask = [0u8; 4];
+        crypto::fill_random(&mut mask);
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        self.stream
+            .write_all(&frame)
+            .and_then(|_| self.stream.flush())
+            .map_err(|e| format!("Failed to send to relay: {}", e))
+    }
+
+    /// The next complete text message, or `None` when none arrived within the poll interval.
+    /// Pings are answered here; fails once the relay closes the connection.
+    pub fn poll(&mut self) -> Result<Option<String>, String> {
+        let mut chunk = [0u8; 16 * 1024];
+        loop {
+            while let Some((frame, length)) = parse_frame(&self.buffer)? {
+                self.buffer.drain(..length);
+                if let Some(text) = self.handle(frame)? {
+                    return Ok(Some(text));
+                }
+            }
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err("Relay closed the connection".to_string()),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(e) => return Err(format!("Failed to read from relay: {}", e)),
+            }
+        }
+    }
+
+    fn handle(&mut self, frame: Frame) -> Result<Option<String>, String> {
+        let (opcode, payload) = match frame.opcode {
+            OP_PING => {
+                self.send(OP_PONG, &frame.payload)?;
+                return Ok(None);
+            }
+            OP_PONG => return Ok(None),
+            OP_CLOSE => {
+                self.close();
+                return Err("Relay closed the connection".to_string());
+            }
+            OP_CONTINUATION => {
+                let (opcode, mut payload) = self
+                    .fragments
+                    .take()
+                    .ok_or("Relay sent an unexpected continuation frame")?;
+                if payload.len() + frame.payload.len() > MAX_MESSAGE {
+                    return Err("Relay sent a message that is too large".to_string());
+                }
+                payload.extend_from_slice(&frame.payload);
+                (opcode, payload)
+            }
+            OP_TEXT | OP_BINARY => (frame.opcode, frame.payload),
+            opcode => return Err(format!("Relay sent an unknown frame type {}", opcode)),
+        };
+        if !frame.fin {
+            self.fragments = Some((opcode, payload));
+            return Ok(None);
+        }
+        // Relays speak JSON text, so binary messages are skipped
+        if opcode == OP_BINARY {
+            return Ok(None);
+        }
+        String::from_utf8(payload)
+            .map(Some)
+            .map_err(|e| format!("Relay sent invalid text: {}", e))
+    }
+}
@@ -0,0 +1,197 @@
+use crate::error::Error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+/// Label of the window created from `tauri.conf.json`
+pub const MAIN_WINDOW: &str = "main";
+
+/// What a window is showing, so each window can keep its own document and workspace
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowState {
+    pub document: Option<String>,
+    pub workspace: Option<String>,
+}
+
+/// Managed state mapping window labels to what they show
+#[derive(Default)]
+pub struct WindowRegistry(Mutex<HashMap<String, WindowState>>);
+
+impl WindowRegistry {
+    pub fn get(&self, label: &str) -> WindowState {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|windows| windows.get(label).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn set(&self, label: &str, state: WindowState) {
+        if let Ok(mut windows) = self.0.lock() {
+            windows.insert(label.to_string(), state);
+        }
+    }
+
+    /// Forget a window once it has been destroyed
+    pub fn remove(&self, label: &str) {
+        if let Ok(mut windows) = self.0.lock() {
+            windows.remove(label);
+        }
+    }
+
+    /// Labels of the windows showing `workspace`, sorted
+    pub fn showing(&self, workspace: &str) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .0
+            .lock()
+            .map(|windows| {
+                windows
+                    .iter()
+                    .filter(|(_, state)| state.workspace.as_deref() == Some(workspace))
+                    .map(|(label, _)| label.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        labels.sort();
+        labels
+    }
+
+    /// Every workspace some window shows
+    pub fn workspaces(&self) -> Vec<String> {
+        let mut workspaces: Vec<String> = self
+            .0
+            .lock()
+            .map(|windows| {
+                windows
+                    .values()
+                    .filter_map(|state| state.workspace.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        workspaces.sort();
+        workspaces.dedup();
+        workspaces
+    }
+
+    fn find_document(&self, document: &str) -> Option<String> {
+        self.0.lock().ok().and_then(|windows| {
+            windows
+                .iter()
+                .find(|(_, state)| state.document.as_deref() == Some(document))
+                .map(|(label, _)| label.clone())
+        })
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowInfo {
+    pub label: String,
+    pub title: String,
+    pub focused: bool,
+    #[serde(flatten)]
+    pub state: WindowState,
+}
+
+/// Open a document in its own window, focusing the existing one if it is already open
+#[cfg(desktop)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn open_document_window(
+    app: AppHandle,
+    registry: State<'_, WindowRegistry>,
+    path: String,
+) -> Result<WindowInfo, Error> {
+    use crate::paths;
+    use tauri::{WebviewUrl, WebviewWindowBuilder};
+
+    paths::check(&app, &path, paths::Scope::Read)?;
+    if let Some(window) = registry
+        .find_document(&path)
+        .and_then(|label| app.get_webview_window(&label))
+    {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(describe(&window, &registry));
+    }
+
+    let title = crate::document::document_stem(std::path::Path::new(&path));
+    let label = format!("document-{}", uuid::Uuid::new_v4().simple());
+    // Registered before the window loads so its frontend can read the state on startup
+    registry.set(
+        &label,
+        WindowState {
+            document: Some(path),
+            workspace: crate::workspace::current_root(&app)
+                .map(|root| root.to_string_lossy().to_string()),
+        },
+    );
+
+    let window = WebviewWindowBuilder::new(&app, &label, WebviewUrl::App("index.html".into()))
+        .title(&title)
+        .inner_size(1000.0, 700.0)
+        .build()
+        .map_err(|e| {
+            registry.remove(&label);
+            format!("Failed to open window: {}", e)
+        })?;
+
+    Ok(describe(&window, &registry))
+}
+
+/// Separate windows are not available on mobile builds
+#[cfg(mobile)]
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn open_document_window(
+    _app: AppHandle,
+    _registry: State<'_, WindowRegistry>,
+    _path: String,
+) -> Result<WindowInfo, Error> {
+    Err("Multiple windows are not supported on this platform".into())
+}
+
+/// List open windows with the document and workspace each one shows
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn list_windows(app: AppHandle, registry: State<'_, WindowRegistry>) -> Vec<WindowInfo> {
+    let mut windows: Vec<WindowInfo> = app
+        .webview_windows()
+        .values()
+        .map(|window| describe(window, &registry))
+        .collect();
+    windows.sort_by(|a, b| a.label.cmp(&b.label));
+    windows
+}
+
+/// State of the calling window
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn get_window_state(window: WebviewWindow, registry: State<'_, WindowRegistry>) -> WindowState {
+    registry.get(window.label())
+}
+
+/// Record what the calling window shows after it opens a document or switches workspace
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn set_window_state(
+    window: WebviewWindow,
+    registry: State<'_, WindowRegistry>,
+    state: WindowState,
+) {
+    if window.is_focused().unwrap_or(false) {
+        crate::handoff::publish(window.app_handle(), state.document.as_deref());
+    }
+    registry.set(window.label(), state);
+}
+
+fn describe(window: &WebviewWindow, registry: &WindowRegistry) -> WindowInfo {
+    WindowInfo {
+        label: window.label().to_string(),
+        title: window.title().unwrap_or_default(),
+        focused: window.is_focused().unwrap_or(false),
+        state: registry.get(window.label()),
+    }
+}
@@ -0,0 +1,89 @@
+use crate::windows::WindowRegistry;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+pub use inkfinite_core::workspace::{
+    append_log, internal_dir, list_documents, read_config, relative_path, rewrite_references,
+    write_config,
+};
+
+/// Store file shared with the frontend's desktop file ops
+pub const STORE_NAME: &str = "inkfinite-desktop.json";
+pub const WORKSPACE_DIR_KEY: &str = "workspaceDir";
+const RECENT_FILES_KEY: &str = "recentFiles";
+
+/// Workspace directory currently selected in the frontend, if any
+pub fn current_root(app: &AppHandle) -> Option<PathBuf> {
+    let store = app.store(STORE_NAME).ok()?;
+    let dir = store.get(WORKSPACE_DIR_KEY)?;
+    dir.as_str().map(PathBuf::from).filter(|path| path.is_dir())
+}
+
+/// The current workspace and every workspace a window shows
+pub fn open_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = app
+        .try_state::<WindowRegistry>()
+        .map(|registry| {
+            registry
+                .workspaces()
+                .into_iter()
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    roots.extend(current_root(app));
+    roots
+}
+
+/// Select a workspace directory, as if picked in the frontend
+pub fn set_current_root(app: &AppHandle, root: &Path) -> Result<(), String> {
+    let store = app
+        .store(STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(WORKSPACE_DIR_KEY, root.to_string_lossy().to_string());
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// Leave no workspace selected
+pub fn clear_current_root(app: &AppHandle) -> Result<(), String> {
+    let store = app
+        .store(STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.delete(WORKSPACE_DIR_KEY);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// Entry in the frontend's recent files list
+#[derive(serde::Deserialize, Clone)]
+pub struct RecentFile {
+    pub path: String,
+    pub name: Option<String>,
+}
+
+/// Recently opened documents, most recent first, skipping files that no longer exist
+pub fn recent_files(app: &AppHandle) -> Vec<RecentFile> {
+    app.store(STORE_NAME)
+        .ok()
+        .and_then(|store| store.get(RECENT_FILES_KEY))
+        .and_then(|value| serde_json::from_value::<Vec<RecentFile>>(value).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|file| Path::new(&file.path).is_file())
+        .collect()
+}
+
+/// Empty the recent files list shared with the frontend
+pub fn clear_recent_files(app: &AppHandle) -> Result<(), String> {
+    let store = app
+        .store(STORE_NAME)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(RECENT_FILES_KEY, serde_json::json!([]));
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
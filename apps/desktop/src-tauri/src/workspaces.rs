@@ -0,0 +1,147 @@
+use crate::blocks::BlockIndexes;
+use crate::dir_cache::DirectoryCache;
+use crate::error::Error;
+use crate::windows::{WindowRegistry, WindowState};
+use crate::{catalog, collab, external_edit, link_index, lock, paths, saves, workspace};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+const OPENED_EVENT: &str = "workspace:opened";
+const CLOSED_EVENT: &str = "workspace:closed";
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenWorkspace {
+    pub root: String,
+    /// Labels of the windows showing it
+    pub windows: Vec<String>,
+    /// Whether it is the workspace commands without an explicit one act on
+    pub current: bool,
+}
+
+fn describe(app: &AppHandle, registry: &WindowRegistry, root: &str) -> OpenWorkspace {
+    OpenWorkspace {
+        root: root.to_string(),
+        windows: registry.showing(root),
+        current: workspace::current_root(app).as_deref() == Some(Path::new(root)),
+    }
+}
+
+/// Tear down what the backend holds for `root` once no window shows it: pending saves are
+/// written, cached listings, block indexes, the metadata cache, link suggestions and related
+/// documents are dropped, collaboration and external edit sessions end, and its keys are locked
+fn release(app: &AppHandle, registry: &WindowRegistry, root: &str) {
+    if !registry.showing(root).is_empty() {
+        return;
+    }
+    let path = Path::new(root);
+    saves::flush_under(app, path);
+    app.state::<DirectoryCache>().invalidate(path);
+    app.state::<BlockIndexes>().evict(path);
+    catalog::close(app, path);
+    link_index::evict(app, path);
+    #[cfg(desktop)]
+    crate::related::evict(app, path);
+    collab::stop_under(app, path);
+    external_edit::stop_under(app, path);
+    lock::workspace_closed(app, path);
+
+    if workspace::current_root(app).as_deref() == Some(path) {
+        // Another window's workspace takes over, so commands keep acting on an open one
+        let result = match registry.workspaces().first() {
+            Some(other) => workspace::set_current_root(app, Path::new(other)),
+            None => workspace::clear_current_root(app),
+        };
+        if let Err(error) = result {
+            tracing::warn!(%error, "Failed to switch workspace");
+        }
+    }
+    tracing::info!(root, "Workspace closed");
+    let _ = app.emit(CLOSED_EVENT, root);
+}
+
+/// Close the workspace of a destroyed window unless another window still shows it
+pub fn window_closed(app: &AppHandle, label: &str) {
+    let registry = app.state::<WindowRegistry>();
+    let state = registry.get(label);
+    registry.remove(label);
+    if let Some(root) = state.workspace {
+        release(app, &registry, &root);
+    }
+}
+
+/// Open a workspace in the calling window, making it the current one.
+///
+/// The window's previous workspace is closed if no other window shows it, and the metadata
+/// cache of a newly opened workspace is brought up to date in the background. Emits
+/// `workspace:opened` the first time a workspace is opened in any window.
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub fn open_workspace(
+    app: AppHandle,
+    window: WebviewWindow,
+    registry: State<'_, WindowRegistry>,
+    path: String,
+) -> Result<OpenWorkspace, Error> {
+    let root = paths::check_folder(&path)?;
+    let root_text = root.to_string_lossy().to_string();
+    let previous = registry.get(window.label());
+    let already_open = !registry.showing(&root_text).is_empty();
+    let same = previous.workspace.as_deref() == Some(root_text.as_str());
+
+    registry.set(
+        window.label(),
+        WindowState {
+            document: previous.document.filter(|_| same),
+            workspace: Some(root_text.clone()),
+        },
+    );
+    if let Some(old) = previous.workspace.filter(|_| !same) {
+        release(&app, &registry, &old);
+    }
+    workspace::set_current_root(&app, &root)?;
+
+    let opened = describe(&app, &registry, &root_text);
+    if !already_open {
+        let handle = app.clone();
+        std::thread::spawn(move || {
+            if let Err(error) = catalog::prepare(&handle, &root) {
+                tracing::warn!(%error, "Failed to prepare metadata cache");
+            }
+        });
+        tracing::info!(root = %root_text, "Workspace opened");
+        let _ = app.emit(OPENED_EVENT, opened.clone());
+    }
+    Ok(opened)
+}
+
+/// Close the calling window's workspace, tearing it down unless another window still shows
+/// it; returns `false` when the window had none open
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn close_workspace(
+    app: AppHandle,
+    window: WebviewWindow,
+    registry: State<'_, WindowRegistry>,
+) -> bool {
+    let Some(root) = registry.get(window.label()).workspace else {
+        return false;
+    };
+    registry.set(window.label(), WindowState::default());
+    release(&app, &registry, &root);
+    true
+}
+
+/// Workspaces open in any window, with the windows showing each
+#[tauri::command]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn list_open_workspaces(
+    app: AppHandle,
+    registry: State<'_, WindowRegistry>,
+) -> Vec<OpenWorkspace> {
+    registry
+        .workspaces()
+        .iter()
+        .map(|root| describe(&app, &registry, root))
+        .collect()
+}
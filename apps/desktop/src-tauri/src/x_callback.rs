@@ -0,0 +1,226 @@
+use crate::deep_link::{self, Navigation};
+use crate::error::{Error, ErrorCode};
+use crate::local_api::{self, AppendRequest, NoteRequest};
+use crate::{document, lock, paths, search, templates, workspace};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+use url::Url;
+
+/// Host of `inkfinite://x-callback-url/<action>` links
+pub const HOST: &str = "x-callback-url";
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+/// Parameters of the x-callback-url convention and of every action, never template values
+const RESERVED: &[&str] = &[
+    "x-success",
+    "x-error",
+    "x-cancel",
+    "x-source",
+    "token",
+    "template",
+    "title",
+    "folder",
+    "open",
+];
+/// Callbacks to these schemes could read local files or run script
+const REFUSED_SCHEMES: &[&str] = &["file", "javascript", "data", "vbscript"];
+
+/// Run an `inkfinite://x-callback-url/<action>` link off the main thread, then open its
+/// `x-success` link with the result or its `x-error` link with `errorCode` and `errorMessage`.
+///
+/// Actions are `create` (`text`, `title`, `folder`, `template`, `open`), `append` (`text` and
+/// `path` or `doc`), `search` (`query`, `limit`) and `open` (`path` or `doc`). Any web page can
+/// open these links, so every action needs the local API token as `token`.
+pub fn handle(app: &AppHandle, url: &Url) {
+    let app = app.clone();
+    let url = url.clone();
+    std::thread::spawn(move || {
+        let params: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let param = |key: &str| {
+            params
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.clone())
+                .filter(|value| !value.is_empty())
+        };
+        let result = if local_api::authorized(&app, &param("token").unwrap_or_default()) {
+            lock::ensure_unlocked(&app)
+                .and_then(|_| run(&app, url.path().trim_matches('/'), &params, &param))
+        } else {
+            Err(Error::new(
+                ErrorCode::PermissionDenied,
+                "Missing or wrong access token",
+            ))
+        };
+        let callback = match result {
+            Ok(values) => param("x-success").map(|link| (link, values)),
+            Err(error) => {
+                tracing::warn!(%error, "x-callback-url action failed");
+                // A forged link must not learn anything from the reply
+                let denied = error.code == ErrorCode::PermissionDenied;
+                param("x-error").filter(|_| !denied).map(|link| {
+                    let mut values = Map::new();
+                    values.insert("errorCode".into(), json!(error.code));
+                    values.insert("errorMessage".into(), json!(error.message));
+                    (link, values)
+                })
+            }
+        };
+        if let Some((link, values)) = callback {
+            if let Err(error) = call_back(&app, &link, &values) {
+                tracing::warn!(%error, "Failed to open x-callback-url callback");
+            }
+        }
+    });
+}
+
+fn run(
+    app: &AppHandle,
+    action: &str,
+    params: &[(String, String)],
+    param: &dyn Fn(&str) -> Option<String>,
+) -> Result<Map<String, Value>, Error> {
+    let mut values = Map::new();
+    match action {
+        "create" => {
+            let path = match param("template") {
+                Some(template) => {
+                    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+                    let fields: Map<String, Value> = params
+                        .iter()
+                        .filter(|(key, _)| !RESERVED.contains(&key.as_str()))
+                        .map(|(key, value)| (key.clone(), json!(value)))
+                        .collect();
+                    templates::create_from_template(
+                        app.clone(),
+                        root.to_string_lossy().to_string(),
+                        template,
+                        Some(Value::Object(fields)),
+                        param("title"),
+                        param("folder"),
+                    )?
+                }
+                None => {
+                    let created = local_api::create_note(
+                        app,
+                        NoteRequest {
+                            text: param("text").unwrap_or_default(),
+                            title: param("title"),
+                            folder: param("folder"),
+                        },
+                    )?;
+                    created["path"].as_str().unwrap_or_default().to_string()
+                }
+            };
+            if param("open").as_deref() != Some("false") {
+                open_document(app, Path::new(&path));
+            }
+            describe(&mut values, Path::new(&path));
+        }
+        "append" => {
+            let appended = local_api::append_text(
+                app,
+                AppendRequest {
+                    path: document_path(app, param)?,
+                    text: param("text").unwrap_or_default(),
+                },
+            )?;
+            describe(
+                &mut values,
+                Path::new(appended["path"].as_str().unwrap_or_default()),
+            );
+        }
+        "search" => {
+            let root = workspace::current_root(app).ok_or("No workspace is open")?;
+            let query = param("query").ok_or("Invalid link: a query is required")?;
+            let limit = param("limit").and_then(|limit| limit.parse().ok());
+            let hits = search::search_documents(
+                app.clone(),
+                root.to_string_lossy().to_string(),
+                query,
+                Some(limit.unwrap_or(DEFAULT_SEARCH_LIMIT)),
+                None,
+                None,
+                None,
+            )?;
+            values.insert("count".into(), json!(hits.len()));
+            values.insert("results".into(), Value::String(json!(hits).to_string()));
+        }
+        "open" => {
+            let path = document_path(app, param)?;
+            let path = match workspace::current_root(app) {
+                Some(root) if Path::new(&path).is_relative() => root.join(&path),
+                _ => Path::new(&path).to_path_buf(),
+            };
+            let path = paths::check(app, &path.to_string_lossy(), paths::Scope::Read)?;
+            if !path.is_file() {
+                return Err(
+                    Error::not_found("Document does not exist").with_context(path.display())
+                );
+            }
+            open_document(app, &path);
+            describe(&mut values, &path);
+        }
+        other => {
+            return Err(format!("Invalid link: unknown action {}", other).into());
+        }
+    }
+    Ok(values)
+}
+
+/// `path`, or the path of the document with board id `doc`
+fn document_path(app: &AppHandle, param: &dyn Fn(&str) -> Option<String>) -> Result<String, Error> {
+    if let Some(path) = param("path") {
+        return Ok(path);
+    }
+    let doc = param("doc").ok_or("Invalid link: a path or doc is required")?;
+    let root = workspace::current_root(app).ok_or("No workspace is open")?;
+    deep_link::find_document(&root, &doc)
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or_else(|| Error::not_found("Document not found").with_context(&doc))
+}
+
+fn open_document(app: &AppHandle, path: &Path) {
+    let Some(root) = workspace::current_root(app) else {
+        return;
+    };
+    deep_link::navigate(
+        app,
+        Navigation::OpenFile {
+            path: path.to_string_lossy().to_string(),
+            workspace: root.to_string_lossy().to_string(),
+        },
+    );
+}
+
+/// Callback values for a document: its path, board id and name
+fn describe(values: &mut Map<String, Value>, path: &Path) {
+    values.insert("path".into(), json!(path.to_string_lossy()));
+    if let Ok(board) = document::read_board(path) {
+        values.insert("doc".into(), json!(board.board.id));
+        values.insert("name".into(), json!(board.board.name));
+    }
+}
+
+fn call_back(app: &AppHandle, link: &str, values: &Map<String, Value>) -> Result<(), String> {
+    let mut url = Url::parse(link).map_err(|e| format!("Invalid callback URL: {}", e))?;
+    if REFUSED_SCHEMES.contains(&url.scheme()) || url.scheme() == deep_link::SCHEME {
+        return Err(format!("Invalid callback URL: {}", link));
+    }
+    {
+        let mut query = url.query_pairs_mut();
+        for (key, value) in values {
+            match value {
+                Value::String(text) => query.append_pair(key, text),
+                other => query.append_pair(key, &other.to_string()),
+            };
+        }
+    }
+    app.opener()
+        .open_url(url.as_str(), None::<&str>)
+        .map_err(|e| format!("Failed to open callback: {}", e))
+}